@@ -0,0 +1,96 @@
+/*
+ * sixcy.h — C API for embedding the .6cy reference implementation
+ *
+ * Format:  .6cy v3+
+ * Source:  src/ffi.rs (feature `ffi`) — keep the two in sync.
+ *
+ * ── Building ───────────────────────────────────────────────────────────────
+ *
+ *    cargo rustc --release --lib --features ffi --crate-type cdylib
+ *
+ *  produces libsixcy.so / libsixcy.dylib / sixcy.dll.
+ *
+ * ── Conventions ────────────────────────────────────────────────────────────
+ *
+ *  Handles are opaque.  Create with sixcy_open / sixcy_create, release with
+ *  sixcy_close.  A handle is NOT thread-safe; use one per thread.
+ *
+ *  Functions returning int32_t use the SIXCY_* codes below.  On failure the
+ *  message is available from sixcy_last_error() on the calling thread.
+ *
+ *  Buffers returned by sixcy_read_file are owned by the caller and MUST be
+ *  released with sixcy_free_buffer — never with free().
+ */
+
+#ifndef SIXCY_H
+#define SIXCY_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* ── Return codes ───────────────────────────────────────────────────────── */
+
+#define SIXCY_OK            0
+#define SIXCY_ERROR        (-1)  /* I/O, format, codec, or crypto failure   */
+#define SIXCY_INVALID_ARG  (-2)  /* null pointer or non-UTF-8 string        */
+#define SIXCY_NOT_FOUND    (-3)  /* named entry does not exist              */
+
+/* ── Types ──────────────────────────────────────────────────────────────── */
+
+typedef struct SixcyArchive SixcyArchive;
+
+typedef struct SixcyFileInfo {
+    uint32_t    id;
+    /* NUL-terminated UTF-8; valid until the next sixcy_list or sixcy_close. */
+    const char *name;
+    uint64_t    original_size;
+    uint64_t    compressed_size;
+} SixcyFileInfo;
+
+/* ── Errors ─────────────────────────────────────────────────────────────── */
+
+/* Last error on this thread, or NULL.  Valid until the next failing call. */
+const char *sixcy_last_error(void);
+
+/* ── Reading ────────────────────────────────────────────────────────────── */
+
+/* Open for reading.  password may be NULL.  Returns NULL on failure. */
+SixcyArchive *sixcy_open(const char *path, const char *password);
+
+/* Fill out[0..cap] and return the total entry count.
+ * Call with out = NULL, cap = 0 to size the buffer first. */
+size_t sixcy_list(SixcyArchive *ar, SixcyFileInfo *out, size_t cap);
+
+/* Read a whole entry into a new buffer owned by the caller. */
+int32_t sixcy_read_file(SixcyArchive *ar, const char *name,
+                        uint8_t **out, size_t *out_len);
+
+void sixcy_free_buffer(uint8_t *buf, size_t len);
+
+/* ── Writing ────────────────────────────────────────────────────────────── */
+
+/* Create for writing.  codec: "zstd" (NULL), "lz4", "brotli", "lzma",
+ * "none".  password may be NULL.  Returns NULL on failure. */
+SixcyArchive *sixcy_create(const char *path, const char *codec,
+                           int32_t level, const char *password);
+
+int32_t sixcy_add_file(SixcyArchive *ar, const char *name,
+                       const uint8_t *data, size_t len);
+
+/* Write the INDEX and superblock.  Call exactly once before sixcy_close. */
+int32_t sixcy_finalize(SixcyArchive *ar);
+
+/* ── Lifetime ───────────────────────────────────────────────────────────── */
+
+/* Release a handle.  Does NOT finalize.  NULL is ignored. */
+void sixcy_close(SixcyArchive *ar);
+
+#ifdef __cplusplus
+} /* extern "C" */
+#endif
+
+#endif /* SIXCY_H */
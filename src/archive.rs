@@ -16,40 +16,558 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 
-use std::fs::File;
-use std::io::{self, Write};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::codec::CodecId;
-use crate::crypto::derive_key;
-use crate::index::FileIndexRecord;
-use crate::io_stream::{SixCyReader, SixCyWriter, DEFAULT_CHUNK_SIZE, DEFAULT_COMPRESSION_LEVEL};
-use crate::superblock::Superblock;
+use crate::atomic_file::AtomicFile;
+use crate::block::BLOCK_HEADER_SIZE;
+use crate::codec::{register_dictionary, CodecId, Dictionary};
+use crate::pipeline::BlockPipeline;
+use crate::crypto::{derive_key, derive_key_with_progress, KdfProgress};
+use crate::index::{EntryOrder, FileIndex, FileIndexRecord, HashAlgorithm, SecondaryHash};
+use crate::error::{Result, SixcyError};
+use crate::events::Observer;
+use crate::limits::{Limits, OpenLimits};
+use crate::names::{EntryName, NamePolicy};
+use crate::optimize::OptimizeReport;
+use crate::perf::PerfStats;
+use crate::remote::{ArchiveMetadata, HttpSource};
+use crate::io_stream::{find_sections, AutoLevel, AutoSolid, BlockHeaders, ChunkPolicy, Section, SharedFile, SixCyReader, SixCyWriter,
+                       SolidOrder, DEFAULT_BLOCK_CACHE_BYTES, DEFAULT_CHUNK_SIZE, DEFAULT_COMPRESSION_LEVEL,
+                       INDEX_COMPRESSION_LEVEL};
+use crate::superblock::{Superblock, SB_FLAG_ENCRYPTED};
+use crate::attest::InclusionProof;
 
 // ── PackOptions ───────────────────────────────────────────────────────────────
 
 /// Configuration for [`Archive::create`].
 #[derive(Debug, Clone)]
 pub struct PackOptions {
-    pub default_codec: CodecId,
-    pub level:         i32,
-    pub chunk_size:    usize,
+    pub default_codec:         CodecId,
+    /// Level for DATA blocks, and SOLID blocks unless `solid_level` is set.
+    pub level:                 i32,
+    /// Level for SOLID blocks; `None` uses `level`.
+    pub solid_level:           Option<i32>,
+    /// Level for the INDEX block.
+    pub index_level:           i32,
+    /// Codec for the INDEX block: Zstd, or `None` so readers with no
+    /// codecs at all (`core_reader`) can parse the file list.
+    pub index_codec:           CodecId,
+    /// Choose the level per file by trial compression, in place of `level`
+    /// (see [`AutoLevel`]).
+    pub auto_level:            Option<AutoLevel>,
+    pub chunk_size:            usize,
+    /// Per-file chunk sizes (e.g. [`AdaptiveChunks`](crate::io_stream::AdaptiveChunks));
+    /// overrides `chunk_size`.
+    pub chunk_policy:          Option<Arc<dyn ChunkPolicy>>,
     /// When set, every block is AES-256-GCM encrypted.
-    /// Key = Argon2id(password, salt=archive_uuid).
-    pub password:      Option<String>,
+    /// Key = Argon2id(password, salt=archive_uuid).  To supply the key
+    /// itself, use [`Archive::create_with_key`].
+    pub password:              Option<String>,
+    /// Byte-identical output for identical inputs (supply-chain attestation):
+    /// no timestamps, plaintext-derived nonces, and a content-derived UUID
+    /// unless `fixed_uuid` is set.  Encrypted deterministic archives require
+    /// `fixed_uuid`.
+    pub deterministic:         bool,
+    /// Archive UUID to use instead of a random (or content-derived) one.
+    pub fixed_uuid:            Option<uuid::Uuid>,
+    /// Pack small files into rolling solid blocks grouped by extension,
+    /// without `begin_solid` / `end_solid` calls.
+    pub auto_solid:            Option<AutoSolid>,
+    /// Roll a solid session (manual or auto-solid) over into a new SOLID
+    /// block before one would exceed this many bytes.  A single larger file
+    /// still gets a block of its own.
+    pub solid_block_max_bytes: Option<usize>,
+    /// Roll a solid session over once a SOLID block holds this many files.
+    pub max_files_per_solid:   Option<usize>,
+    /// Layout of members within each SOLID block.
+    pub solid_order:           SolidOrder,
+    /// Also record each file's digest in this algorithm (e.g. SHA-256 for
+    /// attestation manifests) in its INDEX record.
+    pub secondary_hash:        Option<HashAlgorithm>,
+    /// Collect per-stage timings, read back with [`Archive::perf_stats`].
+    pub perf_stats:            bool,
+    /// Write to a temporary file — unnamed (`O_TMPFILE`) on Linux — and move
+    /// it to the path only in [`Archive::finalize`], so an aborted pack
+    /// leaves nothing there.  See [`crate::atomic_file`].
+    pub atomic:                bool,
+    /// Metadata added to every file's record (e.g. `source` = hostname,
+    /// `job` = `nightly`); keys passed to
+    /// [`Archive::add_file_with_metadata`] take precedence.
+    pub default_metadata:      HashMap<String, String>,
+    /// In [`Archive::finalize`], sync the file, drop it from the page cache
+    /// where the OS allows (Linux), and read every block back — header CRC,
+    /// decryption, decompression, BLAKE3 — checking the INDEX on disk
+    /// against the one in memory.  For media that cannot be trusted to
+    /// store what they acknowledge.  See [`Archive::verify_written`].
+    pub verify_on_write:       bool,
+    /// Prefilter, codec, level, and cipher for every DATA and SOLID block,
+    /// overriding `default_codec`, the codec passed to `add_file_with_codec`
+    /// and `begin_solid`, and the levels above.  A cipher stage needs
+    /// `password` (or [`Archive::create_with_key`]).  See [`crate::pipeline`].
+    pub pipeline:              Option<BlockPipeline>,
+    /// Have [`Archive::pack_dir`] reserve the total input size on disk
+    /// before packing, so the archive is laid out contiguously; the unused
+    /// reservation is released in [`Archive::finalize`].  See
+    /// [`Archive::preallocate`].
+    pub preallocate:           bool,
+    /// Chunks of a file compressed ahead of the write position, bounding
+    /// memory at about `queue_depth × chunk_size` per file; `None` keeps
+    /// two per worker thread.  See [`crate::io_stream`] (*Chunk pipeline*).
+    pub queue_depth:           Option<usize>,
+    /// Treatment of names that would not extract faithfully on every
+    /// platform: stored as given, normalized, or refused.  See
+    /// [`crate::names`].
+    pub name_policy:           NamePolicy,
+    /// Told of each file, block, dedup hit, and error as the archive is
+    /// written; see [`crate::events`].
+    pub observer:              Option<Arc<dyn Observer>>,
+    /// Trained dictionary for Zstd DATA and SOLID blocks, kept outside the
+    /// archive — e.g. in a [`BlockStore`](crate::store::BlockStore) shared
+    /// by many small archives.  It is registered for this process; other
+    /// readers must register it before opening the archive.  See
+    /// [`crate::codec::Dictionary`].
+    pub dictionary:            Option<Arc<Dictionary>>,
 }
 
 impl Default for PackOptions {
     fn default() -> Self {
         Self {
-            default_codec: CodecId::Zstd,
-            level:         DEFAULT_COMPRESSION_LEVEL,
-            chunk_size:    DEFAULT_CHUNK_SIZE,
-            password:      None,
+            default_codec:         CodecId::Zstd,
+            level:                 DEFAULT_COMPRESSION_LEVEL,
+            solid_level:           None,
+            index_level:           INDEX_COMPRESSION_LEVEL,
+            index_codec:           CodecId::Zstd,
+            auto_level:            None,
+            chunk_size:            DEFAULT_CHUNK_SIZE,
+            chunk_policy:          None,
+            password:              None,
+            deterministic:         false,
+            fixed_uuid:            None,
+            auto_solid:            None,
+            solid_block_max_bytes: None,
+            max_files_per_solid:   None,
+            solid_order:           SolidOrder::Insertion,
+            secondary_hash:        None,
+            perf_stats:            false,
+            atomic:                false,
+            default_metadata:      HashMap::new(),
+            verify_on_write:       false,
+            pipeline:              None,
+            preallocate:           false,
+            queue_depth:           None,
+            name_policy:           NamePolicy::default(),
+            observer:              None,
+            dictionary:            None,
         }
     }
 }
 
+/// Summary returned by [`Archive::pack_dir`].
+#[derive(Debug, Clone, Default)]
+pub struct PackReport {
+    pub files:         usize,
+    /// Subdirectories walked (not counting the source directory).
+    pub dirs:          usize,
+    /// Entries that are neither regular files nor directories (symlinks,
+    /// sockets, …), and an earlier copy of the output archive itself.
+    pub skipped:       usize,
+    pub input_bytes:   u64,
+    /// Size of the finished archive.
+    pub archive_bytes: u64,
+    /// Files stored entirely as references to earlier entries; see
+    /// [`Archive::duplicate_files`].
+    pub duplicates:    Vec<DuplicateFile>,
+}
+
+/// A file none of whose content had to be stored: every chunk (or its
+/// SOLID slice) was already in the archive from an earlier entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateFile {
+    pub name:    String,
+    pub size:    u64,
+    /// The first entry with exactly this content, or `None` if the chunks
+    /// came from several files.
+    pub same_as: Option<String>,
+}
+
+// ── Merge ─────────────────────────────────────────────────────────────────────
+
+/// How [`Archive::merge`] names entries, and what it does when two sources
+/// hold different files under the same name.  Same-named files with the
+/// same size and chunk hashes are identical and always stored once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeConflict {
+    /// Prefix every name with its source archive's file stem
+    /// (`part1/a.txt`), so sources never collide.
+    #[default]
+    Prefix,
+    /// Keep the entry from the earliest source.
+    KeepFirst,
+    /// Keep the entry from the source modified last (superblock
+    /// `modified_at`; ties go to the later source, archives without one
+    /// count as oldest) — the newest snapshot wins.
+    KeepNewest,
+    /// Keep both; later copies become `name (1).ext`, `name (2).ext`, ….
+    Rename,
+    /// Fail on the first conflict.
+    Error,
+}
+
+impl MergeConflict {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "prefix"      => Some(Self::Prefix),
+            "keep-first"  => Some(Self::KeepFirst),
+            "keep-newest" => Some(Self::KeepNewest),
+            "rename"      => Some(Self::Rename),
+            "error"       => Some(Self::Error),
+            _             => None,
+        }
+    }
+}
+
+/// Summary returned by [`Archive::merge`].
+#[derive(Debug, Clone, Default)]
+pub struct MergeReport {
+    /// Entries written to the merged archive.
+    pub files:     usize,
+    /// Same-named entries identical to one already merged.
+    pub identical: usize,
+    /// Conflicting entries left out (`KeepFirst` / `KeepNewest`).
+    pub dropped:   usize,
+    /// `(original, merged)` names of entries `Rename` moved aside.
+    pub renamed:   Vec<(String, String)>,
+}
+
+// ── ExtractOptions ────────────────────────────────────────────────────────────
+
+/// How [`Archive::extract_with`] writes entries whose content is identical
+/// to an entry already extracted (same size and same block refs — the CAS
+/// deduplicated case).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupStrategy {
+    /// Write every entry's bytes independently.
+    #[default]
+    Copy,
+    /// Hardlink duplicates to the first extracted copy.  Falls back to
+    /// `Copy` where links are unsupported.
+    Hardlink,
+    /// Clone the first extracted copy so both share extents on
+    /// copy-on-write filesystems: the `FICLONE` ioctl on Linux (btrfs, XFS),
+    /// `clonefile` (through `std::fs::copy`) on macOS APFS.  Falls back to
+    /// copying the bytes where cloning is unsupported.
+    Reflink,
+}
+
+impl DedupStrategy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "copy"     => Some(Self::Copy),
+            "hardlink" => Some(Self::Hardlink),
+            "reflink"  => Some(Self::Reflink),
+            _          => None,
+        }
+    }
+}
+
+/// What [`Archive::extract_with`] does when an entry's destination exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwritePolicy {
+    /// Replace the existing file.  It is unlinked first, so hardlinks made
+    /// by an earlier extraction are not written through.
+    #[default]
+    Overwrite,
+    /// Leave every existing file untouched.
+    SkipExisting,
+    /// Leave an existing file untouched if it was modified after the
+    /// archive (its superblock `modified_at`; archives without one count as
+    /// older than any file), or if its BLAKE3 hash matches the entry's
+    /// content.  Otherwise replace it.
+    KeepNewer,
+    /// Leave an existing file untouched if it already holds the entry —
+    /// its size matches and each piece hashes to the BLAKE3 content hash
+    /// of its block ref — and replace it otherwise.  Re-running an
+    /// interrupted extraction this way reads back what it finished
+    /// instead of decoding and writing it again.
+    Resume,
+}
+
+/// What [`Archive::extract_with`] does with entry names Windows cannot
+/// create — reserved device names, invalid characters, trailing dots or
+/// spaces (see [`crate::winpath`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowsNames {
+    /// Use names as stored.  The default off Windows.
+    Keep,
+    /// Rewrite them with [`winpath::windows_name`](crate::winpath::windows_name)
+    /// and list each change in [`ExtractReport::renamed`].  The default on
+    /// Windows.
+    Rename,
+    /// Fail before anything is written.
+    Reject,
+}
+
+impl WindowsNames {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "keep"   => Some(Self::Keep),
+            "rename" => Some(Self::Rename),
+            "reject" => Some(Self::Reject),
+            _        => None,
+        }
+    }
+}
+
+impl Default for WindowsNames {
+    fn default() -> Self {
+        if cfg!(windows) { Self::Rename } else { Self::Keep }
+    }
+}
+
+/// Configuration for [`Archive::extract_with`].
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    pub dedup_strategy: DedupStrategy,
+    pub overwrite:      OverwritePolicy,
+    /// Check the bytes of every block ref against the ref's INDEX
+    /// `content_hash`, not just each block against its header.  On by default.
+    pub verify_hashes:  bool,
+    /// After extraction, re-read every file from disk, re-hash it along its
+    /// block refs, and require the resulting Merkle root to equal the INDEX
+    /// `root_hash`.  Implies `verify_hashes`; fails if any entry was skipped.
+    pub paranoid:       bool,
+    /// Skip BLAKE3 entirely — block headers as well as INDEX refs — for
+    /// callers that verify the extracted files themselves; see
+    /// [`SixCyReader::danger_skip_content_hash`].  Ignored with `paranoid`.
+    pub danger_skip_content_hash: bool,
+    /// Drop this many leading `/`-separated components from every entry
+    /// name, like tar's `--strip-components`.  Entries with nothing left
+    /// are not extracted.
+    pub strip_components: usize,
+    /// `(old, new)` prefix rewrites applied after stripping; the first whose
+    /// `old` starts the name replaces it with `new`.
+    pub transforms:     Vec<(String, String)>,
+    /// Byte budget for keeping decoded blocks that later entries share, so
+    /// each is decoded and verified once (see
+    /// [`SixCyReader::enable_block_cache`]); `0` disables it.
+    pub block_cache:    usize,
+    pub windows_names:  WindowsNames,
+    /// The order entries are extracted in.  Index order by default; with
+    /// `Name`, a directory's files are written in name order.
+    pub order:          EntryOrder,
+}
+
+impl ExtractOptions {
+    /// Where `name` lands relative to the destination, or `None` if
+    /// [`strip_components`](Self::strip_components) leaves nothing of it.
+    ///
+    /// Fails with `ErrorKind::InvalidInput` if the result — after stripping
+    /// and any transform — would leave the destination: a `..` component,
+    /// an absolute path, or a drive prefix.
+    pub fn dest_name(&self, name: &str) -> io::Result<Option<String>> {
+        let rest: Vec<&str> = name.split('/')
+            .filter(|c| !c.is_empty() && *c != ".")
+            .skip(self.strip_components)
+            .collect();
+        if rest.is_empty() { return Ok(None); }
+        let rest = rest.join("/");
+        let dest = match self.transforms.iter().find(|(old, _)| rest.starts_with(old.as_str())) {
+            Some((old, new)) => format!("{new}{}", &rest[old.len()..]),
+            None             => rest,
+        };
+        check_confined(name, Path::new(&dest))?;
+        Ok(Some(dest))
+    }
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            dedup_strategy: DedupStrategy::default(),
+            overwrite:      OverwritePolicy::default(),
+            verify_hashes:  true,
+            paranoid:       false,
+            danger_skip_content_hash: false,
+            strip_components: 0,
+            transforms:     Vec::new(),
+            block_cache:    DEFAULT_BLOCK_CACHE_BYTES,
+            windows_names:  WindowsNames::default(),
+            order:          EntryOrder::default(),
+        }
+    }
+}
+
+/// Summary returned by [`Archive::extract_with`].
+#[derive(Debug, Clone, Default)]
+pub struct ExtractReport {
+    pub files:     usize,
+    /// Entries materialised by hardlink or reflink instead of writing bytes.
+    pub linked:    usize,
+    /// Entries left alone because the destination existed and the
+    /// [`OverwritePolicy`] kept it.
+    pub skipped:   usize,
+    /// Entries whose destination already held identical content
+    /// ([`OverwritePolicy::KeepNewer`] and [`OverwritePolicy::Resume`]
+    /// only).
+    pub unchanged:     usize,
+    /// Entries not extracted because [`ExtractOptions::strip_components`]
+    /// removed their whole name.
+    pub stripped:      usize,
+    /// Block refs served from the block cache instead of being decoded.
+    pub cache_hits:    u64,
+    /// `(name, written as)` for entries renamed under
+    /// [`WindowsNames::Rename`].
+    pub renamed:       Vec<(String, String)>,
+    /// With [`ExtractOptions::paranoid`], the root hash the extracted files
+    /// were shown to reproduce.
+    pub verified_root: Option<[u8; 32]>,
+}
+
+/// Where an extraction stands, passed to the callback of
+/// [`Archive::extract_with_progress`] before each entry and once at the end.
+///
+/// Sizes come from the INDEX, so `bytes_total` is exact from the first
+/// call and `bytes_done / bytes_total` tracks real work rather than file
+/// counts.  Entries that are linked, skipped, or already up to date count
+/// as done too.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractProgress<'a> {
+    pub bytes_total: u64,
+    /// Original bytes of the entries finished so far.
+    pub bytes_done:  u64,
+    pub files_total: usize,
+    pub files_done:  usize,
+    /// Name of the entry about to be extracted; `None` on the final call.
+    pub current:     Option<&'a str>,
+    pub elapsed:     Duration,
+}
+
+impl ExtractProgress<'_> {
+    /// `bytes_done / bytes_total`, 0.0–1.0 (1.0 for an empty archive).
+    pub fn fraction(&self) -> f64 {
+        if self.bytes_total == 0 { return 1.0; }
+        self.bytes_done as f64 / self.bytes_total as f64
+    }
+
+    /// Time left at the average rate so far; `None` until some bytes are done.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.bytes_done == 0 { return None; }
+        let left = self.bytes_total.saturating_sub(self.bytes_done) as f64;
+        Some(self.elapsed.mul_f64(left / self.bytes_done as f64))
+    }
+}
+
+// ── HealthReport ──────────────────────────────────────────────────────────────
+
+/// Result of [`Archive::health_check`].
+#[derive(Debug, Clone, Default)]
+pub struct HealthReport {
+    /// Distinct blocks stored in the archive.
+    pub blocks_total:   usize,
+    /// Blocks read and verified.
+    pub blocks_checked: usize,
+    /// Offset and error of every block that failed.
+    pub failures:       Vec<(u64, String)>,
+    /// A sampled block failed, so every block was verified.
+    pub escalated:      bool,
+}
+
+impl HealthReport {
+    /// Fraction of checked blocks that verified (1.0 when none were checked).
+    pub fn score(&self) -> f64 {
+        if self.blocks_checked == 0 { return 1.0; }
+        1.0 - self.failures.len() as f64 / self.blocks_checked as f64
+    }
+
+    pub fn is_healthy(&self) -> bool { self.failures.is_empty() }
+}
+
+// ── SpaceReport ───────────────────────────────────────────────────────────────
+
+/// Result of [`Archive::space_report`]: how much of the file is still in
+/// use, and how it is laid out — whether a compaction
+/// ([`optimize`](crate::optimize::optimize)) would pay.
+#[derive(Debug, Clone, Default)]
+pub struct SpaceReport {
+    /// Blocks found by the header walk, INDEX included.
+    pub blocks_total:      usize,
+    /// Their bytes on disk, headers included.
+    pub block_bytes:       u64,
+    pub solid_blocks:      usize,
+    /// Blocks nothing references: DATA, SOLID and META blocks the INDEX
+    /// does not list, superseded INDEX blocks, and a CODECS block the
+    /// superblock no longer points at.
+    pub orphaned_blocks:   usize,
+    /// Their bytes on disk, headers included.
+    pub orphaned_bytes:    u64,
+    /// Uncompressed bytes the entries read from this archive's blocks.
+    pub logical_bytes:     u64,
+    /// Uncompressed bytes of the distinct DATA and SOLID blocks they read.
+    pub unique_bytes:      u64,
+    /// Files stored in more than one block of this archive.
+    pub multi_block_files: usize,
+    /// Of those, files whose blocks are not back to back in read order.
+    pub fragmented_files:  usize,
+    /// Offset of a header the walk could not read; the counts above cover
+    /// the blocks before it.
+    pub unreadable_at:     Option<u64>,
+}
+
+impl SpaceReport {
+    /// Logical bytes per stored byte of content (1.0 when nothing is stored).
+    pub fn dedup_ratio(&self) -> f64 {
+        if self.unique_bytes == 0 { return 1.0; }
+        self.logical_bytes as f64 / self.unique_bytes as f64
+    }
+
+    /// Fraction of multi-block files that are fragmented (0.0 when none).
+    pub fn fragmentation(&self) -> f64 {
+        if self.multi_block_files == 0 { return 0.0; }
+        self.fragmented_files as f64 / self.multi_block_files as f64
+    }
+}
+
+// ── BlockInfo ─────────────────────────────────────────────────────────────────
+
+/// One block ref of a file, returned by [`Archive::file_blocks`].
+#[derive(Debug, Clone)]
+pub struct BlockInfo {
+    /// Offset of the block header in the archive holding the block.
+    pub offset:       u64,
+    /// Where this ref's bytes start in the file.
+    pub file_offset:  u64,
+    /// Bytes of the file this ref supplies; 0 for a chunk whose header is
+    /// unreadable, which also leaves later `file_offset`s short.
+    pub length:       u64,
+    /// On-disk payload bytes of the whole block (0 if its header is unreadable).
+    pub comp_size:    u64,
+    /// Plaintext bytes of the whole block (0 if its header is unreadable).
+    pub orig_size:    u64,
+    /// BLAKE3 of this ref's plaintext, from the INDEX.
+    pub hash:         [u8; 32],
+    pub codec:        Option<[u8; 16]>,
+    pub encrypted:    bool,
+    /// For a slice of a SOLID block: its offset within the block's plaintext.
+    pub intra_offset: Option<u64>,
+    /// Set when the block lives in a base archive of a snapshot chain.
+    pub archive_id:   Option<[u8; 16]>,
+    /// Why the block header could not be read (bad CRC, truncated archive,
+    /// base archive not attached); `None` when it is intact.
+    pub error:        Option<String>,
+}
+
+impl BlockInfo {
+    /// Byte range of the block, header included, in the archive holding it.
+    pub fn archive_range(&self) -> std::ops::Range<u64> {
+        self.offset..self.offset + BLOCK_HEADER_SIZE as u64 + self.comp_size
+    }
+}
+
 // ── FileInfo ──────────────────────────────────────────────────────────────────
 
 /// Lightweight descriptor returned by [`Archive::list`].
@@ -58,9 +576,34 @@ pub struct FileInfo {
     pub id:               u32,
     pub name:             String,
     pub original_size:    u64,
+    /// Stored bytes charged to the file, shared blocks split between their
+    /// files; see [`FileIndexRecord::compressed_size`].
     pub compressed_size:  u64,
+    /// Stored bytes only this file references.  Archives written before
+    /// this was recorded report `compressed_size`.
+    pub unique_compressed_size: u64,
     pub block_count:      usize,
     pub first_block_hash: Option<[u8; 32]>,
+    /// See [`PackOptions::secondary_hash`].
+    pub secondary_hash:   Option<SecondaryHash>,
+    /// The record's metadata, including [`PackOptions::default_metadata`].
+    pub metadata:         HashMap<String, String>,
+    /// Codec UUID of the file's first block.  Only
+    /// [`Archive::entries_detailed`] reads block headers; `None` otherwise
+    /// and for empty files.
+    pub codec:            Option<[u8; 16]>,
+    /// True if the file's first block is encrypted, i.e. extracting it
+    /// needs the password.  Set by [`Archive::entries_detailed`] only.
+    pub encrypted:        bool,
+}
+
+impl FileInfo {
+    /// Original size over `compressed_size` (higher is better); 1.0 when
+    /// nothing is stored for the file here.
+    pub fn ratio(&self) -> f64 {
+        if self.compressed_size == 0 { return 1.0; }
+        self.original_size as f64 / self.compressed_size as f64
+    }
 }
 
 impl From<&FileIndexRecord> for FileInfo {
@@ -70,8 +613,13 @@ impl From<&FileIndexRecord> for FileInfo {
             name:             r.name.clone(),
             original_size:    r.original_size,
             compressed_size:  r.compressed_size,
+            unique_compressed_size: r.unique_compressed_size.unwrap_or(r.compressed_size),
             block_count:      r.block_refs.len(),
             first_block_hash: r.block_refs.first().map(|b| b.content_hash),
+            secondary_hash:   r.secondary_hash.clone(),
+            metadata:         r.metadata.clone(),
+            codec:            None,
+            encrypted:        false,
         }
     }
 }
@@ -79,67 +627,506 @@ impl From<&FileIndexRecord> for FileInfo {
 // ── ArchiveMode ───────────────────────────────────────────────────────────────
 
 enum ArchiveMode {
-    Read(SixCyReader<File>),
-    Write(SixCyWriter<File>, CodecId),
+    Read(Box<SixCyReader<Section<SharedFile>>>),
+    Write(Box<SixCyWriter<File>>, CodecId),
 }
 
 // ── Archive ───────────────────────────────────────────────────────────────────
 
 pub struct Archive {
-    path: PathBuf,
-    mode: ArchiveMode,
+    path:    PathBuf,
+    mode:    ArchiveMode,
+    /// Output of an atomic create, moved into place by `finalize`.
+    pending: Option<AtomicFile>,
+    /// [`PackOptions::verify_on_write`].
+    verify_on_write: bool,
+    /// Set by [`Archive::preallocate`] and [`Archive::open_for_append`]:
+    /// `finalize` truncates the file to the archive's length.
+    truncate_output: bool,
 }
 
 impl Archive {
     // ── Constructors ─────────────────────────────────────────────────────────
 
-    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         Self::open_with_password(path, None)
     }
 
-    pub fn open_encrypted<P: AsRef<Path>>(path: P, password: &str) -> io::Result<Self> {
+    pub fn open_encrypted<P: AsRef<Path>>(path: P, password: &str) -> Result<Self> {
         Self::open_with_password(path, Some(password.to_owned()))
     }
 
-    fn open_with_password<P: AsRef<Path>>(path: P, password: Option<String>) -> io::Result<Self> {
-        let path = path.as_ref().to_owned();
+    /// [`Archive::open_encrypted`], calling `on_tick` every `interval`
+    /// while Argon2id derives the key; see
+    /// [`crypto::derive_key_with_progress`](crate::crypto::derive_key_with_progress).
+    pub fn open_encrypted_with_progress<P: AsRef<Path>>(
+        path:     P,
+        password: &str,
+        interval: Duration,
+        on_tick:  impl FnMut(KdfProgress),
+    ) -> Result<Self> {
+        let sb  = Superblock::read(&mut File::open(path.as_ref())?)?;
+        let key = derive_key_with_progress(password, sb.archive_uuid.as_bytes(), interval, on_tick)?;
+        Self::open_keyed(path, 0, Some(key), Limits::default())
+    }
+
+    /// Open an archive from an untrusted source under resource `limits`
+    /// (see [`Limits`]).  `password` may be `None` for unencrypted archives.
+    pub fn open_untrusted<P: AsRef<Path>>(path: P, password: Option<&str>, limits: Limits) -> Result<Self> {
+        Self::open_limited(path, 0, password.map(str::to_owned), limits)
+    }
+
+    /// Open an unencrypted archive with its INDEX bounded by `limits`,
+    /// failing with [`io::ErrorKind::TimedOut`] if `limits.deadline` passes
+    /// first — for services opening uploads whose INDEX may be built to
+    /// waste time.  Reads after the open are not limited; use
+    /// [`open_untrusted`](Self::open_untrusted) for block limits.
+    pub fn open_with_limits<P: AsRef<Path>>(path: P, limits: OpenLimits) -> Result<Self> {
+        let path   = path.as_ref().to_owned();
+        let file   = Section::new(SharedFile::new(File::open(&path)?), 0)?;
+        let reader = SixCyReader::with_deadline(file, None, limits.limits(), limits.deadline)?;
+        Ok(Self { path, mode: ArchiveMode::Read(Box::new(reader)), pending: None,
+                  verify_on_write: false, truncate_output: false })
+    }
+
+    /// Fetch only the superblock and INDEX of the archive at `url`
+    /// (`http://`, `https://`, or `s3://bucket/key`) — two range requests,
+    /// under [`Limits::untrusted`] — for listing it without any data
+    /// access.  See [`crate::remote`].  Requests use the
+    /// [`HttpSource`] default timeouts; pass a configured source to
+    /// [`open_remote_metadata_with`](Self::open_remote_metadata_with) to change them.
+    pub fn open_remote_metadata(url: &str) -> Result<ArchiveMetadata> {
+        Self::open_remote_metadata_with(&mut HttpSource::new(url)?)
+    }
+
+    /// [`open_remote_metadata`](Self::open_remote_metadata) through
+    /// `source` — e.g. an [`HttpSource`] with
+    /// [`with_timeouts`](HttpSource::with_timeouts), or another
+    /// [`RangeSource`](crate::remote::RangeSource) backend.
+    pub fn open_remote_metadata_with<S: crate::remote::RangeSource + ?Sized>(
+        source: &mut S,
+    ) -> Result<ArchiveMetadata> {
+        ArchiveMetadata::fetch(source, Limits::untrusted())
+    }
 
+    /// Open normally, or — if the INDEX (or superblock) cannot be used —
+    /// with a file list rebuilt by scanning block headers.  Check
+    /// [`Archive::is_reconstructed`]: rebuilt entries have synthesised names
+    /// (`file_XXXXXXXX`) and files stored in SOLID blocks are not listed.
+    /// If the scan fails too, the error from the normal open is returned.
+    pub fn open_resilient<P: AsRef<Path>>(path: P, password: Option<&str>) -> Result<Self> {
+        let path = path.as_ref();
+        let err = match Self::open_limited(path, 0, password.map(str::to_owned), Limits::default()) {
+            Ok(ar) => return Ok(ar),
+            Err(e) => e,
+        };
+        let key = match password {
+            Some(pwd) => {
+                let sb = Superblock::read_lenient(&mut File::open(path)?)?.superblock;
+                Some(derive_key(pwd, sb.archive_uuid.as_bytes())?)
+            }
+            None => None,
+        };
+        match SixCyReader::reconstructed(Section::new(SharedFile::new(File::open(path)?), 0)?, key, Limits::default()) {
+            Ok(reader) => Ok(Self {
+                path:            path.to_owned(),
+                mode:            ArchiveMode::Read(Box::new(reader)),
+                pending:         None,
+                verify_on_write: false,
+                truncate_output: false,
+            }),
+            Err(_)     => Err(err),
+        }
+    }
+
+    /// Base offsets of the archives in a file made by concatenating
+    /// archives (`cat a.6cy b.6cy > c.6cy`); `[0]` for an ordinary archive.
+    pub fn sections<P: AsRef<Path>>(path: P) -> Result<Vec<u64>> {
+        find_sections(&mut File::open(path)?)
+    }
+
+    /// Open the archive starting at byte `base` (one of [`Archive::sections`]).
+    pub fn open_section<P: AsRef<Path>>(path: P, base: u64, password: Option<&str>) -> Result<Self> {
+        Self::open_limited(path, base, password.map(str::to_owned), Limits::default())
+    }
+
+    fn open_with_password<P: AsRef<Path>>(path: P, password: Option<String>) -> Result<Self> {
+        Self::open_limited(path, 0, password, Limits::default())
+    }
+
+    /// Open an archive encrypted under a raw AES-256 `key` — e.g. one
+    /// unwrapped from a KMS — instead of a password, skipping Argon2.
+    /// The key must be the one the archive was created with
+    /// ([`Archive::create_with_key`]).
+    pub fn open_with_key<P: AsRef<Path>>(path: P, key: [u8; 32]) -> Result<Self> {
+        Self::open_keyed(path, 0, Some(key), Limits::default())
+    }
+
+    fn open_limited<P: AsRef<Path>>(path: P, base: u64, password: Option<String>, limits: Limits) -> Result<Self> {
         let key = if let Some(ref pwd) = password {
-            let mut f = File::open(&path)?;
-            let sb = Superblock::read(&mut f)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-            Some(derive_key(pwd, sb.archive_uuid.as_bytes())
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?)
+            let mut f = Section::new(File::open(path.as_ref())?, base)?;
+            let sb = Superblock::read(&mut f)?;
+            Some(derive_key(pwd, sb.archive_uuid.as_bytes())?)
         } else {
             None
         };
+        Self::open_keyed(path, base, key, limits)
+    }
+
+    fn open_keyed<P: AsRef<Path>>(path: P, base: u64, key: Option<[u8; 32]>, limits: Limits) -> Result<Self> {
+        let path   = path.as_ref().to_owned();
+        let reader = SixCyReader::with_limits(Section::new(SharedFile::new(File::open(&path)?), base)?, key, limits)?;
+        Ok(Self { path, mode: ArchiveMode::Read(Box::new(reader)), pending: None,
+                  verify_on_write: false, truncate_output: false })
+    }
+
+    /// Open an incremental snapshot together with its base chain.
+    ///
+    /// `chain` lists the base archives (any order); each must be
+    /// unencrypted.  For encrypted bases open them individually and use
+    /// [`Archive::attach_base`].
+    pub fn open_chain<P: AsRef<Path>, Q: AsRef<Path>>(path: P, chain: &[Q]) -> Result<Self> {
+        let mut ar = Self::open(path)?;
+        for base in chain {
+            ar.attach_base(Self::open(base)?)?;
+        }
+        Ok(ar)
+    }
+
+    /// Attach an opened base archive so blocks this snapshot borrows from it
+    /// can be read.  Both archives must be in read mode.
+    pub fn attach_base(&mut self, base: Archive) -> Result<()> {
+        match (&mut self.mode, base.mode) {
+            (ArchiveMode::Read(r), ArchiveMode::Read(b)) => {
+                r.attach_parent(*b);
+                Ok(())
+            }
+            (ArchiveMode::Read(_), ArchiveMode::Write(..)) => Err(write_only()),
+            (ArchiveMode::Write(..), _)                    => Err(read_only()),
+        }
+    }
 
-        let reader = SixCyReader::with_key(File::open(&path)?, key)?;
-        Ok(Self { path, mode: ArchiveMode::Read(reader) })
+    /// Another read handle on this archive, e.g. one per request in a
+    /// server.  The file handle is shared with positional reads and the
+    /// parsed INDEX through an `Arc`, so nothing is re-read or re-parsed;
+    /// attached bases are cloned too.  Block cache and perf stats are not
+    /// carried over.  Fails for archives in write mode.
+    pub fn try_clone(&self) -> Result<Self> {
+        let ArchiveMode::Read(r) = &self.mode else { return Err(write_only()) };
+        let reader = r.try_clone_with(&mut |s: &Section<SharedFile>| s.try_clone())?;
+        Ok(Self {
+            path:            self.path.clone(),
+            mode:            ArchiveMode::Read(Box::new(reader)),
+            pending:         None,
+            verify_on_write: false,
+            truncate_output: false,
+        })
     }
 
-    pub fn create<P: AsRef<Path>>(path: P, opts: PackOptions) -> io::Result<Self> {
+    pub fn create<P: AsRef<Path>>(path: P, opts: PackOptions) -> Result<Self> {
+        Self::create_keyed(path, opts, None)
+    }
+
+    /// Create an archive whose blocks are encrypted under the raw AES-256
+    /// `key`, with no password or Argon2 derivation; open it again with
+    /// [`Archive::open_with_key`].  `opts.password` must be `None`.
+    pub fn create_with_key<P: AsRef<Path>>(path: P, opts: PackOptions, key: [u8; 32]) -> Result<Self> {
+        if opts.password.is_some() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "create_with_key: PackOptions::password must be None").into());
+        }
+        Self::create_keyed(path, opts, Some(key))
+    }
+
+    fn create_keyed<P: AsRef<Path>>(path: P, opts: PackOptions, key: Option<[u8; 32]>) -> Result<Self> {
         let path = path.as_ref().to_owned();
+        let (file, pending) = if opts.atomic {
+            let out = AtomicFile::create(&path)?;
+            (out.file().try_clone()?, Some(out))
+        } else {
+            (File::create(&path)?, None)
+        };
         let mut writer = SixCyWriter::with_options(
-            File::create(&path)?,
+            file,
             opts.chunk_size,
             opts.level,
             None,
         )?;
 
+        if opts.deterministic {
+            if opts.password.is_some() && opts.fixed_uuid.is_none() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                    "deterministic encrypted archives need PackOptions::fixed_uuid").into());
+            }
+            writer.make_deterministic(opts.fixed_uuid);
+        } else if let Some(uuid) = opts.fixed_uuid {
+            writer.superblock.archive_uuid = uuid;
+        }
+
         if let Some(ref pwd) = opts.password {
-            let key = derive_key(pwd, writer.superblock.archive_uuid.as_bytes())
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let key = derive_key(pwd, writer.superblock.archive_uuid.as_bytes())?;
             writer.encryption_key = Some(key);
+        } else {
+            writer.encryption_key = key;
+        }
+        Self::with_writer(path, writer, opts, pending)
+    }
+
+    /// Reopen the finalized archive at `path` to add files.  New DATA
+    /// blocks follow its INDEX and recovery map, and `finalize` writes a
+    /// fresh INDEX, syncs, and only then patches the superblock to point at
+    /// it.  Content already stored — chunks and solid members alike — is
+    /// deduplicated against, and the archive keeps its UUID, key, name
+    /// policy, and INDEX codec.
+    ///
+    /// `opts.password` must be given for an encrypted archive; `atomic`,
+    /// `deterministic`, and `fixed_uuid` do not apply.  An append that is
+    /// interrupted before `finalize` completes leaves the archive as it
+    /// was, with some unreferenced bytes at the end that the next append
+    /// overwrites.  Each append leaves the old INDEX behind as an orphaned
+    /// block ([`SpaceReport::orphaned_blocks`]) until the archive is
+    /// rewritten.
+    pub fn open_for_append<P: AsRef<Path>>(path: P, mut opts: PackOptions) -> Result<Self> {
+        if opts.atomic || opts.deterministic || opts.fixed_uuid.is_some() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "atomic, deterministic, and fixed-UUID options do not apply to an append").into());
+        }
+        let path = path.as_ref().to_owned();
+        if Self::sections(&path)?.len() > 1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "cannot append to concatenated archives").into());
+        }
+        let sb  = Superblock::read(&mut File::open(&path)?)?;
+        let key = match &opts.password {
+            Some(pwd) => Some(derive_key(pwd, sb.archive_uuid.as_bytes())?),
+            // The INDEX reads without the key, but new blocks need it.
+            None if sb.flags & SB_FLAG_ENCRYPTED != 0 => return Err(io::Error::new(
+                io::ErrorKind::InvalidInput, "appending to an encrypted archive needs its password").into()),
+            None => None,
+        };
+        let mut reader = SixCyReader::with_key(Section::new(File::open(&path)?, 0)?, key)?;
+        let file       = OpenOptions::new().read(true).write(true).open(&path)?;
+        let mut writer = SixCyWriter::append_to(&mut reader, file)?;
+        writer.commit_sync       = Some(File::sync_data);
+        writer.chunk_size        = opts.chunk_size.max(1);
+        writer.compression_level = opts.level;
+        opts.name_policy = writer.index.name_policy.unwrap_or(opts.name_policy);
+        opts.index_codec = writer.index_codec;
+
+        let mut ar = Self::with_writer(path, writer, opts, None)?;
+        ar.truncate_output = true;
+        Ok(ar)
+    }
+
+    /// Apply the writer settings of `opts` (all but the UUID, key, and
+    /// output handling) and wrap `writer` as an archive in write mode.
+    fn with_writer(
+        path:       PathBuf,
+        mut writer: SixCyWriter<File>,
+        opts:       PackOptions,
+        pending:    Option<AtomicFile>,
+    ) -> Result<Self> {
+        writer.solid_level      = opts.solid_level;
+        writer.index_level      = opts.index_level;
+        writer.index_codec      = opts.index_codec;
+        writer.auto_solid       = opts.auto_solid;
+        writer.auto_level       = opts.auto_level;
+        writer.chunk_policy     = opts.chunk_policy;
+        writer.solid_max_bytes  = opts.solid_block_max_bytes;
+        writer.solid_max_files  = opts.max_files_per_solid;
+        writer.solid_order      = opts.solid_order;
+        writer.secondary_hash   = opts.secondary_hash;
+        writer.default_metadata = opts.default_metadata;
+        writer.name_policy      = opts.name_policy;
+        writer.observer         = opts.observer;
+        if let Some(dict) = &opts.dictionary {
+            register_dictionary(Arc::clone(dict));
+        }
+        writer.dictionary       = opts.dictionary;
+        if let Some(depth) = opts.queue_depth {
+            writer.queue_depth = depth.max(1);
+        }
+        if let Some(pipeline) = &opts.pipeline {
+            pipeline.validate(writer.encryption_key.is_some())?;
         }
+        writer.pipeline = opts.pipeline;
+        // After key derivation, so `wall` covers only the pipeline.
+        if opts.perf_stats { writer.enable_perf_stats(); }
 
-        let default_codec = opts.default_codec;
-        Ok(Self { path, mode: ArchiveMode::Write(writer, default_codec) })
+        Ok(Self {
+            path,
+            mode:            ArchiveMode::Write(Box::new(writer), opts.default_codec),
+            pending,
+            verify_on_write: opts.verify_on_write,
+            truncate_output: false,
+        })
+    }
+
+    /// Create an incremental snapshot on top of `base`.
+    ///
+    /// Chunks whose content hash already exists in `base` (or anything `base`
+    /// itself borrows from) are not stored again; the new index references
+    /// them by the base's archive UUID.  Reading the result requires the base
+    /// chain — see [`Archive::open_chain`].  Use the same `chunk_size` as the
+    /// base, otherwise chunk boundaries (and hashes) will not line up.
+    pub fn create_incremental<P: AsRef<Path>>(
+        path: P,
+        opts: PackOptions,
+        base: &Archive,
+    ) -> Result<Self> {
+        let ArchiveMode::Read(b) = &base.mode else { return Err(write_only()) };
+        let mut ar = Self::create(path, opts)?;
+        if let ArchiveMode::Write(w, _) = &mut ar.mode {
+            w.add_base_index(&b.superblock.archive_uuid, &b.index);
+        }
+        Ok(ar)
+    }
+
+    /// Pack every regular file under `src_dir` into a new archive at `dest`
+    /// and finalize it — the counterpart of [`Archive::extract_with`].
+    ///
+    /// Entries are named by their path relative to `src_dir` with `/`
+    /// separators, and added in sorted order so identical trees produce
+    /// identical indexes.  Solid grouping, dedup, and every other setting
+    /// come from `opts` (set [`PackOptions::auto_solid`] to group small
+    /// files).  With the `windows-metadata` feature each file's attributes
+    /// and creation time are recorded.  Symlinks are not followed.
+    pub fn pack_dir<P: AsRef<Path>, Q: AsRef<Path>>(src_dir: P, dest: Q, opts: PackOptions) -> Result<PackReport> {
+        let mut report = PackReport::default();
+        let mut files  = Vec::new();
+        let own = dest.as_ref().canonicalize().ok();
+        walk_dir(src_dir.as_ref(), &EntryName::default(), own.as_deref(), &mut files, &mut report)?;
+
+        let reserve = opts.preallocate;
+        let mut ar = Self::create(dest, opts)?;
+        if reserve {
+            let total = files.iter()
+                .map(|(_, path)| std::fs::metadata(path).map(|m| m.len()))
+                .sum::<io::Result<u64>>()?;
+            ar.preallocate(total)?;
+        }
+        for (name, path) in &files {
+            let file = File::open(path)?;
+            let len  = file.metadata()?.len();
+            #[cfg(feature = "windows-metadata")]
+            let metadata = crate::winmeta::capture(path)?;
+            #[cfg(not(feature = "windows-metadata"))]
+            let metadata = HashMap::new();
+            ar.add_entry_from_reader(name.clone(), file, len, metadata)?;
+            report.files       += 1;
+            report.input_bytes += len;
+        }
+        ar.finalize()?;
+        report.archive_bytes = std::fs::metadata(&ar.path)?.len();
+        report.duplicates    = ar.duplicate_files();
+        Ok(report)
+    }
+
+    /// Merge every entry of `sources`, in order, into a new archive at
+    /// `dest` and finalize it.  Names and conflicts are handled as
+    /// `on_conflict` directs; each entry keeps its metadata.  The INDEXes
+    /// are planned first, then files are copied one at a time, so memory
+    /// holds a single file however large the sources are.
+    pub fn merge<P: AsRef<Path>>(
+        sources:     &mut [Archive],
+        dest:        P,
+        opts:        PackOptions,
+        on_conflict: MergeConflict,
+    ) -> Result<MergeReport> {
+        struct Planned {
+            source:      usize,
+            id:          u32,
+            name:        String,
+            fingerprint: (u64, Vec<[u8; 32]>),
+            mtime:       Option<i64>,
+            metadata:    HashMap<String, String>,
+        }
+        let fingerprint = |r: &FileIndexRecord| {
+            (r.original_size, r.block_refs.iter().map(|b| b.content_hash).collect::<Vec<_>>())
+        };
+
+        let mut report = MergeReport::default();
+        let mut plan: Vec<Planned> = Vec::new();
+        let mut names: HashMap<String, usize> = HashMap::new();
+        for (source, ar) in sources.iter().enumerate() {
+            let ArchiveMode::Read(r) = &ar.mode else { return Err(write_only()) };
+            let stem  = ar.path.file_stem().unwrap_or_default().to_string_lossy();
+            let mtime = r.superblock.modified_at;
+            for rec in &r.index.records {
+                let mut name = match on_conflict {
+                    MergeConflict::Prefix => format!("{stem}/{}", rec.name),
+                    _                     => rec.name.clone(),
+                };
+                let entry = Planned {
+                    source,
+                    id:          rec.id,
+                    name:        name.clone(),
+                    fingerprint: fingerprint(rec),
+                    mtime,
+                    metadata:    rec.metadata.clone(),
+                };
+                let Some(&slot) = names.get(&name) else {
+                    names.insert(name, plan.len());
+                    plan.push(entry);
+                    continue;
+                };
+                if plan[slot].fingerprint == entry.fingerprint {
+                    report.identical += 1;
+                    continue;
+                }
+                match on_conflict {
+                    MergeConflict::Prefix | MergeConflict::KeepFirst => report.dropped += 1,
+                    MergeConflict::KeepNewest => {
+                        if entry.mtime >= plan[slot].mtime {
+                            plan[slot] = entry;
+                        }
+                        report.dropped += 1;
+                    }
+                    MergeConflict::Rename => {
+                        // Number before the extension; a leading dot is not one.
+                        let base = name.rfind('/').map_or(0, |slash| slash + 1);
+                        let dot  = name.rfind('.').filter(|&dot| dot > base);
+                        let (stem, ext) = name.split_at(dot.unwrap_or(name.len()));
+                        let renamed = (1..).map(|n| format!("{stem} ({n}){ext}"))
+                            .find(|candidate| !names.contains_key(candidate))
+                            .expect("some suffix is free");
+                        report.renamed.push((name.clone(), renamed.clone()));
+                        name = renamed;
+                        names.insert(name.clone(), plan.len());
+                        plan.push(Planned { name, ..entry });
+                    }
+                    MergeConflict::Error => {
+                        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!(
+                            "'{name}' differs between {} and {}",
+                            sources[plan[slot].source].path.display(), ar.path.display(),
+                        )).into());
+                    }
+                }
+            }
+        }
+
+        let mut dst = Self::create(dest, opts)?;
+        for entry in plan {
+            let data = sources[entry.source].read_file_by_id(entry.id)?;
+            dst.add_file_with_metadata(&entry.name, &data, entry.metadata)?;
+            report.files += 1;
+        }
+        // App data under a key several sources share comes from the first.
+        for ar in sources.iter_mut() {
+            for key in ar.app_data_keys() {
+                if !dst.app_data_keys().contains(&key) {
+                    let data = ar.get_app_data(&key)?.expect("key listed by the INDEX");
+                    dst.put_app_data(&key, &data)?;
+                }
+            }
+        }
+        dst.finalize()?;
+        Ok(report)
     }
 
     // ── Write ─────────────────────────────────────────────────────────────────
 
-    pub fn add_file(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> Result<()> {
         let codec = match &self.mode {
             ArchiveMode::Write(_, c) => *c,
             ArchiveMode::Read(_)     => return Err(read_only()),
@@ -147,49 +1134,191 @@ impl Archive {
         self.add_file_with_codec(name, data, codec)
     }
 
-    pub fn add_file_with_codec(&mut self, name: &str, data: &[u8], codec: CodecId) -> io::Result<()> {
+    pub fn add_file_with_codec(&mut self, name: &str, data: &[u8], codec: CodecId) -> Result<()> {
         match &mut self.mode {
             ArchiveMode::Write(w, _) => w.add_file(name.to_owned(), data, codec),
             ArchiveMode::Read(_)     => Err(read_only()),
         }
     }
 
-    pub fn begin_solid(&mut self, codec: CodecId) -> io::Result<()> {
+    /// Add a file with the archive's default codec and a metadata map
+    /// stored in its INDEX record (see [`FileIndexRecord::metadata`]).
+    pub fn add_file_with_metadata(
+        &mut self,
+        name:     &str,
+        data:     &[u8],
+        metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        match &mut self.mode {
+            ArchiveMode::Write(w, c) => w.add_file_with_metadata(name.to_owned(), data, *c, metadata),
+            ArchiveMode::Read(_)     => Err(read_only()),
+        }
+    }
+
+    /// Add a file read from `reader`, `len` bytes long, with the archive's
+    /// default codec and `metadata`.  Large files are streamed a chunk at a
+    /// time rather than held whole; see
+    /// [`SixCyWriter::add_file_from_reader`].
+    pub fn add_file_from_reader(
+        &mut self,
+        name:     &str,
+        reader:   impl Read,
+        len:      u64,
+        metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        self.add_entry_from_reader(name.into(), reader, len, metadata)
+    }
+
+    /// [`add_file_from_reader`](Self::add_file_from_reader) under an
+    /// [`EntryName`], which keeps the bytes of a file-system name that is
+    /// not valid UTF-8 ([`EntryName::from_os`]).
+    pub fn add_entry_from_reader(
+        &mut self,
+        name:     EntryName,
+        reader:   impl Read,
+        len:      u64,
+        metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        match &mut self.mode {
+            ArchiveMode::Write(w, c) => w.add_file_from_reader(name, reader, len, *c, metadata),
+            ArchiveMode::Read(_)     => Err(read_only()),
+        }
+    }
+
+    /// Store `data` under `key` beside the files rather than among them —
+    /// a manifest, a version stamp — replacing any blob already put under
+    /// `key`.  Written by [`finalize`](Self::finalize); see
+    /// [`SixCyWriter::put_app_data`].
+    pub fn put_app_data(&mut self, key: &str, data: &[u8]) -> Result<()> {
+        match &mut self.mode {
+            ArchiveMode::Write(w, _) => w.put_app_data(key, data.to_vec()),
+            ArchiveMode::Read(_)     => Err(read_only()),
+        }
+    }
+
+    pub fn begin_solid(&mut self, codec: CodecId) -> Result<()> {
         match &mut self.mode {
             ArchiveMode::Write(w, _) => w.start_solid_session(codec),
             ArchiveMode::Read(_)     => Err(read_only()),
         }
     }
 
-    pub fn end_solid(&mut self) -> io::Result<()> {
+    pub fn end_solid(&mut self) -> Result<()> {
         match &mut self.mode {
             ArchiveMode::Write(w, _) => w.flush_solid_session(),
             ArchiveMode::Read(_)     => Err(read_only()),
         }
     }
 
+    /// Reserve `bytes` of disk for the archive being written, without
+    /// changing its length ([`crate::io_stream::preallocate`]), when the
+    /// caller knows roughly how large it will be.  A filesystem that cannot
+    /// preallocate is not an error; a full disk is.  `finalize` truncates
+    /// the file to its real length, releasing what was not used.
+    pub fn preallocate(&mut self, bytes: u64) -> Result<()> {
+        let ArchiveMode::Write(w, _) = &self.mode else { return Err(read_only()) };
+        self.truncate_output |= crate::io_stream::preallocate(w.get_ref(), bytes)?;
+        Ok(())
+    }
+
     /// Flush the INDEX block and patch the superblock.  Must be called once.
-    pub fn finalize(&mut self) -> io::Result<()> {
+    pub fn finalize(&mut self) -> Result<()> {
         match &mut self.mode {
-            ArchiveMode::Write(w, _) => w.finalize(),
-            ArchiveMode::Read(_)     => Err(read_only()),
+            ArchiveMode::Write(w, _) => {
+                w.finalize()?;
+                if self.truncate_output {
+                    let len = w.finalized_len().expect("set by finalize");
+                    w.get_ref().set_len(len)?;
+                }
+            }
+            ArchiveMode::Read(_)     => return Err(read_only()),
+        }
+        if self.verify_on_write {
+            self.verify_written()?;
+        }
+        if let Some(out) = self.pending.take() {
+            out.persist()?;
+        }
+        Ok(())
+    }
+
+    /// Read back a just-finalized archive; see
+    /// [`PackOptions::verify_on_write`].  Runs before an atomic output is
+    /// moved into place, so a failed check leaves nothing at the path.
+    fn verify_written(&mut self) -> Result<()> {
+        let ArchiveMode::Write(w, _) = &self.mode else { return Err(read_only()) };
+        let file = match &self.pending {
+            Some(out) => out.file().try_clone()?,
+            None      => File::open(&self.path)?,
+        };
+        file.sync_all()?;
+        drop_page_cache(&file);
+
+        let failed = |what: String| -> SixcyError {
+            io::Error::new(io::ErrorKind::InvalidData, format!("read-back verification failed: {what}")).into()
+        };
+        let mut reader = SixCyReader::with_key(Section::new(file, 0)?, w.encryption_key)
+            .map_err(|e| failed(format!("cannot reopen archive: {e}")))?;
+        if reader.index.to_bytes()? != w.index.to_bytes()? {
+            return Err(failed("INDEX on disk differs from the one written".into()));
+        }
+        for offset in reader.local_block_offsets() {
+            reader.verify_block_at(offset).map_err(|e| failed(format!("block at offset {offset}: {e}")))?;
         }
+        Ok(())
     }
 
     // ── Read ──────────────────────────────────────────────────────────────────
 
     pub fn list(&self) -> Vec<FileInfo> {
-        match &self.mode {
-            ArchiveMode::Read(r)     => r.index.records.iter().map(FileInfo::from).collect(),
-            ArchiveMode::Write(w, _) => w.index.records.iter().map(FileInfo::from).collect(),
-        }
+        self.entries().collect()
+    }
+
+    /// [`list`](Self::list) as a lazy iterator: each [`FileInfo`] is built
+    /// as it is consumed, so paging through a huge archive with
+    /// `skip` / `take` does not materialise the whole listing.  Entries
+    /// come in index order, the same on every run (see
+    /// [`FileIndex::records`]).
+    pub fn entries(&self) -> impl Iterator<Item = FileInfo> + '_ {
+        self.entries_in(EntryOrder::Index)
+    }
+
+    /// [`entries`](Self::entries) in `order`.
+    pub fn entries_in(&self, order: EntryOrder) -> impl Iterator<Item = FileInfo> + '_ {
+        self.index().record_order(order).map(|i| FileInfo::from(&self.records()[i]))
+    }
+
+    /// [`entries`](Self::entries) with [`FileInfo::codec`] and
+    /// [`FileInfo::encrypted`] filled in from each file's first block
+    /// header.  Costs one header read per entry; no payload is decoded and
+    /// no password is needed.
+    pub fn entries_detailed(&mut self) -> impl Iterator<Item = Result<FileInfo>> + '_ {
+        self.entries_detailed_in(EntryOrder::Index)
+    }
+
+    /// [`entries_detailed`](Self::entries_detailed) in `order`.
+    pub fn entries_detailed_in(&mut self, order: EntryOrder) -> impl Iterator<Item = Result<FileInfo>> + '_ {
+        self.index().record_order(order).map(move |i| {
+            let rec      = &self.records()[i];
+            let mut info = FileInfo::from(rec);
+            if let Some(br) = rec.block_refs.first().cloned() {
+                let header     = self.reader_mut()?.block_header(&br)?;
+                info.codec     = Some(header.codec_uuid);
+                info.encrypted = header.is_encrypted();
+            }
+            Ok(info)
+        })
+    }
+
+    fn records(&self) -> &[FileIndexRecord] {
+        &self.index().records
     }
 
     pub fn stat(&self, name: &str) -> Option<FileInfo> {
-        self.list().into_iter().find(|f| f.name == name)
+        self.records().iter().find(|r| r.name == name).map(FileInfo::from)
     }
 
-    pub fn read_file(&mut self, name: &str) -> io::Result<Vec<u8>> {
+    pub fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
         let id = self.stat(name)
             .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound,
                 format!("File not found: {name}")))?
@@ -197,14 +1326,72 @@ impl Archive {
         self.read_file_by_id(id)
     }
 
-    pub fn read_file_by_id(&mut self, id: u32) -> io::Result<Vec<u8>> {
+    pub fn read_file_by_id(&mut self, id: u32) -> Result<Vec<u8>> {
         match &mut self.mode {
             ArchiveMode::Read(r) => r.unpack_file(id),
             ArchiveMode::Write(_, _) => Err(write_only()),
         }
     }
 
-    pub fn read_at(&mut self, name: &str, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+    /// The blob stored under `key` by [`put_app_data`](Self::put_app_data),
+    /// or `None` if the archive has none.
+    pub fn get_app_data(&mut self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.reader_mut()?.read_app_data(key)
+    }
+
+    /// Keys of the archive's app data (when writing, those put so far),
+    /// in order.
+    pub fn app_data_keys(&self) -> Vec<String> {
+        match &self.mode {
+            ArchiveMode::Read(r)     => r.index.app_data.keys().cloned().collect(),
+            ArchiveMode::Write(w, _) => w.app_data_keys(),
+        }
+    }
+
+    /// Entries whose every chunk an earlier entry (in index order) had
+    /// already stored, so they cost only INDEX space — identical copies,
+    /// or files assembled from others' chunks.  Empty files and chunks
+    /// borrowed from a base archive do not count.  Works in either mode,
+    /// so a writer can report it right after adding files.
+    pub fn duplicate_files(&self) -> Vec<DuplicateFile> {
+        let mut stored: HashSet<[u8; 32]> = HashSet::new();
+        let mut first: HashMap<Vec<u8>, &str> = HashMap::new();
+        let mut duplicates = Vec::new();
+        for rec in self.records() {
+            let key = content_key(rec);
+            let hit = !rec.block_refs.is_empty()
+                && rec.block_refs.iter().all(|br| !br.is_external() && stored.contains(&br.content_hash));
+            if hit {
+                duplicates.push(DuplicateFile {
+                    name:    rec.name.clone(),
+                    size:    rec.original_size,
+                    same_as: first.get(&key).map(|&name| name.to_owned()),
+                });
+            }
+            stored.extend(rec.block_refs.iter().filter(|br| !br.is_external()).map(|br| br.content_hash));
+            first.entry(key).or_insert(&rec.name);
+        }
+        duplicates
+    }
+
+    /// Files with a block whose BLAKE3 content hash is `hash`.  A file that
+    /// fits in one chunk (or sits in a SOLID block) is found by the hash of
+    /// its whole content.
+    pub fn find_by_hash(&self, hash: &[u8; 32]) -> Vec<FileInfo> {
+        self.records().iter()
+            .filter(|r| r.block_refs.iter().any(|br| &br.content_hash == hash))
+            .map(FileInfo::from)
+            .collect()
+    }
+
+    /// The decoded bytes whose BLAKE3 hash is `hash`, using the archive as a
+    /// content-addressed store.  Fails with `NotFound` if no block has it.
+    pub fn read_block_by_hash(&mut self, hash: &[u8; 32]) -> Result<Vec<u8>> {
+        self.reader_mut()?.read_by_hash(hash)?.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound,
+            format!("No block with content hash {}", hex::encode(hash))).into())
+    }
+
+    pub fn read_at(&mut self, name: &str, offset: u64, buf: &mut [u8]) -> Result<usize> {
         let id = self.stat(name)
             .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound,
                 format!("File not found: {name}")))?
@@ -215,22 +1402,529 @@ impl Archive {
         }
     }
 
-    /// Extract all files into `dest`, creating it if necessary.
-    pub fn extract_all<P: AsRef<Path>>(&mut self, dest: P) -> io::Result<()> {
-        let dest = dest.as_ref();
+    /// Up to `len` bytes of `name` from `offset`, decoding a SOLID member's
+    /// block only as far as the range needs; see
+    /// [`SixCyReader::read_solid_range`].
+    pub fn read_solid_member_range(&mut self, name: &str, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let id = self.stat(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound,
+                format!("File not found: {name}")))?
+            .id;
+        self.reader_mut()?.read_solid_range(id, offset, len)
+    }
+
+    /// Extract all files into `dest`, creating it if necessary, in index
+    /// order.
+    pub fn extract_all<P: AsRef<Path>>(&mut self, dest: P) -> Result<()> {
+        self.extract_with(dest, &ExtractOptions::default()).map(|_| ())
+    }
+
+    /// Extract all files into `dest`, materialising duplicate content as
+    /// `opts.dedup_strategy` directs and treating existing files as
+    /// `opts.overwrite` directs.  Entries are written, and reported to
+    /// progress callbacks and observers, in `opts.order`.
+    pub fn extract_with<P: AsRef<Path>>(&mut self, dest: P, opts: &ExtractOptions) -> Result<ExtractReport> {
+        self.extract_with_progress(dest, opts, |_| {})
+    }
+
+    /// [`extract_with`](Self::extract_with), calling `progress` before each
+    /// entry and once when all are done (see [`ExtractProgress`]).
+    ///
+    /// The reader's block cache and hash-check setting are put back as they
+    /// were once extraction ends.
+    pub fn extract_with_progress<P: AsRef<Path>>(
+        &mut self,
+        dest:         P,
+        opts:         &ExtractOptions,
+        mut progress: impl FnMut(&ExtractProgress),
+    ) -> Result<ExtractReport> {
+        let reader  = self.reader_mut()?;
+        let skipped = reader.skips_content_hash();
+        let cache   = reader.swap_block_cache(None);
+        if opts.block_cache > 0 {
+            reader.enable_block_cache(opts.block_cache);
+        }
+        if opts.danger_skip_content_hash && !opts.paranoid {
+            reader.danger_skip_content_hash(true);
+        }
+        let result = self.extract_entries(dest.as_ref(), opts, &mut progress);
+        let reader = self.reader_mut()?;
+        let hits   = reader.block_cache_stats().map_or(0, |s| s.hits);
+        reader.disable_block_cache();
+        reader.danger_skip_content_hash(skipped);
+        reader.swap_block_cache(cache);
+        result.map(|report| ExtractReport { cache_hits: hits, ..report })
+    }
+
+    fn extract_entries(
+        &mut self,
+        dest:     &Path,
+        opts:     &ExtractOptions,
+        progress: &mut dyn FnMut(&ExtractProgress),
+    ) -> Result<ExtractReport> {
         if !dest.exists() { std::fs::create_dir_all(dest)?; }
-        let ids: Vec<(u32, String)> = self.list().into_iter().map(|f| (f.id, f.name)).collect();
-        for (id, name) in ids {
-            let data = self.read_file_by_id(id)?;
-            File::create(dest.join(&name))?.write_all(&data)?;
+        let mut report = ExtractReport::default();
+        let (index, archive_mtime) = match &self.mode {
+            ArchiveMode::Read(r)     => (&r.index, r.superblock.modified_at),
+            ArchiveMode::Write(_, _) => return Err(write_only()),
+        };
+        // (id, destination, content key, archive name, size)
+        let mut entries: Vec<(u32, PathBuf, Vec<u8>, String, u64)> = Vec::with_capacity(index.records.len());
+        for rec in index.record_order(opts.order).map(|i| &index.records[i]) {
+            let Some(mut name) = opts.dest_name(&rec.name)? else {
+                report.stripped += 1;
+                continue;
+            };
+            if opts.windows_names != WindowsNames::Keep {
+                if let Some(fixed) = crate::winpath::windows_name(&name) {
+                    if opts.windows_names == WindowsNames::Reject {
+                        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                            format!("entry name {name:?} cannot be created on Windows")).into());
+                    }
+                    report.renamed.push((name, fixed.clone()));
+                    name = fixed;
+                }
+            }
+            let rel = match rec.raw_name.as_deref().and_then(|raw| raw_dest_name(raw, &name, opts)) {
+                Some(raw) => raw,
+                None      => PathBuf::from(name),
+            };
+            check_confined(&rec.name, &rel)?;
+            let path = dest.join(rel);
+            #[cfg(windows)]
+            let path = crate::winpath::long_path(&path)?;
+            entries.push((rec.id, path, content_key(rec), rec.name.clone(), rec.original_size));
+        }
+
+        let verify = (opts.verify_hashes && !opts.danger_skip_content_hash) || opts.paranoid;
+        // content key → first extracted path
+        let mut extracted: HashMap<Vec<u8>, PathBuf> = HashMap::new();
+        // content key → bytes per block ref, for the paranoid re-hash
+        let mut ref_lens: HashMap<Vec<u8>, Vec<u64>> = HashMap::new();
+        let started = Instant::now();
+        let mut status = ExtractProgress {
+            bytes_total: entries.iter().map(|e| e.4).sum(),
+            bytes_done:  0,
+            files_total: entries.len(),
+            files_done:  0,
+            current:     None,
+            elapsed:     Duration::ZERO,
+        };
+        for (i, (id, path, key, name, size)) in entries.iter().enumerate() {
+            progress(&ExtractProgress { files_done: i, current: Some(name), elapsed: started.elapsed(), ..status });
+            status.bytes_done += size;
+            let (id, path) = (*id, path.clone());
+            report.files += 1;
+            let existing = std::fs::symlink_metadata(&path).ok();
+            if let Some(meta) = &existing {
+                if opts.overwrite == OverwritePolicy::Resume {
+                    if let Some(lens) = self.already_extracted(id, &path, meta)? {
+                        report.unchanged += 1;
+                        ref_lens.entry(key.clone()).or_insert(lens);
+                        extracted.entry(key.clone()).or_insert(path);
+                        continue;
+                    }
+                }
+                let keep = match opts.overwrite {
+                    OverwritePolicy::Overwrite | OverwritePolicy::Resume => false,
+                    OverwritePolicy::SkipExisting => true,
+                    OverwritePolicy::KeepNewer    => modified_after(meta, archive_mtime),
+                };
+                if keep {
+                    report.skipped += 1;
+                    continue;
+                }
+            } else if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            if opts.dedup_strategy != DedupStrategy::Copy {
+                if let Some(first) = extracted.get(key) {
+                    if link_duplicate(first, &path, opts.dedup_strategy) {
+                        report.linked += 1;
+                        continue;
+                    }
+                }
+            }
+            let data = if verify {
+                let (data, lens) = self.reader_mut()?.unpack_file_verified(id)?;
+                ref_lens.entry(key.clone()).or_insert(lens);
+                data
+            } else {
+                self.read_file_by_id(id)?
+            };
+            if let Some(meta) = &existing {
+                if opts.overwrite == OverwritePolicy::KeepNewer && same_content(&path, meta, &data)? {
+                    report.unchanged += 1;
+                    extracted.entry(key.clone()).or_insert(path);
+                    continue;
+                }
+                std::fs::remove_file(&path)?;
+            }
+            File::create(&path)?.write_all(&data)?;
+            #[cfg(feature = "windows-metadata")]
+            if let ArchiveMode::Read(r) = &self.mode {
+                if let Some(rec) = r.index.records.iter().find(|rec| rec.id == id) {
+                    crate::winmeta::restore(&path, &rec.metadata)?;
+                }
+            }
+            extracted.entry(key.clone()).or_insert(path);
+        }
+        progress(&ExtractProgress { files_done: entries.len(), elapsed: started.elapsed(), ..status });
+
+        if opts.paranoid {
+            if report.skipped > 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                    "{} existing file(s) were kept rather than restored; cannot attest the extraction",
+                    report.skipped,
+                )).into());
+            }
+            if report.stripped > 0 {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                    "{} entry name(s) were stripped away rather than restored; cannot attest the extraction",
+                    report.stripped,
+                )).into());
+            }
+            let mut root = blake3::Hasher::new();
+            for (_, path, key, _, _) in &entries {
+                hash_pieces(path, &ref_lens[key], |hash| { root.update(hash.as_bytes()); })?;
+            }
+            let expected = self.reader_mut()?.index.root_hash;
+            if *root.finalize().as_bytes() != expected {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    "extracted files do not reproduce the archive root hash").into());
+            }
+            report.verified_root = Some(expected);
+        }
+        Ok(report)
+    }
+
+    /// Copy the archive to `dest`, re-encoding only blocks whose codec
+    /// `codec_map` changes; see [`optimize::transcode`](crate::optimize::transcode).
+    pub fn transcode<P: AsRef<Path>>(
+        &mut self,
+        dest:      P,
+        codec_map: impl Fn(CodecId) -> CodecId,
+        level:     i32,
+    ) -> Result<OptimizeReport> {
+        crate::optimize::transcode(self, dest, codec_map, level)
+    }
+
+    /// Per-ref lengths of record `id` if the file at `path` already holds
+    /// its content, checked against the INDEX content hashes.
+    fn already_extracted(&mut self, id: u32, path: &Path, meta: &std::fs::Metadata) -> Result<Option<Vec<u64>>> {
+        let reader = self.reader_mut()?;
+        // Records are sorted by ID (see `FileIndex::records`).
+        let Ok(pos) = reader.index.records.binary_search_by_key(&id, |r| r.id) else { return Ok(None) };
+        let (size, refs) = (reader.index.records[pos].original_size, reader.index.records[pos].block_refs.clone());
+        if !meta.is_file() || meta.len() != size {
+            return Ok(None);
+        }
+        let lens = match refs.len() {
+            1 => vec![size],
+            _ => refs.iter().map(|br| reader.ref_len(br)).collect::<Result<Vec<_>>>()?,
+        };
+        if lens.iter().sum::<u64>() != size {
+            return Ok(None);
+        }
+        let mut refs = refs.iter();
+        let mut same = true;
+        hash_pieces(path, &lens, |hash| same &= refs.next().is_some_and(|br| hash == br.content_hash))?;
+        Ok(same.then_some(lens))
+    }
+
+    /// Write every file to `out` as a tar stream (GNU format, long names
+    /// supported) instead of to disk, for piping into `docker import`, a
+    /// remote `tar x`, and the like.  Returns the number of members.
+    ///
+    /// Contents are read as [`extract_with`](Self::extract_with) reads them
+    /// by default: every block ref is checked against its content hash and
+    /// shared blocks are decoded once.  Members are regular files with mode
+    /// `0644`, owned by uid/gid 0, timestamped with the archive's
+    /// modification time.  Names that are absolute or contain `..` are
+    /// refused.
+    pub fn extract_to_tar<W: Write>(&mut self, out: W) -> Result<usize> {
+        let (entries, mtime): (Vec<(u32, String)>, _) = match &self.mode {
+            ArchiveMode::Read(r)     => (
+                r.index.records.iter().map(|rec| (rec.id, rec.name.clone())).collect(),
+                r.superblock.modified_at.unwrap_or(0).max(0) as u64,
+            ),
+            ArchiveMode::Write(_, _) => return Err(write_only()),
+        };
+
+        let reader = self.reader_mut()?;
+        let cache  = reader.swap_block_cache(None);
+        reader.enable_block_cache(DEFAULT_BLOCK_CACHE_BYTES);
+        let mut tar = tar::Builder::new(out);
+        let result = entries.iter().try_for_each(|(id, name)| -> Result<()> {
+            let (data, _) = reader.unpack_file_verified(*id)?;
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            tar.append_data(&mut header, name, data.as_slice())?;
+            Ok(())
+        });
+        reader.disable_block_cache();
+        reader.swap_block_cache(cache);
+        result?;
+        tar.into_inner()?.flush()?;
+        Ok(entries.len())
+    }
+
+    // ── Integrity ────────────────────────────────────────────────────────────
+
+    /// Verify a random `sample_fraction` (clamped to 0.0–1.0, at least one
+    /// block) of the archive's blocks — header CRC, decryption, BLAKE3 — so
+    /// a store can scrub periodically without reading everything.  If any
+    /// sampled block fails, every remaining block is verified as well and
+    /// [`HealthReport::escalated`] is set.  Blocks that fail are reported,
+    /// not returned as errors.
+    pub fn health_check(&mut self, sample_fraction: f64) -> Result<HealthReport> {
+        use aes_gcm::aead::rand_core::RngCore;
+
+        let reader = self.reader_mut()?;
+        let mut offsets = reader.local_block_offsets();
+        let total  = offsets.len();
+        let sample = ((total as f64 * sample_fraction.clamp(0.0, 1.0)).ceil() as usize).clamp(1, total.max(1));
+        // Partial Fisher–Yates: the first `sample` offsets become the sample.
+        let mut rng = aes_gcm::aead::OsRng;
+        for i in 0..sample.min(total) {
+            let j = i + (rng.next_u64() % (total - i) as u64) as usize;
+            offsets.swap(i, j);
         }
+
+        let mut report = HealthReport { blocks_total: total, ..HealthReport::default() };
+        let mut verify = |report: &mut HealthReport, offset: u64| {
+            report.blocks_checked += 1;
+            if let Err(e) = reader.verify_block_at(offset) {
+                report.failures.push((offset, e.to_string()));
+            }
+        };
+        let (sampled, rest) = offsets.split_at(sample.min(total));
+        for &offset in sampled {
+            verify(&mut report, offset);
+        }
+        if !report.failures.is_empty() {
+            report.escalated = true;
+            for &offset in rest {
+                verify(&mut report, offset);
+            }
+        }
+        report.failures.sort_unstable_by_key(|(offset, _)| *offset);
+        Ok(report)
+    }
+
+    /// Verify only the blocks `state` records as due and update it; see
+    /// [`scrub::scrub`](crate::scrub::scrub).
+    pub fn scrub(
+        &mut self,
+        state: &mut crate::scrub::ScrubState,
+        opts:  &crate::scrub::ScrubOptions,
+    ) -> Result<crate::scrub::ScrubReport> {
+        crate::scrub::scrub(self, state, opts)
+    }
+
+    /// Walk every block header and cross-reference it with the INDEX: how
+    /// many bytes are orphaned, how much deduplication saves, and how
+    /// fragmented multi-block files are.  Only headers are read.  Refs into
+    /// base archives are left out of every figure.
+    pub fn space_report(&mut self) -> Result<SpaceReport> {
+        use crate::block::BlockType;
+
+        let reader = self.reader_mut()?;
+        let index  = Arc::clone(&reader.index);
+        let own    = *reader.superblock.archive_uuid.as_bytes();
+        let mut live: HashSet<u64> = reader.local_block_offsets().into_iter().collect();
+        live.insert(reader.superblock.index_offset);
+        live.extend(reader.superblock.codec_continuation.map(|c| c.offset));
+
+        let mut report  = SpaceReport::default();
+        let mut headers = HashMap::new();
+        let mut next    = crate::superblock::SUPERBLOCK_SIZE as u64;
+        for block in reader.iter_block_headers()? {
+            let Ok((offset, header)) = block else {
+                report.unreadable_at = Some(next);
+                break;
+            };
+            let stored = BLOCK_HEADER_SIZE as u64 + header.comp_size as u64;
+            next = offset + stored;
+            report.blocks_total += 1;
+            report.block_bytes  += stored;
+            if header.block_type == BlockType::Solid {
+                report.solid_blocks += 1;
+            }
+            if !live.contains(&offset) {
+                report.orphaned_blocks += 1;
+                report.orphaned_bytes  += stored;
+            }
+            headers.insert(offset, header);
+        }
+
+        let mut read = HashSet::new();
+        for rec in &index.records {
+            let refs: Vec<_> = rec.block_refs.iter()
+                .filter(|br| br.archive_id.is_none_or(|id| id == own))
+                .filter_map(|br| headers.get(&br.archive_offset).map(|h| (br, h)))
+                .collect();
+            for &(br, header) in &refs {
+                report.logical_bytes += match br.is_solid_slice() {
+                    true  => br.intra_length,
+                    false => header.orig_size as u64,
+                };
+                if read.insert(br.archive_offset) {
+                    report.unique_bytes += header.orig_size as u64;
+                }
+            }
+            if refs.iter().map(|(br, _)| br.archive_offset).collect::<HashSet<_>>().len() > 1 {
+                report.multi_block_files += 1;
+                let scattered = refs.windows(2).any(|pair| {
+                    let ((prev, header), (next, _)) = (pair[0], pair[1]);
+                    let end = prev.archive_offset + BLOCK_HEADER_SIZE as u64 + header.comp_size as u64;
+                    next.archive_offset != prev.archive_offset && next.archive_offset != end
+                });
+                report.fragmented_files += scattered as usize;
+            }
+        }
+        Ok(report)
+    }
+
+    /// The blocks `name` is stored in, in file order, read from their
+    /// headers without decoding payloads — layout for diagnostics and
+    /// mounts.  A header that cannot be read is reported in
+    /// [`BlockInfo::error`], not as an error; [`Archive::verify_block`]
+    /// checks a payload as well.
+    pub fn file_blocks(&mut self, name: &str) -> Result<Vec<BlockInfo>> {
+        let rec = self.records().iter().find(|r| r.name == name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("File not found: {name}")))?;
+        let refs   = rec.block_refs.clone();
+        let reader = self.reader_mut()?;
+        let mut blocks      = Vec::with_capacity(refs.len());
+        let mut file_offset = 0;
+        for br in refs {
+            let mut info = BlockInfo {
+                offset:       br.archive_offset,
+                file_offset,
+                length:       br.intra_length,
+                comp_size:    0,
+                orig_size:    0,
+                hash:         br.content_hash,
+                codec:        None,
+                encrypted:    false,
+                intra_offset: br.is_solid_slice().then_some(br.intra_offset),
+                archive_id:   br.archive_id,
+                error:        None,
+            };
+            match reader.block_header(&br) {
+                Ok(header) => {
+                    info.comp_size = header.comp_size as u64;
+                    info.orig_size = header.orig_size as u64;
+                    info.codec     = Some(header.codec_uuid);
+                    info.encrypted = header.is_encrypted();
+                    if !br.is_solid_slice() { info.length = info.orig_size; }
+                }
+                Err(e) => info.error = Some(e.to_string()),
+            }
+            file_offset += info.length;
+            blocks.push(info);
+        }
+        Ok(blocks)
+    }
+
+    /// Read the block at `offset` and run every check a normal read does —
+    /// header CRC, decryption, decompression, BLAKE3 — without keeping the
+    /// plaintext.
+    pub fn verify_block(&mut self, offset: u64) -> Result<()> {
+        self.reader_mut()?.verify_block_at(offset)
+    }
+
+    /// Every block header in the archive; see
+    /// [`SixCyReader::iter_block_headers`].
+    pub fn iter_block_headers(&mut self) -> Result<BlockHeaders<'_, Section<SharedFile>>> {
+        self.reader_mut()?.iter_block_headers()
+    }
+
+    /// Files whose blocks survive in the archive but that no INDEX record
+    /// lists any more (see [`SixCyReader::find_orphans`]).
+    pub fn orphans(&mut self) -> Result<Vec<FileInfo>> {
+        Ok(self.reader_mut()?.find_orphans()?.iter().map(FileInfo::from).collect())
+    }
+
+    /// Add every orphan to this archive's in-memory file list as an
+    /// `orphan_<id>` entry, so it can be read, extracted, or carried into a
+    /// repaired copy with [`crate::optimize::optimize`].  The file on disk
+    /// is not modified.
+    pub fn resurrect_orphans(&mut self) -> Result<Vec<FileInfo>> {
+        let reader  = self.reader_mut()?;
+        let orphans = reader.find_orphans()?;
+        let infos   = orphans.iter().map(FileInfo::from).collect();
+        Arc::make_mut(&mut reader.index).records.extend(orphans);
+        Ok(infos)
+    }
+
+    // ── Internal access ──────────────────────────────────────────────────────
+
+    /// Borrow the underlying reader; fails for archives in write mode.
+    pub(crate) fn reader_mut(&mut self) -> Result<&mut SixCyReader<Section<SharedFile>>> {
+        match &mut self.mode {
+            ArchiveMode::Read(r)     => Ok(r),
+            ArchiveMode::Write(_, _) => Err(write_only()),
+        }
+    }
+
+    // ── Performance ──────────────────────────────────────────────────────────
+
+    /// Start collecting stage timings for subsequent reads (writers use
+    /// [`PackOptions::perf_stats`]).
+    pub fn enable_perf_stats(&mut self) {
+        match &mut self.mode {
+            ArchiveMode::Read(r)     => r.enable_perf_stats(),
+            ArchiveMode::Write(w, _) => w.enable_perf_stats(),
+        }
+    }
+
+    /// See [`SixCyReader::danger_skip_content_hash`].  Fails on an archive
+    /// opened for writing.
+    pub fn danger_skip_content_hash(&mut self, skip: bool) -> Result<()> {
+        self.reader_mut()?.danger_skip_content_hash(skip);
         Ok(())
     }
 
+    /// Report what the archive does from now on to `observer` (or stop,
+    /// with `None`): files read or extracted, or everything written.  See
+    /// [`crate::events`].
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn Observer>>) {
+        match &mut self.mode {
+            ArchiveMode::Read(r)     => r.set_observer(observer),
+            ArchiveMode::Write(w, _) => w.observer = observer,
+        }
+    }
+
+    /// Stats collected so far, or `None` if collection is not enabled.
+    pub fn perf_stats(&self) -> Option<PerfStats> {
+        match &self.mode {
+            ArchiveMode::Read(r)     => r.perf_stats(),
+            ArchiveMode::Write(w, _) => w.perf_stats(),
+        }
+    }
+
     // ── Metadata ─────────────────────────────────────────────────────────────
 
     pub fn path(&self) -> &Path { &self.path }
 
+    /// True if the file list was rebuilt by [`Archive::open_resilient`]'s
+    /// block scan instead of read from the INDEX.
+    pub fn is_reconstructed(&self) -> bool {
+        matches!(&self.mode, ArchiveMode::Read(r) if r.is_reconstructed())
+    }
+
+    /// True if the superblock's `index_offset` was wrong and the INDEX was
+    /// found by scanning back from the end of the file
+    /// ([`io_stream::locate_index_from_tail`](crate::io_stream::locate_index_from_tail)).
+    pub fn is_index_relocated(&self) -> bool {
+        matches!(&self.mode, ArchiveMode::Read(r) if r.is_index_relocated())
+    }
+
     pub fn uuid(&self) -> uuid::Uuid {
         match &self.mode {
             ArchiveMode::Read(r)     => r.superblock.archive_uuid,
@@ -238,13 +1932,229 @@ impl Archive {
         }
     }
 
-    pub fn root_hash_hex(&self) -> String {
+    /// UUID bytes of the other archives this one's refs point into —
+    /// the bases of an incremental snapshot — without duplicates.
+    pub(crate) fn base_archive_ids(&self) -> BTreeSet<[u8; 16]> {
+        let own = *self.uuid().as_bytes();
+        self.index().block_refs().filter_map(|br| br.archive_id).filter(|id| *id != own).collect()
+    }
+
+    /// UUIDs of the codecs the archive's blocks use (the superblock's
+    /// required-codec list); name them with
+    /// [`codec_display_name`](crate::codec::codec_display_name).
+    pub fn required_codecs(&self) -> &[[u8; 16]] {
+        match &self.mode {
+            ArchiveMode::Read(r)     => &r.superblock.required_codec_uuids,
+            ArchiveMode::Write(w, _) => &w.superblock.required_codec_uuids,
+        }
+    }
+
+    /// Inclusion proof binding `name`'s content hashes to the archive's
+    /// `root_hash` (see [`crate::attest`]).
+    pub fn attest(&self, name: &str) -> Result<InclusionProof> {
+        let id = self.stat(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound,
+                format!("File not found: {name}")))?
+            .id;
+        match &self.mode {
+            ArchiveMode::Read(r)     => InclusionProof::new(&r.index, id),
+            ArchiveMode::Write(_, _) => Err(write_only()),
+        }
+    }
+
+    /// The INDEX layout version (see [`crate::index`]).
+    pub fn index_version(&self) -> u32 {
+        self.index().index_version
+    }
+
+    /// How names were stored (see [`crate::names`]); `None` for archives
+    /// written before the policy was recorded.
+    pub fn name_policy(&self) -> Option<NamePolicy> {
+        self.index().name_policy
+    }
+
+    /// INDEX keys this build did not recognise, by path, with counts — a
+    /// sign the archive came from a newer writer.
+    pub fn unknown_index_fields(&self) -> &BTreeMap<String, u64> {
+        &self.index().unknown_fields
+    }
+
+    fn index(&self) -> &FileIndex {
         match &self.mode {
-            ArchiveMode::Read(r)     => hex::encode(r.index.root_hash),
-            ArchiveMode::Write(w, _) => hex::encode(w.index.root_hash),
+            ArchiveMode::Read(r)     => &r.index,
+            ArchiveMode::Write(w, _) => &w.index,
+        }
+    }
+
+    pub fn root_hash_hex(&self) -> String {
+        hex::encode(self.index().root_hash)
+    }
+}
+
+/// Collect `(name, path)` for the regular files under `dir`, depth-first
+/// in name order; `parent` is `dir`'s entry name (empty for the root).
+/// `own` (the output archive, if it already exists) is left out.
+pub(crate) fn walk_dir(
+    dir:    &Path,
+    parent: &EntryName,
+    own:    Option<&Path>,
+    files:  &mut Vec<(EntryName, PathBuf)>,
+    report: &mut PackReport,
+) -> io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = parent.join(&entry.file_name());
+        let kind = entry.file_type()?;
+        if kind.is_dir() {
+            report.dirs += 1;
+            walk_dir(&path, &name, own, files, report)?;
+        } else if kind.is_file() && !own.is_some_and(|own| path.canonicalize().is_ok_and(|p| p == own)) {
+            files.push((name, path));
+        } else {
+            report.skipped += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Where an entry with a raw name ([`FileIndexRecord::raw_name`]) lands:
+/// its bytes, stripped like `name` (its [`ExtractOptions::dest_name`]).
+/// `None`, so `name` is used, off Unix and when a transform or Windows
+/// renaming applies, since those work on the UTF-8 name.
+fn raw_dest_name(raw: &[u8], name: &str, opts: &ExtractOptions) -> Option<PathBuf> {
+    if opts.windows_names != WindowsNames::Keep
+        || opts.transforms.iter().any(|(old, _)| name.starts_with(old.as_str()))
+    {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let parts: Vec<&[u8]> = raw.split(|&b| b == b'/')
+            .filter(|c| !c.is_empty() && *c != b".")
+            .skip(opts.strip_components)
+            .collect();
+        (!parts.is_empty()).then(|| parts.into_iter().map(std::ffi::OsStr::from_bytes).collect())
+    }
+    #[cfg(not(unix))]
+    { let _ = raw; None }
+}
+
+/// Fail unless `rel` stays inside the directory it is joined to: no `..`,
+/// root, or drive-prefix components.  `entry` names the archive entry.
+fn check_confined(entry: &str, rel: &Path) -> io::Result<()> {
+    use std::path::Component;
+    if rel.components().any(|c| matches!(c, Component::ParentDir | Component::RootDir | Component::Prefix(_))) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "entry {entry:?} would be extracted outside the destination as {}", rel.display())));
+    }
+    Ok(())
+}
+
+/// Identity of a record's content: equal keys mean equal bytes, because
+/// block refs carry the BLAKE3 hash of each block's plaintext.  Empty
+/// entries are keyed by ID so they are never linked together.
+fn content_key(rec: &FileIndexRecord) -> Vec<u8> {
+    if rec.block_refs.is_empty() { return rec.id.to_le_bytes().to_vec(); }
+    let mut key = rec.original_size.to_le_bytes().to_vec();
+    for br in &rec.block_refs {
+        key.extend_from_slice(&br.content_hash);
+        key.extend_from_slice(&br.intra_offset.to_le_bytes());
+        key.extend_from_slice(&br.intra_length.to_le_bytes());
+    }
+    key
+}
+
+/// Create `path` as a link or clone of `first`.  Returns false when the
+/// filesystem refused, so the caller writes the bytes instead.
+fn link_duplicate(first: &Path, path: &Path, strategy: DedupStrategy) -> bool {
+    match std::fs::remove_file(path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(_) => return false,
+    }
+    match strategy {
+        DedupStrategy::Copy     => false,
+        DedupStrategy::Hardlink => std::fs::hard_link(first, path).is_ok(),
+        DedupStrategy::Reflink  => reflink_or_copy(first, path).is_ok(),
+    }
+}
+
+/// Create `path` as a copy-on-write clone of `first`, or a plain copy where
+/// the filesystem (or platform) cannot clone.
+fn reflink_or_copy(first: &Path, path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let src = File::open(first)?;
+        let dst = OpenOptions::new().write(true).create_new(true).open(path)?;
+        // SAFETY: FICLONE reads only the source descriptor passed as its
+        // argument; both descriptors are open for the call.
+        if unsafe { libc::ioctl(dst.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) } == 0 {
+            return Ok(());
+        }
+    }
+    std::fs::copy(first, path).map(|_| ())
+}
+
+/// Ask the OS to forget cached pages of `file`, so reads come from the
+/// device rather than memory.  Best effort; a no-op off Linux.
+fn drop_page_cache(file: &File) {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        // SAFETY: a plain advisory call on an open descriptor.
+        unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED); }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = file;
+}
+
+/// True if `meta` was modified after `archive_mtime` (Unix seconds).
+fn modified_after(meta: &std::fs::Metadata, archive_mtime: Option<i64>) -> bool {
+    let Ok(mtime) = meta.modified() else { return false };
+    let secs = match mtime.duration_since(std::time::UNIX_EPOCH) {
+        Ok(d)  => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    secs > archive_mtime.unwrap_or(i64::MIN)
+}
+
+/// True if the file at `path` holds exactly `data`, compared by BLAKE3.
+fn same_content(path: &Path, meta: &std::fs::Metadata, data: &[u8]) -> Result<bool> {
+    if !meta.is_file() || meta.len() != data.len() as u64 {
+        return Ok(false);
+    }
+    let on_disk = blake3::Hasher::new().update_reader(File::open(path)?)?.finalize();
+    Ok(on_disk == blake3::hash(data))
+}
+
+/// Pass the BLAKE3 hash of each `lens`-sized piece of the file at `path`
+/// to `each`, in order — the hashes the INDEX holds for the block refs
+/// the pieces came from.  Fails unless the file is exactly that long.
+fn hash_pieces(path: &Path, lens: &[u64], mut each: impl FnMut(blake3::Hash)) -> Result<()> {
+    use std::io::Read;
+    let mut file = io::BufReader::new(File::open(path)?);
+    let mut buf  = crate::pool::shared().take(0);
+    for &len in lens {
+        buf.clear();
+        (&mut file).take(len).read_to_end(&mut buf)?;
+        if buf.len() as u64 != len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("{} is shorter than its archived content", path.display())).into());
         }
+        each(blake3::hash(&buf));
+    }
+    let trailing = file.read(&mut [0u8; 1])?;
+    crate::pool::shared().give(buf);
+    if trailing != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("{} is longer than its archived content", path.display())).into());
     }
+    Ok(())
 }
 
-fn read_only()  -> io::Error { io::Error::new(io::ErrorKind::PermissionDenied, "archive is read-only") }
-fn write_only() -> io::Error { io::Error::new(io::ErrorKind::PermissionDenied, "archive is write-only") }
+fn read_only()  -> SixcyError { io::Error::new(io::ErrorKind::PermissionDenied, "archive is read-only").into() }
+fn write_only() -> SixcyError { io::Error::new(io::ErrorKind::PermissionDenied, "archive is write-only").into() }
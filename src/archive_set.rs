@@ -0,0 +1,113 @@
+//! Several archives read as one namespace.
+//!
+//! [`ArchiveSet`] merges the INDEX of each archive it is given, in order:
+//! a name present in a later archive shadows the same name in earlier
+//! ones, so a base archive followed by its incremental snapshots — or by
+//! archives of later changes — reads as the newest state of the tree.
+//! Reads go to the archive that holds the visible entry.  A name cannot be
+//! removed by a later archive, only replaced.
+//!
+//! Members that are incremental snapshots get every other member they
+//! borrow blocks from attached as a base, so a whole chain opens with one
+//! call.  Members that need a key or a section offset are opened by the
+//! caller and handed to [`ArchiveSet::from_archives`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
+
+use crate::archive::{Archive, FileInfo};
+use crate::error::Result;
+
+/// See the module docs.
+pub struct ArchiveSet {
+    archives: Vec<Archive>,
+    /// Each visible name → (member position, its entry there).
+    names:    BTreeMap<String, (usize, FileInfo)>,
+}
+
+impl ArchiveSet {
+    /// Open unencrypted archives `paths`, earliest first.
+    pub fn open<P: AsRef<Path>>(paths: &[P]) -> Result<Self> {
+        Self::from_archives(paths.iter().map(Archive::open).collect::<Result<_>>()?)
+    }
+
+    /// [`open`](Self::open) for archives encrypted under one `password`.
+    pub fn open_encrypted<P: AsRef<Path>>(paths: &[P], password: &str) -> Result<Self> {
+        Self::from_archives(paths.iter().map(|p| Archive::open_encrypted(p, password)).collect::<Result<_>>()?)
+    }
+
+    /// Merge archives already opened for reading, earliest first.
+    pub fn from_archives(mut archives: Vec<Archive>) -> Result<Self> {
+        let by_uuid: HashMap<[u8; 16], usize> = archives.iter().enumerate()
+            .map(|(i, ar)| (*ar.uuid().as_bytes(), i))
+            .collect();
+        // Clone every base before attaching any, so no member is attached
+        // with bases of its own that the snapshot does not name.
+        let mut bases = Vec::new();
+        for (i, ar) in archives.iter().enumerate() {
+            for id in ar.base_archive_ids() {
+                if let Some(&j) = by_uuid.get(&id).filter(|&&j| j != i) {
+                    bases.push((i, archives[j].try_clone()?));
+                }
+            }
+        }
+        for (i, base) in bases {
+            archives[i].attach_base(base)?;
+        }
+
+        let mut names = BTreeMap::new();
+        for (i, ar) in archives.iter_mut().enumerate() {
+            ar.reader_mut()?;
+            for info in ar.entries() {
+                names.insert(info.name.clone(), (i, info));
+            }
+        }
+        Ok(Self { archives, names })
+    }
+
+    /// The members, in the order given.
+    pub fn archives(&self) -> &[Archive] { &self.archives }
+
+    /// Number of visible names.
+    pub fn len(&self) -> usize { self.names.len() }
+
+    pub fn is_empty(&self) -> bool { self.names.is_empty() }
+
+    pub fn list(&self) -> Vec<FileInfo> {
+        self.entries().cloned().collect()
+    }
+
+    /// Every visible entry, sorted by name.  [`FileInfo::id`] is the
+    /// record ID in the member that holds it (see [`source`](Self::source)).
+    pub fn entries(&self) -> impl Iterator<Item = &FileInfo> + '_ {
+        self.names.values().map(|(_, info)| info)
+    }
+
+    pub fn stat(&self, name: &str) -> Option<&FileInfo> {
+        self.names.get(name).map(|(_, info)| info)
+    }
+
+    /// Position in [`archives`](Self::archives) of the member whose
+    /// `name` is visible.
+    pub fn source(&self, name: &str) -> Option<usize> {
+        self.names.get(name).map(|&(i, _)| i)
+    }
+
+    pub fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        let (i, id) = self.locate(name)?;
+        self.archives[i].read_file_by_id(id)
+    }
+
+    /// As [`Archive::read_at`], from the member holding `name`.
+    pub fn read_at(&mut self, name: &str, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let (i, id) = self.locate(name)?;
+        self.archives[i].reader_mut()?.read_at(id, offset, buf)
+    }
+
+    fn locate(&self, name: &str) -> Result<(usize, u32)> {
+        self.names.get(name)
+            .map(|(i, info)| (*i, info.id))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("File not found: {name}")).into())
+    }
+}
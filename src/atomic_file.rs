@@ -0,0 +1,131 @@
+//! Output files that appear at their final path only once complete.
+//!
+//! [`AtomicFile::create`] opens an unnamed file in the target's directory
+//! with `O_TMPFILE` (Linux 3.11+, on filesystems that support it), and
+//! [`AtomicFile::persist`] links it into place.  Elsewhere — or when the
+//! filesystem or a missing `/proc` rules `O_TMPFILE` out — a hidden
+//! temporary named file in the same directory is renamed over the target
+//! instead, and removed if the `AtomicFile` is dropped unpersisted.
+//!
+//! Either way the target path never holds a partial file, and an existing
+//! file there is replaced atomically.  Only the named fallback can leave a
+//! stray temporary behind, and only if the process dies outright.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A file being written for `target`; see the module docs.
+#[derive(Debug)]
+pub struct AtomicFile {
+    file:   File,
+    target: PathBuf,
+    /// The fallback's temporary name; `None` for an `O_TMPFILE` file.
+    temp:   Option<PathBuf>,
+}
+
+impl AtomicFile {
+    pub fn create(target: &Path) -> io::Result<Self> {
+        let dir = match target.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _                                    => Path::new("."),
+        };
+
+        #[cfg(target_os = "linux")]
+        if let Some(file) = unnamed::open(dir) {
+            return Ok(Self { file, target: target.to_owned(), temp: None });
+        }
+
+        let temp = temp_name(dir, target);
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&temp)?;
+        Ok(Self { file, target: target.to_owned(), temp: Some(temp) })
+    }
+
+    /// The open file; write through it or a [`File::try_clone`] of it.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+
+    /// True if the file has no name yet (`O_TMPFILE`).
+    pub fn is_unnamed(&self) -> bool {
+        self.temp.is_none()
+    }
+
+    /// Sync the contents and move them to the target path.
+    pub fn persist(mut self) -> io::Result<()> {
+        self.file.sync_all()?;
+        let temp = match self.temp.take() {
+            Some(temp) => temp,
+            #[cfg(target_os = "linux")]
+            None => {
+                let temp = temp_name(self.target.parent().unwrap_or(Path::new(".")), &self.target);
+                unnamed::link(&self.file, &temp)?;
+                temp
+            }
+            #[cfg(not(target_os = "linux"))]
+            None => unreachable!("unnamed files are only created on Linux"),
+        };
+        std::fs::rename(&temp, &self.target).inspect_err(|_| {
+            let _ = std::fs::remove_file(&temp);
+        })
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if let Some(temp) = &self.temp {
+            let _ = std::fs::remove_file(temp);
+        }
+    }
+}
+
+/// A fresh hidden name beside `target` in `dir`.
+fn temp_name(dir: &Path, target: &Path) -> PathBuf {
+    let stem = target.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    dir.join(format!(".{stem}.{}.tmp", uuid::Uuid::new_v4().simple()))
+}
+
+#[cfg(target_os = "linux")]
+mod unnamed {
+    use std::ffi::CString;
+    use std::fs::{File, OpenOptions};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    /// An `O_TMPFILE` file in `dir`, or `None` if this system cannot
+    /// create or later link one.
+    pub fn open(dir: &Path) -> Option<File> {
+        // Linking goes through the /proc magic link.
+        if !Path::new("/proc/self/fd").is_dir() {
+            return None;
+        }
+        OpenOptions::new()
+            .read(true)
+            .write(true)
+            .mode(0o666)
+            .custom_flags(libc::O_TMPFILE)
+            .open(dir)
+            .ok()
+    }
+
+    /// Give the unnamed `file` the name `to`.
+    pub fn link(file: &File, to: &Path) -> io::Result<()> {
+        let from = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd()))?;
+        let to   = CString::new(to.as_os_str().as_bytes())?;
+        // SAFETY: both paths are NUL-terminated and outlive the call.
+        let rc = unsafe {
+            libc::linkat(libc::AT_FDCWD, from.as_ptr(), libc::AT_FDCWD, to.as_ptr(), libc::AT_SYMLINK_FOLLOW)
+        };
+        if rc != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
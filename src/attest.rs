@@ -0,0 +1,225 @@
+//! Inclusion proofs — show that one file belongs to an archive whose
+//! `root_hash` has been published, without shipping the rest of the archive.
+//!
+//! The INDEX `root_hash` is BLAKE3 over the concatenated content hashes of
+//! every block ref, in record order (the "leaves", 32 bytes each).  BLAKE3
+//! is itself a Merkle tree over 1 KiB chunks, so a proof is the chunks that
+//! hold the file's leaves plus the chaining values of every subtree beside
+//! them — O(log n) hashes, checked against the unchanged `root_hash`:
+//!
+//! ```text
+//! leaves   L0 L1 … | La … Lb-1 | … Ln-1        (32 leaves per chunk)
+//! proof    before ++ content_hashes ++ after     the covering chunk span
+//!          siblings                              subtree CVs, depth-first
+//! ```
+//!
+//! Proofs serialize to JSON with hex-encoded hashes ([`InclusionProof::to_json`]).
+//! Verification needs only the proof and the expected root.
+
+use std::io;
+use std::ops::Range;
+
+use blake3::hazmat::{left_subtree_len, merge_subtrees_non_root, merge_subtrees_root,
+                     ChainingValue, HasherExt, Mode};
+use blake3::CHUNK_LEN;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::index::FileIndex;
+
+const LEAF_LEN: u64 = 32;
+
+/// A file's content hashes and the BLAKE3 tree path binding them to a root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub name:           String,
+    pub original_size:  u64,
+    #[serde(with = "hex32")]
+    pub root_hash:      [u8; 32],
+    /// Content hashes of every block ref in the archive.
+    pub leaf_count:     u64,
+    /// Position of the file's first content hash among all leaves.
+    pub first_leaf:     u64,
+    /// The file's own content hashes, in block-ref order.
+    #[serde(with = "hex32_list")]
+    pub content_hashes: Vec<[u8; 32]>,
+    /// Leaves sharing the first chunk, before `first_leaf`.
+    #[serde(with = "hex32_list")]
+    pub before:         Vec<[u8; 32]>,
+    /// Leaves sharing the last chunk, after the file's.
+    #[serde(with = "hex32_list")]
+    pub after:          Vec<[u8; 32]>,
+    /// Chaining values of the subtrees outside that span, depth-first.
+    #[serde(with = "hex32_list")]
+    pub siblings:       Vec<[u8; 32]>,
+}
+
+impl InclusionProof {
+    /// Build a proof for record `id` of `index`.
+    ///
+    /// Fails with `NotFound` for an unknown id and `InvalidInput` for a file
+    /// with no content blocks (an empty file has nothing to prove).
+    pub fn new(index: &FileIndex, id: u32) -> Result<Self> {
+        let pos = index.records.iter().position(|r| r.id == id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("File id {id} not found")))?;
+        let rec = &index.records[pos];
+        if rec.block_refs.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("{} has no content blocks to attest", rec.name)).into());
+        }
+
+        let leaves: Vec<u8> = index.records.iter()
+            .flat_map(|r| &r.block_refs)
+            .flat_map(|br| br.content_hash)
+            .collect();
+        let leaf_count = leaves.len() as u64 / LEAF_LEN;
+        let first_leaf = index.records[..pos].iter().map(|r| r.block_refs.len() as u64).sum::<u64>();
+        let file       = first_leaf..first_leaf + rec.block_refs.len() as u64;
+        let span       = chunk_span(&file, leaf_count);
+
+        let mut siblings = Vec::new();
+        let mut sibling = |at: Range<u64>| {
+            let cv = blake3::Hasher::new()
+                .set_input_offset(at.start)
+                .update(&leaves[at.start as usize..at.end as usize])
+                .finalize_non_root();
+            siblings.push(cv);
+            Some(cv)
+        };
+        let span_bytes = &leaves[span.start as usize..span.end as usize];
+        let root = tree_root(leaves.len() as u64, &span, span_bytes, &mut sibling);
+        debug_assert_eq!(root, Some(blake3::hash(&leaves).into()));
+
+        let leaf = |i: u64| -> [u8; 32] {
+            leaves[(i * LEAF_LEN) as usize..][..LEAF_LEN as usize].try_into().unwrap()
+        };
+        Ok(Self {
+            name:           rec.name.clone(),
+            original_size:  rec.original_size,
+            root_hash:      index.root_hash,
+            leaf_count,
+            first_leaf,
+            content_hashes: rec.block_refs.iter().map(|br| br.content_hash).collect(),
+            before:         (span.start / LEAF_LEN..file.start).map(leaf).collect(),
+            after:          (file.end..span.end / LEAF_LEN).map(leaf).collect(),
+            siblings,
+        })
+    }
+
+    /// True if the proof is well-formed and reproduces `root_hash`, and
+    /// `root_hash` equals `expected_root`.
+    pub fn verify(&self, expected_root: &[u8; 32]) -> bool {
+        if &self.root_hash != expected_root || self.content_hashes.is_empty() {
+            return false;
+        }
+        let file = self.first_leaf..self.first_leaf + self.content_hashes.len() as u64;
+        if file.end > self.leaf_count {
+            return false;
+        }
+        let span = chunk_span(&file, self.leaf_count);
+        if self.before.len() as u64 != file.start - span.start / LEAF_LEN
+            || self.after.len() as u64 != span.end / LEAF_LEN - file.end
+        {
+            return false;
+        }
+
+        let span_bytes: Vec<u8> = self.before.iter()
+            .chain(&self.content_hashes)
+            .chain(&self.after)
+            .flatten()
+            .copied()
+            .collect();
+        let mut siblings = self.siblings.iter();
+        let root = tree_root(self.leaf_count * LEAF_LEN, &span, &span_bytes, &mut |_| siblings.next().copied());
+        root.as_ref() == Some(expected_root) && siblings.next().is_none()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(s: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(s)
+    }
+}
+
+/// Byte range of the whole chunks covering leaf range `file`.
+fn chunk_span(file: &Range<u64>, leaf_count: u64) -> Range<u64> {
+    let chunk = CHUNK_LEN as u64;
+    let start = file.start * LEAF_LEN / chunk * chunk;
+    let end   = (file.end * LEAF_LEN).div_ceil(chunk) * chunk;
+    start..end.min(leaf_count * LEAF_LEN)
+}
+
+/// Root hash of a `len`-byte BLAKE3 input of which only `span` (chunk
+/// aligned, bytes in `span_bytes`) is known.  Every maximal subtree outside
+/// the span is resolved through `sibling`, depth-first left to right.
+fn tree_root(
+    len:        u64,
+    span:       &Range<u64>,
+    span_bytes: &[u8],
+    sibling:    &mut dyn FnMut(Range<u64>) -> Option<ChainingValue>,
+) -> Option<[u8; 32]> {
+    if len <= CHUNK_LEN as u64 {
+        return (span_bytes.len() as u64 == len).then(|| blake3::hash(span_bytes).into());
+    }
+    let left  = left_subtree_len(len);
+    let l = subtree(0..left, span, span_bytes, sibling)?;
+    let r = subtree(left..len, span, span_bytes, sibling)?;
+    Some(merge_subtrees_root(&l, &r, Mode::Hash).into())
+}
+
+fn subtree(
+    at:         Range<u64>,
+    span:       &Range<u64>,
+    span_bytes: &[u8],
+    sibling:    &mut dyn FnMut(Range<u64>) -> Option<ChainingValue>,
+) -> Option<ChainingValue> {
+    if at.end <= span.start || at.start >= span.end {
+        return sibling(at);
+    }
+    let len = at.end - at.start;
+    if len <= CHUNK_LEN as u64 {
+        let bytes = span_bytes.get((at.start - span.start) as usize..(at.end - span.start) as usize)?;
+        return Some(blake3::Hasher::new().set_input_offset(at.start).update(bytes).finalize_non_root());
+    }
+    let mid = at.start + left_subtree_len(len);
+    let l = subtree(at.start..mid, span, span_bytes, sibling)?;
+    let r = subtree(mid..at.end, span, span_bytes, sibling)?;
+    Some(merge_subtrees_non_root(&l, &r, Mode::Hash))
+}
+
+// ── Hex serde ─────────────────────────────────────────────────────────────────
+
+mod hex32 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &[u8; 32], s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&hex::encode(v))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<[u8; 32], D::Error> {
+        let s = String::deserialize(d)?;
+        let mut out = [0u8; 32];
+        hex::decode_to_slice(&s, &mut out).map_err(D::Error::custom)?;
+        Ok(out)
+    }
+}
+
+mod hex32_list {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(v: &[[u8; 32]], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(v.iter().map(hex::encode))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<[u8; 32]>, D::Error> {
+        Vec::<String>::deserialize(d)?
+            .iter()
+            .map(|s| {
+                let mut out = [0u8; 32];
+                hex::decode_to_slice(s, &mut out).map(|_| out).map_err(D::Error::custom)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,199 @@
+//! Codec benchmark matrix behind `6cy bench`.
+//!
+//! [`run`] splits the input into chunks the way the writer does, then
+//! compresses and decompresses them with every built-in codec (at several
+//! levels where the codec honours one) and every registered plugin.  Chunks
+//! go through [`crate::perf::compress_chunks_parallel`], so with the
+//! `parallel` feature a run costs roughly one core-second per core rather
+//! than the sum over the matrix.
+//!
+//! Every run is round-trip checked; a codec that does not reproduce its
+//! input fails the benchmark rather than reporting a meaningless ratio.
+//!
+//! # Recommendation
+//!
+//! [`recommend`] picks the smallest output among runs that compress at least
+//! a tenth as fast as the fastest real codec, so a ratio gain bought with a
+//! hundredfold slowdown is not suggested.  If nothing saves more than 2 %,
+//! `none` is recommended.
+
+use std::time::{Duration, Instant};
+
+use crate::codec::{get_codec, uuid_to_string, CodecError, CodecId};
+use crate::perf::compress_chunks_parallel;
+
+/// Levels swept for plugins when the caller gives none; built-in codecs
+/// use [`CodecId::level_sweep`].
+pub const PLUGIN_LEVELS: &[i32] = &[3];
+
+/// Runs compressing slower than `fastest / RECOMMEND_MIN_SPEED_SHARE` are
+/// never recommended.
+const RECOMMEND_MIN_SPEED_SHARE: f64 = 10.0;
+/// Recommend `none` unless some run saves more than this fraction.
+const RECOMMEND_MIN_SAVING:      f64 = 0.02;
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Chunk size the input is split into (bytes).
+    pub chunk_size: usize,
+    /// Levels for zstd, brotli, and plugins; `None` uses each codec's
+    /// [`CodecId::level_sweep`] (or [`PLUGIN_LEVELS`]).
+    /// lz4, lzma, and none ignore levels and run once.
+    pub levels:     Option<Vec<i32>>,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self { chunk_size: 4 * 1024 * 1024, levels: None }
+    }
+}
+
+/// One codec / level cell of the matrix.
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    /// `CodecId::name()` or, for plugins, the hyphenated UUID.
+    pub codec:            String,
+    pub level:            i32,
+    pub input_bytes:      u64,
+    pub compressed_bytes: u64,
+    pub compress_time:    Duration,
+    pub decompress_time:  Duration,
+}
+
+impl BenchResult {
+    /// Compressed size over input size (lower is better).
+    pub fn ratio(&self) -> f64 {
+        if self.input_bytes == 0 { return 1.0; }
+        self.compressed_bytes as f64 / self.input_bytes as f64
+    }
+
+    pub fn compress_mbps(&self) -> f64 { mbps(self.input_bytes, self.compress_time) }
+
+    pub fn decompress_mbps(&self) -> f64 { mbps(self.input_bytes, self.decompress_time) }
+}
+
+fn mbps(bytes: u64, t: Duration) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0) / t.as_secs_f64().max(1e-9)
+}
+
+/// Benchmark every built-in codec and registered plugin on `data`.
+pub fn run(data: &[u8], opts: &BenchOptions) -> Result<Vec<BenchResult>, CodecError> {
+    let chunks: Vec<&[u8]> = data.chunks(opts.chunk_size.max(1)).collect();
+    let levels = |default: &'static [i32]| opts.levels.as_deref().unwrap_or(default).to_vec();
+
+    let mut out = Vec::new();
+    for codec in [CodecId::None, CodecId::Lz4, CodecId::Zstd, CodecId::Brotli, CodecId::Lzma] {
+        let levels = match codec.level_sweep() {
+            []    => vec![0],
+            sweep => levels(sweep),
+        };
+        for level in levels {
+            out.push(run_builtin(&chunks, codec, level)?);
+        }
+    }
+    for uuid in crate::plugin::registered_uuids() {
+        for level in levels(PLUGIN_LEVELS) {
+            out.push(run_plugin(&chunks, &uuid, level)?);
+        }
+    }
+    Ok(out)
+}
+
+/// The run [`run`]'s caller should use by default; see the module docs.
+pub fn recommend(results: &[BenchResult]) -> Option<&BenchResult> {
+    let real = || results.iter().filter(|r| r.codec != CodecId::None.name());
+    let fastest = real().map(BenchResult::compress_mbps).fold(0.0, f64::max);
+    let best = real()
+        .filter(|r| r.compress_mbps() * RECOMMEND_MIN_SPEED_SHARE >= fastest)
+        .min_by_key(|r| r.compressed_bytes)?;
+    if best.ratio() > 1.0 - RECOMMEND_MIN_SAVING {
+        return results.iter().find(|r| r.codec == CodecId::None.name()).or(Some(best));
+    }
+    Some(best)
+}
+
+fn run_builtin(chunks: &[&[u8]], codec: CodecId, level: i32) -> Result<BenchResult, CodecError> {
+    let t0 = Instant::now();
+    let compressed = compress_chunks_parallel(chunks, codec, level)?;
+    let compress_time = t0.elapsed();
+
+    let t1 = Instant::now();
+    let decoded = par_map(&compressed, |c| {
+        let mut out = Vec::with_capacity(c.orig_size);
+        get_codec(codec)?.decompress_bounded_into(&c.payload, c.orig_size, &mut out)?;
+        Ok(out)
+    })?;
+    let decompress_time = t1.elapsed();
+
+    check_round_trip(codec.name(), chunks, &decoded)?;
+    let compressed_bytes = compressed.iter().map(|c| c.payload.len() as u64).sum();
+    for c in compressed {
+        crate::pool::shared().give(c.payload);
+    }
+    Ok(BenchResult {
+        codec: codec.name().to_owned(),
+        level,
+        input_bytes: total(chunks),
+        compressed_bytes,
+        compress_time,
+        decompress_time,
+    })
+}
+
+fn run_plugin(chunks: &[&[u8]], uuid: &[u8; 16], level: i32) -> Result<BenchResult, CodecError> {
+    let name   = uuid_to_string(uuid);
+    let plugin = crate::plugin::registered(uuid)
+        .ok_or_else(|| CodecError::UnavailableCodec { uuid: name.clone() })?;
+
+    let t0 = Instant::now();
+    let compressed = par_map(chunks, |c| plugin.compress(c, level).map_err(CodecError::Compression))?;
+    let compress_time = t0.elapsed();
+
+    let pairs: Vec<(&[u8], usize)> = compressed.iter()
+        .map(Vec::as_slice)
+        .zip(chunks.iter().map(|c| c.len()))
+        .collect();
+    let t1 = Instant::now();
+    let decoded = par_map(&pairs, |(payload, len)| {
+        plugin.decompress(payload, *len).map_err(CodecError::Decompression)
+    })?;
+    let decompress_time = t1.elapsed();
+
+    check_round_trip(&name, chunks, &decoded)?;
+    Ok(BenchResult {
+        compressed_bytes: compressed.iter().map(|c| c.len() as u64).sum(),
+        codec: name,
+        level,
+        input_bytes: total(chunks),
+        compress_time,
+        decompress_time,
+    })
+}
+
+fn total(chunks: &[&[u8]]) -> u64 {
+    chunks.iter().map(|c| c.len() as u64).sum()
+}
+
+fn check_round_trip(codec: &str, chunks: &[&[u8]], decoded: &[Vec<u8>]) -> Result<(), CodecError> {
+    match chunks.iter().zip(decoded).position(|(a, b)| *a != b.as_slice()) {
+        None    => Ok(()),
+        Some(i) => Err(CodecError::Decompression(format!("{codec} did not round-trip chunk {i}"))),
+    }
+}
+
+/// Apply `f` to every item, on the Rayon pool when `parallel` is enabled.
+fn par_map<T: Sync, U: Send>(
+    items: &[T],
+    f:     impl Fn(&T) -> Result<U, CodecError> + Send + Sync,
+) -> Result<Vec<U>, CodecError> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        items.par_iter().map(f).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        items.iter().map(f).collect()
+    }
+}
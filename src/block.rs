@@ -1,4 +1,4 @@
-//! Block format v1 — fully self-describing, mandatory checksums.
+//! Block format v1 — fully self-describing, checksummed.
 //!
 //! # On-disk layout (84 bytes header, all fields little-endian)
 //!
@@ -9,6 +9,7 @@
 //!    6      2   header_size  = 84          (LE u16, skip unknown extensions)
 //!    8      2   block_type   0=Data 1=Index 2=Solid  (LE u16)
 //!   10      2   flags        0x0001=Encrypted        (LE u16)
+//!                            0x0F00=prefilter code
 //!   12     16   codec_uuid   frozen 16-byte UUID     (LE field order)
 //!   28      4   file_id      0xFFFF_FFFF = solid/idx (LE u32)
 //!   32      8   file_offset  in decompressed file    (LE u64)
@@ -27,7 +28,12 @@
 //! `header_crc32` covers all 80 bytes before it.  This detects header
 //! corruption before any seek or allocation is attempted.  Payload integrity
 //! is verified separately via `content_hash` (BLAKE3 of uncompressed data)
-//! after decompression.  Both checks are mandatory; there is no opt-out.
+//! after decompression.  Both are always written.  The CRC is always
+//! checked; the BLAKE3 check can be skipped, for callers that verify
+//! output themselves, through
+//! [`SixCyReader::danger_skip_content_hash`](crate::io_stream::SixCyReader::danger_skip_content_hash),
+//! which [`ExtractOptions::paranoid`](crate::archive::ExtractOptions::paranoid)
+//! overrides.
 //!
 //! # Index reconstruction
 //! Every DATA block embeds `file_id`, `file_offset`, `orig_size`, and
@@ -36,19 +42,19 @@
 //! Index block must still be parsed for file-name recovery; see `io_stream`.
 
 use std::io::{self, Read, Write};
-use crate::codec::{CodecId, get_codec_by_uuid, CodecError, uuid_to_string};
+use crate::codec::{CodecId, Dictionary, get_codec_by_uuid, decompress_by_uuid_into, decompress_prefix_by_uuid_into,
+                   CodecError, uuid_to_string};
+use crate::core_reader;
+use crate::crypto::{CryptoError, NONCE_LEN, TAG_LEN};
+use crate::limits::Limits;
+use crate::perf::{PerfStats, Stopwatch};
+use crate::pipeline::{Filter, FLAG_FILTER_SHIFT};
 use crc32fast::Hasher;
 
 // ── Constants ────────────────────────────────────────────────────────────────
 
-/// On-disk magic for every block header.  LE u32.
-pub const BLOCK_MAGIC: u32 = 0x424C_434B;  // "BLCK"
-
-/// Current block header layout version.
-pub const BLOCK_HEADER_VERSION: u16 = 1;
-
-/// Fixed byte size of the block header (including the trailing header_crc32).
-pub const BLOCK_HEADER_SIZE: usize = 84;
+// Layout constants live in the `no_std` core so both builds agree on them.
+pub use crate::core_reader::{BLOCK_MAGIC, BLOCK_HEADER_VERSION, BLOCK_HEADER_SIZE};
 
 /// `file_id` sentinel: this block does not belong to a single file.
 pub const FILE_ID_SHARED: u32 = 0xFFFF_FFFF;
@@ -60,11 +66,15 @@ pub const FILE_ID_SHARED: u32 = 0xFFFF_FFFF;
 #[repr(u16)]
 pub enum BlockType {
     /// Normal data block (one chunk of one file).
-    Data  = 0,
+    Data   = 0,
     /// Index block — payload is the file-name/metadata table.
-    Index = 1,
+    Index  = 1,
     /// Solid block — payload contains multiple concatenated files.
-    Solid = 2,
+    Solid  = 2,
+    /// Codecs block — required codec UUIDs that overflow the superblock.
+    Codecs = 3,
+    /// Meta block — one application blob, keyed in the INDEX's `app_data`.
+    Meta   = 4,
 }
 
 impl BlockType {
@@ -73,15 +83,30 @@ impl BlockType {
             0 => Some(BlockType::Data),
             1 => Some(BlockType::Index),
             2 => Some(BlockType::Solid),
+            3 => Some(BlockType::Codecs),
+            4 => Some(BlockType::Meta),
             _ => None,
         }
     }
+
+    /// The type's name as the spec writes it, e.g. `"DATA"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            BlockType::Data   => "DATA",
+            BlockType::Index  => "INDEX",
+            BlockType::Solid  => "SOLID",
+            BlockType::Codecs => "CODECS",
+            BlockType::Meta   => "META",
+        }
+    }
 }
 
 // ── Flags ────────────────────────────────────────────────────────────────────
 
 /// Payload is AES-256-GCM encrypted (nonce prepended).
-pub const FLAG_ENCRYPTED: u16 = 0x0001;
+pub use crate::core_reader::FLAG_ENCRYPTED;
+/// Prefilter code bits; see [`crate::pipeline`].
+pub use crate::core_reader::FLAG_FILTER_MASK;
 
 // ── Block header ─────────────────────────────────────────────────────────────
 
@@ -156,71 +181,23 @@ impl BlockHeader {
     pub fn read<R: Read>(mut r: R) -> io::Result<Self> {
         let mut buf = [0u8; BLOCK_HEADER_SIZE];
         r.read_exact(&mut buf)?;
+        Self::parse(&buf)
+    }
 
-        // 1. Verify header CRC32 first — cheapest possible check.
-        let mut h = Hasher::new();
-        h.update(&buf[..80]);
-        let expected_crc = h.finalize();
-        let stored_crc   = u32::from_le_bytes(buf[80..84].try_into().unwrap());
-        if stored_crc != expected_crc {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Block header CRC32 mismatch: expected {expected_crc:#010x}, got {stored_crc:#010x}"),
-            ));
-        }
-
-        // 2. Validate magic.
-        let magic = u32::from_le_bytes(buf[0..4].try_into().unwrap());
-        if magic != BLOCK_MAGIC {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Invalid block magic: expected {BLOCK_MAGIC:#010x}, got {magic:#010x}"),
-            ));
-        }
-
-        // 3. Validate header version — we know how to read v1.
-        let header_version = u16::from_le_bytes(buf[4..6].try_into().unwrap());
-        if header_version != BLOCK_HEADER_VERSION {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Unsupported block header version {header_version} (this build handles v{BLOCK_HEADER_VERSION})"),
-            ));
-        }
-
-        // 4. header_size lets future readers skip extensions we don't know.
-        let header_size = u16::from_le_bytes(buf[6..8].try_into().unwrap());
-        if (header_size as usize) < BLOCK_HEADER_SIZE {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                format!("Block header_size {header_size} < minimum {BLOCK_HEADER_SIZE}"),
-            ));
-        }
-
-        // 5. Parse block type.
-        let block_type_raw = u16::from_le_bytes(buf[8..10].try_into().unwrap());
-        let block_type = BlockType::from_u16(block_type_raw).ok_or_else(|| {
-            io::Error::new(io::ErrorKind::InvalidData,
-                format!("Unknown block_type {block_type_raw}"))
-        })?;
-
-        let flags       = u16::from_le_bytes(buf[10..12].try_into().unwrap());
-        let codec_uuid: [u8; 16] = buf[12..28].try_into().unwrap();
-        let file_id     = u32::from_le_bytes(buf[28..32].try_into().unwrap());
-        let file_offset = u64::from_le_bytes(buf[32..40].try_into().unwrap());
-        let orig_size   = u32::from_le_bytes(buf[40..44].try_into().unwrap());
-        let comp_size   = u32::from_le_bytes(buf[44..48].try_into().unwrap());
-        let content_hash: [u8; 32] = buf[48..80].try_into().unwrap();
-
+    /// Validate and decode an in-memory 84-byte header.  Validation order and
+    /// error wording are those of [`core_reader::parse_block_header`].
+    pub fn parse(buf: &[u8; BLOCK_HEADER_SIZE]) -> io::Result<Self> {
+        let raw = core_reader::parse_block_header(buf)?;
         Ok(Self {
-            header_version,
-            block_type,
-            flags,
-            codec_uuid,
-            file_id,
-            file_offset,
-            orig_size,
-            comp_size,
-            content_hash,
+            header_version: raw.header_version,
+            block_type:     BlockType::from_u16(raw.block_type).expect("validated by core_reader"),
+            flags:          raw.flags,
+            codec_uuid:     raw.codec_uuid,
+            file_id:        raw.file_id,
+            file_offset:    raw.file_offset,
+            orig_size:      raw.orig_size,
+            comp_size:      raw.comp_size,
+            content_hash:   raw.content_hash,
         })
     }
 
@@ -246,71 +223,282 @@ pub fn encode_block(
     level:          i32,
     encryption_key: Option<&[u8; 32]>,
 ) -> Result<(BlockHeader, Vec<u8>), CodecError> {
+    encode_block_with(block_type, file_id, file_offset, data, codec_id, level,
+                      EncodeOpts { key: encryption_key, deterministic: false, stats: None, content_hash: None, filter: None,
+                                   dictionary: None })
+}
+
+/// [`encode_block`] for reproducible archives: identical inputs always
+/// produce an identical payload, because the AES-GCM nonce is derived from
+/// the plaintext (see [`crate::crypto::encrypt_deterministic`]).
+pub fn encode_block_deterministic(
+    block_type:     BlockType,
+    file_id:        u32,
+    file_offset:    u64,
+    data:           &[u8],
+    codec_id:       CodecId,
+    level:          i32,
+    encryption_key: Option<&[u8; 32]>,
+) -> Result<(BlockHeader, Vec<u8>), CodecError> {
+    encode_block_with(block_type, file_id, file_offset, data, codec_id, level,
+                      EncodeOpts { key: encryption_key, deterministic: true, stats: None, content_hash: None, filter: None,
+                                   dictionary: None })
+}
+
+/// Encryption and instrumentation settings for [`encode_block_with`].
+pub(crate) struct EncodeOpts<'a> {
+    pub key:           Option<&'a [u8; 32]>,
+    /// Plaintext-derived nonces (see [`encode_block_deterministic`]).
+    pub deterministic: bool,
+    pub stats:         Option<&'a mut PerfStats>,
+    /// BLAKE3 of the data, when the caller already has it.
+    pub content_hash:  Option<[u8; 32]>,
+    /// Prefilter applied before compression and recorded in `flags`.
+    pub filter:        Option<Filter>,
+    /// Compress Zstd blocks with this dictionary, whose UUID then names
+    /// the codec in the header.  Ignored for other codecs.
+    pub dictionary:    Option<&'a Dictionary>,
+}
+
+pub(crate) fn encode_block_with(
+    block_type:  BlockType,
+    file_id:     u32,
+    file_offset: u64,
+    data:        &[u8],
+    codec_id:    CodecId,
+    level:       i32,
+    opts:        EncodeOpts<'_>,
+) -> Result<(BlockHeader, Vec<u8>), CodecError> {
+    let mut clock = Stopwatch::new(opts.stats.is_some());
+    let orig_size = size_field("orig_size", data.len())?;
+
     // BLAKE3 of original plaintext — CAS identity, stored in header.
-    let content_hash: [u8; 32] = blake3::hash(data).into();
+    let content_hash = match opts.content_hash {
+        Some(hash) => hash,
+        None       => crate::perf::hash_chunk(data),
+    };
+    let hash_time = clock.lap();
 
-    // Compress.
+    // Prefilter and compress into pooled buffers.
+    let pool    = crate::pool::shared();
     let codec   = get_codec_by_uuid(&codec_id.uuid())?;
-    let mut payload = codec.compress(data, level)?;
-
-    // Optionally encrypt the compressed payload.
+    let dictionary = opts.dictionary.filter(|_| codec_id == CodecId::Zstd);
+    let compress   = |input: &[u8], out: &mut Vec<u8>| match dictionary {
+        Some(dict) => dict.compress_into(input, level, out),
+        None       => codec.compress_into(input, level, out),
+    };
     let mut flags = 0u16;
-    if let Some(key) = encryption_key {
-        payload = crate::crypto::encrypt(key, &payload)
-            .map_err(|e| CodecError::Encryption(e.to_string()))?;
-        flags |= FLAG_ENCRYPTED;
+    let mut compressed = pool.take(data.len() / 2);
+    match opts.filter {
+        Some(filter) => {
+            let code = filter.code().ok_or_else(|| CodecError::Compression(
+                format!("unsupported prefilter {}", filter.name())))?;
+            let mut filtered = pool.take(data.len());
+            filter.apply(data, &mut filtered);
+            let result = compress(&filtered, &mut compressed);
+            pool.give(filtered);
+            result?;
+            flags |= code << FLAG_FILTER_SHIFT;
+        }
+        None => compress(data, &mut compressed)?,
+    }
+    let compress_time = clock.lap();
+
+    // Optionally encrypt the compressed payload; the plaintext buffer goes
+    // straight back to the pool.
+    let payload = match opts.key {
+        Some(key) => {
+            let mut sealed = pool.take(NONCE_LEN + compressed.len() + TAG_LEN);
+            if opts.deterministic {
+                crate::crypto::encrypt_deterministic_into(key, &compressed, &mut sealed)
+            } else {
+                crate::crypto::encrypt_into(key, &compressed, &mut sealed)
+            }.map_err(|e| CodecError::Encryption(e.to_string()))?;
+            pool.give(compressed);
+            flags |= FLAG_ENCRYPTED;
+            sealed
+        }
+        None => compressed,
+    };
+
+    if let Some(stats) = opts.stats {
+        stats.hash     += hash_time;
+        stats.compress += compress_time;
+        stats.encrypt  += clock.lap();
     }
 
     let header = BlockHeader {
         header_version: BLOCK_HEADER_VERSION,
         block_type,
         flags,
-        codec_uuid:   codec_id.uuid(),
+        codec_uuid:   dictionary.map_or(codec_id.uuid(), Dictionary::uuid),
         file_id,
         file_offset,
-        orig_size:    data.len() as u32,
-        comp_size:    payload.len() as u32,
+        orig_size,
+        comp_size:    size_field("comp_size", payload.len())?,
         content_hash,
     };
 
     Ok((header, payload))
 }
 
+/// `len` as a header size field, or [`CodecError::BlockTooLarge`].
+fn size_field(field: &'static str, len: usize) -> Result<u32, CodecError> {
+    u32::try_from(len).map_err(|_| CodecError::BlockTooLarge { field, size: len as u64 })
+}
+
+// ── reseal_block ──────────────────────────────────────────────────────────────
+
+/// Move an encoded block between encryption states without recompressing:
+/// strip the AEAD layer with `from` if the block is encrypted, then seal
+/// the compressed bytes under `to` if given.  Codec, prefilter, sizes of
+/// the plaintext, and `content_hash` are kept; only the GCM tag is checked
+/// here, the content hash is left for whoever decodes the result.
+pub fn reseal_block(
+    header:  &BlockHeader,
+    payload: &[u8],
+    from:    Option<&[u8; 32]>,
+    to:      Option<&[u8; 32]>,
+) -> Result<(BlockHeader, Vec<u8>), CodecError> {
+    let pool = crate::pool::shared();
+    let mut compressed = pool.take(payload.len());
+    if header.is_encrypted() {
+        let key = from.ok_or(CryptoError::MissingKey)?;
+        crate::crypto::decrypt_into(key, payload, &mut compressed)?;
+    } else {
+        compressed.extend_from_slice(payload);
+    }
+
+    let mut header = header.clone();
+    let payload = match to {
+        Some(key) => {
+            let mut sealed = pool.take(NONCE_LEN + compressed.len() + TAG_LEN);
+            crate::crypto::encrypt_into(key, &compressed, &mut sealed)
+                .map_err(|e| CodecError::Encryption(e.to_string()))?;
+            pool.give(compressed);
+            header.flags |= FLAG_ENCRYPTED;
+            sealed
+        }
+        None => {
+            header.flags &= !FLAG_ENCRYPTED;
+            compressed
+        }
+    };
+    header.comp_size = size_field("comp_size", payload.len())?;
+    Ok((header, payload))
+}
+
 // ── decode_block ──────────────────────────────────────────────────────────────
 
 /// Verify, decrypt (if needed), and decompress a block payload.
 ///
-/// Verification order (no opt-outs):
+/// Verification order (every step always runs here; only readers with
+/// [`danger_skip_content_hash`](crate::io_stream::SixCyReader::danger_skip_content_hash)
+/// set skip step 4):
 ///   1. Decrypt (if FLAG_ENCRYPTED) — GCM tag verifies ciphertext integrity
-///   2. Decompress via the UUID named in the header
-///   3. BLAKE3 of decompressed output == `header.content_hash`
+///   2. Decompress via the UUID named in the header, never producing more
+///      than the header's `orig_size`
+///   3. Undo the prefilter named in `flags` ([`crate::pipeline::Filter`])
+///   4. BLAKE3 of the result == `header.content_hash`
 ///
-/// If step 3 fails the decompressor produced wrong output — treat as
+/// If step 4 fails the decompressor produced wrong output — treat as
 /// corruption regardless of which codec was used.
 pub fn decode_block(
     header:         &BlockHeader,
     payload:        &[u8],
     decryption_key: Option<&[u8; 32]>,
 ) -> Result<Vec<u8>, CodecError> {
+    decode_block_limited(header, payload, decryption_key, &Limits::UNLIMITED)
+}
+
+/// [`decode_block`] that first rejects blocks whose declared sizes exceed
+/// `limits.max_block_size`.
+pub fn decode_block_limited(
+    header:         &BlockHeader,
+    payload:        &[u8],
+    decryption_key: Option<&[u8; 32]>,
+    limits:         &Limits,
+) -> Result<Vec<u8>, CodecError> {
+    let mut out = crate::pool::shared().take(0);
+    decode_block_into(header, payload, decryption_key, limits, &mut out)?;
+    Ok(out)
+}
+
+/// [`decode_block_limited`] appending the plaintext to `out`, with the
+/// decrypted intermediate drawn from [`crate::pool::shared`].  On error
+/// `out` is left as it was.
+pub fn decode_block_into(
+    header:         &BlockHeader,
+    payload:        &[u8],
+    decryption_key: Option<&[u8; 32]>,
+    limits:         &Limits,
+    out:            &mut Vec<u8>,
+) -> Result<(), CodecError> {
+    decode_block_timed(header, payload, decryption_key, limits, out, None, true)
+}
+
+/// [`decode_block_into`] adding stage timings to `stats`, and skipping the
+/// BLAKE3 check unless `verify` — for readers whose caller has opted out
+/// (e.g. [`SixCyReader::danger_skip_content_hash`](crate::io_stream::SixCyReader::danger_skip_content_hash)).
+pub(crate) fn decode_block_timed(
+    header:         &BlockHeader,
+    payload:        &[u8],
+    decryption_key: Option<&[u8; 32]>,
+    limits:         &Limits,
+    out:            &mut Vec<u8>,
+    stats:          Option<&mut PerfStats>,
+    verify:         bool,
+) -> Result<(), CodecError> {
+    limits.check_block(header)?;
+    let pool  = crate::pool::shared();
+    let start = out.len();
+    let mut clock = Stopwatch::new(stats.is_some());
+
     // 1. Decrypt if flagged — GCM tag covers the ciphertext.
-    let compressed = if header.is_encrypted() {
-        let key = decryption_key.ok_or_else(|| {
-            CodecError::Encryption("Block is encrypted but no decryption key was provided".into())
-        })?;
-        crate::crypto::decrypt(key, payload)
-            .map_err(|e| CodecError::Encryption(e.to_string()))?
+    let mut decrypted = None;
+    let compressed: &[u8] = if header.is_encrypted() {
+        let key = decryption_key.ok_or(CryptoError::MissingKey)?;
+        let mut buf = pool.take(payload.len());
+        crate::crypto::decrypt_into(key, payload, &mut buf)?;
+        decrypted.insert(buf)
     } else {
-        payload.to_vec()
+        payload
     };
+    let decrypt_time = clock.lap();
+
+    // 2. Decompress using the UUID embedded in the header, after vetting
+    //    the decoder window the stream asks for.
+    //    Fails hard if neither a built-in codec nor a plugin provides it.
+    let result = match CodecId::from_uuid(&header.codec_uuid).map(|id| limits.check_window(id, compressed)) {
+        Some(Err(e)) => Err(e.into()),
+        _            => decompress_by_uuid_into(&header.codec_uuid, compressed, header.orig_size as usize, out),
+    };
+    if let Some(buf) = decrypted { pool.give(buf); }
+    if let Err(e) = result {
+        out.truncate(start);
+        return Err(e);
+    }
+    let decompress_time = clock.lap();
+
+    // 3. Undo the prefilter recorded in `flags`, if any.
+    match Filter::from_flags(header.flags) {
+        Ok(Some(filter)) => filter.revert(&mut out[start..]),
+        Ok(None)         => {}
+        Err(e)           => {
+            out.truncate(start);
+            return Err(e);
+        }
+    }
 
-    // 2. Decompress using the UUID embedded in the header.
-    //    Fails hard if the UUID is not available in this build.
-    let codec        = get_codec_by_uuid(&header.codec_uuid)?;
-    let decompressed = codec.decompress(&compressed)?;
-
-    // 3. BLAKE3 content hash — mandatory final check.
-    let actual_hash: [u8; 32] = blake3::hash(&decompressed).into();
-    if actual_hash != header.content_hash {
+    // 4. BLAKE3 content hash — the final check, unless opted out.
+    let actual_hash: Option<[u8; 32]> = verify.then(|| blake3::hash(&out[start..]).into());
+    if let Some(stats) = stats {
+        stats.decrypt    += decrypt_time;
+        stats.decompress += decompress_time;
+        stats.hash       += clock.lap();
+    }
+    if let Some(actual_hash) = actual_hash.filter(|hash| *hash != header.content_hash) {
+        out.truncate(start);
         return Err(CodecError::Decompression(format!(
             "BLAKE3 content hash mismatch (got {}, expected {})",
             hex::encode(actual_hash),
@@ -318,5 +506,58 @@ pub fn decode_block(
         )));
     }
 
-    Ok(decompressed)
+    Ok(())
+}
+
+/// The first `prefix` bytes of a block's plaintext, appended to `out`,
+/// decoding no further than needed.  The content hash covers the whole
+/// plaintext and cannot be checked on a prefix, so only the GCM tag (for
+/// encrypted blocks) vouches for it; callers wanting a checked slice hash
+/// it themselves.  A prefix covering the whole block, or a block whose
+/// prefilter does not decode front to back, is decoded and checked in
+/// full.  On error `out` is left as it was.
+pub fn decode_block_prefix_into(
+    header:         &BlockHeader,
+    payload:        &[u8],
+    decryption_key: Option<&[u8; 32]>,
+    limits:         &Limits,
+    prefix:         usize,
+    out:            &mut Vec<u8>,
+) -> Result<(), CodecError> {
+    let filter = Filter::from_flags(header.flags)?;
+    if prefix >= header.orig_size as usize || matches!(filter, Some(Filter::Shuffle(_))) {
+        let start = out.len();
+        decode_block_into(header, payload, decryption_key, limits, out)?;
+        out.truncate(start + prefix);
+        return Ok(());
+    }
+    limits.check_block(header)?;
+    let pool  = crate::pool::shared();
+    let start = out.len();
+
+    let mut decrypted = None;
+    let compressed: &[u8] = if header.is_encrypted() {
+        let key = decryption_key.ok_or(CryptoError::MissingKey)?;
+        let mut buf = pool.take(payload.len());
+        crate::crypto::decrypt_into(key, payload, &mut buf)?;
+        decrypted.insert(buf)
+    } else {
+        payload
+    };
+
+    let result = match CodecId::from_uuid(&header.codec_uuid).map(|id| limits.check_window(id, compressed)) {
+        Some(Err(e)) => Err(e.into()),
+        _            => decompress_prefix_by_uuid_into(&header.codec_uuid, compressed, prefix,
+                                                       header.orig_size as usize, out),
+    };
+    if let Some(buf) = decrypted { pool.give(buf); }
+    if let Err(e) = result {
+        out.truncate(start);
+        return Err(e);
+    }
+    // Delta reverts front to back, so a prefix reverts on its own.
+    if let Some(filter) = filter {
+        filter.revert(&mut out[start..]);
+    }
+    Ok(())
 }
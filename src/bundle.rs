@@ -0,0 +1,344 @@
+//! Read-only access tuned for many small random reads, as when a game
+//! loads assets from a `.6cy` bundle at runtime.
+//!
+//! [`BundleReader`] parses the INDEX once at open into flat arrays — names
+//! sorted for binary search in one string, each file's pieces in one
+//! slice — and then serves reads through `&self`, so one reader is shared
+//! by every loading thread.  [`open_at`](BundleReader::open_at) returns an
+//! [`Asset`], a `Copy` handle of two words; reads go into buffers the
+//! caller supplies.
+//!
+//! Per read:
+//!
+//! - a decoded block in the cache is copied out under a brief lock — no
+//!   file access, no decoding, no allocation;
+//! - otherwise the block is read with a positional read into this thread's
+//!   scratch buffer and decoded, into the cache if it fits the budget and
+//!   into a second per-thread buffer if not.  Decoding draws its
+//!   intermediate buffers from [`crate::pool`].
+//!
+//! The cache keeps the most recently used blocks within a byte budget
+//! ([`DEFAULT_BLOCK_CACHE_BYTES`] unless set with
+//! [`with_cache_bytes`](BundleReader::with_cache_bytes)), so a SOLID block
+//! of small assets is decoded once however many of them are read.
+//!
+//! Only standalone archives are supported: an incremental snapshot, whose
+//! refs point into base archives, is rejected at open.  Files of more than
+//! one chunk cost one block-header read per chunk at open, to learn where
+//! each chunk starts.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::block::{decode_block_timed, BlockHeader, BLOCK_HEADER_SIZE};
+use crate::crypto::derive_key;
+use crate::error::Result;
+use crate::io_stream::{Section, SharedFile, SixCyReader, DEFAULT_BLOCK_CACHE_BYTES};
+use crate::limits::Limits;
+use crate::superblock::Superblock;
+
+thread_local! {
+    /// On-disk payload of the block being decoded.
+    static PAYLOAD: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+    /// Plaintext of a block too large for the cache.
+    static PLAIN:   RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// One file in the flat table.
+#[derive(Debug, Clone)]
+struct Entry {
+    id:    u32,
+    name:  Range<usize>,
+    size:  u64,
+    /// This file's pieces in [`BundleReader::pieces`].
+    span:  Range<usize>,
+}
+
+/// The part of one block a file's bytes `start..start + len` come from.
+#[derive(Debug, Clone, Copy)]
+struct Piece {
+    start:  u64,
+    len:    u64,
+    block:  u64,
+    /// Offset within the decoded block (non-zero for solid members).
+    within: u64,
+}
+
+/// Decoded blocks by archive offset, least recently used evicted first.
+#[derive(Debug, Default)]
+struct Cache {
+    blocks: HashMap<u64, (Arc<Vec<u8>>, u64)>,
+    /// Last use → block offset.
+    lru:    BTreeMap<u64, u64>,
+    tick:   u64,
+    bytes:  usize,
+    hits:   u64,
+    misses: u64,
+}
+
+impl Cache {
+    fn get(&mut self, offset: u64) -> Option<Arc<Vec<u8>>> {
+        let (block, used) = self.blocks.get_mut(&offset)?;
+        self.lru.remove(used);
+        self.tick += 1;
+        *used = self.tick;
+        self.lru.insert(self.tick, offset);
+        self.hits += 1;
+        Some(Arc::clone(block))
+    }
+
+    fn insert(&mut self, offset: u64, block: Arc<Vec<u8>>, max_bytes: usize) {
+        self.tick += 1;
+        self.bytes += block.len();
+        if let Some((old, used)) = self.blocks.insert(offset, (block, self.tick)) {
+            // Another thread decoded it too.
+            self.bytes -= old.len();
+            self.lru.remove(&used);
+        }
+        self.lru.insert(self.tick, offset);
+        while self.bytes > max_bytes {
+            let Some((_, oldest)) = self.lru.pop_first() else { break };
+            if let Some((block, _)) = self.blocks.remove(&oldest) {
+                self.bytes -= block.len();
+            }
+        }
+    }
+
+    /// Drop every held block, keeping the counters.
+    fn clear(&mut self) {
+        self.blocks.clear();
+        self.lru.clear();
+        self.bytes = 0;
+    }
+}
+
+/// Counters reported by [`BundleReader::cache_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BundleCacheStats {
+    /// Reads of a block served from the cache.
+    pub hits:   u64,
+    /// Blocks read and decoded.
+    pub misses: u64,
+    /// Plaintext bytes currently held.
+    pub bytes:  usize,
+}
+
+/// See the module docs.
+#[derive(Debug)]
+pub struct BundleReader {
+    file:      SharedFile,
+    key:       Option<[u8; 32]>,
+    limits:    Limits,
+    /// Every name, concatenated in `entries` order.
+    names:     String,
+    /// Sorted by name.
+    entries:   Vec<Entry>,
+    pieces:    Vec<Piece>,
+    cache:     Mutex<Cache>,
+    max_bytes: usize,
+    verify:    bool,
+}
+
+impl BundleReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_keyed(path.as_ref(), None)
+    }
+
+    pub fn open_encrypted<P: AsRef<Path>>(path: P, password: &str) -> Result<Self> {
+        let sb = Superblock::read(&mut File::open(path.as_ref())?)?;
+        Self::open_keyed(path.as_ref(), Some(derive_key(password, sb.archive_uuid.as_bytes())?))
+    }
+
+    pub fn open_with_key<P: AsRef<Path>>(path: P, key: [u8; 32]) -> Result<Self> {
+        Self::open_keyed(path.as_ref(), Some(key))
+    }
+
+    fn open_keyed(path: &Path, key: Option<[u8; 32]>) -> Result<Self> {
+        let file       = SharedFile::new(File::open(path)?);
+        let mut reader = SixCyReader::with_key(Section::new(file.clone(), 0)?, key)?;
+        let index      = Arc::clone(&reader.index);
+        let own        = *reader.superblock.archive_uuid.as_bytes();
+
+        let mut records: Vec<_> = index.records.iter().collect();
+        records.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        let mut names   = String::with_capacity(records.iter().map(|r| r.name.len()).sum());
+        let mut entries = Vec::with_capacity(records.len());
+        let mut pieces  = Vec::with_capacity(records.iter().map(|r| r.block_refs.len()).sum());
+        for rec in records {
+            let first = pieces.len();
+            let mut start = 0u64;
+            for br in &rec.block_refs {
+                if br.archive_id.is_some_and(|id| id != own) {
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                        "'{}' is stored in a base archive; bundle readers do not follow snapshot chains",
+                        rec.name)).into());
+                }
+                let len = match (br.is_solid_slice(), rec.block_refs.len()) {
+                    (true, _)  => br.intra_length,
+                    (false, 1) => rec.original_size,
+                    (false, _) => reader.block_header(br)?.orig_size as u64,
+                };
+                pieces.push(Piece { start, len, block: br.archive_offset, within: br.intra_offset });
+                start += len;
+            }
+            names.push_str(&rec.name);
+            entries.push(Entry {
+                id:   rec.id,
+                name: names.len() - rec.name.len()..names.len(),
+                size: rec.original_size,
+                span: first..pieces.len(),
+            });
+        }
+
+        Ok(Self {
+            file,
+            key,
+            limits:    *reader.limits(),
+            names,
+            entries,
+            pieces,
+            cache:     Mutex::new(Cache::default()),
+            max_bytes: DEFAULT_BLOCK_CACHE_BYTES,
+            verify:    true,
+        })
+    }
+
+    /// Keep at most `max_bytes` of decoded blocks; 0 disables the cache.
+    pub fn with_cache_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Decode blocks without recomputing their BLAKE3 hashes, for bundles
+    /// whose integrity the caller has already established (e.g. checked
+    /// once at install).  See
+    /// [`SixCyReader::danger_skip_content_hash`](crate::io_stream::SixCyReader::danger_skip_content_hash).
+    /// Turning the checks back on empties the cache of unchecked blocks.
+    pub fn danger_skip_content_hash(mut self, skip: bool) -> Self {
+        if !self.verify && !skip {
+            self.cache.get_mut().unwrap_or_else(|e| e.into_inner()).clear();
+        }
+        self.verify = !skip;
+        self
+    }
+
+    /// The file called `name`, or `None` if the bundle has none.
+    pub fn open_at(&self, name: &str) -> Option<Asset<'_>> {
+        let slot = self.entries.binary_search_by(|e| self.names[e.name.clone()].cmp(name)).ok()?;
+        Some(Asset { bundle: self, slot })
+    }
+
+    /// Every file, in name order.
+    pub fn assets(&self) -> impl Iterator<Item = Asset<'_>> + '_ {
+        (0..self.entries.len()).map(|slot| Asset { bundle: self, slot })
+    }
+
+    pub fn len(&self) -> usize { self.entries.len() }
+
+    pub fn is_empty(&self) -> bool { self.entries.is_empty() }
+
+    pub fn cache_stats(&self) -> BundleCacheStats {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        BundleCacheStats { hits: cache.hits, misses: cache.misses, bytes: cache.bytes }
+    }
+
+    /// Copy bytes `from..` of `piece` into `out`, returning how many.
+    fn read_piece(&self, piece: &Piece, from: u64, out: &mut [u8]) -> Result<usize> {
+        let n    = (piece.len - from).min(out.len() as u64) as usize;
+        let from = (piece.within + from) as usize;
+        let copy = |block: &[u8], out: &mut [u8]| -> Result<usize> {
+            let src = block.get(from..from + n).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                format!("range {from}..{} exceeds the {}-byte block at offset {}", from + n, block.len(), piece.block)))?;
+            out[..n].copy_from_slice(src);
+            Ok(n)
+        };
+
+        if let Some(block) = self.cache.lock().unwrap_or_else(|e| e.into_inner()).get(piece.block) {
+            return copy(&block, out);
+        }
+        PAYLOAD.with_borrow_mut(|payload| {
+            let header = self.read_block(piece.block, payload)?;
+            let orig   = header.orig_size as usize;
+            if orig > self.max_bytes {
+                return PLAIN.with_borrow_mut(|plain| {
+                    plain.clear();
+                    decode_block_timed(&header, payload, self.key.as_ref(), &self.limits, plain, None, self.verify)?;
+                    self.cache.lock().unwrap_or_else(|e| e.into_inner()).misses += 1;
+                    copy(plain, out)
+                });
+            }
+            let mut plain = Vec::with_capacity(orig);
+            decode_block_timed(&header, payload, self.key.as_ref(), &self.limits, &mut plain, None, self.verify)?;
+            let n = copy(&plain, out)?;
+            let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+            cache.misses += 1;
+            cache.insert(piece.block, Arc::new(plain), self.max_bytes);
+            Ok(n)
+        })
+    }
+
+    /// Read the block at `offset` into `payload`, returning its header.
+    fn read_block(&self, offset: u64, payload: &mut Vec<u8>) -> Result<BlockHeader> {
+        let mut file = self.file.clone();
+        file.seek(SeekFrom::Start(offset))?;
+        let mut head = [0u8; BLOCK_HEADER_SIZE];
+        file.read_exact(&mut head)?;
+        let header = BlockHeader::parse(&head)?;
+        self.limits.check_block(&header)?;
+        payload.resize(header.comp_size as usize, 0);
+        file.read_exact(payload)?;
+        Ok(header)
+    }
+}
+
+/// A file in a [`BundleReader`]; see [`BundleReader::open_at`].
+#[derive(Debug, Clone, Copy)]
+pub struct Asset<'a> {
+    bundle: &'a BundleReader,
+    slot:   usize,
+}
+
+impl<'a> Asset<'a> {
+    fn entry(&self) -> &'a Entry { &self.bundle.entries[self.slot] }
+
+    pub fn name(&self) -> &'a str { &self.bundle.names[self.entry().name.clone()] }
+
+    /// Record ID in the INDEX.
+    pub fn id(&self) -> u32 { self.entry().id }
+
+    pub fn len(&self) -> u64 { self.entry().size }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// Fill `buf` from `offset` within the file, stopping at its end;
+    /// returns the bytes copied.
+    pub fn read_at(&self, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let pieces = &self.bundle.pieces[self.entry().span.clone()];
+        let mut at = pieces.partition_point(|p| p.start + p.len <= offset);
+        let mut done = 0;
+        while done < buf.len() {
+            let Some(piece) = pieces.get(at) else { break };
+            let from = offset + done as u64 - piece.start;
+            done += self.bundle.read_piece(piece, from, &mut buf[done..])?;
+            at += 1;
+        }
+        Ok(done)
+    }
+
+    /// Replace the contents of `out` with the whole file, reusing its
+    /// capacity.
+    pub fn read_into(&self, out: &mut Vec<u8>) -> Result<()> {
+        out.resize(self.len() as usize, 0);
+        let n = self.read_at(0, out)?;
+        if n != out.len() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!(
+                "'{}' yielded {n} of {} bytes", self.name(), out.len())).into());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+//! Trained Zstandard dictionaries shared between archives.
+//!
+//! Small archives of similar content compress poorly on their own: each
+//! block starts from an empty window.  A [`Dictionary`] trained on samples
+//! of that content primes every block, and a fleet of archives can share
+//! one — stored once, e.g. in a [`BlockStore`](crate::store::BlockStore),
+//! rather than in each archive.
+//!
+//! A dictionary acts as a codec of its own: its UUID is the first 16 bytes
+//! of the BLAKE3 hash of its bytes, and a Zstd block compressed with it
+//! names that UUID in `codec_uuid`, so the superblock's required-codec list
+//! records which dictionary an archive needs.  Readers resolve it through a
+//! process-wide registry ([`register_dictionary`]), after built-in codecs
+//! and before plugins; an archive whose dictionary is not registered fails
+//! the required-codec check like one whose plugin is not loaded.
+
+use std::io::{self, Write};
+use std::sync::{Arc, RwLock};
+
+use super::{read_bounded_into, read_prefix_into, CodecError};
+
+/// A trained Zstd dictionary; see the module docs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Dictionary {
+    uuid:  [u8; 16],
+    bytes: Vec<u8>,
+}
+
+impl std::fmt::Debug for Dictionary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dictionary")
+            .field("uuid", &super::uuid_to_string(&self.uuid))
+            .field("len",  &self.bytes.len())
+            .finish()
+    }
+}
+
+impl Dictionary {
+    /// Wrap dictionary bytes (as written by `zstd --train`, or
+    /// [`as_bytes`](Self::as_bytes)) and derive their UUID.
+    pub fn new(bytes: Vec<u8>) -> Self {
+        let hash = blake3::hash(&bytes);
+        let uuid = hash.as_bytes()[..16].try_into().expect("BLAKE3 hashes are 32 bytes");
+        Self { uuid, bytes }
+    }
+
+    /// Train a dictionary of at most `max_size` bytes on `samples`.  Zstd
+    /// wants many samples (hundreds) totalling around 100× `max_size`.
+    pub fn train<S: AsRef<[u8]>>(samples: &[S], max_size: usize) -> io::Result<Self> {
+        Ok(Self::new(zstd::dict::from_samples(samples, max_size)?))
+    }
+
+    /// Raw 16-byte UUID (LE field order) written as the `codec_uuid` of
+    /// blocks compressed with this dictionary.
+    pub fn uuid(&self) -> [u8; 16] { self.uuid }
+
+    pub fn as_bytes(&self) -> &[u8] { &self.bytes }
+
+    pub(crate) fn compress_into(&self, data: &[u8], level: i32, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        let compression = |e: io::Error| CodecError::Compression(e.to_string());
+        let mut enc = zstd::stream::write::Encoder::with_dictionary(out, level, &self.bytes).map_err(compression)?;
+        enc.write_all(data).map_err(compression)?;
+        enc.finish().map_err(compression)?;
+        Ok(())
+    }
+
+    pub(crate) fn decompress_bounded_into(&self, data: &[u8], limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        let dec = zstd::stream::read::Decoder::with_dictionary(data, &self.bytes)
+            .map_err(|e| CodecError::Decompression(e.to_string()))?;
+        read_bounded_into(dec, limit, out)
+    }
+
+    pub(crate) fn decompress_prefix_into(&self, data: &[u8], prefix: usize, limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        let dec = zstd::stream::read::Decoder::with_dictionary(data, &self.bytes)
+            .map_err(|e| CodecError::Decompression(e.to_string()))?;
+        read_prefix_into(dec, prefix.min(limit), out)
+    }
+}
+
+// ── Registry ─────────────────────────────────────────────────────────────────
+
+static DICTIONARIES: RwLock<Vec<Arc<Dictionary>>> = RwLock::new(Vec::new());
+
+/// Make `dict` available to every reader in the process.  Registering a
+/// dictionary twice is a no-op: equal UUIDs mean equal bytes.
+pub fn register_dictionary(dict: Arc<Dictionary>) {
+    let mut registry = DICTIONARIES.write().unwrap_or_else(|e| e.into_inner());
+    if !registry.iter().any(|d| d.uuid == dict.uuid) {
+        registry.push(dict);
+    }
+}
+
+/// The registered dictionary with this UUID, if any.
+pub fn registered_dictionary(uuid: &[u8; 16]) -> Option<Arc<Dictionary>> {
+    DICTIONARIES.read().unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|d| d.uuid == *uuid)
+        .cloned()
+}
@@ -13,10 +13,24 @@
 //! # Endianness
 //! All codec IDs on disk are the raw 16 bytes of the UUID in little-endian
 //! field order (RFC 4122 §4.1.2 wire format).  This is non-negotiable.
+//!
+//! # Display names
+//! Codecs this build does not know can be given names for diagnostics; see
+//! [`codec_display_name`].
+//!
+//! # Dictionaries
+//! A trained Zstd [`Dictionary`] shared by many archives is addressed like
+//! a codec, by a UUID derived from its bytes; see [`dictionary`](self::dictionary).
 
 use std::io::{self, Read, Write};
 use thiserror::Error;
 
+mod dictionary;
+mod names;
+pub use dictionary::{register_dictionary, registered_dictionary, Dictionary};
+pub use names::{codec_display_name, codec_name, load_codec_names, register_codec_name,
+                register_name_resolver, CodecNameResolver};
+
 // ── Frozen codec UUIDs ──────────────────────────────────────────────────────
 //
 // These values are permanent.  A UUID is NEVER reused, even if a codec is
@@ -70,7 +84,7 @@ pub const SHORT_LZMA:   ShortId = ShortId(4);
 
 /// Runtime codec discriminant.  Carries both the frozen UUID and an optional
 /// in-process short ID for fast dispatch.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CodecId {
     None,
     Zstd,
@@ -141,6 +155,30 @@ impl CodecId {
         }
     }
 
+    /// Representative levels from fastest to strongest, for codecs whose
+    /// output depends on the level; empty for codecs that ignore it.
+    pub fn level_sweep(self) -> &'static [i32] {
+        match self {
+            CodecId::Zstd   => &[1, 3, 9, 19],
+            CodecId::Brotli => &[1, 5, 9, 11],
+            _               => &[],
+        }
+    }
+
+    /// Chunk size that suits the codec when none is given: LZMA and
+    /// Brotli find more matches in longer inputs, LZ4 is picked for speed
+    /// and gains little from them, and the rest use
+    /// [`DEFAULT_CHUNK_SIZE`](crate::io_stream::DEFAULT_CHUNK_SIZE).
+    pub fn default_chunk_size(self) -> usize {
+        const MIB: usize = 1024 * 1024;
+        match self {
+            CodecId::Lzma   => 16 * MIB,
+            CodecId::Brotli => 8 * MIB,
+            CodecId::Lz4    => MIB,
+            _               => crate::io_stream::DEFAULT_CHUNK_SIZE,
+        }
+    }
+
     /// Format the codec UUID as a hyphenated string (diagnostics only).
     pub fn uuid_str(self) -> String {
         uuid_to_string(&self.uuid())
@@ -171,10 +209,20 @@ pub enum CodecError {
     Decompression(String),
     #[error("Encryption error: {0}")]
     Encryption(String),
+    /// Decryption failed while decoding a block (wrong key, missing key,
+    /// or tampered ciphertext).
+    #[error(transparent)]
+    Crypto(#[from] crate::crypto::CryptoError),
+    #[error(transparent)]
+    LimitExceeded(#[from] crate::limits::LimitExceeded),
     /// Emitted when a required codec UUID is not available in this build.
     /// The UUID is formatted for display; decoding MUST NOT continue.
     #[error("Required codec not available (UUID {uuid}) — cannot decode without it")]
     UnavailableCodec { uuid: String },
+    /// A block's plaintext (`orig_size`) or on-disk payload (`comp_size`)
+    /// would not fit the header's 32-bit size field.
+    #[error("Block {field} of {size} bytes exceeds the 4 GiB block limit")]
+    BlockTooLarge { field: &'static str, size: u64 },
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
 }
@@ -185,6 +233,92 @@ pub trait Codec: Send + Sync {
     fn codec_id(&self) -> CodecId;
     fn compress(&self, data: &[u8], level: i32) -> Result<Vec<u8>, CodecError>;
     fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CodecError>;
+
+    /// Decompress, failing as soon as the output would exceed `limit` bytes.
+    ///
+    /// The default decodes fully and then checks; built-in codecs override
+    /// it to stop mid-stream so a decompression bomb is never buffered.
+    fn decompress_bounded(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, CodecError> {
+        let out = self.decompress(data)?;
+        if out.len() > limit {
+            return Err(overflow(limit));
+        }
+        Ok(out)
+    }
+
+    /// [`Codec::compress`] appending to `out`, so callers can reuse pooled
+    /// buffers.  Built-in codecs write in place; the default copies.
+    fn compress_into(&self, data: &[u8], level: i32, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        out.extend_from_slice(&self.compress(data, level)?);
+        Ok(())
+    }
+
+    /// [`Codec::decompress_bounded`] appending at most `limit` bytes to `out`.
+    fn decompress_bounded_into(&self, data: &[u8], limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        out.extend_from_slice(&self.decompress_bounded(data, limit)?);
+        Ok(())
+    }
+
+    /// Append the first `prefix` bytes of a stream that decodes to at most
+    /// `limit` (fewer if it is shorter).  The default decodes everything and
+    /// truncates; streaming codecs override it to stop once `prefix` bytes
+    /// are out, without checking the rest of the stream.
+    fn decompress_prefix_into(&self, data: &[u8], prefix: usize, limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        let start = out.len();
+        self.decompress_bounded_into(data, limit, out)?;
+        out.truncate(start + prefix);
+        Ok(())
+    }
+}
+
+fn overflow(limit: usize) -> CodecError {
+    CodecError::Decompression(format!("output exceeds declared size of {limit} bytes"))
+}
+
+/// Drain `r` onto the end of `out`, reading at most `limit + 1` bytes.
+fn read_bounded_into<R: Read>(r: R, limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+    let n = r.take(limit as u64 + 1)
+        .read_to_end(out)
+        .map_err(|e| CodecError::Decompression(e.to_string()))?;
+    if n > limit {
+        return Err(overflow(limit));
+    }
+    Ok(())
+}
+
+/// Drain the first `prefix` bytes of `r` onto the end of `out`.
+fn read_prefix_into<R: Read>(r: R, prefix: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+    r.take(prefix as u64)
+        .read_to_end(out)
+        .map_err(|e| CodecError::Decompression(e.to_string()))?;
+    Ok(())
+}
+
+/// Run a `*_into` method against a fresh Vec.
+fn collect(f: impl FnOnce(&mut Vec<u8>) -> Result<(), CodecError>) -> Result<Vec<u8>, CodecError> {
+    let mut out = Vec::new();
+    f(&mut out)?;
+    Ok(out)
+}
+
+/// `Write` sink that refuses to append more than `limit` bytes (for
+/// push-style decoders such as `lzma_rs`).
+struct BoundedSink<'a> {
+    buf:     &'a mut Vec<u8>,
+    written: usize,
+    limit:   usize,
+}
+
+impl Write for BoundedSink<'_> {
+    fn write(&mut self, b: &[u8]) -> io::Result<usize> {
+        if self.written + b.len() > self.limit {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "decoder output exceeds limit"));
+        }
+        self.buf.extend_from_slice(b);
+        self.written += b.len();
+        Ok(b.len())
+    }
+    fn flush(&mut self) -> io::Result<()> { Ok(()) }
 }
 
 // ── Built-in codec implementations ──────────────────────────────────────────
@@ -194,6 +328,23 @@ impl Codec for NoneCodec {
     fn codec_id(&self) -> CodecId { CodecId::None }
     fn compress(&self, data: &[u8], _: i32) -> Result<Vec<u8>, CodecError> { Ok(data.to_vec()) }
     fn decompress(&self, data: &[u8])        -> Result<Vec<u8>, CodecError> { Ok(data.to_vec()) }
+    fn decompress_bounded(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, CodecError> {
+        collect(|out| self.decompress_bounded_into(data, limit, out))
+    }
+    fn compress_into(&self, data: &[u8], _: i32, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        out.extend_from_slice(data);
+        Ok(())
+    }
+    fn decompress_bounded_into(&self, data: &[u8], limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        if data.len() > limit { return Err(overflow(limit)); }
+        out.extend_from_slice(data);
+        Ok(())
+    }
+    fn decompress_prefix_into(&self, data: &[u8], prefix: usize, limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        if data.len() > limit { return Err(overflow(limit)); }
+        out.extend_from_slice(&data[..prefix.min(data.len())]);
+        Ok(())
+    }
 }
 
 pub struct ZstdCodec;
@@ -205,6 +356,23 @@ impl Codec for ZstdCodec {
     fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CodecError> {
         zstd::decode_all(data).map_err(|e| CodecError::Decompression(e.to_string()))
     }
+    fn decompress_bounded(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, CodecError> {
+        collect(|out| self.decompress_bounded_into(data, limit, out))
+    }
+    fn compress_into(&self, data: &[u8], level: i32, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        zstd::stream::copy_encode(data, out, level)
+            .map_err(|e| CodecError::Compression(e.to_string()))
+    }
+    fn decompress_bounded_into(&self, data: &[u8], limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        let dec = zstd::stream::read::Decoder::new(data)
+            .map_err(|e| CodecError::Decompression(e.to_string()))?;
+        read_bounded_into(dec, limit, out)
+    }
+    fn decompress_prefix_into(&self, data: &[u8], prefix: usize, limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        let dec = zstd::stream::read::Decoder::new(data)
+            .map_err(|e| CodecError::Decompression(e.to_string()))?;
+        read_prefix_into(dec, prefix.min(limit), out)
+    }
 }
 
 pub struct Lz4Codec;
@@ -217,19 +385,39 @@ impl Codec for Lz4Codec {
         lz4_flex::decompress_size_prepended(data)
             .map_err(|e| CodecError::Decompression(e.to_string()))
     }
+    fn decompress_bounded(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, CodecError> {
+        collect(|out| self.decompress_bounded_into(data, limit, out))
+    }
+    /// Same layout as `compress_prepend_size`: LE u32 length, then the block.
+    fn compress_into(&self, data: &[u8], _: i32, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        let start = out.len();
+        out.resize(start + lz4_flex::block::get_maximum_output_size(data.len()), 0);
+        let n = lz4_flex::block::compress_into(data, &mut out[start..])
+            .map_err(|e| CodecError::Compression(e.to_string()))?;
+        out.truncate(start + n);
+        Ok(())
+    }
+    fn decompress_bounded_into(&self, data: &[u8], limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        // The size prefix is what gets allocated; vet it first.
+        let declared = data.get(..4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()) as usize)
+            .ok_or_else(|| CodecError::Decompression("missing LZ4 size prefix".into()))?;
+        if declared > limit { return Err(overflow(limit)); }
+        let start = out.len();
+        out.resize(start + declared, 0);
+        let n = lz4_flex::block::decompress_into(&data[4..], &mut out[start..])
+            .map_err(|e| CodecError::Decompression(e.to_string()))?;
+        out.truncate(start + n);
+        Ok(())
+    }
 }
 
 pub struct BrotliCodec;
 impl Codec for BrotliCodec {
     fn codec_id(&self) -> CodecId { CodecId::Brotli }
     fn compress(&self, data: &[u8], level: i32) -> Result<Vec<u8>, CodecError> {
-        let quality = level.clamp(0, 11) as u32;
-        let mut out = Vec::new();
-        {
-            let mut w = brotli::CompressorWriter::new(&mut out, 4096, quality, 22);
-            w.write_all(data).map_err(|e| CodecError::Compression(e.to_string()))?;
-        }
-        Ok(out)
+        collect(|out| self.compress_into(data, level, out))
     }
     fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CodecError> {
         let mut out = Vec::new();
@@ -238,16 +426,28 @@ impl Codec for BrotliCodec {
             .map_err(|e| CodecError::Decompression(e.to_string()))?;
         Ok(out)
     }
+    fn decompress_bounded(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, CodecError> {
+        collect(|out| self.decompress_bounded_into(data, limit, out))
+    }
+    fn compress_into(&self, data: &[u8], level: i32, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        let quality = level.clamp(0, 11) as u32;
+        let mut w = brotli::CompressorWriter::new(out, 4096, quality, 22);
+        w.write_all(data).map_err(|e| CodecError::Compression(e.to_string()))?;
+        w.flush().map_err(|e| CodecError::Compression(e.to_string()))
+    }
+    fn decompress_bounded_into(&self, data: &[u8], limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        read_bounded_into(brotli::Decompressor::new(data, 4096), limit, out)
+    }
+    fn decompress_prefix_into(&self, data: &[u8], prefix: usize, limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        read_prefix_into(brotli::Decompressor::new(data, 4096), prefix.min(limit), out)
+    }
 }
 
 pub struct LzmaCodec;
 impl Codec for LzmaCodec {
     fn codec_id(&self) -> CodecId { CodecId::Lzma }
-    fn compress(&self, data: &[u8], _: i32) -> Result<Vec<u8>, CodecError> {
-        let mut out = Vec::new();
-        lzma_rs::lzma_compress(&mut std::io::Cursor::new(data), &mut out)
-            .map_err(|e| CodecError::Compression(e.to_string()))?;
-        Ok(out)
+    fn compress(&self, data: &[u8], level: i32) -> Result<Vec<u8>, CodecError> {
+        collect(|out| self.compress_into(data, level, out))
     }
     fn decompress(&self, data: &[u8]) -> Result<Vec<u8>, CodecError> {
         let mut out = Vec::new();
@@ -255,10 +455,125 @@ impl Codec for LzmaCodec {
             .map_err(|e| CodecError::Decompression(e.to_string()))?;
         Ok(out)
     }
+    fn decompress_bounded(&self, data: &[u8], limit: usize) -> Result<Vec<u8>, CodecError> {
+        collect(|out| self.decompress_bounded_into(data, limit, out))
+    }
+    fn compress_into(&self, data: &[u8], _: i32, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        lzma_rs::lzma_compress(&mut std::io::Cursor::new(data), out)
+            .map_err(|e| CodecError::Compression(e.to_string()))
+    }
+    fn decompress_bounded_into(&self, data: &[u8], limit: usize, out: &mut Vec<u8>) -> Result<(), CodecError> {
+        let mut sink = BoundedSink { buf: out, written: 0, limit };
+        lzma_rs::lzma_decompress(&mut std::io::Cursor::new(data), &mut sink)
+            .map_err(|e| CodecError::Decompression(e.to_string()))
+    }
+}
+
+// ── Decoder windows ──────────────────────────────────────────────────────────
+
+/// The history window a Brotli or LZMA stream asks its decoder to keep, in
+/// bytes, read from the stream header; `None` for other codecs or a header
+/// too short to parse.  Brotli windows reach 16 MiB (1 GiB with the
+/// large-window extension) and LZMA dictionaries 4 GiB, allocated per
+/// block, so [`Limits`](crate::limits::Limits) caps them before decoding.
+pub fn declared_window(id: CodecId, data: &[u8]) -> Option<u64> {
+    match id {
+        CodecId::Brotli => brotli_window_bits(data).map(|bits| 1u64 << bits),
+        // `.lzma` header: properties byte, then the dictionary size (LE u32).
+        // Decoders round sizes below 4 KiB up to 4 KiB.
+        CodecId::Lzma   => data.get(1..5)
+            .map(|b| u64::from(u32::from_le_bytes(b.try_into().unwrap())).max(0x1000)),
+        _               => None,
+    }
+}
+
+/// WBITS from the first bits of a Brotli stream (RFC 7932 §9.1, plus the
+/// large-window form `0x11` followed by six bits of WBITS).
+fn brotli_window_bits(data: &[u8]) -> Option<u32> {
+    let b0 = u32::from(*data.first()?);
+    if b0 & 1 == 0 {
+        return Some(16);
+    }
+    match (b0 >> 1) & 7 {
+        0 => {}
+        n => return Some(17 + n),
+    }
+    match (b0 >> 4) & 7 {
+        0 => Some(17),
+        1 => data.get(1).map(|&b1| u32::from(b1) & 0x3F),
+        n => Some(8 + n),
+    }
 }
 
 // ── Factory ──────────────────────────────────────────────────────────────────
 
+/// Where a codec UUID is supplied from at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodecSource {
+    /// Compiled into this build.
+    BuiltIn(CodecId),
+    /// Registered through [`crate::plugin::register`].
+    Plugin,
+    /// Zstd with a dictionary registered through [`register_dictionary`].
+    Dictionary,
+}
+
+/// Resolve a UUID through the runtime registry: built-in codecs first, then
+/// registered dictionaries, then registered plugins.  `None` means no
+/// decoder is available.
+pub fn codec_source(uuid: &[u8; 16]) -> Option<CodecSource> {
+    match CodecId::from_uuid(uuid) {
+        Some(id) => Some(CodecSource::BuiltIn(id)),
+        None if registered_dictionary(uuid).is_some() => Some(CodecSource::Dictionary),
+        None     => crate::plugin::registered(uuid).map(|_| CodecSource::Plugin),
+    }
+}
+
+/// Decompress `data` with whichever codec [`codec_source`] resolves `uuid`
+/// to, appending at most `limit` bytes to `out`.
+pub fn decompress_by_uuid_into(
+    uuid:  &[u8; 16],
+    data:  &[u8],
+    limit: usize,
+    out:   &mut Vec<u8>,
+) -> Result<(), CodecError> {
+    if let Some(id) = CodecId::from_uuid(uuid) {
+        return get_codec(id)?.decompress_bounded_into(data, limit, out);
+    }
+    if let Some(dict) = registered_dictionary(uuid) {
+        return dict.decompress_bounded_into(data, limit, out);
+    }
+    let plugin = crate::plugin::registered(uuid)
+        .ok_or_else(|| CodecError::UnavailableCodec { uuid: uuid_to_string(uuid) })?;
+    let decoded = plugin.decompress(data, limit).map_err(CodecError::Decompression)?;
+    if decoded.len() > limit {
+        return Err(overflow(limit));
+    }
+    out.extend_from_slice(&decoded);
+    Ok(())
+}
+
+/// [`decompress_by_uuid_into`] keeping only the first `prefix` bytes; see
+/// [`Codec::decompress_prefix_into`].  Plugins always decode in full.
+pub fn decompress_prefix_by_uuid_into(
+    uuid:   &[u8; 16],
+    data:   &[u8],
+    prefix: usize,
+    limit:  usize,
+    out:    &mut Vec<u8>,
+) -> Result<(), CodecError> {
+    if let Some(id) = CodecId::from_uuid(uuid) {
+        return get_codec(id)?.decompress_prefix_into(data, prefix, limit, out);
+    }
+    if let Some(dict) = registered_dictionary(uuid) {
+        return dict.decompress_prefix_into(data, prefix, limit, out);
+    }
+    let start = out.len();
+    decompress_by_uuid_into(uuid, data, limit, out)?;
+    out.truncate(start + prefix);
+    Ok(())
+}
+
 /// Resolve a UUID to a built-in codec.
 ///
 /// Returns `Err(CodecError::UnavailableCodec)` if the UUID is not recognised.
@@ -0,0 +1,94 @@
+//! Display names for codec UUIDs.
+//!
+//! Built-in codecs have names of their own ([`CodecId::name`]).  Plugin and
+//! foreign codecs are only UUIDs on disk, so diagnostics would print bare
+//! hex.  This module lets a host name them:
+//!
+//! - [`register_codec_name`] — one UUID, e.g. from a plugin's metadata when
+//!   the host loads it;
+//! - [`load_codec_names`] — a local registry file of `UUID NAME` lines
+//!   (`#` starts a comment), which the CLI reads from `$SIXCY_CODEC_NAMES`;
+//! - [`register_name_resolver`] — any [`CodecNameResolver`], consulted
+//!   after the explicit names, in registration order.
+//!
+//! [`codec_display_name`] combines the name with where the codec comes
+//! from, e.g. `zstd-custom-v2 (plugin)`.  Names are display-only: identity
+//! is always the UUID.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::RwLock;
+
+use super::{codec_source, uuid_to_string, CodecId, CodecSource};
+
+/// Supplies display names for codec UUIDs; see the module docs.
+pub trait CodecNameResolver: Send + Sync {
+    fn name(&self, uuid: &[u8; 16]) -> Option<String>;
+}
+
+impl<F: Fn(&[u8; 16]) -> Option<String> + Send + Sync> CodecNameResolver for F {
+    fn name(&self, uuid: &[u8; 16]) -> Option<String> {
+        self(uuid)
+    }
+}
+
+static NAMES:     RwLock<BTreeMap<[u8; 16], String>>        = RwLock::new(BTreeMap::new());
+static RESOLVERS: RwLock<Vec<Box<dyn CodecNameResolver>>> = RwLock::new(Vec::new());
+
+/// Name `uuid` for display, replacing any earlier name for it.
+pub fn register_codec_name(uuid: [u8; 16], name: impl Into<String>) {
+    NAMES.write().unwrap_or_else(|e| e.into_inner()).insert(uuid, name.into());
+}
+
+/// Add a resolver consulted for UUIDs without a registered name.
+pub fn register_name_resolver(resolver: impl CodecNameResolver + 'static) {
+    RESOLVERS.write().unwrap_or_else(|e| e.into_inner()).push(Box::new(resolver));
+}
+
+/// Register every `UUID NAME` line of the file at `path` and return how
+/// many were read.  UUIDs are in the hyphenated display form
+/// [`uuid_to_string`] prints; blank lines and `#` comments are skipped.
+pub fn load_codec_names(path: &Path) -> io::Result<usize> {
+    let text = std::fs::read_to_string(path)?;
+    let mut count = 0;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() { continue; }
+        let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData,
+            format!("{}:{}: {what}", path.display(), i + 1));
+        let (uuid, name) = line.split_once(char::is_whitespace)
+            .ok_or_else(|| invalid("expected `UUID NAME`"))?;
+        let uuid = uuid::Uuid::parse_str(uuid).map_err(|e| invalid(&e.to_string()))?;
+        register_codec_name(uuid.to_bytes_le(), name.trim());
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// The name of `uuid`: the built-in codec's, else a registered name, else
+/// the first resolver's answer.
+pub fn codec_name(uuid: &[u8; 16]) -> Option<String> {
+    if let Some(id) = CodecId::from_uuid(uuid) {
+        return Some(id.name().to_owned());
+    }
+    if let Some(name) = NAMES.read().unwrap_or_else(|e| e.into_inner()).get(uuid) {
+        return Some(name.clone());
+    }
+    RESOLVERS.read().unwrap_or_else(|e| e.into_inner()).iter().find_map(|r| r.name(uuid))
+}
+
+/// `uuid` for diagnostics: `zstd`, `zstd-custom-v2 (plugin)`,
+/// `lzham (not installed)`, or the bare UUID when nothing names it.
+pub fn codec_display_name(uuid: &[u8; 16]) -> String {
+    let name = codec_name(uuid);
+    match (codec_source(uuid), name) {
+        (Some(CodecSource::BuiltIn(id)), _)         => id.name().to_owned(),
+        (Some(CodecSource::Plugin), Some(name))     => format!("{name} (plugin)"),
+        (Some(CodecSource::Plugin), None)           => format!("{} (plugin)", uuid_to_string(uuid)),
+        (Some(CodecSource::Dictionary), Some(name)) => format!("{name} (zstd dictionary)"),
+        (Some(CodecSource::Dictionary), None)       => format!("{} (zstd dictionary)", uuid_to_string(uuid)),
+        (None, Some(name))                          => format!("{name} (not installed)"),
+        (None, None)                                => uuid_to_string(uuid),
+    }
+}
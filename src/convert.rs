@@ -0,0 +1,100 @@
+//! Add or remove an archive's encryption without recompressing.
+//!
+//! [`encrypt`] and [`decrypt`] copy every block's compressed payload as is
+//! and only apply or strip the AES-256-GCM layer ([`reseal_block`]), so a
+//! conversion costs one read and one write of the archive plus the cipher,
+//! never a codec pass.  The INDEX is carried over with only block offsets
+//! rewritten, and the output keeps the source's archive UUID, so snapshots
+//! that reference it by UUID stay valid.  Snapshot refs into other archives
+//! (`BlockRef::archive_id`) are copied unchanged.
+//!
+//! The output is written beside `dest` and moved into place once complete
+//! ([`AtomicFile`]), so `dest` may be the source's own path.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+
+use crate::archive::Archive;
+use crate::atomic_file::AtomicFile;
+use crate::block::reseal_block;
+use crate::crypto::{derive_key, CryptoError};
+use crate::error::Result;
+use crate::io_stream::SixCyWriter;
+use crate::superblock::SB_FLAG_ENCRYPTED;
+
+/// Summary returned by [`encrypt`] and [`decrypt`].
+#[derive(Debug, Clone, Default)]
+pub struct ConvertReport {
+    pub files:          usize,
+    pub blocks:         usize,
+    /// Sum of on-disk payload bytes before and after.
+    pub payload_before: u64,
+    pub payload_after:  u64,
+}
+
+/// Write an encrypted copy of the unencrypted `src` to `dest`, keyed by
+/// `password` and the source's UUID.  Fails with `InvalidInput` if `src`
+/// is already encrypted.
+pub fn encrypt<P: AsRef<Path>>(src: &mut Archive, dest: P, password: &str) -> Result<ConvertReport> {
+    let reader = src.reader_mut()?;
+    if reader.superblock.flags & SB_FLAG_ENCRYPTED != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            "archive is already encrypted (use `optimize --new-password` to change its password)").into());
+    }
+    let key = derive_key(password, reader.superblock.archive_uuid.as_bytes())?;
+    convert(src, dest.as_ref(), Some(key))
+}
+
+/// Write an unencrypted copy of the encrypted `src` to `dest`.  `src` must
+/// have been opened with its password; fails with `InvalidInput` if it is
+/// not encrypted.
+pub fn decrypt<P: AsRef<Path>>(src: &mut Archive, dest: P) -> Result<ConvertReport> {
+    let reader = src.reader_mut()?;
+    if reader.superblock.flags & SB_FLAG_ENCRYPTED == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "archive is not encrypted").into());
+    }
+    if reader.decryption_key.is_none() {
+        return Err(CryptoError::MissingKey.into());
+    }
+    convert(src, dest.as_ref(), None)
+}
+
+/// Copy `src` to `dest` with every block resealed under `key`.
+fn convert(src: &mut Archive, dest: &Path, key: Option<[u8; 32]>) -> Result<ConvertReport> {
+    let reader = src.reader_mut()?;
+    let out    = AtomicFile::create(dest)?;
+
+    let mut writer = SixCyWriter::new(out.file().try_clone()?)?;
+    writer.superblock.archive_uuid = reader.superblock.archive_uuid;
+    writer.encryption_key          = key;
+
+    let mut index  = (*reader.index).clone();
+    let mut report = ConvertReport { files: index.records.len(), ..ConvertReport::default() };
+    // Chunks shared by several files and solid blocks shared by their
+    // members are copied once: source offset → new offset.
+    let mut copied: HashMap<u64, u64> = HashMap::new();
+
+    for br in index.block_refs_mut().filter(|br| !br.is_external()) {
+        let offset = match copied.get(&br.archive_offset) {
+            Some(&offset) => offset,
+            None => {
+                let (header, payload) = reader.read_block_at(br.archive_offset)?;
+                let (new_header, new_payload) = reseal_block(&header, &payload,
+                    reader.decryption_key.as_ref(), key.as_ref())?;
+                let offset = writer.write_raw_block(&new_header, &new_payload)?;
+                report.blocks         += 1;
+                report.payload_before += payload.len() as u64;
+                report.payload_after  += new_payload.len() as u64;
+                copied.insert(br.archive_offset, offset);
+                offset
+            }
+        };
+        br.archive_offset = offset;
+    }
+
+    writer.index = index;
+    writer.finalize()?;
+    out.persist()?;
+    Ok(report)
+}
@@ -0,0 +1,489 @@
+//! `no_std` core: superblock and block-header parsing, CRC32/BLAKE3
+//! verification, and the `None` codec (feature `core`, alloc only).
+//!
+//! This module is the single source of truth for the on-disk layouts in
+//! `superblock.rs` and `block.rs`; the `std` types there parse through it.
+//! It is what remains when the crate is built with
+//! `--no-default-features --features core`, so bootloaders and firmware
+//! updaters can walk an archive held in memory and read stored (codec
+//! `None`, unencrypted) payloads without an allocator-heavy runtime.
+//!
+//! ```ignore
+//! let sb = sixcy::core_reader::parse_superblock(&image[..SUPERBLOCK_SIZE])?;
+//! for block in sixcy::core_reader::blocks(&image) {
+//!     let (offset, header, payload) = block?;
+//!     if header.file_id == WANTED {
+//!         flash(header.file_offset, sixcy::core_reader::verify_stored(&header, payload)?);
+//!     }
+//! }
+//! ```
+//!
+//! The INDEX block is JSON, Zstd-compressed by default; callers locate
+//! payloads through the self-describing DATA block headers instead.  An
+//! archive packed with `PackOptions::index_codec = CodecId::None` stores
+//! the INDEX verbatim, so [`verify_stored`] returns its JSON for callers
+//! that bring a parser.
+
+use alloc::vec::Vec;
+use core::fmt;
+use crc32fast::Hasher;
+
+// ── Constants ─────────────────────────────────────────────────────────────────
+
+/// Superblock magic at offset 0.
+pub const MAGIC:              &[u8; 4] = b".6cy";
+pub const FORMAT_VERSION:     u32      = 3;
+pub const MIN_FORMAT_VERSION: u32      = 3;  // v1/v2 are not forward-compatible
+pub const SUPERBLOCK_SIZE:    usize    = 256;
+
+/// Superblock flag: a provenance trailer occupies the last
+/// `SUPERBLOCK_SIZE - PROVENANCE_OFFSET` bytes (see `superblock.rs`).
+pub const SB_FLAG_PROVENANCE: u32   = 0x0002;
+/// Offset of the provenance trailer within the superblock.
+pub const PROVENANCE_OFFSET:  usize = 176;
+/// Bytes reserved for the writer name in the provenance trailer.
+pub const WRITER_LEN:         usize = 60;
+
+/// Codecs that fit in the superblock without a continuation block.
+pub const MAX_INLINE_CODECS:  usize = (SUPERBLOCK_SIZE - 50) / 16;
+/// Superblock flag: the codec list continues in a CODECS block.
+pub const SB_FLAG_CODECS_CONTINUED: u32 = 0x0008;
+/// Offset of the continuation slot (LE u64 offset, LE u32 count, CRC32).
+pub const CODECS_CONTINUATION_OFFSET: usize = 148;
+/// Codecs kept inline when the list continues in a CODECS block.
+pub const MAX_INLINE_CODECS_CONTINUED: usize = 6;
+
+/// Superblock flag: a `feature_flags` slot precedes the provenance trailer.
+pub const SB_FLAG_FEATURES:   u32   = 0x0004;
+/// Offset of the `feature_flags` slot (LE u64 + CRC32) within the superblock.
+pub const FEATURES_OFFSET:    usize = 164;
+
+/// Feature bit: the INDEX block is encrypted.
+pub const FEATURE_ENCRYPTED_INDEX: u64 = 1 << 0;
+/// Feature bit: parity/repair blocks are present.
+pub const FEATURE_PARITY:          u64 = 1 << 1;
+/// Feature bit: blocks carry seek tables.
+pub const FEATURE_SEEK_TABLES:     u64 = 1 << 2;
+/// Feature bit: block headers use 64-bit sizes.
+pub const FEATURE_BLOCKS_64:       u64 = 1 << 3;
+/// Feature bit: some blocks are prefiltered ([`FLAG_FILTER_MASK`]).
+pub const FEATURE_PREFILTER:       u64 = 1 << 4;
+/// Feature bits this build can decode.  Any other set bit makes the
+/// archive unreadable here.  (Prefiltered blocks are never "stored", so
+/// [`verify_stored`] refuses them.)
+pub const SUPPORTED_FEATURES:      u64 = FEATURE_PREFILTER;
+
+/// Human-readable name of a single feature bit.
+pub fn feature_name(bit: u64) -> Option<&'static str> {
+    Some(match bit {
+        FEATURE_ENCRYPTED_INDEX => "encrypted-index",
+        FEATURE_PARITY          => "parity",
+        FEATURE_SEEK_TABLES     => "seek-tables",
+        FEATURE_BLOCKS_64       => "64-bit-blocks",
+        FEATURE_PREFILTER       => "prefilter",
+        _                       => return None,
+    })
+}
+
+/// On-disk magic for every block header.  LE u32.
+pub const BLOCK_MAGIC: u32 = 0x424C_434B;  // "BLCK"
+/// Current block header layout version.
+pub const BLOCK_HEADER_VERSION: u16 = 1;
+/// Fixed byte size of the block header (including the trailing header_crc32).
+pub const BLOCK_HEADER_SIZE: usize = 84;
+
+/// Block `flags` bit: payload is AES-256-GCM encrypted.
+pub const FLAG_ENCRYPTED: u16 = 0x0001;
+/// Block `flags` bits holding the prefilter code (0 = none); see
+/// `pipeline::Filter`.
+pub const FLAG_FILTER_MASK:  u16 = 0x0F00;
+/// Shift of the prefilter code within `flags`.
+pub const FLAG_FILTER_SHIFT: u16 = 8;
+/// Block type discriminant of the INDEX block.
+pub const BLOCK_TYPE_INDEX: u16 = 1;
+/// Block type discriminant of the CODECS continuation block.
+pub const BLOCK_TYPE_CODECS: u16 = 3;
+/// Block type discriminant of a META (application data) block.
+pub const BLOCK_TYPE_META: u16 = 4;
+
+/// UUID of the `None` codec (payload stored verbatim).
+pub const UUID_NONE: [u8; 16] = [0u8; 16];
+
+// ── Errors ────────────────────────────────────────────────────────────────────
+
+/// Parse or verification failure.  `Display` wording matches the `std` errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoreError {
+    /// Input shorter than the structure being parsed.
+    Truncated { needed: usize, available: usize },
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    /// `required_codec_count` would run past the superblock.
+    CodecCountOverflow,
+    SuperblockCrc32Mismatch,
+    ProvenanceCrc32Mismatch,
+    FeaturesCrc32Mismatch,
+    BlockCrc32Mismatch { expected: u32, stored: u32 },
+    InvalidBlockMagic(u32),
+    UnsupportedHeaderVersion(u16),
+    HeaderSizeTooSmall(u16),
+    UnknownBlockType(u16),
+    /// The payload needs a codec, key, or prefilter this core reader does
+    /// not provide.
+    NotStored,
+    ContentHashMismatch,
+}
+
+impl fmt::Display for CoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreError::Truncated { needed, available } =>
+                write!(f, "Truncated input: need {needed} bytes, have {available}"),
+            CoreError::InvalidMagic =>
+                write!(f, "Invalid magic number — not a .6cy archive"),
+            CoreError::UnsupportedVersion(v) =>
+                write!(f, "Unsupported format version {v} (minimum supported: {MIN_FORMAT_VERSION})"),
+            CoreError::CodecCountOverflow =>
+                write!(f, "required_codec_count overflows superblock"),
+            CoreError::SuperblockCrc32Mismatch =>
+                write!(f, "Superblock header_crc32 mismatch — file is corrupted"),
+            CoreError::ProvenanceCrc32Mismatch =>
+                write!(f, "Superblock provenance CRC32 mismatch — file is corrupted"),
+            CoreError::FeaturesCrc32Mismatch =>
+                write!(f, "Superblock feature_flags CRC32 mismatch — file is corrupted"),
+            CoreError::BlockCrc32Mismatch { expected, stored } =>
+                write!(f, "Block header CRC32 mismatch: expected {expected:#010x}, got {stored:#010x}"),
+            CoreError::InvalidBlockMagic(m) =>
+                write!(f, "Invalid block magic: expected {BLOCK_MAGIC:#010x}, got {m:#010x}"),
+            CoreError::UnsupportedHeaderVersion(v) =>
+                write!(f, "Unsupported block header version {v} (this build handles v{BLOCK_HEADER_VERSION})"),
+            CoreError::HeaderSizeTooSmall(s) =>
+                write!(f, "Block header_size {s} < minimum {BLOCK_HEADER_SIZE}"),
+            CoreError::UnknownBlockType(t) =>
+                write!(f, "Unknown block_type {t}"),
+            CoreError::NotStored =>
+                write!(f, "Block is compressed, encrypted, or filtered; the core reader only decodes stored blocks"),
+            CoreError::ContentHashMismatch =>
+                write!(f, "BLAKE3 content hash mismatch"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CoreError {}
+
+#[cfg(feature = "std")]
+impl From<CoreError> for std::io::Error {
+    fn from(e: CoreError) -> Self {
+        let kind = match e {
+            CoreError::Truncated { .. } => std::io::ErrorKind::UnexpectedEof,
+            _                           => std::io::ErrorKind::InvalidData,
+        };
+        std::io::Error::new(kind, e)
+    }
+}
+
+#[inline]
+fn le_u16(b: &[u8], at: usize) -> u16 { u16::from_le_bytes([b[at], b[at + 1]]) }
+#[inline]
+fn le_u32(b: &[u8], at: usize) -> u32 { u32::from_le_bytes(b[at..at + 4].try_into().unwrap()) }
+#[inline]
+fn le_u64(b: &[u8], at: usize) -> u64 { u64::from_le_bytes(b[at..at + 8].try_into().unwrap()) }
+
+fn need(buf: &[u8], n: usize) -> Result<(), CoreError> {
+    if buf.len() < n {
+        return Err(CoreError::Truncated { needed: n, available: buf.len() });
+    }
+    Ok(())
+}
+
+// ── Superblock ────────────────────────────────────────────────────────────────
+
+/// Superblock fields exactly as stored (see `superblock.rs` for the layout).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawSuperblock {
+    pub format_version:       u32,
+    pub archive_uuid:         [u8; 16],
+    pub flags:                u32,
+    pub index_offset:         u64,
+    pub index_size:           u64,
+    pub required_codec_uuids: Vec<[u8; 16]>,
+    /// Present when `SB_FLAG_CODECS_CONTINUED` is set: the rest of
+    /// `required_codec_uuids` is in the CODECS block at this offset.
+    pub codecs_continued:     Option<RawCodecContinuation>,
+    /// `FEATURE_*` bits; 0 unless `SB_FLAG_FEATURES` is set.
+    pub feature_flags:        u64,
+    /// Present when `SB_FLAG_PROVENANCE` is set.
+    pub provenance:           Option<RawProvenance>,
+}
+
+/// Location of the CODECS continuation block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawCodecContinuation {
+    /// Byte offset of the CODECS block header.
+    pub offset: u64,
+    /// UUIDs held by that block.
+    pub count:  u32,
+}
+
+/// Provenance trailer fields exactly as stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawProvenance {
+    /// Unix seconds; 0 in deterministic archives.
+    pub created_at:  i64,
+    pub modified_at: i64,
+    /// UTF-8 writer name and version, zero-padded.
+    pub writer:      [u8; WRITER_LEN],
+}
+
+/// Parse and validate (magic, version, CRC32) the first `SUPERBLOCK_SIZE`
+/// bytes of `buf`.  Codec availability is NOT checked here.
+pub fn parse_superblock(buf: &[u8]) -> Result<RawSuperblock, CoreError> {
+    let (raw, mut problems) = parse_superblock_lenient(buf)?;
+    match problems.is_empty() {
+        true  => Ok(raw),
+        false => Err(problems.swap_remove(0)),
+    }
+}
+
+/// Parse the first `SUPERBLOCK_SIZE` bytes of `buf` without stopping at the
+/// first problem.  Every check [`parse_superblock`] makes is run; failures
+/// are returned in the same order alongside best-effort field values (an
+/// overflowing codec count is clamped, a slot with a bad CRC reads as
+/// absent).  Only a short buffer is a hard error.
+pub fn parse_superblock_lenient(buf: &[u8]) -> Result<(RawSuperblock, Vec<CoreError>), CoreError> {
+    need(buf, SUPERBLOCK_SIZE)?;
+    let buf = &buf[..SUPERBLOCK_SIZE];
+    let mut problems = Vec::new();
+
+    if &buf[0..4] != MAGIC {
+        problems.push(CoreError::InvalidMagic);
+    }
+    let format_version = le_u32(buf, 4);
+    if format_version < MIN_FORMAT_VERSION {
+        problems.push(CoreError::UnsupportedVersion(format_version));
+    }
+
+    let mut codec_count = le_u16(buf, 44) as usize;
+    if 50 + codec_count * 16 > SUPERBLOCK_SIZE {
+        problems.push(CoreError::CodecCountOverflow);
+        codec_count = MAX_INLINE_CODECS;
+    }
+    let uuid_end = 46 + codec_count * 16;
+    let required_codec_uuids = buf[46..uuid_end]
+        .chunks_exact(16)
+        .map(|c| c.try_into().unwrap())
+        .collect();
+
+    // CRC32 covers buf[0..uuid_end].
+    let mut h = Hasher::new();
+    h.update(&buf[..uuid_end]);
+    if h.finalize() != le_u32(buf, uuid_end) {
+        problems.push(CoreError::SuperblockCrc32Mismatch);
+    }
+
+    let flags      = le_u32(buf, 24);
+    let provenance = if flags & SB_FLAG_PROVENANCE != 0 {
+        parse_provenance(&buf[PROVENANCE_OFFSET..]).map_err(|e| problems.push(e)).ok()
+    } else {
+        None
+    };
+    let codecs_continued = if flags & SB_FLAG_CODECS_CONTINUED != 0 {
+        let at = CODECS_CONTINUATION_OFFSET;
+        if uuid_end + 4 > at {
+            problems.push(CoreError::CodecCountOverflow);
+            None
+        } else if !crc_matches(&buf[at..at + 16]) {
+            problems.push(CoreError::SuperblockCrc32Mismatch);
+            None
+        } else {
+            Some(RawCodecContinuation { offset: le_u64(buf, at), count: le_u32(buf, at + 8) })
+        }
+    } else {
+        None
+    };
+    let feature_flags = if flags & SB_FLAG_FEATURES != 0 {
+        if uuid_end + 4 > FEATURES_OFFSET {
+            problems.push(CoreError::CodecCountOverflow);
+            0
+        } else if !crc_matches(&buf[FEATURES_OFFSET..FEATURES_OFFSET + 12]) {
+            problems.push(CoreError::FeaturesCrc32Mismatch);
+            0
+        } else {
+            le_u64(buf, FEATURES_OFFSET)
+        }
+    } else {
+        0
+    };
+
+    let raw = RawSuperblock {
+        format_version,
+        archive_uuid: buf[8..24].try_into().unwrap(),
+        flags,
+        index_offset: le_u64(buf, 28),
+        index_size:   le_u64(buf, 36),
+        required_codec_uuids,
+        codecs_continued,
+        feature_flags,
+        provenance,
+    };
+    Ok((raw, problems))
+}
+
+/// True if the last 4 bytes of `slot` are the CRC32 of the rest.
+fn crc_matches(slot: &[u8]) -> bool {
+    let at = slot.len() - 4;
+    let mut h = Hasher::new();
+    h.update(&slot[..at]);
+    h.finalize() == le_u32(slot, at)
+}
+
+impl RawSuperblock {
+    /// Feature bits set in this archive that [`SUPPORTED_FEATURES`] lacks.
+    pub fn unsupported_features(&self) -> u64 {
+        self.feature_flags & !SUPPORTED_FEATURES
+    }
+}
+
+/// Trailer layout: created_at i64, modified_at i64, writer, CRC32 of the
+/// preceding trailer bytes.
+fn parse_provenance(t: &[u8]) -> Result<RawProvenance, CoreError> {
+    let crc_at = 16 + WRITER_LEN;
+    if !crc_matches(&t[..crc_at + 4]) {
+        return Err(CoreError::ProvenanceCrc32Mismatch);
+    }
+    Ok(RawProvenance {
+        created_at:  le_u64(t, 0) as i64,
+        modified_at: le_u64(t, 8) as i64,
+        writer:      t[16..crc_at].try_into().unwrap(),
+    })
+}
+
+// ── Block header ──────────────────────────────────────────────────────────────
+
+/// Block header fields exactly as stored (see `block.rs` for the layout).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawBlockHeader {
+    pub header_version: u16,
+    pub block_type:     u16,
+    pub flags:          u16,
+    pub codec_uuid:     [u8; 16],
+    pub file_id:        u32,
+    pub file_offset:    u64,
+    pub orig_size:      u32,
+    pub comp_size:      u32,
+    pub content_hash:   [u8; 32],
+}
+
+impl RawBlockHeader {
+    #[inline] pub fn is_encrypted(&self) -> bool { self.flags & FLAG_ENCRYPTED != 0 }
+}
+
+/// Parse and validate (CRC32 first, then magic, version, size, type) the
+/// first `BLOCK_HEADER_SIZE` bytes of `buf`.
+pub fn parse_block_header(buf: &[u8]) -> Result<RawBlockHeader, CoreError> {
+    need(buf, BLOCK_HEADER_SIZE)?;
+
+    // 1. Header CRC32 — cheapest possible check.
+    let mut h = Hasher::new();
+    h.update(&buf[..80]);
+    let expected = h.finalize();
+    let stored   = le_u32(buf, 80);
+    if stored != expected {
+        return Err(CoreError::BlockCrc32Mismatch { expected, stored });
+    }
+
+    // 2. Magic.
+    let magic = le_u32(buf, 0);
+    if magic != BLOCK_MAGIC {
+        return Err(CoreError::InvalidBlockMagic(magic));
+    }
+
+    // 3. Header version.
+    let header_version = le_u16(buf, 4);
+    if header_version != BLOCK_HEADER_VERSION {
+        return Err(CoreError::UnsupportedHeaderVersion(header_version));
+    }
+
+    // 4. header_size lets future readers skip extensions we don't know.
+    let header_size = le_u16(buf, 6);
+    if (header_size as usize) < BLOCK_HEADER_SIZE {
+        return Err(CoreError::HeaderSizeTooSmall(header_size));
+    }
+
+    // 5. Block type: 0=Data 1=Index 2=Solid 3=Codecs.
+    let block_type = le_u16(buf, 8);
+    if block_type > BLOCK_TYPE_META {
+        return Err(CoreError::UnknownBlockType(block_type));
+    }
+
+    Ok(RawBlockHeader {
+        header_version,
+        block_type,
+        flags:        le_u16(buf, 10),
+        codec_uuid:   buf[12..28].try_into().unwrap(),
+        file_id:      le_u32(buf, 28),
+        file_offset:  le_u64(buf, 32),
+        orig_size:    le_u32(buf, 40),
+        comp_size:    le_u32(buf, 44),
+        content_hash: buf[48..80].try_into().unwrap(),
+    })
+}
+
+// ── Payload verification ──────────────────────────────────────────────────────
+
+/// True if BLAKE3(`data`) equals `expected`.
+pub fn verify_content(data: &[u8], expected: &[u8; 32]) -> bool {
+    blake3::hash(data).as_bytes() == expected
+}
+
+/// Decode a stored block (codec `None`, unencrypted): verify its BLAKE3 and
+/// return the payload, borrowed — no allocation.
+pub fn verify_stored<'a>(header: &RawBlockHeader, payload: &'a [u8]) -> Result<&'a [u8], CoreError> {
+    if header.is_encrypted() || header.codec_uuid != UUID_NONE || header.flags & FLAG_FILTER_MASK != 0 {
+        return Err(CoreError::NotStored);
+    }
+    if !verify_content(payload, &header.content_hash) {
+        return Err(CoreError::ContentHashMismatch);
+    }
+    Ok(payload)
+}
+
+// ── Block walk ────────────────────────────────────────────────────────────────
+
+/// Iterator over `(archive_offset, header, payload)` for every block in an
+/// in-memory archive, stopping after the INDEX block or at the first error.
+pub struct Blocks<'a> {
+    image: &'a [u8],
+    pos:   usize,
+    done:  bool,
+}
+
+/// Walk the blocks of `image` forward from `SUPERBLOCK_SIZE`.
+pub fn blocks(image: &[u8]) -> Blocks<'_> {
+    Blocks { image, pos: SUPERBLOCK_SIZE, done: false }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Result<(u64, RawBlockHeader, &'a [u8]), CoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.pos >= self.image.len() {
+            return None;
+        }
+        let at = self.pos;
+        let header = match parse_block_header(&self.image[at..]) {
+            Ok(h)  => h,
+            Err(e) => { self.done = true; return Some(Err(e)); }
+        };
+        let start = at + BLOCK_HEADER_SIZE;
+        let end   = start + header.comp_size as usize;
+        if end > self.image.len() {
+            self.done = true;
+            return Some(Err(CoreError::Truncated { needed: end, available: self.image.len() }));
+        }
+        self.pos  = end;
+        self.done = header.block_type == BLOCK_TYPE_INDEX;
+        Some(Ok((at as u64, header, &self.image[start..end])))
+    }
+}
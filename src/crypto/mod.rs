@@ -2,16 +2,21 @@
 //!
 //! Key derivation: Argon2id(password, salt=archive_uuid_bytes) → 32-byte key
 //! Encryption:     AES-256-GCM, nonce prepended to ciphertext
+//! Deterministic:  nonce = BLAKE3-keyed(subkey(key), plaintext)[..12]  (SIV-style)
 //!
 //! Encrypted payload layout: [ nonce (12 B) | ciphertext | GCM tag (16 B) ]
 
 use argon2::{Argon2, Algorithm, Version, Params};
-use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng as AeadOsRng};
+use aes_gcm::aead::{AeadCore, AeadInPlace, KeyInit, OsRng as AeadOsRng};
 use aes_gcm::Aes256Gcm;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// Byte length of the AES-GCM nonce prepended to every encrypted payload.
 pub const NONCE_LEN: usize = 12;
+/// Byte length of the GCM authentication tag appended to the ciphertext.
+pub const TAG_LEN:   usize = 16;
 
 #[derive(Error, Debug)]
 pub enum CryptoError {
@@ -25,6 +30,30 @@ pub enum CryptoError {
     TooShort,
     #[error("Block is encrypted but no decryption key was provided")]
     MissingKey,
+    /// The key does not open the archive's key-check value
+    /// ([`verify_key_check`]): wrong password or key, not damaged data.
+    #[error("Wrong password or key")]
+    WrongPassword,
+}
+
+/// Argon2id memory cost in KiB (64 MiB).
+pub const KDF_MEMORY_KIB: u32 = 64 * 1024;
+/// Argon2id passes over memory.
+pub const KDF_PASSES:     u32 = 3;
+/// Argon2id lanes.
+pub const KDF_LANES:      u32 = 1;
+
+/// Cost divisor of the calibration run behind [`estimate_derive_time`].
+const CALIBRATION_SCALE: u32 = 16;
+
+/// Duration of the last [`derive_key`] in this process, the best estimate
+/// of the next one.
+static LAST_DERIVE: Mutex<Option<Duration>> = Mutex::new(None);
+
+fn argon2(memory_kib: u32, passes: u32) -> Result<Argon2<'static>, CryptoError> {
+    let params = Params::new(memory_kib, passes, KDF_LANES, Some(32))
+        .map_err(|e| CryptoError::KeyDerivation(e.to_string()))?;
+    Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
 }
 
 /// Derive a 256-bit encryption key from a password and a salt using Argon2id.
@@ -32,45 +61,181 @@ pub enum CryptoError {
 /// `salt` should be the 16-byte archive UUID, giving each archive a unique key
 /// even when the same password is reused across archives.
 pub fn derive_key(password: &str, salt: &[u8]) -> Result<[u8; 32], CryptoError> {
-    // Use Argon2id with conservative parameters suitable for archive encryption.
-    let params = Params::new(64 * 1024, 3, 1, Some(32))
-        .map_err(|e| CryptoError::KeyDerivation(e.to_string()))?;
-    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+    let start = Instant::now();
     let mut key = [0u8; 32];
-    argon2
+    argon2(KDF_MEMORY_KIB, KDF_PASSES)?
         .hash_password_into(password.as_bytes(), salt, &mut key)
         .map_err(|e: argon2::Error| CryptoError::KeyDerivation(e.to_string()))?;
+    *LAST_DERIVE.lock().unwrap_or_else(|e| e.into_inner()) = Some(start.elapsed());
     Ok(key)
 }
 
+/// How long [`derive_key`] should take on this machine: the duration of
+/// the last derivation in this process, or else a run at 1/16 of the
+/// memory cost and a single pass, scaled up (Argon2 time is linear in
+/// memory × passes).  The calibration run takes a few tens of ms.
+pub fn estimate_derive_time() -> Result<Duration, CryptoError> {
+    if let Some(last) = *LAST_DERIVE.lock().unwrap_or_else(|e| e.into_inner()) {
+        return Ok(last);
+    }
+    let start = Instant::now();
+    let mut key = [0u8; 32];
+    argon2(KDF_MEMORY_KIB / CALIBRATION_SCALE, 1)?
+        .hash_password_into(b"calibration", &[0u8; 16], &mut key)
+        .map_err(|e: argon2::Error| CryptoError::KeyDerivation(e.to_string()))?;
+    Ok(start.elapsed() * CALIBRATION_SCALE * KDF_PASSES)
+}
+
+/// A heartbeat from [`derive_key_with_progress`].
+#[derive(Debug, Clone, Copy)]
+pub struct KdfProgress {
+    pub elapsed:  Duration,
+    /// From [`estimate_derive_time`]; the derivation may overrun it.
+    pub estimate: Duration,
+}
+
+impl KdfProgress {
+    /// Estimated fraction done, in `0.0..=0.99` until the key is ready.
+    pub fn fraction(&self) -> f64 {
+        if self.estimate.is_zero() { return 0.0; }
+        (self.elapsed.as_secs_f64() / self.estimate.as_secs_f64()).min(0.99)
+    }
+}
+
+/// [`derive_key`] on a worker thread, calling `on_tick` on the calling
+/// thread every `interval` until the key is ready — so a GUI can animate
+/// instead of freezing for the seconds Argon2id takes on slow hardware.
+/// Argon2 reports nothing itself; progress is elapsed time against
+/// [`estimate_derive_time`].
+pub fn derive_key_with_progress(
+    password: &str,
+    salt:     &[u8],
+    interval: Duration,
+    mut on_tick: impl FnMut(KdfProgress),
+) -> Result<[u8; 32], CryptoError> {
+    let estimate = estimate_derive_time()?;
+    let start    = Instant::now();
+    std::thread::scope(|scope| {
+        let (tx, rx) = mpsc::channel();
+        scope.spawn(move || {
+            let _ = tx.send(derive_key(password, salt));
+        });
+        loop {
+            match rx.recv_timeout(interval) {
+                Ok(key) => return key,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    on_tick(KdfProgress { elapsed: start.elapsed(), estimate });
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    return Err(CryptoError::KeyDerivation("derivation thread panicked".into()));
+                }
+            }
+        }
+    })
+}
+
+/// Plaintext of the key-check value; see [`key_check`].
+pub const KEY_CHECK_PLAINTEXT: &[u8] = b"6cy key check v1";
+
+/// The key-check value the writer records in the INDEX of an encrypted
+/// archive: [`KEY_CHECK_PLAINTEXT`] sealed under `key` with a derived
+/// nonce, so it is deterministic.  It lets a reader tell a wrong key from
+/// damaged blocks before reading any.
+pub fn key_check(key: &[u8; 32]) -> Result<Vec<u8>, CryptoError> {
+    encrypt_deterministic(key, KEY_CHECK_PLAINTEXT)
+}
+
+/// [`CryptoError::WrongPassword`] unless `check` opens under `key` to
+/// [`KEY_CHECK_PLAINTEXT`].
+pub fn verify_key_check(key: &[u8; 32], check: &[u8]) -> Result<(), CryptoError> {
+    match decrypt(key, check) {
+        Ok(plain) if plain == KEY_CHECK_PLAINTEXT => Ok(()),
+        _                                         => Err(CryptoError::WrongPassword),
+    }
+}
+
 /// Encrypt `plaintext` with AES-256-GCM using a random nonce.
 ///
 /// Returns `nonce (12 B) || ciphertext || GCM-tag (16 B)`.
 pub fn encrypt(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
-    let cipher = Aes256Gcm::new_from_slice(key)
-        .map_err(|_| CryptoError::EncryptionFailed)?;
+    let mut out = Vec::with_capacity(NONCE_LEN + plaintext.len() + TAG_LEN);
+    encrypt_into(key, plaintext, &mut out)?;
+    Ok(out)
+}
+
+/// [`encrypt`] appending to `out`; the ciphertext is produced in place.
+pub fn encrypt_into(key: &[u8; 32], plaintext: &[u8], out: &mut Vec<u8>) -> Result<(), CryptoError> {
     let nonce = Aes256Gcm::generate_nonce(&mut AeadOsRng);
-    let ciphertext = cipher
-        .encrypt(&nonce, plaintext)
-        .map_err(|_| CryptoError::EncryptionFailed)?;
+    seal_into(key, &nonce, plaintext, out)
+}
 
-    let mut out = Vec::with_capacity(NONCE_LEN + ciphertext.len());
-    out.extend_from_slice(nonce.as_slice());
-    out.extend_from_slice(&ciphertext);
+/// Encrypt `plaintext` with AES-256-GCM using a nonce derived from the
+/// plaintext itself, for reproducible archives.
+///
+/// The nonce is a keyed BLAKE3 MAC of the plaintext under a subkey of `key`,
+/// so a nonce repeats only when the plaintext does — in which case the whole
+/// ciphertext repeats and nothing beyond equality is revealed (equality is
+/// already visible through CAS dedup).  Output layout matches [`encrypt`];
+/// [`decrypt`] handles both.
+pub fn encrypt_deterministic(key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut out = Vec::with_capacity(NONCE_LEN + plaintext.len() + TAG_LEN);
+    encrypt_deterministic_into(key, plaintext, &mut out)?;
     Ok(out)
 }
 
+/// [`encrypt_deterministic`] appending to `out`.
+pub fn encrypt_deterministic_into(key: &[u8; 32], plaintext: &[u8], out: &mut Vec<u8>) -> Result<(), CryptoError> {
+    let subkey = blake3::derive_key("sixcy deterministic nonce v1", key);
+    let mac    = blake3::keyed_hash(&subkey, plaintext);
+    seal_into(key, aes_gcm::Nonce::from_slice(&mac.as_bytes()[..NONCE_LEN]), plaintext, out)
+}
+
+fn seal_into(
+    key:       &[u8; 32],
+    nonce:     &aes_gcm::Nonce<<Aes256Gcm as AeadCore>::NonceSize>,
+    plaintext: &[u8],
+    out:       &mut Vec<u8>,
+) -> Result<(), CryptoError> {
+    let cipher = Aes256Gcm::new_from_slice(key)
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+    out.extend_from_slice(nonce.as_slice());
+    let start = out.len();
+    out.extend_from_slice(plaintext);
+    let tag = cipher
+        .encrypt_in_place_detached(nonce, b"", &mut out[start..])
+        .map_err(|_| CryptoError::EncryptionFailed)?;
+    out.extend_from_slice(&tag);
+    Ok(())
+}
+
 /// Decrypt an AES-256-GCM payload produced by [`encrypt`].
 ///
 /// Input must start with the 12-byte nonce followed by ciphertext + GCM tag.
 pub fn decrypt(key: &[u8; 32], data: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let mut out = Vec::with_capacity(data.len());
+    decrypt_into(key, data, &mut out)?;
+    Ok(out)
+}
+
+/// [`decrypt`] appending the plaintext to `out`.  On failure `out` is left
+/// as it was.
+pub fn decrypt_into(key: &[u8; 32], data: &[u8], out: &mut Vec<u8>) -> Result<(), CryptoError> {
     if data.len() < NONCE_LEN {
         return Err(CryptoError::TooShort);
     }
+    if data.len() < NONCE_LEN + TAG_LEN {
+        return Err(CryptoError::DecryptionFailed);
+    }
     let cipher = Aes256Gcm::new_from_slice(key)
         .map_err(|_| CryptoError::DecryptionFailed)?;
-    let nonce = aes_gcm::Nonce::from_slice(&data[..NONCE_LEN]);
+    let nonce       = aes_gcm::Nonce::from_slice(&data[..NONCE_LEN]);
+    let (body, tag) = data[NONCE_LEN..].split_at(data.len() - NONCE_LEN - TAG_LEN);
+    let start = out.len();
+    out.extend_from_slice(body);
     cipher
-        .decrypt(nonce, &data[NONCE_LEN..])
-        .map_err(|_| CryptoError::DecryptionFailed)
+        .decrypt_in_place_detached(nonce, b"", &mut out[start..], aes_gcm::Tag::from_slice(tag))
+        .map_err(|_| {
+            out.truncate(start);
+            CryptoError::DecryptionFailed
+        })
 }
@@ -0,0 +1,200 @@
+//! Dedup estimates behind `6cy dedup-stats`.
+//!
+//! The writer deduplicates fixed-size chunks: two files share a block only
+//! if equal bytes sit at equal multiples of the chunk size, so one inserted
+//! byte shifts every later chunk out of alignment.  Content-defined
+//! chunking (CDC) cuts where a rolling hash of the last bytes hits a
+//! pattern instead, and boundaries move with the content.
+//!
+//! [`DedupSimulator`] chunks its input both ways at several average sizes
+//! and counts the distinct chunks, so a user can see what each choice would
+//! save before packing terabytes.  Nothing is compressed or written.
+//!
+//! # CDC parameters
+//!
+//! A gear hash (`h = (h << 1) + GEAR[byte]`) is tested against a mask of
+//! its top `log2(avg / 2)` bits once a chunk holds `avg / 2` bytes, which
+//! puts the mean chunk near `avg`; chunks are cut at `4 × avg` regardless.
+//! Chunks are identified by the first 8 bytes of their BLAKE3 hash — enough
+//! for statistics, where a rare collision only skews a count.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+use crate::archive::{Archive, PackReport};
+use crate::error::Result;
+
+/// Average chunk sizes simulated when the caller gives none.
+pub const DEFAULT_SIZES: &[usize] = &[16 << 10, 64 << 10, 256 << 10, 1 << 20, 4 << 20];
+
+/// How a [`DedupResult`] split its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunking {
+    /// Fixed-size chunks, as the writer uses.
+    Fixed,
+    /// Content-defined chunks (module docs).
+    Cdc,
+}
+
+impl Chunking {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Fixed => "fixed",
+            Self::Cdc   => "cdc",
+        }
+    }
+}
+
+/// One chunking scheme at one average size.
+#[derive(Debug, Clone)]
+pub struct DedupResult {
+    pub chunking:      Chunking,
+    /// Chunk size (fixed) or target average (CDC), in bytes.
+    pub size:          usize,
+    pub chunks:        u64,
+    pub unique_chunks: u64,
+    pub input_bytes:   u64,
+    /// Bytes left after storing each distinct chunk once.
+    pub unique_bytes:  u64,
+}
+
+impl DedupResult {
+    /// Input size over deduplicated size (higher is better).
+    pub fn ratio(&self) -> f64 {
+        if self.unique_bytes == 0 { return 1.0; }
+        self.input_bytes as f64 / self.unique_bytes as f64
+    }
+
+    /// Fraction of the input that dedup removes.
+    pub fn saving(&self) -> f64 {
+        if self.input_bytes == 0 { return 0.0; }
+        1.0 - self.unique_bytes as f64 / self.input_bytes as f64
+    }
+
+    /// Mean chunk length actually produced.
+    pub fn mean_chunk(&self) -> u64 {
+        self.input_bytes.checked_div(self.chunks).unwrap_or(0)
+    }
+}
+
+struct Scheme {
+    result: DedupResult,
+    seen:   HashSet<u64>,
+}
+
+/// Accumulates chunk statistics over any number of files; see the module
+/// docs.  Each file is chunked on its own, as the writer does.
+pub struct DedupSimulator {
+    schemes: Vec<Scheme>,
+}
+
+impl DedupSimulator {
+    /// Simulate fixed and CDC chunking at each of `sizes` (bytes; empty
+    /// means [`DEFAULT_SIZES`]).
+    pub fn new(sizes: &[usize]) -> Self {
+        let sizes = if sizes.is_empty() { DEFAULT_SIZES } else { sizes };
+        let schemes = [Chunking::Fixed, Chunking::Cdc].into_iter()
+            .flat_map(|chunking| sizes.iter().map(move |&size| Scheme {
+                result: DedupResult {
+                    chunking,
+                    size: size.max(64),
+                    chunks: 0, unique_chunks: 0, input_bytes: 0, unique_bytes: 0,
+                },
+                seen: HashSet::new(),
+            }))
+            .collect();
+        Self { schemes }
+    }
+
+    /// Chunk one file's contents under every scheme.
+    pub fn add(&mut self, data: &[u8]) {
+        for scheme in &mut self.schemes {
+            let size = scheme.result.size;
+            match scheme.result.chunking {
+                Chunking::Fixed => data.chunks(size).for_each(|c| scheme.count(c)),
+                Chunking::Cdc   => cdc_chunks(data, size).for_each(|c| scheme.count(c)),
+            }
+        }
+    }
+
+    /// [`add`](Self::add) every regular file under `dir`; returns how many.
+    pub fn add_dir(&mut self, dir: &Path) -> io::Result<usize> {
+        let mut files = Vec::new();
+        crate::archive::walk_dir(dir, &Default::default(), None, &mut files, &mut PackReport::default())?;
+        for (_, path) in &files {
+            self.add(&std::fs::read(path)?);
+        }
+        Ok(files.len())
+    }
+
+    /// [`add`](Self::add) every file stored in `archive`; returns how many.
+    pub fn add_archive(&mut self, archive: &mut Archive) -> Result<usize> {
+        let ids: Vec<u32> = archive.entries().map(|e| e.id).collect();
+        for &id in &ids {
+            self.add(&archive.read_file_by_id(id)?);
+        }
+        Ok(ids.len())
+    }
+
+    /// One result per scheme: fixed sizes first, then CDC, each in the
+    /// order the sizes were given.
+    pub fn results(&self) -> Vec<DedupResult> {
+        self.schemes.iter().map(|s| s.result.clone()).collect()
+    }
+}
+
+impl Scheme {
+    fn count(&mut self, chunk: &[u8]) {
+        let hash = blake3::hash(chunk);
+        let key  = u64::from_le_bytes(hash.as_bytes()[..8].try_into().expect("8 bytes"));
+        let r    = &mut self.result;
+        r.chunks      += 1;
+        r.input_bytes += chunk.len() as u64;
+        if self.seen.insert(key) {
+            r.unique_chunks += 1;
+            r.unique_bytes  += chunk.len() as u64;
+        }
+    }
+}
+
+/// `data` split at content-defined boundaries averaging about `avg` bytes.
+pub fn cdc_chunks(data: &[u8], avg: usize) -> impl Iterator<Item = &[u8]> {
+    let min  = (avg / 2).max(1);
+    let max  = avg.saturating_mul(4).max(min);
+    let bits = min.ilog2().max(1);
+    let mask = !0u64 << (64 - bits);
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.is_empty() { return None; }
+        let mut cut = rest.len().min(max);
+        let mut h   = 0u64;
+        for (i, &b) in rest.iter().enumerate().take(cut).skip(min) {
+            h = (h << 1).wrapping_add(GEAR[b as usize]);
+            if h & mask == 0 {
+                cut = i + 1;
+                break;
+            }
+        }
+        let (chunk, tail) = rest.split_at(cut);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// Fixed pseudo-random gear table (SplitMix64 from seed 0), so boundaries
+/// are stable across runs and builds.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state = 0u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
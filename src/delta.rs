@@ -0,0 +1,206 @@
+//! Binary deltas between two versions of an archive.
+//!
+//! [`diff`] writes a *patch archive*: a regular `.6cy` file that carries the
+//! new version's UUID and complete INDEX, but stores only the blocks the old
+//! version does not already contain.  Every other block ref points into the
+//! old archive through `BlockRef::archive_id`, exactly like an incremental
+//! snapshot.  [`apply`] resolves those refs against the old archive and
+//! writes a standalone copy of the new version.
+//!
+//! The patch names its base by UUID in the app-data blob [`BASE_KEY`], and
+//! [`apply`] refuses an `old` archive with any other UUID rather than
+//! leaving its refs dangling in the output.
+//!
+//! Blocks are matched on their header `content_hash` and copied verbatim —
+//! nothing is recompressed on either side.  Encrypted archives are rejected
+//! because each archive's key is salted with its own UUID, so a block copied
+//! from the old archive could not be decrypted under the new one.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::archive::Archive;
+use crate::block::{BlockType, FILE_ID_SHARED};
+use crate::io_stream::{Section, SharedFile, SixCyReader, SixCyWriter};
+use crate::superblock::SB_FLAG_ENCRYPTED;
+
+/// App-data key under which a patch records the 16-byte UUID of its base.
+pub const BASE_KEY: &str = "6cy.delta.base";
+
+/// Summary of a patch produced by [`diff`].
+#[derive(Debug, Clone)]
+pub struct PatchArchive {
+    /// Where the patch was written.
+    pub path:          PathBuf,
+    /// UUID of the archive the patch applies to.
+    pub base_uuid:     Uuid,
+    /// UUID of the archive `apply` reproduces (also the patch's own UUID).
+    pub target_uuid:   Uuid,
+    /// Blocks stored in the patch.
+    pub blocks_copied: usize,
+    /// Blocks referenced from the base instead of stored.
+    pub blocks_reused: usize,
+    /// Size of the patch file in bytes.
+    pub patch_bytes:   u64,
+}
+
+fn reject_encrypted(r: &SixCyReader<Section<SharedFile>>) -> io::Result<()> {
+    if r.superblock.flags & SB_FLAG_ENCRYPTED != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            "delta patches cannot be built from encrypted archives"));
+    }
+    Ok(())
+}
+
+/// Compute a patch that turns `old` into `new` and write it to `out`.
+pub fn diff<P: AsRef<Path>>(old: &mut Archive, new: &mut Archive, out: P) -> io::Result<PatchArchive> {
+    let out = out.as_ref().to_owned();
+    let old_r = old.reader_mut()?;
+    reject_encrypted(old_r)?;
+    let base_uuid = old_r.superblock.archive_uuid;
+
+    // content_hash → offset for every physical block the old archive owns.
+    let mut old_offsets: Vec<u64> = old_r.index.block_refs()
+        .filter(|br| !br.is_external())
+        .map(|br| br.archive_offset)
+        .collect();
+    old_offsets.sort_unstable();
+    old_offsets.dedup();
+    let mut old_blocks: HashMap<[u8; 32], u64> = HashMap::with_capacity(old_offsets.len());
+    for offset in old_offsets {
+        let (header, _) = old_r.read_block_at(offset)?;
+        old_blocks.entry(header.content_hash).or_insert(offset);
+    }
+
+    let new_r = new.reader_mut()?;
+    reject_encrypted(new_r)?;
+    let target_uuid = new_r.superblock.archive_uuid;
+
+    let mut writer = SixCyWriter::new(File::create(&out)?)?;
+    writer.superblock.archive_uuid = target_uuid;
+
+    // new offset → Some(offset in patch) or None (reused from base, at old offset)
+    let mut placed: HashMap<u64, (Option<u64>, u64)> = HashMap::new();
+    let mut blocks_copied = 0usize;
+    let mut blocks_reused = 0usize;
+    let mut index = (*new_r.index).clone();
+
+    for br in index.block_refs_mut() {
+        if br.is_external() { continue; }
+        let slot = match placed.get(&br.archive_offset) {
+            Some(&slot) => slot,
+            None => {
+                let (header, payload) = new_r.read_block_at(br.archive_offset)?;
+                let slot = match old_blocks.get(&header.content_hash) {
+                    Some(&old_offset) => { blocks_reused += 1; (None, old_offset) }
+                    None => {
+                        blocks_copied += 1;
+                        (Some(writer.write_raw_block(&header, &payload)?), 0)
+                    }
+                };
+                placed.insert(br.archive_offset, slot);
+                slot
+            }
+        };
+        match slot {
+            (Some(patch_offset), _) => br.archive_offset = patch_offset,
+            (None, old_offset) => {
+                br.archive_offset = old_offset;
+                br.archive_id     = Some(*base_uuid.as_bytes());
+            }
+        }
+    }
+
+    writer.index = index;
+    writer.put_app_data(BASE_KEY, base_uuid.as_bytes().to_vec())?;
+    writer.finalize()?;
+    drop(writer);
+
+    Ok(PatchArchive {
+        patch_bytes: std::fs::metadata(&out)?.len(),
+        path: out,
+        base_uuid,
+        target_uuid,
+        blocks_copied,
+        blocks_reused,
+    })
+}
+
+/// Apply `patch` to `old`, writing the reconstructed new archive to `out`.
+///
+/// Refs into `old` are materialised by copying its blocks verbatim; DATA
+/// block headers are rewritten with the owning file id and offset so the
+/// result stays recoverable by a forward scan.  Refs to any *other* archive
+/// (the new version was itself incremental) are left untouched.
+///
+/// Fails with `ErrorKind::InvalidInput` if `old` is not the archive the
+/// patch was built against.  Patches written before [`BASE_KEY`] existed
+/// are accepted only if one of their foreign refs names `old`.
+pub fn apply<P: AsRef<Path>>(old: &mut Archive, patch: &mut Archive, out: P) -> io::Result<()> {
+    let old_r = old.reader_mut()?;
+    let base_id = *old_r.superblock.archive_uuid.as_bytes();
+    let patch_r = patch.reader_mut()?;
+
+    let mut index = (*patch_r.index).clone();
+    let matches_base = match patch_r.read_app_data(BASE_KEY)? {
+        Some(recorded) => recorded == base_id,
+        None => {
+            let mut foreign = index.block_refs().filter_map(|br| br.archive_id).peekable();
+            foreign.peek().is_none() || foreign.any(|id| id == base_id)
+        }
+    };
+    if !matches_base {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "patch does not apply to archive {}", old_r.superblock.archive_uuid)));
+    }
+    index.app_data.remove(BASE_KEY);
+
+    let mut writer = SixCyWriter::new(File::create(out)?)?;
+    writer.superblock.archive_uuid = patch_r.superblock.archive_uuid;
+
+    // (from_base, source offset) → (offset in output, orig_size)
+    let mut placed: HashMap<(bool, u64), (u64, u64)> = HashMap::new();
+
+    // Each record's refs, then the app-data refs, which no file owns.
+    let records  = index.records.iter_mut().map(|rec| (rec.id, rec.block_refs.iter_mut().collect::<Vec<_>>()));
+    let app_data = (FILE_ID_SHARED, index.app_data.values_mut().collect());
+    for (file_id, refs) in records.chain(std::iter::once(app_data)) {
+        let mut file_pos = 0u64;
+        for br in refs {
+            let from_base = match br.archive_id {
+                Some(id) if id == base_id => true,
+                Some(_)                   => continue,
+                None                      => false,
+            };
+            let key = (from_base, br.archive_offset);
+            if let Some(&(offset, orig_size)) = placed.get(&key) {
+                br.archive_offset = offset;
+                br.archive_id     = None;
+                file_pos += if br.is_solid_slice() { br.intra_length } else { orig_size };
+                continue;
+            }
+            let (mut header, payload) = if from_base {
+                old_r.read_block_at(br.archive_offset)?
+            } else {
+                patch_r.read_block_at(br.archive_offset)?
+            };
+            if from_base && header.block_type == BlockType::Data {
+                header.file_id     = file_id;
+                header.file_offset = file_pos;
+            }
+            let orig_size = header.orig_size as u64;
+            file_pos += if br.is_solid_slice() { br.intra_length } else { orig_size };
+            let offset = writer.write_raw_block(&header, &payload)?;
+            placed.insert(key, (offset, orig_size));
+            br.archive_offset = offset;
+            br.archive_id     = None;
+        }
+    }
+
+    writer.index = index;
+    Ok(writer.finalize()?)
+}
@@ -0,0 +1,87 @@
+//! Unified library error type.
+//!
+//! [`Archive`](crate::archive::Archive), [`SixCyReader`](crate::io_stream::SixCyReader),
+//! and [`SixCyWriter`](crate::io_stream::SixCyWriter) return [`SixcyError`],
+//! so embedders can match on the cause instead of parsing messages:
+//!
+//! ```no_run
+//! use sixcy::{Archive, SixcyError, CryptoError};
+//!
+//! match Archive::open_encrypted("secret.6cy", "guess").and_then(|mut a| a.read_file("x")) {
+//!     Err(SixcyError::Crypto(CryptoError::WrongPassword))    => eprintln!("wrong password"),
+//!     Err(SixcyError::Crypto(CryptoError::DecryptionFailed)) => eprintln!("damaged block"),
+//!     Err(SixcyError::Superblock(e)) => eprintln!("not a usable archive: {e}"),
+//!     Err(SixcyError::Index(e))      => eprintln!("corrupt index: {e}"),
+//!     Err(e)                         => eprintln!("{e}"),
+//!     Ok(data)                       => println!("{} bytes", data.len()),
+//! }
+//! ```
+//!
+//! `SixcyError` converts into `io::Error`, so code that returns
+//! `io::Result` keeps working with `?`.
+
+use std::io;
+use thiserror::Error;
+
+use crate::codec::CodecError;
+use crate::crypto::CryptoError;
+use crate::limits::LimitExceeded;
+use crate::superblock::SuperblockError;
+
+#[derive(Error, Debug)]
+pub enum SixcyError {
+    /// Bad magic, unsupported version, corrupt superblock, or missing codec.
+    #[error(transparent)]
+    Superblock(#[from] SuperblockError),
+    /// Compression, decompression, or content-hash failure in a block.
+    #[error(transparent)]
+    Codec(CodecError),
+    /// Wrong password, missing key, or tampered ciphertext.
+    #[error(transparent)]
+    Crypto(#[from] CryptoError),
+    /// The INDEX block could not be decoded.
+    #[error("Index error: {0}")]
+    Index(#[from] serde_json::Error),
+    /// A configured [`Limits`](crate::limits::Limits) bound was exceeded.
+    #[error(transparent)]
+    Limit(#[from] LimitExceeded),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// `Result` alias used throughout the library API.
+pub type Result<T> = std::result::Result<T, SixcyError>;
+
+impl From<CodecError> for SixcyError {
+    /// Lift crypto and limit failures out of the codec layer so they can be
+    /// matched directly.
+    fn from(e: CodecError) -> Self {
+        match e {
+            CodecError::Crypto(c)        => SixcyError::Crypto(c),
+            CodecError::LimitExceeded(l) => SixcyError::Limit(l),
+            CodecError::Io(io)           => SixcyError::Io(io),
+            other                        => SixcyError::Codec(other),
+        }
+    }
+}
+
+impl From<SixcyError> for io::Error {
+    fn from(e: SixcyError) -> Self {
+        match e {
+            SixcyError::Io(e)                              => e,
+            SixcyError::Superblock(SuperblockError::Io(e)) => e,
+            other => io::Error::new(io::ErrorKind::InvalidData, other),
+        }
+    }
+}
+
+impl SixcyError {
+    /// The `io::ErrorKind` this error maps to (`InvalidData` for format errors).
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            SixcyError::Io(e)                              => e.kind(),
+            SixcyError::Superblock(SuperblockError::Io(e)) => e.kind(),
+            _                                              => io::ErrorKind::InvalidData,
+        }
+    }
+}
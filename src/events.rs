@@ -0,0 +1,90 @@
+//! Structured events for applications embedding the library.
+//!
+//! A GUI or backup daemon that wants to show what a pack or extraction is
+//! doing sets an [`Observer`] on the writer
+//! ([`SixCyWriter::observer`](crate::io_stream::SixCyWriter::observer),
+//! [`PackOptions::observer`](crate::archive::PackOptions::observer)) or the
+//! reader ([`SixCyReader::set_observer`](crate::io_stream::SixCyReader::set_observer),
+//! [`Archive::set_observer`](crate::archive::Archive::set_observer)) and is
+//! called as things happen, instead of polling
+//! [`PerfStats`](crate::perf::PerfStats) or parsing log output.
+//!
+//! Every method has an empty default, so an observer implements only what
+//! it shows.  Calls are made on the thread driving the writer or reader,
+//! in order, and block it; an observer with slow work to do should hand
+//! the event to a channel.
+//!
+//! | Event              | Writer                                  | Reader                     |
+//! |--------------------|-----------------------------------------|----------------------------|
+//! | `on_file_start`    | a file begins                           | a file begins decoding     |
+//! | `on_block_written` | any block is appended, INDEX included   | —                          |
+//! | `on_dedup_hit`     | a chunk or solid member is not stored   | —                          |
+//! | `on_file_done`     | a file's record is complete             | a file is decoded          |
+//! | `on_error`         | an add or `finalize` fails              | a file fails to decode     |
+//!
+//! A file that auto-solid holds back is reported when its group is
+//! written, and one stored solid is done before its SOLID block is written.
+
+use crate::block::BlockType;
+use crate::error::SixcyError;
+
+/// Receives [`crate::events`] as they happen.
+pub trait Observer: std::fmt::Debug + Send + Sync {
+    fn on_file_start(&self, _file: &FileEvent) {}
+    fn on_block_written(&self, _block: &BlockEvent) {}
+    fn on_dedup_hit(&self, _hit: &DedupEvent) {}
+    fn on_file_done(&self, _file: &FileEvent) {}
+    fn on_error(&self, _error: &ErrorEvent) {}
+}
+
+/// A file starting or finishing.
+#[derive(Debug, Clone, Copy)]
+pub struct FileEvent<'a> {
+    /// Record ID in the INDEX.
+    pub id:   u32,
+    pub name: &'a str,
+    /// Uncompressed bytes: expected at the start of a write, read at its
+    /// end, and the record's size when reading.
+    pub size: u64,
+}
+
+/// A block appended to the archive.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockEvent {
+    pub block_type: BlockType,
+    pub offset:     u64,
+    /// Bytes before encoding.
+    pub orig_size:  u64,
+    /// Bytes on disk, header included.
+    pub stored:     u64,
+}
+
+/// Where deduplicated content was already stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupSource {
+    /// A DATA block of this archive.
+    Block,
+    /// A block of a base archive (incremental snapshot).
+    Base,
+    /// A member of a SOLID block, written or still buffered.
+    Solid,
+}
+
+/// Content referenced rather than stored again.
+#[derive(Debug, Clone, Copy)]
+pub struct DedupEvent {
+    /// Record ID of the file the content belongs to.
+    pub file_id:      u32,
+    pub content_hash: [u8; 32],
+    /// Uncompressed bytes not stored again.
+    pub bytes:        u64,
+    pub source:       DedupSource,
+}
+
+/// A failed call, reported before the error is returned to the caller.
+#[derive(Debug)]
+pub struct ErrorEvent<'a> {
+    /// The entry the failing call was adding or reading, if any.
+    pub name:  Option<&'a str>,
+    pub error: &'a SixcyError,
+}
@@ -0,0 +1,264 @@
+//! C FFI surface for the [`Archive`] API (feature `ffi`).
+//!
+//! The matching header is `include/sixcy.h`.  Build a shared library with:
+//!
+//! ```text
+//! cargo rustc --release --lib --features ffi --crate-type cdylib
+//! ```
+//!
+//! # Conventions
+//! - Handles are opaque `SixcyArchive *` created by [`sixcy_open`] or
+//!   [`sixcy_create`] and released by [`sixcy_close`].
+//! - Functions returning `int32_t` use the [`rc`] codes; on failure the
+//!   message is available from [`sixcy_last_error`] on the same thread.
+//! - Buffers returned by [`sixcy_read_file`] are owned by the caller and
+//!   MUST be released with [`sixcy_free_buffer`], never `free()`.
+//! - A handle is not thread-safe; serialise access or open one per thread.
+
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use crate::archive::{Archive, PackOptions};
+use crate::codec::CodecId;
+
+/// Return codes for FFI functions.
+pub mod rc {
+    pub const OK:          i32 = 0;
+    /// I/O, format, codec, or crypto failure — see `sixcy_last_error`.
+    pub const ERROR:       i32 = -1;
+    /// A required pointer was null or a string was not valid UTF-8.
+    pub const INVALID_ARG: i32 = -2;
+    /// The named entry does not exist.
+    pub const NOT_FOUND:   i32 = -3;
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(msg: impl ToString) {
+    let msg = CString::new(msg.to_string().replace('\0', " ")).expect("NULs removed");
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+}
+
+/// Opaque archive handle.
+pub struct SixcyArchive {
+    inner: Archive,
+    /// Names backing the pointers handed out by the last `sixcy_list` call.
+    names: Vec<CString>,
+}
+
+/// One entry as reported by [`sixcy_list`].
+#[repr(C)]
+pub struct SixcyFileInfo {
+    pub id:              u32,
+    /// NUL-terminated UTF-8; valid until the next `sixcy_list` or `sixcy_close`.
+    pub name:            *const c_char,
+    pub original_size:   u64,
+    pub compressed_size: u64,
+}
+
+/// # Safety
+/// `s` must be null or a valid NUL-terminated string.
+unsafe fn opt_str<'a>(s: *const c_char) -> Result<Option<&'a str>, i32> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(s).to_str().map(Some).map_err(|e| {
+        set_error(e);
+        rc::INVALID_ARG
+    })
+}
+
+/// # Safety
+/// `s` must be null or a valid NUL-terminated string.
+unsafe fn req_str<'a>(s: *const c_char, what: &str) -> Result<&'a str, i32> {
+    opt_str(s)?.ok_or_else(|| {
+        set_error(format!("{what} must not be null"));
+        rc::INVALID_ARG
+    })
+}
+
+fn into_handle(r: crate::error::Result<Archive>) -> *mut SixcyArchive {
+    match r {
+        Ok(inner) => Box::into_raw(Box::new(SixcyArchive { inner, names: Vec::new() })),
+        Err(e)    => { set_error(e); ptr::null_mut() }
+    }
+}
+
+fn status(r: crate::error::Result<()>) -> i32 {
+    match r {
+        Ok(())                                               => rc::OK,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => { set_error(e); rc::NOT_FOUND }
+        Err(e)                                               => { set_error(e); rc::ERROR }
+    }
+}
+
+/// Last error message on this thread, or null.  Valid until the next failing
+/// call on the same thread.
+#[no_mangle]
+pub extern "C" fn sixcy_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |s| s.as_ptr()))
+}
+
+/// Open an archive for reading.  `password` may be null.  Returns null on
+/// failure.
+///
+/// # Safety
+/// `path` and `password` must be null or valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn sixcy_open(path: *const c_char, password: *const c_char) -> *mut SixcyArchive {
+    let (path, password) = match (req_str(path, "path"), opt_str(password)) {
+        (Ok(p), Ok(pw)) => (p, pw),
+        _               => return ptr::null_mut(),
+    };
+    into_handle(match password {
+        Some(pw) => Archive::open_encrypted(path, pw),
+        None     => Archive::open(path),
+    })
+}
+
+/// Create an archive for writing.  `codec` may be null (zstd); `password`
+/// may be null (unencrypted).  Returns null on failure.
+///
+/// # Safety
+/// String arguments must be null or valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn sixcy_create(
+    path:     *const c_char,
+    codec:    *const c_char,
+    level:    i32,
+    password: *const c_char,
+) -> *mut SixcyArchive {
+    let (path, codec, password) = match (req_str(path, "path"), opt_str(codec), opt_str(password)) {
+        (Ok(p), Ok(c), Ok(pw)) => (p, c, pw),
+        _                      => return ptr::null_mut(),
+    };
+    let default_codec = match codec {
+        None => CodecId::Zstd,
+        Some(name) => match CodecId::from_name(name) {
+            Some(c) => c,
+            None    => { set_error(format!("Unknown codec '{name}'")); return ptr::null_mut(); }
+        },
+    };
+    let opts = PackOptions {
+        default_codec,
+        level,
+        password: password.map(str::to_owned),
+        ..PackOptions::default()
+    };
+    into_handle(Archive::create(path, opts))
+}
+
+/// Add a file to an archive opened with [`sixcy_create`].
+///
+/// # Safety
+/// `ar` must be a live handle; `name` a valid NUL-terminated string;
+/// `data[0..len]` readable (`data` may be null only when `len == 0`).
+#[no_mangle]
+pub unsafe extern "C" fn sixcy_add_file(
+    ar:   *mut SixcyArchive,
+    name: *const c_char,
+    data: *const u8,
+    len:  usize,
+) -> i32 {
+    let Some(ar) = ar.as_mut() else { set_error("archive handle is null"); return rc::INVALID_ARG };
+    let name = match req_str(name, "name") { Ok(n) => n, Err(rc) => return rc };
+    let data = if len == 0 { &[][..] } else if data.is_null() {
+        set_error("data is null");
+        return rc::INVALID_ARG;
+    } else {
+        std::slice::from_raw_parts(data, len)
+    };
+    status(ar.inner.add_file(name, data))
+}
+
+/// Write the INDEX and superblock.  Must be called once before `sixcy_close`
+/// on a created archive.
+///
+/// # Safety
+/// `ar` must be a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn sixcy_finalize(ar: *mut SixcyArchive) -> i32 {
+    let Some(ar) = ar.as_mut() else { set_error("archive handle is null"); return rc::INVALID_ARG };
+    status(ar.inner.finalize())
+}
+
+/// Fill `out[0..cap]` with entry descriptors and return the total entry
+/// count.  Call with `out = NULL, cap = 0` to size the buffer first.
+///
+/// # Safety
+/// `ar` must be a live handle; `out[0..cap]` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn sixcy_list(ar: *mut SixcyArchive, out: *mut SixcyFileInfo, cap: usize) -> usize {
+    let Some(ar) = ar.as_mut() else { set_error("archive handle is null"); return 0 };
+    let files = ar.inner.list();
+    ar.names = files.iter()
+        .map(|f| CString::new(f.name.replace('\0', "\u{FFFD}")).expect("NULs removed"))
+        .collect();
+    if !out.is_null() {
+        for (i, (f, name)) in files.iter().zip(&ar.names).take(cap).enumerate() {
+            out.add(i).write(SixcyFileInfo {
+                id:              f.id,
+                name:            name.as_ptr(),
+                original_size:   f.original_size,
+                compressed_size: f.compressed_size,
+            });
+        }
+    }
+    files.len()
+}
+
+/// Read a whole entry.  On success `*out` / `*out_len` receive a buffer that
+/// must be released with [`sixcy_free_buffer`].
+///
+/// # Safety
+/// `ar` must be a live handle; `name` a valid NUL-terminated string;
+/// `out` and `out_len` writable.
+#[no_mangle]
+pub unsafe extern "C" fn sixcy_read_file(
+    ar:      *mut SixcyArchive,
+    name:    *const c_char,
+    out:     *mut *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    let Some(ar) = ar.as_mut() else { set_error("archive handle is null"); return rc::INVALID_ARG };
+    if out.is_null() || out_len.is_null() {
+        set_error("out and out_len must not be null");
+        return rc::INVALID_ARG;
+    }
+    let name = match req_str(name, "name") { Ok(n) => n, Err(rc) => return rc };
+    match ar.inner.read_file(name) {
+        Ok(data) => {
+            let boxed = data.into_boxed_slice();
+            *out_len = boxed.len();
+            *out     = Box::into_raw(boxed) as *mut u8;
+            rc::OK
+        }
+        Err(e) => status(Err(e)),
+    }
+}
+
+/// Release a buffer returned by [`sixcy_read_file`].
+///
+/// # Safety
+/// `buf`/`len` must come from one successful `sixcy_read_file` call and not
+/// have been freed already.  Null is accepted and ignored.
+#[no_mangle]
+pub unsafe extern "C" fn sixcy_free_buffer(buf: *mut u8, len: usize) {
+    if !buf.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buf, len)));
+    }
+}
+
+/// Release a handle.  Does NOT finalize a created archive.  Null is ignored.
+///
+/// # Safety
+/// `ar` must be null or a handle not yet closed.
+#[no_mangle]
+pub unsafe extern "C" fn sixcy_close(ar: *mut SixcyArchive) {
+    if !ar.is_null() {
+        drop(Box::from_raw(ar));
+    }
+}
@@ -1,6 +1,19 @@
 //! File index — reconstructible by scanning blocks.
+//!
+//! # Versioning
+//!
+//! The INDEX carries an `index_version` ([`INDEX_VERSION`] when written by
+//! this build; indexes from before versioning omit it and read as `0`).
+//! Within one version, new fields are optional and safe to ignore: a
+//! reader skips keys it does not know, and records each one's path in
+//! [`FileIndex::unknown_fields`] so callers can report that the archive
+//! came from a newer writer.  A change older readers must not skip bumps
+//! `index_version`, and [`FileIndex::from_bytes`] refuses any version
+//! newer than its own.
 use serde::{Serialize, Deserialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::limits::{Limits, LimitExceeded};
+use crate::names::NamePolicy;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BlockRef {
@@ -10,11 +23,20 @@ pub struct BlockRef {
     pub intra_offset:   u64,
     #[serde(default)]
     pub intra_length:   u64,
+    /// UUID bytes of the archive that physically holds this block.
+    /// `None` means the block lives in the archive that owns this index;
+    /// incremental snapshots set it for blocks reused from a base archive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archive_id:     Option<[u8; 16]>,
 }
 
 impl BlockRef {
     #[inline]
     pub fn is_solid_slice(&self) -> bool { self.intra_length > 0 }
+
+    /// True if the block is stored in another archive of a snapshot chain.
+    #[inline]
+    pub fn is_external(&self) -> bool { self.archive_id.is_some() }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -24,9 +46,109 @@ pub struct FileIndexRecord {
     pub name:            String,
     pub block_refs:      Vec<BlockRef>,
     pub original_size:   u64,
+    /// Stored bytes charged to this file: each block it references counted
+    /// once, split evenly between the files that share it, with a solid
+    /// member charged its slice's share of the block.  Settled at
+    /// [`FileIndex::account_sizes`] when the writer finalizes.
     pub compressed_size: u64,
-    #[serde(default)]
+    /// Stored bytes no other file references — what removing this file
+    /// would free.  Absent in indexes written before it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unique_compressed_size: Option<u64>,
+    /// Serialized in key order so identical inputs give an identical INDEX.
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub metadata:        HashMap<String, String>,
+    /// Whole-file digest in a second algorithm, recorded at pack time when
+    /// the writer's `secondary_hash` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_hash:  Option<SecondaryHash>,
+    /// Exact bytes of a name that is not valid UTF-8, hex-encoded; `name`
+    /// then holds its lossy form.  See [`crate::names`].
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "hex_option")]
+    pub raw_name:        Option<Vec<u8>>,
+}
+
+/// Digest algorithms available for [`FileIndexRecord::secondary_hash`].
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace('-', "").as_str() {
+            "sha256" => Some(Self::Sha256),
+            "sha512" => Some(Self::Sha512),
+            _        => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+        }
+    }
+
+    pub fn digest(self, data: &[u8]) -> SecondaryHash {
+        use sha2::Digest;
+        let digest = match self {
+            Self::Sha256 => sha2::Sha256::digest(data).to_vec(),
+            Self::Sha512 => sha2::Sha512::digest(data).to_vec(),
+        };
+        SecondaryHash { algorithm: self, digest }
+    }
+
+    /// An incremental [`digest`](Self::digest), for data read in pieces.
+    pub fn hasher(self) -> SecondaryHasher {
+        use sha2::Digest;
+        match self {
+            Self::Sha256 => SecondaryHasher::Sha256(sha2::Sha256::new()),
+            Self::Sha512 => SecondaryHasher::Sha512(sha2::Sha512::new()),
+        }
+    }
+}
+
+/// Running state of a [`HashAlgorithm::hasher`].
+#[derive(Clone)]
+pub enum SecondaryHasher {
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+}
+
+impl SecondaryHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        use sha2::Digest;
+        match self {
+            Self::Sha256(h) => h.update(data),
+            Self::Sha512(h) => h.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> SecondaryHash {
+        use sha2::Digest;
+        match self {
+            Self::Sha256(h) => SecondaryHash { algorithm: HashAlgorithm::Sha256, digest: h.finalize().to_vec() },
+            Self::Sha512(h) => SecondaryHash { algorithm: HashAlgorithm::Sha512, digest: h.finalize().to_vec() },
+        }
+    }
+}
+
+/// A whole-file digest; `digest` is hex-encoded in the INDEX.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SecondaryHash {
+    pub algorithm: HashAlgorithm,
+    #[serde(with = "hex")]
+    pub digest:    Vec<u8>,
+}
+
+fn serialize_sorted<S: serde::Serializer>(
+    map: &HashMap<String, String>,
+    s:   S,
+) -> Result<S::Ok, S::Error> {
+    map.iter().collect::<std::collections::BTreeMap<_, _>>().serialize(s)
 }
 
 impl FileIndexRecord {
@@ -38,23 +160,141 @@ impl FileIndexRecord {
             block_refs: refs,
             original_size,
             compressed_size: 0,
+            unique_compressed_size: None,
             metadata: HashMap::new(),
+            secondary_hash: None,
+            raw_name: None,
+        }
+    }
+}
+
+/// The order to visit an index's records in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntryOrder {
+    /// As stored: record-ID order (see [`FileIndex::records`]).
+    #[default]
+    Index,
+    /// By name, bytewise; records with equal names stay in index order.
+    Name,
+}
+
+impl EntryOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "index" => Some(Self::Index),
+            "name"  => Some(Self::Name),
+            _       => None,
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+/// The INDEX layout this build writes and the newest it reads.
+pub const INDEX_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileIndex {
-    pub records:   Vec<FileIndexRecord>,
-    pub root_hash: [u8; 32],
+    /// `0` for indexes written before the field existed.
+    #[serde(default)]
+    pub index_version:  u32,
+    /// Ascending by record ID, which writers assign in the order files are
+    /// added — except that auto-solid files take theirs when their group
+    /// is written.  Listing and extraction keep this order, and indexes
+    /// rebuilt by scanning are sorted by ID, so it does not vary from run
+    /// to run.
+    pub records:        Vec<FileIndexRecord>,
+    pub root_hash:      [u8; 32],
+    /// [`crypto::key_check`](crate::crypto::key_check) of an encrypted
+    /// archive's key, hex-encoded; `None` for unencrypted archives and
+    /// those written before the field existed.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "hex_option")]
+    pub key_check:      Option<Vec<u8>>,
+    /// How the writer treated entry names; `None` for indexes written
+    /// before the field existed, whose names are as given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_policy:    Option<NamePolicy>,
+    /// Application blobs by key, each in a META block of its own, outside
+    /// the file namespace and `root_hash`.  Empty for indexes written
+    /// before the field existed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub app_data:       BTreeMap<String, BlockRef>,
+    /// Keys skipped while parsing, by path (`records[].block_refs[].key`),
+    /// with how many times each occurred.  Not serialized, so rewriting an
+    /// index drops them.
+    #[serde(skip)]
+    pub unknown_fields: BTreeMap<String, u64>,
+}
+
+impl Default for FileIndex {
+    fn default() -> Self {
+        Self {
+            index_version:  INDEX_VERSION,
+            records:        Vec::new(),
+            root_hash:      [0u8; 32],
+            key_check:      None,
+            name_policy:    None,
+            app_data:       BTreeMap::new(),
+            unknown_fields: BTreeMap::new(),
+        }
+    }
 }
 
 impl FileIndex {
     pub fn to_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
         serde_json::to_vec(self)
     }
+    /// Parse an INDEX, collecting unknown keys into
+    /// [`unknown_fields`](Self::unknown_fields).  Fails if `index_version`
+    /// is newer than [`INDEX_VERSION`].
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, serde_json::Error> {
-        serde_json::from_slice(bytes)
+        let mut unknown = BTreeMap::new();
+        let mut de = serde_json::Deserializer::from_slice(bytes);
+        let mut index: Self = serde_ignored::deserialize(&mut de, |path| {
+            *unknown.entry(field_pattern(&path)).or_insert(0) += 1;
+        })?;
+        de.end()?;
+        if index.index_version > INDEX_VERSION {
+            return Err(serde::de::Error::custom(format_args!(
+                "INDEX version {} is newer than supported version {INDEX_VERSION}",
+                index.index_version,
+            )));
+        }
+        index.unknown_fields = unknown;
+        Ok(index)
+    }
+    /// [`FileIndex::from_bytes`] bounded by `limits.max_index_size` (checked
+    /// before parsing) and `limits.max_records`.
+    pub fn from_bytes_limited(bytes: &[u8], limits: &Limits) -> crate::error::Result<Self> {
+        if bytes.len() as u64 > limits.max_index_size {
+            return Err(LimitExceeded {
+                what:  "INDEX size",
+                value: bytes.len() as u64,
+                limit: limits.max_index_size,
+            }.into());
+        }
+        let index = Self::from_bytes(bytes)?;
+        limits.check_records(index.records.len())?;
+        Ok(index)
+    }
+    /// Positions in [`records`](Self::records) in `order`.  `Index` order
+    /// costs nothing; `Name` sorts a list of positions up front.
+    pub fn record_order(&self, order: EntryOrder) -> impl Iterator<Item = usize> {
+        let mut sorted = Vec::new();
+        if order == EntryOrder::Name {
+            sorted.extend(0..self.records.len());
+            sorted.sort_by(|&a, &b| self.records[a].name.cmp(&self.records[b].name));
+        }
+        let stored = (order == EntryOrder::Index).then_some(0..self.records.len());
+        stored.into_iter().flatten().chain(sorted)
+    }
+    /// Every block ref in the INDEX: each record's in order, then each
+    /// app-data entry's.
+    pub fn block_refs(&self) -> impl Iterator<Item = &BlockRef> {
+        self.records.iter().flat_map(|r| &r.block_refs).chain(self.app_data.values())
+    }
+    /// [`block_refs`](Self::block_refs), mutably, for tools that move
+    /// blocks.
+    pub fn block_refs_mut(&mut self) -> impl Iterator<Item = &mut BlockRef> {
+        self.records.iter_mut().flat_map(|r| &mut r.block_refs).chain(self.app_data.values_mut())
     }
     pub fn compute_root_hash(&mut self) {
         let mut h = blake3::Hasher::new();
@@ -65,4 +305,79 @@ impl FileIndex {
         }
         self.root_hash = h.finalize().into();
     }
+
+    /// Recompute every record's `compressed_size` and
+    /// `unique_compressed_size` from `cost`, the stored bytes behind one
+    /// local block ref (a solid slice's share of its block).  A block piece
+    /// shared by `n` records charges each `1/n` of its cost and counts as
+    /// unique for none.  Refs into other archives cost nothing here; a
+    /// record with a ref `cost` does not know is left as it was.
+    pub fn account_sizes(&mut self, cost: impl Fn(&BlockRef) -> Option<u64>) {
+        let piece = |br: &BlockRef| (br.archive_offset, br.intra_offset, br.intra_length);
+        let pieces = |rec: &FileIndexRecord| -> HashSet<(u64, u64, u64)> {
+            rec.block_refs.iter().filter(|br| !br.is_external()).map(piece).collect()
+        };
+
+        let mut sharers: HashMap<(u64, u64, u64), u64> = HashMap::new();
+        for rec in &self.records {
+            for p in pieces(rec) {
+                *sharers.entry(p).or_insert(0) += 1;
+            }
+        }
+
+        for rec in &mut self.records {
+            let mut seen = HashSet::new();
+            let (mut share, mut unique) = (0u64, 0u64);
+            let settled = rec.block_refs.iter()
+                .filter(|br| !br.is_external() && seen.insert(piece(br)))
+                .all(|br| match cost(br) {
+                    Some(cost) => {
+                        let n = sharers[&piece(br)];
+                        share += cost / n;
+                        if n == 1 { unique += cost; }
+                        true
+                    }
+                    None => false,
+                });
+            if settled {
+                rec.compressed_size        = share;
+                rec.unique_compressed_size = Some(unique);
+            }
+        }
+    }
+}
+
+/// `Option<Vec<u8>>` as an optional hex string.
+mod hex_option {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &Option<Vec<u8>>, s: S) -> Result<S::Ok, S::Error> {
+        match bytes {
+            Some(bytes) => s.serialize_str(&hex::encode(bytes)),
+            None        => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|h| hex::decode(h).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// `path` with sequence indices elided, so every record's copy of a key
+/// is counted under one name.
+fn field_pattern(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root                          => String::new(),
+        Path::Seq { parent, .. }            => format!("{}[]", field_pattern(parent)),
+        Path::Map { parent, key }           => match field_pattern(parent) {
+            p if p.is_empty() => key.clone(),
+            p                 => format!("{p}.{key}"),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent }   => field_pattern(parent),
+    }
 }
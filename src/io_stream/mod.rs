@@ -13,6 +13,14 @@
 //! availability check (fail hard if any required codec is missing — no
 //! negotiation), then seeks to the INDEX block to build the file list.
 //!
+//! # Reader (INDEX relocation)
+//! If the block at the superblock's `index_offset` is not a readable INDEX —
+//! say a buggy tool appended to the archive and patched the offset wrong —
+//! [`locate_index_from_tail`] scans backwards from the end of the stream
+//! for the last CRC-valid INDEX header and the reader uses that instead
+//! ([`SixCyReader::is_index_relocated`]).  This reads only the tail, so it
+//! is much cheaper than the reconstruction scan below.
+//!
 //! # Reader (reconstruction path)
 //! If the INDEX block is absent or corrupt, `SixCyReader::scan_blocks()`
 //! reconstructs the block list by reading every block header sequentially.
@@ -20,24 +28,360 @@
 //! `"file_{file_id:08x}"`.  Solid-block file names cannot be recovered
 //! without the INDEX block.
 //!
+//! # Incremental snapshots
+//! A writer seeded with [`SixCyWriter::add_base_index`] skips chunks already
+//! stored in a base archive and records a [`BlockRef`] whose `archive_id`
+//! names that base.  A reader resolves such refs through the parents
+//! attached with [`SixCyReader::attach_parent`].
+//!
+//! # Appending
+//! [`SixCyWriter::append_to`] continues a finalized archive: new blocks
+//! follow its INDEX and recovery map, `finalize()` writes a fresh INDEX for
+//! old and new records, and chunks or solid members matching existing
+//! blocks are referenced, not stored again.  The superblock is patched
+//! last, so until then it still points at the old INDEX and an interrupted
+//! append leaves the archive as it was.  The old INDEX stays behind as a
+//! superseded block; block walks step over it and its recovery map.
+//!
+//! # Auto-solid grouping
+//! With [`SixCyWriter::auto_solid`] set, files below the size threshold are
+//! held back in per-extension groups (outside any manual solid session) and
+//! written as rolling SOLID blocks: a group is flushed once it reaches
+//! `block_size`, and the remainder at `finalize()`, in extension order with
+//! files sorted by name.  Small files therefore receive their record IDs
+//! when their group is flushed, after the large files added around them.
+//!
+//! # Chunk sizing
+//! By default every file is cut into `chunk_size` chunks.  A
+//! [`ChunkPolicy`] in [`SixCyWriter::chunk_policy`] picks the size per file
+//! instead; [`AdaptiveChunks`] keeps small files whole and gives huge files
+//! larger chunks, cutting header overhead and INDEX size.  Chunk boundaries
+//! are what CAS dedup and incremental snapshots match on, so a policy must
+//! be a pure function of its inputs.
+//!
+//! # Chunk pipeline
+//! New chunks are compressed and encrypted on worker threads while the
+//! caller keeps reading and the finished blocks are written in file order;
+//! `queue_depth` bounds how many chunks are in flight.  With
+//! [`SixCyWriter::add_file_from_reader`] a large file is read a chunk at a
+//! time, so packing it takes about `queue_depth × chunk_size` of memory
+//! rather than the whole file.
+//!
+//! # Concatenated archives
+//! Archives joined with `cat` stay readable: [`find_sections`] walks from
+//! each archive's recovery map to the next superblock, and a reader opened
+//! on a [`Section`] sees that archive at its own offsets.
+//!
+//! # Deterministic mode
+//! [`SixCyWriter::make_deterministic`] makes the output a pure function of
+//! the inputs: recovery checkpoints and superblock times are 0, encrypted payloads use
+//! plaintext-derived nonces, and — unless a fixed UUID is supplied — the
+//! archive UUID is derived from the serialized INDEX at `finalize()`.
+//!
+//! # Preallocation
+//! [`preallocate`] reserves disk space for an output file up front
+//! (`fallocate` on Linux, the allocation size on Windows) without changing
+//! its length, so a large pack is laid out contiguously and a full disk is
+//! reported before any block is written.  The reservation is released by
+//! truncating to [`SixCyWriter::finalized_len`] once the writer finalizes;
+//! see [`Archive::preallocate`](crate::archive::Archive::preallocate).
+//!
 //! # Endianness
 //! All binary I/O is strictly little-endian; see `block.rs` and
 //! `superblock.rs` for field-level documentation.  No runtime negotiation
 //! is ever performed.
 
 use std::io::{self, Read, Write, Seek, SeekFrom};
-use std::collections::HashMap;
-use crate::superblock::{Superblock, SUPERBLOCK_SIZE};
-use crate::block::{encode_block, decode_block, BlockHeader, BlockType, FILE_ID_SHARED};
-use crate::index::{FileIndex, FileIndexRecord, BlockRef};
-use crate::codec::CodecId;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::collections::hash_map::Entry;
+use std::fs::File;
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use crate::superblock::{CodecContinuation, Superblock, FEATURE_PREFILTER, SUPERBLOCK_SIZE};
+use crate::block::{encode_block, encode_block_with, decode_block, decode_block_timed, EncodeOpts,
+                   BlockHeader, BlockType, BLOCK_HEADER_SIZE, BLOCK_MAGIC, FILE_ID_SHARED};
+use crate::index::{FileIndex, FileIndexRecord, BlockRef, HashAlgorithm};
+use crate::names::{admit, fold, EntryName, NamePolicy};
+use crate::events::{BlockEvent, DedupEvent, DedupSource, ErrorEvent, FileEvent, Observer};
+use crate::codec::{get_codec, CodecError, CodecId, Dictionary};
+use crate::crypto::{key_check, verify_key_check};
+use crate::pipeline::{BlockPipeline, FLAG_FILTER_MASK};
+use crate::limits::{check_deadline, Limits};
+use crate::perf::{PerfStats, Stopwatch};
+use crate::error::Result;
 use crate::recovery::{RecoveryMap, RecoveryCheckpoint};
 use chrono::Utc;
 
 /// Default chunk size: 4 MiB.
 pub const DEFAULT_CHUNK_SIZE:        usize = 4 * 1024 * 1024;
+/// Largest chunk (and SOLID block) the writer produces: 2 GiB.  Block
+/// headers hold sizes in 32 bits, and the headroom below 4 GiB absorbs
+/// codec expansion and the AES-GCM nonce and tag on incompressible data.
+pub const MAX_CHUNK_SIZE:            usize = 1 << 31;
+/// Chunk sizes [`validate_chunk_size`] accepts: 64 KiB to 1 GiB.  Below
+/// that, headers and INDEX refs cost more than dedup saves; above it, a
+/// single block holds gigabytes, and the upper end keeps any codec's
+/// worst-case output plus the AES-GCM nonce and tag far inside a block
+/// header's 32-bit size field.
+pub const CHUNK_SIZE_RANGE: std::ops::RangeInclusive<usize> = 64 * 1024..=1 << 30;
+/// Chunks larger than this rarely repeat, so CAS dedup finds little.
+pub const DEDUP_CHUNK_SIZE: usize = 64 * 1024 * 1024;
 /// Default Zstd compression level.
 pub const DEFAULT_COMPRESSION_LEVEL: i32   = 3;
+/// Default Zstd level for the INDEX block: it is written once and read on
+/// every open, so it gets the strongest non-ultra level.
+pub const INDEX_COMPRESSION_LEVEL:   i32   = 19;
+
+// ── Chunk policy ─────────────────────────────────────────────────────────────
+
+/// A chunk size that works but is likely a mistake; see
+/// [`validate_chunk_size`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkSizeWarning {
+    /// Above [`DEDUP_CHUNK_SIZE`]: identical data is only found when whole
+    /// chunks this large repeat.
+    DefeatsDedup { chunk_size: usize },
+}
+
+impl std::fmt::Display for ChunkSizeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DefeatsDedup { chunk_size } => write!(f,
+                "{} KiB chunks rarely repeat; dedup works best at {} KiB or less",
+                chunk_size / 1024, DEDUP_CHUNK_SIZE / 1024),
+        }
+    }
+}
+
+/// Check a user-supplied chunk size: outside [`CHUNK_SIZE_RANGE`] is an
+/// `InvalidInput` error, and a size that will work poorly comes back as
+/// warnings.  The writer itself accepts any size up to [`MAX_CHUNK_SIZE`].
+pub fn validate_chunk_size(chunk_size: usize) -> io::Result<Vec<ChunkSizeWarning>> {
+    if !CHUNK_SIZE_RANGE.contains(&chunk_size) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+            "chunk size {} KiB is outside {}..={} KiB",
+            chunk_size / 1024, CHUNK_SIZE_RANGE.start() / 1024, CHUNK_SIZE_RANGE.end() / 1024,
+        )));
+    }
+    let mut warnings = Vec::new();
+    if chunk_size > DEDUP_CHUNK_SIZE {
+        warnings.push(ChunkSizeWarning::DefeatsDedup { chunk_size });
+    }
+    Ok(warnings)
+}
+
+/// Chooses the chunk size for each file written in chunked (non-solid) mode.
+pub trait ChunkPolicy: std::fmt::Debug + Send + Sync {
+    /// Chunk size in bytes for a file called `name` holding `len` bytes.
+    /// Values are clamped to `1..=u32::MAX` (a block's `orig_size` limit).
+    fn chunk_size(&self, name: &str, len: u64) -> usize;
+}
+
+/// Aim for about `target_chunks` chunks per file, rounded up to a power of
+/// two and clamped to `min..=max`.  Files no larger than `min` are a single
+/// chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptiveChunks {
+    pub min:           usize,
+    pub max:           usize,
+    pub target_chunks: usize,
+}
+
+impl Default for AdaptiveChunks {
+    fn default() -> Self {
+        Self { min: DEFAULT_CHUNK_SIZE, max: 64 * 1024 * 1024, target_chunks: 256 }
+    }
+}
+
+impl ChunkPolicy for AdaptiveChunks {
+    fn chunk_size(&self, _name: &str, len: u64) -> usize {
+        let per_chunk = (len as usize).div_ceil(self.target_chunks.max(1));
+        per_chunk.next_power_of_two().clamp(self.min, self.max.max(self.min))
+    }
+}
+
+// ── Auto-solid policy ────────────────────────────────────────────────────────
+
+/// Route small files into rolling solid blocks grouped by extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoSolid {
+    /// Files strictly smaller than this many bytes are grouped.
+    pub threshold:  usize,
+    /// A group is written as one SOLID block once it holds this many bytes.
+    pub block_size: usize,
+}
+
+impl Default for AutoSolid {
+    fn default() -> Self {
+        Self { threshold: 256 * 1024, block_size: 16 * 1024 * 1024 }
+    }
+}
+
+// ── Solid member order ───────────────────────────────────────────────────────
+
+/// Order in which a SOLID block's members are laid out in its buffer.
+///
+/// Putting alike files next to each other lets the codec's window find
+/// matches across them.  Index records keep their insertion order; each
+/// member's `intra_offset` points at wherever it landed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SolidOrder {
+    /// As added.
+    #[default]
+    Insertion,
+    /// By lower-cased extension, then name.
+    Extension,
+    /// By size, smallest first.
+    Size,
+    /// Clustered by a MinHash of each member's first
+    /// [`SIMILARITY_SAMPLE`] bytes, so files sharing content sort together.
+    Similarity,
+}
+
+impl SolidOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "insertion"  => Some(Self::Insertion),
+            "extension"  => Some(Self::Extension),
+            "size"       => Some(Self::Size),
+            "similarity" => Some(Self::Similarity),
+            _            => None,
+        }
+    }
+}
+
+/// Leading bytes of each member hashed by [`SolidOrder::Similarity`].
+pub const SIMILARITY_SAMPLE: usize = 4096;
+
+/// MinHash over the 8-byte shingles of `data`'s first [`SIMILARITY_SAMPLE`]
+/// bytes.  Sorting by it groups members whose samples overlap: two members
+/// share each component with probability equal to their Jaccard similarity.
+fn minhash(data: &[u8]) -> [u64; 4] {
+    const SEEDS: [u64; 4] = [0x9E37_79B9_7F4A_7C15, 0xC2B2_AE3D_27D4_EB4F, 0x1656_67B1_9E37_79F9, 0x27D4_EB2F_1656_67C5];
+    let sample = &data[..data.len().min(SIMILARITY_SAMPLE)];
+    let mut sig = [u64::MAX; 4];
+    for window in sample.windows(8) {
+        let shingle = u64::from_le_bytes(window.try_into().unwrap());
+        for (min, seed) in sig.iter_mut().zip(SEEDS) {
+            let h = (shingle ^ seed).wrapping_mul(0xFF51_AFD7_ED55_8CCD).rotate_left(31);
+            *min = (*min).min(h);
+        }
+    }
+    sig
+}
+
+// ── Auto level ───────────────────────────────────────────────────────────────
+
+/// Pick a compression level per file from a trial on its leading bytes.
+///
+/// The first `sample_bytes` of each chunked file are compressed at every
+/// level of [`CodecId::level_sweep`], fastest first.  A stronger level is
+/// taken while it shrinks the sample by at least `min_gain` (a fraction of
+/// the previous size) for every doubling of compression time, and by at
+/// least `min_gain` however cheap it is; the sweep
+/// stops at the first level that does not pay, so incompressible data never
+/// reaches the expensive levels.  The chosen level is used for the rest of
+/// the file.
+///
+/// Codecs without levels, SOLID blocks, and deterministic writers (timings
+/// are not reproducible) use the writer's fixed `compression_level`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AutoLevel {
+    pub sample_bytes: usize,
+    pub min_gain:     f64,
+}
+
+impl Default for AutoLevel {
+    fn default() -> Self {
+        Self { sample_bytes: 1024 * 1024, min_gain: 0.01 }
+    }
+}
+
+impl AutoLevel {
+    /// The level to compress `data` with, or `None` if `codec` has no
+    /// levels to choose from.
+    pub fn pick(&self, codec: CodecId, data: &[u8]) -> std::result::Result<Option<i32>, CodecError> {
+        let sweep = codec.level_sweep();
+        let Some((&first, rest)) = sweep.split_first() else { return Ok(None) };
+        let sample = &data[..data.len().min(self.sample_bytes)];
+        let codec  = get_codec(codec)?;
+        let trial  = |level| -> std::result::Result<(usize, f64), CodecError> {
+            let t0 = std::time::Instant::now();
+            let n  = codec.compress(sample, level)?.len();
+            Ok((n, t0.elapsed().as_secs_f64().max(1e-9)))
+        };
+
+        let (mut level, (mut size, mut secs)) = (first, trial(first)?);
+        for &next in rest {
+            let (n, t) = trial(next)?;
+            let gain = 1.0 - n as f64 / size.max(1) as f64;
+            if gain < self.min_gain * (t / secs).log2().max(1.0) {
+                break;
+            }
+            (level, size, secs) = (next, n, t);
+        }
+        Ok(Some(level))
+    }
+}
+
+/// Files waiting for their auto-solid group to fill.
+struct SolidGroup {
+    codec: CodecId,
+    bytes: usize,
+    files: Vec<(EntryName, Vec<u8>, HashMap<String, String>)>,
+}
+
+/// First copy of a solid member, for deduplicating later identical files.
+enum SolidCopy {
+    /// In the open session's buffer, before reordering.
+    Pending { intra_offset: u64 },
+    /// In a written SOLID block.
+    Written { slice: BlockRef },
+}
+
+/// A chunk entering the chunk pipeline.
+struct ChunkJob<C> {
+    file_offset: u64,
+    data:        C,
+}
+
+/// A chunk leaving the chunk pipeline, in file order: its hash, its block
+/// if it was not already stored when a worker hashed it, and stage times.
+struct ChunkDone {
+    hash:    [u8; 32],
+    len:     u64,
+    encoded: Option<(BlockHeader, Vec<u8>)>,
+    stats:   Option<PerfStats>,
+}
+
+/// Size of a written DATA or SOLID block, for charging it to files.
+#[derive(Clone, Copy)]
+struct StoredBlock {
+    solid:       bool,
+    payload_len: u64,
+    orig_size:   u64,
+}
+
+impl StoredBlock {
+    /// Payload bytes behind `br`: the whole block, or a solid slice's share
+    /// of it in proportion to its length.
+    fn cost(&self, br: &BlockRef) -> u64 {
+        if !self.solid { return self.payload_len; }
+        (self.payload_len as u128 * br.intra_length as u128 / self.orig_size.max(1) as u128) as u64
+    }
+}
+
+/// Group key: lower-cased extension (empty if none), then codec UUID.
+fn solid_group_key(name: &str, codec: CodecId) -> (String, [u8; 16]) {
+    (extension(name), codec.uuid())
+}
+
+fn extension(name: &str) -> String {
+    std::path::Path::new(name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
 
 // ── Writer ───────────────────────────────────────────────────────────────────
 
@@ -52,17 +396,77 @@ pub struct SixCyWriter<W: Write + Seek> {
     solid_codec:       Option<CodecId>,
     /// (file_id, intra_offset, intra_length, content_hash)
     solid_file_ranges: Vec<(u32, u64, u64, [u8; 32])>,
+    // Solid dedup: BLAKE3(file) → where its first copy went.
+    solid_dedup:       HashMap<[u8; 32], SolidCopy>,
 
     // CAS: BLAKE3(uncompressed chunk) → (archive_offset, compressed_payload_len)
     block_dedup:       HashMap<[u8; 32], (u64, u64)>,
+    // Incremental CAS: BLAKE3 → BlockRef into a base archive (archive_id set)
+    base_blocks:       HashMap<[u8; 32], BlockRef>,
+    // Every DATA / SOLID block written: archive_offset → StoredBlock.
+    stored_blocks:     HashMap<u64, StoredBlock>,
 
     pub chunk_size:        usize,
+    /// Per-file chunk size; overrides `chunk_size` when set.
+    pub chunk_policy:      Option<Arc<dyn ChunkPolicy>>,
+    /// Chunks of a file in flight between reading and writing (module
+    /// docs); 1 reads, encodes, and writes each chunk in turn.
+    pub queue_depth:       usize,
+    /// Level for DATA blocks, and SOLID blocks unless `solid_level` is set.
     pub compression_level: i32,
+    /// Level for SOLID blocks; `None` uses `compression_level`.
+    pub solid_level:       Option<i32>,
+    /// Level for the INDEX block.
+    pub index_level:       i32,
+    /// Codec for the INDEX block.  `CodecId::None` stores the JSON as is,
+    /// so readers built without codecs (`core_reader`) can parse it.
+    pub index_codec:       CodecId,
     pub encryption_key:    Option<[u8; 32]>,
+    /// Reproducible output; see [`SixCyWriter::make_deterministic`].
+    pub deterministic:     bool,
+    /// Group small files into solid blocks automatically (module docs).
+    pub auto_solid:        Option<AutoSolid>,
+    /// Choose `compression_level` per chunked file by trial compression.
+    pub auto_level:        Option<AutoLevel>,
+    /// Start a new SOLID block, within the same session, before a file
+    /// would take the current one past this many bytes.
+    pub solid_max_bytes:   Option<usize>,
+    /// Start a new SOLID block once the current one holds this many files.
+    pub solid_max_files:   Option<usize>,
+    /// Layout of members within each SOLID block.
+    pub solid_order:       SolidOrder,
+    /// Record a whole-file digest in this algorithm for every file.
+    pub secondary_hash:    Option<HashAlgorithm>,
+    /// Metadata stored in every record; a file's own entries win.
+    pub default_metadata:  HashMap<String, String>,
+    /// Stages for every DATA and SOLID block, overriding the codec passed
+    /// in and the levels above; see [`crate::pipeline`].  Its cipher stage
+    /// must match `encryption_key`, or adding a file fails.
+    pub pipeline:          Option<BlockPipeline>,
+    /// What to do with names that would not extract faithfully everywhere;
+    /// recorded in the INDEX.  See [`crate::names`].
+    pub name_policy:       NamePolicy,
+    /// Case-folded names stored so far, under `Normalize` and `Reject`.
+    names_seen:            HashSet<String>,
+    /// Told of files, blocks, dedup hits, and errors; see [`crate::events`].
+    pub observer:          Option<Arc<dyn Observer>>,
+    /// Compress Zstd DATA and SOLID blocks with this dictionary.  The
+    /// archive then requires it by UUID, so readers must have it
+    /// registered; see [`crate::codec::Dictionary`].
+    pub dictionary:        Option<Arc<Dictionary>>,
+    /// Blobs from [`put_app_data`](Self::put_app_data), written by `finalize`.
+    app_data:              BTreeMap<String, Vec<u8>>,
+    solid_groups:          BTreeMap<(String, [u8; 16]), SolidGroup>,
+    perf:                  Option<PerfStats>,
+    finalized_len:         Option<u64>,
+    /// Called on the output before and after `finalize` patches the
+    /// superblock, so the new INDEX is durable before anything points at
+    /// it; `Archive::open_for_append` sets `File::sync_data`.
+    pub commit_sync:       Option<fn(&W) -> io::Result<()>>,
 }
 
 impl<W: Write + Seek> SixCyWriter<W> {
-    pub fn new(writer: W) -> io::Result<Self> {
+    pub fn new(writer: W) -> Result<Self> {
         Self::with_options(writer, DEFAULT_CHUNK_SIZE, DEFAULT_COMPRESSION_LEVEL, None)
     }
 
@@ -71,38 +475,383 @@ impl<W: Write + Seek> SixCyWriter<W> {
         chunk_size:        usize,
         compression_level: i32,
         encryption_key:    Option<[u8; 32]>,
-    ) -> io::Result<Self> {
-        let sb = Superblock::new();
+    ) -> Result<Self> {
         writer.seek(SeekFrom::Start(0))?;
         writer.write_all(&[0u8; SUPERBLOCK_SIZE])?; // reserved; overwritten on finalize
-        Ok(Self {
+        Ok(Self::blank(writer, chunk_size, compression_level, encryption_key))
+    }
+
+    /// A writer with default settings and nothing written.
+    fn blank(writer: W, chunk_size: usize, compression_level: i32, encryption_key: Option<[u8; 32]>) -> Self {
+        Self {
             writer,
-            superblock:        sb,
+            superblock:        Superblock::new(),
             index:             FileIndex::default(),
             recovery_map:      RecoveryMap::default(),
             solid_buffer:      Vec::new(),
             solid_codec:       None,
             solid_file_ranges: Vec::new(),
+            solid_dedup:       HashMap::new(),
             block_dedup:       HashMap::new(),
+            base_blocks:       HashMap::new(),
+            stored_blocks:     HashMap::new(),
             chunk_size:        chunk_size.max(1),
+            chunk_policy:      None,
+            queue_depth:       crate::perf::encode_window(),
             compression_level,
+            solid_level:       None,
+            index_level:       INDEX_COMPRESSION_LEVEL,
+            index_codec:       CodecId::Zstd,
             encryption_key,
-        })
+            deterministic:     false,
+            auto_solid:        None,
+            auto_level:        None,
+            solid_max_bytes:   None,
+            solid_max_files:   None,
+            solid_order:       SolidOrder::Insertion,
+            secondary_hash:    None,
+            default_metadata:  HashMap::new(),
+            pipeline:          None,
+            name_policy:       NamePolicy::default(),
+            names_seen:        HashSet::new(),
+            observer:          None,
+            dictionary:        None,
+            app_data:          BTreeMap::new(),
+            solid_groups:      BTreeMap::new(),
+            perf:              None,
+            finalized_len:     None,
+            commit_sync:       None,
+        }
+    }
+
+    /// The underlying output.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Length of the finished archive — through the recovery map — once
+    /// [`finalize`](Self::finalize) has run; `None` before.
+    pub fn finalized_len(&self) -> Option<u64> {
+        self.finalized_len
+    }
+
+    /// Switch to reproducible output.  The archive UUID becomes `uuid`, or —
+    /// when `None` — the nil UUID, which `finalize()` replaces with one
+    /// derived from the INDEX.  Call before any block is written; an
+    /// encryption key must be derived from the final UUID, so encrypted
+    /// archives need a fixed one.
+    pub fn make_deterministic(&mut self, uuid: Option<uuid::Uuid>) {
+        self.deterministic = true;
+        self.superblock.archive_uuid = uuid.unwrap_or_else(uuid::Uuid::nil);
+        self.superblock.created_at   = Some(0);
+        self.superblock.modified_at  = Some(0);
+    }
+
+    /// Start collecting [`PerfStats`] for everything written from now on.
+    pub fn enable_perf_stats(&mut self) {
+        self.perf = Some(PerfStats::start());
+    }
+
+    /// Stats collected so far, if enabled.
+    pub fn perf_stats(&self) -> Option<PerfStats> {
+        self.perf.map(|p| p.snapshot())
+    }
+
+    /// Encode one DATA or SOLID block at `level` with this writer's key
+    /// and nonce mode.
+    fn encode(
+        &mut self,
+        block_type:  BlockType,
+        file_id:     u32,
+        file_offset: u64,
+        data:        &[u8],
+        codec:       CodecId,
+        level:       i32,
+    ) -> Result<(BlockHeader, Vec<u8>)> {
+        let opts = EncodeOpts {
+            key:           self.encryption_key.as_ref(),
+            deterministic: self.deterministic,
+            stats:         self.perf.as_mut(),
+            content_hash:  None,
+            filter:        self.pipeline.as_ref().and_then(|p| p.filter),
+            dictionary:    self.dictionary.as_deref(),
+        };
+        Ok(encode_block_with(block_type, file_id, file_offset, data, codec, level, opts)?)
+    }
+
+    /// Store `chunks` as the DATA blocks of `record`, in order, through
+    /// the chunk pipeline: workers hash each chunk and encode it unless it
+    /// is already stored; the caller's thread reads the chunks, checks each
+    /// hash against what has been written by then, and writes new blocks.
+    /// Copies of a chunk in flight together may both be encoded; the later
+    /// one is dropped unwritten.
+    fn write_chunks<C: AsRef<[u8]> + Send>(
+        &mut self,
+        record: &mut FileIndexRecord,
+        chunks: impl Iterator<Item = io::Result<C>>,
+        codec:  CodecId,
+        level:  i32,
+    ) -> Result<()> {
+        let key           = self.encryption_key;
+        let deterministic = self.deterministic;
+        let timed         = self.perf.is_some();
+        let filter        = self.pipeline.as_ref().and_then(|p| p.filter);
+        let dictionary    = self.dictionary.clone();
+        let file_id       = record.id;
+        let depth         = self.queue_depth;
+
+        // Workers consult the CAS maps while the sink adds to them, so they
+        // leave `self` for the duration; see the end of this function.
+        let stored = RwLock::new(std::mem::take(&mut self.block_dedup));
+        let base   = std::mem::take(&mut self.base_blocks);
+        let known  = |hash: &[u8; 32]| {
+            stored.read().unwrap_or_else(|e| e.into_inner()).contains_key(hash) || base.contains_key(hash)
+        };
+
+        let mut chunks = chunks;
+        let mut offset = 0u64;
+        let ingest = |w: &mut Self| -> Result<Option<ChunkJob<C>>> {
+            let Some(data) = chunks.next().transpose()? else { return Ok(None) };
+            let len = data.as_ref().len() as u64;
+            if let Some(perf) = &mut w.perf { perf.bytes_in += len; }
+            let file_offset = offset;
+            offset += len;
+            Ok(Some(ChunkJob { file_offset, data }))
+        };
+        let encode = |ChunkJob { file_offset, data }: ChunkJob<C>| -> Result<ChunkDone> {
+            let len       = data.as_ref().len() as u64;
+            let mut stats = timed.then(PerfStats::default);
+            let mut clock = Stopwatch::new(timed);
+            let hash      = crate::perf::hash_chunk(data.as_ref());
+            if let Some(stats) = &mut stats { stats.hash += clock.lap(); }
+            if known(&hash) {
+                return Ok(ChunkDone { hash, len, encoded: None, stats });
+            }
+            let opts = EncodeOpts {
+                key:          key.as_ref(),
+                deterministic,
+                stats:        stats.as_mut(),
+                content_hash: Some(hash),
+                filter,
+                dictionary:   dictionary.as_deref(),
+            };
+            let (header, payload) = encode_block_with(
+                BlockType::Data, file_id, file_offset, data.as_ref(), codec, level, opts,
+            )?;
+            Ok(ChunkDone { hash, len, encoded: Some((header, payload)), stats })
+        };
+        let write = |w: &mut Self, done: ChunkDone| -> Result<()> {
+            let content_hash = done.hash;
+            if let (Some(perf), Some(stats)) = (&mut w.perf, done.stats) {
+                perf.add_encode_stages(&stats);
+            }
+            // An earlier chunk in flight with this one may have stored it.
+            let (payload, hit) = match done.encoded {
+                Some((header, payload)) if !known(&content_hash) => {
+                    let archive_offset = w.write_block(&header, &payload)?;
+                    let entry = (archive_offset, payload.len() as u64);
+                    stored.write().unwrap_or_else(|e| e.into_inner()).insert(content_hash, entry);
+                    (Some(payload), false)
+                }
+                Some((_, payload)) => (Some(payload), true),
+                None               => (None, true),
+            };
+            if let Some(payload) = payload { crate::pool::shared().give(payload); }
+            let local = stored.read().unwrap_or_else(|e| e.into_inner()).get(&content_hash).copied();
+            if let Some((archive_offset, comp_len)) = local {
+                // Stored in this archive — just now or by an earlier chunk.
+                record.block_refs.push(BlockRef {
+                    content_hash,
+                    archive_offset,
+                    intra_offset: 0,
+                    intra_length: 0,
+                    archive_id:   None,
+                });
+                record.compressed_size += comp_len;
+                if hit { w.notify_dedup(file_id, content_hash, done.len, DedupSource::Block); }
+            } else {
+                // Incremental hit — block lives in a base archive; nothing
+                // is stored here, so compressed_size is not charged.
+                record.block_refs.push(base[&content_hash].clone());
+                w.notify_dedup(file_id, content_hash, done.len, DedupSource::Base);
+            }
+            Ok(())
+        };
+        let result = crate::perf::ordered_pipeline(self, depth, ingest, encode, write);
+        self.block_dedup = stored.into_inner().unwrap_or_else(|e| e.into_inner());
+        self.base_blocks = base;
+        result
+    }
+
+    /// Record a chunked file written by [`write_chunks`](Self::write_chunks).
+    fn push_chunked_record(&mut self, record: FileIndexRecord) -> Result<()> {
+        self.recovery_map.checkpoints.push(RecoveryCheckpoint {
+            archive_offset: self.writer.stream_position()?,
+            last_file_id:   record.id,
+            timestamp:      if self.deterministic { 0 } else { Utc::now().timestamp() },
+        });
+        self.notify_file(Observer::on_file_done, &record);
+        self.index.records.push(record);
+        Ok(())
+    }
+
+    /// Append a block and return its archive offset.
+    fn write_block(&mut self, header: &BlockHeader, payload: &[u8]) -> Result<u64> {
+        let mut clock = Stopwatch::new(self.perf.is_some());
+        let archive_offset = self.writer.stream_position()?;
+        if header.flags & FLAG_FILTER_MASK != 0 {
+            self.superblock.feature_flags |= FEATURE_PREFILTER;
+        }
+        header.write(&mut self.writer)?;
+        self.writer.write_all(payload)?;
+        if matches!(header.block_type, BlockType::Data | BlockType::Solid) {
+            self.stored_blocks.insert(archive_offset, StoredBlock {
+                solid:       header.block_type == BlockType::Solid,
+                payload_len: payload.len() as u64,
+                orig_size:   header.orig_size as u64,
+            });
+        }
+        if let Some(perf) = &mut self.perf {
+            perf.write     += clock.lap();
+            perf.bytes_out += (BLOCK_HEADER_SIZE + payload.len()) as u64;
+            perf.blocks    += 1;
+        }
+        if let Some(observer) = &self.observer {
+            observer.on_block_written(&BlockEvent {
+                block_type: header.block_type,
+                offset:     archive_offset,
+                orig_size:  header.orig_size as u64,
+                stored:     (BLOCK_HEADER_SIZE + payload.len()) as u64,
+            });
+        }
+        Ok(archive_offset)
+    }
+
+    // ── Incremental snapshots ───────────────────────────────────────────────
+
+    /// Register the DATA blocks of a base archive for incremental packing.
+    ///
+    /// Chunks whose BLAKE3 matches a base block are not written again; the
+    /// record receives a [`BlockRef`] carrying the base's `archive_id`
+    /// instead.  Refs the base itself borrowed from an older snapshot are
+    /// copied verbatim, so chains of any depth resolve to the physical block.
+    /// Solid slices are not reused — they cannot be addressed per chunk.
+    pub fn add_base_index(&mut self, base_uuid: &uuid::Uuid, base_index: &FileIndex) {
+        for rec in &base_index.records {
+            for br in rec.block_refs.iter().filter(|br| !br.is_solid_slice()) {
+                let mut external = br.clone();
+                external.archive_id.get_or_insert(*base_uuid.as_bytes());
+                self.base_blocks.entry(br.content_hash).or_insert(external);
+            }
+        }
+    }
+
+    // ── Appending ───────────────────────────────────────────────────────────
+
+    /// A writer that continues the finalized archive behind `reader`.
+    /// `writer` must write to the same archive; it is positioned just past
+    /// the archive's recovery map, over anything an interrupted append left
+    /// there, and the caller truncates the file to
+    /// [`finalized_len`](Self::finalized_len) after `finalize()`.
+    ///
+    /// The superblock, INDEX, key, recovery map, and INDEX codec carry
+    /// over, and every DATA block and solid member the INDEX references is
+    /// registered for
+    /// CAS dedup.  Blocks of base archives are not: seed them with
+    /// [`add_base_index`](Self::add_base_index).  Fails with
+    /// `InvalidData` if the INDEX was relocated or rebuilt, references
+    /// anything past itself, or its recovery map cannot be read.
+    pub fn append_to<R: Read + Seek>(reader: &mut SixCyReader<R>, writer: W) -> Result<Self> {
+        let invalid = |msg: &str| -> crate::error::SixcyError {
+            io::Error::new(io::ErrorKind::InvalidData, format!("cannot append: {msg}")).into()
+        };
+        if reader.reconstructed || reader.index_relocated {
+            return Err(invalid("the INDEX is not where the superblock says"));
+        }
+        let end   = reader.superblock.index_offset;
+        let index = Arc::clone(&reader.index);
+        let refs  = || index.records.iter().flat_map(|r| &r.block_refs).filter(|br| !br.is_external());
+        if refs().chain(index.app_data.values()).any(|br| br.archive_offset >= end) {
+            return Err(invalid("blocks follow the INDEX"));
+        }
+
+        let mut appender = Self::blank(writer, DEFAULT_CHUNK_SIZE, DEFAULT_COMPRESSION_LEVEL, reader.decryption_key);
+        for br in refs() {
+            let block = match appender.stored_blocks.entry(br.archive_offset) {
+                Entry::Occupied(stored) => *stored.get(),
+                Entry::Vacant(slot)     => {
+                    let header = reader.block_header(br)?;
+                    *slot.insert(StoredBlock {
+                        solid:       header.block_type == BlockType::Solid,
+                        payload_len: header.comp_size as u64,
+                        orig_size:   header.orig_size as u64,
+                    })
+                }
+            };
+            match br.is_solid_slice() {
+                true  => { appender.solid_dedup.entry(br.content_hash).or_insert(SolidCopy::Written { slice: br.clone() }); }
+                false => { appender.block_dedup.entry(br.content_hash).or_insert((br.archive_offset, block.payload_len)); }
+            }
+        }
+        reader.reader.seek(SeekFrom::Start(end))?;
+        let index_header = BlockHeader::read(&mut reader.reader)?;
+        appender.index_codec = index_header.codec_id()
+            .ok_or_else(|| invalid("the INDEX codec is unknown"))?;
+        let (recovery_map, tail) = reader.read_recovery_map()?;
+        appender.names_seen   = index.records.iter().map(|r| fold(&r.name)).collect();
+        appender.recovery_map = recovery_map;
+        appender.superblock   = Superblock { codec_continuation: None, ..reader.superblock.clone() };
+        appender.index        = (*index).clone();
+
+        appender.writer.seek(SeekFrom::Start(tail))?;
+        Ok(appender)
+    }
+
+    /// ID for the next record: one past the highest so far, which is the
+    /// record count unless the writer continues an archive with gaps.
+    fn next_file_id(&self) -> u32 {
+        self.index.records.last().map_or(0, |r| r.id + 1)
+    }
+
+    // ── Raw block copy ──────────────────────────────────────────────────────
+
+    /// Append an already-encoded block verbatim and return its archive offset.
+    ///
+    /// The payload is not decoded; the header's codec UUID is added to the
+    /// required-codec list.  The caller is responsible for pointing index
+    /// records at the returned offset.
+    pub fn write_raw_block(&mut self, header: &BlockHeader, payload: &[u8]) -> Result<u64> {
+        if header.block_type != BlockType::Index {
+            self.superblock.add_required_uuid(&header.codec_uuid);
+        }
+        self.write_block(header, payload)
     }
 
     // ── Solid mode ──────────────────────────────────────────────────────────
 
     /// Begin accumulating files into a single compressed solid block.
     /// Flushes any open solid session first.
-    pub fn start_solid_session(&mut self, codec: CodecId) -> io::Result<()> {
+    pub fn start_solid_session(&mut self, codec: CodecId) -> Result<()> {
         self.flush_solid_session()?;
-        self.solid_codec = Some(codec);
+        self.solid_codec = Some(self.pipeline.as_ref().map_or(codec, |p| p.codec));
         Ok(())
     }
 
+    /// True if a file of `len` bytes should open a new SOLID block: the
+    /// current one is not empty and would exceed a `solid_max_*` limit or
+    /// [`MAX_CHUNK_SIZE`].
+    fn solid_block_full(&self, len: usize) -> bool {
+        let files     = self.solid_file_ranges.len();
+        let max_bytes = self.solid_max_bytes.unwrap_or(MAX_CHUNK_SIZE).min(MAX_CHUNK_SIZE);
+        files > 0 && (
+            self.solid_buffer.len() + len > max_bytes
+            || self.solid_max_files.is_some_and(|max| files >= max)
+        )
+    }
+
     /// Compress the accumulated solid buffer as one SOLID block and update
     /// every pending file's block_refs with correct intra-block ranges.
-    pub fn flush_solid_session(&mut self) -> io::Result<()> {
+    pub fn flush_solid_session(&mut self) -> Result<()> {
         let codec = match self.solid_codec.take() {
             Some(c) => c,
             None    => return Ok(()),
@@ -112,48 +861,99 @@ impl<W: Write + Seek> SixCyWriter<W> {
             return Ok(());
         }
 
-        self.superblock.add_required_codec(codec);
+        self.require_codec(codec);
+        self.reorder_solid_members();
 
-        let (header, payload) = encode_block(
-            BlockType::Solid,
-            FILE_ID_SHARED,
-            0,
-            &self.solid_buffer,
-            codec,
-            self.compression_level,
-            self.encryption_key.as_ref(),
-        ).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let buffer = std::mem::take(&mut self.solid_buffer);
+        let level = match self.pipeline.as_ref().and_then(|p| p.level) {
+            Some(level) => level,
+            None        => self.solid_level.unwrap_or(self.compression_level),
+        };
+        let encoded = self.encode(BlockType::Solid, FILE_ID_SHARED, 0, &buffer, codec, level);
+        self.solid_buffer = buffer;
+        let (header, payload) = encoded?;
 
-        let archive_offset = self.writer.stream_position()?;
-        let payload_len    = payload.len() as u64;
-        header.write(&mut self.writer)?;
-        self.writer.write_all(&payload)?;
+        let archive_offset = self.write_block(&header, &payload)?;
+        let block          = self.stored_blocks[&archive_offset];
+        crate::pool::shared().give(payload);
 
         for (file_id, intra_offset, intra_length, content_hash) in
             self.solid_file_ranges.drain(..)
         {
+            let slice = BlockRef {
+                content_hash,
+                archive_offset,
+                intra_offset,
+                intra_length,
+                archive_id: None,
+            };
+            if let Some(copy @ SolidCopy::Pending { .. }) = self.solid_dedup.get_mut(&content_hash) {
+                *copy = SolidCopy::Written { slice: slice.clone() };
+            }
             if let Some(rec) = self.index.records.iter_mut().find(|r| r.id == file_id) {
-                rec.block_refs.push(BlockRef {
-                    content_hash,
-                    archive_offset,
-                    intra_offset,
-                    intra_length,
-                });
-                rec.compressed_size = payload_len;
+                rec.compressed_size = block.cost(&slice);
+                rec.block_refs.push(slice);
             }
         }
         self.solid_buffer.clear();
         Ok(())
     }
 
+    /// Lay the pending solid buffer out in `solid_order`, moving each
+    /// member's intra-offset with it.
+    fn reorder_solid_members(&mut self) {
+        let ranges = &self.solid_file_ranges;
+        if self.solid_order == SolidOrder::Insertion || ranges.len() < 2 {
+            return;
+        }
+        let member = |i: usize| {
+            let (_, off, len, _) = ranges[i];
+            &self.solid_buffer[off as usize..(off + len) as usize]
+        };
+
+        let mut order: Vec<usize> = (0..ranges.len()).collect();
+        match self.solid_order {
+            SolidOrder::Insertion  => unreachable!(),
+            SolidOrder::Extension  => {
+                let names: HashMap<u32, &str> = self.index.records.iter()
+                    .map(|r| (r.id, r.name.as_str()))
+                    .collect();
+                order.sort_by_cached_key(|&i| {
+                    let name = names.get(&ranges[i].0).copied().unwrap_or_default();
+                    (extension(name), name)
+                });
+            }
+            SolidOrder::Size       => order.sort_by_key(|&i| ranges[i].2),
+            SolidOrder::Similarity => order.sort_by_cached_key(|&i| minhash(member(i))),
+        }
+
+        // Deduplicated members share a range; copy each range once.
+        let mut buffer = Vec::with_capacity(self.solid_buffer.len());
+        let mut placed: HashMap<(u64, u64), u64> = HashMap::new();
+        let reordered = order.into_iter()
+            .map(|i| {
+                let (file_id, off, len, hash) = ranges[i];
+                let new_off = *placed.entry((off, len)).or_insert_with(|| {
+                    buffer.extend_from_slice(member(i));
+                    (buffer.len() - len as usize) as u64
+                });
+                (file_id, new_off, len, hash)
+            })
+            .collect();
+        self.solid_buffer      = buffer;
+        self.solid_file_ranges = reordered;
+    }
+
     // ── File ingestion ───────────────────────────────────────────────────────
 
     /// Add a file to the archive.
     ///
     /// **Solid mode**: data accumulates in the buffer; block_refs are filled
-    /// by the next `flush_solid_session`.
+    /// by the next `flush_solid_session`.  A file larger than
+    /// [`MAX_CHUNK_SIZE`] is chunked instead.
     ///
-    /// **Normal mode**: data is split into `chunk_size` chunks.  Each unique
+    /// **Normal mode**: data is split into chunks of `chunk_size` (or what
+    /// `chunk_policy` picks for this file).  Each unique
     /// chunk is written once (CAS deduplication); subsequent identical chunks
     /// receive a BlockRef pointing at the existing block.
     pub fn add_file(
@@ -161,91 +961,373 @@ impl<W: Write + Seek> SixCyWriter<W> {
         name:  String,
         data:  &[u8],
         codec: CodecId,
-    ) -> io::Result<()> {
-        let file_id = self.index.records.len() as u32;
+    ) -> Result<()> {
+        self.add_file_with_metadata(name, data, codec, HashMap::new())
+    }
+
+    /// [`add_file`](Self::add_file), storing `metadata` in the file's record.
+    pub fn add_file_with_metadata(
+        &mut self,
+        name:     impl Into<EntryName>,
+        data:     &[u8],
+        codec:    CodecId,
+        metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        let name   = name.into();
+        let shown  = self.observer.as_ref().map(|_| name.name.clone());
+        let result = self.admit_name(name).and_then(|name| self.add_admitted(name, data, codec, metadata));
+        self.report(shown.as_deref(), result)
+    }
+
+    /// `name` as [`name_policy`](Self::name_policy) has it stored.
+    fn admit_name(&mut self, name: EntryName) -> Result<EntryName> {
+        Ok(admit(self.name_policy, &mut self.names_seen, name)?)
+    }
 
-        if self.solid_codec.is_some() {
+    /// [`add_file_with_metadata`](Self::add_file_with_metadata) for a name
+    /// already admitted.
+    fn add_admitted(
+        &mut self,
+        name:     EntryName,
+        data:     &[u8],
+        codec:    CodecId,
+        mut metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        let codec = self.resolve_codec(codec)?;
+        self.merge_default_metadata(&mut metadata);
+        if let Some(policy) = self.auto_solid {
+            if self.solid_codec.is_none() && data.len() < policy.threshold {
+                return self.queue_small_file(policy, (name, data.to_vec(), metadata), codec);
+            }
+        }
+
+        let file_id = self.next_file_id();
+        let mut clock = Stopwatch::new(self.perf.is_some());
+        let secondary_hash = self.secondary_hash.map(|alg| alg.digest(data));
+        if let Some(perf) = &mut self.perf { perf.hash += clock.lap(); }
+
+        // Files too big for one SOLID block are chunked like any other.
+        if let Some(codec) = self.solid_codec.filter(|_| data.len() <= MAX_CHUNK_SIZE) {
             // ── Solid mode ──────────────────────────────────────────────────
-            let intra_offset = self.solid_buffer.len() as u64;
             let intra_length = data.len() as u64;
+            let mut clock    = Stopwatch::new(self.perf.is_some());
             let content_hash: [u8; 32] = blake3::hash(data).into();
+            if let Some(perf) = &mut self.perf {
+                perf.hash     += clock.lap();
+                perf.bytes_in += intra_length;
+            }
 
-            self.solid_file_ranges.push((file_id, intra_offset, intra_length, content_hash));
-            self.solid_buffer.extend_from_slice(data);
-
-            self.index.records.push(FileIndexRecord {
-                id:              file_id,
-                parent_id:       0,
-                name,
-                block_refs:      Vec::new(),
-                original_size:   data.len() as u64,
-                compressed_size: 0,
-                metadata:        HashMap::new(),
-            });
+            let mut record = FileIndexRecord {
+                id:                     file_id,
+                parent_id:              0,
+                name:                   name.name,
+                block_refs:             Vec::new(),
+                original_size:          intra_length,
+                compressed_size:        0,
+                unique_compressed_size: None,
+                metadata,
+                secondary_hash,
+                raw_name:               name.raw,
+            };
+            self.notify_file(Observer::on_file_start, &record);
+
+            // Identical members are stored once, like CAS chunks: a copy of
+            // a member of a written block gets its slice ref now, a copy
+            // within this session shares the original's range.  An empty
+            // member has nothing to slice (a ref with `intra_length` 0
+            // would name the whole block) and, like an empty chunked file,
+            // gets no refs.
+            match self.solid_dedup.get(&content_hash) {
+                _ if intra_length == 0 => {}
+                Some(SolidCopy::Written { slice }) => {
+                    record.compressed_size = self.stored_blocks[&slice.archive_offset].cost(slice);
+                    record.block_refs.push(slice.clone());
+                    self.notify_dedup(file_id, content_hash, intra_length, DedupSource::Solid);
+                }
+                Some(&SolidCopy::Pending { intra_offset }) => {
+                    self.solid_file_ranges.push((file_id, intra_offset, intra_length, content_hash));
+                    self.notify_dedup(file_id, content_hash, intra_length, DedupSource::Solid);
+                }
+                None => {
+                    if self.solid_block_full(data.len()) {
+                        self.start_solid_session(codec)?;
+                    }
+                    let intra_offset = self.solid_buffer.len() as u64;
+                    self.solid_file_ranges.push((file_id, intra_offset, intra_length, content_hash));
+                    self.solid_dedup.insert(content_hash, SolidCopy::Pending { intra_offset });
+                    self.solid_buffer.extend_from_slice(data);
+                }
+            }
+
+            self.notify_file(Observer::on_file_done, &record);
+            self.index.records.push(record);
             return Ok(());
         }
 
         // ── Normal (chunked CAS) mode ────────────────────────────────────────
-        self.superblock.add_required_codec(codec);
+        self.require_codec(codec);
 
         let mut record = FileIndexRecord {
-            id:              file_id,
-            parent_id:       0,
-            name,
-            block_refs:      Vec::new(),
-            original_size:   data.len() as u64,
-            compressed_size: 0,
-            metadata:        HashMap::new(),
+            id:                     file_id,
+            parent_id:              0,
+            name:                   name.name,
+            block_refs:             Vec::new(),
+            original_size:          data.len() as u64,
+            compressed_size:        0,
+            unique_compressed_size: None,
+            metadata,
+            secondary_hash,
+            raw_name:               name.raw,
         };
+        self.notify_file(Observer::on_file_start, &record);
+        let chunk_size = self.file_chunk_size(&record.name, data.len() as u64);
+        let level      = self.chunk_level(codec, data)?;
+        let chunks     = data.chunks(chunk_size).map(Ok::<_, io::Error>);
+        self.write_chunks(&mut record, chunks, codec, level)?;
+        self.push_chunked_record(record)
+    }
 
-        for (chunk_idx, chunk) in data.chunks(self.chunk_size).enumerate() {
-            let file_offset:  u64       = (chunk_idx * self.chunk_size) as u64;
-            let content_hash: [u8; 32]  = blake3::hash(chunk).into();
+    /// [`add_file_with_metadata`](Self::add_file_with_metadata) reading the
+    /// file from `reader`.  `len` is its expected size, used to pick the
+    /// chunk size and whether it is stored solid; the record holds what was
+    /// actually read.  A chunked file is read a chunk at a time, so at most
+    /// `queue_depth` of its chunks are in memory; one stored solid is read
+    /// whole.
+    pub fn add_file_from_reader(
+        &mut self,
+        name:     impl Into<EntryName>,
+        reader:   impl Read,
+        len:      u64,
+        codec:    CodecId,
+        metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        let name   = name.into();
+        let shown  = self.observer.as_ref().map(|_| name.name.clone());
+        let result = self.admit_name(name).and_then(|name| self.add_streamed(name, reader, len, codec, metadata));
+        self.report(shown.as_deref(), result)
+    }
 
-            if let Some(&(existing_offset, comp_len)) = self.block_dedup.get(&content_hash) {
-                // CAS hit — reuse existing block, no new I/O.
-                record.block_refs.push(BlockRef {
-                    content_hash,
-                    archive_offset: existing_offset,
-                    intra_offset:   0,
-                    intra_length:   0,
-                });
-                record.compressed_size += comp_len;
-            } else {
-                // New chunk — compress, (optionally) encrypt, write.
-                let (header, payload) = encode_block(
-                    BlockType::Data,
-                    file_id,
-                    file_offset,
-                    chunk,
-                    codec,
-                    self.compression_level,
-                    self.encryption_key.as_ref(),
-                ).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-
-                let archive_offset = self.writer.stream_position()?;
-                let comp_len       = payload.len() as u64;
-                header.write(&mut self.writer)?;
-                self.writer.write_all(&payload)?;
+    /// [`add_file_from_reader`](Self::add_file_from_reader) for a name
+    /// already admitted.
+    fn add_streamed(
+        &mut self,
+        name:         EntryName,
+        mut reader:   impl Read,
+        len:          u64,
+        codec:        CodecId,
+        mut metadata: HashMap<String, String>,
+    ) -> Result<()> {
+        let whole = match (self.solid_codec, self.auto_solid) {
+            (Some(_), _)      => len <= MAX_CHUNK_SIZE as u64,
+            (None, Some(pol)) => len < pol.threshold as u64,
+            (None, None)      => false,
+        };
+        if whole {
+            let mut data = Vec::with_capacity(len as usize);
+            reader.read_to_end(&mut data)?;
+            return self.add_admitted(name, &data, codec, metadata);
+        }
 
-                record.compressed_size += comp_len;
-                self.block_dedup.insert(content_hash, (archive_offset, comp_len));
-                record.block_refs.push(BlockRef {
-                    content_hash,
-                    archive_offset,
-                    intra_offset: 0,
-                    intra_length: 0,
-                });
+        let codec = self.resolve_codec(codec)?;
+        self.merge_default_metadata(&mut metadata);
+        self.require_codec(codec);
+
+        let mut record = FileIndexRecord {
+            id:                     self.next_file_id(),
+            parent_id:              0,
+            name:                   name.name,
+            block_refs:             Vec::new(),
+            // Expected until the file is read.
+            original_size:          len,
+            compressed_size:        0,
+            unique_compressed_size: None,
+            metadata,
+            secondary_hash:         None,
+            raw_name:               name.raw,
+        };
+        self.notify_file(Observer::on_file_start, &record);
+        let chunk_size = self.file_chunk_size(&record.name, len);
+        let read_chunk = |reader: &mut dyn Read| -> io::Result<Option<Vec<u8>>> {
+            let mut chunk = Vec::with_capacity(chunk_size.min(len as usize));
+            reader.take(chunk_size as u64).read_to_end(&mut chunk)?;
+            Ok((!chunk.is_empty()).then_some(chunk))
+        };
+        // The level is picked from the first chunk, which is read up front.
+        let first = read_chunk(&mut reader)?;
+        let level = self.chunk_level(codec, first.as_deref().unwrap_or_default())?;
+
+        let timed      = self.perf.is_some();
+        let mut hasher = self.secondary_hash.map(HashAlgorithm::hasher);
+        let mut read   = 0u64;
+        let mut hashed = std::time::Duration::ZERO;
+        let chunks = std::iter::successors(Some(Ok(first)), |_| Some(read_chunk(&mut reader)))
+            .map_while(|chunk| chunk.transpose())
+            .inspect(|chunk| if let Ok(chunk) = chunk {
+                read += chunk.len() as u64;
+                if let Some(hasher) = &mut hasher {
+                    let mut clock = Stopwatch::new(timed);
+                    hasher.update(chunk);
+                    hashed += clock.lap();
+                }
+            });
+        self.write_chunks(&mut record, chunks, codec, level)?;
+
+        if let Some(perf) = &mut self.perf { perf.hash += hashed; }
+        record.original_size  = read;
+        record.secondary_hash = hasher.map(|h| h.finalize());
+        self.push_chunked_record(record)
+    }
+
+    /// Record `codec` as required — or, for Zstd under a dictionary, the
+    /// dictionary that its blocks name instead.
+    fn require_codec(&mut self, codec: CodecId) {
+        match &self.dictionary {
+            Some(dict) if codec == CodecId::Zstd => self.superblock.add_required_uuid(&dict.uuid()),
+            _                                    => self.superblock.add_required_codec(codec),
+        }
+    }
+
+    /// `codec`, or the pipeline's codec once the pipeline is checked
+    /// against the writer's key.
+    fn resolve_codec(&self, codec: CodecId) -> Result<CodecId> {
+        match &self.pipeline {
+            Some(pipeline) => {
+                pipeline.validate(self.encryption_key.is_some())?;
+                Ok(pipeline.codec)
             }
+            None => Ok(codec),
         }
+    }
 
-        self.recovery_map.checkpoints.push(RecoveryCheckpoint {
-            archive_offset: self.writer.stream_position()?,
-            last_file_id:   file_id,
-            timestamp:      Utc::now().timestamp(),
-        });
+    fn notify_file(&self, event: fn(&(dyn Observer + 'static), &FileEvent), record: &FileIndexRecord) {
+        if let Some(observer) = &self.observer {
+            event(observer.as_ref(), &FileEvent { id: record.id, name: &record.name, size: record.original_size });
+        }
+    }
 
-        self.index.records.push(record);
+    fn notify_dedup(&self, file_id: u32, content_hash: [u8; 32], bytes: u64, source: DedupSource) {
+        if let Some(observer) = &self.observer {
+            observer.on_dedup_hit(&DedupEvent { file_id, content_hash, bytes, source });
+        }
+    }
+
+    /// Pass `result` through, telling the observer if it is an error.
+    fn report<T>(&self, name: Option<&str>, result: Result<T>) -> Result<T> {
+        if let (Some(observer), Err(error)) = (&self.observer, &result) {
+            observer.on_error(&ErrorEvent { name, error });
+        }
+        result
+    }
+
+    fn merge_default_metadata(&self, metadata: &mut HashMap<String, String>) {
+        for (key, value) in &self.default_metadata {
+            metadata.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+
+    /// Chunk size for a file of `len` bytes named `name`.
+    fn file_chunk_size(&self, name: &str, len: u64) -> usize {
+        match &self.chunk_policy {
+            Some(policy) => policy.chunk_size(name, len),
+            None         => self.chunk_size,
+        }.clamp(1, MAX_CHUNK_SIZE)
+    }
+
+    /// Level for a chunked file's DATA blocks, trial-compressing `sample`
+    /// when `auto_level` applies.
+    fn chunk_level(&mut self, codec: CodecId, sample: &[u8]) -> Result<i32> {
+        let mut clock = Stopwatch::new(self.perf.is_some());
+        let fixed = self.pipeline.as_ref().and_then(|p| p.level);
+        let level = match self.auto_level.filter(|_| !self.deterministic && fixed.is_none()) {
+            Some(auto) => auto.pick(codec, sample)?.unwrap_or(self.compression_level),
+            None       => fixed.unwrap_or(self.compression_level),
+        };
+        if let Some(perf) = &mut self.perf { perf.compress += clock.lap(); }
+        Ok(level)
+    }
+
+    fn queue_small_file(
+        &mut self,
+        policy: AutoSolid,
+        file:   (EntryName, Vec<u8>, HashMap<String, String>),
+        codec:  CodecId,
+    ) -> Result<()> {
+        let key   = solid_group_key(&file.0.name, codec);
+        let group = self.solid_groups.entry(key.clone())
+            .or_insert_with(|| SolidGroup { codec, bytes: 0, files: Vec::new() });
+        group.bytes += file.1.len();
+        group.files.push(file);
+        if group.bytes >= policy.block_size {
+            let group = self.solid_groups.remove(&key).expect("just inserted");
+            self.write_solid_group(group)?;
+        }
+        Ok(())
+    }
+
+    fn write_solid_group(&mut self, mut group: SolidGroup) -> Result<()> {
+        group.files.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+        self.start_solid_session(group.codec)?;
+        for (name, data, metadata) in group.files {
+            self.add_admitted(name, &data, group.codec, metadata)?;
+        }
+        self.flush_solid_session()
+    }
+
+    /// Write every pending auto-solid group, in extension order.
+    pub fn flush_auto_solid(&mut self) -> Result<()> {
+        for (_, group) in std::mem::take(&mut self.solid_groups) {
+            self.write_solid_group(group)?;
+        }
+        Ok(())
+    }
+
+    // ── Application data ────────────────────────────────────────────────────
+
+    /// Store `data` under `key` outside the file namespace, replacing an
+    /// earlier blob with that key.  Blobs are held until
+    /// [`finalize`](Self::finalize), which writes each to a META block of
+    /// its own — after every file, so a forward scan by a reader that
+    /// predates META blocks has recovered all file data before it stops at
+    /// one.  They take the INDEX codec and level and the archive's key.
+    pub fn put_app_data(&mut self, key: &str, data: Vec<u8>) -> Result<()> {
+        if key.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "app data key is empty").into());
+        }
+        if data.len() > MAX_CHUNK_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "app data '{key}' is {} bytes; a META block holds at most {MAX_CHUNK_SIZE}", data.len())).into());
+        }
+        self.app_data.insert(key.to_owned(), data);
+        Ok(())
+    }
+
+    /// Keys put so far, or carried in with a copied INDEX, in order.
+    pub fn app_data_keys(&self) -> Vec<String> {
+        let keys: BTreeSet<&String> = self.app_data.keys().chain(self.index.app_data.keys()).collect();
+        keys.into_iter().cloned().collect()
+    }
+
+    fn write_app_data(&mut self) -> Result<()> {
+        for (key, data) in std::mem::take(&mut self.app_data) {
+            let opts = EncodeOpts {
+                key:           self.encryption_key.as_ref(),
+                deterministic: self.deterministic,
+                stats:         None,
+                content_hash:  None,
+                filter:        None,
+                dictionary:    None,
+            };
+            let (header, payload) = encode_block_with(
+                BlockType::Meta, FILE_ID_SHARED, 0, &data, self.index_codec, self.index_level, opts)?;
+            let archive_offset = self.write_block(&header, &payload)?;
+            self.index.app_data.insert(key, BlockRef {
+                content_hash:   header.content_hash,
+                archive_offset,
+                intra_offset:   0,
+                intra_length:   0,
+                archive_id:     None,
+            });
+        }
         Ok(())
     }
 
@@ -253,38 +1335,77 @@ impl<W: Write + Seek> SixCyWriter<W> {
 
     /// Flush any open solid session, write the INDEX block, then patch the
     /// superblock at offset 0.  Must be called exactly once.
-    pub fn finalize(&mut self) -> io::Result<()> {
+    pub fn finalize(&mut self) -> Result<()> {
+        let result = self.finish();
+        self.report(None, result)
+    }
+
+    fn finish(&mut self) -> Result<()> {
         self.flush_solid_session()?;
+        self.flush_auto_solid()?;
+        self.write_app_data()?;
+
+        // Charge stored blocks to files now that every sharer is known.
+        let stored = &self.stored_blocks;
+        self.index.account_sizes(|br| stored.get(&br.archive_offset).map(|b| b.cost(br)));
 
         // Merkle root over all content hashes.
         self.index.compute_root_hash();
+        // Recomputed even for a copied INDEX, which may carry another key's.
+        self.index.key_check = self.encryption_key.as_ref().map(key_check).transpose()?;
+        // A copied INDEX keeps the policy its names were stored under.
+        self.index.name_policy.get_or_insert(self.name_policy);
 
         // Serialize the FileIndex.
-        let index_payload = self.index.to_bytes()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let index_payload = self.index.to_bytes()?;
+
+        // Deterministic mode without a fixed UUID: name the archive after
+        // its INDEX, which covers every name, size, and content hash.
+        if self.deterministic && self.superblock.archive_uuid.is_nil() {
+            if self.encryption_key.is_some() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                    "deterministic encrypted archives need a fixed UUID (the key is salted with it)").into());
+            }
+            let digest = blake3::derive_key("sixcy deterministic archive uuid v1", &index_payload);
+            let bytes: [u8; 16] = digest[..16].try_into().unwrap();
+            self.superblock.archive_uuid = uuid::Builder::from_custom_bytes(bytes).into_uuid();
+        }
+
+        // The INDEX codec is required to read anything at all.
+        self.superblock.add_required_codec(self.index_codec);
+
+        // Codec UUIDs that do not fit the superblock go in a CODECS block.
+        if let Some(overflow) = self.superblock.codec_overflow() {
+            let payload = overflow.concat();
+            let (header, on_disk) = encode_block(
+                BlockType::Codecs, FILE_ID_SHARED, 0, &payload, CodecId::None, 0, None)?;
+            let offset = self.write_block(&header, &on_disk)?;
+            self.superblock.codec_continuation = Some(CodecContinuation {
+                offset,
+                count: (payload.len() / 16) as u32,
+            });
+        }
 
-        // Write the INDEX block — codec=None (stored verbatim), unencrypted.
+        // Write the INDEX block with `index_codec`, unencrypted.
         let (idx_header, idx_on_disk) = encode_block(
             BlockType::Index,
             FILE_ID_SHARED,
             0,
             &index_payload,
-            CodecId::Zstd,           // compress the index with Zstd always
-            DEFAULT_COMPRESSION_LEVEL,
+            self.index_codec,
+            self.index_level,
             None,                     // index is never encrypted
-        ).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        )?;
 
-        let index_offset = self.writer.stream_position()?;
-        idx_header.write(&mut self.writer)?;
-        self.writer.write_all(&idx_on_disk)?;
+        let index_offset = self.write_block(&idx_header, &idx_on_disk)?;
 
         // Write the RecoveryMap (JSON blob, no block wrapper needed).
-        let recovery_bytes = self.recovery_map.to_bytes()
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let recovery_bytes = self.recovery_map.to_bytes().map_err(io::Error::other)?;
         let recovery_offset = self.writer.stream_position()?;
         // Write recovery map length prefix (LE u64) then data.
         self.writer.write_all(&(recovery_bytes.len() as u64).to_le_bytes())?;
         self.writer.write_all(&recovery_bytes)?;
+        self.finalized_len = Some(self.writer.stream_position()?);
 
         // Patch the superblock.
         self.superblock.index_offset = index_offset;
@@ -292,52 +1413,401 @@ impl<W: Write + Seek> SixCyWriter<W> {
         if self.encryption_key.is_some() {
             self.superblock.flags |= crate::superblock::SB_FLAG_ENCRYPTED;
         }
+        if !self.deterministic {
+            self.superblock.modified_at = Some(Utc::now().timestamp());
+        }
         // recovery_map_offset stored in superblock for diagnostics
         // (superblock doesn't have the field in v3; stored in RecoveryCheckpoint)
         let _ = recovery_offset; // acknowledged
 
+        if let Some(sync) = self.commit_sync { sync(&self.writer)?; }
         self.writer.seek(SeekFrom::Start(0))?;
         self.superblock.write(&mut self.writer)?;
+        if let Some(sync) = self.commit_sync { sync(&self.writer)?; }
 
+        if let Some(perf) = &mut self.perf { perf.finish(); }
         Ok(())
     }
 }
 
+// ── Block cache ──────────────────────────────────────────────────────────────
+
+/// Default byte budget for [`SixCyReader::enable_block_cache`].
+pub const DEFAULT_BLOCK_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Counters reported by [`SixCyReader::block_cache_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockCacheStats {
+    /// Block refs served from the cache, with no read or decode.
+    pub hits:   u64,
+    /// Blocks read and decoded while the cache was on.
+    pub misses: u64,
+    /// Plaintext bytes currently held.
+    pub bytes:  usize,
+}
+
+/// Decoded blocks a reader keeps for refs still to come; see
+/// [`SixCyReader::enable_block_cache`].
+pub(crate) struct BlockCache {
+    /// Archive offset → refs to that block not yet read.
+    remaining: HashMap<u64, usize>,
+    blocks:    HashMap<u64, Vec<u8>>,
+    /// Insertion order, for evicting the oldest block when over budget.
+    order:     VecDeque<u64>,
+    /// Refs whose bytes were checked against their `content_hash`:
+    /// (offset, intra_offset, intra_length, content_hash).
+    verified:  HashSet<(u64, u64, u64, [u8; 32])>,
+    max_bytes: usize,
+    stats:     BlockCacheStats,
+}
+
+impl BlockCache {
+    fn new(index: &FileIndex, own: &[u8; 16], max_bytes: usize) -> Self {
+        let mut remaining = HashMap::new();
+        for br in index.records.iter().flat_map(|r| &r.block_refs) {
+            if br.archive_id.is_none_or(|id| &id == own) {
+                *remaining.entry(br.archive_offset).or_insert(0) += 1;
+            }
+        }
+        Self {
+            remaining,
+            blocks:    HashMap::new(),
+            order:     VecDeque::new(),
+            verified:  HashSet::new(),
+            max_bytes,
+            stats:     BlockCacheStats::default(),
+        }
+    }
+
+    /// Keep a freshly decoded block if another ref to it is still to come.
+    /// Returns the block back when it is not kept.
+    fn offer(&mut self, offset: u64, block: Vec<u8>) -> Option<Vec<u8>> {
+        self.stats.misses += 1;
+        if self.remaining.get(&offset).is_none_or(|&n| n < 2) || block.len() > self.max_bytes {
+            return Some(block);
+        }
+        self.stats.bytes += block.len();
+        self.blocks.insert(offset, block);
+        self.order.push_back(offset);
+        while self.stats.bytes > self.max_bytes {
+            let Some(oldest) = self.order.pop_front() else { break };
+            self.evict(oldest);
+        }
+        None
+    }
+
+    /// Count one ref to `offset` as read, dropping the block after its last.
+    fn used(&mut self, offset: u64) {
+        if let Some(n) = self.remaining.get_mut(&offset) {
+            *n = n.saturating_sub(1);
+            if *n == 0 {
+                self.evict(offset);
+            }
+        }
+    }
+
+    fn evict(&mut self, offset: u64) {
+        if let Some(block) = self.blocks.remove(&offset) {
+            self.stats.bytes -= block.len();
+            crate::pool::shared().give(block);
+        }
+    }
+
+    /// Drop every held block, keeping the ref counts and verified refs.
+    fn clear_blocks(&mut self) {
+        for offset in std::mem::take(&mut self.order) {
+            self.evict(offset);
+        }
+    }
+}
+
+/// Append the part of `block` that `br` addresses to `out`.
+fn append_ref(block: &[u8], br: &BlockRef, out: &mut Vec<u8>) -> Result<usize> {
+    if !br.is_solid_slice() {
+        out.extend_from_slice(block);
+        return Ok(block.len());
+    }
+    let start = br.intra_offset as usize;
+    let end   = start + br.intra_length as usize;
+    if end > block.len() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Solid intra range {start}..{end} exceeds decompressed size {}",
+            block.len()
+        )).into());
+    }
+    out.extend_from_slice(&block[start..end]);
+    Ok(end - start)
+}
+
 // ── Reader ───────────────────────────────────────────────────────────────────
 
 pub struct SixCyReader<R: Read + Seek> {
     reader:             R,
     pub superblock:     Superblock,
-    pub index:          FileIndex,
+    /// Shared with handles from [`try_clone_with`](Self::try_clone_with);
+    /// modify through [`Arc::make_mut`].
+    pub index:          Arc<FileIndex>,
     pub decryption_key: Option<[u8; 32]>,
+    /// Base archives of an incremental snapshot chain, consulted for
+    /// block refs whose `archive_id` names another archive.
+    parents:            Vec<SixCyReader<R>>,
+    limits:             Limits,
+    /// Bytes decompressed so far, charged against `limits`.
+    decompressed_total: u64,
+    perf:               Option<PerfStats>,
+    /// Record position → start of each block ref within the file, built on
+    /// the first [`read_at`](Self::read_at) into that file.
+    ref_offsets:        HashMap<usize, Vec<u64>>,
+    reconstructed:      bool,
+    /// The INDEX was found by [`locate_index_from_tail`].
+    index_relocated:    bool,
+    block_cache:        Option<BlockCache>,
+    observer:           Option<Arc<dyn Observer>>,
+    /// See [`danger_skip_content_hash`](Self::danger_skip_content_hash).
+    skip_content_hash:  bool,
 }
 
 impl<R: Read + Seek> SixCyReader<R> {
-    pub fn new(reader: R) -> io::Result<Self> {
+    pub fn new(reader: R) -> Result<Self> {
         Self::with_key(reader, None)
     }
 
     /// Open an archive.  Performs an upfront codec availability check —
     /// fails immediately if the superblock lists a codec UUID not available
     /// in this build.  No partial opening, no negotiation.
-    pub fn with_key(mut reader: R, decryption_key: Option<[u8; 32]>) -> io::Result<Self> {
-        // Superblock::read already calls check_codecs() internally.
-        let sb = Superblock::read(&mut reader)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    pub fn with_key(reader: R, decryption_key: Option<[u8; 32]>) -> Result<Self> {
+        Self::with_limits(reader, decryption_key, Limits::default())
+    }
+
+    /// Open an archive from an untrusted source.  The INDEX is size- and
+    /// record-checked before it is allocated or parsed, every block's
+    /// declared sizes are checked before its payload is read, and the total
+    /// decompressed by this reader is capped.
+    pub fn with_limits(
+        reader:         R,
+        decryption_key: Option<[u8; 32]>,
+        limits:         Limits,
+    ) -> Result<Self> {
+        Self::with_deadline(reader, decryption_key, limits, None)
+    }
+
+    /// [`with_limits`](Self::with_limits) that gives up with
+    /// [`io::ErrorKind::TimedOut`] between the steps of the open once
+    /// `deadline` passes (see [`OpenLimits`](crate::limits::OpenLimits)).
+    pub fn with_deadline(
+        mut reader:     R,
+        decryption_key: Option<[u8; 32]>,
+        limits:         Limits,
+        deadline:       Option<Instant>,
+    ) -> Result<Self> {
+        // Superblock::read already calls check_codecs() internally; the
+        // continuation (if any) is checked as it is loaded.
+        check_deadline(deadline, "reading the superblock")?;
+        let mut sb = Superblock::read(&mut reader)?;
+        sb.load_codec_continuation(&mut reader)?;
+
+        // Read the INDEX block, or failing that the last one in the stream.
+        let mut relocated = false;
+        let index = match read_index_before(&mut reader, sb.index_offset, &limits, deadline) {
+            Ok((_, index)) => index,
+            Err(err) if err.kind() == io::ErrorKind::TimedOut => return Err(err),
+            Err(err) => match locate_index_from_tail_before(&mut reader, deadline)? {
+                Some(offset) if offset != sb.index_offset => {
+                    let (header, index) = read_index_before(&mut reader, offset, &limits, deadline)
+                        .map_err(|e| if e.kind() == io::ErrorKind::TimedOut { e } else { err })?;
+                    sb.index_offset = offset;
+                    sb.index_size   = header.comp_size as u64;
+                    relocated = true;
+                    index
+                }
+                _ => return Err(err),
+            },
+        };
+        if let (Some(key), Some(check)) = (&decryption_key, &index.key_check) {
+            verify_key_check(key, check)?;
+        }
+
+        let mut r = Self::assemble(reader, sb, index, decryption_key, limits);
+        r.index_relocated = relocated;
+        Ok(r)
+    }
+
+    /// Open an archive whose INDEX is unusable, with the file list rebuilt
+    /// by [`scan_blocks`](Self::scan_blocks) — names are synthesised and
+    /// solid-block contents are missing.  The superblock is read leniently,
+    /// so a damaged one does not prevent the scan.
+    pub fn reconstructed(
+        mut reader:     R,
+        decryption_key: Option<[u8; 32]>,
+        limits:         Limits,
+    ) -> Result<Self> {
+        let sb = Superblock::read_lenient(&mut reader)?.superblock;
+        let mut r = Self::assemble(reader, sb, FileIndex::default(), decryption_key, limits);
+        r.index         = Arc::new(r.scan_blocks()?);
+        r.reconstructed = true;
+        Ok(r)
+    }
+
+    /// A second reader over the same archive (and base chain) that shares
+    /// the parsed INDEX instead of re-reading it.  `reopen` supplies each
+    /// new stream from an existing one.  The clone starts with no block
+    /// cache, no perf stats, and a fresh decompression budget; it keeps
+    /// the observer and [`danger_skip_content_hash`](Self::danger_skip_content_hash).
+    pub fn try_clone_with<F: FnMut(&R) -> io::Result<R>>(&self, reopen: &mut F) -> io::Result<Self> {
+        let parents = self.parents.iter()
+            .map(|p| p.try_clone_with(reopen))
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            reader:             reopen(&self.reader)?,
+            superblock:         self.superblock.clone(),
+            index:              Arc::clone(&self.index),
+            decryption_key:     self.decryption_key,
+            parents,
+            limits:             self.limits,
+            decompressed_total: 0,
+            perf:               None,
+            ref_offsets:        HashMap::new(),
+            reconstructed:      self.reconstructed,
+            index_relocated:    self.index_relocated,
+            block_cache:        None,
+            observer:           self.observer.clone(),
+            skip_content_hash:  self.skip_content_hash,
+        })
+    }
+
+    fn assemble(
+        reader:         R,
+        superblock:     Superblock,
+        index:          FileIndex,
+        decryption_key: Option<[u8; 32]>,
+        limits:         Limits,
+    ) -> Self {
+        Self {
+            reader,
+            superblock,
+            index:              Arc::new(index),
+            decryption_key,
+            parents:            Vec::new(),
+            limits,
+            decompressed_total: 0,
+            perf:               None,
+            ref_offsets:        HashMap::new(),
+            reconstructed:      false,
+            index_relocated:    false,
+            block_cache:        None,
+            observer:           None,
+            skip_content_hash:  false,
+        }
+    }
+
+    /// True if [`index`](Self::index) was rebuilt by scanning rather than
+    /// read from the INDEX block (see [`reconstructed`](Self::reconstructed)).
+    pub fn is_reconstructed(&self) -> bool { self.reconstructed }
+
+    /// True if the superblock's `index_offset` was unusable and the INDEX
+    /// was found by [`locate_index_from_tail`]; `superblock.index_offset`
+    /// then holds the offset actually used.
+    pub fn is_index_relocated(&self) -> bool { self.index_relocated }
+
+    pub fn limits(&self) -> &Limits { &self.limits }
+
+    /// Stop (or, with `false`, resume) recomputing BLAKE3 over what this
+    /// reader and its base chain decode for files: each block's hash
+    /// against its header, and in
+    /// [`unpack_file_verified`](Self::unpack_file_verified) each ref's
+    /// against the INDEX.  Decoding gets roughly twice as fast for LZ4.
+    ///
+    /// Corrupt or tampered blocks then go unnoticed unless the codec or,
+    /// for encrypted archives, the GCM tag catches them, so this is only
+    /// for callers that verify the output end to end themselves — e.g.
+    /// streaming into a store that checksums what it receives.
+    /// [`verify_block_at`](Self::verify_block_at),
+    /// [`read_by_hash`](Self::read_by_hash), and
+    /// [`read_app_data`](Self::read_app_data) always check.
+    ///
+    /// Resuming drops the blocks the [block cache](Self::enable_block_cache)
+    /// took in while checks were off, so none of them is served unchecked.
+    pub fn danger_skip_content_hash(&mut self, skip: bool) {
+        if self.skip_content_hash && !skip {
+            if let Some(cache) = &mut self.block_cache {
+                cache.clear_blocks();
+            }
+        }
+        self.skip_content_hash = skip;
+        for parent in &mut self.parents {
+            parent.danger_skip_content_hash(skip);
+        }
+    }
+
+    /// True while [`danger_skip_content_hash`](Self::danger_skip_content_hash)
+    /// is in effect.
+    pub fn skips_content_hash(&self) -> bool { self.skip_content_hash }
+
+    /// Report each file unpacked from now on to `observer`; see
+    /// [`crate::events`].
+    pub fn set_observer(&mut self, observer: Option<Arc<dyn Observer>>) {
+        self.observer = observer;
+    }
+
+    /// Start collecting [`PerfStats`] for everything read from now on.
+    pub fn enable_perf_stats(&mut self) {
+        self.perf = Some(PerfStats::start());
+    }
+
+    /// Stats collected so far, if enabled.
+    pub fn perf_stats(&self) -> Option<PerfStats> {
+        self.perf.map(|p| p.snapshot())
+    }
+
+    // ── Snapshot chain ───────────────────────────────────────────────────────
+
+    /// Keep decoded blocks that more INDEX refs still point at, up to
+    /// `max_bytes` of plaintext, so a block shared by many files (CAS
+    /// dedup, or members of one SOLID block) is read, decrypted,
+    /// decompressed, and hash-checked once rather than once per ref.
+    ///
+    /// Refs are counted when the cache is enabled, and each block is dropped
+    /// after its last ref is read — a pass over the files in INDEX order,
+    /// like extraction, holds only blocks with uses still ahead.  When the
+    /// budget is exceeded the oldest block goes first.  Cache hits are not
+    /// charged against [`Limits::max_total_decompressed`].  Blocks in
+    /// attached parents are not cached.  Calling again resets the counts.
+    pub fn enable_block_cache(&mut self, max_bytes: usize) {
+        let own = *self.superblock.archive_uuid.as_bytes();
+        self.disable_block_cache();
+        self.block_cache = Some(BlockCache::new(&self.index, &own, max_bytes));
+    }
 
-        // Read and decompress the INDEX block.
-        reader.seek(SeekFrom::Start(sb.index_offset))?;
-        let idx_header = BlockHeader::read(&mut reader)?;
-        let mut idx_payload = vec![0u8; idx_header.comp_size as usize];
-        reader.read_exact(&mut idx_payload)?;
+    /// Drop the block cache and everything it holds.
+    pub fn disable_block_cache(&mut self) {
+        if let Some(cache) = self.block_cache.take() {
+            for block in cache.blocks.into_values() {
+                crate::pool::shared().give(block);
+            }
+        }
+    }
+
+    pub fn block_cache_stats(&self) -> Option<BlockCacheStats> {
+        self.block_cache.as_ref().map(|c| c.stats)
+    }
 
-        let idx_raw = decode_block(&idx_header, &idx_payload, None)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    /// Install `cache` and return the one it replaces, so an operation can
+    /// run with a cache of its own and then put the caller's back.
+    pub(crate) fn swap_block_cache(&mut self, cache: Option<BlockCache>) -> Option<BlockCache> {
+        std::mem::replace(&mut self.block_cache, cache)
+    }
 
-        let index = FileIndex::from_bytes(&idx_raw)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    /// Attach a base archive so external block refs can be resolved.
+    /// Parents may themselves carry parents; lookup walks the whole chain.
+    pub fn attach_parent(&mut self, mut parent: SixCyReader<R>) {
+        if self.skip_content_hash { parent.danger_skip_content_hash(true); }
+        self.parents.push(parent);
+    }
 
-        Ok(Self { reader, superblock: sb, index, decryption_key })
+    /// True if `archive_id` names this archive or any attached ancestor.
+    pub fn contains_archive(&self, archive_id: &[u8; 16]) -> bool {
+        self.superblock.archive_uuid.as_bytes() == archive_id
+            || self.parents.iter().any(|p| p.contains_archive(archive_id))
     }
 
     // ── Block reconstruction (no INDEX) ──────────────────────────────────────
@@ -351,19 +1821,22 @@ impl<R: Read + Seek> SixCyReader<R> {
     /// intra-file ranges are not reconstructed.
     ///
     /// Returns the reconstructed [`FileIndex`] without modifying `self.index`.
-    pub fn scan_blocks(&mut self) -> io::Result<FileIndex> {
+    pub fn scan_blocks(&mut self) -> Result<FileIndex> {
+        self.scan_blocks_with_progress(|_, _| {})
+    }
+
+    /// [`scan_blocks`](Self::scan_blocks), calling `progress` after every
+    /// block with `(bytes_scanned, total_bytes)` like
+    /// [`recovery::scan`](crate::recovery::scan).
+    pub fn scan_blocks_with_progress(&mut self, mut progress: impl FnMut(u64, u64)) -> Result<FileIndex> {
+        let total = self.reader.seek(SeekFrom::End(0))?;
         self.reader.seek(SeekFrom::Start(SUPERBLOCK_SIZE as u64))?;
 
         // file_id → Vec<(file_offset, BlockRef)>
         let mut chunks: HashMap<u32, Vec<(u64, BlockRef)>> = HashMap::new();
         let mut orig_sizes: HashMap<u32, u64> = HashMap::new();
 
-        loop {
-            let pos = match self.reader.stream_position() {
-                Ok(p) => p,
-                Err(_) => break,
-            };
-
+        while let Ok(pos) = self.reader.stream_position() {
             let header = match BlockHeader::read(&mut self.reader) {
                 Ok(h)  => h,
                 Err(_) => break,   // EOF or corruption — stop scan here
@@ -372,12 +1845,17 @@ impl<R: Read + Seek> SixCyReader<R> {
             // Skip the payload bytes to reach the next block.
             let skip = header.comp_size as u64;
             match self.reader.seek(SeekFrom::Current(skip as i64)) {
-                Ok(_)  => {},
-                Err(_) => break,
+                Ok(end) => progress(end.min(total), total),
+                Err(_)  => break,
             }
 
             match header.block_type {
-                BlockType::Index => break, // reached the end sentinel
+                // An appended archive continues after the recovery map.
+                BlockType::Index => {
+                    let next = skip_recovery_map(&mut self.reader, pos + BLOCK_HEADER_SIZE as u64 + skip)?;
+                    self.reader.seek(SeekFrom::Start(next))?;
+                }
+                BlockType::Codecs | BlockType::Meta => {}
                 BlockType::Solid => {
                     // Solid block — we know its position but not which files
                     // it contains (intra-offsets are in the INDEX).
@@ -395,6 +1873,7 @@ impl<R: Read + Seek> SixCyReader<R> {
                         archive_offset: pos,
                         intra_offset:   0,
                         intra_length:   0,
+                        archive_id:     None,
                     };
                     chunks.entry(fid)
                         .or_default()
@@ -412,55 +1891,293 @@ impl<R: Read + Seek> SixCyReader<R> {
         }).collect();
         records.sort_by_key(|r| r.id);
 
-        let mut idx = FileIndex { records, root_hash: [0u8; 32] };
+        let mut idx = FileIndex { records, ..FileIndex::default() };
         idx.compute_root_hash();
         Ok(idx)
     }
 
-    // ── Internal helpers ─────────────────────────────────────────────────────
+    /// Files the INDEX has lost: DATA blocks no record references, grouped
+    /// by the `file_id` in their headers, for ids no record carries.  Each
+    /// is returned as a record named `orphan_<id>` (see [`Self::scan_blocks`]
+    /// for how sizes are inferred).  Chunks the lost file shared with
+    /// another file through CAS dedup were written under that file's id, so
+    /// such a record has gaps, which reading closes up.
+    pub fn find_orphans(&mut self) -> Result<Vec<FileIndexRecord>> {
+        let referenced: std::collections::HashSet<u64> = self.local_block_offsets().into_iter().collect();
+        let ids: std::collections::HashSet<u32> = self.index.records.iter().map(|r| r.id).collect();
+        let mut orphans: Vec<FileIndexRecord> = self.scan_blocks()?.records.into_iter()
+            .filter(|r| !ids.contains(&r.id))
+            .filter(|r| r.block_refs.iter().any(|br| !referenced.contains(&br.archive_offset)))
+            .collect();
+        for rec in &mut orphans {
+            rec.name = format!("orphan_{:08x}", rec.id);
+        }
+        Ok(orphans)
+    }
+
+    // ── Block access ─────────────────────────────────────────────────────────
+
+    /// Every block header in the archive with its offset, in file order,
+    /// from the first block through the INDEX — whatever the INDEX
+    /// references.  Only headers are read; a header that fails to parse
+    /// is yielded as an error and ends the walk.
+    pub fn iter_block_headers(&mut self) -> Result<BlockHeaders<'_, R>> {
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        Ok(BlockHeaders {
+            reader: &mut self.reader,
+            next:   Some(SUPERBLOCK_SIZE as u64),
+            end,
+            index:  self.superblock.index_offset,
+        })
+    }
 
-    fn read_block_at(&mut self, offset: u64) -> io::Result<(BlockHeader, Vec<u8>)> {
+    /// Read the header and raw on-disk payload of the block at `offset`
+    /// without decrypting or decompressing it.
+    pub fn read_block_at(&mut self, offset: u64) -> Result<(BlockHeader, Vec<u8>)> {
+        let mut clock = Stopwatch::new(self.perf.is_some());
         self.reader.seek(SeekFrom::Start(offset))?;
         let header = BlockHeader::read(&mut self.reader)?;
-        let mut payload = vec![0u8; header.comp_size as usize];
+        self.limits.check_block(&header)?;
+        let mut payload = crate::pool::shared().take(header.comp_size as usize);
+        payload.resize(header.comp_size as usize, 0);
         self.reader.read_exact(&mut payload)?;
+        if let Some(perf) = &mut self.perf {
+            perf.read     += clock.lap();
+            perf.bytes_in += (BLOCK_HEADER_SIZE + payload.len()) as u64;
+            perf.blocks   += 1;
+        }
         Ok((header, payload))
     }
 
-    fn decompress_ref(&mut self, br: &BlockRef) -> io::Result<Vec<u8>> {
-        let (header, payload) = self.read_block_at(br.archive_offset)?;
-        let decompressed = decode_block(&header, &payload, self.decryption_key.as_ref())
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    /// Read the block at `offset` and run every check a normal read does —
+    /// header CRC, limits, decryption, decompression, BLAKE3 — discarding
+    /// the plaintext.
+    pub fn verify_block_at(&mut self, offset: u64) -> Result<()> {
+        let (header, payload) = self.read_block_at(offset)?;
+        let pool  = crate::pool::shared();
+        let mut plain = pool.take(0);
+        let result = decode_block_timed(&header, &payload, self.decryption_key.as_ref(),
+                                        &self.limits, &mut plain, self.perf.as_mut(), true);
+        pool.give(payload);
+        pool.give(plain);
+        Ok(result?)
+    }
 
-        if br.is_solid_slice() {
-            let start = br.intra_offset as usize;
-            let end   = start + br.intra_length as usize;
-            if end > decompressed.len() {
-                return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
-                    "Solid intra range {start}..{end} exceeds decompressed size {}",
-                    decompressed.len()
-                )));
+    /// Offsets of the distinct blocks stored in this archive that the INDEX
+    /// references (refs into attached ancestors excluded), ascending.
+    pub fn local_block_offsets(&self) -> Vec<u64> {
+        let own = self.superblock.archive_uuid.as_bytes();
+        let mut offsets: Vec<u64> = self.index.block_refs()
+            .filter(|br| br.archive_id.is_none_or(|id| &id == own))
+            .map(|br| br.archive_offset)
+            .collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        offsets
+    }
+
+    /// The attached ancestor holding `br`, or `None` if it lives here.
+    fn foreign_parent(&mut self, br: &BlockRef) -> Result<Option<&mut Self>> {
+        let Some(id) = br.archive_id else { return Ok(None) };
+        if &id == self.superblock.archive_uuid.as_bytes() {
+            return Ok(None);
+        }
+        self.parents.iter_mut()
+            .find(|p| p.contains_archive(&id))
+            .map(Some)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!(
+                "Block lives in base archive {} which is not attached",
+                uuid::Uuid::from_bytes(id),
+            )).into())
+    }
+
+    fn decompress_ref(&mut self, br: &BlockRef) -> Result<Vec<u8>> {
+        let mut out = crate::pool::shared().take(0);
+        self.decompress_ref_into(br, &mut out)?;
+        Ok(out)
+    }
+
+    /// Append the bytes `br` addresses to `out`.  The on-disk payload and,
+    /// for solid slices, the whole decoded block are pooled scratch.
+    fn decompress_ref_into(&mut self, br: &BlockRef, out: &mut Vec<u8>) -> Result<()> {
+        if let Some(parent) = self.foreign_parent(br)? {
+            return parent.decompress_ref_into(br, out);
+        }
+
+        if let Some(cache) = &mut self.block_cache {
+            if let Some(block) = cache.blocks.get(&br.archive_offset) {
+                let n = append_ref(block, br, out)?;
+                cache.stats.hits += 1;
+                cache.used(br.archive_offset);
+                if let Some(perf) = &mut self.perf { perf.bytes_out += n as u64; }
+                return Ok(());
             }
-            Ok(decompressed[start..end].to_vec())
-        } else {
-            Ok(decompressed)
         }
+
+        let pool = crate::pool::shared();
+        let (header, payload) = self.read_block_at(br.archive_offset)?;
+        self.decompressed_total += header.orig_size as u64;
+        self.limits.check_total(self.decompressed_total)?;
+
+        let key = self.decryption_key.as_ref();
+        if !br.is_solid_slice() && self.block_cache.is_none() {
+            let start  = out.len();
+            let result = decode_block_timed(&header, &payload, key, &self.limits, out, self.perf.as_mut(),
+                                            !self.skip_content_hash);
+            pool.give(payload);
+            result?;
+            if let Some(perf) = &mut self.perf { perf.bytes_out += (out.len() - start) as u64; }
+            return Ok(());
+        }
+
+        let mut block = pool.take(0);
+        let result = decode_block_timed(&header, &payload, key, &self.limits, &mut block, self.perf.as_mut(),
+                                        !self.skip_content_hash);
+        pool.give(payload);
+        result?;
+        let n = append_ref(&block, br, out);
+        let unkept = match &mut self.block_cache {
+            Some(cache) => {
+                let unkept = cache.offer(br.archive_offset, block);
+                cache.used(br.archive_offset);
+                unkept
+            }
+            None => Some(block),
+        };
+        if let Some(block) = unkept { pool.give(block); }
+        let n = n?;
+        if let Some(perf) = &mut self.perf { perf.bytes_out += n as u64; }
+        Ok(())
     }
 
     // ── Public API ───────────────────────────────────────────────────────────
 
+    /// How many of record `pos`'s block refs make up its contents.  A
+    /// zero-byte file reads none: older writers gave an empty SOLID member
+    /// a ref with `intra_length` 0, which names the whole block.
+    fn refs_to_read(&self, pos: usize) -> usize {
+        let record = &self.index.records[pos];
+        match record.original_size {
+            0 => 0,
+            _ => record.block_refs.len(),
+        }
+    }
+
+    /// Position of record `file_id` in the INDEX.
+    fn record_pos(&self, file_id: u32) -> Result<usize> {
+        self.index.records.iter()
+            .position(|r| r.id == file_id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found").into())
+    }
+
+    /// Run `read` on the record `file_id`, telling the observer when it
+    /// starts and how it ends.
+    fn observed<T>(&mut self, file_id: u32, read: impl FnOnce(&mut Self, usize) -> Result<T>) -> Result<T> {
+        let Some(observer) = self.observer.clone() else {
+            let pos = self.record_pos(file_id)?;
+            return read(self, pos);
+        };
+        let pos = match self.record_pos(file_id) {
+            Ok(pos)    => pos,
+            Err(error) => {
+                observer.on_error(&ErrorEvent { name: None, error: &error });
+                return Err(error);
+            }
+        };
+        let index  = Arc::clone(&self.index);
+        let record = &index.records[pos];
+        let event  = FileEvent { id: record.id, name: &record.name, size: record.original_size };
+        observer.on_file_start(&event);
+        let result = read(self, pos);
+        match &result {
+            Ok(_)      => observer.on_file_done(&event),
+            Err(error) => observer.on_error(&ErrorEvent { name: Some(&record.name), error }),
+        }
+        result
+    }
+
     /// Return the complete contents of a file by record ID.
-    pub fn unpack_file(&mut self, file_id: u32) -> io::Result<Vec<u8>> {
-        let record = self.index.records.iter()
-            .find(|r| r.id == file_id)
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))?;
+    pub fn unpack_file(&mut self, file_id: u32) -> Result<Vec<u8>> {
+        self.observed(file_id, |r, pos| {
+            let record = &r.index.records[pos];
+            let mut out = Vec::with_capacity(record.original_size as usize);
+            for i in 0..r.refs_to_read(pos) {
+                let br = r.index.records[pos].block_refs[i].clone();
+                r.decompress_ref_into(&br, &mut out)?;
+            }
+            Ok(out)
+        })
+    }
+
+    /// [`unpack_file`](Self::unpack_file) that also checks the bytes each
+    /// block ref yields against the ref's own `content_hash`, and returns
+    /// their lengths.  Block decoding only checks a block against its
+    /// header, so this catches INDEX refs pointing at the wrong block and
+    /// bad solid slices.
+    pub fn unpack_file_verified(&mut self, file_id: u32) -> Result<(Vec<u8>, Vec<u64>)> {
+        self.observed(file_id, |r, pos| r.unpack_verified_at(file_id, pos))
+    }
+
+    fn unpack_verified_at(&mut self, file_id: u32, pos: usize) -> Result<(Vec<u8>, Vec<u64>)> {
+        let record = &self.index.records[pos];
+        let mut out  = Vec::with_capacity(record.original_size as usize);
+        let mut lens = Vec::with_capacity(record.block_refs.len());
+        for i in 0..self.refs_to_read(pos) {
+            let br = self.index.records[pos].block_refs[i].clone();
+            let start = out.len();
+            self.decompress_ref_into(&br, &mut out)?;
+            // With the block cache on, a local ref already checked is not re-hashed.
+            let checked = (br.archive_offset, br.intra_offset, br.intra_length, br.content_hash);
+            let cache   = self.block_cache.as_mut().filter(|_| br.archive_id.is_none());
+            if self.skip_content_hash || cache.as_ref().is_some_and(|c| c.verified.contains(&checked)) {
+                lens.push((out.len() - start) as u64);
+                continue;
+            }
+            if blake3::hash(&out[start..]) != br.content_hash {
+                return Err(CodecError::Decompression(format!(
+                    "BLAKE3 content hash mismatch in block ref {i} of file {file_id}"
+                )).into());
+            }
+            if let Some(cache) = cache {
+                cache.verified.insert(checked);
+            }
+            lens.push((out.len() - start) as u64);
+        }
+        Ok((out, lens))
+    }
 
-        let refs = record.block_refs.clone();
-        let mut out = Vec::with_capacity(record.original_size as usize);
-        for br in &refs {
-            out.extend(self.decompress_ref(br)?);
+    /// The bytes of the first block ref whose `content_hash` is `hash` — a
+    /// whole chunk, or a file stored in a SOLID block — or `None` if the
+    /// INDEX has no such ref.  The result is checked against `hash`.
+    pub fn read_by_hash(&mut self, hash: &[u8; 32]) -> Result<Option<Vec<u8>>> {
+        let Some(br) = self.index.records.iter()
+            .flat_map(|r| &r.block_refs)
+            .find(|br| &br.content_hash == hash)
+            .cloned()
+        else {
+            return Ok(None);
+        };
+        let data = self.decompress_ref(&br)?;
+        if blake3::hash(&data) != *hash {
+            return Err(CodecError::Decompression(format!(
+                "BLAKE3 content hash mismatch for block {}", hex::encode(hash)
+            )).into());
         }
-        Ok(out)
+        Ok(Some(data))
+    }
+
+    /// The blob stored under `key` by
+    /// [`SixCyWriter::put_app_data`], or `None` if there is none.  The
+    /// result is checked against the INDEX's `content_hash` for it.
+    pub fn read_app_data(&mut self, key: &str) -> Result<Option<Vec<u8>>> {
+        let Some(br) = self.index.app_data.get(key).cloned() else { return Ok(None) };
+        let data = self.decompress_ref(&br)?;
+        if blake3::hash(&data) != br.content_hash {
+            return Err(CodecError::Decompression(format!(
+                "BLAKE3 content hash mismatch for app data '{key}'"
+            )).into());
+        }
+        Ok(Some(data))
     }
 
     /// Random-access read spanning chunk boundaries.
@@ -468,46 +2185,456 @@ impl<R: Read + Seek> SixCyReader<R> {
     /// Fills `buf` with bytes starting at `offset` within the file identified
     /// by `file_id`.  Reads continue across block boundaries until `buf` is
     /// full or EOF is reached.  Returns bytes copied.
-    pub fn read_at(&mut self, file_id: u32, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
-        let record = self.index.records.iter()
-            .find(|r| r.id == file_id)
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "File not found"))?;
-
-        if offset >= record.original_size || buf.is_empty() {
+    ///
+    /// The first block is found by binary search over the file's block ref
+    /// offsets, which are worked out from block headers on the first call
+    /// for each file and cached.
+    pub fn read_at(&mut self, file_id: u32, offset: u64, buf: &mut [u8]) -> Result<usize> {
+        let pos = self.record_pos(file_id)?;
+        if offset >= self.index.records[pos].original_size || buf.is_empty() {
             return Ok(0);
         }
 
-        let refs = record.block_refs.clone();
-        let mut file_pos    = 0u64;
-        let mut buf_written = 0usize;
+        let refs = self.index.records[pos].block_refs.len();
+        if !self.ref_offsets.contains_key(&pos) {
+            let mut offsets = Vec::with_capacity(refs);
+            let mut at = 0u64;
+            for i in 0..refs {
+                offsets.push(at);
+                let br = self.index.records[pos].block_refs[i].clone();
+                at += self.ref_len(&br)?;
+            }
+            self.ref_offsets.insert(pos, offsets);
+        }
 
-        for br in &refs {
+        let first = self.ref_offsets[&pos].partition_point(|&o| o <= offset).saturating_sub(1);
+        let mut buf_written = 0usize;
+        for i in first..refs {
             if buf_written == buf.len() { break; }
 
-            let block = self.decompress_ref(br)?;
-            let block_len = block.len() as u64;
-            let block_end = file_pos + block_len;
-
-            // Skip blocks entirely before the requested offset.
-            if block_end <= offset {
-                file_pos = block_end;
+            let file_pos = self.ref_offsets[&pos][i];
+            let br       = self.index.records[pos].block_refs[i].clone();
+            let block = self.decompress_ref(&br)?;
+            let read_start = offset.saturating_sub(file_pos) as usize;
+            if read_start >= block.len() {
+                crate::pool::shared().give(block);
                 continue;
             }
-
-            let read_start = if offset > file_pos {
-                (offset - file_pos) as usize
-            } else {
-                // Offset is before or at this block start — cover the overlap.
-                0
-            };
             let to_copy = (buf.len() - buf_written).min(block.len() - read_start);
             buf[buf_written..buf_written + to_copy]
                 .copy_from_slice(&block[read_start..read_start + to_copy]);
 
             buf_written += to_copy;
-            file_pos     = block_end;
+            crate::pool::shared().give(block);
         }
 
         Ok(buf_written)
     }
+
+    /// Up to `len` bytes of file `file_id` from `offset`, for a file stored
+    /// as one SOLID slice: the block is decoded only as far as the end of
+    /// the range, so a small read near the start of a member skips the rest
+    /// of the block.  A cached block is sliced instead.  Other files fall
+    /// back to [`read_at`](Self::read_at).
+    ///
+    /// A range short of the block's end is not checked against any content
+    /// hash (see [`crate::block::decode_block_prefix_into`]).
+    pub fn read_solid_range(&mut self, file_id: u32, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let pos  = self.record_pos(file_id)?;
+        let size = self.index.records[pos].original_size;
+        let end  = offset.saturating_add(len).min(size);
+        if offset >= end {
+            return Ok(Vec::new());
+        }
+
+        let br = match self.index.records[pos].block_refs.as_slice() {
+            [br] if br.is_solid_slice() => br.clone(),
+            _ => {
+                let mut out = vec![0u8; (end - offset) as usize];
+                let n = self.read_at(file_id, offset, &mut out)?;
+                out.truncate(n);
+                return Ok(out);
+            }
+        };
+        let from  = (br.intra_offset + offset) as usize;
+        let block = self.solid_prefix(&br, (br.intra_offset + end) as usize)?;
+        if block.len() < (br.intra_offset + end) as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "Solid intra range {}..{} exceeds decompressed size {}",
+                br.intra_offset, br.intra_offset + br.intra_length, block.len(),
+            )).into());
+        }
+        let out = block[from..].to_vec();
+        crate::pool::shared().give(block);
+        Ok(out)
+    }
+
+    /// The first `prefix` bytes of the SOLID block `br` points into.
+    fn solid_prefix(&mut self, br: &BlockRef, prefix: usize) -> Result<Vec<u8>> {
+        if let Some(parent) = self.foreign_parent(br)? {
+            return parent.solid_prefix(br, prefix);
+        }
+        let pool = crate::pool::shared();
+        let mut out = pool.take(0);
+        if let Some(cache) = &mut self.block_cache {
+            if let Some(block) = cache.blocks.get(&br.archive_offset) {
+                out.extend_from_slice(&block[..prefix.min(block.len())]);
+                cache.stats.hits += 1;
+                cache.used(br.archive_offset);
+                return Ok(out);
+            }
+        }
+
+        let (header, payload) = self.read_block_at(br.archive_offset)?;
+        let prefix = prefix.min(header.orig_size as usize);
+        self.decompressed_total += prefix as u64;
+        self.limits.check_total(self.decompressed_total)?;
+        let result = crate::block::decode_block_prefix_into(&header, &payload,
+            self.decryption_key.as_ref(), &self.limits, prefix, &mut out);
+        pool.give(payload);
+        result?;
+        if let Some(perf) = &mut self.perf { perf.bytes_out += out.len() as u64; }
+        Ok(out)
+    }
+
+    /// The header of the block `br` points into — in a base archive for a
+    /// foreign ref — without reading its payload.
+    pub fn block_header(&mut self, br: &BlockRef) -> Result<BlockHeader> {
+        if let Some(parent) = self.foreign_parent(br)? {
+            return parent.block_header(br);
+        }
+        self.reader.seek(SeekFrom::Start(br.archive_offset))?;
+        Ok(BlockHeader::read(&mut self.reader)?)
+    }
+
+    /// The recovery map stored after the INDEX block, and where it ends.
+    fn read_recovery_map(&mut self) -> Result<(RecoveryMap, u64)> {
+        let at = self.superblock.index_offset + BLOCK_HEADER_SIZE as u64 + self.superblock.index_size;
+        self.reader.seek(SeekFrom::Start(at))?;
+        let mut len = [0u8; 8];
+        self.reader.read_exact(&mut len)?;
+        let len = u64::from_le_bytes(len);
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut bytes)?;
+        if (bytes.len() as u64) < len {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "recovery map is truncated").into());
+        }
+        let map = RecoveryMap::from_bytes(&bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("recovery map: {e}")))?;
+        Ok((map, at + 8 + len))
+    }
+
+    /// Number of file bytes `br` yields, read from its block header (or the
+    /// solid slice length) without decoding the payload.
+    pub(crate) fn ref_len(&mut self, br: &BlockRef) -> Result<u64> {
+        if br.is_solid_slice() {
+            return Ok(br.intra_length);
+        }
+        if let Some(parent) = self.foreign_parent(br)? {
+            return parent.ref_len(br);
+        }
+        self.reader.seek(SeekFrom::Start(br.archive_offset))?;
+        Ok(BlockHeader::read(&mut self.reader)?.orig_size as u64)
+    }
+}
+
+/// Iterator returned by [`SixCyReader::iter_block_headers`].
+pub struct BlockHeaders<'a, R> {
+    reader: &'a mut R,
+    /// Offset of the next header; `None` once the walk is over.
+    next:   Option<u64>,
+    end:    u64,
+    /// The superblock's `index_offset`, after which the archive ends.
+    index:  u64,
+}
+
+impl<R: Read + Seek> Iterator for BlockHeaders<'_, R> {
+    type Item = Result<(u64, BlockHeader)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let pos = self.next.take().filter(|&pos| pos < self.end)?;
+        let header = self.reader.seek(SeekFrom::Start(pos))
+            .and_then(|_| BlockHeader::read(&mut *self.reader));
+        let header = match header {
+            Ok(header) => header,
+            Err(e)     => return Some(Err(e.into())),
+        };
+        let after = pos + BLOCK_HEADER_SIZE as u64 + header.comp_size as u64;
+        self.next = match header.block_type {
+            BlockType::Index if pos == self.index => None,
+            // Superseded by an append: blocks resume after its recovery map.
+            BlockType::Index => match skip_recovery_map(&mut *self.reader, after) {
+                Ok(next) => Some(next),
+                Err(e)   => return Some(Err(e.into())),
+            },
+            _ => Some(after),
+        };
+        Some(Ok((pos, header)))
+    }
+}
+
+/// Offset of the next block after an INDEX block ending at `at`: past the
+/// recovery map `finalize()` writes there, unless a block header follows
+/// directly.  Only an appended archive has blocks after it.
+pub(crate) fn skip_recovery_map<R: Read + Seek>(r: &mut R, at: u64) -> io::Result<u64> {
+    r.seek(SeekFrom::Start(at))?;
+    let mut word = Vec::with_capacity(8);
+    r.take(8).read_to_end(&mut word)?;
+    if word.len() < 8 || word[..4] == BLOCK_MAGIC.to_le_bytes() {
+        return Ok(at);
+    }
+    let len = u64::from_le_bytes(word.try_into().expect("eight bytes"));
+    Ok(at.saturating_add(8).saturating_add(len))
+}
+
+// ── INDEX location ───────────────────────────────────────────────────────────
+
+/// Read and parse the INDEX block whose header is at `offset`, rejecting
+/// any other block type.
+pub(crate) fn read_index_at<R: Read + Seek>(r: &mut R, offset: u64, limits: &Limits) -> Result<(BlockHeader, FileIndex)> {
+    read_index_before(r, offset, limits, None)
+}
+
+/// [`read_index_at`], giving up between steps once `deadline` passes.
+fn read_index_before<R: Read + Seek>(
+    r:        &mut R,
+    offset:   u64,
+    limits:   &Limits,
+    deadline: Option<Instant>,
+) -> Result<(BlockHeader, FileIndex)> {
+    check_deadline(deadline, "reading the INDEX")?;
+    r.seek(SeekFrom::Start(offset))?;
+    let header = BlockHeader::read(&mut *r)?;
+    if header.block_type != BlockType::Index {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("block at index_offset {offset} is {:?}, not INDEX", header.block_type)).into());
+    }
+    limits.check_index_block(&header)?;
+    let mut payload = vec![0u8; header.comp_size as usize];
+    r.read_exact(&mut payload)?;
+    check_deadline(deadline, "decompressing the INDEX")?;
+    let raw = decode_block(&header, &payload, None)?;
+    check_deadline(deadline, "parsing the INDEX")?;
+    Ok((header, FileIndex::from_bytes_limited(&raw, limits)?))
+}
+
+/// Bytes read per step of [`locate_index_from_tail`].
+const TAIL_SCAN_WINDOW: usize = 1 << 20;
+
+/// Offset of the last INDEX block header in `r`: the highest offset past
+/// the superblock holding a CRC-valid header of type INDEX whose payload
+/// ends within the stream.  Scans backwards from the end a window at a
+/// time and stops at the first hit, so a trailing INDEX costs one read.
+/// In a file of concatenated archives this finds the last one's.
+pub fn locate_index_from_tail<R: Read + Seek>(r: &mut R) -> io::Result<Option<u64>> {
+    locate_index_from_tail_before(r, None)
+}
+
+/// [`locate_index_from_tail`], giving up before a window once `deadline`
+/// passes.
+fn locate_index_from_tail_before<R: Read + Seek>(r: &mut R, deadline: Option<Instant>) -> io::Result<Option<u64>> {
+    let len      = r.seek(SeekFrom::End(0))?;
+    let magic    = BLOCK_MAGIC.to_le_bytes();
+    let floor    = SUPERBLOCK_SIZE as u64;
+    let mut end  = len;
+    let mut buf  = Vec::new();
+    while end > floor {
+        check_deadline(deadline, "scanning for the INDEX")?;
+        // Candidates start in [start, end); read a header's worth past `end`.
+        let start = end.saturating_sub(TAIL_SCAN_WINDOW as u64).max(floor);
+        let stop  = (end + BLOCK_HEADER_SIZE as u64 - 1).min(len);
+        buf.resize((stop - start) as usize, 0);
+        r.seek(SeekFrom::Start(start))?;
+        r.read_exact(&mut buf)?;
+        let mut i = (end - start) as usize;
+        while let Some(at) = buf[..(i + 3).min(buf.len())].windows(4).rposition(|w| w == magic) {
+            i = at;
+            let Some(bytes) = buf.get(at..at + BLOCK_HEADER_SIZE) else { continue };
+            let offset = start + at as u64;
+            let fits   = |h: &BlockHeader| offset + (BLOCK_HEADER_SIZE as u64) + h.comp_size as u64 <= len;
+            match BlockHeader::parse(bytes.try_into().expect("header-sized slice")) {
+                Ok(h) if h.block_type == BlockType::Index && fits(&h) => return Ok(Some(offset)),
+                _ => {}
+            }
+            if at == 0 { break; }
+        }
+        end = start;
+    }
+    Ok(None)
+}
+
+// ── Concatenated archives ────────────────────────────────────────────────────
+
+/// A view of `inner` starting at byte `base`, so an archive stored after
+/// others in the same file (`cat a.6cy b.6cy > c.6cy`) reads with its own
+/// offsets.  `SeekFrom::Start` and reported positions are section-relative.
+pub struct Section<R> {
+    inner: R,
+    base:  u64,
+}
+
+impl<R: Seek> Section<R> {
+    /// Wrap `inner` and position it at the start of the section.
+    pub fn new(mut inner: R, base: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(base))?;
+        Ok(Self { inner, base })
+    }
+}
+
+impl<R> Section<R> {
+    /// Absolute offset of the section within the underlying stream.
+    pub fn base(&self) -> u64 { self.base }
+
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+impl<R: Read> Read for Section<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for Section<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(p) => SeekFrom::Start(self.base + p),
+            other              => other,
+        };
+        self.inner.seek(pos)?
+            .checked_sub(self.base)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before start of section"))
+    }
+}
+
+impl Section<SharedFile> {
+    /// Another view of the same section with its own position.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Section::new(self.inner.clone(), self.base)
+    }
+}
+
+// ── Shared file handles ──────────────────────────────────────────────────────
+
+/// A read-only [`File`] shared between handles, each with its own
+/// position.  Reads are positional (`pread` on Unix, `seek_read` on
+/// Windows), so clones used from different threads do not disturb one
+/// another; cloning costs one `Arc` increment.  Elsewhere reads go through
+/// the shared OS cursor and clones must not be used concurrently.
+#[derive(Debug, Clone)]
+pub struct SharedFile {
+    file: Arc<File>,
+    pos:  u64,
+}
+
+impl SharedFile {
+    pub fn new(file: File) -> Self {
+        Self { file: Arc::new(file), pos: 0 }
+    }
+}
+
+impl Read for SharedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(unix)]
+        let n = std::os::unix::fs::FileExt::read_at(&*self.file, buf, self.pos)?;
+        #[cfg(windows)]
+        let n = std::os::windows::fs::FileExt::seek_read(&*self.file, buf, self.pos)?;
+        #[cfg(not(any(unix, windows)))]
+        let n = {
+            (&*self.file).seek(SeekFrom::Start(self.pos))?;
+            (&*self.file).read(buf)?
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for SharedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(p)   => Some(p),
+            SeekFrom::Current(d) => self.pos.checked_add_signed(d),
+            SeekFrom::End(d)     => self.file.metadata()?.len().checked_add_signed(d),
+        };
+        self.pos = target.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek"))?;
+        Ok(self.pos)
+    }
+}
+
+/// Reserve `len` bytes of disk for `file` without changing its length (see
+/// the module docs).  Returns `false`, reserving nothing, where the
+/// platform or filesystem has no way to; a full disk is an error.
+pub fn preallocate(file: &File, len: u64) -> io::Result<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let len = libc::off_t::try_from(len)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "preallocation too large"))?;
+        // SAFETY: the descriptor is open for the duration of the call.
+        if unsafe { libc::fallocate(file.as_raw_fd(), libc::FALLOC_FL_KEEP_SIZE, 0, len) } == 0 {
+            return Ok(true);
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EOPNOTSUPP | libc::ENOSYS | libc::EINVAL) => Ok(false),
+            _ => Err(err),
+        }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::AsRawHandle;
+        use windows_sys::Win32::Storage::FileSystem::{
+            FileAllocationInfo, SetFileInformationByHandle, FILE_ALLOCATION_INFO,
+        };
+        let info = FILE_ALLOCATION_INFO {
+            AllocationSize: i64::try_from(len)
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "preallocation too large"))?,
+        };
+        // SAFETY: the handle is open and `info` outlives the call.
+        let ok = unsafe {
+            SetFileInformationByHandle(file.as_raw_handle() as _, FileAllocationInfo,
+                (&info as *const FILE_ALLOCATION_INFO).cast(), std::mem::size_of::<FILE_ALLOCATION_INFO>() as u32)
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(true)
+    }
+    #[cfg(not(any(target_os = "linux", windows)))]
+    {
+        let _ = (file, len);
+        Ok(false)
+    }
+}
+
+/// Base offsets of every archive in a stream of concatenated archives.
+///
+/// Each archive ends with its recovery map; if another superblock magic
+/// follows, it starts the next section.  Anything else after an archive is
+/// ignored.  The first section must be a valid archive; later sections
+/// whose magic matches must be too.
+pub fn find_sections<R: Read + Seek>(r: &mut R) -> Result<Vec<u64>> {
+    let len = r.seek(SeekFrom::End(0))?;
+    let mut bases = Vec::new();
+    let mut base  = 0u64;
+    loop {
+        r.seek(SeekFrom::Start(base))?;
+        let sb = Superblock::read(&mut *r)?;
+        bases.push(base);
+
+        // INDEX block, then the length-prefixed recovery map.
+        let map_at = base + sb.index_offset + BLOCK_HEADER_SIZE as u64 + sb.index_size;
+        if map_at + 8 > len { break; }
+        r.seek(SeekFrom::Start(map_at))?;
+        let mut map_len = [0u8; 8];
+        r.read_exact(&mut map_len)?;
+        let end = (map_at + 8).saturating_add(u64::from_le_bytes(map_len));
+        if end.saturating_add(SUPERBLOCK_SIZE as u64) > len { break; }
+
+        r.seek(SeekFrom::Start(end))?;
+        let mut magic = [0u8; 4];
+        r.read_exact(&mut magic)?;
+        if &magic != crate::superblock::MAGIC { break; }
+        base = end;
+    }
+    Ok(bases)
 }
@@ -10,25 +10,106 @@
 //! - The INDEX block is at the end; the full block list is reconstructible by
 //!   scanning forward from `SUPERBLOCK_SIZE` without the INDEX
 //! - The plugin C ABI (`plugin.rs`) is stable at `SIXCY_PLUGIN_ABI_VERSION=1`
+//!
+//! Without the default `std` feature only `core_reader` (feature `core`) is
+//! compiled, on `no_std` + `alloc`: header parsing, CRC32/BLAKE3 checks, and
+//! stored (`None` codec) blocks.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "core")]
+pub mod core_reader;
 
+#[cfg(feature = "std")]
+pub mod error;
+#[cfg(feature = "std")]
 pub mod superblock;
+#[cfg(feature = "std")]
 pub mod codec;
+#[cfg(feature = "std")]
 pub mod crypto;
+#[cfg(feature = "std")]
 pub mod block;
+#[cfg(feature = "std")]
 pub mod index;
+#[cfg(feature = "std")]
+pub mod limits;
+#[cfg(feature = "std")]
 pub mod recovery;
+#[cfg(feature = "std")]
 pub mod io_stream;
+#[cfg(feature = "std")]
 pub mod archive;
+#[cfg(feature = "std")]
+pub mod atomic_file;
+#[cfg(feature = "std")]
 pub mod plugin;
+#[cfg(feature = "std")]
 pub mod perf;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "std")]
+pub mod dedup;
+#[cfg(feature = "std")]
+pub mod pool;
+#[cfg(feature = "std")]
+pub mod pipeline;
+#[cfg(feature = "std")]
+pub mod store;
+#[cfg(feature = "std")]
+pub mod delta;
+#[cfg(feature = "std")]
+pub mod optimize;
+#[cfg(feature = "std")]
+pub mod convert;
+#[cfg(feature = "std")]
+pub mod remote;
+#[cfg(feature = "std")]
+pub mod bundle;
+#[cfg(feature = "std")]
+pub mod archive_set;
+#[cfg(feature = "std")]
+pub mod sync;
+#[cfg(feature = "std")]
+pub mod scrub;
+#[cfg(feature = "std")]
+pub mod attest;
+#[cfg(feature = "std")]
+pub mod names;
+#[cfg(feature = "std")]
+pub mod events;
+#[cfg(feature = "std")]
+pub mod winpath;
+#[cfg(feature = "windows-metadata")]
+pub mod winmeta;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
 // Flat re-exports for the most common types.
+#[cfg(feature = "std")]
 pub use superblock::Superblock;
+#[cfg(feature = "std")]
 pub use codec::{CodecId, get_codec, get_codec_by_uuid, CodecError};
+#[cfg(feature = "std")]
 pub use block::{BlockHeader, BlockType, encode_block, decode_block,
                 BLOCK_HEADER_SIZE, BLOCK_MAGIC};
-pub use index::{FileIndex, FileIndexRecord, BlockRef};
+#[cfg(feature = "std")]
+pub use index::{FileIndex, FileIndexRecord, BlockRef, EntryOrder};
+#[cfg(feature = "std")]
 pub use crypto::{derive_key, CryptoError};
-pub use archive::{Archive, PackOptions, FileInfo};
+#[cfg(feature = "std")]
+pub use archive::{Archive, PackOptions, PackReport, DuplicateFile, FileInfo, ExtractOptions, DedupStrategy};
+#[cfg(feature = "std")]
 pub use plugin::{SixcyCodecPlugin, PluginCodec, SIXCY_PLUGIN_ABI_VERSION};
+#[cfg(feature = "std")]
 pub use recovery::{RecoveryReport, RecoveryQuality, BlockHealth, scan_file};
+#[cfg(feature = "std")]
+pub use store::BlockStore;
+#[cfg(feature = "std")]
+pub use limits::{Limits, OpenLimits};
+#[cfg(feature = "std")]
+pub use error::SixcyError;
@@ -0,0 +1,179 @@
+//! Resource limits for opening untrusted archives.
+//!
+//! Every size in a block header is attacker-controlled.  Without limits a
+//! crafted archive can declare a 4 GiB `orig_size` per block, an INDEX that
+//! inflates to gigabytes of JSON, or millions of records, and a service that
+//! opens it gets OOM-killed.  [`Limits`] bounds each of these and is enforced
+//! by [`crate::block::decode_block_limited`], [`crate::index::FileIndex::from_bytes_limited`],
+//! [`crate::io_stream::SixCyReader::with_limits`], and the recovery extractor.
+//!
+//! Brotli and LZMA streams also name the window their decoder must keep
+//! (up to 1 GiB and 4 GiB), allocated per block whatever the block's size.
+//! `max_brotli_window` and `max_lzma_dict` cap it, checked against the
+//! stream header ([`crate::codec::declared_window`]) before decoding starts.
+//!
+//! Independently of any configured limit, decoders never produce more than
+//! the `orig_size` a block header declares: a codec stream that inflates
+//! past it is rejected mid-stream instead of being buffered first.
+//!
+//! [`OpenLimits`] bounds opening alone — the INDEX and a deadline — for
+//! services that must answer "is this upload an archive, and what is in
+//! it" in bounded time.
+
+use std::io;
+use std::time::Instant;
+use thiserror::Error;
+
+use crate::block::BlockHeader;
+use crate::codec::{declared_window, CodecId};
+
+/// A configured limit was exceeded.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Resource limit exceeded: {what} is {value}, limit is {limit}")]
+pub struct LimitExceeded {
+    pub what:  &'static str,
+    pub value: u64,
+    pub limit: u64,
+}
+
+impl From<LimitExceeded> for io::Error {
+    fn from(e: LimitExceeded) -> Self { io::Error::new(io::ErrorKind::InvalidData, e) }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Largest `orig_size` or `comp_size` accepted for one block.
+    pub max_block_size:         u64,
+    /// Total bytes one reader may decompress over its lifetime.
+    pub max_total_decompressed: u64,
+    /// Largest INDEX accepted, compressed or decompressed.
+    pub max_index_size:         u64,
+    /// Most records an INDEX may list.
+    pub max_records:            usize,
+    /// Largest Brotli window a block's stream may declare.
+    pub max_brotli_window:      u64,
+    /// Largest LZMA dictionary a block's stream may declare.
+    pub max_lzma_dict:          u64,
+}
+
+impl Default for Limits {
+    /// No configured limits — the historical behaviour.  Only the implicit
+    /// `orig_size` bound on decoder output applies.
+    fn default() -> Self { Self::UNLIMITED }
+}
+
+impl Limits {
+    pub const UNLIMITED: Limits = Limits {
+        max_block_size:         u64::MAX,
+        max_total_decompressed: u64::MAX,
+        max_index_size:         u64::MAX,
+        max_records:            usize::MAX,
+        max_brotli_window:      u64::MAX,
+        max_lzma_dict:          u64::MAX,
+    };
+
+    /// Conservative preset for archives from untrusted sources: 64 MiB per
+    /// block, 4 GiB per reader, 64 MiB INDEX, one million records, and
+    /// 16 MiB Brotli windows (the standard maximum) and LZMA dictionaries.
+    pub fn untrusted() -> Self {
+        Self {
+            max_block_size:         64 * 1024 * 1024,
+            max_total_decompressed: 4 * 1024 * 1024 * 1024,
+            max_index_size:         64 * 1024 * 1024,
+            max_records:            1_000_000,
+            max_brotli_window:      16 * 1024 * 1024,
+            max_lzma_dict:          16 * 1024 * 1024,
+        }
+    }
+
+    fn check(what: &'static str, value: u64, limit: u64) -> Result<(), LimitExceeded> {
+        if value > limit {
+            return Err(LimitExceeded { what, value, limit });
+        }
+        Ok(())
+    }
+
+    /// Reject a block whose declared sizes exceed `max_block_size` — before
+    /// its payload is read or allocated.
+    pub fn check_block(&self, header: &BlockHeader) -> Result<(), LimitExceeded> {
+        Self::check("block orig_size", header.orig_size as u64, self.max_block_size)?;
+        Self::check("block comp_size", header.comp_size as u64, self.max_block_size)
+    }
+
+    /// Reject an INDEX block whose declared sizes exceed `max_index_size`.
+    pub fn check_index_block(&self, header: &BlockHeader) -> Result<(), LimitExceeded> {
+        Self::check("INDEX orig_size", header.orig_size as u64, self.max_index_size)?;
+        Self::check("INDEX comp_size", header.comp_size as u64, self.max_index_size)
+    }
+
+    /// Reject a `codec` stream whose header declares a decoder window above
+    /// `max_brotli_window` or `max_lzma_dict`.
+    pub fn check_window(&self, codec: CodecId, stream: &[u8]) -> Result<(), LimitExceeded> {
+        let (what, limit) = match codec {
+            CodecId::Brotli => ("Brotli window", self.max_brotli_window),
+            CodecId::Lzma   => ("LZMA dictionary", self.max_lzma_dict),
+            _               => return Ok(()),
+        };
+        match declared_window(codec, stream) {
+            Some(window) => Self::check(what, window, limit),
+            None         => Ok(()),
+        }
+    }
+
+    pub fn check_records(&self, count: usize) -> Result<(), LimitExceeded> {
+        Self::check("INDEX record count", count as u64, self.max_records as u64)
+    }
+
+    /// Reject a running decompression total above `max_total_decompressed`.
+    pub fn check_total(&self, total: u64) -> Result<(), LimitExceeded> {
+        Self::check("total decompressed", total, self.max_total_decompressed)
+    }
+}
+
+/// Bounds on opening an archive, for
+/// [`Archive::open_with_limits`](crate::archive::Archive::open_with_limits).
+///
+/// The deadline is checked between the steps of an open — reading the
+/// superblock, each window of a tail scan for a misplaced INDEX, reading,
+/// decompressing, and parsing the INDEX — and a step already under way is
+/// bounded by `max_index_bytes` rather than by the clock.  It does not
+/// apply to reads after the open returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenLimits {
+    /// Largest INDEX accepted, compressed or decompressed.
+    pub max_index_bytes: u64,
+    /// Most records the INDEX may list.
+    pub max_records:     usize,
+    /// Fail with [`io::ErrorKind::TimedOut`] once this passes.
+    pub deadline:        Option<Instant>,
+}
+
+impl Default for OpenLimits {
+    /// No limits and no deadline, as [`Limits::default`].
+    fn default() -> Self {
+        Self { max_index_bytes: u64::MAX, max_records: usize::MAX, deadline: None }
+    }
+}
+
+impl OpenLimits {
+    /// The INDEX bounds of [`Limits::untrusted`], with no deadline.
+    pub fn untrusted() -> Self {
+        let limits = Limits::untrusted();
+        Self { max_index_bytes: limits.max_index_size, max_records: limits.max_records, deadline: None }
+    }
+
+    /// The reader [`Limits`] these imply: the INDEX bounds, nothing else.
+    pub fn limits(&self) -> Limits {
+        Limits { max_index_size: self.max_index_bytes, max_records: self.max_records, ..Limits::UNLIMITED }
+    }
+}
+
+/// Fail with [`io::ErrorKind::TimedOut`] if `deadline` has passed;
+/// `step` names what was about to start.
+pub(crate) fn check_deadline(deadline: Option<Instant>, step: &str) -> io::Result<()> {
+    match deadline {
+        Some(deadline) if Instant::now() >= deadline => Err(io::Error::new(io::ErrorKind::TimedOut,
+            format!("open deadline passed before {step}"))),
+        _ => Ok(()),
+    }
+}
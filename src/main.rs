@@ -1,12 +1,31 @@
 use clap::{Parser, Subcommand};
-use sixcy::archive::{Archive, PackOptions};
-use sixcy::codec::{CodecId, uuid_to_string};
-use sixcy::io_stream::DEFAULT_CHUNK_SIZE;
+use sixcy::archive::{Archive, DedupStrategy, DuplicateFile, ExtractOptions, ExtractProgress, FileInfo, MergeConflict,
+                     OverwritePolicy, PackOptions, WindowsNames};
+use sixcy::attest::InclusionProof;
+use sixcy::bench::{self, BenchOptions};
+use sixcy::dedup::DedupSimulator;
+use sixcy::codec::{codec_display_name, codec_name, codec_source, load_codec_names, registered_dictionary,
+                   CodecId, CodecSource, Dictionary, uuid_to_string};
+use sixcy::convert;
+use sixcy::index::{EntryOrder, HashAlgorithm};
+use sixcy::io_stream::{validate_chunk_size, AdaptiveChunks, AutoLevel, AutoSolid, ChunkPolicy, SolidOrder,
+                       DEFAULT_COMPRESSION_LEVEL};
+use sixcy::names::{EntryName, NamePolicy};
+use sixcy::optimize::{optimize, CodecPolicy, OptimizeOptions};
 use sixcy::perf;
+use sixcy::remote::{is_remote_url, HttpSource};
+use sixcy::pipeline::{BlockPipeline, Cipher};
+use sixcy::superblock::SuperblockError;
+use sixcy::{BlockHeader, SixcyError};
+use std::io::{IsTerminal, Write};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
-#[command(name = "6cy", version = "1.0.0", about = "The .6cy container format CLI")]
+#[command(name = "6cy", version = "1.0.0", about = "The .6cy container format CLI",
+          after_help = EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -21,17 +40,96 @@ enum Commands {
         /// Codec: zstd (default), lz4, brotli, lzma, none
         #[arg(short, long, default_value = "zstd")]
         codec: String,
+        /// Compression level, or `auto` to pick one per file by trial compression
         #[arg(short, long, default_value = "3")]
-        level: i32,
-        /// Maximum chunk size in KiB (default 4096 = 4 MiB)
-        #[arg(long, default_value = "4096")]
-        chunk_size: usize,
-        /// Combine all inputs into a single solid block
+        level: String,
+        /// Chunk size in KiB, 64..=1048576 (default by codec: lzma 16384, brotli 8192,
+        /// lz4 1024, others 4096)
+        #[arg(long)]
+        chunk_size: Option<usize>,
+        /// Size chunks per file: whole files up to --chunk-size, larger
+        /// chunks for huge files up to --max-chunk-size
+        #[arg(long)]
+        adaptive_chunks: bool,
+        /// Largest adaptive chunk in KiB
+        #[arg(long, default_value = "65536", requires = "adaptive_chunks")]
+        max_chunk_size: usize,
+        /// Combine all inputs into a solid block (see --solid-block-size)
         #[arg(short, long)]
         solid: bool,
+        /// Group files smaller than --solid-threshold into solid blocks by extension
+        #[arg(long, conflicts_with = "solid")]
+        auto_solid: bool,
+        /// Auto-solid size threshold in KiB
+        #[arg(long, default_value = "256")]
+        solid_threshold: usize,
+        /// Start a new solid block before one would exceed this many MiB
+        #[arg(long)]
+        solid_block_size: Option<usize>,
+        /// Start a new solid block once one holds this many files
+        #[arg(long)]
+        solid_max_files: Option<usize>,
+        /// Member layout in solid blocks: insertion, extension, size, similarity
+        #[arg(long, default_value = "insertion")]
+        solid_order: String,
+        /// Compression level for solid blocks (default: --level, or 3 with --level auto)
+        #[arg(long)]
+        solid_level: Option<i32>,
+        /// Level for the INDEX block
+        #[arg(long, default_value_t = sixcy::io_stream::INDEX_COMPRESSION_LEVEL)]
+        index_level: i32,
+        /// Codec for the INDEX block: zstd, or none for readers without codecs
+        #[arg(long, default_value = "zstd")]
+        index_codec: String,
         /// Encrypt with AES-256-GCM
         #[arg(short, long)]
         password: Option<String>,
+        /// Write an incremental snapshot that reuses blocks from this archive
+        #[arg(long)]
+        base: Option<PathBuf>,
+        /// Byte-reproducible output: inputs sorted by name, no timestamps,
+        /// UUID derived from content (or --uuid)
+        #[arg(long)]
+        deterministic: bool,
+        /// Fixed archive UUID (required with --deterministic --password)
+        #[arg(long)]
+        uuid: Option<uuid::Uuid>,
+        /// Print per-stage timings (hash, compress, encrypt, write) and files stored as duplicates
+        #[arg(long)]
+        stats: bool,
+        /// Also record each file's digest: sha256 | sha512
+        #[arg(long, value_name = "ALG")]
+        hash: Option<String>,
+        /// Metadata stored in every file's record, KEY=VALUE (repeatable)
+        #[arg(long = "meta", value_name = "KEY=VALUE")]
+        meta: Vec<String>,
+        /// Read every block back after writing and check it against what was written
+        #[arg(long)]
+        verify: bool,
+        /// Block stages, overriding --codec and --level: [FILTER,]CODEC[:LEVEL][,CIPHER],
+        /// e.g. delta4,zstd:19 (filters: delta1|2|4|8, shuffle2|4|8; cipher aes-256-gcm,
+        /// implied by --password)
+        #[arg(long, value_name = "SPEC")]
+        pipeline: Option<String>,
+        /// Reserve the total input size on disk before packing
+        #[arg(long)]
+        preallocate: bool,
+        /// Chunks per file compressed ahead of writing (memory ≈ depth × chunk size);
+        /// default two per worker thread
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        queue_depth: Option<u64>,
+        /// Names that may not extract faithfully elsewhere (not UTF-8, not NFC,
+        /// or differing only by case): raw | normalize | reject
+        #[arg(long, default_value = "raw", value_parser = parse_name_policy)]
+        names: NamePolicy,
+        /// Compress zstd blocks with this trained dictionary, from the store
+        /// named by $SIXCY_DICTIONARIES (see `store train`)
+        #[arg(long, value_name = "UUID")]
+        dictionary: Option<uuid::Uuid>,
+        /// Add the inputs to the existing archive --output instead of replacing it;
+        /// content it already stores is not written again
+        #[arg(long, conflicts_with_all = ["base", "deterministic", "uuid"])]
+        append: bool,
         #[arg(short, long, required = true, num_args = 1..)]
         input: Vec<PathBuf>,
     },
@@ -42,18 +140,143 @@ enum Commands {
         output_dir: PathBuf,
         #[arg(short, long)]
         password: Option<String>,
+        /// Base archive(s) of an incremental snapshot (repeatable)
+        #[arg(long)]
+        base: Vec<PathBuf>,
+        /// How to write entries with identical content: copy | hardlink | reflink
+        #[arg(long, default_value = "copy")]
+        dedup: String,
+        /// Replace files that already exist (the default)
+        #[arg(long, conflicts_with_all = ["skip_existing", "keep_newer", "resume"])]
+        overwrite: bool,
+        /// Leave files that already exist untouched
+        #[arg(long, conflicts_with_all = ["keep_newer", "resume"])]
+        skip_existing: bool,
+        /// Keep existing files modified after the archive or already identical
+        #[arg(long, conflicts_with = "resume")]
+        keep_newer: bool,
+        /// Continue an interrupted unpack: keep files whose size and BLAKE3 hashes match
+        #[arg(long)]
+        resume: bool,
+        /// Skip checking each file's blocks against its INDEX content hashes
+        #[arg(long, conflicts_with = "paranoid")]
+        no_verify: bool,
+        /// Re-hash the extracted files and require them to reproduce the root hash
+        #[arg(long)]
+        paranoid: bool,
+        /// Skip all BLAKE3 checks; only for output verified end to end elsewhere
+        #[arg(long, conflicts_with = "paranoid")]
+        danger_skip_hashes: bool,
+        /// Drop this many leading path components from each entry name
+        #[arg(long, default_value = "0")]
+        strip_components: usize,
+        /// Rewrite a leading name prefix, OLD=NEW (repeatable; first match wins)
+        #[arg(long)]
+        transform: Vec<String>,
+        /// Names Windows cannot create: keep | rename | reject
+        /// (default: rename on Windows, keep elsewhere)
+        #[arg(long)]
+        windows_names: Option<String>,
+        /// Extraction order: index (as stored) or name
+        #[arg(long, default_value = "index")]
+        order: String,
+        /// Show byte-level progress and an ETA on stderr
+        #[arg(long)]
+        progress: bool,
+    },
+    /// Write the archive's files as a tar stream (stdout by default)
+    ToTar {
+        input: PathBuf,
+        /// Write the tar here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(short, long)]
+        password: Option<String>,
+        /// Base archive(s) of an incremental snapshot (repeatable)
+        #[arg(long)]
+        base: Vec<PathBuf>,
     },
     /// List archive contents
     List {
+        /// Archive path, or an http(s):// or s3:// URL (fetches only the INDEX)
         input: PathBuf,
+        /// Print recorded file digests as a checksum manifest (`sha256sum -c` format)
+        #[arg(long)]
+        digests: bool,
+        /// Skip this many entries
+        #[arg(long, default_value = "0")]
+        offset: usize,
+        /// Print at most this many entries
+        #[arg(long)]
+        limit: Option<usize>,
+        /// Only entries whose metadata has KEY=VALUE (repeatable; all must match)
+        #[arg(long, value_name = "KEY=VALUE")]
+        filter: Vec<String>,
+        /// Entry order: index (as stored) or name
+        #[arg(long, default_value = "index")]
+        order: String,
+        /// For a URL: give up on each request after this many seconds
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<f64>,
     },
     /// Show archive metadata
     Info {
         input: PathBuf,
+        /// Only report where each required codec comes from; fail if any is missing
+        #[arg(long)]
+        check_codecs: bool,
+        /// Codec plugin library to load first (repeatable; needs the `plugins` feature)
+        #[arg(long = "plugin", value_name = "PATH")]
+        plugins: Vec<PathBuf>,
+    },
+    /// Verify a random sample of blocks; verify all of them if any sample fails
+    Health {
+        input: PathBuf,
+        /// Fraction of blocks to sample (0.0–1.0)
+        #[arg(long, default_value = "0.05")]
+        sample: f64,
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+    /// Verify only blocks not verified within --max-age, journaling each check
+    Scrub {
+        input: PathBuf,
+        /// Journal of per-block checks; created on the first run
+        #[arg(long, value_name = "PATH")]
+        state: PathBuf,
+        /// Re-verify blocks last verified longer ago than this (s, m, h or d suffix)
+        #[arg(long, default_value = "30d", value_parser = parse_age)]
+        max_age: Duration,
+        /// Verify at most N blocks this run, oldest first
+        #[arg(long, value_name = "N")]
+        max_blocks: Option<usize>,
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+    /// Find files whose blocks survive but that the INDEX no longer lists
+    Fsck {
+        input: PathBuf,
+        #[arg(short, long)]
+        password: Option<String>,
+        /// Write a repaired copy with the orphans added as orphan_<id> entries
+        #[arg(long, requires = "output")]
+        resurrect: bool,
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
     /// Scan block headers and reconstruct the file list without the INDEX block
     Scan {
         input: PathBuf,
+        /// Print progress as JSON lines on stderr instead of a progress bar
+        #[arg(long)]
+        progress_json: bool,
+    },
+    /// Dump every block header: offset, type, codec, file, sizes, flags, hash
+    Blocks {
+        input: PathBuf,
+        /// One JSON object per block instead of a table
+        #[arg(long)]
+        json: bool,
     },
     /// Full index-bypass recovery: scan, assess, and extract all recoverable data
     Recover {
@@ -65,16 +288,74 @@ enum Commands {
         /// Print per-block health log
         #[arg(long)]
         verbose: bool,
+        /// Decode every block and verify its BLAKE3 hash while scanning
+        #[arg(long)]
+        deep: bool,
+        /// Save blocks with an unknown codec as raw payload + JSON header entries
+        #[arg(long)]
+        raw_unknown: bool,
+        /// Stop at the first damaged block instead of searching past it for the next
+        #[arg(long)]
+        no_resync: bool,
+        /// Write recovered files into OUTPUT as a directory instead of an archive
+        #[arg(long)]
+        dir: bool,
+        /// Print progress as JSON lines on stderr instead of a progress bar
+        #[arg(long)]
+        progress_json: bool,
     },
     /// Re-compress at maximum Zstd ratio
     Optimize {
         input:  PathBuf,
         #[arg(short, long)]
         output: PathBuf,
+        /// Password of the input archive
         #[arg(short, long)]
         password: Option<String>,
+        /// Re-encrypt the output under a new password (default: keep the input's)
+        #[arg(long)]
+        new_password: Option<String>,
+        #[arg(short, long, default_value = "zstd")]
+        codec: String,
         #[arg(short, long, default_value = "19")]
         level: i32,
+        /// Per-file override PATTERN=CODEC[:LEVEL], e.g. '*.log=brotli:11' (repeatable)
+        #[arg(long)]
+        policy: Vec<String>,
+    },
+    /// Encrypt an archive without recompressing it
+    Encrypt {
+        input:  PathBuf,
+        /// Write here instead of replacing INPUT
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(short, long)]
+        password: String,
+    },
+    /// Remove an archive's encryption without recompressing it
+    Decrypt {
+        input:  PathBuf,
+        /// Write here instead of replacing INPUT
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Password of the input archive
+        #[arg(short, long)]
+        password: String,
+    },
+    /// Re-encode only the blocks of the given codecs; copy the rest as is
+    Transcode {
+        input:  PathBuf,
+        /// Write here instead of replacing INPUT
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// FROM=TO codec migration, e.g. lzma=zstd (repeatable)
+        #[arg(long = "map", value_name = "FROM=TO", required = true)]
+        maps: Vec<String>,
+        /// Compression level for re-encoded blocks
+        #[arg(short, long, default_value = "3")]
+        level: i32,
+        #[arg(short, long)]
+        password: Option<String>,
     },
     /// Merge two or more archives into one (deduplication applied)
     Merge {
@@ -84,69 +365,435 @@ enum Commands {
         output: PathBuf,
         #[arg(short, long, default_value = "zstd")]
         codec: String,
+        /// Same-named entries: prefix (names under each source's stem),
+        /// keep-first, keep-newest, rename, or error
+        #[arg(long, default_value = "prefix")]
+        on_conflict: String,
     },
-    /// Run RLE pre-filter benchmark on a file and report savings
+    /// Benchmark every codec (and loaded plugins) on a file and recommend one
     Bench {
         input: PathBuf,
+        /// Levels for zstd, brotli, and plugins, comma-separated (default: a sweep per codec)
+        #[arg(long, value_delimiter = ',')]
+        levels: Vec<i32>,
+        /// Chunk size in KiB the input is split into
+        #[arg(long, default_value = "4096")]
+        chunk_size: usize,
+        /// Codec plugin library to benchmark as well (repeatable; needs the `plugins` feature)
+        #[arg(long = "plugin", value_name = "PATH")]
+        plugins: Vec<PathBuf>,
+    },
+    /// Estimate dedup under fixed and content-defined chunking at several sizes
+    DedupStats {
+        /// A directory, or a .6cy archive whose files are analysed
+        input: PathBuf,
+        /// Average chunk sizes in KiB, comma-separated (default 16,64,256,1024,4096)
+        #[arg(long, value_delimiter = ',')]
+        sizes: Vec<usize>,
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+    /// Write a patch holding only the blocks of NEW that OLD lacks
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Apply a patch produced by `diff` to OLD, writing the new version
+    Patch {
+        old:   PathBuf,
+        patch: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Upload only the blocks a remote block store is missing
+    Sync {
+        input: PathBuf,
+        /// Local store directory
+        #[arg(long, conflicts_with_all = ["ssh", "http"])]
+        store: Option<PathBuf>,
+        /// Remote store as HOST:DIR, reached via `ssh HOST 6cy sync-serve`
+        #[arg(long, conflicts_with = "http")]
+        ssh: Option<String>,
+        /// URL of a `6cy sync-serve --http` endpoint
+        #[arg(long)]
+        http: Option<String>,
+    },
+    /// Prove a file belongs to an archive: `attest ARCHIVE FILE`, or
+    /// check a proof: `attest --verify ROOT_HASH PROOF.json`
+    Attest {
+        /// Archive (with --verify: the expected root hash, hex)
+        target: String,
+        /// File name inside the archive (with --verify: the proof JSON file)
+        item:   String,
+        #[arg(long)]
+        verify: bool,
+        /// Write the proof here instead of stdout
+        #[arg(short, long, conflicts_with = "verify")]
+        output: Option<PathBuf>,
+    },
+    /// Serve the sync protocol on stdin/stdout (invoked by `sync --ssh`),
+    /// or over HTTP with `--http ADDR`
+    #[command(hide = true)]
+    SyncServe {
+        #[arg(long)]
+        store: PathBuf,
+        /// Listen for `sync --http` requests on this address (e.g.
+        /// 127.0.0.1:8660).  Unauthenticated: expose it only through an
+        /// authenticating reverse proxy
+        #[arg(long)]
+        http: Option<String>,
     },
+    /// Share blocks between archives through a content-addressable store
+    Store {
+        /// Store directory (created if missing)
+        #[arg(long)]
+        store: PathBuf,
+        #[command(subcommand)]
+        action: StoreAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum StoreAction {
+    /// Copy an archive's blocks into the store and record its manifest
+    Push {
+        #[arg(required = true, num_args = 1..)]
+        inputs: Vec<PathBuf>,
+    },
+    /// Rebuild an archive from the store by UUID
+    Pull {
+        uuid: String,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// List archives and dictionaries held in the store
+    List,
+    /// Train a zstd dictionary on sample files and keep it in the store
+    Train {
+        #[arg(required = true, num_args = 1..)]
+        samples: Vec<PathBuf>,
+        /// Largest dictionary size in bytes
+        #[arg(long, default_value = "112640")]
+        max_size: usize,
+    },
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(())   => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {err}");
+            ExitCode::from(exit_code(&*err))
+        }
+    }
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn run() -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(path) = std::env::var_os("SIXCY_CODEC_NAMES") {
+        if let Err(e) = load_codec_names(std::path::Path::new(&path)) {
+            eprintln!("Ignoring SIXCY_CODEC_NAMES: {e}");
+        }
+    }
+    if let Some(dir) = std::env::var_os("SIXCY_DICTIONARIES") {
+        if let Err(e) = sixcy::BlockStore::open(&dir).and_then(|store| store.register_dictionaries()) {
+            eprintln!("Ignoring SIXCY_DICTIONARIES: {e}");
+        }
+    }
     match Cli::parse().command {
 
         // ── Pack ─────────────────────────────────────────────────────────────
-        Commands::Pack { output, input, codec, level, chunk_size, solid, password } => {
+        Commands::Pack { output, mut input, codec, level, chunk_size, adaptive_chunks,
+                         max_chunk_size, solid, auto_solid, solid_threshold, solid_block_size,
+                         solid_max_files, solid_order, solid_level, index_level, index_codec, password, base,
+                         deterministic, uuid, stats, hash, meta, verify, pipeline, preallocate,
+                         queue_depth, names, dictionary, append } => {
             let codec_id = parse_codec(&codec);
+            let (level, auto_level) = match level.as_str() {
+                "auto" => (DEFAULT_COMPRESSION_LEVEL, Some(AutoLevel::default())),
+                n      => (n.parse().map_err(|_| format!("Invalid level '{n}' (expected a number or auto)"))?, None),
+            };
+            let solid_order = SolidOrder::from_name(&solid_order)
+                .ok_or_else(|| format!("Unknown solid order '{solid_order}' (expected insertion, extension, size or similarity)"))?;
+            let secondary_hash = match &hash {
+                Some(name) => Some(HashAlgorithm::from_name(name)
+                    .ok_or_else(|| format!("Unknown hash algorithm '{name}' (expected sha256 or sha512)"))?),
+                None => None,
+            };
+            let index_codec = CodecId::from_name(&index_codec)
+                .ok_or_else(|| format!("Unknown --index-codec '{index_codec}' (expected zstd or none)"))?;
+            let default_metadata = parse_key_values("--meta", &meta)?.into_iter().collect();
+            let pipeline = match &pipeline {
+                Some(spec) => {
+                    let mut p = BlockPipeline::from_spec(spec)?;
+                    if password.is_some() && p.cipher.is_none() {
+                        p = p.cipher(Cipher::Aes256Gcm);
+                    }
+                    Some(p)
+                }
+                None => None,
+            };
+            let chunk_size = match chunk_size {
+                Some(kib) => kib.saturating_mul(1024),
+                None      => pipeline.as_ref().map_or(codec_id, |p| p.codec).default_chunk_size(),
+            };
+            for (flag, bytes) in [("--chunk-size", Some(chunk_size)),
+                                  ("--max-chunk-size", adaptive_chunks.then(|| max_chunk_size.saturating_mul(1024)))] {
+                let Some(bytes) = bytes else { continue };
+                let warnings = validate_chunk_size(bytes).map_err(|e| format!("{flag}: {e}"))?;
+                for warning in warnings {
+                    eprintln!("Warning: {flag}: {warning}");
+                }
+            }
+            let base_ar = match &base {
+                Some(b) => Some(open_archive(b, &password)?),
+                None    => None,
+            };
             let opts = PackOptions {
                 default_codec: codec_id,
                 level,
-                chunk_size: chunk_size * 1024,
+                solid_level,
+                index_level,
+                index_codec,
+                auto_level,
+                chunk_size,
+                chunk_policy: adaptive_chunks.then(|| Arc::new(AdaptiveChunks {
+                    min: chunk_size,
+                    max: max_chunk_size * 1024,
+                    ..AdaptiveChunks::default()
+                }) as Arc<dyn ChunkPolicy>),
                 password,
+                deterministic,
+                fixed_uuid: uuid,
+                auto_solid: auto_solid.then(|| AutoSolid {
+                    threshold: solid_threshold * 1024,
+                    ..AutoSolid::default()
+                }),
+                solid_block_max_bytes: solid_block_size.map(|mib| mib * 1024 * 1024),
+                max_files_per_solid:   solid_max_files,
+                solid_order,
+                secondary_hash,
+                perf_stats: stats,
+                atomic:     !append,
+                default_metadata,
+                verify_on_write: verify,
+                pipeline,
+                preallocate,
+                queue_depth: queue_depth.map(|n| n as usize),
+                name_policy: names,
+                observer:    None,
+                dictionary:  dictionary.map(|uuid| registered_dictionary(&uuid.to_bytes_le()).ok_or_else(||
+                    format!("dictionary {uuid} not found; set SIXCY_DICTIONARIES to the store that holds it")))
+                    .transpose()?,
             };
-            let mut ar = Archive::create(&output, opts)?;
+            if deterministic {
+                input.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+            }
+            let mut ar = match &base_ar {
+                Some(b)        => Archive::create_incremental(&output, opts, b)?,
+                None if append => Archive::open_for_append(&output, opts)?,
+                None           => Archive::create(&output, opts)?,
+            };
+            if preallocate {
+                let total = input.iter()
+                    .map(|path| std::fs::metadata(path).map(|m| m.len()))
+                    .sum::<std::io::Result<u64>>()?;
+                ar.preallocate(total)?;
+            }
             if solid { ar.begin_solid(codec_id)?; }
             for path in &input {
-                let data = std::fs::read(path)?;
-                ar.add_file(path.file_name().unwrap().to_string_lossy().as_ref(), &data)?;
-                println!("  packed  {} ({} B)", path.display(), data.len());
+                let file = std::fs::File::open(path)?;
+                let len  = file.metadata()?.len();
+                let name = EntryName::from_os(path.file_name().unwrap());
+                #[cfg(feature = "windows-metadata")]
+                let metadata = sixcy::winmeta::capture(path)?;
+                #[cfg(not(feature = "windows-metadata"))]
+                let metadata = Default::default();
+                ar.add_entry_from_reader(name, file, len, metadata)?;
+                println!("  packed  {} ({} B)", path.display(), len);
             }
             if solid { ar.end_solid()?; }
-            ar.finalize()?;
+            // With --verify, a bad read-back is the InvalidData finalize reports.
+            ar.finalize().map_err(|e| match verify && e.kind() == std::io::ErrorKind::InvalidData {
+                true  => fail(exit::VERIFY_FAILED, e),
+                false => e.into(),
+            })?;
             let size = std::fs::metadata(&output)?.len();
-            println!("Created: {}  ({} B on disk)", output.display(), size);
+            println!("{}: {}  ({} B on disk)", if append { "Updated" } else { "Created" }, output.display(), size);
+            if verify {
+                println!("  read back and verified every block");
+            }
+            if let Some(perf) = ar.perf_stats() {
+                print!("{perf}");
+            }
+            if stats {
+                print_duplicates(&ar.duplicate_files());
+            }
         }
 
         // ── Unpack ───────────────────────────────────────────────────────────
-        Commands::Unpack { input, output_dir, password } => {
+        Commands::Unpack {
+            input, output_dir, password, base, dedup,
+            overwrite: _, skip_existing, keep_newer, resume, no_verify, paranoid, danger_skip_hashes,
+            strip_components, transform, windows_names, order, progress,
+        } => {
+            let order = parse_entry_order(&order)?;
+            let windows_names = match &windows_names {
+                Some(name) => WindowsNames::from_name(name)
+                    .ok_or_else(|| format!("Unknown --windows-names '{name}' (expected keep, rename, or reject)"))?,
+                None => WindowsNames::default(),
+            };
+            let transforms = transform.iter()
+                .map(|t| t.split_once('=').map(|(old, new)| (old.to_owned(), new.to_owned()))
+                    .ok_or_else(|| format!("Invalid --transform '{t}' (expected OLD=NEW)")))
+                .collect::<Result<Vec<_>, _>>()?;
+            let dedup_strategy = DedupStrategy::from_name(&dedup)
+                .ok_or_else(|| format!("Unknown dedup strategy '{dedup}' (expected copy, hardlink, or reflink)"))?;
+            let mut ar = open_archive(&input, &password)?;
+            for b in &base {
+                ar.attach_base(open_archive(b, &password)?)?;
+            }
+            let overwrite = if skip_existing {
+                OverwritePolicy::SkipExisting
+            } else if keep_newer {
+                OverwritePolicy::KeepNewer
+            } else if resume {
+                OverwritePolicy::Resume
+            } else {
+                OverwritePolicy::Overwrite
+            };
+            let opts = ExtractOptions {
+                dedup_strategy, overwrite, verify_hashes: !no_verify, paranoid, strip_components, transforms,
+                danger_skip_content_hash: danger_skip_hashes, windows_names, order, ..ExtractOptions::default()
+            };
+            let r = if progress {
+                ar.extract_with_progress(&output_dir, &opts, print_extract_progress)?
+            } else {
+                ar.extract_with(&output_dir, &opts)?
+            };
+            println!("Unpacked {} files to: {}", r.files, output_dir.display());
+            if r.linked > 0 {
+                println!("  {} duplicate(s) linked instead of written", r.linked);
+            }
+            if r.skipped > 0 {
+                println!("  {} existing file(s) kept", r.skipped);
+            }
+            if r.unchanged > 0 {
+                println!("  {} file(s) already up to date", r.unchanged);
+            }
+            if r.stripped > 0 {
+                println!("  {} entry(ies) left out by --strip-components", r.stripped);
+            }
+            for (name, written) in &r.renamed {
+                println!("  renamed {name} → {written}");
+            }
+            if let Some(root) = r.verified_root {
+                println!("  Verified root hash {}", hex::encode(root));
+            }
+        }
+
+        // ── ToTar ────────────────────────────────────────────────────────────
+        Commands::ToTar { input, output, password, base } => {
             let mut ar = open_archive(&input, &password)?;
-            ar.extract_all(&output_dir)?;
-            println!("Unpacked to: {}", output_dir.display());
+            for b in &base {
+                ar.attach_base(open_archive(b, &password)?)?;
+            }
+            match output {
+                Some(path) => {
+                    let n = ar.extract_to_tar(std::io::BufWriter::new(std::fs::File::create(&path)?))?;
+                    eprintln!("Wrote {n} files to {}", path.display());
+                }
+                None => {
+                    ar.extract_to_tar(std::io::BufWriter::new(std::io::stdout().lock()))?;
+                }
+            }
         }
 
         // ── List ─────────────────────────────────────────────────────────────
-        Commands::List { input } => {
-            let ar = open_archive(&input, &None)?;
-            println!("Archive: {}", input.display());
-            println!("{:<28} {:>12} {:>12} {:>7}  First block hash",
-                     "Name", "Size", "Compressed", "Chunks");
-            for info in ar.list() {
-                let hash = info.first_block_hash
-                    .map(|h| hex::encode(&h[..6]))
-                    .unwrap_or_else(|| "—".into());
-                println!("{:<28} {:>12} {:>12} {:>7}  {}",
-                    info.name, info.original_size, info.compressed_size,
-                    info.block_count, hash);
+        Commands::List { input, digests, offset, limit, filter, order, timeout } => {
+            let order  = parse_entry_order(&order)?;
+            let filter = parse_key_values("--filter", &filter)?;
+            let matches = |info: &FileInfo| filter.iter().all(|(k, v)| info.metadata.get(k) == Some(v));
+            let limit   = limit.unwrap_or(usize::MAX);
+            // Stream straight to stdout; a closed pipe (`| head`) ends the listing.
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            let written = match input.to_str().filter(|s| is_remote_url(s)) {
+                // Remote: superblock and INDEX only, so no codec / Enc columns.
+                Some(url) => {
+                    let mut source = HttpSource::new(url)?;
+                    if let Some(secs) = timeout {
+                        let total = std::time::Duration::try_from_secs_f64(secs)
+                            .map_err(|e| format!("--timeout: {e}"))?;
+                        source = source.with_timeouts(total.min(HttpSource::DEFAULT_CONNECT_TIMEOUT), total);
+                    }
+                    let meta = Archive::open_remote_metadata_with(&mut source)?;
+                    let entries = meta.entries_in(order).filter(matches).skip(offset).take(limit);
+                    if digests {
+                        write_digests(&mut out, entries)
+                    } else {
+                        write_listing(&mut out, &input, meta.required_codecs(), entries.map(Ok)).map(|()| 0)
+                    }
+                }
+                None if digests => {
+                    let ar = open_archive(&input, &None)?;
+                    write_digests(&mut out, ar.entries_in(order).filter(matches).skip(offset).take(limit))
+                }
+                None => {
+                    let mut ar = open_archive(&input, &None)?;
+                    let codecs = ar.required_codecs().to_vec();
+                    let entries = ar.entries_detailed_in(order)
+                        .filter(|info| info.as_ref().map_or(true, matches))
+                        .skip(offset)
+                        .take(limit);
+                    write_listing(&mut out, &input, &codecs, entries).map(|()| 0)
+                }
+            };
+            match written.and_then(|missing| out.flush().map(|_| missing)) {
+                Ok(0) => {}
+                Ok(missing) => {
+                    return Err(format!("{missing} file(s) have no recorded digest (pack with --hash)").into());
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+                Err(e) => return Err(e.into()),
             }
         }
 
         // ── Info ─────────────────────────────────────────────────────────────
-        Commands::Info { input } => {
-            let ar    = open_archive(&input, &None)?;
-            let files = ar.list();
-            let sb = {
+        Commands::Info { input, check_codecs, plugins } => {
+            load_plugins(&plugins)?;
+
+            // Read leniently so a damaged superblock is still described.
+            let diagnosis = {
                 let mut f = std::fs::File::open(&input)?;
-                sixcy::Superblock::read(&mut f)?
+                let mut d = sixcy::Superblock::read_lenient(&mut f)?;
+                let inline = d.superblock.required_codec_uuids.len();
+                match d.superblock.load_codec_continuation(&mut f) {
+                    Ok(()) | Err(SuperblockError::UnavailableCodec { .. }) => {
+                        d.problems.extend(d.superblock.required_codec_uuids[inline..].iter()
+                            .filter(|u| codec_source(u).is_none())
+                            .map(|u| SuperblockError::UnavailableCodec { uuid: uuid_to_string(u) }));
+                    }
+                    Err(e) => d.problems.push(e),
+                }
+                d
             };
+
+            if check_codecs {
+                let sb = &diagnosis.superblock;
+                print_codec_sources(&sb.required_codec_uuids);
+                let missing = sb.required_codec_uuids.iter().filter(|u| codec_source(u).is_none()).count();
+                if missing > 0 {
+                    return Err(fail(exit::UNSUPPORTED, format!("{missing} required codec(s) unavailable")));
+                }
+                return Ok(());
+            }
+
+            let mut ar = match diagnosis.is_healthy() {
+                true  => Some(open_archive(&input, &None)?),
+                false => None,
+            };
+            let sb = &diagnosis.superblock;
             let file_size = std::fs::metadata(&input)?.len();
 
             println!("── .6cy Archive ─────────────────────────────────────────");
@@ -155,24 +802,132 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("  Format version {}", sb.format_version);
             println!("  UUID           {}", sb.archive_uuid);
             println!("  Encrypted      {}", sb.flags & sixcy::superblock::SB_FLAG_ENCRYPTED != 0);
+            let time = |t: Option<i64>| match t.and_then(|t| chrono::DateTime::from_timestamp(t, 0)) {
+                Some(t) => t.to_rfc3339(),
+                None    => "unknown".to_owned(),
+            };
+            println!("  Created        {}", time(sb.created_at));
+            println!("  Modified       {}", time(sb.modified_at));
+            println!("  Writer         {}", sb.writer.as_deref().unwrap_or("unknown"));
+            let features: Vec<String> = (0..64).map(|i| 1u64 << i)
+                .filter(|bit| sb.feature_flags & bit != 0)
+                .map(|bit| sixcy::superblock::feature_name(bit)
+                    .map_or_else(|| format!("bit {}", bit.trailing_zeros()), str::to_owned))
+                .collect();
+            println!("  Features       {}", if features.is_empty() { "none".to_owned() } else { features.join(", ") });
             println!("  Index offset   {} B", sb.index_offset);
             println!("  Index size     {} B", sb.index_size);
-            println!("  Files          {}", files.len());
-            println!("  Root hash      {}", ar.root_hash_hex());
-            println!("  Required codecs ({}):", sb.required_codec_uuids.len());
-            for uuid_bytes in &sb.required_codec_uuids {
-                let name = CodecId::from_uuid(uuid_bytes)
-                    .map(|c| c.name())
-                    .unwrap_or("UNKNOWN");
-                println!("    {} ({})", uuid_to_string(uuid_bytes), name);
+            if let Some(ar) = &mut ar {
+                if ar.is_index_relocated() {
+                    println!("  Index          found by tail scan; index_offset above is wrong");
+                }
+                println!("  Files          {}", ar.list().len());
+                println!("  Index version  {}", ar.index_version());
+                println!("  Name policy    {}", ar.name_policy().map_or("unrecorded", NamePolicy::name));
+                if !ar.app_data_keys().is_empty() {
+                    println!("  App data       {}", ar.app_data_keys().join(", "));
+                }
+                if !ar.unknown_index_fields().is_empty() {
+                    let fields: Vec<String> = ar.unknown_index_fields().iter()
+                        .map(|(path, n)| format!("{path} ×{n}"))
+                        .collect();
+                    println!("  Unknown fields {} (written by a newer version; ignored)", fields.join(", "));
+                }
+                println!("  Root hash      {}", ar.root_hash_hex());
+                let sections = Archive::sections(&input)?;
+                if sections.len() > 1 {
+                    let offsets: Vec<String> = sections.iter().map(u64::to_string).collect();
+                    println!("  Sections       {} concatenated, at offsets {}", sections.len(), offsets.join(", "));
+                }
+                let space = ar.space_report()?;
+                println!("  Blocks         {} ({} solid)", space.blocks_total, space.solid_blocks);
+                println!("  Orphaned       {} block(s), {} B ({:.1}% of file){}",
+                         space.orphaned_blocks, space.orphaned_bytes,
+                         space.orphaned_bytes as f64 * 100.0 / file_size.max(1) as f64,
+                         if space.orphaned_bytes > 0 { "; `6cy optimize` reclaims it" } else { "" });
+                println!("  Dedup ratio    {:.2}× ({} B read from {} B of blocks)",
+                         space.dedup_ratio(), space.logical_bytes, space.unique_bytes);
+                println!("  Fragmentation  {:.1}% ({} of {} multi-block file(s) scattered)",
+                         space.fragmentation() * 100.0, space.fragmented_files, space.multi_block_files);
+                if let Some(offset) = space.unreadable_at {
+                    println!("  Block walk     stopped at unreadable header at {offset}; figures cover blocks before it");
+                }
+            }
+            print_codec_sources(&sb.required_codec_uuids);
+            if !diagnosis.is_healthy() {
+                println!("  Problems ({}):", diagnosis.problems.len());
+                for problem in &diagnosis.problems {
+                    println!("    {problem}");
+                }
+                // Problems come in check order; the first decides the status.
+                return Err(fail(superblock_exit_code(&diagnosis.problems[0]), "superblock failed validation"));
+            }
+        }
+
+        // ── Health ───────────────────────────────────────────────────────────
+        Commands::Health { input, sample, password } => {
+            let mut ar = open_archive(&input, &password)?;
+            let report = ar.health_check(sample)?;
+            println!("Health: {}/{} block(s) checked, score {:.1}%{}",
+                     report.blocks_checked, report.blocks_total, report.score() * 100.0,
+                     if report.escalated { " (sample failed; verified all)" } else { "" });
+            for (offset, err) in &report.failures {
+                println!("  ✗ @{offset:10}  {err}");
+            }
+            if !report.is_healthy() {
+                return Err(fail(exit::VERIFY_FAILED,
+                                format!("{} block(s) failed verification", report.failures.len())));
+            }
+        }
+
+        // ── Scrub ────────────────────────────────────────────────────────────
+        Commands::Scrub { input, state, max_age, max_blocks, password } => {
+            let mut ar      = open_archive(&input, &password)?;
+            let mut journal = sixcy::scrub::ScrubState::load(&state)?;
+            let report = ar.scrub(&mut journal, &sixcy::scrub::ScrubOptions { max_age, max_blocks })?;
+            journal.save(&state)?;
+            println!("Scrub: {} block(s) checked, {} fresh, {} pending of {}",
+                     report.blocks_checked, report.blocks_fresh(), report.blocks_pending, report.blocks_total);
+            for (offset, err) in &report.failures {
+                println!("  ✗ @{offset:10}  {err}");
+            }
+            if !report.is_healthy() {
+                return Err(fail(exit::VERIFY_FAILED,
+                                format!("{} block(s) failed verification", report.failures.len())));
+            }
+        }
+
+        // ── Fsck ─────────────────────────────────────────────────────────────
+        Commands::Fsck { input, password, resurrect, output } => {
+            let mut ar = open_archive(&input, &password)?;
+            let orphans = match resurrect {
+                true  => ar.resurrect_orphans()?,
+                false => ar.orphans()?,
+            };
+            println!("{}: {} orphaned file(s)", input.display(), orphans.len());
+            for o in &orphans {
+                println!("  {:<20} {:>12} B  {} block(s)", o.name, o.original_size, o.block_count);
+            }
+            if let (true, Some(output)) = (resurrect, output) {
+                let r = optimize(&mut ar, &output, &OptimizeOptions::default())?;
+                println!("Repaired copy ({} files) → {}", r.files, output.display());
+            } else if !orphans.is_empty() {
+                return Err(fail(exit::CORRUPT, "orphaned blocks found (rerun with --resurrect -o OUT to recover them)"));
             }
         }
 
         // ── Scan ─────────────────────────────────────────────────────────────
-        Commands::Scan { input } => {
+        Commands::Scan { input, progress_json } => {
             use sixcy::io_stream::SixCyReader;
-            let mut reader = SixCyReader::new(std::fs::File::open(&input)?)?;
-            let idx = reader.scan_blocks()?;
+            let mut progress = ScanProgress::new(progress_json);
+            let idx = match SixCyReader::new(std::fs::File::open(&input)?) {
+                Ok(mut reader) => reader.scan_blocks_with_progress(|done, total| progress.block(done, total))?,
+                Err(e) => {
+                    println!("Superblock unreadable ({e}); scanning from offset 0");
+                    sixcy::recovery::scan_file_with_progress(&input, &mut |done, total| progress.block(done, total))?.index
+                }
+            };
+            progress.finish();
             println!("Scan recovered {} file(s) from block headers:", idx.records.len());
             for r in &idx.records {
                 println!("  id={:08x}  chunks={}  size={}  name={}",
@@ -180,10 +935,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
+        // ── Blocks ───────────────────────────────────────────────────────────
+        Commands::Blocks { input, json } => {
+            let mut ar  = open_archive(&input, &None)?;
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            match write_blocks(&mut out, ar.iter_block_headers()?, json).and_then(|()| Ok(out.flush()?)) {
+                Err(SixcyError::Io(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {}
+                result => result?,
+            }
+        }
+
         // ── Recover ──────────────────────────────────────────────────────────
-        Commands::Recover { input, output, password, verbose } => {
+        Commands::Recover { input, output, password, verbose, deep, raw_unknown, no_resync, dir, progress_json } => {
             use sixcy::recovery;
-            use std::io::Seek;
 
             println!("── Index-bypass recovery ────────────────────────────────");
             println!("  Source: {}", input.display());
@@ -204,10 +968,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 None
             };
 
-            let mut src = std::fs::File::open(&input)?;
-            let mut dst = std::fs::File::create(&output)?;
-
-            let report = recovery::extract_recoverable(&mut src, &mut dst, key.as_ref())?;
+            let mut src  = std::fs::File::open(&input)?;
+            let progress = std::sync::Arc::new(std::sync::Mutex::new(ScanProgress::new(progress_json)));
+            let shown    = std::sync::Arc::clone(&progress);
+            let opts = recovery::RecoveryOptions {
+                deep_verify:               deep,
+                resync:                    !no_resync,
+                parallel:                  true,
+                decryption_key:            key,
+                include_unknown_codec_raw: raw_unknown,
+                progress:                  Some(recovery::RecoveryProgress::new(move |done, total| {
+                    shown.lock().expect("progress lock").block(done, total);
+                })),
+                ..Default::default()
+            };
+            let report = if dir {
+                recovery::run(&mut src, &mut recovery::DirSink::new(&output), &opts)?
+            } else {
+                recovery::run(&mut src, &mut recovery::archive_sink(std::fs::File::create(&output)?)?, &opts)?
+            };
+            progress.lock().expect("progress lock").finish();
 
             println!();
             println!("  {}", report.summary());
@@ -216,10 +996,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("  Corrupt blocks:      {}", report.corrupt_blocks);
             println!("  Truncated blocks:    {}", report.truncated_blocks);
             println!("  Unknown codec:       {}", report.unknown_codec_blocks);
+            if deep {
+                println!("  Payload corrupt:     {}", report.payload_corrupt_blocks);
+            }
             println!("  Recoverable:         {:.2} MiB",
                      report.recoverable_bytes as f64 / 1048576.0);
             println!("  Files extracted:     {}", report.index.records.len());
             println!("  Quality:             {:?}", report.quality);
+            if !report.superblock_valid {
+                println!("  Superblock:          unreadable — scanned from offset 0");
+            }
+            let gaps     = report.block_log.iter().filter(|b| b.is_gap()).count();
+            let overlaps = report.block_log.iter().filter(|b| b.is_overlap()).count();
+            if gaps + overlaps > 0 {
+                println!("  Gaps / overlaps:     {gaps} / {overlaps} (see --verbose)");
+            }
 
             if verbose {
                 println!();
@@ -230,71 +1021,160 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         sixcy::BlockHealth::HeaderCorrupt        => "✗ header corrupt".into(),
                         sixcy::BlockHealth::TruncatedPayload { declared, available } =>
                             format!("⚠ truncated ({declared} declared, {available} available)"),
-                        sixcy::BlockHealth::UnknownCodec { uuid_hex } =>
-                            format!("? unknown codec {uuid_hex}"),
+                        sixcy::BlockHealth::UnknownCodec { uuid_hex } => {
+                            let mut uuid = [0u8; 16];
+                            match hex::decode_to_slice(uuid_hex, &mut uuid) {
+                                Ok(()) => format!("? unknown codec {}", codec_display_name(&uuid)),
+                                Err(_) => format!("? unknown codec {uuid_hex}"),
+                            }
+                        }
+                        sixcy::BlockHealth::PayloadCorrupt { reason } =>
+                            format!("✗ payload corrupt ({reason})"),
                     };
-                    println!("  [{i:4}] @{:10}  {status}", sb.archive_offset);
+                    let gap = match sb.gap {
+                        0          => String::new(),
+                        g if g > 0 => format!("  (gap of {g} bytes before)"),
+                        g          => format!("  (overlaps previous block by {} bytes)", -g),
+                    };
+                    println!("  [{i:4}] @{:10}  {status}{gap}", sb.archive_offset);
+                }
+
+                println!();
+                println!("  ── Files ────────────────────────────────────────────");
+                for f in &report.files {
+                    let status = match f.status {
+                        recovery::FileStatus::HashVerified => "✓ complete, hash-verified".into(),
+                        recovery::FileStatus::Complete     => "✓ complete".into(),
+                        recovery::FileStatus::Incomplete { missing, total } =>
+                            format!("✗ missing {missing} of {total} chunks"),
+                    };
+                    println!("  file_{:08x}  {:>12} B  {:4} chunk(s)  {status}",
+                             f.file_id, f.size, f.chunks_found);
                 }
             }
 
             println!();
             println!("Recovery complete → {}", output.display());
+            if report.quality != recovery::RecoveryQuality::Full {
+                return Err(fail(exit::PARTIAL_RECOVERY,
+                                format!("recovery incomplete ({:?})", report.quality)));
+            }
         }
 
         // ── Optimize ─────────────────────────────────────────────────────────
-        Commands::Optimize { input, output, password, level } => {
+        Commands::Optimize { input, output, password, new_password, codec, level, policy } => {
             let mut src = open_archive(&input, &password)?;
-            let files: Vec<(String, Vec<u8>)> = src.list()
-                .into_iter()
-                .map(|info| (info.name.clone(), src.read_file_by_id(info.id).unwrap_or_default()))
-                .collect();
-
-            let opts = PackOptions {
-                default_codec: CodecId::Zstd,
+            let policies = policy.iter()
+                .map(|spec| CodecPolicy::parse(spec, level)
+                    .ok_or_else(|| format!("Invalid policy '{spec}' (expected PATTERN=CODEC[:LEVEL])")))
+                .collect::<Result<Vec<_>, _>>()?;
+            let opts = OptimizeOptions {
+                codec: parse_codec(&codec),
                 level,
-                chunk_size: DEFAULT_CHUNK_SIZE,
-                password: None,
+                policies,
+                password: new_password,
             };
-            let mut dst = Archive::create(&output, opts)?;
-            for (name, data) in &files {
-                dst.add_file(name, data)?;
-            }
-            dst.finalize()?;
-            println!("Optimized ({} files) → {}", files.len(), output.display());
+            let r = optimize(&mut src, &output, &opts)?;
+            println!("Optimized ({} files, {} blocks) → {}", r.files, r.blocks_rewritten, output.display());
+            println!("  payload {} B → {} B", r.payload_before, r.payload_after);
+        }
+
+        // ── Encrypt / Decrypt ────────────────────────────────────────────────
+        Commands::Encrypt { input, output, password } => {
+            let mut src = Archive::open(&input)?;
+            let output = output.unwrap_or_else(|| input.clone());
+            let r = convert::encrypt(&mut src, &output, &password)?;
+            println!("Encrypted ({} files, {} blocks) → {}", r.files, r.blocks, output.display());
+        }
+        Commands::Decrypt { input, output, password } => {
+            let mut src = open_archive(&input, &Some(password))?;
+            let output = output.unwrap_or_else(|| input.clone());
+            let r = convert::decrypt(&mut src, &output)?;
+            println!("Decrypted ({} files, {} blocks) → {}", r.files, r.blocks, output.display());
+        }
+
+        // ── Transcode ────────────────────────────────────────────────────────
+        Commands::Transcode { input, output, maps, level, password } => {
+            let maps = maps.iter()
+                .map(|spec| spec.split_once('=')
+                    .and_then(|(from, to)| Some((CodecId::from_name(from)?, CodecId::from_name(to)?)))
+                    .ok_or_else(|| format!("Invalid --map '{spec}' (expected FROM=TO, e.g. lzma=zstd)")))
+                .collect::<Result<std::collections::HashMap<_, _>, _>>()?;
+            let mut src = open_archive(&input, &password)?;
+            let output = output.unwrap_or_else(|| input.clone());
+            let r = src.transcode(&output, |codec| maps.get(&codec).copied().unwrap_or(codec), level)?;
+            println!("Transcoded ({} blocks re-encoded, {} copied) → {}",
+                r.blocks_rewritten, r.blocks_copied, output.display());
+            println!("  payload {} B → {} B", r.payload_before, r.payload_after);
         }
 
         // ── Merge ─────────────────────────────────────────────────────────────
-        Commands::Merge { inputs, output, codec } => {
-            let codec_id = parse_codec(&codec);
+        Commands::Merge { inputs, output, codec, on_conflict } => {
+            let on_conflict = MergeConflict::from_name(&on_conflict)
+                .ok_or_else(|| format!("Unknown --on-conflict '{on_conflict}' (expected prefix, keep-first, keep-newest, rename, or error)"))?;
             let opts = PackOptions {
-                default_codec: codec_id,
+                default_codec: parse_codec(&codec),
                 ..PackOptions::default()
             };
-            let mut dst = Archive::create(&output, opts)?;
-
-            let mut total_files = 0usize;
-            for path in &inputs {
-                let mut src = open_archive(path, &None)?;
-                for info in src.list() {
-                    let data = src.read_file_by_id(info.id)?;
-                    // Prefix with source archive name to avoid name collisions.
-                    let merged_name = format!(
-                        "{}/{}",
-                        path.file_stem().unwrap_or_default().to_string_lossy(),
-                        info.name,
-                    );
-                    dst.add_file(&merged_name, &data)?;
-                    total_files += 1;
-                }
-                println!("  merged  {} ({} files)", path.display(), src.list().len());
+            let mut sources = inputs.iter()
+                .map(|path| open_archive(path, &None))
+                .collect::<Result<Vec<_>, _>>()?;
+            let report = Archive::merge(&mut sources, &output, opts, on_conflict)?;
+            for (from, to) in &report.renamed {
+                println!("  renamed {from} → {to}");
+            }
+            println!("Merged {} file(s) from {} archive(s) → {}", report.files, inputs.len(), output.display());
+            if report.identical + report.dropped > 0 {
+                println!("  skipped {} identical and {} conflicting entries", report.identical, report.dropped);
+            }
+        }
+
+        // ── Dedup stats ─────────────────────────────────────────────────────
+        Commands::DedupStats { input, sizes, password } => {
+            let sizes: Vec<usize> = sizes.iter().map(|kib| kib * 1024).collect();
+            let mut sim = DedupSimulator::new(&sizes);
+            let files = if input.is_dir() {
+                sim.add_dir(&input)?
+            } else {
+                sim.add_archive(&mut open_archive(&input, &password)?)?
+            };
+            let results = sim.results();
+            let bytes = results.first().map_or(0, |r| r.input_bytes);
+            println!("── Dedup estimate ({files} files, {bytes} B) ─────────────────");
+            println!("  {:<6} {:>10} {:>10} {:>12} {:>12} {:>7} {:>7}",
+                     "Scheme", "Size", "Mean", "Chunks", "Unique", "Ratio", "Saved");
+            for r in &results {
+                println!("  {:<6} {:>7} KiB {:>6} KiB {:>12} {:>12} {:>6.2}x {:>6.1}%",
+                         r.chunking.name(), r.size / 1024, r.mean_chunk() / 1024,
+                         r.chunks, r.unique_chunks, r.ratio(), r.saving() * 100.0);
             }
-            dst.finalize()?;
-            println!("Merged {} file(s) → {}", total_files, output.display());
         }
 
         // ── Bench ─────────────────────────────────────────────────────────────
-        Commands::Bench { input } => {
+        Commands::Bench { input, levels, chunk_size, plugins } => {
+            load_plugins(&plugins)?;
             let data = std::fs::read(&input)?;
+            let opts = BenchOptions {
+                chunk_size: chunk_size * 1024,
+                levels:     (!levels.is_empty()).then_some(levels),
+            };
+            let results = bench::run(&data, &opts)?;
+            println!("── Codec benchmark ({} B, {} KiB chunks) ─────────────────", data.len(), chunk_size);
+            println!("  {:<38} {:>5} {:>12} {:>7} {:>10} {:>10}",
+                     "Codec", "Level", "Compressed", "Ratio", "Comp MB/s", "Dec MB/s");
+            for r in &results {
+                println!("  {:<38} {:>5} {:>12} {:>6.1}% {:>10.1} {:>10.1}",
+                         r.codec, r.level, r.compressed_bytes, r.ratio() * 100.0,
+                         r.compress_mbps(), r.decompress_mbps());
+            }
+            match bench::recommend(&results) {
+                Some(best) if CodecId::from_name(&best.codec).is_some() =>
+                    println!("  Recommended:  --codec {} --level {}", best.codec, best.level),
+                Some(best) => println!("  Recommended:  plugin {} at level {}", best.codec, best.level),
+                None       => {}
+            }
+            println!();
+
             let t0   = std::time::Instant::now();
             let enc  = perf::rle_encode(&data);
             let enc_ms = t0.elapsed().as_millis();
@@ -312,20 +1192,507 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("  Decode time:  {} ms", dec_ms);
             println!("  Round-trip:   {}", if correct { "✓ correct" } else { "✗ MISMATCH" });
         }
+
+        // ── Diff / Patch ──────────────────────────────────────────────────────
+        Commands::Diff { old, new, output } => {
+            let mut old_ar = Archive::open(&old)?;
+            let mut new_ar = Archive::open(&new)?;
+            let p = sixcy::delta::diff(&mut old_ar, &mut new_ar, &output)?;
+            println!("Patch: {}  ({} B, {} blocks stored, {} reused from base)",
+                     p.path.display(), p.patch_bytes, p.blocks_copied, p.blocks_reused);
+        }
+
+        Commands::Patch { old, patch, output } => {
+            let mut old_ar   = Archive::open(&old)?;
+            let mut patch_ar = Archive::open(&patch)?;
+            sixcy::delta::apply(&mut old_ar, &mut patch_ar, &output)?;
+            println!("Patched → {}", output.display());
+        }
+
+        // ── Sync ──────────────────────────────────────────────────────────────
+        Commands::Attest { target, item, verify, output } => {
+            if verify {
+                let mut root = [0u8; 32];
+                hex::decode_to_slice(target.trim(), &mut root)
+                    .map_err(|e| format!("invalid root hash: {e}"))?;
+                let proof = InclusionProof::from_json(&std::fs::read_to_string(&item)?)?;
+                if !proof.verify(&root) {
+                    return Err(fail(exit::VERIFY_FAILED,
+                                    format!("proof for {} does not match root {}", proof.name, target)));
+                }
+                println!("OK  {} ({} B, {} block(s)) is included in root {}",
+                         proof.name, proof.original_size, proof.content_hashes.len(), target);
+            } else {
+                let ar    = Archive::open(&target)?;
+                let json  = ar.attest(&item)?.to_json()?;
+                match output {
+                    Some(path) => {
+                        std::fs::write(&path, json + "\n")?;
+                        eprintln!("Proof for {} written to {}", item, path.display());
+                    }
+                    None => println!("{json}"),
+                }
+            }
+        }
+
+        Commands::Sync { input, store, ssh, http } => {
+            let mut ar = Archive::open(&input)?;
+            let report = match (store, ssh, http) {
+                (Some(dir), _, _) => sixcy::sync::push(&mut ar, &mut sixcy::BlockStore::open(dir)?)?,
+                (None, Some(remote), _) => {
+                    let (host, dir) = remote.split_once(':')
+                        .ok_or("--ssh expects HOST:DIR")?;
+                    let mut t = sixcy::sync::StreamTransport::ssh(host, dir)?;
+                    sixcy::sync::push(&mut ar, &mut t)?
+                }
+                (None, None, Some(url)) => {
+                    sixcy::sync::push(&mut ar, &mut sixcy::sync::HttpTransport::new(&url)?)?
+                }
+                (None, None, None) => return Err("sync needs --store, --ssh, or --http".into()),
+            };
+            if report.up_to_date {
+                println!("Remote already up to date (root hash matches)");
+            } else {
+                println!("Synced {}  ({} of {} blocks sent, {} B)",
+                         input.display(), report.blocks_sent, report.blocks_total, report.bytes_sent);
+            }
+        }
+
+        Commands::SyncServe { store, http } => {
+            let mut bs = sixcy::BlockStore::open(&store)?;
+            match http {
+                Some(addr) => {
+                    let listener = std::net::TcpListener::bind(&addr)?;
+                    eprintln!("Serving {} on http://{}", store.display(), listener.local_addr()?);
+                    sixcy::sync::serve_http(&mut bs, listener)?;
+                }
+                None => sixcy::sync::serve(&mut bs, std::io::stdin().lock(), std::io::stdout().lock())?,
+            }
+        }
+
+        // ── Store ─────────────────────────────────────────────────────────────
+        Commands::Store { store, action } => {
+            let bs = sixcy::BlockStore::open(&store)?;
+            match action {
+                StoreAction::Push { inputs } => {
+                    for path in &inputs {
+                        let r = bs.push(path)?;
+                        println!("  pushed  {}  ({} blocks, {} new, {} B added{})",
+                                 path.display(), r.blocks_total, r.blocks_new, r.bytes_new,
+                                 match r.dictionaries_new {
+                                     0 => String::new(),
+                                     n => format!(", {n} dictionar{}", if n == 1 { "y" } else { "ies" }),
+                                 });
+                    }
+                }
+                StoreAction::Pull { uuid, output } => {
+                    let uuid = uuid::Uuid::parse_str(&uuid)?;
+                    bs.pull(&uuid, &output)?;
+                    println!("Pulled {} → {}", uuid, output.display());
+                }
+                StoreAction::List => {
+                    for uuid in bs.archives()? {
+                        let m = bs.manifest(&uuid)?;
+                        println!("{}  {:>6} files  {:>6} blocks", uuid, m.index.records.len(), m.blocks.len());
+                    }
+                    for uuid in bs.dictionaries()? {
+                        let dict = bs.dictionary(&uuid)?;
+                        println!("{}  dictionary  {:>6} B", uuid_to_string(&uuid), dict.as_bytes().len());
+                    }
+                }
+                StoreAction::Train { samples, max_size } => {
+                    let samples = samples.iter().map(std::fs::read).collect::<Result<Vec<_>, _>>()?;
+                    let dict = Dictionary::train(&samples, max_size)?;
+                    bs.put_dictionary(&dict)?;
+                    println!("Dictionary {}  ({} B from {} samples)",
+                             uuid_to_string(&dict.uuid()), dict.as_bytes().len(), samples.len());
+                }
+            }
+        }
     }
 
     Ok(())
 }
 
+// ── Exit codes ───────────────────────────────────────────────────────────────
+
+/// Process exit statuses, listed in `--help` by [`EXIT_CODES_HELP`].
+/// 0 is success and 2 is clap's usage error.
+mod exit {
+    /// Anything not classified below: I/O, bad options, invalid input.
+    pub const FAILURE:          u8 = 1;
+    pub const CORRUPT:          u8 = 3;
+    pub const WRONG_PASSWORD:   u8 = 4;
+    /// Required codec, feature, or format version missing from this build.
+    pub const UNSUPPORTED:      u8 = 5;
+    pub const PARTIAL_RECOVERY: u8 = 6;
+    pub const VERIFY_FAILED:    u8 = 7;
+    pub const LIMIT_EXCEEDED:   u8 = 8;
+}
+
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  success
+  1  other failure (I/O error, invalid option or input)
+  2  usage error
+  3  archive corrupt or not a .6cy archive
+  4  wrong or missing password
+  5  required codec, feature, or format version unavailable
+  6  recover could not recover everything
+  7  verification failed (health, pack --verify, attest --verify)
+  8  resource limit exceeded";
+
+/// An error that exits with a specific status.
+#[derive(Debug)]
+struct Failure {
+    code:    u8,
+    message: String,
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+fn fail(code: u8, message: impl ToString) -> Box<dyn std::error::Error> {
+    Box::new(Failure { code, message: message.to_string() })
+}
+
+/// Exit status for `err`, looking through `io::Error` wrappers to the
+/// library error inside.
+fn exit_code(err: &(dyn std::error::Error + 'static)) -> u8 {
+    use sixcy::{CodecError, CryptoError, SixcyError};
+    use sixcy::limits::LimitExceeded;
+
+    if let Some(f) = err.downcast_ref::<Failure>() {
+        return f.code;
+    }
+    if let Some(e) = err.downcast_ref::<SixcyError>() {
+        return match e {
+            SixcyError::Superblock(e) => superblock_exit_code(e),
+            SixcyError::Codec(e)      => exit_code(e),
+            SixcyError::Crypto(e)     => exit_code(e),
+            SixcyError::Index(_)      => exit::CORRUPT,
+            SixcyError::Limit(_)      => exit::LIMIT_EXCEEDED,
+            SixcyError::Io(e)         => exit_code(e),
+        };
+    }
+    if let Some(e) = err.downcast_ref::<SuperblockError>() {
+        return superblock_exit_code(e);
+    }
+    if let Some(e) = err.downcast_ref::<CodecError>() {
+        return match e {
+            CodecError::Decompression(_)        => exit::CORRUPT,
+            CodecError::UnavailableCodec { .. } => exit::UNSUPPORTED,
+            CodecError::Crypto(e)               => exit_code(e),
+            CodecError::LimitExceeded(_)        => exit::LIMIT_EXCEEDED,
+            CodecError::Io(e)                   => exit_code(e),
+            _                                   => exit::FAILURE,
+        };
+    }
+    if let Some(e) = err.downcast_ref::<CryptoError>() {
+        return match e {
+            CryptoError::WrongPassword | CryptoError::MissingKey          => exit::WRONG_PASSWORD,
+            CryptoError::DecryptionFailed | CryptoError::TooShort         => exit::CORRUPT,
+            CryptoError::EncryptionFailed | CryptoError::KeyDerivation(_) => exit::FAILURE,
+        };
+    }
+    if err.is::<LimitExceeded>() {
+        return exit::LIMIT_EXCEEDED;
+    }
+    if let Some(e) = err.downcast_ref::<std::io::Error>() {
+        if let Some(code) = e.get_ref().map(|inner| exit_code(inner)).filter(|&c| c != exit::FAILURE) {
+            return code;
+        }
+        return match e.kind() {
+            std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => exit::CORRUPT,
+            _                                                                    => exit::FAILURE,
+        };
+    }
+    exit::FAILURE
+}
+
+fn superblock_exit_code(err: &SuperblockError) -> u8 {
+    match err {
+        SuperblockError::InvalidMagic | SuperblockError::Crc32Mismatch => exit::CORRUPT,
+        SuperblockError::UnsupportedVersion(_)
+        | SuperblockError::UnavailableCodec { .. }
+        | SuperblockError::UnsupportedFeature { .. }                    => exit::UNSUPPORTED,
+        SuperblockError::Io(e)                                         => exit_code(e),
+    }
+}
+
 // ── helpers ──────────────────────────────────────────────────────────────────
 
+/// Open `path`, showing key-derivation progress on a terminal: Argon2id
+/// can take seconds on slow machines.
 fn open_archive(path: &PathBuf, password: &Option<String>) -> Result<Archive, Box<dyn std::error::Error>> {
     Ok(match password {
+        Some(pwd) if std::io::stderr().is_terminal() => {
+            let ar = Archive::open_encrypted_with_progress(path, pwd, Duration::from_millis(200), |p| {
+                eprint!("\r\x1b[2KDeriving key… {:3.0}%", p.fraction() * 100.0);
+            });
+            eprint!("\r\x1b[2K");
+            ar?
+        }
         Some(pwd) => Archive::open_encrypted(path, pwd)?,
         None      => Archive::open(path)?,
     })
 }
 
+/// Print each required codec UUID with the built-in codec or plugin that
+/// provides it.
+fn print_codec_sources(uuids: &[[u8; 16]]) {
+    println!("  Required codecs ({}):", uuids.len());
+    for uuid_bytes in uuids {
+        let named = |what: &str| match codec_name(uuid_bytes) {
+            Some(name) => format!("{name}, {what}"),
+            None       => what.to_owned(),
+        };
+        let source = match codec_source(uuid_bytes) {
+            Some(CodecSource::BuiltIn(c)) => format!("{}, built-in", c.name()),
+            Some(CodecSource::Plugin)     => named("plugin"),
+            Some(CodecSource::Dictionary) => named("zstd dictionary"),
+            None                          => named("MISSING"),
+        };
+        println!("    {} ({})", uuid_to_string(uuid_bytes), source);
+    }
+}
+
+/// One `\r`-rewritten status line on stderr; the final call ends it.
+fn print_extract_progress(p: &ExtractProgress) {
+    const MIB: f64 = 1024.0 * 1024.0;
+    let eta = match p.eta() {
+        Some(eta) => format!("ETA {}s", eta.as_secs()),
+        None      => "ETA --".to_owned(),
+    };
+    eprint!("\r\x1b[2K{:5.1}%  {:.1} / {:.1} MiB  {}/{} files  {eta}  {}",
+            p.fraction() * 100.0, p.bytes_done as f64 / MIB, p.bytes_total as f64 / MIB,
+            p.files_done, p.files_total, p.current.unwrap_or(""));
+    if p.current.is_none() {
+        eprintln!();
+    }
+}
+
+/// Block-scan progress for `scan` and `recover`: a `\r`-rewritten bar on
+/// a terminal, or with `--progress-json` one JSON object per line, both on
+/// stderr and throttled.  `finish` prints the final state.
+struct ScanProgress {
+    json:    bool,
+    visible: bool,
+    start:   Instant,
+    shown:   Option<Instant>,
+    blocks:  u64,
+    done:    u64,
+    total:   u64,
+}
+
+impl ScanProgress {
+    const BAR_INTERVAL:  Duration = Duration::from_millis(100);
+    const JSON_INTERVAL: Duration = Duration::from_secs(1);
+
+    fn new(json: bool) -> Self {
+        Self {
+            json,
+            visible: json || std::io::stderr().is_terminal(),
+            start:   Instant::now(),
+            shown:   None,
+            blocks:  0,
+            done:    0,
+            total:   0,
+        }
+    }
+
+    /// Called by the scan after every block.
+    fn block(&mut self, done: u64, total: u64) {
+        (self.blocks, self.done, self.total) = (self.blocks + 1, done, total.max(done));
+        let interval = if self.json { Self::JSON_INTERVAL } else { Self::BAR_INTERVAL };
+        if self.shown.is_none_or(|t| t.elapsed() >= interval) {
+            self.show(false);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.show(true);
+    }
+
+    fn show(&mut self, finished: bool) {
+        if !self.visible {
+            return;
+        }
+        self.shown = Some(Instant::now());
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate    = self.blocks as f64 / elapsed.max(1e-3);
+        let eta     = (self.done > 0 && !finished)
+            .then(|| elapsed * (self.total - self.done) as f64 / self.done as f64);
+        if self.json {
+            let line = serde_json::json!({
+                "event":          if finished { "done" } else { "progress" },
+                "bytes_scanned":  self.done,
+                "bytes_total":    self.total,
+                "blocks":         self.blocks,
+                "blocks_per_sec": (rate * 10.0).round() / 10.0,
+                "elapsed_secs":   (elapsed * 10.0).round() / 10.0,
+                "eta_secs":       eta.map(|s| s.ceil() as u64),
+            });
+            eprintln!("{line}");
+            return;
+        }
+        const WIDTH: usize = 30;
+        let fraction = if self.total == 0 { 1.0 } else { self.done as f64 / self.total as f64 };
+        let filled   = (fraction * WIDTH as f64) as usize;
+        let eta = match eta {
+            Some(secs) => format!("ETA {}s", secs.ceil() as u64),
+            None       => "ETA --".to_owned(),
+        };
+        eprint!("\r\x1b[2K[{}{}] {:5.1}%  {} blocks  {rate:.0} blocks/s  {eta}",
+                "#".repeat(filled), ".".repeat(WIDTH - filled), fraction * 100.0, self.blocks);
+        if finished {
+            eprintln!();
+        }
+    }
+}
+
+#[cfg(feature = "plugins")]
+fn load_plugins(paths: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+    for path in paths {
+        // SAFETY: the user asked for this library to be loaded as a plugin.
+        let uuid = unsafe { sixcy::plugin::load(path)? };
+        eprintln!("Loaded plugin {} ({})", uuid_to_string(&uuid), path.display());
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "plugins"))]
+fn load_plugins(paths: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+    match paths.is_empty() {
+        true  => Ok(()),
+        false => Err("this build has no plugin support (enable the `plugins` feature)".into()),
+    }
+}
+
+/// `6cy list` table.
+fn write_listing(
+    out:     &mut impl Write,
+    input:   &std::path::Path,
+    codecs:  &[[u8; 16]],
+    entries: impl Iterator<Item = sixcy::error::Result<FileInfo>>,
+) -> std::io::Result<()> {
+    writeln!(out, "Archive: {}", input.display())?;
+    let codecs: Vec<String> = codecs.iter().map(codec_display_name).collect();
+    writeln!(out, "Codecs:  {}", codecs.join(", "))?;
+    writeln!(out, "{:<28} {:>12} {:>12} {:>12} {:>7} {:>7} {:<8} {:<3}  First block hash",
+             "Name", "Size", "Compressed", "Unique", "Ratio", "Chunks", "Codec", "Enc")?;
+    for info in entries {
+        let info  = info?;
+        let hash  = info.first_block_hash
+            .map(|h| hex::encode(&h[..6]))
+            .unwrap_or_else(|| "—".into());
+        let codec = info.codec.as_ref().map(codec_display_name).unwrap_or_else(|| "—".into());
+        writeln!(out, "{:<28} {:>12} {:>12} {:>12} {:>6.2}x {:>7} {:<8} {:<3}  {}",
+            info.name, info.original_size, info.compressed_size, info.unique_compressed_size,
+            info.ratio(), info.block_count, codec, if info.encrypted { "yes" } else { "no" }, hash)?;
+    }
+    Ok(())
+}
+
+/// `6cy pack --stats` lines for files stored as references.
+fn print_duplicates(duplicates: &[DuplicateFile]) {
+    if duplicates.is_empty() {
+        return;
+    }
+    let bytes: u64 = duplicates.iter().map(|d| d.size).sum();
+    println!("Duplicates: {} file(s), {bytes} B not stored again", duplicates.len());
+    for d in duplicates {
+        match &d.same_as {
+            Some(first) => println!("  {} ({} B) = {first}", d.name, d.size),
+            None        => println!("  {} ({} B) from chunks of earlier files", d.name, d.size),
+        }
+    }
+}
+
+fn parse_entry_order(name: &str) -> Result<EntryOrder, String> {
+    EntryOrder::from_name(name).ok_or_else(|| format!("Unknown order '{name}' (expected index or name)"))
+}
+
+/// `6cy blocks` output, a table or JSON lines.
+fn write_blocks(
+    out:     &mut impl Write,
+    headers: impl Iterator<Item = sixcy::error::Result<(u64, BlockHeader)>>,
+    json:    bool,
+) -> sixcy::error::Result<()> {
+    if !json {
+        writeln!(out, "{:>12} {:<6} {:<8} {:>8} {:>12} {:>10} {:>10} {:>6}  Hash",
+                 "Offset", "Type", "Codec", "File ID", "File offset", "Orig", "Comp", "Flags")?;
+    }
+    for entry in headers {
+        let (offset, h) = entry?;
+        if json {
+            let line = serde_json::json!({
+                "offset":       offset,
+                "type":         h.block_type.name(),
+                "codec":        codec_display_name(&h.codec_uuid),
+                "file_id":      h.file_id,
+                "file_offset":  h.file_offset,
+                "orig_size":    h.orig_size,
+                "comp_size":    h.comp_size,
+                "flags":        h.flags,
+                "encrypted":    h.is_encrypted(),
+                "content_hash": hex::encode(h.content_hash),
+            });
+            writeln!(out, "{line}")?;
+        } else {
+            writeln!(out, "{:>12} {:<6} {:<8} {:>8x} {:>12} {:>10} {:>10} {:>#6x}  {}",
+                offset, h.block_type.name(), codec_display_name(&h.codec_uuid), h.file_id, h.file_offset,
+                h.orig_size, h.comp_size, h.flags, hex::encode(&h.content_hash[..6]))?;
+        }
+    }
+    Ok(())
+}
+
+/// `6cy list --digests` manifest lines.  Returns how many entries had no
+/// recorded digest.
+fn write_digests(out: &mut impl Write, entries: impl Iterator<Item = FileInfo>) -> std::io::Result<usize> {
+    let mut missing = 0;
+    for info in entries {
+        match &info.secondary_hash {
+            Some(h) => writeln!(out, "{}  {}", hex::encode(&h.digest), info.name)?,
+            None    => missing += 1,
+        }
+    }
+    Ok(missing)
+}
+
+/// `pack --names` argument.
+fn parse_name_policy(name: &str) -> Result<NamePolicy, String> {
+    NamePolicy::from_name(name).ok_or_else(|| format!("expected raw, normalize, or reject, got '{name}'"))
+}
+
+/// `scrub --max-age`: a number with an `s`, `m`, `h` or `d` suffix.
+fn parse_age(s: &str) -> Result<Duration, String> {
+    let unit = match s.chars().last() {
+        Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 60 * 60,
+        Some('d') => 24 * 60 * 60,
+        _         => return Err(format!("expected a number with an s, m, h or d suffix, got '{s}'")),
+    };
+    s[..s.len() - 1].parse::<u64>()
+        .map(|n| Duration::from_secs(n.saturating_mul(unit)))
+        .map_err(|_| format!("expected a number with an s, m, h or d suffix, got '{s}'"))
+}
+
+/// `KEY=VALUE` arguments of `flag` as pairs.
+fn parse_key_values(flag: &str, args: &[String]) -> Result<Vec<(String, String)>, String> {
+    args.iter()
+        .map(|a| a.split_once('=').map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .ok_or_else(|| format!("Invalid {flag} '{a}' (expected KEY=VALUE)")))
+        .collect()
+}
+
 fn parse_codec(s: &str) -> CodecId {
     CodecId::from_name(s).unwrap_or_else(|| {
         eprintln!("Unknown codec '{}', defaulting to zstd", s);
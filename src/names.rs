@@ -0,0 +1,149 @@
+//! Entry-name encoding policy.
+//!
+//! Entry names are `/`-separated UTF-8 strings, but file systems disagree
+//! about names: Unix accepts any bytes but `/` and NUL, macOS stores
+//! decomposed (NFD) Unicode, and Windows and macOS compare names without
+//! regard to case, so `a.txt` and `A.txt` extract over each other there.
+//! [`NamePolicy`] decides what a writer does about it, and is recorded in
+//! the INDEX ([`FileIndex::name_policy`](crate::index::FileIndex::name_policy))
+//! so a reader knows what the names promise:
+//!
+//! - **`Raw`** (the default) stores names as given.  A name that is not
+//!   valid UTF-8 is stored lossily, as a display hint, with its exact bytes
+//!   in [`FileIndexRecord::raw_name`](crate::index::FileIndexRecord::raw_name);
+//!   extraction on Unix recreates those bytes.
+//! - **`Normalize`** makes names portable: NFC, invalid bytes replaced, and
+//!   a name equal to an earlier one but for case numbered like
+//!   `a (1).txt`.
+//! - **`Reject`** fails on any name `Normalize` would change.
+//!
+//! Raw bytes are captured on Unix only.  Windows names that are not valid
+//! Unicode (unpaired surrogates) are stored lossily under `Raw`.
+
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io;
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// See the module docs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamePolicy {
+    #[default]
+    Raw,
+    Normalize,
+    Reject,
+}
+
+impl NamePolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "raw"       => Some(Self::Raw),
+            "normalize" => Some(Self::Normalize),
+            "reject"    => Some(Self::Reject),
+            _           => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Raw       => "raw",
+            Self::Normalize => "normalize",
+            Self::Reject    => "reject",
+        }
+    }
+}
+
+/// An entry name on its way into an archive: the UTF-8 name, and the exact
+/// bytes it came from when those are not valid UTF-8.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntryName {
+    pub name: String,
+    pub raw:  Option<Vec<u8>>,
+}
+
+impl EntryName {
+    /// The entry for file-system name `component` inside this one (at the
+    /// top level if this name is empty).
+    pub fn join(&self, component: &OsStr) -> Self {
+        let lossy = component.to_string_lossy();
+        let sep   = if self.name.is_empty() { "" } else { "/" };
+        let raw   = match (&self.raw, os_bytes(component)) {
+            (None, None)        => None,
+            (parent, component) => {
+                let mut raw = parent.clone().unwrap_or_else(|| self.name.clone().into_bytes());
+                raw.extend_from_slice(sep.as_bytes());
+                raw.extend_from_slice(component.unwrap_or(lossy.as_bytes()));
+                Some(raw)
+            }
+        };
+        Self { name: format!("{}{sep}{lossy}", self.name), raw }
+    }
+
+    /// A top-level entry for file-system name `name`.
+    pub fn from_os(name: &OsStr) -> Self {
+        Self::default().join(name)
+    }
+}
+
+impl From<String> for EntryName {
+    fn from(name: String) -> Self { Self { name, raw: None } }
+}
+
+impl From<&str> for EntryName {
+    fn from(name: &str) -> Self { name.to_owned().into() }
+}
+
+/// `name`'s bytes if they are not valid UTF-8 and this platform exposes
+/// them.
+fn os_bytes(name: &OsStr) -> Option<&[u8]> {
+    if name.to_str().is_some() {
+        return None;
+    }
+    #[cfg(unix)]
+    { Some(std::os::unix::ffi::OsStrExt::as_bytes(name)) }
+    #[cfg(not(unix))]
+    { None }
+}
+
+/// Apply `policy` to the next name a writer stores.  `seen` holds the
+/// case-folded names admitted so far under `Normalize` or `Reject`.
+pub(crate) fn admit(policy: NamePolicy, seen: &mut HashSet<String>, name: EntryName) -> io::Result<EntryName> {
+    let name = match policy {
+        NamePolicy::Raw => return Ok(name),
+        NamePolicy::Normalize => EntryName { name: name.name.nfc().collect(), raw: None },
+        NamePolicy::Reject => {
+            let reject = |why: &str| Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("entry name {:?} {why}", name.name)));
+            if name.raw.is_some() {
+                return reject("is not valid UTF-8");
+            }
+            if !is_nfc(&name.name) {
+                return reject("is not in Unicode normal form C");
+            }
+            if seen.contains(&fold(&name.name)) {
+                return reject("differs from an earlier entry only by case");
+            }
+            name
+        }
+    };
+    if seen.insert(fold(&name.name)) {
+        return Ok(name);
+    }
+    // Number before the extension; a leading dot is not one.
+    let full = &name.name;
+    let base = full.rfind('/').map_or(0, |slash| slash + 1);
+    let dot  = full.rfind('.').filter(|&dot| dot > base);
+    let (stem, ext) = full.split_at(dot.unwrap_or(full.len()));
+    let renamed = (1..).map(|n| format!("{stem} ({n}){ext}"))
+        .find(|candidate| seen.insert(fold(candidate)))
+        .expect("some suffix is free");
+    Ok(EntryName { name: renamed, raw: None })
+}
+
+/// Comparison key for names on case-insensitive file systems.
+pub(crate) fn fold(name: &str) -> String {
+    name.to_lowercase()
+}
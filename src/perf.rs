@@ -12,14 +12,46 @@
 //! initialises its global pool lazily and falls back to sequential execution
 //! if the pool is not available.
 //!
+//! # Hashing
+//!
+//! [`hash_chunk`] hashes one chunk, spreading inputs of
+//! [`PARALLEL_HASH_MIN`] bytes or more over the Rayon pool with BLAKE3's
+//! multi-threaded hasher; [`hash_chunks`] hashes many chunks concurrently.
+//! Both are sequential without the `parallel` feature.
+//!
+//! # Writer pipeline
+//!
+//! [`ordered_pipeline`] is the writer's ingest → hash and compress → write
+//! loop.  The calling thread reads (or slices) chunks and hands them to
+//! [`encode_workers`] threads through a bounded channel; the workers hash
+//! each chunk and compress the ones not already stored, and the results
+//! come back out of order to be deduplicated and written in order, also on
+//! the calling thread.  At most `queue_depth` chunks are in flight, so
+//! reading, hashing, compressing, and writing overlap while memory stays
+//! near `queue_depth × chunk_size`.  A file of one chunk, or a build with
+//! one worker, runs every stage on the calling thread instead.
+//!
 //! # Write buffer
 //!
 //! [`WriteBuffer`] accumulates small writes into a fixed-capacity buffer and
 //! flushes to the underlying writer in large aligned chunks.  This reduces
 //! the number of `write` syscalls by 10–50× on typical archives, which is
 //! the dominant cost for small-file workloads.
+//!
+//! # Stage statistics
+//!
+//! [`PerfStats`] breaks pack and unpack time down by stage (hash, compress,
+//! encrypt, write / read, decrypt, decompress) for `6cy pack --stats` and
+//! [`crate::archive::Archive::perf_stats`].
+//!
+//! # Buffer pooling
+//!
+//! Chunk payloads from [`compress_chunks_parallel`] are drawn from
+//! [`crate::pool::shared`]; return them with `give` after writing so the
+//! next batch reuses their capacity.
 
 use std::io::{self, Write};
+use std::time::{Duration, Instant};
 use crate::codec::{CodecId, get_codec, CodecError};
 
 // ── Parallel chunk compression ────────────────────────────────────────────────
@@ -60,17 +92,7 @@ pub fn compress_chunks_parallel(
         let results: Vec<Result<CompressedChunk, CodecError>> = chunks
             .par_iter()
             .enumerate()
-            .map(|(i, chunk)| {
-                let hash: [u8; 32] = blake3::hash(chunk).into();
-                let c = get_codec(codec)?;
-                let payload = c.compress(chunk, level)?;
-                Ok(CompressedChunk {
-                    chunk_index:  i,
-                    content_hash: hash,
-                    orig_size:    chunk.len(),
-                    payload,
-                })
-            })
+            .map(|(i, chunk)| compress_chunk(i, chunk, codec, level))
             .collect();
 
         // Surface the first error if any.
@@ -86,21 +108,245 @@ pub fn compress_chunks_parallel(
         chunks
             .iter()
             .enumerate()
-            .map(|(i, chunk)| {
-                let hash: [u8; 32] = blake3::hash(chunk).into();
-                let c = get_codec(codec)?;
-                let payload = c.compress(chunk, level)?;
-                Ok(CompressedChunk {
-                    chunk_index:  i,
-                    content_hash: hash,
-                    orig_size:    chunk.len(),
-                    payload,
-                })
-            })
+            .map(|(i, chunk)| compress_chunk(i, chunk, codec, level))
             .collect()
     }
 }
 
+/// Compress one chunk into a buffer drawn from [`crate::pool::shared`];
+/// hand `payload` back with `pool::shared().give` once it is written.
+fn compress_chunk(i: usize, chunk: &[u8], codec: CodecId, level: i32) -> Result<CompressedChunk, CodecError> {
+    let hash = hash_chunk(chunk);
+    let mut payload = crate::pool::shared().take(chunk.len() / 2);
+    get_codec(codec)?.compress_into(chunk, level, &mut payload)?;
+    Ok(CompressedChunk {
+        chunk_index:  i,
+        content_hash: hash,
+        orig_size:    chunk.len(),
+        payload,
+    })
+}
+
+// ── Hashing ───────────────────────────────────────────────────────────────────
+
+/// Chunks at least this large are hashed with BLAKE3's multi-threaded
+/// hasher under the `parallel` feature; below it the split costs more
+/// than it saves.
+pub const PARALLEL_HASH_MIN: usize = 1024 * 1024;
+
+/// BLAKE3 of `data`; see the module docs.
+pub fn hash_chunk(data: &[u8]) -> [u8; 32] {
+    #[cfg(feature = "parallel")]
+    if data.len() >= PARALLEL_HASH_MIN {
+        return blake3::Hasher::new().update_rayon(data).finalize().into();
+    }
+    blake3::hash(data).into()
+}
+
+/// [`hash_chunk`] of every chunk, in order.
+pub fn hash_chunks(chunks: &[&[u8]]) -> Vec<[u8; 32]> {
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        chunks.par_iter().map(|c| hash_chunk(c)).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        chunks.iter().map(|c| hash_chunk(c)).collect()
+    }
+}
+
+// ── Writer pipeline ───────────────────────────────────────────────────────────
+
+/// Threads that hash, compress, and encrypt chunks in
+/// [`ordered_pipeline`]: one per Rayon pool thread, or a single one
+/// without the `parallel` feature.
+pub fn encode_workers() -> usize {
+    #[cfg(feature = "parallel")]
+    { rayon::current_num_threads() }
+    #[cfg(not(feature = "parallel"))]
+    { 1 }
+}
+
+/// Default writer queue depth: two chunks per [`encode_workers`] thread,
+/// so workers stay busy while finished blocks are written out.
+pub fn encode_window() -> usize {
+    encode_workers() * 2
+}
+
+/// Pull jobs from `next` and pass each through `work` on
+/// [`encode_workers`] threads, handing the results to `sink` in job order;
+/// see the module docs.  `next` and `sink` run on the calling thread and
+/// share `state`.  At most `depth` jobs are between `next` and `sink` at
+/// once.  Stops at the first error from any stage.  With a depth of 1, a
+/// single worker, a single job, or on targets without threads, every stage
+/// runs in turn on the caller, so no thread is spawned.
+pub(crate) fn ordered_pipeline<S, J: Send, R: Send, E: Send>(
+    state:    &mut S,
+    depth:    usize,
+    mut next: impl FnMut(&mut S) -> Result<Option<J>, E>,
+    work:     impl Fn(J) -> Result<R, E> + Sync,
+    mut sink: impl FnMut(&mut S, R) -> Result<(), E>,
+) -> Result<(), E> {
+    if depth <= 1 || encode_workers() == 1 || cfg!(target_family = "wasm") {
+        while let Some(job) = next(state)? {
+            sink(state, work(job)?)?;
+        }
+        return Ok(());
+    }
+    // Most files are one chunk: only start workers for a second.
+    let Some(first) = next(state)? else { return Ok(()) };
+    let Some(second) = next(state)? else { return sink(state, work(first)?) };
+
+    use std::collections::BTreeMap;
+    use std::sync::{mpsc, Mutex};
+
+    let (job_tx, job_rx) = mpsc::sync_channel::<(u64, J)>(depth);
+    let (done_tx, done_rx) = mpsc::channel::<(u64, Result<R, E>)>();
+    let job_rx = Mutex::new(job_rx);
+    std::thread::scope(|scope| {
+        for _ in 0..encode_workers().min(depth) {
+            let done_tx = done_tx.clone();
+            let (job_rx, work) = (&job_rx, &work);
+            scope.spawn(move || loop {
+                let job = job_rx.lock().unwrap_or_else(|e| e.into_inner()).recv();
+                let Ok((seq, job)) = job else { break };
+                if done_tx.send((seq, work(job))).is_err() { break; }
+            });
+        }
+        drop(done_tx);
+        for (seq, job) in [first, second].into_iter().enumerate() {
+            job_tx.send((seq as u64, job)).expect("encode workers outlive the queue");
+        }
+
+        let mut run = || -> Result<(), E> {
+            let (mut sent, mut written) = (2u64, 0u64);
+            let mut ingesting = true;
+            let mut ready     = BTreeMap::new();
+            loop {
+                while ingesting && sent - written < depth as u64 {
+                    match next(state)? {
+                        Some(job) => {
+                            job_tx.send((sent, job)).expect("encode workers outlive the queue");
+                            sent += 1;
+                        }
+                        None => ingesting = false,
+                    }
+                }
+                if written == sent {
+                    return Ok(());
+                }
+                let (seq, result) = done_rx.recv().expect("encode workers outlive the queue");
+                ready.insert(seq, result);
+                while let Some(result) = ready.remove(&written) {
+                    sink(state, result?)?;
+                    written += 1;
+                }
+            }
+        };
+        let result = run();
+        // Workers finish the queued jobs, then see the closed channel.
+        drop(job_tx);
+        drop(done_rx);
+        result
+    })
+}
+
+// ── Stage statistics ──────────────────────────────────────────────────────────
+
+/// Timings and byte counts collected by [`crate::io_stream::SixCyWriter`]
+/// and [`crate::io_stream::SixCyReader`] once `enable_perf_stats` is called.
+///
+/// Writers fill `hash`, `compress`, `encrypt`, and `write`; readers fill
+/// `read`, `decrypt`, `decompress`, and `hash` (content verification).
+/// `bytes_in` is what the pipeline consumed (plaintext for a writer, on-disk
+/// bytes for a reader) and `bytes_out` what it produced.  Disabled
+/// collection never reads the clock.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PerfStats {
+    /// Time since collection started; frozen when a writer finalizes.
+    pub wall:       Duration,
+    pub bytes_in:   u64,
+    pub bytes_out:  u64,
+    pub blocks:     u64,
+    pub hash:       Duration,
+    pub compress:   Duration,
+    pub encrypt:    Duration,
+    pub write:      Duration,
+    pub read:       Duration,
+    pub decrypt:    Duration,
+    pub decompress: Duration,
+    started:        Option<Instant>,
+}
+
+impl PerfStats {
+    /// Begin collecting now.
+    pub fn start() -> Self {
+        Self { started: Some(Instant::now()), ..Self::default() }
+    }
+
+    /// A copy with `wall` brought up to date.
+    pub fn snapshot(&self) -> Self {
+        let mut s = *self;
+        if let Some(t) = self.started { s.wall = t.elapsed(); }
+        s
+    }
+
+    /// Add `other`'s hash, compress, and encrypt times, as measured on a
+    /// worker thread.
+    pub(crate) fn add_encode_stages(&mut self, other: &PerfStats) {
+        self.hash     += other.hash;
+        self.compress += other.compress;
+        self.encrypt  += other.encrypt;
+    }
+
+    /// Stop the wall clock.
+    pub fn finish(&mut self) {
+        *self = self.snapshot();
+        self.started = None;
+    }
+}
+
+impl std::fmt::Display for PerfStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mb_s = |bytes: u64| match self.wall.as_secs_f64() {
+            t if t > 0.0 => bytes as f64 / t / 1e6,
+            _            => 0.0,
+        };
+        writeln!(f, "  wall        {:>10.3?}  ({:.1} MB/s in)", self.wall, mb_s(self.bytes_in))?;
+        writeln!(f, "  bytes       {} in → {} out, {} blocks", self.bytes_in, self.bytes_out, self.blocks)?;
+        for (stage, t) in [
+            ("hash", self.hash), ("compress", self.compress), ("encrypt", self.encrypt),
+            ("write", self.write), ("read", self.read), ("decrypt", self.decrypt),
+            ("decompress", self.decompress),
+        ] {
+            if !t.is_zero() { writeln!(f, "  {stage:<11} {t:>10.3?}")?; }
+        }
+        Ok(())
+    }
+}
+
+/// Lap timer that is free when stats are disabled.
+pub(crate) struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    pub(crate) fn new(enabled: bool) -> Self { Self(enabled.then(Instant::now)) }
+
+    /// Time since the previous lap (or creation); zero when disabled.
+    pub(crate) fn lap(&mut self) -> Duration {
+        match &mut self.0 {
+            Some(t) => {
+                let now = Instant::now();
+                let d   = now - *t;
+                *t = now;
+                d
+            }
+            None => Duration::ZERO,
+        }
+    }
+}
+
 // ── Write buffer ─────────────────────────────────────────────────────────────
 
 /// Buffered writer with configurable flush threshold.
@@ -197,10 +443,13 @@ pub fn decompress_into(
 ///
 /// The encoded format is a sequence of frames:
 /// ```text
-/// LIT frame: 0x00 <u16 LE count> <count raw bytes>
-/// RUN frame: 0x01 <u16 LE count> <byte>
+/// LIT   frame: 0x00 <u16 LE count> <count raw bytes>
+/// RUN   frame: 0x01 <u16 LE count> <byte>
+/// RUN32 frame: 0x02 <u32 LE count> <byte>     (runs longer than 65535)
 /// ```
-/// Maximum run length per frame: 65535 bytes.
+/// A multi-megabyte zero run is one RUN32 frame rather than a chain of
+/// 65535-byte RUN frames.  Run detection compares 16 bytes at a time with
+/// SSE2 (x86_64) or NEON (aarch64), with a scalar fallback elsewhere.
 pub fn rle_encode(data: &[u8]) -> Vec<u8> {
     if data.is_empty() { return Vec::new(); }
 
@@ -210,43 +459,97 @@ pub fn rle_encode(data: &[u8]) -> Vec<u8> {
     while i < data.len() {
         // Detect run.
         let run_byte = data[i];
-        let mut run_len = 1usize;
-        while i + run_len < data.len()
-            && data[i + run_len] == run_byte
-            && run_len < 65535
-        {
-            run_len += 1;
-        }
+        let end      = data.len().min(i + u32::MAX as usize);
+        let run_len  = run_length(&data[i..end]);
 
-        if run_len >= 4 {
-            // Emit RUN frame.
-            out.push(0x01);
-            out.extend_from_slice(&(run_len as u16).to_le_bytes());
+        if run_len >= RLE_MIN_RUN {
+            // Emit RUN or RUN32 frame.
+            if run_len <= u16::MAX as usize {
+                out.push(0x01);
+                out.extend_from_slice(&(run_len as u16).to_le_bytes());
+            } else {
+                out.push(0x02);
+                out.extend_from_slice(&(run_len as u32).to_le_bytes());
+            }
             out.push(run_byte);
             i += run_len;
         } else {
             // Accumulate literal bytes until we see a long run or EOF.
             let lit_start = i;
-            let mut lit_len = 0usize;
-            while i < data.len() && lit_len < 65535 {
-                // Peek for a run.
-                let b = data[i];
-                let mut peek = 0usize;
-                while i + peek < data.len() && data[i + peek] == b && peek < 4 {
-                    peek += 1;
+            while i < data.len() && i - lit_start < u16::MAX as usize {
+                if data.get(i..i + RLE_MIN_RUN).is_some_and(|w| w.iter().all(|&b| b == w[0])) {
+                    break;
                 }
-                if peek >= 4 { break; }
-                lit_len += 1;
                 i += 1;
             }
             out.push(0x00);
-            out.extend_from_slice(&(lit_len as u16).to_le_bytes());
-            out.extend_from_slice(&data[lit_start..lit_start + lit_len]);
+            out.extend_from_slice(&((i - lit_start) as u16).to_le_bytes());
+            out.extend_from_slice(&data[lit_start..i]);
         }
     }
     out
 }
 
+/// Shortest run emitted as a RUN frame; a frame costs 4 bytes.
+const RLE_MIN_RUN: usize = 4;
+
+/// Number of leading bytes of `data` equal to `data[0]`.
+fn run_length(data: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    { run_length_sse2(data) }
+    #[cfg(target_arch = "aarch64")]
+    { run_length_neon(data) }
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    { data.first().map_or(0, |&b| run_length_scalar(data, b)) }
+}
+
+fn run_length_scalar(data: &[u8], byte: u8) -> usize {
+    data.iter().take_while(|&&b| b == byte).count()
+}
+
+#[cfg(target_arch = "x86_64")]
+fn run_length_sse2(data: &[u8]) -> usize {
+    use std::arch::x86_64::*;
+
+    let Some(&byte) = data.first() else { return 0 };
+    let mut i = 0usize;
+    // SAFETY: SSE2 is part of the x86_64 baseline; every load reads 16
+    // in-bounds bytes through an unaligned load.
+    unsafe {
+        let needle = _mm_set1_epi8(byte as i8);
+        while i + 16 <= data.len() {
+            let v    = _mm_loadu_si128(data.as_ptr().add(i) as *const __m128i);
+            let mask = _mm_movemask_epi8(_mm_cmpeq_epi8(v, needle)) as u32;
+            if mask != 0xFFFF {
+                return i + (!mask).trailing_zeros() as usize;
+            }
+            i += 16;
+        }
+    }
+    i + run_length_scalar(&data[i..], byte)
+}
+
+#[cfg(target_arch = "aarch64")]
+fn run_length_neon(data: &[u8]) -> usize {
+    use std::arch::aarch64::*;
+
+    let Some(&byte) = data.first() else { return 0 };
+    let mut i = 0usize;
+    // SAFETY: NEON is mandatory on aarch64; every load reads 16 in-bounds
+    // bytes.
+    unsafe {
+        let needle = vdupq_n_u8(byte);
+        while i + 16 <= data.len() {
+            let eq = vceqq_u8(vld1q_u8(data.as_ptr().add(i)), needle);
+            if vminvq_u8(eq) != 0xFF {
+                break; // mismatch in this lane group; the scalar tail finds it
+            }
+            i += 16;
+        }
+    }
+    i + run_length_scalar(&data[i..], byte)
+}
+
 /// Decode a buffer produced by [`rle_encode`].
 pub fn rle_decode(data: &[u8]) -> Option<Vec<u8>> {
     let mut out = Vec::new();
@@ -254,8 +557,13 @@ pub fn rle_decode(data: &[u8]) -> Option<Vec<u8>> {
 
     while i < data.len() {
         let frame_type = data[i]; i += 1;
-        if i + 2 > data.len() { return None; }
-        let count = u16::from_le_bytes([data[i], data[i + 1]]) as usize; i += 2;
+        let width = if frame_type == 0x02 { 4 } else { 2 };
+        let count_bytes = data.get(i..i + width)?;
+        let count = match width {
+            4 => u32::from_le_bytes(count_bytes.try_into().ok()?) as usize,
+            _ => u16::from_le_bytes(count_bytes.try_into().ok()?) as usize,
+        };
+        i += width;
 
         match frame_type {
             0x00 => {
@@ -264,11 +572,10 @@ pub fn rle_decode(data: &[u8]) -> Option<Vec<u8>> {
                 out.extend_from_slice(&data[i..i + count]);
                 i += count;
             }
-            0x01 => {
-                // RUN frame.
-                if i >= data.len() { return None; }
-                let byte = data[i]; i += 1;
-                out.extend(std::iter::repeat(byte).take(count));
+            0x01 | 0x02 => {
+                // RUN / RUN32 frame.
+                let byte = *data.get(i)?; i += 1;
+                out.extend(std::iter::repeat_n(byte, count));
             }
             _ => return None,
         }
@@ -299,6 +606,37 @@ mod tests {
         assert_eq!(decoded, data);
     }
 
+    #[test]
+    fn rle_long_run_is_one_frame() {
+        let mut data = vec![0u8; 8 * 1024 * 1024];
+        data.extend(b"tail");
+        let encoded = rle_encode(&data);
+        assert_eq!(&encoded[..6], &[0x02, 0x00, 0x00, 0x80, 0x00, 0x00]);
+        assert_eq!(encoded.len(), 6 + 3 + 4);
+        assert_eq!(rle_decode(&encoded).expect("decode failed"), data);
+    }
+
+    #[test]
+    fn run_length_matches_scalar() {
+        let mut data = vec![7u8; 100];
+        for cut in [0, 1, 3, 15, 16, 17, 31, 32, 64, 99] {
+            let mut d = data.clone();
+            d[cut] = 8;
+            assert_eq!(run_length(&d[..]), run_length_scalar(&d, d[0]), "cut {cut}");
+        }
+        data.truncate(37);
+        assert_eq!(run_length(&data), 37);
+        assert_eq!(run_length(&[]), 0);
+    }
+
+    #[test]
+    fn rle_decodes_u16_run_frames() {
+        let frames = [0x01, 0xFF, 0xFF, 0xAA, 0x00, 0x02, 0x00, b'h', b'i'];
+        let decoded = rle_decode(&frames).expect("decode failed");
+        assert_eq!(decoded.len(), 65535 + 2);
+        assert!(rle_decode(&[0x02, 0x01, 0x00]).is_none());
+    }
+
     #[test]
     fn rle_empty() {
         assert_eq!(rle_encode(&[]), Vec::<u8>::new());
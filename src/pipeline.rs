@@ -0,0 +1,256 @@
+//! Per-archive block pipelines: prefilter → codec → cipher.
+//!
+//! By default the writer compresses each block with the codec the caller
+//! names and encrypts it when a key is set.  A [`BlockPipeline`] fixes all
+//! three stages for every DATA and SOLID block of an archive:
+//!
+//! ```ignore
+//! let pipeline = BlockPipeline::new(CodecId::Zstd)
+//!     .filter(Filter::Delta(4))      // e.g. 32-bit samples
+//!     .level(19)
+//!     .cipher(Cipher::Aes256Gcm);    // needs a password or key
+//! let opts = PackOptions { pipeline: Some(pipeline), ..PackOptions::default() };
+//! ```
+//!
+//! Each block header records its stages, so a reader never has to be told
+//! the pipeline: the filter in bits 8–11 of `flags` ([`FLAG_FILTER_MASK`]),
+//! the codec in `codec_uuid`, the cipher in [`FLAG_ENCRYPTED`].  Filters are
+//! size-preserving and `content_hash` stays the BLAKE3 of the unfiltered
+//! plaintext, so dedup and verification are unaffected.  Archives with
+//! filtered blocks set [`FEATURE_PREFILTER`], which older readers refuse
+//! rather than return filtered bytes.
+//!
+//! [`FLAG_ENCRYPTED`]: crate::block::FLAG_ENCRYPTED
+//! [`FEATURE_PREFILTER`]: crate::superblock::FEATURE_PREFILTER
+
+use std::fmt;
+use std::io;
+
+use crate::codec::{CodecError, CodecId};
+
+pub use crate::core_reader::{FLAG_FILTER_MASK, FLAG_FILTER_SHIFT};
+
+// ── Filters ──────────────────────────────────────────────────────────────────
+
+/// A reversible, size-preserving transform applied before compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    /// Each byte minus the byte `stride` positions earlier (wrapping), for
+    /// slowly changing fixed-width samples.  Stride 1, 2, 4, or 8.
+    Delta(u8),
+    /// Byte 0 of every `width`-byte element, then byte 1, and so on, so
+    /// similar bytes of numeric arrays sit together.  Width 2, 4, or 8; a
+    /// trailing partial element is left in place.
+    Shuffle(u8),
+}
+
+impl Filter {
+    /// Parse `delta4`, `shuffle8`, … (`delta` alone is `delta1`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let (kind, width) = name.split_at(name.find(|c: char| c.is_ascii_digit()).unwrap_or(name.len()));
+        let width = if width.is_empty() { 1 } else { width.parse().ok()? };
+        let filter = match kind {
+            "delta"   => Self::Delta(width),
+            "shuffle" => Self::Shuffle(width),
+            _         => return None,
+        };
+        filter.code().map(|_| filter)
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Self::Delta(stride)  => format!("delta{stride}"),
+            Self::Shuffle(width) => format!("shuffle{width}"),
+        }
+    }
+
+    /// The 4-bit code stored in a block's `flags`; `None` for an
+    /// unsupported stride or width.
+    pub fn code(&self) -> Option<u16> {
+        Some(match self {
+            Self::Delta(1)   => 1,
+            Self::Delta(2)   => 2,
+            Self::Delta(4)   => 3,
+            Self::Delta(8)   => 4,
+            Self::Shuffle(2) => 5,
+            Self::Shuffle(4) => 6,
+            Self::Shuffle(8) => 7,
+            _                => return None,
+        })
+    }
+
+    /// The filter recorded in block `flags`, if any.
+    pub fn from_flags(flags: u16) -> Result<Option<Self>, CodecError> {
+        let filter = match (flags & FLAG_FILTER_MASK) >> FLAG_FILTER_SHIFT {
+            0 => return Ok(None),
+            1 => Self::Delta(1),
+            2 => Self::Delta(2),
+            3 => Self::Delta(4),
+            4 => Self::Delta(8),
+            5 => Self::Shuffle(2),
+            6 => Self::Shuffle(4),
+            7 => Self::Shuffle(8),
+            code => return Err(CodecError::Decompression(format!("unknown prefilter code {code}"))),
+        };
+        Ok(Some(filter))
+    }
+
+    /// Append the filtered form of `data` to `out`.
+    pub fn apply(&self, data: &[u8], out: &mut Vec<u8>) {
+        match *self {
+            Self::Delta(stride) => {
+                let stride = stride as usize;
+                out.extend(data.iter().enumerate().map(|(i, &b)| match i.checked_sub(stride) {
+                    Some(prev) => b.wrapping_sub(data[prev]),
+                    None       => b,
+                }));
+            }
+            Self::Shuffle(width) => {
+                let width = width as usize;
+                let whole = data.len() / width * width;
+                for lane in 0..width {
+                    out.extend(data[..whole].iter().skip(lane).step_by(width));
+                }
+                out.extend_from_slice(&data[whole..]);
+            }
+        }
+    }
+
+    /// Undo [`apply`](Self::apply) in place.
+    pub fn revert(&self, data: &mut [u8]) {
+        match *self {
+            Self::Delta(stride) => {
+                for i in stride as usize..data.len() {
+                    data[i] = data[i].wrapping_add(data[i - stride as usize]);
+                }
+            }
+            Self::Shuffle(width) => {
+                let width = width as usize;
+                let whole = data.len() / width * width;
+                let lanes = data[..whole].to_vec();
+                let count = whole / width;
+                for (i, b) in data[..whole].iter_mut().enumerate() {
+                    *b = lanes[(i % width) * count + i / width];
+                }
+            }
+        }
+    }
+}
+
+// ── Ciphers ──────────────────────────────────────────────────────────────────
+
+/// Block cipher stage.  The key is the archive's (password-derived or
+/// supplied with [`Archive::create_with_key`](crate::archive::Archive::create_with_key)).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+    /// AES-256-GCM, flagged by [`FLAG_ENCRYPTED`](crate::block::FLAG_ENCRYPTED).
+    Aes256Gcm,
+}
+
+impl Cipher {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "aes-256-gcm" | "aes256gcm" | "aes" => Some(Self::Aes256Gcm),
+            _                                   => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Aes256Gcm => "aes-256-gcm",
+        }
+    }
+}
+
+// ── Pipeline ─────────────────────────────────────────────────────────────────
+
+/// The stages every DATA and SOLID block of an archive goes through; see
+/// the module docs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockPipeline {
+    pub filter: Option<Filter>,
+    /// Replaces the codec passed to `add_file` and `begin_solid`.
+    pub codec:  CodecId,
+    /// Replaces the writer's levels (and `auto_level`) when set.
+    pub level:  Option<i32>,
+    pub cipher: Option<Cipher>,
+}
+
+impl BlockPipeline {
+    pub fn new(codec: CodecId) -> Self {
+        Self { filter: None, codec, level: None, cipher: None }
+    }
+
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn level(mut self, level: i32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    pub fn cipher(mut self, cipher: Cipher) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Parse comma-separated stages in order — optional filter, codec
+    /// (with an optional `:level`), optional cipher — e.g.
+    /// `delta4,zstd:19,aes-256-gcm`.
+    pub fn from_spec(spec: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        let mut stages = spec.split(',').map(str::trim).peekable();
+        let filter = match stages.peek().and_then(|s| Filter::from_name(s)) {
+            Some(f) => { stages.next(); Some(f) }
+            None    => None,
+        };
+        let codec_stage = stages.next().ok_or_else(|| invalid(format!("pipeline `{spec}` has no codec")))?;
+        let (codec, level) = match codec_stage.split_once(':') {
+            Some((c, l)) => (c, Some(l.parse().map_err(|_| invalid(format!("bad level in `{codec_stage}`")))?)),
+            None         => (codec_stage, None),
+        };
+        let codec = CodecId::from_name(codec)
+            .ok_or_else(|| invalid(format!("unknown codec or filter `{codec}` in pipeline")))?;
+        let cipher = stages.next()
+            .map(|c| Cipher::from_name(c).ok_or_else(|| invalid(format!("unknown cipher `{c}` in pipeline"))))
+            .transpose()?;
+        if let Some(extra) = stages.next() {
+            return Err(invalid(format!("unexpected stage `{extra}` in pipeline")));
+        }
+        Ok(Self { filter, codec, level, cipher })
+    }
+
+    /// Check the stages are supported and that a cipher stage has a key
+    /// (`keyed`) and a key has a cipher stage to use it.
+    pub fn validate(&self, keyed: bool) -> io::Result<()> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        if let Some(filter) = self.filter.filter(|f| f.code().is_none()) {
+            return Err(invalid(format!("unsupported prefilter {}", filter.name())));
+        }
+        match (self.cipher, keyed) {
+            (Some(c), false) => Err(invalid(format!("pipeline cipher {} needs a password or key", c.name()))),
+            (None, true)     => Err(invalid("archive is encrypted but the pipeline has no cipher stage".into())),
+            _                => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for BlockPipeline {
+    /// `delta4 → zstd:19 → aes-256-gcm`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(filter) = self.filter {
+            write!(f, "{} → ", filter.name())?;
+        }
+        f.write_str(self.codec.name())?;
+        if let Some(level) = self.level {
+            write!(f, ":{level}")?;
+        }
+        if let Some(cipher) = self.cipher {
+            write!(f, " → {}", cipher.name())?;
+        }
+        Ok(())
+    }
+}
@@ -27,6 +27,14 @@
 //! The host pre-allocates output buffers using the upper bound returned by
 //! `compress_bound`.  A plugin that needs scratch space must manage its own
 //! memory independently.
+//!
+//! # Registry
+//! [`register`] (or [`load`], with the `plugins` feature) adds a plugin to
+//! the process-wide registry.  Registered UUIDs satisfy the superblock's
+//! required-codec check and decode blocks exactly like built-in codecs;
+//! see [`crate::codec::codec_source`].
+
+use std::sync::RwLock;
 
 /// ABI version of this header.  Written into `SixcyCodecPlugin::abi_version`.
 pub const SIXCY_PLUGIN_ABI_VERSION: u32 = 1;
@@ -116,6 +124,7 @@ unsafe impl Send for SixcyCodecPlugin {}
 unsafe impl Sync for SixcyCodecPlugin {}
 
 /// Safe Rust wrapper around a loaded [`SixcyCodecPlugin`].
+#[derive(Clone, Copy)]
 pub struct PluginCodec {
     /// Raw descriptor — lifetime must outlive this wrapper.
     desc: &'static SixcyCodecPlugin,
@@ -172,3 +181,67 @@ impl PluginCodec {
         Ok(out)
     }
 }
+
+// ── Registry ─────────────────────────────────────────────────────────────────
+
+static REGISTRY: RwLock<Vec<PluginCodec>> = RwLock::new(Vec::new());
+
+/// Add a plugin to the process-wide registry.
+///
+/// # Errors
+/// Fails if the ABI version is too new, `decompress` is missing, the UUID is
+/// nil or belongs to a built-in codec, or a different descriptor already
+/// claims the UUID.  Registering the same descriptor twice is a no-op.
+pub fn register(desc: &'static SixcyCodecPlugin) -> Result<(), String> {
+    let codec = PluginCodec::new(desc)?;
+    let uuid  = crate::codec::uuid_to_string(&desc.uuid);
+    if desc.decompress.is_none() {
+        return Err(format!("Plugin {uuid} has no decompress fn"));
+    }
+    if desc.uuid == crate::codec::UUID_NONE || crate::codec::CodecId::from_uuid(&desc.uuid).is_some() {
+        return Err(format!("Plugin UUID {uuid} collides with a built-in codec"));
+    }
+    let mut registry = REGISTRY.write().unwrap_or_else(|e| e.into_inner());
+    match registry.iter().find(|p| p.desc.uuid == desc.uuid) {
+        Some(p) if std::ptr::eq(p.desc, desc) => Ok(()),
+        Some(_) => Err(format!("Plugin UUID {uuid} is already registered")),
+        None    => { registry.push(codec); Ok(()) }
+    }
+}
+
+/// The registered plugin for `uuid`, if any.
+pub fn registered(uuid: &[u8; 16]) -> Option<PluginCodec> {
+    REGISTRY.read().unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .find(|p| p.uuid() == uuid)
+        .copied()
+}
+
+/// UUIDs of every registered plugin, in registration order.
+pub fn registered_uuids() -> Vec<[u8; 16]> {
+    REGISTRY.read().unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|p| *p.uuid())
+        .collect()
+}
+
+/// Load a plugin shared library, call its `sixcy_codec_register`, and
+/// register the descriptor.  The library stays loaded for the life of the
+/// process.  Returns the plugin's UUID.
+///
+/// # Safety
+/// Loading a library runs its initialisers, and the descriptor it returns
+/// is trusted to honour the ABI contract above.
+#[cfg(feature = "plugins")]
+pub unsafe fn load(path: &std::path::Path) -> Result<[u8; 16], String> {
+    type RegisterFn = unsafe extern "C" fn() -> *const SixcyCodecPlugin;
+    let lib = libloading::Library::new(path)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    let lib: &'static libloading::Library = Box::leak(Box::new(lib));
+    let entry: libloading::Symbol<RegisterFn> = lib.get(b"sixcy_codec_register\0")
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    let desc = entry().as_ref()
+        .ok_or_else(|| format!("{}: sixcy_codec_register returned null", path.display()))?;
+    register(desc)?;
+    Ok(desc.uuid)
+}
@@ -0,0 +1,202 @@
+//! Reusable byte buffers for the block pipeline.
+//!
+//! Encoding and decoding a block needs scratch space — the compressed
+//! payload before encryption, the decrypted payload before decompression,
+//! the on-disk bytes read from the archive.  Allocating these per block
+//! dominates allocator traffic for archives with hundreds of thousands of
+//! small blocks.  [`BufferPool`] keeps released buffers (with their
+//! capacity) and hands them back out; [`shared`] is the process-wide pool
+//! used by [`crate::block`], [`crate::io_stream`], and [`crate::perf`].
+//!
+//! The pool is a mutex-guarded free list rather than thread-local storage so
+//! a buffer released on one thread (e.g. a Rayon worker's output consumed
+//! by the writer) is reusable by any other.  Buffers are bounded in count,
+//! in capacity, and in total idle capacity, so a single huge block does not
+//! stay resident and the shared pool pins at most
+//! [`DEFAULT_MAX_IDLE_BYTES`].
+//!
+//! Pooled buffers routinely hold decrypted plaintext, so [`BufferPool::give`]
+//! zeroes a buffer's contents before keeping it — only those it keeps, and
+//! only up to its length, since every caller of [`BufferPool::take`] writes
+//! before it reads.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Default number of idle buffers retained by [`shared`].
+pub const DEFAULT_MAX_BUFFERS:  usize = 64;
+/// Buffers with more capacity than this are freed instead of pooled.
+pub const DEFAULT_MAX_CAPACITY: usize = 16 * 1024 * 1024;
+/// Default cap on the summed capacity of idle buffers retained by [`shared`].
+pub const DEFAULT_MAX_IDLE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Counters reported by [`BufferPool::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// `take` calls served from the free list.
+    pub hits:   u64,
+    /// `take` calls that allocated.
+    pub misses: u64,
+    /// Buffers currently idle in the pool.
+    pub idle:   usize,
+    /// Summed capacity of the idle buffers.
+    pub idle_bytes: usize,
+}
+
+/// Idle buffers and their summed capacity.
+#[derive(Default)]
+struct FreeList {
+    bufs:  Vec<Vec<u8>>,
+    bytes: usize,
+}
+
+impl FreeList {
+    fn has_room(&self, capacity: usize, max_buffers: usize, max_bytes: usize) -> bool {
+        self.bufs.len() < max_buffers && self.bytes.saturating_add(capacity) <= max_bytes
+    }
+}
+
+pub struct BufferPool {
+    free:           Mutex<FreeList>,
+    max_buffers:    usize,
+    max_capacity:   usize,
+    max_idle_bytes: usize,
+    hits:           AtomicU64,
+    misses:         AtomicU64,
+}
+
+impl Default for BufferPool {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BUFFERS, DEFAULT_MAX_CAPACITY).with_max_idle_bytes(DEFAULT_MAX_IDLE_BYTES)
+    }
+}
+
+impl BufferPool {
+    /// A pool keeping up to `max_buffers` idle buffers of at most
+    /// `max_capacity` bytes each, with no cap on their total.
+    pub fn new(max_buffers: usize, max_capacity: usize) -> Self {
+        Self {
+            free:           Mutex::new(FreeList::default()),
+            max_buffers,
+            max_capacity,
+            max_idle_bytes: usize::MAX,
+            hits:           AtomicU64::new(0),
+            misses:         AtomicU64::new(0),
+        }
+    }
+
+    /// Keep idle buffers only while their summed capacity stays within
+    /// `max_bytes`.
+    pub fn with_max_idle_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_idle_bytes = max_bytes;
+        self
+    }
+
+    fn free_list(&self) -> std::sync::MutexGuard<'_, FreeList> {
+        self.free.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// An empty buffer with at least `min_capacity` bytes reserved.
+    pub fn take(&self, min_capacity: usize) -> Vec<u8> {
+        let reused = {
+            let mut free = self.free_list();
+            let buf = free.bufs.pop();
+            free.bytes -= buf.as_ref().map_or(0, Vec::capacity);
+            buf
+        };
+        match reused {
+            Some(mut buf) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                buf.reserve(min_capacity);
+                buf
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                Vec::with_capacity(min_capacity)
+            }
+        }
+    }
+
+    /// Return a buffer for reuse.  If the pool has room for it, its
+    /// contents are zeroed and it is kept; otherwise it is freed.
+    pub fn give(&self, mut buf: Vec<u8>) {
+        let capacity = buf.capacity();
+        if capacity == 0 || capacity > self.max_capacity
+            || !self.free_list().has_room(capacity, self.max_buffers, self.max_idle_bytes)
+        {
+            return;
+        }
+        // Zeroed outside the lock; the room is checked again below.
+        buf.fill(0);
+        buf.clear();
+        let mut free = self.free_list();
+        if free.has_room(capacity, self.max_buffers, self.max_idle_bytes) {
+            free.bytes += capacity;
+            free.bufs.push(buf);
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let free = self.free_list();
+        PoolStats {
+            hits:       self.hits.load(Ordering::Relaxed),
+            misses:     self.misses.load(Ordering::Relaxed),
+            idle:       free.bufs.len(),
+            idle_bytes: free.bytes,
+        }
+    }
+}
+
+/// The process-wide pool used by the block pipeline.
+pub fn shared() -> &'static BufferPool {
+    static POOL: OnceLock<BufferPool> = OnceLock::new();
+    POOL.get_or_init(BufferPool::default)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_capacity_within_bounds() {
+        let pool = BufferPool::new(2, 1024);
+        let mut a = pool.take(100);
+        a.extend_from_slice(b"scratch");
+        let cap = a.capacity();
+        pool.give(a);
+
+        let b = pool.take(10);
+        assert!(b.is_empty());
+        assert_eq!(b.capacity(), cap);
+        assert_eq!(pool.stats(), PoolStats { hits: 1, misses: 1, idle: 0, idle_bytes: 0 });
+
+        pool.give(Vec::with_capacity(4096)); // over max_capacity: dropped
+        for _ in 0..3 { pool.give(Vec::with_capacity(8)); }
+        assert_eq!(pool.stats().idle, 2);
+    }
+
+    #[test]
+    fn caps_idle_bytes() {
+        let pool = BufferPool::new(8, 1024).with_max_idle_bytes(1500);
+        for _ in 0..3 { pool.give(Vec::with_capacity(1000)); }
+        assert_eq!(pool.stats().idle, 1);
+        assert!(pool.stats().idle_bytes <= 1500);
+
+        drop(pool.take(0));
+        assert_eq!(pool.stats().idle_bytes, 0);
+    }
+
+    #[test]
+    fn zeroes_buffers_before_pooling() {
+        let pool = BufferPool::new(1, 1024);
+        let mut a = pool.take(64);
+        a.extend_from_slice(&[0xAA; 64]);
+        let len = a.len();
+        pool.give(a);
+
+        let mut b = pool.take(0);
+        // SAFETY: `give` zeroed the first `len` bytes; u8 has no invalid values.
+        unsafe { b.set_len(len) };
+        assert!(b.iter().all(|&x| x == 0));
+    }
+}
@@ -1,9 +1,13 @@
 pub mod scanner;
+pub mod sink;
 
 pub use scanner::{
-    scan, scan_file, extract_recoverable,
-    RecoveryReport, RecoveryQuality, BlockHealth, ScannedBlock,
+    scan, scan_deep, scan_file, scan_file_with_progress, extract_recoverable, extract_recoverable_deep,
+    extract_recoverable_into, extract_recoverable_into_with_progress, extract_recoverable_limited,
+    extract_recoverable_with, extract_recoverable_with_progress, run, ProgressFn, RecoveryOptions, RecoveryProgress,
+    RecoveryReport, RecoveryQuality, BlockHealth, ScannedBlock, FileRecovery, FileStatus,
 };
+pub use sink::{archive_sink, DirSink, RecoverySink};
 
 use serde::{Serialize, Deserialize};
 
@@ -7,6 +7,16 @@
 //! the RecoveryMap, or any prior state.  Every block is self-describing; the
 //! scanner only needs the `header_crc32` to hold for a block to be usable.
 //!
+//! After a header that does not parse, the scanner resyncs on the next
+//! occurrence of `BLOCK_MAGIC`, logging the skipped region as one
+//! `HeaderCorrupt` entry; after a payload that runs past EOF it resyncs
+//! the same way from the end of that header.  Each logged block carries its
+//! distance from the previous block's declared end ([`ScannedBlock::gap`]).
+//! If the superblock itself is unreadable — the first bytes destroyed, or cut
+//! off — the scan starts at offset 0 the same way, and
+//! [`RecoveryReport::superblock_valid`] is false; the archive written by
+//! [`extract_recoverable`] gets a fresh superblock either way.
+//!
 //! ## Recovery modes
 //!
 //! | Mode | Description |
@@ -23,20 +33,52 @@
 //! - `HeaderCorrupt` — CRC32 mismatch; block is skipped
 //! - `TruncatedPayload` — header valid but fewer bytes follow than `comp_size` declares
 //! - `UnknownCodec` — header valid but `codec_uuid` is not in registry
+//! - `PayloadCorrupt` — deep scan only: the payload does not decrypt,
+//!   decompress, or match the header's BLAKE3 `content_hash`
+//!
+//! ## Deep scan
+//!
+//! [`scan`] trusts a payload once its header checks out, so a block whose
+//! payload rotted still counts as `Healthy`.  [`scan_deep`] also decodes
+//! every block and verifies its content hash, at the cost of reading and
+//! decompressing the whole archive.  Encrypted blocks are verified only
+//! when a key is supplied; without one they keep their header verdict.
+//!
+//! ## Unknown codecs
+//!
+//! Blocks compressed with a codec this build lacks cannot be decoded, but
+//! with [`RecoveryOptions::include_unknown_codec_raw`] they are not lost:
+//! each one is written to the output archive as two entries,
+//! `unknown_codec/<offset>.bin` (the payload, decrypted if a key was given
+//! but still compressed) and `unknown_codec/<offset>.json` (its header), so
+//! it can be decoded once the right plugin is available.
 //!
 //! ## Progress
 //!
 //! `scan()` accepts an optional `ProgressFn` callback called after every block.
 //! The callback receives `(bytes_scanned, total_bytes_estimate)`.
-//! Pass `None` to disable progress reporting.
+//! Pass `None` to disable progress reporting.  [`scan_file_with_progress`]
+//! and the `_with_progress` extraction functions pass one through to the
+//! scan; the callback count is the number of blocks scanned so far.
+//!
+//! ## Running a recovery
+//!
+//! [`run`] is the one engine: it takes every setting — deep scan, resync,
+//! parallel decoding, key, raw unknown-codec blocks, limits, progress — in
+//! [`RecoveryOptions`] and writes to any [`RecoverySink`].  The
+//! `extract_recoverable*` functions are shorthands for it.
 
 use std::io::{self, Read, Seek, SeekFrom};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::block::{BlockHeader, BlockType, BLOCK_HEADER_SIZE};
-use crate::codec::CodecId;
+use crate::block::{decode_block_limited, BlockHeader, BlockType, BLOCK_HEADER_SIZE};
+use crate::codec::codec_source;
 use crate::index::{FileIndex, FileIndexRecord, BlockRef};
-use crate::superblock::SUPERBLOCK_SIZE;
+use crate::limits::Limits;
+use super::sink::{archive_sink, RecoverySink};
+use crate::block::BLOCK_MAGIC;
+use crate::io_stream::skip_recovery_map;
+use crate::superblock::{Superblock, SUPERBLOCK_SIZE};
 
 // ── Types ─────────────────────────────────────────────────────────────────────
 
@@ -51,6 +93,9 @@ pub enum BlockHealth {
     TruncatedPayload { declared: u32, available: u64 },
     /// Header valid, codec UUID not in this build's registry.
     UnknownCodec { uuid_hex: String },
+    /// Header valid but the payload fails decoding or its BLAKE3 check
+    /// ([`scan_deep`] only).
+    PayloadCorrupt { reason: String },
 }
 
 impl BlockHealth {
@@ -69,12 +114,54 @@ pub struct ScannedBlock {
     pub header:         Option<BlockHeader>,
     /// Health verdict.
     pub health:         BlockHealth,
+    /// Bytes between the previous parsed block's declared end (or the start
+    /// of the scan) and this header.  Positive is a gap — a lost region;
+    /// negative is an overlap — the previous block's size field is wrong.
+    pub gap:            i64,
 }
 
 impl ScannedBlock {
     pub fn is_usable(&self) -> bool {
         self.health.is_usable() && self.header.is_some()
     }
+
+    pub fn is_gap(&self) -> bool { self.gap > 0 }
+
+    pub fn is_overlap(&self) -> bool { self.gap < 0 }
+}
+
+/// How much of one file the scan found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileStatus {
+    /// Every chunk is present and the deep scan checked each one's hash.
+    HashVerified,
+    /// Every chunk is present; only block headers were checked.
+    Complete,
+    /// `missing` of `total` chunks are corrupt or absent.  Chunks lost in
+    /// a gap are counted from an estimated chunk size, so `total` is
+    /// approximate when chunks are missing.
+    Incomplete { missing: usize, total: usize },
+}
+
+/// Per-file entry of [`RecoveryReport::files`].
+///
+/// Coverage is judged from offset 0 to the end of the last chunk seen, so
+/// a file that lost only its trailing chunks still reads as complete.
+#[derive(Debug, Clone)]
+pub struct FileRecovery {
+    pub file_id:      u32,
+    /// Usable chunks found.
+    pub chunks_found: usize,
+    /// End of the furthest chunk seen, usable or not.
+    pub size:         u64,
+    pub status:       FileStatus,
+}
+
+impl FileRecovery {
+    /// True unless chunks are known to be missing.
+    pub fn is_complete(&self) -> bool {
+        !matches!(self.status, FileStatus::Incomplete { .. })
+    }
 }
 
 /// Overall quality of the recovery scan result.
@@ -103,12 +190,19 @@ pub struct RecoveryReport {
     pub truncated_blocks: usize,
     /// Blocks with an unrecognised codec UUID.
     pub unknown_codec_blocks: usize,
+    /// Blocks whose payload failed verification (deep scan only).
+    pub payload_corrupt_blocks: usize,
     /// Bytes of archive file scanned.
     pub bytes_scanned:   u64,
+    /// The superblock parsed; if not, the scan started at offset 0.
+    pub superblock_valid: bool,
     /// Per-block diagnostic records.
     pub block_log:       Vec<ScannedBlock>,
     /// Reconstructed file index (may be partial).
     pub index:           FileIndex,
+    /// Status of every file id seen in a parsed DATA header, by id.  Files
+    /// with no usable chunk appear here but not in `index`.
+    pub files:           Vec<FileRecovery>,
     /// Estimated total bytes recoverable (sum of `orig_size` for healthy blocks).
     pub recoverable_bytes: u64,
     /// Overall quality rating.
@@ -158,17 +252,91 @@ pub type ProgressFn<'a> = dyn FnMut(u64 /*scanned*/, u64 /*total_estimate*/) + '
 pub fn scan<R, F>(
     reader:         &mut R,
     file_size_hint: u64,
+    progress:       Option<&mut F>,
+) -> io::Result<RecoveryReport>
+where
+    R: Read + Seek,
+    F: FnMut(u64, u64),
+{
+    scan_impl(reader, file_size_hint, None, true, progress)
+}
+
+/// [`scan`] that also decodes every block and checks its BLAKE3 content
+/// hash, reporting failures as [`BlockHealth::PayloadCorrupt`].
+/// `decryption_key` lets encrypted blocks be verified too.
+pub fn scan_deep<R, F>(
+    reader:         &mut R,
+    file_size_hint: u64,
+    decryption_key: Option<&[u8; 32]>,
+    progress:       Option<&mut F>,
+) -> io::Result<RecoveryReport>
+where
+    R: Read + Seek,
+    F: FnMut(u64, u64),
+{
+    scan_impl(reader, file_size_hint, Some(DeepScan { key: decryption_key }), true, progress)
+}
+
+/// Payload verification settings for [`scan_deep`].
+#[derive(Clone, Copy)]
+struct DeepScan<'a> {
+    key: Option<&'a [u8; 32]>,
+}
+
+impl DeepScan<'_> {
+    /// Whether [`Self::payload_failure`] actually checks `header`'s payload.
+    fn checks(&self, header: &BlockHeader) -> bool {
+        !(header.is_encrypted() && self.key.is_none()) && Limits::default().check_block(header).is_ok()
+    }
+
+    /// Why the payload after `header` (the reader sits at its first byte)
+    /// is corrupt, or `None` if it verifies or cannot be checked.  Leaves
+    /// the reader where it found it.
+    fn payload_failure<R: Read + Seek>(&self, reader: &mut R, header: &BlockHeader) -> io::Result<Option<String>> {
+        let limits = Limits::default();
+        if !self.checks(header) {
+            return Ok(None);
+        }
+        let start = reader.stream_position()?;
+        let mut payload = vec![0u8; header.comp_size as usize];
+        reader.read_exact(&mut payload)?;
+        reader.seek(SeekFrom::Start(start))?;
+        Ok(match decode_block_limited(header, &payload, self.key, &limits) {
+            Ok(plain) => { crate::pool::shared().give(plain); None }
+            Err(e)    => Some(e.to_string()),
+        })
+    }
+}
+
+/// `resync`: look for the next block magic after a damaged header or a
+/// truncated payload, rather than end the scan there.
+fn scan_impl<R, F>(
+    reader:         &mut R,
+    file_size_hint: u64,
+    deep:           Option<DeepScan>,
+    resync:         bool,
     mut progress:   Option<&mut F>,
 ) -> io::Result<RecoveryReport>
 where
     R: Read + Seek,
     F: FnMut(u64, u64),
 {
-    reader.seek(SeekFrom::Start(SUPERBLOCK_SIZE as u64))?;
+    reader.seek(SeekFrom::Start(0))?;
+    let superblock_valid = Superblock::read(&mut *reader).is_ok();
+    if !superblock_valid {
+        match find_magic(reader, 0)? {
+            Some(first) => reader.seek(SeekFrom::Start(first))?,
+            None        => reader.seek(SeekFrom::End(0))?,
+        };
+    }
 
     // Per-file chunk accumulation: file_id → Vec<(file_offset, ScannedBlock)>
     let mut chunks: HashMap<u32, Vec<(u64, ScannedBlock)>> = HashMap::new();
     let mut orig_sizes: HashMap<u32, u64> = HashMap::new();
+    // Parsed DATA blocks that are unusable: file_id → (file_offset, orig_size).
+    let mut lost: HashMap<u32, Vec<(u64, u64)>> = HashMap::new();
+    // Files with a usable block whose payload the scan did not verify.
+    let mut unverified: HashSet<u32> = HashSet::new();
     let mut block_log: Vec<ScannedBlock> = Vec::new();
 
     let mut total_scanned        = 0usize;
@@ -176,11 +344,15 @@ where
     let mut corrupt_blocks       = 0usize;
     let mut truncated_blocks     = 0usize;
     let mut unknown_codec_blocks = 0usize;
+    let mut payload_corrupt      = 0usize;
     let mut recoverable_bytes    = 0u64;
-    let mut bytes_scanned        = SUPERBLOCK_SIZE as u64;
+    let mut bytes_scanned        = reader.stream_position()?;
+    // Declared end of the last block whose header parsed.
+    let mut prev_end             = if superblock_valid { SUPERBLOCK_SIZE as u64 } else { 0 };
 
     loop {
         let pos = reader.stream_position()?;
+        let gap = pos as i64 - prev_end as i64;
 
         // Try to read a full 84-byte header.
         let mut hdr_buf = [0u8; BLOCK_HEADER_SIZE];
@@ -191,6 +363,8 @@ where
         }
         bytes_scanned += BLOCK_HEADER_SIZE as u64;
         total_scanned += 1;
+        // Set when this block ends the scan.
+        let mut last = false;
 
         // Attempt to parse.  BlockHeader::read re-reads from a cursor; we
         // already have the bytes, so parse from the buffer directly.
@@ -204,13 +378,16 @@ where
                     archive_offset: pos,
                     header: None,
                     health: BlockHealth::HeaderCorrupt,
+                    gap,
                 };
                 block_log.push(sb);
 
-                // Skip one byte and retry — allows finding the next valid
-                // header after a run of corruption.
-                reader.seek(SeekFrom::Start(pos + 1))?;
-                bytes_scanned = pos + 1 + BLOCK_HEADER_SIZE as u64; // approx
+                // Resync on the next block magic after this position.
+                let next = if resync { find_magic(reader, pos + 1)? } else { None };
+                match next {
+                    Some(next) => { reader.seek(SeekFrom::Start(next))?; bytes_scanned = next; }
+                    None       => last = true,
+                }
             }
             Ok(header) => {
                 // Header parsed.  Now assess codec and payload availability.
@@ -218,12 +395,12 @@ where
                 let block_type = header.block_type;
 
                 // Check codec availability.
-                let health = if CodecId::from_uuid(&header.codec_uuid).is_none()
+                let health = if codec_source(&header.codec_uuid).is_none()
                     && header.codec_uuid != crate::codec::UUID_NONE
                 {
                     unknown_codec_blocks += 1;
                     BlockHealth::UnknownCodec {
-                        uuid_hex: hex::encode(&header.codec_uuid),
+                        uuid_hex: hex::encode(header.codec_uuid),
                     }
                 } else {
                     // Check payload truncation.
@@ -234,15 +411,22 @@ where
                         // Seek to end to get remaining bytes.
                         let end = reader.seek(SeekFrom::End(0))?;
                         let _ = reader.seek(SeekFrom::Start(stream_pos))?;
-                        if end > stream_pos { end - stream_pos } else { 0 }
+                        end.saturating_sub(stream_pos)
                     };
 
+                    let failure = match deep {
+                        Some(deep) if remaining >= comp_size => deep.payload_failure(reader, &header)?,
+                        _                                    => None,
+                    };
                     if remaining < comp_size {
                         truncated_blocks += 1;
                         BlockHealth::TruncatedPayload {
                             declared:  header.comp_size,
                             available: remaining,
                         }
+                    } else if let Some(reason) = failure {
+                        payload_corrupt += 1;
+                        BlockHealth::PayloadCorrupt { reason }
                     } else {
                         healthy_blocks   += 1;
                         recoverable_bytes += header.orig_size as u64;
@@ -263,37 +447,75 @@ where
                         archive_offset: pos,
                         header: Some(header.clone()),
                         health: health.clone(),
+                        gap,
                     };
                     chunks.entry(fid).or_default().push((header.file_offset, sb));
+                    if !deep.as_ref().is_some_and(|d| d.checks(&header)) {
+                        unverified.insert(fid);
+                    }
+                } else if block_type == BlockType::Data {
+                    lost.entry(header.file_id).or_default()
+                        .push((header.file_offset, header.orig_size as u64));
                 }
 
+                let truncated = matches!(health, BlockHealth::TruncatedPayload { .. });
                 let sb = ScannedBlock {
                     archive_offset: pos,
                     header: Some(header.clone()),
                     health,
+                    gap,
                 };
                 block_log.push(sb);
-
-                // Seek past payload.
-                if reader.seek(SeekFrom::Current(comp_size as i64)).is_err() {
-                    break;
-                }
-                bytes_scanned += comp_size;
-
-                // Stop at INDEX block — it marks the end of data blocks.
-                if block_type == BlockType::Index {
-                    break;
+                prev_end = pos + BLOCK_HEADER_SIZE as u64 + comp_size;
+
+                if truncated {
+                    // The payload runs past EOF: either the file was cut
+                    // here or the size field is wrong.  Look for a later
+                    // block; it will report the overlap.
+                    let next = if resync { find_magic(reader, pos + BLOCK_HEADER_SIZE as u64)? } else { None };
+                    match next {
+                        Some(next) => { reader.seek(SeekFrom::Start(next))?; bytes_scanned = next; }
+                        None       => last = true,
+                    }
+                } else if reader.seek(SeekFrom::Current(comp_size as i64)).is_ok() {
+                    bytes_scanned += comp_size;
+                    // An INDEX block ends the data, unless an append wrote
+                    // more blocks after it and its recovery map.
+                    if block_type == BlockType::Index {
+                        prev_end = skip_recovery_map(reader, prev_end)?;
+                        reader.seek(SeekFrom::Start(prev_end))?;
+                        bytes_scanned = prev_end;
+                    }
+                } else {
+                    last = true;
                 }
             }
         }
 
-        // Progress callback.
+        // Progress callback, for the last block too.
         if let Some(ref mut cb) = progress {
             let estimate = if file_size_hint > 0 { file_size_hint } else { bytes_scanned * 2 };
             cb(bytes_scanned, estimate);
         }
+        if last {
+            break;
+        }
     }
 
+    let mut file_ids: Vec<u32> = chunks.keys().chain(lost.keys()).copied().collect();
+    file_ids.sort_unstable();
+    file_ids.dedup();
+    let files = file_ids
+        .into_iter()
+        .map(|fid| {
+            let found: Vec<(u64, u64)> = chunks.get(&fid).into_iter().flatten()
+                .map(|(off, sb)| (*off, sb.header.as_ref().map_or(0, |h| h.orig_size as u64)))
+                .collect();
+            let lost = lost.get(&fid).map_or(&[][..], Vec::as_slice);
+            file_recovery(fid, &found, lost, !unverified.contains(&fid))
+        })
+        .collect();
+
     // Build FileIndexRecords from accumulated chunks.
     let mut records: Vec<FileIndexRecord> = chunks
         .into_iter()
@@ -306,6 +528,7 @@ where
                     archive_offset: sb.archive_offset,
                     intra_offset:   0,
                     intra_length:   0,
+                    archive_id:     None,
                 })
                 .collect();
             let size = *orig_sizes.get(&fid).unwrap_or(&0);
@@ -314,7 +537,7 @@ where
         .collect();
     records.sort_by_key(|r| r.id);
 
-    let mut index = FileIndex { records, root_hash: [0u8; 32] };
+    let mut index = FileIndex { records, ..FileIndex::default() };
     index.compute_root_hash();
 
     // Determine quality.
@@ -336,14 +559,79 @@ where
         corrupt_blocks,
         truncated_blocks,
         unknown_codec_blocks,
+        payload_corrupt_blocks: payload_corrupt,
         bytes_scanned,
+        superblock_valid,
         block_log,
         index,
+        files,
         recoverable_bytes,
         quality,
     })
 }
 
+/// Status of one file from its usable (`found`) and unusable (`lost`)
+/// chunk ranges, each `(file_offset, orig_size)`.
+fn file_recovery(file_id: u32, found: &[(u64, u64)], lost: &[(u64, u64)], verified: bool) -> FileRecovery {
+    let mut ranges: Vec<(u64, u64)> = found.iter().chain(lost).copied().collect();
+    ranges.sort_unstable();
+    // Offsets are multiples of the chunk size, which still gives a sensible
+    // estimate when the only surviving chunk is a short tail.
+    let longest   = ranges.iter().map(|&(_, len)| len).max().unwrap_or(0);
+    let stride    = ranges.iter().map(|&(off, _)| off).fold(0, gcd);
+    let chunk_len = longest.max(stride).max(1);
+
+    // Chunks absent from the scan altogether, estimated per hole.
+    let mut end   = 0u64;
+    let mut holes = 0usize;
+    for (off, len) in ranges {
+        if off > end {
+            holes += (off - end).div_ceil(chunk_len) as usize;
+        }
+        end = end.max(off + len);
+    }
+
+    let missing = lost.len() + holes;
+    let status = if missing > 0 {
+        FileStatus::Incomplete { missing, total: found.len() + missing }
+    } else if verified {
+        FileStatus::HashVerified
+    } else {
+        FileStatus::Complete
+    };
+    FileRecovery { file_id, chunks_found: found.len(), size: end, status }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Offset of the first `BLOCK_MAGIC` at or after `from`, or `None` at EOF.
+fn find_magic<R: Read + Seek>(reader: &mut R, from: u64) -> io::Result<Option<u64>> {
+    let magic = BLOCK_MAGIC.to_le_bytes();
+    let mut buf  = vec![0u8; 64 * 1024];
+    let mut base = from;
+    reader.seek(SeekFrom::Start(from))?;
+    loop {
+        let mut n = 0;
+        while n < buf.len() {
+            match reader.read(&mut buf[n..])? {
+                0 => break,
+                k => n += k,
+            }
+        }
+        if let Some(i) = buf[..n].windows(magic.len()).position(|w| w == magic) {
+            return Ok(Some(base + i as u64));
+        }
+        if n < buf.len() {
+            return Ok(None);
+        }
+        // Re-read the last bytes so a magic split across buffers is found.
+        base += (n - magic.len() + 1) as u64;
+        reader.seek(SeekFrom::Start(base))?;
+    }
+}
+
 /// Convenience: scan a file at `path` and return the report.
 pub fn scan_file(path: &std::path::Path) -> io::Result<RecoveryReport> {
     let mut f    = std::fs::File::open(path)?;
@@ -351,6 +639,16 @@ pub fn scan_file(path: &std::path::Path) -> io::Result<RecoveryReport> {
     scan::<_, fn(u64, u64)>(&mut f, size, None)
 }
 
+/// [`scan_file`] reporting to `progress` after every block.
+pub fn scan_file_with_progress(
+    path:         &std::path::Path,
+    mut progress: &mut ProgressFn,
+) -> io::Result<RecoveryReport> {
+    let mut f    = std::fs::File::open(path)?;
+    let size     = f.metadata()?.len();
+    scan(&mut f, size, Some(&mut progress))
+}
+
 /// Extract all recoverable DATA blocks from `src` into new archive `dst`.
 ///
 /// Only `Healthy` DATA blocks are copied.  The resulting archive will have a
@@ -366,19 +664,188 @@ where
     R: Read + Seek,
     W: std::io::Write + Seek,
 {
-    use crate::io_stream::{SixCyWriter, DEFAULT_COMPRESSION_LEVEL};
-    use crate::codec::CodecId;
+    extract_recoverable_with(src, dst, decryption_key, &RecoveryOptions::default())
+}
+
+/// Settings for a recovery run ([`run`], and the `extract_recoverable_*`
+/// functions, which are shorthands for it).
+#[derive(Debug, Clone)]
+pub struct RecoveryOptions {
+    /// Report from a [`scan_deep`] rather than a header-only [`scan`].
+    pub deep_verify:               bool,
+    /// After a damaged header or a payload running past EOF, look for the
+    /// next block magic (module docs) rather than end the scan there.  On
+    /// by default.
+    pub resync:                    bool,
+    /// Decode each recovered file's blocks on the Rayon pool.  Needs the
+    /// `parallel` feature; ignored without it.
+    pub parallel:                  bool,
+    /// Key for encrypted blocks, from
+    /// [`derive_key`](crate::crypto::derive_key) with the archive's UUID.
+    pub decryption_key:            Option<[u8; 32]>,
+    /// Save unknown-codec blocks as raw payload + header sidecar entries
+    /// (module docs).
+    pub include_unknown_codec_raw: bool,
+    pub limits:                    Limits,
+    /// Called after every block scanned.
+    pub progress:                  Option<RecoveryProgress>,
+}
+
+impl Default for RecoveryOptions {
+    fn default() -> Self {
+        Self {
+            deep_verify:               false,
+            resync:                    true,
+            parallel:                  false,
+            decryption_key:            None,
+            include_unknown_codec_raw: false,
+            limits:                    Limits::default(),
+            progress:                  None,
+        }
+    }
+}
+
+impl RecoveryOptions {
+    /// These options with `key`, if given, in place of `decryption_key`.
+    fn keyed(&self, key: Option<&[u8; 32]>) -> Self {
+        Self { decryption_key: key.copied().or(self.decryption_key), ..self.clone() }
+    }
+}
+
+/// Progress callback for [`RecoveryOptions::progress`], called with
+/// `(bytes_scanned, total_estimate)` as [`ProgressFn`] is.
+#[derive(Clone)]
+pub struct RecoveryProgress(pub std::sync::Arc<dyn Fn(u64, u64) + Send + Sync>);
+
+impl RecoveryProgress {
+    pub fn new(progress: impl Fn(u64, u64) + Send + Sync + 'static) -> Self {
+        Self(std::sync::Arc::new(progress))
+    }
+}
+
+impl std::fmt::Debug for RecoveryProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("RecoveryProgress(..)")
+    }
+}
+
+/// [`extract_recoverable`] under resource `limits`: blocks whose declared
+/// sizes exceed `max_block_size` are skipped like corrupt ones, and the run
+/// aborts once `max_total_decompressed` bytes have been recovered.
+pub fn extract_recoverable_limited<R, W>(
+    src:            &mut R,
+    dst:            &mut W,
+    decryption_key: Option<&[u8; 32]>,
+    limits:         &Limits,
+) -> io::Result<RecoveryReport>
+where
+    R: Read + Seek,
+    W: std::io::Write + Seek,
+{
+    let opts = RecoveryOptions { limits: *limits, ..RecoveryOptions::default() };
+    extract_recoverable_with(src, dst, decryption_key, &opts)
+}
+
+/// [`extract_recoverable_limited`] reporting from a [`scan_deep`], so the
+/// report's block log and quality rating reflect payload verification.
+pub fn extract_recoverable_deep<R, W>(
+    src:            &mut R,
+    dst:            &mut W,
+    decryption_key: Option<&[u8; 32]>,
+    limits:         &Limits,
+) -> io::Result<RecoveryReport>
+where
+    R: Read + Seek,
+    W: std::io::Write + Seek,
+{
+    let opts = RecoveryOptions { deep_verify: true, limits: *limits, ..RecoveryOptions::default() };
+    extract_recoverable_with(src, dst, decryption_key, &opts)
+}
+
+/// [`extract_recoverable`] configured by `opts`; `decryption_key`, if
+/// given, replaces `opts.decryption_key`.
+pub fn extract_recoverable_with<R, W>(
+    src:            &mut R,
+    dst:            &mut W,
+    decryption_key: Option<&[u8; 32]>,
+    opts:           &RecoveryOptions,
+) -> io::Result<RecoveryReport>
+where
+    R: Read + Seek,
+    W: std::io::Write + Seek,
+{
+    run(src, &mut archive_sink(dst)?, &opts.keyed(decryption_key))
+}
+
+/// [`extract_recoverable_with`] reporting scan progress to `progress`
+/// after every block, in place of `opts.progress`.
+pub fn extract_recoverable_with_progress<R, W>(
+    src:            &mut R,
+    dst:            &mut W,
+    decryption_key: Option<&[u8; 32]>,
+    opts:           &RecoveryOptions,
+    progress:       &mut ProgressFn,
+) -> io::Result<RecoveryReport>
+where
+    R: Read + Seek,
+    W: std::io::Write + Seek,
+{
+    run_reporting(src, &mut archive_sink(dst)?, &opts.keyed(decryption_key), progress)
+}
+
+/// [`extract_recoverable_with`] handing each recovered file to `sink`
+/// instead of writing an archive.  `sink.finish()` runs after the last file.
+pub fn extract_recoverable_into<R: Read + Seek>(
+    src:            &mut R,
+    sink:           &mut dyn RecoverySink,
+    decryption_key: Option<&[u8; 32]>,
+    opts:           &RecoveryOptions,
+) -> io::Result<RecoveryReport> {
+    run(src, sink, &opts.keyed(decryption_key))
+}
+
+/// [`extract_recoverable_into`] reporting scan progress to `progress`
+/// after every block, in place of `opts.progress`.
+pub fn extract_recoverable_into_with_progress<R: Read + Seek>(
+    src:            &mut R,
+    sink:           &mut dyn RecoverySink,
+    decryption_key: Option<&[u8; 32]>,
+    opts:           &RecoveryOptions,
+    progress:       &mut ProgressFn,
+) -> io::Result<RecoveryReport> {
+    run_reporting(src, sink, &opts.keyed(decryption_key), progress)
+}
+
+/// Scan `src` as `options` direct and hand every recovered file to `dst`
+/// — a [`SixCyWriter`](crate::io_stream::SixCyWriter) (see
+/// [`archive_sink`]), a [`DirSink`](super::DirSink), or any other
+/// [`RecoverySink`].  `dst.finish()` runs after the last file.  This is
+/// the engine behind every `extract_recoverable_*` function and
+/// `6cy recover`.
+pub fn run<R: Read + Seek>(
+    src:     &mut R,
+    dst:     &mut dyn RecoverySink,
+    options: &RecoveryOptions,
+) -> io::Result<RecoveryReport> {
+    let callback     = options.progress.clone();
+    let mut progress = |scanned, total| if let Some(p) = &callback { (p.0)(scanned, total) };
+    run_reporting(src, dst, options, &mut progress)
+}
+
+/// [`run`] reporting to `progress` rather than `opts.progress`.
+fn run_reporting<R: Read + Seek>(
+    src:          &mut R,
+    sink:         &mut dyn RecoverySink,
+    opts:         &RecoveryOptions,
+    mut progress: &mut ProgressFn,
+) -> io::Result<RecoveryReport> {
     use crate::block::decode_block;
 
+    let decryption_key = opts.decryption_key.as_ref();
     let size   = src.seek(SeekFrom::End(0))?;
-    let report = scan::<_, fn(u64, u64)>(src, size, None)?;
-
-    let mut writer = SixCyWriter::with_options(
-        dst,
-        4 * 1024 * 1024,
-        DEFAULT_COMPRESSION_LEVEL,
-        None,
-    )?;
+    let limits = &opts.limits;
+    let deep   = opts.deep_verify.then_some(DeepScan { key: decryption_key });
+    let report = scan_impl(src, size, deep, opts.resync, Some(&mut progress))?;
 
     // Group healthy blocks by file_id and sort by file_offset.
     let mut by_file: HashMap<u32, Vec<&ScannedBlock>> = HashMap::new();
@@ -392,6 +859,8 @@ where
 
     let mut file_ids: Vec<u32> = by_file.keys().copied().collect();
     file_ids.sort_unstable();
+    let mut total = 0u64;
+    let parallel  = opts.parallel && cfg!(feature = "parallel");
 
     for fid in file_ids {
         let mut blocks = by_file.remove(&fid).unwrap();
@@ -399,13 +868,23 @@ where
 
         let name = format!("recovered_file_{fid:08x}");
         let mut data: Vec<u8> = Vec::new();
+        let mut pending: Vec<(&BlockHeader, Vec<u8>)> = Vec::new();
 
         for sb in blocks {
             let h = sb.header.as_ref().unwrap();
+            if limits.check_block(h).is_err() {
+                continue;
+            }
+            total += h.orig_size as u64;
+            limits.check_total(total)?;
             src.seek(SeekFrom::Start(sb.archive_offset + crate::block::BLOCK_HEADER_SIZE as u64))?;
             let mut payload = vec![0u8; h.comp_size as usize];
             src.read_exact(&mut payload)?;
 
+            if parallel {
+                pending.push((h, payload));
+                continue;
+            }
             match decode_block(h, &payload, decryption_key) {
                 Ok(chunk) => data.extend(chunk),
                 Err(_)    => {
@@ -414,12 +893,74 @@ where
                 }
             }
         }
+        for chunk in decode_parallel(&pending, decryption_key).into_iter().flatten() {
+            data.extend(chunk);
+        }
 
         if !data.is_empty() {
-            writer.add_file(name, &data, CodecId::Zstd)?;
+            sink.put_file(&name, &data, false)?;
         }
     }
 
-    writer.finalize()?;
+    if opts.include_unknown_codec_raw {
+        let unknown = report.block_log.iter()
+            .filter(|sb| matches!(sb.health, BlockHealth::UnknownCodec { .. }));
+        for sb in unknown {
+            let h = sb.header.as_ref().unwrap();
+            if limits.check_block(h).is_err() {
+                continue;
+            }
+            total += h.comp_size as u64;
+            limits.check_total(total)?;
+            src.seek(SeekFrom::Start(sb.archive_offset + BLOCK_HEADER_SIZE as u64))?;
+            let mut payload = vec![0u8; h.comp_size as usize];
+            if src.read_exact(&mut payload).is_err() {
+                continue; // truncated
+            }
+            let decrypted = match decryption_key {
+                Some(key) if h.is_encrypted() => crate::crypto::decrypt(key, &payload).ok(),
+                _                             => None,
+            };
+            let sidecar = raw_block_sidecar(sb.archive_offset, h, decrypted.is_some());
+            let stem    = format!("unknown_codec/{:016x}", sb.archive_offset);
+            sink.put_file(&format!("{stem}.bin"), decrypted.as_ref().unwrap_or(&payload), true)?;
+            sink.put_file(&format!("{stem}.json"), sidecar.as_bytes(), false)?;
+        }
+    }
+
+    sink.finish()?;
     Ok(report)
 }
+
+/// Decode `blocks` on the Rayon pool, in order; `None` for a block that
+/// fails, which recovery skips.
+fn decode_parallel(blocks: &[(&BlockHeader, Vec<u8>)], key: Option<&[u8; 32]>) -> Vec<Option<Vec<u8>>> {
+    let decode = |(h, payload): &(&BlockHeader, Vec<u8>)| crate::block::decode_block(h, payload, key).ok();
+    #[cfg(feature = "parallel")]
+    {
+        use rayon::prelude::*;
+        blocks.par_iter().map(decode).collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    {
+        blocks.iter().map(decode).collect()
+    }
+}
+
+/// JSON description of a block saved raw, enough to decode its `.bin`
+/// later: the codec UUID, the sizes, and the hash the output must match.
+fn raw_block_sidecar(offset: u64, h: &BlockHeader, decrypted: bool) -> String {
+    let json = serde_json::json!({
+        "archive_offset": offset,
+        "block_type":     h.block_type as u16,
+        "codec_uuid":     crate::codec::uuid_to_string(&h.codec_uuid),
+        "file_id":        h.file_id,
+        "file_offset":    h.file_offset,
+        "orig_size":      h.orig_size,
+        "comp_size":      h.comp_size,
+        "content_hash":   hex::encode(h.content_hash),
+        "encrypted":      h.is_encrypted() && !decrypted,
+    });
+    serde_json::to_string_pretty(&json).expect("JSON values serialize")
+}
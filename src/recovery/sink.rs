@@ -0,0 +1,72 @@
+//! Destinations for files salvaged by [`super::extract_recoverable_into`].
+//!
+//! [`RecoverySink`] is the pluggable seam.  A [`SixCyWriter`] implements it
+//! directly (a fresh archive, as [`super::extract_recoverable`] writes);
+//! [`DirSink`] writes a directory tree; any
+//! `FnMut(&str, &[u8], bool) -> io::Result<()>` closure is a sink too, which
+//! covers object stores and pipes to another process without a second
+//! extraction pass.
+//!
+//! Names are the ones recovery generates — `recovered_file_<id>` and, with
+//! `include_unknown_codec_raw`, `unknown_codec/<offset>.bin` / `.json`.
+
+use std::io::{self, Seek, Write};
+use std::path::PathBuf;
+
+use crate::codec::CodecId;
+use crate::io_stream::{SixCyWriter, DEFAULT_COMPRESSION_LEVEL};
+
+/// Receiver for recovered files.
+pub trait RecoverySink {
+    /// Store one recovered file.  `raw` marks an undecoded block payload,
+    /// which is not worth compressing again.
+    fn put_file(&mut self, name: &str, data: &[u8], raw: bool) -> io::Result<()>;
+    /// Called once, after the last file.
+    fn finish(&mut self) -> io::Result<()> { Ok(()) }
+}
+
+/// The archive writer [`super::extract_recoverable`] recovers into:
+/// unencrypted, 4 MiB chunks at the default level.
+pub fn archive_sink<W: Write + Seek>(dst: W) -> io::Result<SixCyWriter<W>> {
+    Ok(SixCyWriter::with_options(dst, 4 * 1024 * 1024, DEFAULT_COMPRESSION_LEVEL, None)?)
+}
+
+impl<W: Write + Seek> RecoverySink for SixCyWriter<W> {
+    fn put_file(&mut self, name: &str, data: &[u8], raw: bool) -> io::Result<()> {
+        let codec = if raw { CodecId::None } else { CodecId::Zstd };
+        Ok(self.add_file(name.to_owned(), data, codec)?)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(self.finalize()?)
+    }
+}
+
+impl<F: FnMut(&str, &[u8], bool) -> io::Result<()>> RecoverySink for F {
+    fn put_file(&mut self, name: &str, data: &[u8], raw: bool) -> io::Result<()> {
+        self(name, data, raw)
+    }
+}
+
+/// Writes each recovered file under a root directory, creating it and any
+/// subdirectories as needed.
+#[derive(Debug, Clone)]
+pub struct DirSink {
+    pub root: PathBuf,
+}
+
+impl DirSink {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl RecoverySink for DirSink {
+    fn put_file(&mut self, name: &str, data: &[u8], _raw: bool) -> io::Result<()> {
+        let path = self.root.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, data)
+    }
+}
@@ -0,0 +1,235 @@
+//! Metadata-only access to archives served over HTTP(S) or S3.
+//!
+//! [`ArchiveMetadata::fetch`] reads two byte ranges of a remote archive —
+//! the 256-byte superblock, then the INDEX block (starting at the CODECS
+//! block just before it when the codec list overflows) — and never
+//! requests a DATA or SOLID block.  The result holds no connection:
+//! `list`, `stat`, and `find_by_hash` answer from the INDEX alone, which
+//! is what a remote browsing UI needs before the user picks a file.
+//!
+//! Unlike [`SixCyReader`](crate::io_stream::SixCyReader), a stale
+//! `index_offset` is an error here rather than a reason to scan the tail
+//! of the archive, which would cost further ranges of unknown size.
+//!
+//! # Sources
+//! [`RangeSource`] is the pluggable seam, in the manner of
+//! [`sync::Transport`](crate::sync::Transport).  [`HttpSource`] runs
+//! `curl` (which must be on `PATH`) per range rather than linking an HTTP
+//! stack into the library.  `s3://bucket/key` is fetched from the bucket's
+//! virtual-hosted HTTPS endpoint, which serves public objects; private
+//! ones need a presigned `https://` URL.  Each request is bounded by a
+//! connect timeout and a total timeout (see [`HttpSource::with_timeouts`]);
+//! running out of either fails with `ErrorKind::TimedOut`.
+
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::process::Command;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::archive::FileInfo;
+use crate::block::BLOCK_HEADER_SIZE;
+use crate::error::Result;
+use crate::index::{EntryOrder, FileIndex};
+use crate::io_stream::read_index_at;
+use crate::limits::{LimitExceeded, Limits};
+use crate::superblock::{Superblock, SUPERBLOCK_SIZE};
+
+// ── RangeSource ───────────────────────────────────────────────────────────────
+
+/// Random access to a remote object, one byte range per call.
+pub trait RangeSource {
+    /// The `len` bytes starting at `offset`; fewer only where the object
+    /// ends first.
+    fn read_range(&mut self, offset: u64, len: u64) -> io::Result<Vec<u8>>;
+}
+
+/// True if `location` names an object [`HttpSource`] can fetch rather
+/// than a local path.
+pub fn is_remote_url(location: &str) -> bool {
+    ["http://", "https://", "s3://"].iter().any(|scheme| location.starts_with(scheme))
+}
+
+/// Ranged `GET`s through `curl`; see the module docs.
+#[derive(Debug, Clone)]
+pub struct HttpSource {
+    url:             String,
+    connect_timeout: Duration,
+    timeout:         Duration,
+}
+
+/// curl's exit status when `--connect-timeout` or `--max-time` expires.
+const CURL_TIMED_OUT: i32 = 28;
+
+impl HttpSource {
+    /// Fails with `InvalidInput` unless `url` is `http://`, `https://`, or
+    /// `s3://bucket/key`.
+    pub fn new(url: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+        let url = match url.strip_prefix("s3://") {
+            Some(path) => match path.split_once('/') {
+                Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() =>
+                    format!("https://{bucket}.s3.amazonaws.com/{key}"),
+                _ => return Err(invalid(format!("expected s3://BUCKET/KEY, got {url}"))),
+            },
+            None if is_remote_url(url) => url.to_owned(),
+            None => return Err(invalid(format!("not an http(s) or s3 URL: {url}"))),
+        };
+        Ok(Self { url, connect_timeout: Self::DEFAULT_CONNECT_TIMEOUT, timeout: Self::DEFAULT_TIMEOUT })
+    }
+
+    pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+    pub const DEFAULT_TIMEOUT:         Duration = Duration::from_secs(60);
+
+    /// Give up on a request that has not connected within `connect`, or
+    /// not finished within `total`.
+    pub fn with_timeouts(mut self, connect: Duration, total: Duration) -> Self {
+        self.connect_timeout = connect;
+        self.timeout         = total;
+        self
+    }
+
+    pub fn url(&self) -> &str { &self.url }
+}
+
+impl RangeSource for HttpSource {
+    fn read_range(&mut self, offset: u64, len: u64) -> io::Result<Vec<u8>> {
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+        let last = offset.checked_add(len - 1).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData, format!("range of {len} B at offset {offset} overflows")))?;
+        // `--max-filesize` stops a server that ignores `Range` from sending
+        // the whole archive.
+        let out = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--location"])
+            .arg("--range").arg(format!("{offset}-{last}"))
+            .arg("--max-filesize").arg(len.to_string())
+            .arg("--connect-timeout").arg(format!("{:.3}", self.connect_timeout.as_secs_f64()))
+            .arg("--max-time").arg(format!("{:.3}", self.timeout.as_secs_f64()))
+            .arg(&self.url)
+            .output()?;
+        if !out.status.success() {
+            let kind = match out.status.code() {
+                Some(CURL_TIMED_OUT) => io::ErrorKind::TimedOut,
+                _                    => io::ErrorKind::Other,
+            };
+            return Err(io::Error::new(kind, format!("range {offset}-{last} of {}: {}",
+                self.url, String::from_utf8_lossy(&out.stderr).trim())));
+        }
+        if out.stdout.len() as u64 > len {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("{} ignored the byte range request", self.url)));
+        }
+        Ok(out.stdout)
+    }
+}
+
+// ── ArchiveMetadata ───────────────────────────────────────────────────────────
+
+/// Superblock and INDEX of an archive whose blocks were not fetched.
+#[derive(Debug, Clone)]
+pub struct ArchiveMetadata {
+    pub superblock: Superblock,
+    pub index:      FileIndex,
+}
+
+impl ArchiveMetadata {
+    /// Read the superblock and INDEX from `source` in two ranges, the
+    /// second bounded by `limits.max_index_size` before it is requested.
+    pub fn fetch<S: RangeSource + ?Sized>(source: &mut S, limits: Limits) -> Result<Self> {
+        let head = source.read_range(0, SUPERBLOCK_SIZE as u64)?;
+        let mut sb = Superblock::read(head.as_slice())?;
+        if sb.index_size > limits.max_index_size {
+            return Err(LimitExceeded {
+                what:  "INDEX comp_size",
+                value: sb.index_size,
+                limit: limits.max_index_size,
+            }.into());
+        }
+
+        // Writers place the CODECS block directly before the INDEX, so one
+        // range covers both.
+        let overflow = || io::Error::new(io::ErrorKind::InvalidData,
+            "superblock INDEX or CODECS offsets overflow");
+        let header = BLOCK_HEADER_SIZE as u64;
+        let start  = match sb.codec_continuation {
+            Some(c) => {
+                let codecs_end = (c.count as u64).checked_mul(16)
+                    .and_then(|n| n.checked_add(header))
+                    .and_then(|n| n.checked_add(c.offset))
+                    .ok_or_else(overflow)?;
+                if codecs_end != sb.index_offset {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                        "CODECS block at offset {} does not precede the INDEX at {}",
+                        c.offset, sb.index_offset)).into());
+                }
+                c.offset
+            }
+            None => sb.index_offset,
+        };
+        let end  = sb.index_offset.checked_add(header)
+            .and_then(|n| n.checked_add(sb.index_size))
+            .ok_or_else(overflow)?;
+        let mut window = Window { start, data: Cursor::new(source.read_range(start, end - start)?) };
+
+        sb.load_codec_continuation(&mut window)?;
+        let (_, index) = read_index_at(&mut window, sb.index_offset, &limits)?;
+        Ok(Self { superblock: sb, index })
+    }
+
+    pub fn uuid(&self) -> Uuid { self.superblock.archive_uuid }
+
+    pub fn required_codecs(&self) -> &[[u8; 16]] { &self.superblock.required_codec_uuids }
+
+    pub fn list(&self) -> Vec<FileInfo> {
+        self.entries().collect()
+    }
+
+    /// [`list`](Self::list) as a lazy iterator.  [`FileInfo::codec`] and
+    /// [`FileInfo::encrypted`] are never filled in: both come from block
+    /// headers, which are not fetched.
+    pub fn entries(&self) -> impl Iterator<Item = FileInfo> + '_ {
+        self.entries_in(EntryOrder::Index)
+    }
+
+    /// [`entries`](Self::entries) in `order`.
+    pub fn entries_in(&self, order: EntryOrder) -> impl Iterator<Item = FileInfo> + '_ {
+        self.index.record_order(order).map(|i| FileInfo::from(&self.index.records[i]))
+    }
+
+    pub fn stat(&self, name: &str) -> Option<FileInfo> {
+        self.index.records.iter().find(|r| r.name == name).map(FileInfo::from)
+    }
+
+    /// As [`Archive::find_by_hash`](crate::archive::Archive::find_by_hash).
+    pub fn find_by_hash(&self, hash: &[u8; 32]) -> Vec<FileInfo> {
+        self.index.records.iter()
+            .filter(|r| r.block_refs.iter().any(|br| &br.content_hash == hash))
+            .map(FileInfo::from)
+            .collect()
+    }
+}
+
+/// A fetched byte range read at its absolute archive offsets.
+struct Window {
+    start: u64,
+    data:  Cursor<Vec<u8>>,
+}
+
+impl Read for Window {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> { self.data.read(buf) }
+}
+
+impl Seek for Window {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(offset) => SeekFrom::Start(offset.checked_sub(self.start).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput,
+                    format!("offset {offset} lies before the fetched range at {}", self.start))
+            })?),
+            other => other,
+        };
+        Ok(self.start + self.data.seek(pos)?)
+    }
+}
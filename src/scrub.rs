@@ -0,0 +1,160 @@
+//! Incremental scrubbing — catching bit rot in archives that sit unread.
+//!
+//! [`Archive::health_check`] verifies a random sample, and a full verify
+//! reads everything; neither remembers what it saw.  [`scrub`] keeps a
+//! journal, [`ScrubState`], of when each block last verified and the
+//! header hash it verified against, and on each run decodes only the
+//! blocks that are due: never checked, failed last time, rewritten since
+//! (the hash changed), or last verified longer ago than
+//! [`ScrubOptions::max_age`].  Run it daily with a 30-day `max_age` and
+//! every block is read about once a month; [`ScrubOptions::max_blocks`]
+//! caps one run, oldest first, so the work spreads evenly.
+//!
+//! The journal is JSON, keyed by block offset, and belongs to one archive
+//! (by UUID).  Entries for blocks the archive no longer references are
+//! dropped.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::archive::Archive;
+use crate::atomic_file::AtomicFile;
+use crate::error::Result;
+
+/// What a scrub knows about an archive's blocks; see the module docs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrubState {
+    /// UUID of the archive the journal belongs to; set by the first scrub.
+    pub archive_uuid: Option<String>,
+    /// Block header offset → its last verification.
+    pub blocks:       BTreeMap<u64, BlockCheck>,
+}
+
+/// The last verification of one block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockCheck {
+    /// Unix seconds.
+    pub verified_at:  i64,
+    /// The block header's BLAKE3 `content_hash` (hex) at the time.
+    pub content_hash: String,
+    pub ok:           bool,
+}
+
+impl ScrubState {
+    /// Read the journal at `path`; a missing file is an empty journal.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e)    => Err(e),
+        }
+    }
+
+    /// Write the journal to `path`, replacing any previous one atomically.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let out = AtomicFile::create(path)?;
+        serde_json::to_writer_pretty(out.file(), self)?;
+        out.persist()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScrubOptions {
+    /// Re-verify a block once its last verification is this old.
+    pub max_age:    Duration,
+    /// Verify at most this many blocks in one run, oldest first.
+    pub max_blocks: Option<usize>,
+}
+
+impl Default for ScrubOptions {
+    fn default() -> Self {
+        Self { max_age: Duration::from_secs(30 * 24 * 60 * 60), max_blocks: None }
+    }
+}
+
+/// Result of [`scrub`].
+#[derive(Debug, Clone, Default)]
+pub struct ScrubReport {
+    /// Distinct blocks stored in the archive.
+    pub blocks_total:   usize,
+    /// Blocks read and verified this run.
+    pub blocks_checked: usize,
+    /// Blocks due but left for a later run by `max_blocks`.
+    pub blocks_pending: usize,
+    /// Offset and error of every block that failed this run.
+    pub failures:       Vec<(u64, String)>,
+}
+
+impl ScrubReport {
+    /// Blocks verified recently enough that this run skipped them.
+    pub fn blocks_fresh(&self) -> usize {
+        self.blocks_total - self.blocks_checked - self.blocks_pending
+    }
+
+    pub fn is_healthy(&self) -> bool { self.failures.is_empty() }
+}
+
+/// Verify the blocks of `src` that `state` says are due, and record the
+/// results in `state`.  Fails if `state` belongs to another archive.
+pub fn scrub(src: &mut Archive, state: &mut ScrubState, opts: &ScrubOptions) -> Result<ScrubReport> {
+    let uuid = src.uuid().to_string();
+    if let Some(other) = state.archive_uuid.as_ref().filter(|other| **other != uuid) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            format!("scrub state belongs to archive {other}, not {uuid}")).into());
+    }
+    state.archive_uuid = Some(uuid);
+
+    let now    = chrono::Utc::now().timestamp();
+    let reader = src.reader_mut()?;
+    let live: HashSet<u64> = reader.local_block_offsets().into_iter().collect();
+    // Current header hashes.  A header that cannot be read leaves its block
+    // without one, so it is due and its verification reports the damage.
+    let mut hashes = BTreeMap::new();
+    for block in reader.iter_block_headers()? {
+        let Ok((offset, header)) = block else { break };
+        if live.contains(&offset) {
+            hashes.insert(offset, hex::encode(header.content_hash));
+        }
+    }
+    state.blocks.retain(|offset, _| live.contains(offset));
+
+    let max_age = i64::try_from(opts.max_age.as_secs()).unwrap_or(i64::MAX);
+    let mut due: Vec<(i64, u64)> = live.iter()
+        .filter_map(|&offset| match state.blocks.get(&offset) {
+            // Never checked or failed last time: first in line.
+            None                         => Some((i64::MIN, offset)),
+            Some(check) if !check.ok     => Some((i64::MIN, offset)),
+            Some(check) if hashes.get(&offset) != Some(&check.content_hash)
+                || now.saturating_sub(check.verified_at) >= max_age
+                                         => Some((check.verified_at, offset)),
+            Some(_)                      => None,
+        })
+        .collect();
+    due.sort_unstable();
+
+    let take = opts.max_blocks.unwrap_or(usize::MAX).min(due.len());
+    let mut report = ScrubReport {
+        blocks_total:   live.len(),
+        blocks_checked: take,
+        blocks_pending: due.len() - take,
+        ..ScrubReport::default()
+    };
+    for &(_, offset) in &due[..take] {
+        let result = reader.verify_block_at(offset);
+        if let Err(e) = &result {
+            report.failures.push((offset, e.to_string()));
+        }
+        state.blocks.insert(offset, BlockCheck {
+            verified_at:  now,
+            content_hash: hashes.get(&offset).cloned().unwrap_or_default(),
+            ok:           result.is_ok(),
+        });
+    }
+    report.failures.sort_unstable_by_key(|(offset, _)| *offset);
+    Ok(report)
+}
@@ -0,0 +1,356 @@
+//! Cross-archive content-addressable block store.
+//!
+//! A store is a directory that holds every distinct block of many archives
+//! exactly once, keyed by the BLAKE3 `content_hash` already present in each
+//! block header.  Archives are pushed into the store as a *manifest* (the
+//! original block order plus the FileIndex) and can be pulled back out as a
+//! standalone `.6cy` file at any time.
+//!
+//! # Layout
+//!
+//! ```text
+//! <root>/objects/<hh>/<hash-hex>.blk   84-byte block header + raw payload
+//! <root>/manifests/<archive-uuid>.json one per pushed archive
+//! <root>/dictionaries/<uuid>.dict      shared Zstd dictionaries
+//! ```
+//!
+//! Objects are stored exactly as they appear on disk (compressed, never
+//! re-encoded).  The header's `file_id` / `file_offset` belong to whichever
+//! archive pushed the block first; `pull` rewrites them from the manifest.
+//!
+//! # Dictionaries
+//! Archives written with a trained [`Dictionary`] (see
+//! [`PackOptions::dictionary`](crate::archive::PackOptions::dictionary))
+//! name it by UUID; the store keeps each dictionary once, however many
+//! archives use it, and lists it in their manifests.
+//! [`register_dictionaries`](BlockStore::register_dictionaries) makes them
+//! all available for reading.  [`crate::sync`] does not transfer them, and
+//! a store only accepts blocks it can verify, so a remote store must already
+//! hold the dictionaries of the archives synced into it.
+//!
+//! # Encryption
+//! Encrypted blocks are rejected: the same plaintext hash under different
+//! archive keys would map to different ciphertexts, breaking the CAS key.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::atomic_file::AtomicFile;
+use crate::block::{decode_block_limited, BlockHeader, BlockType, BLOCK_HEADER_SIZE};
+use crate::codec::{register_dictionary, registered_dictionary, uuid_to_string, Dictionary};
+use crate::index::FileIndex;
+use crate::io_stream::{SixCyReader, SixCyWriter, MAX_CHUNK_SIZE};
+use crate::limits::Limits;
+
+// ── Manifest ──────────────────────────────────────────────────────────────────
+
+/// One block of a pushed archive, in original on-disk order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredBlock {
+    /// Offset of the block in the archive that was pushed.
+    pub archive_offset: u64,
+    pub block_type:     u16,
+    pub file_id:        u32,
+    pub file_offset:    u64,
+    pub content_hash:   [u8; 32],
+}
+
+/// Everything needed to rebuild one archive from store objects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreManifest {
+    pub archive_uuid: String,
+    pub blocks:       Vec<StoredBlock>,
+    pub index:        FileIndex,
+    /// UUIDs ([`uuid_to_string`] form) of the dictionaries the archive's
+    /// blocks were compressed with.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dictionaries: Vec<String>,
+}
+
+/// Result of [`BlockStore::push`].
+#[derive(Debug, Clone, Default)]
+pub struct PushReport {
+    /// Blocks referenced by the archive's index.
+    pub blocks_total:     usize,
+    /// Blocks that were not yet in the store and were written.
+    pub blocks_new:       usize,
+    /// On-disk bytes (header + payload) added to the store.
+    pub bytes_new:        u64,
+    /// Dictionaries the archive uses that were not yet in the store.
+    pub dictionaries_new: usize,
+}
+
+/// Build the manifest for the archive behind `reader`: every block its
+/// index owns (external snapshot refs excluded), in on-disk order.
+pub fn build_manifest<R: Read + Seek>(reader: &mut SixCyReader<R>) -> io::Result<StoreManifest> {
+    let mut offsets: Vec<u64> = reader.index.block_refs()
+        .filter(|br| !br.is_external())
+        .map(|br| br.archive_offset)
+        .collect();
+    offsets.sort_unstable();
+    offsets.dedup();
+
+    let mut blocks = Vec::with_capacity(offsets.len());
+    for offset in offsets {
+        let (header, _) = reader.read_block_at(offset)?;
+        blocks.push(StoredBlock {
+            archive_offset: offset,
+            block_type:     header.block_type as u16,
+            file_id:        header.file_id,
+            file_offset:    header.file_offset,
+            content_hash:   header.content_hash,
+        });
+    }
+
+    Ok(StoreManifest {
+        archive_uuid: reader.superblock.archive_uuid.to_string(),
+        blocks,
+        index:        (*reader.index).clone(),
+        dictionaries: reader.superblock.required_codec_uuids.iter()
+            .filter(|uuid| registered_dictionary(uuid).is_some())
+            .map(uuid_to_string)
+            .collect(),
+    })
+}
+
+// ── BlockStore ────────────────────────────────────────────────────────────────
+
+pub struct BlockStore {
+    root: PathBuf,
+}
+
+impl BlockStore {
+    /// Open a store rooted at `root`, creating the directory layout if needed.
+    pub fn open<P: AsRef<Path>>(root: P) -> io::Result<Self> {
+        let root = root.as_ref().to_owned();
+        fs::create_dir_all(root.join("objects"))?;
+        fs::create_dir_all(root.join("manifests"))?;
+        fs::create_dir_all(root.join("dictionaries"))?;
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Path { &self.root }
+
+    fn object_path(&self, hash: &[u8; 32]) -> PathBuf {
+        let hex = hex::encode(hash);
+        self.root.join("objects").join(&hex[..2]).join(format!("{hex}.blk"))
+    }
+
+    fn manifest_path(&self, uuid: &Uuid) -> PathBuf {
+        self.root.join("manifests").join(format!("{uuid}.json"))
+    }
+
+    fn dictionary_path(&self, uuid: &[u8; 16]) -> PathBuf {
+        self.root.join("dictionaries").join(format!("{}.dict", uuid_to_string(uuid)))
+    }
+
+    /// True if a block with this content hash is already stored.
+    pub fn contains(&self, hash: &[u8; 32]) -> bool {
+        self.object_path(hash).exists()
+    }
+
+    /// Read a stored block: its header and raw on-disk payload.
+    pub fn get(&self, hash: &[u8; 32]) -> io::Result<(BlockHeader, Vec<u8>)> {
+        let mut f = File::open(self.object_path(hash))?;
+        let header = BlockHeader::read(&mut f)?;
+        let mut payload = vec![0u8; header.comp_size as usize];
+        f.read_exact(&mut payload)?;
+        Ok((header, payload))
+    }
+
+    /// Store a block if absent.  Returns `true` if it was written.
+    ///
+    /// The block is decoded first and rejected with `ErrorKind::InvalidData`
+    /// unless its plaintext hashes to `content_hash` — the object name — so
+    /// a faulty or hostile peer cannot plant wrong data under a valid key.
+    /// Blocks compressed with a dictionary need it registered (see
+    /// [`register_dictionaries`](Self::register_dictionaries)).
+    ///
+    /// Objects are written through an [`AtomicFile`], so a crash never leaves
+    /// a truncated object under its final name and concurrent writers of the
+    /// same block do not share a temporary.
+    pub fn put(&self, header: &BlockHeader, payload: &[u8]) -> io::Result<bool> {
+        if header.is_encrypted() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "encrypted blocks cannot be placed in a shared block store"));
+        }
+        let path = self.object_path(&header.content_hash);
+        if path.exists() {
+            return Ok(false);
+        }
+        let limits = Limits { max_block_size: MAX_CHUNK_SIZE as u64, ..Limits::UNLIMITED };
+        let plain = decode_block_limited(header, payload, None, &limits).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData,
+                format!("block {} failed verification: {e}", hex::encode(header.content_hash)))
+        })?;
+        crate::pool::shared().give(plain);
+
+        fs::create_dir_all(path.parent().expect("object path has a parent"))?;
+        let out = AtomicFile::create(&path)?;
+        let mut f = out.file();
+        header.write(&mut f)?;
+        f.write_all(payload)?;
+        out.persist()?;
+        Ok(true)
+    }
+
+    /// Store a dictionary if absent.  Returns `true` if it was written.
+    pub fn put_dictionary(&self, dict: &Dictionary) -> io::Result<bool> {
+        let path = self.dictionary_path(&dict.uuid());
+        if path.exists() {
+            return Ok(false);
+        }
+        let out = AtomicFile::create(&path)?;
+        out.file().write_all(dict.as_bytes())?;
+        out.persist()?;
+        Ok(true)
+    }
+
+    /// Read a stored dictionary, checking its bytes against `uuid`.
+    ///
+    /// Returns `ErrorKind::NotFound` if the store does not hold it.
+    pub fn dictionary(&self, uuid: &[u8; 16]) -> io::Result<Dictionary> {
+        let dict = Dictionary::new(fs::read(self.dictionary_path(uuid))?);
+        if dict.uuid() != *uuid {
+            return Err(io::Error::new(io::ErrorKind::InvalidData,
+                format!("stored dictionary {} does not match its UUID", uuid_to_string(uuid))));
+        }
+        Ok(dict)
+    }
+
+    /// UUIDs of every dictionary held in this store.
+    pub fn dictionaries(&self) -> io::Result<Vec<[u8; 16]>> {
+        let mut out = Vec::new();
+        for entry in fs::read_dir(self.root.join("dictionaries"))? {
+            let name = entry?.file_name();
+            let name = name.to_string_lossy();
+            if let Some(stem) = name.strip_suffix(".dict") {
+                if let Ok(u) = Uuid::parse_str(stem) {
+                    out.push(u.to_bytes_le());
+                }
+            }
+        }
+        out.sort();
+        Ok(out)
+    }
+
+    /// Register every stored dictionary with
+    /// [`register_dictionary`], so archives that use them can be read.
+    /// Returns how many there are.
+    pub fn register_dictionaries(&self) -> io::Result<usize> {
+        let uuids = self.dictionaries()?;
+        for uuid in &uuids {
+            register_dictionary(std::sync::Arc::new(self.dictionary(uuid)?));
+        }
+        Ok(uuids.len())
+    }
+
+    /// Push every block referenced by the archive at `path` into the store
+    /// and record its manifest.  Blocks already present are not rewritten.
+    /// The archive's dictionaries come from the store or, if this is the
+    /// first archive to use one, from the process registry; either way the
+    /// store keeps one copy.
+    pub fn push<P: AsRef<Path>>(&self, path: P) -> io::Result<PushReport> {
+        self.register_dictionaries()?;
+        let mut reader = SixCyReader::new(File::open(path)?)?;
+        let manifest = build_manifest(&mut reader)?;
+
+        let mut report = PushReport { blocks_total: manifest.blocks.len(), ..PushReport::default() };
+        for uuid in &reader.superblock.required_codec_uuids {
+            if let Some(dict) = registered_dictionary(uuid) {
+                report.dictionaries_new += usize::from(self.put_dictionary(&dict)?);
+            }
+        }
+        for sb in &manifest.blocks {
+            let (header, payload) = reader.read_block_at(sb.archive_offset)?;
+            if self.put(&header, &payload)? {
+                report.blocks_new += 1;
+                report.bytes_new  += (BLOCK_HEADER_SIZE + payload.len()) as u64;
+            }
+        }
+        self.put_manifest(&manifest)?;
+        Ok(report)
+    }
+
+    /// Record (or replace) an archive manifest, atomically: readers see the
+    /// old manifest or the new one, never a partial write.
+    pub fn put_manifest(&self, manifest: &StoreManifest) -> io::Result<()> {
+        let uuid = Uuid::parse_str(&manifest.archive_uuid)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let bytes = serde_json::to_vec(manifest).map_err(io::Error::other)?;
+        let out = AtomicFile::create(&self.manifest_path(&uuid))?;
+        out.file().write_all(&bytes)?;
+        out.persist()
+    }
+
+    /// Rebuild the archive `uuid` from its manifest into a new file at `dest`.
+    ///
+    /// The result keeps the original archive UUID and index; block offsets
+    /// are remapped because stored payloads may differ in size from the ones
+    /// the archive was pushed with (first writer wins).
+    ///
+    /// Fails with `ErrorKind::NotFound` if a dictionary the archive needs
+    /// is missing from the store.
+    pub fn pull<P: AsRef<Path>>(&self, uuid: &Uuid, dest: P) -> io::Result<()> {
+        let manifest = self.manifest(uuid)?;
+        for dict in &manifest.dictionaries {
+            let dict = Uuid::parse_str(dict).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.dictionary(&dict.to_bytes_le())?;
+        }
+
+        let mut writer = SixCyWriter::new(File::create(dest)?)?;
+        writer.superblock.archive_uuid = *uuid;
+
+        let mut remap = std::collections::HashMap::with_capacity(manifest.blocks.len());
+        for sb in &manifest.blocks {
+            let (mut header, payload) = self.get(&sb.content_hash)?;
+            header.block_type = BlockType::from_u16(sb.block_type).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData,
+                    format!("manifest block_type {} is unknown", sb.block_type))
+            })?;
+            header.file_id     = sb.file_id;
+            header.file_offset = sb.file_offset;
+            let new_offset = writer.write_raw_block(&header, &payload)?;
+            remap.insert(sb.archive_offset, new_offset);
+        }
+
+        let mut index = manifest.index;
+        for br in index.block_refs_mut() {
+            if br.is_external() { continue; }
+            br.archive_offset = *remap.get(&br.archive_offset).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData,
+                    format!("manifest has no block at offset {}", br.archive_offset))
+            })?;
+        }
+        writer.index = index;
+        Ok(writer.finalize()?)
+    }
+
+    /// Load the manifest of a pushed archive.
+    ///
+    /// Returns `ErrorKind::NotFound` if the archive was never pushed.
+    pub fn manifest(&self, uuid: &Uuid) -> io::Result<StoreManifest> {
+        let bytes = fs::read(self.manifest_path(uuid))?;
+        serde_json::from_slice(&bytes).map_err(io::Error::other)
+    }
+
+    /// UUIDs of every archive pushed into this store.
+    pub fn archives(&self) -> io::Result<Vec<Uuid>> {
+        let mut out = Vec::new();
+        for entry in fs::read_dir(self.root.join("manifests"))? {
+            let name = entry?.file_name();
+            let name = name.to_string_lossy();
+            if let Some(stem) = name.strip_suffix(".json") {
+                if let Ok(u) = Uuid::parse_str(stem) {
+                    out.push(u);
+                }
+            }
+        }
+        out.sort();
+        Ok(out)
+    }
+}
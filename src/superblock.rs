@@ -16,9 +16,52 @@
 //!   ...    ...  zero padding to exactly 256 bytes
 //! ```
 //!
+//! # Codec continuation
+//! At most [`MAX_INLINE_CODECS`] UUIDs fit inline.  A longer list keeps its
+//! first [`MAX_INLINE_CODECS_CONTINUED`] entries inline and moves the rest
+//! to a CODECS block (`block_type = 3`, codec None, payload = N×16 UUID
+//! bytes) written before the INDEX.  Flag `0x08` marks the slot that
+//! points at it:
+//!
+//! ```text
+//!  148      8   codecs_offset      byte offset of the CODECS block header (LE u64)
+//!  156      4   codecs_count       UUIDs in that block (LE u32)
+//!  160      4   continuation_crc32 CRC32 of [148..160] (LE u32)
+//! ```
+//!
+//! [`Superblock::read`] checks only the inline list; readers call
+//! [`Superblock::load_codec_continuation`] to fetch and check the rest.
+//!
+//! # Feature flags
+//! When flag `0x04` is set, a capability bitset precedes the trailer:
+//!
+//! ```text
+//!  164      8   feature_flags      FEATURE_* bits (LE u64)
+//!  172      4   features_crc32     CRC32 of [164..172] (LE u32)
+//! ```
+//!
+//! Each bit names an optional capability that changes how blocks or the
+//! INDEX must be read.  A reader refuses an archive with any bit outside
+//! [`SUPPORTED_FEATURES`] ([`SuperblockError::UnsupportedFeature`]) rather
+//! than misparse it.  The slot is written only when a bit is set.
+//!
+//! # Provenance trailer
+//! When flag `0x02` is set, the last 80 bytes of the padding hold:
+//!
+//! ```text
+//!  176      8   created_at         Unix seconds (LE i64)
+//!  184      8   modified_at        Unix seconds (LE i64)
+//!  192     60   writer             UTF-8 implementation/version, zero-padded
+//!  252      4   provenance_crc32   CRC32 of [176..252] (LE u32)
+//! ```
+//!
+//! The flag is the minor-version marker: readers that predate it ignore
+//! both the bit and the padding.  The trailer fits only while the codec list
+//! ends by offset 176 (at most 7 codecs); beyond that it is omitted.
+//!
 //! # Codec declaration
-//! `required_codec_uuids` lists every codec UUID that appears in DATA or
-//! SOLID blocks.  A decoder MUST fail immediately if it cannot supply every
+//! `required_codec_uuids` lists every codec UUID that appears in DATA,
+//! SOLID, or INDEX blocks.  A decoder MUST fail immediately if it cannot supply every
 //! listed UUID.  There is no negotiation, no fallback, no partial decode.
 //! The UUID list is written during `finalize()`; it is empty while packing.
 //!
@@ -26,19 +69,29 @@
 //! All numeric fields are little-endian.  The magic is four ASCII bytes.
 //! This is frozen for format_version 3 and above.
 
-use std::io::{self, Read, Write};
+use std::io::{self, Read, Seek, SeekFrom, Write};
 use uuid::Uuid;
 use crc32fast::Hasher;
 use thiserror::Error;
-use crate::codec::{CodecId, uuid_to_string};
+use crate::codec::{codec_source, CodecId, uuid_to_string};
+use crate::block::{decode_block, BlockHeader, BlockType};
+use crate::core_reader::{self, CoreError, CODECS_CONTINUATION_OFFSET, FEATURES_OFFSET,
+                         PROVENANCE_OFFSET, WRITER_LEN};
 
-pub const MAGIC:              &[u8; 4] = b".6cy";
-pub const FORMAT_VERSION:     u32      = 3;
-pub const MIN_FORMAT_VERSION: u32      = 3;  // v1/v2 are not forward-compatible
-pub const SUPERBLOCK_SIZE:    usize    = 256;
+pub use crate::core_reader::{MAGIC, FORMAT_VERSION, MIN_FORMAT_VERSION, SUPERBLOCK_SIZE,
+                             MAX_INLINE_CODECS, MAX_INLINE_CODECS_CONTINUED,
+                             SB_FLAG_CODECS_CONTINUED, RawCodecContinuation as CodecContinuation};
 
 /// Archive-level flag: at least one block is AES-256-GCM encrypted.
 pub const SB_FLAG_ENCRYPTED: u32 = 0x0001;
+pub use crate::core_reader::{
+    SB_FLAG_PROVENANCE, SB_FLAG_FEATURES, SUPPORTED_FEATURES, feature_name,
+    FEATURE_ENCRYPTED_INDEX, FEATURE_PARITY, FEATURE_SEEK_TABLES, FEATURE_BLOCKS_64,
+    FEATURE_PREFILTER,
+};
+
+/// Implementation name and version recorded in new archives.
+pub const WRITER_NAME: &str = concat!("sixcy ", env!("CARGO_PKG_VERSION"));
 
 #[derive(Error, Debug)]
 pub enum SuperblockError {
@@ -52,10 +105,28 @@ pub enum SuperblockError {
     /// The archive CANNOT be decoded; there is no fallback.
     #[error("Required codec UUID {uuid} is not available — cannot open archive")]
     UnavailableCodec { uuid: String },
+    /// A `feature_flags` bit this build does not implement is set.
+    #[error("Archive uses unsupported feature {feature} — cannot open archive")]
+    UnsupportedFeature { feature: String },
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
 }
 
+/// Outcome of [`Superblock::read_lenient`].
+#[derive(Debug)]
+pub struct SuperblockDiagnosis {
+    /// Field values as stored, as far as they could be parsed.  Untrustworthy
+    /// whenever `problems` is non-empty.
+    pub superblock: Superblock,
+    /// Every failed check, in the order [`Superblock::read`] runs them.
+    pub problems:   Vec<SuperblockError>,
+}
+
+impl SuperblockDiagnosis {
+    /// True if [`Superblock::read`] would have succeeded.
+    pub fn is_healthy(&self) -> bool { self.problems.is_empty() }
+}
+
 #[derive(Debug, Clone)]
 pub struct Superblock {
     pub magic:                 [u8; 4],
@@ -67,10 +138,27 @@ pub struct Superblock {
     /// Each entry is the raw 16-byte UUID (LE field order) of a required codec.
     /// Written during `finalize()`; empty while packing is in progress.
     pub required_codec_uuids:  Vec<[u8; 16]>,
+    /// Where the tail of `required_codec_uuids` is stored when the list is
+    /// longer than [`MAX_INLINE_CODECS`].  Set by the writer at finalize.
+    pub codec_continuation:    Option<CodecContinuation>,
+    /// `FEATURE_*` capability bits a reader must implement.
+    pub feature_flags:         u64,
+    /// Creation time, Unix seconds.  `None` for archives without a
+    /// provenance trailer; 0 in deterministic archives.
+    pub created_at:            Option<i64>,
+    /// Time of the last `finalize()`, Unix seconds.
+    pub modified_at:           Option<i64>,
+    /// Implementation that wrote the archive, e.g. [`WRITER_NAME`].
+    pub writer:                Option<String>,
+}
+
+impl Default for Superblock {
+    fn default() -> Self { Self::new() }
 }
 
 impl Superblock {
     pub fn new() -> Self {
+        let now = chrono::Utc::now().timestamp();
         Self {
             magic:                *MAGIC,
             format_version:       FORMAT_VERSION,
@@ -79,6 +167,11 @@ impl Superblock {
             index_offset:         0,
             index_size:           0,
             required_codec_uuids: Vec::new(),
+            codec_continuation:   None,
+            feature_flags:        0,
+            created_at:           Some(now),
+            modified_at:          Some(now),
+            writer:               Some(WRITER_NAME.to_owned()),
         }
     }
 
@@ -86,18 +179,46 @@ impl Superblock {
     ///
     /// `header_crc32` covers all bytes from offset 0 up to (but not including)
     /// the CRC field itself.  The padding after the CRC is not covered.
+    ///
+    /// Fails with `InvalidInput` if the codec list does not fit (more than
+    /// [`MAX_INLINE_CODECS`] without `codec_continuation`), or if
+    /// `feature_flags` is set but the codec list leaves no room for it.
     pub fn write<W: Write>(&self, mut w: W) -> io::Result<()> {
         // Build the variable-length portion in a buffer first so we can CRC it.
         let mut body = Vec::with_capacity(SUPERBLOCK_SIZE);
 
+        let uuids = &self.required_codec_uuids;
+        let inline = match self.codec_continuation {
+            Some(c) => uuids.len().checked_sub(c.count as usize)
+                .filter(|&n| n <= MAX_INLINE_CODECS_CONTINUED),
+            None    => Some(uuids.len()).filter(|&n| n <= MAX_INLINE_CODECS),
+        };
+        let Some(inline) = inline else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "{} required codecs do not fit the superblock — a CODECS continuation block is needed",
+                uuids.len())));
+        };
+
+        let crc_end    = 50 + 16 * inline;
+        let features   = self.feature_flags != 0;
+        if features && crc_end > FEATURES_OFFSET {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "too many required codecs to record feature_flags"));
+        }
+        let provenance = self.has_provenance() && crc_end <= PROVENANCE_OFFSET;
+        let mut flags  = self.flags & !(SB_FLAG_PROVENANCE | SB_FLAG_FEATURES | SB_FLAG_CODECS_CONTINUED);
+        if self.codec_continuation.is_some() { flags |= SB_FLAG_CODECS_CONTINUED; }
+        if provenance { flags |= SB_FLAG_PROVENANCE; }
+        if features   { flags |= SB_FLAG_FEATURES; }
+
         body.extend_from_slice(&self.magic);                                       // 4
         body.extend_from_slice(&self.format_version.to_le_bytes());                // 4
         body.extend_from_slice(self.archive_uuid.as_bytes());                      // 16
-        body.extend_from_slice(&self.flags.to_le_bytes());                         // 4
+        body.extend_from_slice(&flags.to_le_bytes());                              // 4
         body.extend_from_slice(&self.index_offset.to_le_bytes());                  // 8
         body.extend_from_slice(&self.index_size.to_le_bytes());                    // 8
-        body.extend_from_slice(&(self.required_codec_uuids.len() as u16).to_le_bytes()); // 2
-        for uuid_bytes in &self.required_codec_uuids {
+        body.extend_from_slice(&(inline as u16).to_le_bytes());                    // 2
+        for uuid_bytes in &uuids[..inline] {
             body.extend_from_slice(uuid_bytes);                                    // 16 each
         }
         // Fixed pre-CRC size: 4+4+16+4+8+8+2 = 46; + 16*n for codecs.
@@ -108,14 +229,42 @@ impl Superblock {
         body.extend_from_slice(&h.finalize().to_le_bytes()); // 4
 
         // Pad to exactly SUPERBLOCK_SIZE with zeros.
-        assert!(body.len() <= SUPERBLOCK_SIZE,
-            "Superblock body {} B exceeds reserved {} B — too many required codecs",
-            body.len(), SUPERBLOCK_SIZE);
         body.resize(SUPERBLOCK_SIZE, 0u8);
 
+        if let Some(c) = self.codec_continuation {
+            let s = &mut body[CODECS_CONTINUATION_OFFSET..CODECS_CONTINUATION_OFFSET + 16];
+            s[..8].copy_from_slice(&c.offset.to_le_bytes());
+            s[8..12].copy_from_slice(&c.count.to_le_bytes());
+            let mut h = Hasher::new();
+            h.update(&s[..12]);
+            s[12..].copy_from_slice(&h.finalize().to_le_bytes());
+        }
+
+        if features {
+            let f = &mut body[FEATURES_OFFSET..PROVENANCE_OFFSET];
+            f[..8].copy_from_slice(&self.feature_flags.to_le_bytes());
+            let mut h = Hasher::new();
+            h.update(&f[..8]);
+            f[8..].copy_from_slice(&h.finalize().to_le_bytes());
+        }
+        if provenance {
+            let t = &mut body[PROVENANCE_OFFSET..];
+            t[0..8].copy_from_slice(&self.created_at.unwrap_or(0).to_le_bytes());
+            t[8..16].copy_from_slice(&self.modified_at.unwrap_or(0).to_le_bytes());
+            let name = truncate_utf8(self.writer.as_deref().unwrap_or(""), WRITER_LEN);
+            t[16..16 + name.len()].copy_from_slice(name.as_bytes());
+            let mut h = Hasher::new();
+            h.update(&t[..16 + WRITER_LEN]);
+            t[16 + WRITER_LEN..].copy_from_slice(&h.finalize().to_le_bytes());
+        }
+
         w.write_all(&body)
     }
 
+    fn has_provenance(&self) -> bool {
+        self.created_at.is_some() || self.modified_at.is_some() || self.writer.is_some()
+    }
+
     /// Read, validate magic, version, and CRC32, then check codec availability.
     ///
     /// Returns `UnavailableCodec` if any required UUID is not in this build.
@@ -124,65 +273,73 @@ impl Superblock {
         let mut buf = [0u8; SUPERBLOCK_SIZE];
         r.read_exact(&mut buf)?;
 
-        // Magic.
-        if &buf[0..4] != MAGIC {
-            return Err(SuperblockError::InvalidMagic);
-        }
+        let raw = core_reader::parse_superblock(&buf).map_err(from_core)?;
+        let sb  = Self::from_raw(*MAGIC, raw);
 
-        // Version — fail hard if below minimum.
-        let format_version = u32::from_le_bytes(buf[4..8].try_into().unwrap());
-        if format_version < MIN_FORMAT_VERSION {
-            return Err(SuperblockError::UnsupportedVersion(format_version));
-        }
+        // Feature and codec availability checks — fail now, not at block
+        // decode time.
+        sb.check_features()?;
+        sb.check_codecs()?;
 
-        let archive_uuid = Uuid::from_bytes(buf[8..24].try_into().unwrap());
-        let flags        = u32::from_le_bytes(buf[24..28].try_into().unwrap());
-        let index_offset = u64::from_le_bytes(buf[28..36].try_into().unwrap());
-        let index_size   = u64::from_le_bytes(buf[36..44].try_into().unwrap());
-        let codec_count  = u16::from_le_bytes(buf[44..46].try_into().unwrap()) as usize;
-
-        // Parse codec UUIDs.
-        let uuid_end = 46 + codec_count * 16;
-        if uuid_end + 4 > SUPERBLOCK_SIZE {
-            return Err(io::Error::new(io::ErrorKind::InvalidData,
-                "required_codec_count overflows superblock").into());
-        }
-        let mut required_codec_uuids = Vec::with_capacity(codec_count);
-        for i in 0..codec_count {
-            let start = 46 + i * 16;
-            let u: [u8; 16] = buf[start..start+16].try_into().unwrap();
-            required_codec_uuids.push(u);
-        }
+        Ok(sb)
+    }
 
-        // Verify CRC32 — covers buf[0..uuid_end].
-        let stored_crc   = u32::from_le_bytes(buf[uuid_end..uuid_end+4].try_into().unwrap());
-        let mut h = Hasher::new();
-        h.update(&buf[..uuid_end]);
-        if h.finalize() != stored_crc {
-            return Err(SuperblockError::Crc32Mismatch);
+    /// Read the superblock for diagnostics: run every check [`read`](Self::read)
+    /// runs, but report failures instead of stopping at the first one.
+    ///
+    /// A short input is padded with zeros and reported as an `Io` problem.
+    /// Only an I/O error from `r` itself fails the call.
+    pub fn read_lenient<R: Read>(r: R) -> io::Result<SuperblockDiagnosis> {
+        let mut buf = Vec::with_capacity(SUPERBLOCK_SIZE);
+        r.take(SUPERBLOCK_SIZE as u64).read_to_end(&mut buf)?;
+        let mut problems = Vec::new();
+        if buf.len() < SUPERBLOCK_SIZE {
+            problems.push(SuperblockError::Io(io::Error::new(io::ErrorKind::UnexpectedEof,
+                format!("superblock truncated: {} of {SUPERBLOCK_SIZE} bytes", buf.len()))));
+            buf.resize(SUPERBLOCK_SIZE, 0);
         }
 
-        let sb = Self {
-            magic: *MAGIC,
-            format_version,
-            archive_uuid,
-            flags,
-            index_offset,
-            index_size,
-            required_codec_uuids,
-        };
+        let (raw, core_problems) = core_reader::parse_superblock_lenient(&buf)
+            .expect("buffer padded to SUPERBLOCK_SIZE");
+        problems.extend(core_problems.into_iter().map(from_core));
+        let superblock = Self::from_raw(buf[..4].try_into().unwrap(), raw);
 
-        // Codec availability check — fail now, not at block decode time.
-        sb.check_codecs()?;
+        if let Err(e) = superblock.check_features() {
+            problems.push(e);
+        }
+        problems.extend(superblock.required_codec_uuids.iter()
+            .filter(|u| codec_source(u).is_none())
+            .map(|u| SuperblockError::UnavailableCodec { uuid: uuid_to_string(u) }));
 
-        Ok(sb)
+        Ok(SuperblockDiagnosis { superblock, problems })
+    }
+
+    fn from_raw(magic: [u8; 4], raw: core_reader::RawSuperblock) -> Self {
+        Self {
+            magic,
+            format_version:       raw.format_version,
+            archive_uuid:         Uuid::from_bytes(raw.archive_uuid),
+            flags:                raw.flags,
+            index_offset:         raw.index_offset,
+            index_size:           raw.index_size,
+            required_codec_uuids: raw.required_codec_uuids,
+            codec_continuation:   raw.codecs_continued,
+            feature_flags:        raw.feature_flags,
+            created_at:           raw.provenance.as_ref().map(|p| p.created_at),
+            modified_at:          raw.provenance.as_ref().map(|p| p.modified_at),
+            writer:               raw.provenance.as_ref().map(|p| {
+                let end = p.writer.iter().position(|&b| b == 0).unwrap_or(WRITER_LEN);
+                String::from_utf8_lossy(&p.writer[..end]).into_owned()
+            }),
+        }
     }
 
-    /// Verify that every required codec UUID is available in this build.
+    /// Verify that every required codec UUID is available, either built in
+    /// or from a registered plugin.
     /// Returns the first unavailable UUID if any are missing.
     pub fn check_codecs(&self) -> Result<(), SuperblockError> {
         for uuid_bytes in &self.required_codec_uuids {
-            if CodecId::from_uuid(uuid_bytes).is_none() {
+            if codec_source(uuid_bytes).is_none() {
                 return Err(SuperblockError::UnavailableCodec {
                     uuid: uuid_to_string(uuid_bytes),
                 });
@@ -191,15 +348,87 @@ impl Superblock {
         Ok(())
     }
 
+    /// Append the UUIDs held in the CODECS continuation block (if any) to
+    /// `required_codec_uuids`, then re-run [`check_codecs`](Self::check_codecs)
+    /// over the full list.  A no-op for archives without a continuation and
+    /// for a list that is already complete.
+    pub fn load_codec_continuation<R: Read + Seek>(&mut self, mut r: R) -> Result<(), SuperblockError> {
+        let Some(c) = self.codec_continuation else { return Ok(()) };
+        if self.required_codec_uuids.len() > MAX_INLINE_CODECS_CONTINUED {
+            return self.check_codecs();
+        }
+        let invalid = |msg: String| SuperblockError::Io(io::Error::new(io::ErrorKind::InvalidData, msg));
+
+        r.seek(SeekFrom::Start(c.offset))?;
+        let header = BlockHeader::read(&mut r)?;
+        let len = c.count as u64 * 16;
+        if header.block_type != BlockType::Codecs
+            || header.orig_size as u64 != len
+            || header.comp_size as u64 != len
+        {
+            return Err(invalid(format!("no CODECS block of {} UUIDs at offset {}", c.count, c.offset)));
+        }
+        let mut payload = vec![0u8; len as usize];
+        r.read_exact(&mut payload)?;
+        let uuids = decode_block(&header, &payload, None)
+            .map_err(|e| invalid(format!("CODECS block: {e}")))?;
+        self.required_codec_uuids.extend(uuids.chunks_exact(16).map(|u| <[u8; 16]>::try_from(u).unwrap()));
+        self.check_codecs()
+    }
+
+    /// The codec UUIDs a writer must move to a CODECS block, or `None` if
+    /// the list fits inline.
+    pub fn codec_overflow(&self) -> Option<&[[u8; 16]]> {
+        (self.required_codec_uuids.len() > MAX_INLINE_CODECS)
+            .then(|| &self.required_codec_uuids[MAX_INLINE_CODECS_CONTINUED..])
+    }
+
+    /// Verify that this build implements every bit in `feature_flags`.
+    /// Reports the lowest unsupported bit by name (or number, if unknown).
+    pub fn check_features(&self) -> Result<(), SuperblockError> {
+        let missing = self.feature_flags & !SUPPORTED_FEATURES;
+        if missing == 0 {
+            return Ok(());
+        }
+        let bit = missing & missing.wrapping_neg();
+        let feature = feature_name(bit)
+            .map(str::to_owned)
+            .unwrap_or_else(|| format!("bit {}", bit.trailing_zeros()));
+        Err(SuperblockError::UnsupportedFeature { feature })
+    }
+
     /// Register a codec UUID as required (called by the writer when a new
     /// codec appears in a block).  Duplicate entries are deduplicated.
     pub fn add_required_codec(&mut self, codec_id: CodecId) {
-        if codec_id == CodecId::None {
+        self.add_required_uuid(&codec_id.uuid());
+    }
+
+    /// Register a raw codec UUID as required.  Used when copying blocks
+    /// verbatim, where the codec may not be resolvable by this build.
+    pub fn add_required_uuid(&mut self, uuid: &[u8; 16]) {
+        if uuid == &crate::codec::UUID_NONE {
             return; // None codec requires no decoder capability
         }
-        let uuid = codec_id.uuid();
-        if !self.required_codec_uuids.iter().any(|u| u == &uuid) {
-            self.required_codec_uuids.push(uuid);
+        if !self.required_codec_uuids.iter().any(|u| u == uuid) {
+            self.required_codec_uuids.push(*uuid);
         }
     }
 }
+
+fn from_core(e: CoreError) -> SuperblockError {
+    match e {
+        CoreError::InvalidMagic            => SuperblockError::InvalidMagic,
+        CoreError::UnsupportedVersion(v)   => SuperblockError::UnsupportedVersion(v),
+        CoreError::SuperblockCrc32Mismatch
+        | CoreError::ProvenanceCrc32Mismatch
+        | CoreError::FeaturesCrc32Mismatch => SuperblockError::Crc32Mismatch,
+        other                              => SuperblockError::Io(other.into()),
+    }
+}
+
+/// Longest prefix of `s` that fits in `max` bytes without splitting a char.
+fn truncate_utf8(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) { end -= 1; }
+    &s[..end]
+}
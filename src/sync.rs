@@ -0,0 +1,494 @@
+//! Remote sync — rsync for archives, driven by the Merkle root.
+//!
+//! The remote side is a [`BlockStore`].  Syncing an archive:
+//!
+//! 1. Ask the remote for the manifest it holds for this archive UUID.  If its
+//!    index `root_hash` equals ours the remote is already up to date.
+//! 2. Send the content hashes of every block we own; the remote answers with
+//!    the subset it lacks.
+//! 3. Stream only those blocks, then the manifest.  The manifest is written
+//!    last so an interrupted sync never publishes an index whose blocks are
+//!    missing.
+//!
+//! # Transports
+//! [`Transport`] is the pluggable seam.  A local [`BlockStore`] implements
+//! it directly; [`StreamTransport`] speaks a framed request/response protocol
+//! over any byte pipe, and [`StreamTransport::ssh`] runs `6cy sync-serve` on
+//! the far end of an `ssh` subprocess.  [`HttpTransport`] `POST`s each
+//! request frame through `curl` to a [`serve_http`] endpoint, which answers
+//! with the response frame.  Object-store transports implement the same four
+//! methods.
+//!
+//! Servers verify every uploaded block against its content hash before
+//! storing it (see [`BlockStore::put`]), and refuse frames longer than
+//! [`MAX_FRAME`] before reading their body.
+//!
+//! Neither server authenticates its peer: any client may upload blocks and
+//! replace any archive's manifest.  `ssh` authenticates [`serve`]'s peer;
+//! put [`serve_http`] behind a reverse proxy that authenticates clients
+//! (and terminates TLS), and bind it to a loopback or private address.
+//!
+//! # Wire protocol
+//! ```text
+//! request:  op (u8) | len (LE u32) | body
+//! response: status (u8, 0=ok 1=err) | len (LE u32) | body
+//!
+//! op 1 STATE     body = archive UUID (ASCII)   → JSON Option<root_hash hex>
+//! op 2 MISSING   body = JSON [hash hex]        → JSON [hash hex]
+//! op 3 PUT_BLOCK body = header (84 B) + payload → empty
+//! op 4 MANIFEST  body = JSON StoreManifest     → empty
+//! ```
+//! Error bodies are UTF-8 messages.  Over HTTP the request frame is the
+//! body of a `POST` and the response frame the body of its `200` reply.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::archive::Archive;
+use crate::block::{BlockHeader, BLOCK_HEADER_SIZE};
+use crate::io_stream::MAX_CHUNK_SIZE;
+use crate::store::{build_manifest, BlockStore, StoreManifest};
+
+const OP_STATE:     u8 = 1;
+const OP_MISSING:   u8 = 2;
+const OP_PUT_BLOCK: u8 = 3;
+const OP_MANIFEST:  u8 = 4;
+
+/// Largest frame body either side accepts: a [`MAX_CHUNK_SIZE`] block plus
+/// its header, with 1 MiB to spare for codec overhead.  Longer frames are
+/// rejected with `ErrorKind::InvalidData` before any of the body is read.
+pub const MAX_FRAME: usize = MAX_CHUNK_SIZE + (1 << 20);
+
+// ── Transport ─────────────────────────────────────────────────────────────────
+
+/// Connection to a remote block store.
+pub trait Transport {
+    /// Root hash of the index the remote holds for `archive_uuid`, if any.
+    fn remote_root(&mut self, archive_uuid: &Uuid) -> io::Result<Option<[u8; 32]>>;
+    /// Return the subset of `hashes` the remote does not have.
+    fn missing(&mut self, hashes: &[[u8; 32]]) -> io::Result<Vec<[u8; 32]>>;
+    /// Upload one block verbatim.
+    fn put_block(&mut self, header: &BlockHeader, payload: &[u8]) -> io::Result<()>;
+    /// Publish the manifest; called once, after every block is uploaded.
+    fn put_manifest(&mut self, manifest: &StoreManifest) -> io::Result<()>;
+}
+
+impl Transport for BlockStore {
+    fn remote_root(&mut self, archive_uuid: &Uuid) -> io::Result<Option<[u8; 32]>> {
+        match self.manifest(archive_uuid) {
+            Ok(m) => Ok(Some(m.index.root_hash)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn missing(&mut self, hashes: &[[u8; 32]]) -> io::Result<Vec<[u8; 32]>> {
+        Ok(hashes.iter().filter(|h| !self.contains(h)).copied().collect())
+    }
+
+    fn put_block(&mut self, header: &BlockHeader, payload: &[u8]) -> io::Result<()> {
+        self.put(header, payload).map(|_| ())
+    }
+
+    fn put_manifest(&mut self, manifest: &StoreManifest) -> io::Result<()> {
+        BlockStore::put_manifest(self, manifest)
+    }
+}
+
+// ── Sync ──────────────────────────────────────────────────────────────────────
+
+/// Outcome of [`push`].
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// Remote root hash already matched; nothing was sent.
+    pub up_to_date:   bool,
+    /// Blocks owned by the local archive.
+    pub blocks_total: usize,
+    /// Blocks the remote lacked and received.
+    pub blocks_sent:  usize,
+    /// Header + payload bytes sent.
+    pub bytes_sent:   u64,
+}
+
+/// Bring the remote copy of `archive` up to date, sending only missing blocks.
+pub fn push(archive: &mut Archive, transport: &mut dyn Transport) -> io::Result<SyncReport> {
+    let reader = archive.reader_mut()?;
+    let uuid   = reader.superblock.archive_uuid;
+
+    if transport.remote_root(&uuid)? == Some(reader.index.root_hash) {
+        return Ok(SyncReport { up_to_date: true, ..SyncReport::default() });
+    }
+
+    let manifest = build_manifest(reader)?;
+    let hashes: Vec<[u8; 32]> = manifest.blocks.iter().map(|b| b.content_hash).collect();
+    let missing: std::collections::HashSet<[u8; 32]> =
+        transport.missing(&hashes)?.into_iter().collect();
+
+    let mut report = SyncReport { blocks_total: manifest.blocks.len(), ..SyncReport::default() };
+    for sb in manifest.blocks.iter().filter(|b| missing.contains(&b.content_hash)) {
+        let (header, payload) = reader.read_block_at(sb.archive_offset)?;
+        transport.put_block(&header, &payload)?;
+        report.blocks_sent += 1;
+        report.bytes_sent  += (BLOCK_HEADER_SIZE + payload.len()) as u64;
+    }
+    transport.put_manifest(&manifest)?;
+    Ok(report)
+}
+
+// ── Stream transport ──────────────────────────────────────────────────────────
+
+fn write_frame<W: Write>(w: &mut W, tag: u8, body: &[u8]) -> io::Result<()> {
+    w.write_all(&[tag])?;
+    w.write_all(&(body.len() as u32).to_le_bytes())?;
+    w.write_all(body)?;
+    w.flush()
+}
+
+/// Read one frame; `Ok(None)` on clean EOF before the tag byte.
+fn read_frame<R: Read>(r: &mut R) -> io::Result<Option<(u8, Vec<u8>)>> {
+    let mut tag = [0u8; 1];
+    match r.read_exact(&mut tag) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME {
+        return Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("sync frame of {len} B exceeds the {MAX_FRAME} B limit")));
+    }
+    // Grown as bytes arrive rather than allocated from the untrusted length.
+    let mut body = Vec::new();
+    r.take(len as u64).read_to_end(&mut body)?;
+    if body.len() != len {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "sync frame truncated"));
+    }
+    Ok(Some((tag[0], body)))
+}
+
+/// The body of a response frame, or its error message as an `io::Error`.
+fn response_body(frame: Option<(u8, Vec<u8>)>) -> io::Result<Vec<u8>> {
+    match frame {
+        Some((0, body)) => Ok(body),
+        Some((_, msg))  => Err(io::Error::other(String::from_utf8_lossy(&msg).into_owned())),
+        None            => Err(io::Error::new(io::ErrorKind::UnexpectedEof,
+                               "remote closed the sync connection")),
+    }
+}
+
+fn hashes_to_json(hashes: &[[u8; 32]]) -> io::Result<Vec<u8>> {
+    let hex: Vec<String> = hashes.iter().map(hex::encode).collect();
+    serde_json::to_vec(&hex).map_err(io::Error::other)
+}
+
+fn hash_from_hex(h: &str) -> io::Result<[u8; 32]> {
+    let mut out = [0u8; 32];
+    hex::decode_to_slice(h, &mut out)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(out)
+}
+
+fn hashes_from_json(body: &[u8]) -> io::Result<Vec<[u8; 32]>> {
+    let hex: Vec<String> = serde_json::from_slice(body)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    hex.iter().map(|h| hash_from_hex(h)).collect()
+}
+
+/// One request/response exchange of the framed protocol; the four
+/// [`Transport`] methods are the same for every transport that speaks it.
+trait FramedCall {
+    fn call(&mut self, op: u8, body: &[u8]) -> io::Result<Vec<u8>>;
+
+    fn framed_remote_root(&mut self, archive_uuid: &Uuid) -> io::Result<Option<[u8; 32]>> {
+        let body = self.call(OP_STATE, archive_uuid.to_string().as_bytes())?;
+        let root: Option<String> = serde_json::from_slice(&body)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        root.as_deref().map(hash_from_hex).transpose()
+    }
+
+    fn framed_missing(&mut self, hashes: &[[u8; 32]]) -> io::Result<Vec<[u8; 32]>> {
+        let body = self.call(OP_MISSING, &hashes_to_json(hashes)?)?;
+        hashes_from_json(&body)
+    }
+
+    fn framed_put_block(&mut self, header: &BlockHeader, payload: &[u8]) -> io::Result<()> {
+        let mut body = Vec::with_capacity(BLOCK_HEADER_SIZE + payload.len());
+        header.write(&mut body)?;
+        body.extend_from_slice(payload);
+        self.call(OP_PUT_BLOCK, &body).map(|_| ())
+    }
+
+    fn framed_put_manifest(&mut self, manifest: &StoreManifest) -> io::Result<()> {
+        let body = serde_json::to_vec(manifest).map_err(io::Error::other)?;
+        self.call(OP_MANIFEST, &body).map(|_| ())
+    }
+}
+
+/// [`Transport`] over a byte pipe speaking the framed protocol.
+pub struct StreamTransport<R: Read, W: Write> {
+    rx:    R,
+    tx:    W,
+    child: Option<Child>,
+}
+
+impl<R: Read, W: Write> StreamTransport<R, W> {
+    pub fn new(rx: R, tx: W) -> Self {
+        Self { rx, tx, child: None }
+    }
+}
+
+impl<R: Read, W: Write> FramedCall for StreamTransport<R, W> {
+    fn call(&mut self, op: u8, body: &[u8]) -> io::Result<Vec<u8>> {
+        write_frame(&mut self.tx, op, body)?;
+        response_body(read_frame(&mut self.rx)?)
+    }
+}
+
+impl StreamTransport<ChildStdout, ChildStdin> {
+    /// Spawn `ssh <host> 6cy sync-serve --store <remote_store>` and talk to it
+    /// over the child's stdin/stdout.
+    pub fn ssh(host: &str, remote_store: &str) -> io::Result<Self> {
+        let mut child = Command::new("ssh")
+            .arg(host)
+            .args(["6cy", "sync-serve", "--store", remote_store])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let tx = child.stdin.take().expect("stdin is piped");
+        let rx = child.stdout.take().expect("stdout is piped");
+        Ok(Self { rx, tx, child: Some(child) })
+    }
+}
+
+impl<R: Read, W: Write> Drop for StreamTransport<R, W> {
+    fn drop(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+impl<R: Read, W: Write> Transport for StreamTransport<R, W> {
+    fn remote_root(&mut self, archive_uuid: &Uuid) -> io::Result<Option<[u8; 32]>> {
+        self.framed_remote_root(archive_uuid)
+    }
+
+    fn missing(&mut self, hashes: &[[u8; 32]]) -> io::Result<Vec<[u8; 32]>> {
+        self.framed_missing(hashes)
+    }
+
+    fn put_block(&mut self, header: &BlockHeader, payload: &[u8]) -> io::Result<()> {
+        self.framed_put_block(header, payload)
+    }
+
+    fn put_manifest(&mut self, manifest: &StoreManifest) -> io::Result<()> {
+        self.framed_put_manifest(manifest)
+    }
+}
+
+// ── HTTP transport ────────────────────────────────────────────────────────────
+
+/// [`Transport`] that `POST`s each request frame to a [`serve_http`]
+/// endpoint through `curl`, which must be on `PATH`.
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    url: String,
+}
+
+impl HttpTransport {
+    /// Fails with `InvalidInput` unless `url` is `http://` or `https://`.
+    pub fn new(url: &str) -> io::Result<Self> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("not an http(s) URL: {url}")));
+        }
+        Ok(Self { url: url.to_owned() })
+    }
+
+    pub fn url(&self) -> &str { &self.url }
+}
+
+impl FramedCall for HttpTransport {
+    fn call(&mut self, op: u8, body: &[u8]) -> io::Result<Vec<u8>> {
+        let mut request = Vec::with_capacity(5 + body.len());
+        write_frame(&mut request, op, body)?;
+        // An empty `Expect:` stops curl pausing for `100 Continue` on large
+        // bodies.
+        let mut child = Command::new("curl")
+            .args(["--silent", "--show-error", "--fail", "--data-binary", "@-"])
+            .args(["--header", "Content-Type: application/octet-stream", "--header", "Expect:"])
+            .arg(&self.url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let written = child.stdin.take().expect("stdin is piped").write_all(&request);
+        let out = child.wait_with_output()?;
+        if !out.status.success() {
+            return Err(io::Error::other(format!("{}: {}",
+                self.url, String::from_utf8_lossy(&out.stderr).trim())));
+        }
+        written?;
+        response_body(read_frame(&mut out.stdout.as_slice())?)
+    }
+}
+
+impl Transport for HttpTransport {
+    fn remote_root(&mut self, archive_uuid: &Uuid) -> io::Result<Option<[u8; 32]>> {
+        self.framed_remote_root(archive_uuid)
+    }
+
+    fn missing(&mut self, hashes: &[[u8; 32]]) -> io::Result<Vec<[u8; 32]>> {
+        self.framed_missing(hashes)
+    }
+
+    fn put_block(&mut self, header: &BlockHeader, payload: &[u8]) -> io::Result<()> {
+        self.framed_put_block(header, payload)
+    }
+
+    fn put_manifest(&mut self, manifest: &StoreManifest) -> io::Result<()> {
+        self.framed_put_manifest(manifest)
+    }
+}
+
+// ── Server ────────────────────────────────────────────────────────────────────
+
+/// Serve the framed protocol for `store` until the peer closes the stream.
+///
+/// Per-request failures are reported to the peer as error frames; only
+/// transport-level I/O errors end the session.  The store's dictionaries
+/// are registered first so blocks compressed with them can be verified.
+pub fn serve<R: Read, W: Write>(store: &mut BlockStore, mut rx: R, mut tx: W) -> io::Result<()> {
+    store.register_dictionaries()?;
+    while let Some((op, body)) = read_frame(&mut rx)? {
+        respond(&mut tx, handle(store, op, &body))?;
+    }
+    Ok(())
+}
+
+/// Serve the framed protocol over HTTP, one `POST` per request, for every
+/// connection `listener` accepts; see [`HttpTransport`].
+///
+/// Connections are handled one at a time and closed after each reply.  A
+/// connection that fails, or stalls for [`HTTP_IO_TIMEOUT`] on a read or
+/// write, is dropped without ending the server; only an error from
+/// `accept` does.
+///
+/// There is no authentication; see the module docs before exposing it.
+pub fn serve_http(store: &mut BlockStore, listener: TcpListener) -> io::Result<()> {
+    store.register_dictionaries()?;
+    for conn in listener.incoming() {
+        let _ = serve_http_request(store, conn?);
+    }
+    Ok(())
+}
+
+/// Longest request line plus headers [`serve_http`] reads.
+const MAX_HTTP_HEAD: u64 = 16 * 1024;
+
+/// How long [`serve_http`] waits on one read from, or write to, a client.
+pub const HTTP_IO_TIMEOUT: Duration = Duration::from_secs(30);
+
+fn serve_http_request(store: &mut BlockStore, conn: TcpStream) -> io::Result<()> {
+    conn.set_read_timeout(Some(HTTP_IO_TIMEOUT))?;
+    conn.set_write_timeout(Some(HTTP_IO_TIMEOUT))?;
+    let mut tx = conn.try_clone()?;
+    let mut rx = BufReader::new(conn);
+    let reply = |tx: &mut TcpStream, status: &str, body: &[u8]| -> io::Result<()> {
+        write!(tx, "HTTP/1.1 {status}\r\nContent-Type: application/octet-stream\r\n\
+                    Content-Length: {}\r\nConnection: close\r\n\r\n", body.len())?;
+        tx.write_all(body)?;
+        tx.flush()
+    };
+
+    let mut head = (&mut rx).take(MAX_HTTP_HEAD);
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    let post = line.starts_with("POST ");
+    let (mut length, mut expect_continue) = (None, false);
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 {
+            return reply(&mut tx, "431 Request Header Fields Too Large", b"");
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.parse::<usize>().ok();
+            } else if name.eq_ignore_ascii_case("expect") {
+                expect_continue = value.eq_ignore_ascii_case("100-continue");
+            }
+        }
+    }
+    if !post {
+        return reply(&mut tx, "405 Method Not Allowed", b"sync requests must be POSTed");
+    }
+    let Some(length) = length else {
+        return reply(&mut tx, "411 Length Required", b"");
+    };
+    if length > 5 + MAX_FRAME {
+        return reply(&mut tx, "413 Content Too Large", b"");
+    }
+    if expect_continue {
+        tx.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+    }
+
+    let mut body = (&mut rx).take(length as u64);
+    let (op, request) = match read_frame(&mut body) {
+        Ok(Some(frame)) if body.limit() == 0 => frame,
+        Ok(_) | Err(_) => return reply(&mut tx, "400 Bad Request", b"malformed sync frame"),
+    };
+    let mut response = Vec::new();
+    respond(&mut response, handle(store, op, &request))?;
+    reply(&mut tx, "200 OK", &response)
+}
+
+fn respond<W: Write>(tx: &mut W, result: io::Result<Vec<u8>>) -> io::Result<()> {
+    match result {
+        Ok(body) => write_frame(tx, 0, &body),
+        Err(e)   => write_frame(tx, 1, e.to_string().as_bytes()),
+    }
+}
+
+/// Carry out one request against `store`.
+fn handle(store: &mut BlockStore, op: u8, body: &[u8]) -> io::Result<Vec<u8>> {
+    match op {
+        OP_STATE => std::str::from_utf8(body)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            .and_then(|s| Uuid::parse_str(s)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+            .and_then(|u| store.remote_root(&u))
+            .and_then(|root| serde_json::to_vec(&root.map(hex::encode))
+                .map_err(io::Error::other)),
+        OP_MISSING => hashes_from_json(body)
+            .and_then(|h| store.missing(&h))
+            .and_then(|m| hashes_to_json(&m)),
+        OP_PUT_BLOCK => {
+            BlockHeader::read(body).and_then(|h| {
+                let payload = &body[BLOCK_HEADER_SIZE..];
+                if payload.len() != h.comp_size as usize {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData,
+                        "block payload length does not match comp_size"));
+                }
+                store.put(&h, payload).map(|_| Vec::new())
+            })
+        }
+        OP_MANIFEST => serde_json::from_slice::<StoreManifest>(body)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            .and_then(|m| BlockStore::put_manifest(store, &m))
+            .map(|_| Vec::new()),
+        other => Err(io::Error::new(io::ErrorKind::InvalidInput,
+            format!("unknown sync op {other}"))),
+    }
+}
@@ -0,0 +1,103 @@
+//! JS-facing reader API for `wasm32-unknown-unknown` (feature `wasm`).
+//!
+//! Web apps hand the archive bytes over from an `ArrayBuffer`
+//! (`new Uint8Array(buf)`) and list or extract entries entirely client-side.
+//! Nothing here touches the filesystem; the reader runs over an in-memory
+//! cursor.
+//!
+//! ```js
+//! const ar    = new SixcyArchive(new Uint8Array(await file.arrayBuffer()));
+//! const files = JSON.parse(ar.entriesJson());
+//! const bytes = ar.readFile(files[0].name);   // Uint8Array
+//! ```
+
+use std::io::Cursor;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::crypto::derive_key;
+use crate::io_stream::SixCyReader;
+use crate::superblock::Superblock;
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    id:              u32,
+    name:            &'a str,
+    size:            u64,
+    compressed_size: u64,
+}
+
+fn js_err<E: std::fmt::Display>(e: E) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// A read-only archive held in memory.
+#[wasm_bindgen(js_name = SixcyArchive)]
+pub struct WasmArchive {
+    reader: SixCyReader<Cursor<Vec<u8>>>,
+}
+
+#[wasm_bindgen(js_class = SixcyArchive)]
+impl WasmArchive {
+    /// Open an unencrypted archive from its raw bytes.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<WasmArchive, JsError> {
+        let reader = SixCyReader::new(Cursor::new(bytes.to_vec())).map_err(js_err)?;
+        Ok(Self { reader })
+    }
+
+    /// Open an encrypted archive.  Argon2id runs on the calling thread; call
+    /// this from a Web Worker to keep the UI responsive.
+    #[wasm_bindgen(js_name = openEncrypted)]
+    pub fn open_encrypted(bytes: &[u8], password: &str) -> Result<WasmArchive, JsError> {
+        let sb = Superblock::read(bytes).map_err(js_err)?;
+        let key = derive_key(password, sb.archive_uuid.as_bytes()).map_err(js_err)?;
+        let reader = SixCyReader::with_key(Cursor::new(bytes.to_vec()), Some(key))
+            .map_err(js_err)?;
+        Ok(Self { reader })
+    }
+
+    /// Archive UUID as a hyphenated string.
+    pub fn uuid(&self) -> String {
+        self.reader.superblock.archive_uuid.to_string()
+    }
+
+    /// Merkle root of the index, hex-encoded.
+    #[wasm_bindgen(js_name = rootHash)]
+    pub fn root_hash(&self) -> String {
+        hex::encode(self.reader.index.root_hash)
+    }
+
+    #[wasm_bindgen(js_name = fileCount)]
+    pub fn file_count(&self) -> usize {
+        self.reader.index.records.len()
+    }
+
+    /// Entry names in index order.
+    pub fn names(&self) -> Vec<String> {
+        self.reader.index.records.iter().map(|r| r.name.clone()).collect()
+    }
+
+    /// `[{id, name, size, compressed_size}, …]` as a JSON string.
+    #[wasm_bindgen(js_name = entriesJson)]
+    pub fn entries_json(&self) -> Result<String, JsError> {
+        let entries: Vec<Entry> = self.reader.index.records.iter().map(|r| Entry {
+            id:              r.id,
+            name:            &r.name,
+            size:            r.original_size,
+            compressed_size: r.compressed_size,
+        }).collect();
+        serde_json::to_string(&entries).map_err(js_err)
+    }
+
+    /// Extract one entry by name; returned to JS as a `Uint8Array`.
+    #[wasm_bindgen(js_name = readFile)]
+    pub fn read_file(&mut self, name: &str) -> Result<Vec<u8>, JsError> {
+        let id = self.reader.index.records.iter()
+            .find(|r| r.name == name)
+            .map(|r| r.id)
+            .ok_or_else(|| JsError::new(&format!("File not found: {name}")))?;
+        self.reader.unpack_file(id).map_err(js_err)
+    }
+}
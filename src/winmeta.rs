@@ -0,0 +1,132 @@
+//! Windows file metadata — attributes and creation time — carried in the
+//! per-file `metadata` map of the INDEX.
+//!
+//! # Keys
+//!
+//! | Key              | Value                                                  |
+//! |------------------|--------------------------------------------------------|
+//! | `win.attributes` | hex `u32` of READONLY / HIDDEN / SYSTEM / ARCHIVE bits |
+//! | `win.created`    | decimal FILETIME (100 ns ticks since 1601-01-01 UTC)   |
+//!
+//! Records are tagged rather than given a dedicated block type: the INDEX
+//! already stores a string map per file, every reader round-trips it, and
+//! readers that do not know these keys simply ignore them.
+//!
+//! On Windows every attribute and the creation time are captured and
+//! restored.  Elsewhere capture maps the read-only permission bit and the
+//! birth time (where the platform reports one), and restore applies only
+//! read-only — so archives move between systems without losing the tags.
+//!
+//! Alternate data streams are not captured.
+
+use std::collections::HashMap;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const KEY_ATTRIBUTES: &str = "win.attributes";
+pub const KEY_CREATED:    &str = "win.created";
+
+pub const ATTR_READONLY: u32 = 0x0001;
+pub const ATTR_HIDDEN:   u32 = 0x0002;
+pub const ATTR_SYSTEM:   u32 = 0x0004;
+pub const ATTR_ARCHIVE:  u32 = 0x0020;
+/// Attribute bits that are captured and restored; the rest are derived by
+/// the filesystem (directory, compressed, reparse point, …).
+pub const ATTR_MASK:     u32 = ATTR_READONLY | ATTR_HIDDEN | ATTR_SYSTEM | ATTR_ARCHIVE;
+
+/// FILETIME ticks between 1601-01-01 and the Unix epoch.
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
+/// Metadata tags for the file at `path`.
+pub fn capture(path: &Path) -> io::Result<HashMap<String, String>> {
+    let meta = std::fs::metadata(path)?;
+    let mut out = HashMap::new();
+    out.insert(KEY_ATTRIBUTES.to_owned(), format!("{:08x}", attributes(&meta)));
+    if let Ok(created) = meta.created() {
+        out.insert(KEY_CREATED.to_owned(), system_time_to_filetime(created).to_string());
+    }
+    Ok(out)
+}
+
+/// Apply tags produced by [`capture`] to the extracted file at `path`.
+/// Missing keys are skipped; malformed values fail with `InvalidData`.
+pub fn restore(path: &Path, meta: &HashMap<String, String>) -> io::Result<()> {
+    // Creation time first: a read-only file cannot be opened for writing.
+    if let Some(created) = meta.get(KEY_CREATED) {
+        let ticks = created.parse::<u64>().map_err(|_| invalid(KEY_CREATED, created))?;
+        set_created(path, filetime_to_system_time(ticks))?;
+    }
+    if let Some(attrs) = meta.get(KEY_ATTRIBUTES) {
+        let bits = u32::from_str_radix(attrs, 16).map_err(|_| invalid(KEY_ATTRIBUTES, attrs))?;
+        set_attributes(path, bits & ATTR_MASK)?;
+    }
+    Ok(())
+}
+
+pub fn system_time_to_filetime(t: SystemTime) -> u64 {
+    match t.duration_since(UNIX_EPOCH) {
+        Ok(d)  => FILETIME_UNIX_EPOCH + (d.as_nanos() / 100) as u64,
+        Err(e) => FILETIME_UNIX_EPOCH.saturating_sub((e.duration().as_nanos() / 100) as u64),
+    }
+}
+
+pub fn filetime_to_system_time(ticks: u64) -> SystemTime {
+    if ticks >= FILETIME_UNIX_EPOCH {
+        UNIX_EPOCH + Duration::from_nanos((ticks - FILETIME_UNIX_EPOCH) * 100)
+    } else {
+        UNIX_EPOCH - Duration::from_nanos((FILETIME_UNIX_EPOCH - ticks) * 100)
+    }
+}
+
+fn invalid(key: &str, value: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("malformed {key} value {value:?}"))
+}
+
+// ── Platform ──────────────────────────────────────────────────────────────────
+
+#[cfg(windows)]
+fn attributes(meta: &std::fs::Metadata) -> u32 {
+    use std::os::windows::fs::MetadataExt;
+    meta.file_attributes() & ATTR_MASK
+}
+
+#[cfg(not(windows))]
+fn attributes(meta: &std::fs::Metadata) -> u32 {
+    if meta.permissions().readonly() { ATTR_READONLY } else { 0 }
+}
+
+#[cfg(windows)]
+fn set_attributes(path: &Path, bits: u32) -> io::Result<()> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::{SetFileAttributesW, FILE_ATTRIBUTE_NORMAL};
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let bits = if bits == 0 { FILE_ATTRIBUTE_NORMAL } else { bits };
+    // SAFETY: `wide` is a NUL-terminated UTF-16 path that outlives the call.
+    if unsafe { SetFileAttributesW(wide.as_ptr(), bits) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn set_attributes(path: &Path, bits: u32) -> io::Result<()> {
+    let mut perms = std::fs::metadata(path)?.permissions();
+    perms.set_readonly(bits & ATTR_READONLY != 0);
+    std::fs::set_permissions(path, perms)
+}
+
+#[cfg(windows)]
+fn set_created(path: &Path, created: SystemTime) -> io::Result<()> {
+    use std::fs::{FileTimes, OpenOptions};
+    use std::os::windows::fs::FileTimesExt;
+
+    let file = OpenOptions::new().write(true).open(path)?;
+    file.set_times(FileTimes::new().set_created(created))
+}
+
+#[cfg(not(windows))]
+fn set_created(_path: &Path, _created: SystemTime) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Entry names that Windows cannot create, and long destination paths.
+//!
+//! Archive names are `/`-separated and may hold anything a Unix filesystem
+//! accepts.  Windows refuses, per component:
+//!
+//! - the characters `< > : " \ | ? *` and controls `0x00`–`0x1F`;
+//! - a trailing `.` or space (silently stripped by Win32, so two names
+//!   could collide);
+//! - the device names `CON`, `PRN`, `AUX`, `NUL`, `COM1`–`COM9`,
+//!   `LPT1`–`LPT9`, with or without an extension (`nul.txt` is `NUL`).
+//!
+//! [`windows_name`] rewrites such a name: invalid characters and trailing
+//! dots or spaces become `_`, and a device-name stem gets a `_` appended
+//! (`CON.txt` → `CON_.txt`).  Which of these
+//! [`Archive::extract_with`](crate::archive::Archive::extract_with) applies
+//! is [`ExtractOptions::windows_names`](crate::archive::ExtractOptions::windows_names).
+//!
+//! On Windows, destination paths of [`MAX_PATH`] characters or more are
+//! also given the `\\?\` verbatim prefix, which lifts the limit to about
+//! 32 767.
+
+use std::borrow::Cow;
+
+/// The classic Win32 path length limit, terminating NUL included.
+pub const MAX_PATH: usize = 260;
+
+const RESERVED: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+const NUMBERED: &[&str] = &["COM", "LPT"];
+
+/// True if Windows treats `component` as a device (`CON`, `lpt3.log`, …).
+pub fn is_reserved(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or(component).trim_end_matches(' ');
+    let upper = stem.to_ascii_uppercase();
+    RESERVED.contains(&upper.as_str())
+        || (upper.len() == 4
+            && NUMBERED.contains(&&upper[..3])
+            && matches!(upper.as_bytes()[3], b'1'..=b'9'))
+}
+
+fn is_invalid_char(c: char) -> bool {
+    matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*') || (c as u32) < 0x20
+}
+
+/// `component` made creatable on Windows; borrowed when already valid.
+pub fn windows_component(component: &str) -> Cow<'_, str> {
+    let trimmed = component.trim_end_matches(['.', ' ']);
+    let trailing = component.len() - trimmed.len();
+    if trailing == 0 && !component.contains(is_invalid_char) && !is_reserved(component) {
+        return Cow::Borrowed(component);
+    }
+    let mut out: String = trimmed.chars()
+        .map(|c| if is_invalid_char(c) { '_' } else { c })
+        .chain(std::iter::repeat_n('_', trailing))
+        .collect();
+    if is_reserved(&out) {
+        let stem = out.find('.').unwrap_or(out.len());
+        out.insert(stem, '_');
+    }
+    Cow::Owned(out)
+}
+
+/// `name` with every `/`-separated component made creatable on Windows,
+/// or `None` if it already is.
+pub fn windows_name(name: &str) -> Option<String> {
+    let parts: Vec<Cow<str>> = name.split('/').map(windows_component).collect();
+    if parts.iter().all(|p| matches!(p, Cow::Borrowed(_))) {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// `path` with the `\\?\` prefix when it is [`MAX_PATH`] characters or
+/// longer.  Verbatim paths skip Win32 normalisation, so the path is made
+/// absolute and `/` turned into `\` first.
+#[cfg(windows)]
+pub fn long_path(path: &std::path::Path) -> std::io::Result<std::path::PathBuf> {
+    let len = path.as_os_str().len();
+    if len < MAX_PATH || path.as_os_str().to_string_lossy().starts_with(r"\\?\") {
+        return Ok(path.to_owned());
+    }
+    let abs = std::path::absolute(path)?;
+    let abs = abs.to_string_lossy().replace('/', "\\");
+    Ok(match abs.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{unc}"),
+        None      => format!(r"\\?\{abs}"),
+    }.into())
+}
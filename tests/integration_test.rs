@@ -2,6 +2,7 @@ use sixcy::io_stream::SixCyWriter;
 use sixcy::CodecId;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom};
+use sixcy::block::{BlockHeader, decode_block};
 use tempfile::NamedTempFile;
 
 #[test]
@@ -24,12 +25,3792 @@ fn test_pack_and_list() {
         let sb = sixcy::superblock::Superblock::read(&mut file).unwrap();
         
         file.seek(SeekFrom::Start(sb.index_offset)).unwrap();
-        let mut index_bytes = Vec::new();
-        file.read_to_end(&mut index_bytes).unwrap();
-        
+        let header = BlockHeader::read(&mut file).unwrap();
+        let mut payload = vec![0u8; header.comp_size as usize];
+        file.read_exact(&mut payload).unwrap();
+        let index_bytes = decode_block(&header, &payload, None).unwrap();
+
         let index = sixcy::index::FileIndex::from_bytes(&index_bytes).unwrap();
         assert_eq!(index.records.len(), 1);
         assert_eq!(index.records[0].name, file_name);
         assert_eq!(index.records[0].original_size, test_data.len() as u64);
     }
 }
+
+#[test]
+fn test_incremental_snapshot_reuses_base_blocks() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let base_path = dir.path().join("base.6cy");
+    let snap_path = dir.path().join("snap.6cy");
+    let opts = PackOptions { chunk_size: 1024, ..PackOptions::default() };
+
+    let shared: Vec<u8> = (0..8192u32).map(|i| (i % 251) as u8).collect();
+    {
+        let mut ar = Archive::create(&base_path, opts.clone()).unwrap();
+        ar.add_file("shared.bin", &shared).unwrap();
+        ar.finalize().unwrap();
+    }
+    {
+        let base = Archive::open(&base_path).unwrap();
+        let mut ar = Archive::create_incremental(&snap_path, opts, &base).unwrap();
+        ar.add_file("shared.bin", &shared).unwrap();
+        ar.add_file("new.txt", b"only in the snapshot").unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let snap_len = std::fs::metadata(&snap_path).unwrap().len();
+    let base_len = std::fs::metadata(&base_path).unwrap().len();
+    assert!(snap_len < base_len, "snapshot must not store the shared blocks again");
+
+    let mut lone = Archive::open(&snap_path).unwrap();
+    assert_eq!(lone.read_file("new.txt").unwrap(), b"only in the snapshot");
+    assert!(lone.read_file("shared.bin").is_err());
+
+    let mut chained = Archive::open_chain(&snap_path, &[&base_path]).unwrap();
+    assert_eq!(chained.read_file("shared.bin").unwrap(), shared);
+}
+
+#[test]
+fn test_block_store_push_pull_shares_blocks() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::BlockStore;
+
+    let dir = tempfile::tempdir().unwrap();
+    let store = BlockStore::open(dir.path().join("store")).unwrap();
+    let opts = PackOptions { chunk_size: 1024, ..PackOptions::default() };
+    let common: Vec<u8> = (0..4096u32).map(|i| (i * 7 % 256) as u8).collect();
+
+    let mut uuids = Vec::new();
+    for (i, extra) in [b"image one".as_slice(), b"image two".as_slice()].iter().enumerate() {
+        let path = dir.path().join(format!("img{i}.6cy"));
+        let mut ar = Archive::create(&path, opts.clone()).unwrap();
+        ar.add_file("base.img", &common).unwrap();
+        ar.add_file("host.cfg", extra).unwrap();
+        ar.finalize().unwrap();
+        uuids.push(ar.uuid());
+
+        let report = store.push(&path).unwrap();
+        if i == 1 {
+            assert_eq!(report.blocks_new, 1, "only host.cfg should be new");
+        }
+    }
+
+    let pulled = dir.path().join("pulled.6cy");
+    store.pull(&uuids[1], &pulled).unwrap();
+    let mut ar = Archive::open(&pulled).unwrap();
+    assert_eq!(ar.uuid(), uuids[1]);
+    assert_eq!(ar.read_file("base.img").unwrap(), common);
+    assert_eq!(ar.read_file("host.cfg").unwrap(), b"image two");
+}
+
+#[test]
+fn test_delta_diff_and_apply() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let (old_p, new_p) = (dir.path().join("v1.6cy"), dir.path().join("v2.6cy"));
+    let (patch_p, out_p) = (dir.path().join("v1-v2.patch"), dir.path().join("v2b.6cy"));
+    let opts = PackOptions { chunk_size: 1024, ..PackOptions::default() };
+    let big: Vec<u8> = (0..16384u32).map(|i| (i % 253) as u8).collect();
+
+    {
+        let mut ar = Archive::create(&old_p, opts.clone()).unwrap();
+        ar.add_file("data.bin", &big).unwrap();
+        ar.add_file("version", b"1").unwrap();
+        ar.finalize().unwrap();
+    }
+    let mut changed = big.clone();
+    changed[0] = 0xFF;
+    {
+        let mut ar = Archive::create(&new_p, opts).unwrap();
+        ar.add_file("data.bin", &changed).unwrap();
+        ar.add_file("version", b"2").unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let patch = {
+        let mut old = Archive::open(&old_p).unwrap();
+        let mut new = Archive::open(&new_p).unwrap();
+        sixcy::delta::diff(&mut old, &mut new, &patch_p).unwrap()
+    };
+    assert_eq!(patch.blocks_copied, 2, "first chunk and version file changed");
+    assert!(patch.patch_bytes < std::fs::metadata(&new_p).unwrap().len());
+
+    {
+        let mut old = Archive::open(&old_p).unwrap();
+        let mut p = Archive::open(&patch_p).unwrap();
+        sixcy::delta::apply(&mut old, &mut p, &out_p).unwrap();
+    }
+    let mut rebuilt = Archive::open(&out_p).unwrap();
+    assert_eq!(rebuilt.uuid(), patch.target_uuid);
+    assert_eq!(rebuilt.read_file("data.bin").unwrap(), changed);
+    assert_eq!(rebuilt.read_file("version").unwrap(), b"2");
+    assert!(rebuilt.app_data_keys().is_empty());
+
+    // The patch refuses any base but the one it was built against.
+    let mut wrong = Archive::open(&new_p).unwrap();
+    let mut p = Archive::open(&patch_p).unwrap();
+    let err = sixcy::delta::apply(&mut wrong, &mut p, dir.path().join("bad.6cy")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn test_sync_sends_only_missing_blocks() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::BlockStore;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut remote = BlockStore::open(dir.path().join("remote")).unwrap();
+    let path = dir.path().join("a.6cy");
+    let opts = PackOptions { chunk_size: 512, ..PackOptions::default() };
+    let data: Vec<u8> = (0..4096u32).map(|i| (i % 241) as u8).collect();
+    {
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("a.bin", &data).unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let mut ar = Archive::open(&path).unwrap();
+    let first = sixcy::sync::push(&mut ar, &mut remote).unwrap();
+    assert!(!first.up_to_date);
+    assert_eq!(first.blocks_sent, first.blocks_total);
+
+    let second = sixcy::sync::push(&mut ar, &mut remote).unwrap();
+    assert!(second.up_to_date);
+    assert_eq!(second.blocks_sent, 0);
+}
+
+#[test]
+fn test_sync_over_stream_transport() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::sync::StreamTransport;
+    use std::process::{Command, Stdio};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.6cy");
+    {
+        let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+        ar.add_file("hello.txt", b"over the wire").unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_6cy"))
+        .args(["sync-serve", "--store"])
+        .arg(dir.path().join("remote"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let report = {
+        let mut t = StreamTransport::new(child.stdout.take().unwrap(), child.stdin.take().unwrap());
+        let mut ar = Archive::open(&path).unwrap();
+        sixcy::sync::push(&mut ar, &mut t).unwrap()
+    };
+    assert!(child.wait().unwrap().success());
+    assert_eq!(report.blocks_sent, 1);
+
+    let remote = sixcy::BlockStore::open(dir.path().join("remote")).unwrap();
+    let uuid = Archive::open(&path).unwrap().uuid();
+    assert_eq!(remote.manifest(&uuid).unwrap().index.records.len(), 1);
+}
+
+#[test]
+fn test_sync_over_http_transport() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::sync::HttpTransport;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.6cy");
+    {
+        let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+        ar.add_file("hello.txt", b"over http").unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let mut store = sixcy::BlockStore::open(dir.path().join("remote")).unwrap();
+    std::thread::spawn(move || sixcy::sync::serve_http(&mut store, listener));
+
+    let mut t = HttpTransport::new(&url).unwrap();
+    let mut ar = Archive::open(&path).unwrap();
+    let first = sixcy::sync::push(&mut ar, &mut t).unwrap();
+    assert_eq!(first.blocks_sent, 1);
+    assert!(sixcy::sync::push(&mut ar, &mut t).unwrap().up_to_date);
+}
+
+#[test]
+fn test_block_store_rejects_block_not_matching_its_hash() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::io_stream::SixCyReader;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.6cy");
+    let opts = PackOptions { default_codec: sixcy::CodecId::None, ..PackOptions::default() };
+    {
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("a.txt", b"genuine contents").unwrap();
+        ar.finalize().unwrap();
+    }
+    let mut reader = SixCyReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+    let offset = reader.local_block_offsets()[0];
+    let (header, mut payload) = reader.read_block_at(offset).unwrap();
+    payload[0] ^= 0xFF;
+
+    let store = sixcy::BlockStore::open(dir.path().join("store")).unwrap();
+    let err = store.put(&header, &payload).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    assert!(!store.contains(&header.content_hash));
+}
+
+#[test]
+fn test_sync_serve_rejects_oversized_frame() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut store = sixcy::BlockStore::open(dir.path().join("remote")).unwrap();
+    let mut request = vec![2u8];
+    request.extend_from_slice(&u32::MAX.to_le_bytes());
+    let err = sixcy::sync::serve(&mut store, request.as_slice(), std::io::sink()).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_core_reader_walks_stored_blocks() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::core_reader::{self, CoreError};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fw.6cy");
+    let opts = PackOptions { default_codec: CodecId::None, chunk_size: 1000, ..PackOptions::default() };
+    let image: Vec<u8> = (0..2500u32).map(|i| (i % 199) as u8).collect();
+    {
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("fw.bin", &image).unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let bytes = std::fs::read(&path).unwrap();
+    let sb = core_reader::parse_superblock(&bytes).unwrap();
+    assert_eq!(sb.archive_uuid, *Archive::open(&path).unwrap().uuid().as_bytes());
+
+    let mut rebuilt = vec![0u8; image.len()];
+    let mut saw_index = false;
+    for block in core_reader::blocks(&bytes) {
+        let (_, header, payload) = block.unwrap();
+        if header.block_type == core_reader::BLOCK_TYPE_INDEX {
+            saw_index = true;
+            assert_eq!(core_reader::verify_stored(&header, payload), Err(CoreError::NotStored));
+            continue;
+        }
+        let data = core_reader::verify_stored(&header, payload).unwrap();
+        let at = header.file_offset as usize;
+        rebuilt[at..at + data.len()].copy_from_slice(data);
+    }
+    assert!(saw_index);
+    assert_eq!(rebuilt, image);
+
+    let mut corrupt = bytes.clone();
+    corrupt[core_reader::SUPERBLOCK_SIZE + 20] ^= 0xFF;
+    assert!(matches!(core_reader::blocks(&corrupt).next(),
+        Some(Err(CoreError::BlockCrc32Mismatch { .. }))));
+}
+
+#[test]
+fn test_deterministic_pack_is_byte_identical() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let data: Vec<u8> = (0..9000u32).map(|i| (i % 251) as u8).collect();
+    let pack = |name: &str, opts: PackOptions| {
+        let path = dir.path().join(name);
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("a.bin", &data).unwrap();
+        ar.begin_solid(CodecId::Zstd).unwrap();
+        ar.add_file("b.txt", b"solid").unwrap();
+        ar.end_solid().unwrap();
+        ar.finalize().unwrap();
+        std::fs::read(path).unwrap()
+    };
+
+    let opts = PackOptions { chunk_size: 2048, deterministic: true, ..PackOptions::default() };
+    let first = pack("1.6cy", opts.clone());
+    assert_eq!(first, pack("2.6cy", opts.clone()));
+    assert!(!Archive::open(dir.path().join("1.6cy")).unwrap().uuid().is_nil());
+
+    let fixed = uuid::Uuid::from_bytes([7u8; 16]);
+    let enc = PackOptions { password: Some("pw".into()), fixed_uuid: Some(fixed), ..opts.clone() };
+    assert_eq!(pack("3.6cy", enc.clone()), pack("4.6cy", enc));
+    let mut ar = Archive::open_encrypted(dir.path().join("3.6cy"), "pw").unwrap();
+    assert_eq!(ar.uuid(), fixed);
+    assert_eq!(ar.read_file("a.bin").unwrap(), data);
+
+    let no_uuid = PackOptions { password: Some("pw".into()), ..opts };
+    assert!(Archive::create(dir.path().join("5.6cy"), no_uuid).is_err());
+}
+
+#[test]
+fn test_resource_limits_reject_bombs() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::block::{encode_block, BlockType};
+    use sixcy::Limits;
+
+    // A block whose header understates orig_size must not be inflated.
+    let bomb = vec![0u8; 8 * 1024 * 1024];
+    for codec in [CodecId::Zstd, CodecId::Lz4, CodecId::Brotli, CodecId::Lzma, CodecId::None] {
+        let (mut header, payload) = encode_block(BlockType::Data, 0, 0, &bomb, codec, 1, None).unwrap();
+        header.orig_size = 1024;
+        assert!(decode_block(&header, &payload, None).is_err(), "{codec:?}");
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("l.6cy");
+    {
+        let opts = PackOptions { chunk_size: 4096, ..PackOptions::default() };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("a", &[1u8; 10_000]).unwrap();
+        ar.add_file("b", &[2u8; 10_000]).unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let few = Limits { max_records: 1, ..Limits::default() };
+    let err = Archive::open_untrusted(&path, None, few).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let small = Limits { max_block_size: 1024, ..Limits::default() };
+    let mut ar = Archive::open_untrusted(&path, None, small).unwrap();
+    assert!(ar.read_file("a").is_err());
+
+    let total = Limits { max_total_decompressed: 15_000, ..Limits::untrusted() };
+    let mut ar = Archive::open_untrusted(&path, None, total).unwrap();
+    assert_eq!(ar.read_file("a").unwrap().len(), 10_000);
+    assert!(ar.read_file("b").is_err());
+}
+
+#[test]
+fn test_codec_window_limits() {
+    use sixcy::block::{decode_block_limited, encode_block, BlockType};
+    use sixcy::codec::{declared_window, CodecError};
+    use sixcy::Limits;
+
+    // Large-window Brotli header asking for 1 GiB, and a 3 GiB LZMA dictionary.
+    assert_eq!(declared_window(CodecId::Brotli, &[0x11, 30]), Some(1 << 30));
+    assert_eq!(declared_window(CodecId::Lzma, &[0x5d, 0, 0, 0, 0xC0]), Some(3 << 30));
+    assert_eq!(declared_window(CodecId::Zstd, &[0x28, 0xb5, 0x2f, 0xfd]), None);
+
+    let data = b"window limits ".repeat(1000);
+    for codec in [CodecId::Brotli, CodecId::Lzma] {
+        let (header, payload) = encode_block(BlockType::Data, 0, 0, &data, codec, 5, None).unwrap();
+        let window = declared_window(codec, &payload).unwrap();
+        assert!(window >= 1 << 16, "{codec:?}");
+
+        let ok = decode_block_limited(&header, &payload, None, &Limits::untrusted()).unwrap();
+        assert_eq!(ok, data);
+
+        let tight = Limits { max_brotli_window: window - 1, max_lzma_dict: window - 1, ..Limits::default() };
+        match decode_block_limited(&header, &payload, None, &tight) {
+            Err(CodecError::LimitExceeded(e)) => assert_eq!(e.value, window),
+            other => panic!("{codec:?}: expected LimitExceeded, got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_error_variants_are_matchable() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::{CryptoError, SixcyError};
+    use sixcy::superblock::SuperblockError;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("e.6cy");
+    {
+        let opts = PackOptions { password: Some("right".into()), ..PackOptions::default() };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("x", b"secret").unwrap();
+        ar.finalize().unwrap();
+    }
+
+    assert!(matches!(Archive::open_encrypted(&path, "wrong"), Err(SixcyError::Crypto(CryptoError::WrongPassword))));
+    let mut ar = Archive::open(&path).unwrap();
+    assert!(matches!(ar.read_file("x"), Err(SixcyError::Crypto(CryptoError::MissingKey))));
+    assert_eq!(ar.read_file("y").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+
+    let junk = dir.path().join("junk.6cy");
+    std::fs::write(&junk, [0u8; 512]).unwrap();
+    assert!(matches!(Archive::open(&junk), Err(SixcyError::Superblock(SuperblockError::InvalidMagic))));
+}
+
+#[test]
+fn test_auto_solid_groups_small_files_by_extension() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::io_stream::AutoSolid;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("s.6cy");
+    let big: Vec<u8> = (0..50_000u32).map(|i| (i % 251) as u8).collect();
+    let policy = AutoSolid { threshold: 1000, block_size: 1500 };
+    {
+        let opts = PackOptions { auto_solid: Some(policy), ..PackOptions::default() };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("b.txt", &[b'b'; 600]).unwrap();
+        ar.add_file("big.bin", &big).unwrap();
+        ar.add_file("a.txt", &[b'a'; 600]).unwrap();
+        ar.add_file("x.rs", b"fn main() {}").unwrap();
+        ar.add_file("c.txt", &[b'c'; 600]).unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let mut ar = Archive::open(&path).unwrap();
+    let files = ar.list();
+    let names: Vec<&str> = files.iter().map(|f| f.name.as_str()).collect();
+    // big.bin is chunked immediately; the .txt group fills at 1800 B and is
+    // flushed name-sorted; .rs waits for finalize.
+    assert_eq!(names, ["big.bin", "a.txt", "b.txt", "c.txt", "x.rs"]);
+
+    // Members of one solid block split its size by length: the three
+    // 600-byte .txt members are charged equal shares.
+    let solid_size = files[1].compressed_size;
+    assert!(files[1..4].iter().all(|f| f.compressed_size == solid_size));
+    assert_ne!(files[4].compressed_size, solid_size);
+    for f in &files {
+        let want: Vec<u8> = match f.name.as_str() {
+            "big.bin" => big.clone(),
+            "x.rs"    => b"fn main() {}".to_vec(),
+            n         => vec![n.as_bytes()[0]; 600],
+        };
+        assert_eq!(ar.read_file(&f.name).unwrap(), want);
+    }
+}
+
+#[test]
+fn test_optimize_preserves_index_and_encryption() {
+    use sixcy::archive::Archive;
+    use sixcy::crypto::derive_key;
+    use sixcy::io_stream::SixCyReader;
+    use sixcy::optimize::{optimize, CodecPolicy, OptimizeOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let src_path = dir.path().join("src.6cy");
+    let dst_path = dir.path().join("dst.6cy");
+    let log: Vec<u8> = (0..50_000u32).flat_map(|i| format!("line {i}\n").into_bytes()).collect();
+    {
+        let mut w = SixCyWriter::with_options(File::create(&src_path).unwrap(), 64 * 1024, 1, None).unwrap();
+        w.encryption_key = Some(derive_key("pw", w.superblock.archive_uuid.as_bytes()).unwrap());
+        w.add_file("big.log".into(), &log, CodecId::Zstd).unwrap();
+        w.start_solid_session(CodecId::Zstd).unwrap();
+        w.add_file("a.txt".into(), b"alpha", CodecId::Zstd).unwrap();
+        w.add_file("b.txt".into(), b"bravo", CodecId::Zstd).unwrap();
+        w.flush_solid_session().unwrap();
+        w.index.records[0].metadata.insert("mode".into(), "0644".into());
+        w.finalize().unwrap();
+    }
+
+    let opts = OptimizeOptions {
+        level:    19,
+        policies: vec![CodecPolicy::parse("*.log=brotli:9", 19).unwrap()],
+        ..OptimizeOptions::default()
+    };
+    let mut src = Archive::open_encrypted(&src_path, "pw").unwrap();
+    let report = optimize(&mut src, &dst_path, &opts).unwrap();
+    assert_eq!(report.files, 3);
+    assert!(report.payload_after < report.payload_before, "{report:?}");
+
+    // Same password, same UUID, same records.
+    assert!(Archive::open(&dst_path).unwrap().read_file("a.txt").is_err());
+    let mut dst = Archive::open_encrypted(&dst_path, "pw").unwrap();
+    let before: Vec<_> = src.list().into_iter().map(|f| (f.id, f.name)).collect();
+    let after:  Vec<_> = dst.list().into_iter().map(|f| (f.id, f.name)).collect();
+    assert_eq!(before, after);
+    assert_eq!(dst.read_file("big.log").unwrap(), log);
+    assert_eq!(dst.read_file("a.txt").unwrap(), b"alpha");
+    assert_eq!(dst.read_file("b.txt").unwrap(), b"bravo");
+
+    let reader = SixCyReader::new(File::open(&dst_path).unwrap()).unwrap();
+    assert_eq!(reader.index.records[0].metadata["mode"], "0644");
+}
+
+#[test]
+fn test_encrypt_and_decrypt_without_recompressing() {
+    use sixcy::archive::Archive;
+    use sixcy::convert;
+    use sixcy::crypto::{NONCE_LEN, TAG_LEN};
+    use sixcy::error::SixcyError;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path  = dir.path().join("a.6cy");
+    let plain = dir.path().join("plain.6cy");
+    let log: Vec<u8> = (0..50_000u32).flat_map(|i| format!("line {i}\n").into_bytes()).collect();
+    let uuid = {
+        let mut w = SixCyWriter::with_options(File::create(&path).unwrap(), 64 * 1024, 1, None).unwrap();
+        w.add_file("big.log".into(), &log, CodecId::Zstd).unwrap();
+        w.add_file("copy.log".into(), &log, CodecId::Zstd).unwrap();
+        w.start_solid_session(CodecId::Zstd).unwrap();
+        w.add_file("a.txt".into(), b"alpha", CodecId::Zstd).unwrap();
+        w.add_file("b.txt".into(), b"bravo", CodecId::Zstd).unwrap();
+        w.flush_solid_session().unwrap();
+        let uuid = w.superblock.archive_uuid;
+        w.finalize().unwrap();
+        uuid
+    };
+
+    // In place: each shared block is sealed once and grows by nonce + tag.
+    let mut src = Archive::open(&path).unwrap();
+    let report = convert::encrypt(&mut src, &path, "pw").unwrap();
+    drop(src);
+    assert_eq!(report.files, 4);
+    assert_eq!(report.payload_after - report.payload_before,
+               report.blocks as u64 * (NONCE_LEN + TAG_LEN) as u64);
+    assert!(Archive::open(&path).unwrap().read_file("a.txt").is_err());
+    let mut enc = Archive::open_encrypted(&path, "pw").unwrap();
+    assert_eq!(enc.uuid(), uuid);
+    assert_eq!(enc.read_file("copy.log").unwrap(), log);
+    assert_eq!(enc.read_file("b.txt").unwrap(), b"bravo");
+    match convert::encrypt(&mut enc, &plain, "pw") {
+        Err(SixcyError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput),
+        other                  => panic!("expected InvalidInput, got {other:?}"),
+    }
+
+    let back = convert::decrypt(&mut enc, &plain).unwrap();
+    assert_eq!(back.payload_after, report.payload_before);
+    let mut dec = Archive::open(&plain).unwrap();
+    assert_eq!(dec.read_file("big.log").unwrap(), log);
+    assert_eq!(dec.read_file("a.txt").unwrap(), b"alpha");
+    assert!(convert::decrypt(&mut dec, dir.path().join("x.6cy")).is_err());
+}
+
+#[test]
+fn test_name_policies() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::error::SixcyError;
+    use sixcy::names::NamePolicy;
+
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    std::fs::create_dir_all(src.join("docs")).unwrap();
+    std::fs::write(src.join("README"), b"upper").unwrap();
+    std::fs::write(src.join("readme"), b"lower").unwrap();
+    std::fs::write(src.join("docs/cafe\u{301}.txt"), b"nfd").unwrap();
+    #[cfg(unix)]
+    let latin1 = {
+        use std::os::unix::ffi::OsStrExt;
+        let name = std::ffi::OsStr::from_bytes(b"latin1-\xe9.txt").to_owned();
+        std::fs::write(src.join(&name), b"raw").unwrap();
+        name
+    };
+    let pack = |name: &str, policy| {
+        let path = dir.path().join(name);
+        Archive::pack_dir(&src, &path, PackOptions { name_policy: policy, ..PackOptions::default() })
+            .map(|_| Archive::open(&path).unwrap())
+    };
+    let names = |ar: &Archive| ar.list().into_iter().map(|f| f.name).collect::<Vec<_>>();
+
+    // Raw: names as given, the non-UTF-8 one lossy with its bytes kept.
+    let mut raw = pack("raw.6cy", NamePolicy::Raw).unwrap();
+    assert_eq!(raw.name_policy(), Some(NamePolicy::Raw));
+    assert!(names(&raw).contains(&"docs/cafe\u{301}.txt".to_owned()));
+    assert!(names(&raw).contains(&"readme".to_owned()));
+    #[cfg(unix)]
+    {
+        assert!(names(&raw).contains(&"latin1-\u{FFFD}.txt".to_owned()));
+        let out = dir.path().join("out");
+        raw.extract_with(&out, &Default::default()).unwrap();
+        assert_eq!(std::fs::read(out.join(&latin1)).unwrap(), b"raw");
+    }
+
+    // Normalize: NFC, and the later case twin numbered.
+    let norm = pack("norm.6cy", NamePolicy::Normalize).unwrap();
+    assert_eq!(norm.name_policy(), Some(NamePolicy::Normalize));
+    let listed = names(&norm);
+    assert!(listed.contains(&"docs/caf\u{e9}.txt".to_owned()), "{listed:?}");
+    assert!(listed.contains(&"README".to_owned()) && listed.contains(&"readme (1)".to_owned()), "{listed:?}");
+
+    // Reject: any of those names fails the pack.
+    match pack("reject.6cy", NamePolicy::Reject) {
+        Err(SixcyError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput),
+        other                  => panic!("expected InvalidInput, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn test_observer_events() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::events::{BlockEvent, DedupEvent, ErrorEvent, FileEvent, Observer};
+    use sixcy::names::NamePolicy;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default)]
+    struct Log(Mutex<Vec<String>>);
+    impl Log {
+        fn push(&self, event: String) { self.0.lock().unwrap().push(event); }
+        fn take(&self) -> Vec<String> { std::mem::take(&mut self.0.lock().unwrap()) }
+    }
+    impl Observer for Log {
+        fn on_file_start(&self, f: &FileEvent)     { self.push(format!("start {} {}", f.name, f.size)); }
+        fn on_block_written(&self, b: &BlockEvent) { self.push(format!("block {:?}", b.block_type)); }
+        fn on_dedup_hit(&self, h: &DedupEvent)     { self.push(format!("dedup {:?} {}", h.source, h.bytes)); }
+        fn on_file_done(&self, f: &FileEvent)      { self.push(format!("done {} {}", f.name, f.size)); }
+        fn on_error(&self, e: &ErrorEvent)         { self.push(format!("error {:?}", e.name)); }
+    }
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.6cy");
+    let log  = Arc::new(Log::default());
+    let opts = PackOptions {
+        chunk_size:  4,
+        name_policy: NamePolicy::Reject,
+        observer:    Some(log.clone()),
+        ..PackOptions::default()
+    };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("a.bin", b"abcdabcd").unwrap();
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    ar.add_file("b.txt", b"same").unwrap();
+    ar.add_file("c.txt", b"same").unwrap();
+    ar.end_solid().unwrap();
+    assert!(ar.add_file("A.BIN", b"twin").is_err());
+    ar.finalize().unwrap();
+    assert_eq!(log.take(), [
+        "start a.bin 8", "block Data", "dedup Block 4", "done a.bin 8",
+        "start b.txt 4", "done b.txt 4",
+        "start c.txt 4", "dedup Solid 4", "done c.txt 4",
+        "block Solid",
+        "error Some(\"A.BIN\")",
+        "block Index",
+    ]);
+
+    let mut ar = Archive::open(&path).unwrap();
+    ar.set_observer(Some(log.clone()));
+    ar.read_file("c.txt").unwrap();
+    assert_eq!(log.take(), ["start c.txt 4", "done c.txt 4"]);
+}
+
+#[test]
+fn test_app_data_outside_file_namespace() {
+    use sixcy::archive::{Archive, MergeConflict, PackOptions};
+    use sixcy::convert;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("app.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    ar.add_file("level1.bin", &[7u8; 4096]).unwrap();
+    ar.put_app_data("manifest", b"v1").unwrap();
+    ar.put_app_data("manifest", b"{\"version\": 2}").unwrap();
+    ar.put_app_data("build", b"1234").unwrap();
+    assert!(ar.put_app_data("", b"x").is_err());
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open(&path).unwrap();
+    assert_eq!(ar.app_data_keys(), ["build", "manifest"]);
+    assert_eq!(ar.get_app_data("manifest").unwrap().unwrap(), b"{\"version\": 2}");
+    assert_eq!(ar.get_app_data("missing").unwrap(), None);
+    assert_eq!(ar.list().len(), 1);
+    assert!(ar.health_check(1.0).unwrap().is_healthy());
+
+    // Tools that move blocks carry it along.
+    let enc = dir.path().join("enc.6cy");
+    convert::encrypt(&mut ar, &enc, "pw").unwrap();
+    let mut enc = Archive::open_encrypted(&enc, "pw").unwrap();
+    assert_eq!(enc.get_app_data("build").unwrap().unwrap(), b"1234");
+
+    let merged = dir.path().join("merged.6cy");
+    let mut sources = [Archive::open(&path).unwrap()];
+    Archive::merge(&mut sources, &merged, PackOptions::default(), MergeConflict::Error).unwrap();
+    let mut merged = Archive::open(&merged).unwrap();
+    assert_eq!(merged.get_app_data("manifest").unwrap().unwrap(), b"{\"version\": 2}");
+}
+
+#[test]
+fn test_bundle_reader_random_access() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::bundle::BundleReader;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("assets.6cy");
+    let big: Vec<u8> = (0..5000u32).map(|i| (i * 31 % 251) as u8).collect();
+    let mut ar = Archive::create(&path, PackOptions { chunk_size: 1024, ..PackOptions::default() }).unwrap();
+    ar.add_file("music/theme.ogg", &big).unwrap();
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    for i in 0..20 {
+        ar.add_file(&format!("sprites/{i:02}.png"), format!("sprite {i}").as_bytes()).unwrap();
+    }
+    ar.add_file("empty", b"").unwrap();
+    ar.end_solid().unwrap();
+    ar.finalize().unwrap();
+
+    let bundle = BundleReader::open(&path).unwrap();
+    assert_eq!(bundle.len(), 22);
+    assert!(bundle.open_at("missing").is_none());
+
+    let theme = bundle.open_at("music/theme.ogg").unwrap();
+    assert_eq!(theme.len(), 5000);
+    let mut buf = [0u8; 100];
+    assert_eq!(theme.read_at(1000, &mut buf).unwrap(), 100);
+    assert_eq!(&buf[..], &big[1000..1100]);
+    assert_eq!(theme.read_at(4950, &mut buf).unwrap(), 50);
+    let mut out = Vec::new();
+    theme.read_into(&mut out).unwrap();
+    assert_eq!(out, big);
+    bundle.open_at("empty").unwrap().read_into(&mut out).unwrap();
+    assert!(out.is_empty());
+
+    // One reader shared by loader threads; the SOLID block decodes once.
+    std::thread::scope(|s| for t in 0..4 {
+        let bundle = &bundle;
+        s.spawn(move || {
+            let mut out = Vec::new();
+            for i in (t..20).step_by(4) {
+                bundle.open_at(&format!("sprites/{i:02}.png")).unwrap().read_into(&mut out).unwrap();
+                assert_eq!(out, format!("sprite {i}").as_bytes());
+            }
+        });
+    });
+    let stats = bundle.cache_stats();
+    assert!(stats.hits >= 15, "{stats:?}");
+
+    // Without a cache every read decodes.
+    let uncached = BundleReader::open(&path).unwrap().with_cache_bytes(0);
+    uncached.open_at("sprites/03.png").unwrap().read_into(&mut out).unwrap();
+    assert_eq!(out, b"sprite 3");
+    assert_eq!(uncached.cache_stats().bytes, 0);
+}
+
+#[test]
+fn test_danger_skip_content_hash() {
+    use sixcy::archive::{Archive, ExtractOptions, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("trusted.6cy");
+    let body = b"payload that the consumer checksums on its own".repeat(20);
+    let mut ar = Archive::create(&path, PackOptions { default_codec: CodecId::None, ..PackOptions::default() }).unwrap();
+    ar.add_file("a.bin", &body).unwrap();
+    ar.finalize().unwrap();
+
+    // Round trip with the checks off.
+    let mut ar = Archive::open(&path).unwrap();
+    ar.danger_skip_content_hash(true).unwrap();
+    assert_eq!(ar.read_file("a.bin").unwrap(), body);
+
+    // Flip one stored byte: caught by default, passed through when skipped.
+    let mut bytes = std::fs::read(&path).unwrap();
+    let at = bytes.windows(body.len()).position(|w| w == &body[..]).unwrap();
+    bytes[at] ^= 0x20;
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(Archive::open(&path).unwrap().read_file("a.bin").is_err());
+
+    let mut ar = Archive::open(&path).unwrap();
+    let opts = ExtractOptions { danger_skip_content_hash: true, ..ExtractOptions::default() };
+    ar.extract_with(dir.path().join("out"), &opts).unwrap();
+    let out = std::fs::read(dir.path().join("out/a.bin")).unwrap();
+    assert_eq!(out.len(), body.len());
+    assert_ne!(out, body);
+
+    // `paranoid` overrides the skip.
+    let paranoid = ExtractOptions { paranoid: true, ..opts };
+    assert!(ar.extract_with(dir.path().join("checked"), &paranoid).is_err());
+}
+
+#[test]
+fn test_entry_order_is_stable() {
+    use sixcy::archive::{Archive, ExtractOptions, PackOptions};
+    use sixcy::EntryOrder;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("order.6cy");
+    let added = ["zeta.txt", "alpha/b.txt", "mid.txt", "alpha/a.txt"];
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    for name in added {
+        ar.add_file(name, name.as_bytes()).unwrap();
+    }
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open(&path).unwrap();
+    let names = |infos: Vec<sixcy::FileInfo>| infos.into_iter().map(|i| i.name).collect::<Vec<_>>();
+    assert_eq!(names(ar.list()), added);
+    let mut sorted = added.to_vec();
+    sorted.sort();
+    assert_eq!(names(ar.entries_in(EntryOrder::Name).collect()), sorted);
+    let detailed: Vec<_> = ar.entries_detailed_in(EntryOrder::Name).map(|i| i.unwrap().name).collect();
+    assert_eq!(detailed, sorted);
+
+    // An index rebuilt from the blocks comes back in the same order.
+    let scanned = sixcy::io_stream::SixCyReader::new(std::fs::File::open(&path).unwrap()).unwrap().scan_blocks().unwrap();
+    let ids: Vec<u32> = scanned.records.iter().map(|r| r.id).collect();
+    assert_eq!(ids, [0, 1, 2, 3]);
+
+    for (order, expected) in [(EntryOrder::Index, added.to_vec()), (EntryOrder::Name, sorted)] {
+        let mut seen = Vec::new();
+        let opts = ExtractOptions { order, ..ExtractOptions::default() };
+        ar.extract_with_progress(dir.path().join(format!("{order:?}")), &opts, |p| {
+            seen.extend(p.current.map(str::to_owned));
+        }).unwrap();
+        assert_eq!(seen, expected);
+    }
+}
+
+#[test]
+fn test_archive_set_shadows_earlier_names() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::archive_set::ArchiveSet;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let base = dir.path().join("base.6cy");
+    let snap = dir.path().join("snap.6cy");
+    let kept: Vec<u8> = (0..200_000u32).map(|i| (i % 241) as u8).collect();
+    let mut ar = Archive::create(&base, PackOptions::default()).unwrap();
+    ar.add_file("kept.bin", &kept).unwrap();
+    ar.add_file("conf.txt", b"v1").unwrap();
+    ar.finalize().unwrap();
+
+    // The snapshot re-adds `kept.bin` by reference into the base.
+    let base_ar = Archive::open(&base).unwrap();
+    let mut ar = Archive::create_incremental(&snap, PackOptions::default(), &base_ar).unwrap();
+    ar.add_file("copy.bin", &kept).unwrap();
+    ar.add_file("conf.txt", b"v2").unwrap();
+    ar.add_file("new.txt", b"new").unwrap();
+    ar.finalize().unwrap();
+
+    let mut set = ArchiveSet::open(&[&base, &snap]).unwrap();
+    let names: Vec<&str> = set.entries().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, ["conf.txt", "copy.bin", "kept.bin", "new.txt"]);
+    assert_eq!(set.source("conf.txt"), Some(1));
+    assert_eq!(set.source("kept.bin"), Some(0));
+    assert_eq!(set.read_file("conf.txt").unwrap(), b"v2");
+    assert_eq!(set.read_file("copy.bin").unwrap(), kept);
+    let mut buf = [0u8; 4];
+    assert_eq!(set.read_at("new.txt", 1, &mut buf).unwrap(), 2);
+    assert_eq!(&buf[..2], b"ew");
+    assert!(set.read_file("missing").is_err());
+
+    // Reversed, the base's version wins.
+    let mut set = ArchiveSet::open(&[&snap, &base]).unwrap();
+    assert_eq!(set.read_file("conf.txt").unwrap(), b"v1");
+    assert_eq!(set.read_file("copy.bin").unwrap(), kept);
+}
+
+#[test]
+fn test_iter_block_headers() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("blocks.6cy");
+    let big: Vec<u8> = (0..150_000u32).map(|i| (i % 199) as u8).collect();
+    let mut ar = Archive::create(&path, PackOptions { chunk_size: 64 * 1024, ..PackOptions::default() }).unwrap();
+    ar.add_file("big.bin", &big).unwrap();
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    ar.add_file("s1", b"one").unwrap();
+    ar.add_file("s2", b"two").unwrap();
+    ar.end_solid().unwrap();
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open(&path).unwrap();
+    let blocks: Vec<(u64, BlockHeader)> = ar.iter_block_headers().unwrap().map(Result::unwrap).collect();
+    let types: Vec<&str> = blocks.iter().map(|(_, h)| h.block_type.name()).collect();
+    assert_eq!(types, ["DATA", "DATA", "DATA", "SOLID", "INDEX"]);
+    assert_eq!(blocks[0].0, 256);
+    for pair in blocks.windows(2) {
+        let (offset, h) = &pair[0];
+        assert_eq!(pair[1].0, offset + sixcy::block::BLOCK_HEADER_SIZE as u64 + h.comp_size as u64);
+    }
+    let offsets: Vec<u64> = blocks.iter().map(|(offset, _)| *offset).collect();
+    for info in ar.file_blocks("big.bin").unwrap() {
+        assert!(offsets.contains(&info.offset));
+    }
+    assert_eq!(blocks[1].1.file_offset, 64 * 1024);
+
+    // A corrupt header is reported and ends the walk.
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[blocks[2].0 as usize + 10] ^= 0xff;
+    std::fs::write(&path, &bytes).unwrap();
+    let mut ar = Archive::open(&path).unwrap();
+    let walked: Vec<_> = ar.iter_block_headers().unwrap().collect();
+    assert_eq!(walked.len(), 3);
+    assert!(walked[2].is_err());
+}
+
+#[test]
+fn test_pack_reports_duplicate_files() {
+    use sixcy::archive::{Archive, DuplicateFile, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    std::fs::create_dir_all(src.join("sub")).unwrap();
+    let a: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    let b: Vec<u8> = (0..100_000u32).map(|i| (i % 239) as u8).collect();
+    std::fs::write(src.join("a.bin"), &a).unwrap();
+    std::fs::write(src.join("b.bin"), &b).unwrap();
+    std::fs::write(src.join("sub/a-copy.bin"), &a).unwrap();
+    std::fs::write(src.join("empty"), b"").unwrap();
+    std::fs::write(src.join("empty2"), b"").unwrap();
+
+    let opts   = PackOptions { chunk_size: 64 * 1024, ..PackOptions::default() };
+    let report = Archive::pack_dir(&src, dir.path().join("d.6cy"), opts.clone()).unwrap();
+    assert_eq!(report.duplicates, [DuplicateFile {
+        name:    "sub/a-copy.bin".into(),
+        size:    a.len() as u64,
+        same_as: Some("a.bin".into()),
+    }]);
+
+    // Chunks from two files make a duplicate of neither.
+    let mut ar = Archive::create(dir.path().join("m.6cy"), opts).unwrap();
+    ar.add_file("a", &a).unwrap();
+    ar.add_file("b", &b).unwrap();
+    ar.add_file("mix", &[&a[..64 * 1024], &b[..64 * 1024]].concat()).unwrap();
+    ar.add_file("partial", &a[..64 * 1024 + 1]).unwrap();
+    let dups = ar.duplicate_files();
+    assert_eq!(dups.len(), 1);
+    assert_eq!((dups[0].name.as_str(), dups[0].same_as.as_deref()), ("mix", None));
+    ar.finalize().unwrap();
+}
+
+#[test]
+fn test_extract_resume_keeps_finished_files() {
+    use sixcy::archive::{Archive, ExtractOptions, OverwritePolicy, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("r.6cy");
+    let big: Vec<u8> = (0..200_000u32).map(|i| (i % 233) as u8).collect();
+    let mut ar = Archive::create(&path, PackOptions { chunk_size: 64 * 1024, ..PackOptions::default() }).unwrap();
+    ar.add_file("big.bin", &big).unwrap();
+    ar.add_file("small.txt", b"small").unwrap();
+    ar.add_file("same-size.txt", b"12345").unwrap();
+    ar.add_file("missing.txt", b"gone").unwrap();
+    ar.finalize().unwrap();
+
+    let out = dir.path().join("out");
+    let mut ar = Archive::open(&path).unwrap();
+    ar.extract_all(&out).unwrap();
+    // An interrupted run: one file cut short, one garbled, one never written.
+    std::fs::write(out.join("big.bin"), &big[..100_000]).unwrap();
+    std::fs::write(out.join("same-size.txt"), b"54321").unwrap();
+    std::fs::remove_file(out.join("missing.txt")).unwrap();
+
+    let opts   = ExtractOptions { overwrite: OverwritePolicy::Resume, paranoid: true, ..ExtractOptions::default() };
+    let report = ar.extract_with(&out, &opts).unwrap();
+    assert_eq!((report.files, report.unchanged, report.skipped), (4, 1, 0));
+    assert!(report.verified_root.is_some());
+    assert_eq!(std::fs::read(out.join("big.bin")).unwrap(), big);
+    assert_eq!(std::fs::read(out.join("same-size.txt")).unwrap(), b"12345");
+    assert_eq!(std::fs::read(out.join("missing.txt")).unwrap(), b"gone");
+
+    // Once complete, a re-run writes nothing.
+    let report = ar.extract_with(&out, &opts).unwrap();
+    assert_eq!(report.unchanged, 4);
+}
+
+#[test]
+fn test_transcode_reencodes_only_mapped_codecs() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("t.6cy");
+    let text = b"transcode me ".repeat(5000);
+    let opts = PackOptions { password: Some("pw".into()), ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file_with_codec("old.txt", &text, CodecId::Lzma).unwrap();
+    ar.add_file_with_codec("new.txt", b"already zstd", CodecId::Zstd).unwrap();
+    ar.begin_solid(CodecId::Lzma).unwrap();
+    ar.add_file("s1", b"solid one").unwrap();
+    ar.add_file("s2", b"solid two").unwrap();
+    ar.end_solid().unwrap();
+    ar.put_app_data("app", b"blob").unwrap();
+    ar.finalize().unwrap();
+
+    // In place: the new copy replaces the source once complete.
+    let mut ar = Archive::open_encrypted(&path, "pw").unwrap();
+    let uuid   = ar.uuid();
+    let report = ar.transcode(&path, |c| if c == CodecId::Lzma { CodecId::Zstd } else { c }, 3).unwrap();
+    assert_eq!((report.blocks_rewritten, report.blocks_copied), (2, 2));
+
+    let mut ar = Archive::open_encrypted(&path, "pw").unwrap();
+    assert_eq!(ar.uuid(), uuid);
+    let codecs: Vec<_> = ar.iter_block_headers().unwrap()
+        .map(|b| b.unwrap().1)
+        .filter(|h| h.block_type.name() != "INDEX")
+        .map(|h| h.codec_id().unwrap())
+        .collect();
+    assert!(codecs.iter().all(|&c| c == CodecId::Zstd), "{codecs:?}");
+    assert_eq!(ar.read_file("old.txt").unwrap(), text);
+    assert_eq!(ar.read_file("new.txt").unwrap(), b"already zstd");
+    assert_eq!(ar.read_file("s2").unwrap(), b"solid two");
+    assert_eq!(ar.get_app_data("app").unwrap().unwrap(), b"blob");
+}
+
+#[test]
+fn test_space_report_counts_orphans_dedup_and_fragmentation() {
+    use sixcy::archive::Archive;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("s.6cy");
+    let x: Vec<u8> = (0..2000u32).map(|i| (i * 13 % 251) as u8).collect();
+    let swapped    = [&x[1000..], &x[..1000]].concat();
+    let lost: Vec<u8> = (0..3000u32).map(|i| (i * 7 % 241) as u8).collect();
+    {
+        let mut w = SixCyWriter::with_options(File::create(&path).unwrap(), 1000, 3, None).unwrap();
+        w.add_file("a".into(), &x, CodecId::Zstd).unwrap();
+        w.add_file("lost".into(), &lost, CodecId::Zstd).unwrap();
+        // Both chunks dedup against "a", read back to front.
+        w.add_file("swapped".into(), &swapped, CodecId::Zstd).unwrap();
+        w.start_solid_session(CodecId::Zstd).unwrap();
+        w.add_file("s1".into(), b"one", CodecId::Zstd).unwrap();
+        w.add_file("s2".into(), b"two", CodecId::Zstd).unwrap();
+        w.flush_solid_session().unwrap();
+        w.index.records.retain(|r| r.name != "lost");
+        w.finalize().unwrap();
+    }
+
+    let report = Archive::open(&path).unwrap().space_report().unwrap();
+    // a ×2, lost ×3, one SOLID, the INDEX.
+    assert_eq!((report.blocks_total, report.solid_blocks, report.orphaned_blocks), (7, 1, 3));
+    assert!(report.orphaned_bytes > 0 && report.orphaned_bytes < report.block_bytes);
+    assert_eq!((report.logical_bytes, report.unique_bytes), (4006, 2006));
+    assert!((report.dedup_ratio() - 4006.0 / 2006.0).abs() < 1e-9);
+    assert_eq!((report.multi_block_files, report.fragmented_files), (2, 1));
+    assert_eq!(report.fragmentation(), 0.5);
+    assert_eq!(report.unreadable_at, None);
+}
+
+#[test]
+fn test_empty_archive_and_zero_length_files() {
+    use sixcy::archive::{Archive, ExtractOptions, OverwritePolicy, PackOptions};
+    use sixcy::io_stream::AutoSolid;
+    use sixcy::optimize::{optimize, OptimizeOptions};
+    use sixcy::recovery::extract_recoverable;
+
+    let dir = tempfile::tempdir().unwrap();
+
+    // An archive with no entries: one INDEX block, nothing else.
+    let empty = dir.path().join("empty.6cy");
+    Archive::create(&empty, PackOptions::default()).unwrap().finalize().unwrap();
+    let mut ar = Archive::open(&empty).unwrap();
+    assert!(ar.list().is_empty());
+    assert_eq!(ar.iter_block_headers().unwrap().count(), 1);
+    assert_eq!(ar.health_check(1.0).unwrap().blocks_total, 0);
+    assert_eq!(ar.space_report().unwrap().orphaned_blocks, 0);
+    let opts = ExtractOptions { paranoid: true, ..ExtractOptions::default() };
+    assert_eq!(ar.extract_with(dir.path().join("out-empty"), &opts).unwrap().files, 0);
+    assert!(dir.path().join("out-empty").is_dir());
+    assert!(ar.orphans().unwrap().is_empty());
+    assert_eq!(sixcy::scan_file(&empty).unwrap().corrupt_blocks, 0);
+    let rebuilt = dir.path().join("rebuilt.6cy");
+    extract_recoverable(&mut File::open(&empty).unwrap(), &mut File::create(&rebuilt).unwrap(), None).unwrap();
+    assert!(Archive::open(&rebuilt).unwrap().list().is_empty());
+    optimize(&mut ar, dir.path().join("empty2.6cy"), &OptimizeOptions::default()).unwrap();
+    assert!(Archive::open(dir.path().join("empty2.6cy")).unwrap().list().is_empty());
+
+    // Zero-byte files by every write path: plain, streamed, solid and
+    // auto-solid.  Each is a record with no block refs.
+    let path = dir.path().join("zero.6cy");
+    let opts = PackOptions { auto_solid: Some(AutoSolid::default()), ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("plain", b"").unwrap();
+    ar.add_file_from_reader("streamed", std::io::empty(), 0, Default::default()).unwrap();
+    ar.add_file("auto", b"").unwrap();
+    ar.add_file("full", &b"not empty".repeat(100_000)).unwrap(); // over the auto-solid threshold
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    ar.add_file("solid", b"").unwrap();
+    ar.end_solid().unwrap();
+    ar.finalize().unwrap();
+
+    let names = ["plain", "streamed", "auto", "solid"];
+    let mut ar = Archive::open(&path).unwrap();
+    for name in names {
+        let info = ar.stat(name).unwrap();
+        assert_eq!((info.original_size, info.block_count), (0, 0), "{name}");
+        assert!(ar.read_file(name).unwrap().is_empty());
+        assert_eq!(ar.read_at(name, 0, &mut [0u8; 4]).unwrap(), 0);
+        assert!(ar.file_blocks(name).unwrap().is_empty());
+    }
+    assert!(ar.duplicate_files().is_empty());
+    let out  = dir.path().join("out");
+    let opts = ExtractOptions { paranoid: true, ..ExtractOptions::default() };
+    assert_eq!(ar.extract_with(&out, &opts).unwrap().files, 5);
+    for name in names {
+        assert_eq!(std::fs::metadata(out.join(name)).unwrap().len(), 0);
+    }
+    let resume = ExtractOptions { overwrite: OverwritePolicy::Resume, ..ExtractOptions::default() };
+    assert_eq!(ar.extract_with(&out, &resume).unwrap().unchanged, 5);
+
+    optimize(&mut ar, dir.path().join("zero2.6cy"), &OptimizeOptions::default()).unwrap();
+    let mut ar = Archive::open(dir.path().join("zero2.6cy")).unwrap();
+    assert_eq!(ar.list().len(), 5);
+    assert!(ar.read_file("solid").unwrap().is_empty());
+
+    // With the INDEX gone there is nothing on disk to recover them from;
+    // the rest still comes back.
+    let sb = sixcy::superblock::Superblock::read(&mut File::open(&path).unwrap()).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[(sb.index_offset + sixcy::block::BLOCK_HEADER_SIZE as u64 + 4) as usize] ^= 0xFF;
+    std::fs::write(&path, &bytes).unwrap();
+    let mut ar = Archive::open_resilient(&path, None).unwrap();
+    assert!(ar.is_reconstructed());
+    let names: Vec<String> = ar.list().into_iter().map(|f| f.name).collect();
+    assert_eq!(names, ["file_00000000"]); // auto-solid members get IDs last
+    assert_eq!(ar.read_file("file_00000000").unwrap().len(), 900_000);
+}
+
+#[test]
+fn test_open_with_limits_bounds_index_and_time() {
+    use std::time::{Duration, Instant};
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::OpenLimits;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("l.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    for i in 0..20 {
+        ar.add_file(&format!("f{i}"), b"x").unwrap();
+    }
+    ar.finalize().unwrap();
+
+    let later = OpenLimits { deadline: Some(Instant::now() + Duration::from_secs(60)), ..OpenLimits::untrusted() };
+    assert_eq!(Archive::open_with_limits(&path, later).unwrap().list().len(), 20);
+
+    let few = OpenLimits { max_records: 10, ..OpenLimits::default() };
+    assert!(matches!(Archive::open_with_limits(&path, few), Err(sixcy::SixcyError::Limit(_))));
+    let small = OpenLimits { max_index_bytes: 64, ..OpenLimits::default() };
+    assert!(matches!(Archive::open_with_limits(&path, small), Err(sixcy::SixcyError::Limit(_))));
+
+    let past = OpenLimits { deadline: Some(Instant::now()), ..OpenLimits::default() };
+    let err  = Archive::open_with_limits(&path, past).err().unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn test_recovery_run_options() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use sixcy::recovery::{archive_sink, run, RecoveryOptions, RecoveryProgress};
+
+    let temp = NamedTempFile::new().unwrap();
+    let key  = [7u8; 32];
+    let a: Vec<u8> = (0..10_000u32).map(|i| (i % 13) as u8).collect();
+    {
+        let mut w = SixCyWriter::with_options(File::create(temp.path()).unwrap(), 4096, 3, Some(key)).unwrap();
+        w.add_file("a".into(), &a, CodecId::Zstd).unwrap();
+        w.add_file("b".into(), b"after the damage", CodecId::Lz4).unwrap();
+        w.finalize().unwrap();
+    }
+    // Break the header of a's second chunk.
+    let mut bytes = std::fs::read(temp.path()).unwrap();
+    let second = sixcy::io_stream::SixCyReader::with_key(std::io::Cursor::new(&bytes), Some(key)).unwrap()
+        .index.records[0].block_refs[1].archive_offset as usize;
+    bytes[second + 10] ^= 0xFF;
+    std::fs::write(temp.path(), &bytes).unwrap();
+
+    let calls = Arc::new(AtomicU64::new(0));
+    let seen  = Arc::clone(&calls);
+    let opts  = RecoveryOptions {
+        parallel:       true,
+        decryption_key: Some(key),
+        progress:       Some(RecoveryProgress::new(move |_, _| { seen.fetch_add(1, Ordering::Relaxed); })),
+        ..RecoveryOptions::default()
+    };
+    let mut names = Vec::new();
+    let mut sink  = |name: &str, _: &[u8], _: bool| { names.push(name.to_owned()); Ok(()) };
+    let report = run(&mut File::open(temp.path()).unwrap(), &mut sink, &opts).unwrap();
+    assert_eq!(names, ["recovered_file_00000000", "recovered_file_00000001"]);
+    assert_eq!(calls.load(Ordering::Relaxed), report.total_scanned as u64);
+
+    // Without resync the scan ends at the damaged header.
+    let opts = RecoveryOptions { resync: false, ..opts };
+    let mut out = std::io::Cursor::new(Vec::new());
+    let report  = run(&mut File::open(temp.path()).unwrap(), &mut archive_sink(&mut out).unwrap(), &opts).unwrap();
+    assert_eq!((report.corrupt_blocks, report.index.records.len()), (1, 1));
+    let mut ar = sixcy::io_stream::SixCyReader::with_key(std::io::Cursor::new(out.into_inner()), None).unwrap();
+    assert_eq!(ar.unpack_file(0).unwrap(), &a[..4096]);
+}
+
+#[test]
+fn test_scrub_reverifies_only_due_blocks() {
+    use std::time::Duration;
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::block::BLOCK_HEADER_SIZE;
+    use sixcy::scrub::{ScrubOptions, ScrubState};
+    use sixcy::superblock::SUPERBLOCK_SIZE;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("s.6cy");
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * 31 % 253) as u8).collect();
+    let mut ar = Archive::create(&path, PackOptions { chunk_size: 1000, ..PackOptions::default() }).unwrap();
+    ar.add_file("f", &data).unwrap();
+    ar.finalize().unwrap();
+
+    let journal = dir.path().join("state.json");
+    let mut state = ScrubState::load(&journal).unwrap();
+    assert_eq!(state, ScrubState::default());
+    let opts = ScrubOptions::default();
+    let mut ar = Archive::open(&path).unwrap();
+    let report = ar.scrub(&mut state, &opts).unwrap();
+    assert_eq!((report.blocks_total, report.blocks_checked, report.blocks_pending), (20, 20, 0));
+    assert!(report.is_healthy());
+    state.save(&journal).unwrap();
+    assert_eq!(ScrubState::load(&journal).unwrap(), state);
+
+    // Everything is fresh; then one aged entry and one failed entry are due.
+    let report = ar.scrub(&mut state, &opts).unwrap();
+    assert_eq!((report.blocks_checked, report.blocks_fresh()), (0, 20));
+    state.blocks.values_mut().next().unwrap().verified_at = 0;
+    state.blocks.values_mut().last().unwrap().ok = false;
+    assert_eq!(ar.scrub(&mut state, &opts).unwrap().blocks_checked, 2);
+    assert!(state.blocks.values().all(|c| c.ok));
+
+    // A cap spreads a pass over several runs.
+    let capped = ScrubOptions { max_blocks: Some(8), ..ScrubOptions::default() };
+    let mut fresh = ScrubState::default();
+    let report = ar.scrub(&mut fresh, &capped).unwrap();
+    assert_eq!((report.blocks_checked, report.blocks_pending), (8, 12));
+    let report = ar.scrub(&mut fresh, &capped).unwrap();
+    assert_eq!((report.blocks_checked, report.blocks_pending, report.blocks_fresh()), (8, 4, 8));
+
+    // A journal of another archive is refused.
+    let mut other = ScrubState { archive_uuid: Some("not-this-one".into()), ..ScrubState::default() };
+    assert!(ar.scrub(&mut other, &opts).is_err());
+    drop(ar);
+
+    // Damage is found once the block is due, and stays due until it passes.
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[SUPERBLOCK_SIZE + BLOCK_HEADER_SIZE + 2] ^= 0xFF;
+    std::fs::write(&path, bytes).unwrap();
+    let mut ar  = Archive::open(&path).unwrap();
+    let every   = ScrubOptions { max_age: Duration::ZERO, ..ScrubOptions::default() };
+    let report  = ar.scrub(&mut state, &every).unwrap();
+    assert_eq!(report.blocks_checked, 20);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].0, SUPERBLOCK_SIZE as u64);
+    assert!(!state.blocks[&(SUPERBLOCK_SIZE as u64)].ok);
+    let report = ar.scrub(&mut state, &opts).unwrap();
+    assert_eq!((report.blocks_checked, report.failures.len()), (1, 1));
+}
+
+#[test]
+fn test_store_shares_trained_dictionary() {
+    use std::sync::Arc;
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::codec::{codec_source, uuid_to_string, CodecSource, Dictionary};
+    use sixcy::BlockStore;
+
+    let record = |i: u32| format!(
+        "{{\"host\":\"node-{:03}.fleet.example\",\"service\":\"ingest\",\"region\":\"eu-west-{}\",\
+         \"status\":\"{}\",\"latency_ms\":{},\"build\":\"2026.10.{}\"}}\n",
+        i % 97, i % 3, if i.is_multiple_of(7) { "degraded" } else { "ok" }, i * 37 % 1000, i % 28).into_bytes();
+    let samples: Vec<Vec<u8>> = (0..400).map(record).collect();
+    let dict = Dictionary::train(&samples, 4096).unwrap();
+    assert_eq!(Dictionary::new(dict.as_bytes().to_vec()).uuid(), dict.uuid());
+
+    let dir   = tempfile::tempdir().unwrap();
+    let store = BlockStore::open(dir.path().join("store")).unwrap();
+    assert!(store.put_dictionary(&dict).unwrap());
+    assert!(!store.put_dictionary(&dict).unwrap());
+    assert_eq!(store.dictionaries().unwrap(), vec![dict.uuid()]);
+    assert_eq!(store.dictionary(&dict.uuid()).unwrap(), dict);
+    assert_eq!(store.register_dictionaries().unwrap(), 1);
+    assert_eq!(codec_source(&dict.uuid()), Some(CodecSource::Dictionary));
+
+    // Two small archives share the dictionary and beat plain zstd.
+    let dict = Arc::new(dict);
+    let with = PackOptions { dictionary: Some(Arc::clone(&dict)), ..PackOptions::default() };
+    let mut uuids = Vec::new();
+    for i in 0..2u32 {
+        let content = record(1000 + i);
+        let path = dir.path().join(format!("a{i}.6cy"));
+        let mut ar = Archive::create(&path, with.clone()).unwrap();
+        ar.add_file("event.json", &content).unwrap();
+        ar.finalize().unwrap();
+        uuids.push(ar.uuid());
+
+        let mut plain = Archive::create(dir.path().join(format!("p{i}.6cy")), PackOptions::default()).unwrap();
+        plain.add_file("event.json", &content).unwrap();
+        plain.finalize().unwrap();
+
+        let mut ar = Archive::open(&path).unwrap();
+        assert!(ar.required_codecs().contains(&dict.uuid()));
+        assert_eq!(ar.read_file("event.json").unwrap(), content);
+        let plain = Archive::open(dir.path().join(format!("p{i}.6cy"))).unwrap();
+        assert!(ar.list()[0].compressed_size < plain.list()[0].compressed_size);
+
+        let report = store.push(&path).unwrap();
+        assert_eq!(report.dictionaries_new, 0, "the store already holds it");
+        assert_eq!(store.manifest(&uuids[i as usize]).unwrap().dictionaries, vec![uuid_to_string(&dict.uuid())]);
+    }
+    assert_eq!(store.dictionaries().unwrap().len(), 1);
+
+    let pulled = dir.path().join("pulled.6cy");
+    store.pull(&uuids[1], &pulled).unwrap();
+    assert_eq!(Archive::open(&pulled).unwrap().read_file("event.json").unwrap(), record(1001));
+
+    // A dictionary no reader has registered makes the archive unreadable.
+    let unknown = Arc::new(Dictionary::new(b"a dictionary nobody registers".repeat(8)));
+    let path = dir.path().join("unknown.6cy");
+    {
+        let mut w = SixCyWriter::new(File::create(&path).unwrap()).unwrap();
+        w.dictionary = Some(Arc::clone(&unknown));
+        w.add_file("event.json".into(), &record(7), CodecId::Zstd).unwrap();
+        w.finalize().unwrap();
+    }
+    assert!(Archive::open(&path).is_err());
+    sixcy::codec::register_dictionary(unknown);
+    assert_eq!(Archive::open(&path).unwrap().read_file("event.json").unwrap(), record(7));
+}
+
+#[test]
+fn test_open_for_append_dedups_against_existing_blocks() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("a.6cy");
+    let opts = PackOptions { chunk_size: 1000, ..PackOptions::default() };
+    let big: Vec<u8> = (0..5000u32).map(|i| (i * 31 % 253) as u8).collect();
+    let mut ar = Archive::create(&path, opts.clone()).unwrap();
+    ar.add_file("big", &big).unwrap();
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    ar.add_file("s1", b"first solid member").unwrap();
+    ar.add_file("s2", b"second solid member").unwrap();
+    ar.end_solid().unwrap();
+    ar.finalize().unwrap();
+    let before = Archive::open(&path).unwrap().space_report().unwrap().blocks_total;
+
+    // Copies of stored content add no blocks; only the new file does.
+    let fresh: Vec<u8> = (0..1500u32).map(|i| (i * 7 % 241) as u8).collect();
+    let mut ar = Archive::open_for_append(&path, opts.clone()).unwrap();
+    ar.add_file("copy-of-big", &big).unwrap();
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    ar.add_file("copy-of-s1", b"first solid member").unwrap();
+    ar.end_solid().unwrap();
+    ar.add_file("fresh", &fresh).unwrap();
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open(&path).unwrap();
+    let report = ar.space_report().unwrap();
+    assert_eq!(report.blocks_total, before + 3, "two DATA blocks and the new INDEX");
+    assert_eq!(report.orphaned_blocks, 1, "the superseded INDEX");
+    assert!(report.unreadable_at.is_none());
+    for (name, data) in [("big", &big[..]), ("copy-of-big", &big), ("s2", b"second solid member"),
+                         ("copy-of-s1", b"first solid member"), ("fresh", &fresh)] {
+        assert_eq!(ar.read_file(name).unwrap(), data, "{name}");
+    }
+    let ids: std::collections::HashSet<u32> = ar.list().iter().map(|f| f.id).collect();
+    assert_eq!(ids.len(), 6);
+    drop(ar);
+    // A block scan steps over the superseded INDEX and its recovery map.
+    let scan = sixcy::recovery::scan_file(&path).unwrap();
+    assert_eq!(scan.index.records.iter().filter(|r| r.original_size == 1500).count(), 1);
+
+    // An append that never finalizes leaves the archive as it was.
+    let len = std::fs::metadata(&path).unwrap().len();
+    let mut ar = Archive::open_for_append(&path, opts.clone()).unwrap();
+    ar.add_file("lost", &fresh.iter().rev().copied().collect::<Vec<u8>>()).unwrap();
+    drop(ar);
+    assert!(std::fs::metadata(&path).unwrap().len() > len);
+    assert_eq!(Archive::open(&path).unwrap().list().len(), 6);
+
+    // Appends chain; options that only make sense on create are refused.
+    let mut ar = Archive::open_for_append(&path, opts.clone()).unwrap();
+    ar.add_file("later", b"third session").unwrap();
+    ar.finalize().unwrap();
+    let mut ar = Archive::open(&path).unwrap();
+    assert_eq!(ar.list().len(), 7);
+    assert_eq!(ar.read_file("later").unwrap(), b"third session");
+    assert_eq!(ar.space_report().unwrap().orphaned_blocks, 2, "the leftover was overwritten");
+    assert!(Archive::open_for_append(&path, PackOptions { atomic: true, ..opts.clone() }).is_err());
+
+    // An encrypted archive keeps its key and needs the password.
+    let enc  = dir.path().join("e.6cy");
+    let with = PackOptions { password: Some("pw".into()), ..opts.clone() };
+    let mut ar = Archive::create(&enc, with.clone()).unwrap();
+    ar.add_file("big", &big).unwrap();
+    ar.finalize().unwrap();
+    assert!(Archive::open_for_append(&enc, opts.clone()).is_err());
+    let mut ar = Archive::open_for_append(&enc, with).unwrap();
+    ar.add_file("copy-of-big", &big).unwrap();
+    ar.finalize().unwrap();
+    let mut ar = Archive::open_encrypted(&enc, "pw").unwrap();
+    assert_eq!(ar.read_file("copy-of-big").unwrap(), big);
+    assert_eq!(ar.list()[0].first_block_hash, ar.list()[1].first_block_hash);
+
+    // The new INDEX keeps the archive's INDEX codec, whatever `opts` say.
+    let raw = dir.path().join("raw-index.6cy");
+    let mut ar = Archive::create(&raw, PackOptions { index_codec: CodecId::None, ..opts.clone() }).unwrap();
+    ar.add_file("a.txt", b"a").unwrap();
+    ar.finalize().unwrap();
+    let mut ar = Archive::open_for_append(&raw, opts.clone()).unwrap();
+    ar.add_file("b.txt", b"b").unwrap();
+    ar.finalize().unwrap();
+    let mut r = sixcy::io_stream::SixCyReader::new(File::open(&raw).unwrap()).unwrap();
+    let index_offset = r.superblock.index_offset;
+    assert_eq!(r.read_block_at(index_offset).unwrap().0.codec_id(), Some(CodecId::None));
+    assert_eq!(r.index.records.len(), 2);
+}
+
+#[test]
+fn test_extract_hardlinks_duplicate_content() {
+    use sixcy::archive::{Archive, DedupStrategy, ExtractOptions, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("d.6cy");
+    let body: Vec<u8> = (0..20_000u32).map(|i| (i % 97) as u8).collect();
+    {
+        let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+        ar.add_file("one.bin", &body).unwrap();
+        ar.add_file("two.bin", &body).unwrap();
+        ar.add_file("other.bin", b"different").unwrap();
+        ar.add_file("empty1", b"").unwrap();
+        ar.add_file("empty2", b"").unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let out = dir.path().join("out");
+    let opts = ExtractOptions { dedup_strategy: DedupStrategy::Hardlink, ..Default::default() };
+    let report = Archive::open(&path).unwrap().extract_with(&out, &opts).unwrap();
+    assert_eq!((report.files, report.linked), (5, 1));
+    assert_eq!(std::fs::read(out.join("two.bin")).unwrap(), body);
+    assert_eq!(std::fs::read(out.join("other.bin")).unwrap(), b"different");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let one = std::fs::metadata(out.join("one.bin")).unwrap();
+        let two = std::fs::metadata(out.join("two.bin")).unwrap();
+        assert_eq!(one.ino(), two.ino());
+        assert_eq!(one.nlink(), 2);
+    }
+
+    // Re-extracting over existing files replaces them rather than failing.
+    let opts = ExtractOptions { dedup_strategy: DedupStrategy::Reflink, ..Default::default() };
+    let report = Archive::open(&path).unwrap().extract_with(&out, &opts).unwrap();
+    assert_eq!(report.linked, 1);
+    assert_eq!(std::fs::read(out.join("two.bin")).unwrap(), body);
+}
+
+#[test]
+fn test_pooled_codec_paths_match_allocating_ones() {
+    use sixcy::block::{decode_block_into, encode_block, BlockType};
+    use sixcy::codec::get_codec;
+    use sixcy::limits::Limits;
+
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 61) as u8).collect();
+    for codec in [CodecId::None, CodecId::Zstd, CodecId::Lz4, CodecId::Brotli, CodecId::Lzma] {
+        let c = get_codec(codec).unwrap();
+        let mut out = b"prefix".to_vec();
+        c.compress_into(&data, 3, &mut out).unwrap();
+        assert_eq!(c.decompress(&out[6..]).unwrap(), data, "{codec:?}");
+
+        let packed = c.compress(&data, 3).unwrap();
+        let mut plain = b"prefix".to_vec();
+        c.decompress_bounded_into(&packed, data.len(), &mut plain).unwrap();
+        assert_eq!(&plain[6..], &data[..], "{codec:?}");
+        assert!(c.decompress_bounded_into(&packed, data.len() - 1, &mut Vec::new()).is_err(), "{codec:?}");
+    }
+
+    // decode_block_into appends, and leaves `out` untouched on failure.
+    let key = [7u8; 32];
+    let (header, payload) = encode_block(BlockType::Data, 0, 0, &data, CodecId::Zstd, 3, Some(&key)).unwrap();
+    let mut out = b"head".to_vec();
+    decode_block_into(&header, &payload, Some(&key), &Limits::default(), &mut out).unwrap();
+    assert_eq!(&out[4..], &data[..]);
+    let mut out = b"head".to_vec();
+    assert!(decode_block_into(&header, &payload, Some(&[8u8; 32]), &Limits::default(), &mut out).is_err());
+    assert_eq!(out, b"head");
+}
+
+#[test]
+fn test_perf_stats_count_bytes_and_blocks() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("p.6cy");
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 13) as u8).collect();
+    {
+        let opts = PackOptions { chunk_size: 4096, perf_stats: true, ..PackOptions::default() };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("a", &data).unwrap();
+        ar.add_file("b", &data).unwrap(); // CAS hit: hashed, not written
+        ar.finalize().unwrap();
+        let s = ar.perf_stats().unwrap();
+        assert_eq!(s.bytes_in, 20_000);
+        assert_eq!(s.blocks, 3 + 1); // three chunks + INDEX
+        assert!(!s.wall.is_zero() && !s.compress.is_zero());
+        assert_eq!(s.wall, ar.perf_stats().unwrap().wall, "frozen at finalize");
+    }
+
+    let mut ar = Archive::open(&path).unwrap();
+    assert!(ar.perf_stats().is_none());
+    ar.enable_perf_stats();
+    ar.read_file("b").unwrap();
+    let s = ar.perf_stats().unwrap();
+    assert_eq!((s.bytes_out, s.blocks), (10_000, 3));
+    assert!(s.bytes_in > 0 && !s.decompress.is_zero());
+    assert!(s.to_string().contains("decompress"));
+}
+
+#[test]
+fn test_adaptive_chunk_policy() {
+    use std::sync::Arc;
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::io_stream::{AdaptiveChunks, ChunkPolicy};
+
+    let policy = AdaptiveChunks { min: 1024, max: 8192, target_chunks: 4 };
+    assert_eq!(policy.chunk_size("small", 900), 1024);
+    assert_eq!(policy.chunk_size("mid", 10_000), 4096);
+    assert_eq!(policy.chunk_size("huge", 1 << 30), 8192);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("c.6cy");
+    let small: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+    let big:   Vec<u8> = (0..100_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    {
+        let opts = PackOptions {
+            chunk_size:   512,
+            chunk_policy: Some(Arc::new(policy)),
+            ..PackOptions::default()
+        };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("small", &small).unwrap();
+        ar.add_file("big", &big).unwrap();
+        ar.finalize().unwrap();
+    }
+    let mut ar = Archive::open(&path).unwrap();
+    assert_eq!(ar.stat("small").unwrap().block_count, 1);
+    assert_eq!(ar.stat("big").unwrap().block_count, 100_000usize.div_ceil(8192));
+    assert_eq!(ar.read_file("big").unwrap(), big);
+    let mut buf = [0u8; 100];
+    ar.read_at("big", 8150, &mut buf).unwrap();
+    assert_eq!(&buf[..], &big[8150..8250]);
+}
+
+#[test]
+fn test_superblock_provenance_trailer() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::core_reader;
+    use sixcy::superblock::{Superblock, SuperblockError, SB_FLAG_PROVENANCE, WRITER_NAME};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("t.6cy");
+    for deterministic in [false, true] {
+        let opts = PackOptions { deterministic, ..PackOptions::default() };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("a", b"abc").unwrap();
+        ar.finalize().unwrap();
+        let sb = Superblock::read(File::open(&path).unwrap()).unwrap();
+        assert_eq!(sb.writer.as_deref(), Some(WRITER_NAME));
+        assert!(sb.flags & SB_FLAG_PROVENANCE != 0);
+        let created = sb.created_at.unwrap();
+        assert_eq!(created == 0, deterministic);
+        assert!(sb.modified_at.unwrap() >= created);
+    }
+
+    // The trailer has its own CRC.
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[core_reader::PROVENANCE_OFFSET + 20] ^= 1;
+    assert!(matches!(Superblock::read(&bytes[..]), Err(SuperblockError::Crc32Mismatch)));
+
+    // Pre-trailer superblocks read back as unknown provenance.
+    let mut sb = Superblock::new();
+    (sb.created_at, sb.modified_at, sb.writer) = (None, None, None);
+    let mut buf = Vec::new();
+    sb.write(&mut buf).unwrap();
+    let back = Superblock::read(&buf[..]).unwrap();
+    assert!(back.writer.is_none() && back.created_at.is_none());
+    assert_eq!(back.flags & SB_FLAG_PROVENANCE, 0);
+
+    // Too many codecs to fit the trailer: it is dropped, not overlapped.
+    let mut sb = Superblock::new();
+    sb.required_codec_uuids = (1..=8u8).map(|i| [i; 16]).collect();
+    let mut buf = Vec::new();
+    sb.write(&mut buf).unwrap();
+    let raw = core_reader::parse_superblock(&buf).unwrap();
+    assert_eq!(raw.required_codec_uuids.len(), 8);
+    assert!(raw.provenance.is_none());
+}
+
+#[test]
+fn test_unsupported_feature_flags_fail_fast() {
+    use sixcy::core_reader;
+    use sixcy::superblock::{Superblock, SuperblockError, FEATURE_PARITY, SB_FLAG_FEATURES};
+
+    let mut sb = Superblock::new();
+    let mut plain = Vec::new();
+    sb.write(&mut plain).unwrap();
+    assert_eq!(Superblock::read(&plain[..]).unwrap().feature_flags, 0);
+    assert_eq!(core_reader::parse_superblock(&plain).unwrap().flags & SB_FLAG_FEATURES, 0);
+
+    sb.feature_flags = FEATURE_PARITY | (1 << 40);
+    let mut buf = Vec::new();
+    sb.write(&mut buf).unwrap();
+    let raw = core_reader::parse_superblock(&buf).unwrap();
+    assert_eq!(raw.feature_flags, FEATURE_PARITY | (1 << 40));
+    assert_eq!(raw.unsupported_features(), raw.feature_flags);
+    match Superblock::read(&buf[..]) {
+        Err(SuperblockError::UnsupportedFeature { feature }) => assert_eq!(feature, "parity"),
+        other => panic!("expected UnsupportedFeature, got {other:?}"),
+    }
+
+    let mut corrupt = buf.clone();
+    corrupt[core_reader::FEATURES_OFFSET] ^= 0x10;
+    assert!(matches!(Superblock::read(&corrupt[..]), Err(SuperblockError::Crc32Mismatch)));
+
+    // Feature bits are never silently dropped for lack of space.
+    sb.required_codec_uuids = (1..=8u8).map(|i| [i; 16]).collect();
+    assert!(sb.write(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn test_codec_list_overflows_into_codecs_block() {
+    use sixcy::codec::CodecId;
+    use sixcy::core_reader;
+    use sixcy::io_stream::{SixCyReader, SixCyWriter};
+    use sixcy::superblock::{SuperblockError, MAX_INLINE_CODECS, MAX_INLINE_CODECS_CONTINUED};
+
+    let dir = tempfile::tempdir().unwrap();
+    let write = |name: &str, extra: &[[u8; 16]]| {
+        let path = dir.path().join(name);
+        let mut w = SixCyWriter::new(File::create(&path).unwrap()).unwrap();
+        w.add_file("a.txt".into(), b"hello codecs", CodecId::Zstd).unwrap();
+        w.superblock.required_codec_uuids.extend_from_slice(extra);
+        w.finalize().unwrap();
+        path
+    };
+
+    // More codecs than fit inline: finalize succeeds and the reader sees
+    // the whole list.
+    let zstd = CodecId::Zstd.uuid();
+    let path = write("ok.6cy", &[zstd; MAX_INLINE_CODECS]);
+    let bytes = std::fs::read(&path).unwrap();
+    let raw = core_reader::parse_superblock(&bytes).unwrap();
+    assert_eq!(raw.required_codec_uuids.len(), MAX_INLINE_CODECS_CONTINUED);
+    assert_eq!(raw.codecs_continued.unwrap().count as usize,
+               MAX_INLINE_CODECS + 1 - MAX_INLINE_CODECS_CONTINUED);
+    assert!(raw.provenance.is_some());
+    let mut r = SixCyReader::new(File::open(&path).unwrap()).unwrap();
+    assert_eq!(r.superblock.required_codec_uuids.len(), MAX_INLINE_CODECS + 1);
+    assert_eq!(r.unpack_file(r.index.records[0].id).unwrap(), b"hello codecs");
+
+    // An unknown codec in the continuation still fails at open time.
+    let mut extra = vec![zstd; MAX_INLINE_CODECS];
+    extra.push([0xAB; 16]);
+    let path = write("bad.6cy", &extra);
+    match SixCyReader::new(File::open(&path).unwrap()) {
+        Err(sixcy::SixcyError::Superblock(SuperblockError::UnavailableCodec { uuid })) =>
+            assert!(uuid.starts_with("abababab")),
+        other => panic!("expected UnavailableCodec, got {:?}", other.err()),
+    }
+}
+
+#[test]
+fn test_remote_metadata_fetches_superblock_and_index_only() {
+    use sixcy::block::BLOCK_HEADER_SIZE;
+    use sixcy::io_stream::SixCyReader;
+    use sixcy::limits::Limits;
+    use sixcy::remote::{ArchiveMetadata, RangeSource};
+    use sixcy::superblock::{MAX_INLINE_CODECS, SUPERBLOCK_SIZE};
+
+    struct Recorded { bytes: Vec<u8>, ranges: Vec<(u64, u64)> }
+    impl RangeSource for Recorded {
+        fn read_range(&mut self, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+            self.ranges.push((offset, len));
+            let start = (offset as usize).min(self.bytes.len());
+            let end   = (start + len as usize).min(self.bytes.len());
+            Ok(self.bytes[start..end].to_vec())
+        }
+    }
+
+    let big: Vec<u8> = (0..300_000u32).flat_map(|i| i.to_le_bytes()).collect();
+    let dir = tempfile::tempdir().unwrap();
+    for extra in [0, MAX_INLINE_CODECS] {
+        let path = dir.path().join(format!("{extra}.6cy"));
+        let mut w = SixCyWriter::with_options(File::create(&path).unwrap(), 64 * 1024, 1, None).unwrap();
+        w.add_file("big.bin".into(), &big, CodecId::Zstd).unwrap();
+        w.add_file("small.txt".into(), b"small", CodecId::Lz4).unwrap();
+        w.superblock.required_codec_uuids.extend(vec![CodecId::Zstd.uuid(); extra]);
+        w.finalize().unwrap();
+        let mut r = SixCyReader::new(File::open(&path).unwrap()).unwrap();
+        let data_end = r.local_block_offsets().into_iter()
+            .map(|at| at + (BLOCK_HEADER_SIZE + r.read_block_at(at).unwrap().1.len()) as u64)
+            .max().unwrap();
+
+        let mut src = Recorded { bytes: std::fs::read(&path).unwrap(), ranges: Vec::new() };
+        let meta = ArchiveMetadata::fetch(&mut src, Limits::untrusted()).unwrap();
+        assert_eq!(src.ranges.len(), 2, "{:?}", src.ranges);
+        assert_eq!(src.ranges[0], (0, SUPERBLOCK_SIZE as u64));
+        assert!(src.ranges[1].0 >= data_end, "{:?} reaches into data ending at {data_end}", src.ranges);
+
+        let names: Vec<_> = meta.list().into_iter().map(|f| f.name).collect();
+        assert_eq!(names, ["big.bin", "small.txt"]);
+        assert_eq!(meta.stat("big.bin").unwrap().original_size, big.len() as u64);
+        let hash: [u8; 32] = blake3::hash(b"small").into();
+        assert_eq!(meta.find_by_hash(&hash)[0].name, "small.txt");
+        assert_eq!(meta.required_codecs().len() > MAX_INLINE_CODECS, extra > 0);
+
+        // An INDEX over the limit is refused before it is requested.
+        src.ranges.clear();
+        let tight = Limits { max_index_size: 16, ..Limits::untrusted() };
+        assert!(matches!(ArchiveMetadata::fetch(&mut src, tight), Err(sixcy::SixcyError::Limit(_))));
+        assert_eq!(src.ranges.len(), 1);
+    }
+}
+
+#[test]
+fn test_http_source_times_out_and_rejects_overflowing_ranges() {
+    use sixcy::remote::{HttpSource, RangeSource};
+    use std::time::Duration;
+
+    // Connections complete in the backlog but are never answered.
+    let silent = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/a.6cy", silent.local_addr().unwrap());
+    let mut src = HttpSource::new(&url).unwrap()
+        .with_timeouts(Duration::from_secs(1), Duration::from_millis(500));
+    assert_eq!(src.read_range(0, 256).unwrap_err().kind(), std::io::ErrorKind::TimedOut);
+    let err = sixcy::archive::Archive::open_remote_metadata_with(&mut src).unwrap_err();
+    assert_eq!(std::io::Error::from(err).kind(), std::io::ErrorKind::TimedOut);
+
+    assert_eq!(src.read_range(u64::MAX, 2).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_read_lenient_reports_every_problem() {
+    use sixcy::superblock::{Superblock, SuperblockError};
+
+    let mut sb = Superblock::new();
+    sb.required_codec_uuids.push([0xCD; 16]);
+    let mut buf = Vec::new();
+    sb.write(&mut buf).unwrap();
+
+    // Healthy apart from the unknown codec, which strict read also rejects.
+    let d = Superblock::read_lenient(&buf[..]).unwrap();
+    assert!(matches!(d.problems.as_slice(), [SuperblockError::UnavailableCodec { .. }]));
+    assert_eq!(d.superblock.archive_uuid, sb.archive_uuid);
+
+    buf[0] = b'X';
+    buf[4..8].copy_from_slice(&2u32.to_le_bytes());
+    let d = Superblock::read_lenient(&buf[..]).unwrap();
+    assert!(!d.is_healthy());
+    assert!(matches!(d.problems.as_slice(), [
+        SuperblockError::InvalidMagic,
+        SuperblockError::UnsupportedVersion(2),
+        SuperblockError::Crc32Mismatch,
+        SuperblockError::UnavailableCodec { .. },
+    ]), "{:?}", d.problems);
+    assert_eq!(&d.superblock.magic, b"X6cy");
+    assert_eq!(d.superblock.writer, sb.writer, "trailer has its own CRC");
+    assert!(Superblock::read(&buf[..]).is_err());
+
+    let d = Superblock::read_lenient(&buf[..100]).unwrap();
+    assert!(matches!(&d.problems[0], SuperblockError::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof));
+
+    // A codec count one past what fits is clamped, not sliced past the end.
+    buf[44..46].copy_from_slice(&13u16.to_le_bytes());
+    let d = Superblock::read_lenient(&buf[..]).unwrap();
+    assert!(d.problems.iter().any(|p| p.to_string().contains("overflows")), "{:?}", d.problems);
+}
+
+#[test]
+fn test_registered_plugin_satisfies_required_codec() {
+    use sixcy::block::{decode_block, encode_block, BlockType, FILE_ID_SHARED};
+    use sixcy::codec::{codec_source, CodecId, CodecSource};
+    use sixcy::plugin::{self, rc, SixcyCodecPlugin, SIXCY_PLUGIN_ABI_VERSION};
+    use sixcy::superblock::{Superblock, SuperblockError};
+
+    unsafe extern "C" fn copy(inp: *const u8, in_len: u32, out: *mut u8, out_len: *mut u32) -> i32 {
+        if *out_len < in_len { return rc::OVERFLOW; }
+        std::ptr::copy_nonoverlapping(inp, out, in_len as usize);
+        *out_len = in_len;
+        rc::OK
+    }
+    static COPY_PLUGIN: SixcyCodecPlugin = SixcyCodecPlugin {
+        uuid:           [0x5C; 16],
+        short_id:       0,
+        abi_version:    SIXCY_PLUGIN_ABI_VERSION,
+        compress:       None,
+        decompress:     Some(copy),
+        compress_bound: None,
+    };
+
+    let mut sb = Superblock::new();
+    sb.required_codec_uuids.push(COPY_PLUGIN.uuid);
+    let mut buf = Vec::new();
+    sb.write(&mut buf).unwrap();
+    assert!(matches!(Superblock::read(&buf[..]), Err(SuperblockError::UnavailableCodec { .. })));
+    assert_eq!(codec_source(&COPY_PLUGIN.uuid), None);
+
+    plugin::register(&COPY_PLUGIN).unwrap();
+    plugin::register(&COPY_PLUGIN).unwrap();
+    assert_eq!(codec_source(&COPY_PLUGIN.uuid), Some(CodecSource::Plugin));
+    assert_eq!(codec_source(&CodecId::Zstd.uuid()), Some(CodecSource::BuiltIn(CodecId::Zstd)));
+    assert!(plugin::registered_uuids().contains(&COPY_PLUGIN.uuid));
+    Superblock::read(&buf[..]).unwrap();
+
+    // Blocks tagged with the plugin's UUID decode through it.
+    let (mut header, payload) = encode_block(
+        BlockType::Data, FILE_ID_SHARED, 0, b"via plugin", CodecId::None, 0, None).unwrap();
+    header.codec_uuid = COPY_PLUGIN.uuid;
+    assert_eq!(decode_block(&header, &payload, None).unwrap(), b"via plugin");
+
+    static BUILTIN_CLASH: SixcyCodecPlugin = SixcyCodecPlugin {
+        uuid: [0; 16], short_id: 0, abi_version: SIXCY_PLUGIN_ABI_VERSION,
+        compress: None, decompress: Some(copy), compress_bound: None,
+    };
+    assert!(plugin::register(&BUILTIN_CLASH).is_err());
+}
+
+#[test]
+fn test_attest_inclusion_proofs() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::attest::InclusionProof;
+
+    let dir = tempfile::tempdir().unwrap();
+    for files in [3usize, 150] {
+        let path = dir.path().join(format!("a{files}.6cy"));
+        let opts = PackOptions { chunk_size: 64, ..PackOptions::default() };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        for i in 0..files {
+            // Sizes vary so files span 1..=8 blocks across chunk boundaries.
+            let data: Vec<u8> = (0..(i % 8 + 1) * 64).map(|b| (b * 7 + i) as u8).collect();
+            ar.add_file(&format!("f{i:03}"), &data).unwrap();
+        }
+        ar.finalize().unwrap();
+
+        let ar = Archive::open(&path).unwrap();
+        let mut root = [0u8; 32];
+        hex::decode_to_slice(ar.root_hash_hex(), &mut root).unwrap();
+        for i in [0, files / 2, files - 1] {
+            let proof = ar.attest(&format!("f{i:03}")).unwrap();
+            assert_eq!(proof.content_hashes.len(), i % 8 + 1);
+            assert!(proof.verify(&root), "file {i} of {files}");
+
+            let json = proof.to_json().unwrap();
+            assert_eq!(InclusionProof::from_json(&json).unwrap(), proof);
+
+            let mut wrong_root = root;
+            wrong_root[0] ^= 1;
+            assert!(!proof.verify(&wrong_root));
+            let mut forged = proof.clone();
+            forged.content_hashes[0][5] ^= 1;
+            assert!(!forged.verify(&root));
+            let mut moved = proof.clone();
+            moved.first_leaf += 1;
+            assert!(!moved.verify(&root));
+        }
+        if files > 100 {
+            assert!(!ar.attest("f075").unwrap().siblings.is_empty());
+        }
+    }
+}
+
+#[test]
+fn test_file_metadata_survives_auto_solid() {
+    use std::collections::HashMap;
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::io_stream::{AutoSolid, SixCyReader};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("meta.6cy");
+    let opts = PackOptions { auto_solid: Some(AutoSolid::default()), ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    let tags = HashMap::from([("owner".to_owned(), "build".to_owned())]);
+    ar.add_file_with_metadata("b.txt", b"tagged", tags.clone()).unwrap();
+    ar.add_file("a.txt", b"plain").unwrap();
+    ar.finalize().unwrap();
+
+    let reader = SixCyReader::new(File::open(&path).unwrap()).unwrap();
+    let rec = |name: &str| reader.index.records.iter().find(|r| r.name == name).unwrap();
+    assert_eq!(rec("b.txt").metadata, tags);
+    assert!(rec("a.txt").metadata.is_empty());
+}
+
+#[cfg(feature = "windows-metadata")]
+#[test]
+fn test_windows_metadata_round_trip() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::winmeta;
+
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("locked.txt");
+    std::fs::write(&src, b"read only").unwrap();
+    let mut perms = std::fs::metadata(&src).unwrap().permissions();
+    perms.set_readonly(true);
+    std::fs::set_permissions(&src, perms).unwrap();
+
+    let meta = winmeta::capture(&src).unwrap();
+    let attrs = u32::from_str_radix(&meta[winmeta::KEY_ATTRIBUTES], 16).unwrap();
+    assert_ne!(attrs & winmeta::ATTR_READONLY, 0);
+
+    let path = dir.path().join("win.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    ar.add_file_with_metadata("locked.txt", b"read only", meta).unwrap();
+    ar.finalize().unwrap();
+
+    let out = dir.path().join("out");
+    Archive::open(&path).unwrap().extract_all(&out).unwrap();
+    let restored = std::fs::metadata(out.join("locked.txt")).unwrap();
+    assert!(restored.permissions().readonly());
+
+    let t = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+    assert_eq!(winmeta::filetime_to_system_time(winmeta::system_time_to_filetime(t)), t);
+}
+
+#[test]
+fn test_extract_overwrite_policies() {
+    use std::time::{Duration, SystemTime};
+    use sixcy::archive::{Archive, ExtractOptions, OverwritePolicy, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("o.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    for name in ["a", "b", "c"] {
+        ar.add_file(name, format!("archived {name}").as_bytes()).unwrap();
+    }
+    ar.finalize().unwrap();
+
+    let out = dir.path().join("out");
+    let extract = |overwrite| {
+        let opts = ExtractOptions { overwrite, ..Default::default() };
+        Archive::open(&path).unwrap().extract_with(&out, &opts).unwrap()
+    };
+    let stage = |name: &str, body: &[u8], mtime: SystemTime| {
+        std::fs::write(out.join(name), body).unwrap();
+        File::options().write(true).open(out.join(name)).unwrap().set_modified(mtime).unwrap();
+    };
+    let read = |name: &str| std::fs::read_to_string(out.join(name)).unwrap();
+    let past   = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let future = SystemTime::now() + Duration::from_secs(86_400);
+
+    extract(OverwritePolicy::Overwrite);
+    stage("a", b"local a", past);
+    stage("b", b"archived b", past);
+    stage("c", b"local c", future);
+
+    let r = extract(OverwritePolicy::SkipExisting);
+    assert_eq!((r.files, r.skipped), (3, 3));
+    assert_eq!(read("a"), "local a");
+
+    // Older and different: replaced.  Older but identical: left alone.
+    // Newer: kept.
+    let r = extract(OverwritePolicy::KeepNewer);
+    assert_eq!((r.skipped, r.unchanged), (1, 1));
+    assert_eq!(read("a"), "archived a");
+    assert_eq!(read("b"), "archived b");
+    assert_eq!(read("c"), "local c");
+    assert_eq!(std::fs::metadata(out.join("b")).unwrap().modified().unwrap(), past);
+
+    let r = extract(OverwritePolicy::Overwrite);
+    assert_eq!((r.skipped, r.unchanged), (0, 0));
+    assert_eq!(read("c"), "archived c");
+}
+
+#[test]
+fn test_paranoid_extraction_attests_root() {
+    use sixcy::archive::{Archive, DedupStrategy, ExtractOptions, OverwritePolicy, PackOptions};
+    use sixcy::io_stream::AutoSolid;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("p.6cy");
+    let opts = PackOptions {
+        chunk_size: 1024,
+        auto_solid: Some(AutoSolid { threshold: 512, block_size: 4096 }),
+        ..PackOptions::default()
+    };
+    let big: Vec<u8> = (0..5000u32).map(|i| (i * 31 % 251) as u8).collect();
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("big.bin", &big).unwrap();
+    ar.add_file("copy.bin", &big).unwrap();
+    ar.add_file("small.txt", b"solid slice").unwrap();
+    ar.add_file("empty", b"").unwrap();
+    ar.finalize().unwrap();
+
+    let out = dir.path().join("out");
+    let paranoid = ExtractOptions {
+        dedup_strategy: DedupStrategy::Hardlink,
+        paranoid:       true,
+        ..Default::default()
+    };
+    let mut ar = Archive::open(&path).unwrap();
+    let report = ar.extract_with(&out, &paranoid).unwrap();
+    let mut root = [0u8; 32];
+    hex::decode_to_slice(ar.root_hash_hex(), &mut root).unwrap();
+    assert_eq!(report.verified_root, Some(root));
+
+    // A kept file cannot be attested, even if it is correct.
+    let skip = ExtractOptions { overwrite: OverwritePolicy::SkipExisting, ..paranoid.clone() };
+    assert!(Archive::open(&path).unwrap().extract_with(&out, &skip).is_err());
+
+    // Swap two refs' block offsets: every block still matches its own
+    // header, so only the per-ref check notices.
+    let swapped = dir.path().join("swapped.6cy");
+    let mut w = SixCyWriter::new(File::create(&swapped).unwrap()).unwrap();
+    w.add_file("x".into(), b"first file", CodecId::Zstd).unwrap();
+    w.add_file("y".into(), b"second file", CodecId::Zstd).unwrap();
+    let x = w.index.records[0].block_refs[0].archive_offset;
+    let y = w.index.records[1].block_refs[0].archive_offset;
+    w.index.records[0].block_refs[0].archive_offset = y;
+    w.index.records[1].block_refs[0].archive_offset = x;
+    w.finalize().unwrap();
+
+    let out = dir.path().join("swapped");
+    let unchecked = ExtractOptions { verify_hashes: false, ..Default::default() };
+    Archive::open(&swapped).unwrap().extract_with(&out, &unchecked).unwrap();
+    assert_eq!(std::fs::read(out.join("x")).unwrap(), b"second file");
+    assert!(Archive::open(&swapped).unwrap().extract_with(&out, &ExtractOptions::default()).is_err());
+}
+
+#[test]
+fn test_concatenated_archives_open_as_sections() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut joined = Vec::new();
+    for (name, body) in [("a.txt", &b"first archive"[..]), ("b.txt", &b"second archive"[..])] {
+        let path = dir.path().join(format!("{name}.6cy"));
+        let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+        ar.add_file(name, body).unwrap();
+        ar.finalize().unwrap();
+        joined.extend(std::fs::read(&path).unwrap());
+    }
+    let first_len = std::fs::metadata(dir.path().join("a.txt.6cy")).unwrap().len();
+    joined.extend_from_slice(b"trailing bytes that are not an archive");
+    let path = dir.path().join("joined.6cy");
+    std::fs::write(&path, &joined).unwrap();
+
+    let sections = Archive::sections(&path).unwrap();
+    assert_eq!(sections, vec![0, first_len]);
+    assert_eq!(Archive::open(&path).unwrap().read_file("a.txt").unwrap(), b"first archive");
+    let mut second = Archive::open_section(&path, sections[1], None).unwrap();
+    assert_eq!(second.read_file("b.txt").unwrap(), b"second archive");
+    let out = dir.path().join("out");
+    second.extract_with(&out, &Default::default()).unwrap();
+    assert_eq!(std::fs::read(out.join("b.txt")).unwrap(), b"second archive");
+
+    assert_eq!(Archive::sections(dir.path().join("a.txt.6cy")).unwrap(), vec![0]);
+}
+
+#[test]
+fn test_read_at_random_offsets_across_snapshot_chain() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let base = dir.path().join("base.6cy");
+    let snap = dir.path().join("snap.6cy");
+    let opts = || PackOptions { chunk_size: 1000, ..PackOptions::default() };
+    let old: Vec<u8> = (0..50_000u32).map(|i| (i * 13 % 251) as u8).collect();
+    let mut new = old.clone();
+    new[25_500..26_500].fill(0xEE);
+    new.extend_from_slice(b"tail");
+
+    let mut ar = Archive::create(&base, opts()).unwrap();
+    ar.add_file("f", &old).unwrap();
+    ar.finalize().unwrap();
+    let base_ar = Archive::open(&base).unwrap();
+    let mut ar = Archive::create_incremental(&snap, opts(), &base_ar).unwrap();
+    ar.add_file("f", &new).unwrap();
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open_chain(&snap, &[&base]).unwrap();
+    for (offset, len) in [(0, 10), (999, 2), (1000, 1000), (25_400, 1_300), (49_990, 100), (50_003, 8)] {
+        let mut buf = vec![0u8; len];
+        let n = ar.read_at("f", offset as u64, &mut buf).unwrap();
+        let end = (offset + len).min(new.len());
+        assert_eq!(&buf[..n], &new[offset..end], "offset {offset}");
+    }
+    assert_eq!(ar.read_at("f", new.len() as u64, &mut [0u8; 4]).unwrap(), 0);
+}
+
+#[test]
+fn test_solid_session_rolls_over_by_size_and_count() {
+    use std::collections::HashSet;
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::io_stream::SixCyReader;
+
+    let dir = tempfile::tempdir().unwrap();
+    let cases = [(None, None, 1), (None, Some(3), 4), (Some(250), None, 5), (Some(250), Some(1), 10)];
+    for (max_bytes, max_files, blocks) in cases {
+        let path = dir.path().join("s.6cy");
+        let opts = PackOptions {
+            solid_block_max_bytes: max_bytes,
+            max_files_per_solid:   max_files,
+            ..PackOptions::default()
+        };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.begin_solid(CodecId::Zstd).unwrap();
+        for i in 0..10u8 {
+            ar.add_file(&format!("f{i}"), &[i; 100]).unwrap();
+        }
+        ar.end_solid().unwrap();
+        ar.finalize().unwrap();
+
+        let reader = SixCyReader::new(File::open(&path).unwrap()).unwrap();
+        let offsets: HashSet<u64> = reader.index.records.iter()
+            .flat_map(|r| r.block_refs.iter().map(|br| br.archive_offset))
+            .collect();
+        assert_eq!(offsets.len(), blocks, "{max_bytes:?} {max_files:?}");
+
+        let mut ar = Archive::open(&path).unwrap();
+        for i in 0..10u8 {
+            assert_eq!(ar.read_file(&format!("f{i}")).unwrap(), [i; 100]);
+        }
+    }
+}
+
+#[test]
+fn test_open_resilient_falls_back_to_block_scan() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("r.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    ar.add_file("one.txt", b"first payload").unwrap();
+    ar.add_file("two.txt", b"second payload").unwrap();
+    ar.finalize().unwrap();
+
+    let ar = Archive::open_resilient(&path, None).unwrap();
+    assert!(!ar.is_reconstructed());
+    assert_eq!(ar.list()[0].name, "one.txt");
+
+    // Corrupt the INDEX payload.
+    let sb = sixcy::superblock::Superblock::read(&mut File::open(&path).unwrap()).unwrap();
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[(sb.index_offset + sixcy::block::BLOCK_HEADER_SIZE as u64 + 4) as usize] ^= 0xFF;
+    std::fs::write(&path, &bytes).unwrap();
+    assert!(Archive::open(&path).is_err());
+
+    let mut ar = Archive::open_resilient(&path, None).unwrap();
+    assert!(ar.is_reconstructed());
+    let names: Vec<String> = ar.list().into_iter().map(|f| f.name).collect();
+    assert_eq!(names, ["file_00000000", "file_00000001"]);
+    assert_eq!(ar.read_file("file_00000001").unwrap(), b"second payload");
+}
+
+#[test]
+fn test_content_hash_lookup() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("h.6cy");
+    let big: Vec<u8> = (0..3000u32).map(|i| (i % 199) as u8).collect();
+    let mut ar = Archive::create(&path, PackOptions { chunk_size: 1024, ..PackOptions::default() }).unwrap();
+    ar.add_file("a", b"shared content").unwrap();
+    ar.add_file("b", b"shared content").unwrap();
+    ar.add_file("big", &big).unwrap();
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    ar.add_file("s1", b"solid one").unwrap();
+    ar.add_file("s2", b"solid two").unwrap();
+    ar.end_solid().unwrap();
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open(&path).unwrap();
+    let hash = |d: &[u8]| -> [u8; 32] { blake3::hash(d).into() };
+    let names = |v: Vec<sixcy::archive::FileInfo>| v.into_iter().map(|f| f.name).collect::<Vec<_>>();
+
+    assert_eq!(names(ar.find_by_hash(&hash(b"shared content"))), ["a", "b"]);
+    assert_eq!(names(ar.find_by_hash(&hash(&big[1024..2048]))), ["big"]);
+    assert_eq!(names(ar.find_by_hash(&hash(b"solid two"))), ["s2"]);
+    assert!(ar.find_by_hash(&hash(b"absent")).is_empty());
+
+    assert_eq!(ar.read_block_by_hash(&hash(&big[2048..])).unwrap(), &big[2048..]);
+    assert_eq!(ar.read_block_by_hash(&hash(b"solid one")).unwrap(), b"solid one");
+    let err = ar.read_block_by_hash(&hash(b"absent")).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_secondary_hash_recorded_per_file() {
+    use sha2::Digest;
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::index::HashAlgorithm;
+    use sixcy::io_stream::AutoSolid;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sha.6cy");
+    let big: Vec<u8> = (0..10_000u32).map(|i| (i % 241) as u8).collect();
+    let opts = PackOptions {
+        chunk_size:     1024,
+        auto_solid:     Some(AutoSolid { threshold: 100, ..AutoSolid::default() }),
+        secondary_hash: Some(HashAlgorithm::Sha256),
+        ..PackOptions::default()
+    };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("big.bin", &big).unwrap();
+    ar.add_file("tiny.txt", b"tiny").unwrap();
+    ar.finalize().unwrap();
+
+    let ar = Archive::open(&path).unwrap();
+    for (name, data) in [("big.bin", &big[..]), ("tiny.txt", &b"tiny"[..])] {
+        let h = ar.stat(name).unwrap().secondary_hash.unwrap();
+        assert_eq!(h.algorithm, HashAlgorithm::Sha256);
+        assert_eq!(h.digest, sha2::Sha256::digest(data).to_vec());
+    }
+
+    let plain = dir.path().join("plain.6cy");
+    let mut ar = Archive::create(&plain, PackOptions::default()).unwrap();
+    ar.add_file("x", b"x").unwrap();
+    ar.finalize().unwrap();
+    assert!(Archive::open(&plain).unwrap().stat("x").unwrap().secondary_hash.is_none());
+}
+
+#[test]
+fn test_entries_pages_through_listing() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("e.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    for i in 0..10 {
+        ar.add_file(&format!("f{i}"), &[i as u8]).unwrap();
+    }
+    ar.finalize().unwrap();
+
+    let ar = Archive::open(&path).unwrap();
+    let page: Vec<String> = ar.entries().skip(3).take(4).map(|f| f.name).collect();
+    assert_eq!(page, ["f3", "f4", "f5", "f6"]);
+    assert_eq!(ar.entries().count(), ar.list().len());
+    assert_eq!(ar.stat("f9").unwrap().original_size, 1);
+}
+
+#[test]
+fn test_codec_bench_matrix() {
+    use sixcy::bench::{self, BenchOptions};
+
+    let data: Vec<u8> = b"the quick brown fox ".iter().cycle().take(256 * 1024).copied().collect();
+    let opts = BenchOptions { chunk_size: 64 * 1024, levels: Some(vec![1, 3]) };
+    let results = bench::run(&data, &opts).unwrap();
+
+    let zstd: Vec<i32> = results.iter().filter(|r| r.codec == "zstd").map(|r| r.level).collect();
+    assert_eq!(zstd, [1, 3]);
+    assert_eq!(results.iter().filter(|r| r.codec == "lzma").count(), 1);
+    assert!(results.iter().all(|r| r.input_bytes == data.len() as u64));
+
+    let best = bench::recommend(&results).unwrap();
+    assert_ne!(best.codec, "none");
+    assert!(best.ratio() < 0.1);
+}
+
+#[test]
+fn test_auto_level_stays_low_on_incompressible_data() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::io_stream::AutoLevel;
+
+    let mut noise = vec![0u8; 256 * 1024];
+    blake3::Hasher::new().update(b"noise").finalize_xof().fill(&mut noise);
+    let text: Vec<u8> = (0..20_000u32)
+        .flat_map(|i| format!("record {} value {} ", i % 97, i * 7 % 1013).into_bytes())
+        .collect();
+
+    let auto = AutoLevel { sample_bytes: 64 * 1024, ..AutoLevel::default() };
+    assert_eq!(auto.pick(CodecId::Zstd, &noise).unwrap(), Some(1));
+    assert!(CodecId::Zstd.level_sweep().contains(&auto.pick(CodecId::Zstd, &text).unwrap().unwrap()));
+    assert_eq!(auto.pick(CodecId::Lz4, &text).unwrap(), None);
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("auto.6cy");
+    let opts = PackOptions { auto_level: Some(auto), ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("noise.bin", &noise).unwrap();
+    ar.add_file("text.txt", &text).unwrap();
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open(&path).unwrap();
+    assert_eq!(ar.read_file("noise.bin").unwrap(), noise);
+    assert_eq!(ar.read_file("text.txt").unwrap(), text);
+    assert!(ar.stat("text.txt").unwrap().compressed_size < text.len() as u64 / 4);
+}
+
+#[test]
+fn test_deep_scan_flags_rotten_payload() {
+    use sixcy::block::BLOCK_HEADER_SIZE;
+    use sixcy::recovery::{scan, scan_deep, BlockHealth};
+    use sixcy::superblock::SUPERBLOCK_SIZE;
+
+    let temp = NamedTempFile::new().unwrap();
+    {
+        let mut w = SixCyWriter::with_options(File::create(temp.path()).unwrap(), 4096, 3, None).unwrap();
+        let data: Vec<u8> = (0..12_000u32).map(|i| (i % 7) as u8).collect();
+        w.add_file("f".into(), &data, CodecId::Zstd).unwrap();
+        w.finalize().unwrap();
+    }
+    // Flip a payload byte of the first block; its header stays valid.
+    let mut bytes = std::fs::read(temp.path()).unwrap();
+    bytes[SUPERBLOCK_SIZE + BLOCK_HEADER_SIZE + 4] ^= 0xFF;
+    let mut cur = std::io::Cursor::new(bytes);
+
+    let shallow = scan::<_, fn(u64, u64)>(&mut cur, 0, None).unwrap();
+    assert_eq!(shallow.payload_corrupt_blocks, 0);
+    assert_eq!(shallow.block_log[0].health, BlockHealth::Healthy);
+
+    let deep = scan_deep::<_, fn(u64, u64)>(&mut cur, 0, None, None).unwrap();
+    assert_eq!(deep.payload_corrupt_blocks, 1);
+    assert!(matches!(deep.block_log[0].health, BlockHealth::PayloadCorrupt { .. }));
+    assert_eq!(deep.healthy_blocks, shallow.healthy_blocks - 1);
+    assert!(deep.block_log[1..].iter().all(|b| b.health == BlockHealth::Healthy));
+}
+
+#[test]
+fn test_recover_keeps_unknown_codec_blocks_raw() {
+    use sixcy::archive::Archive;
+    use sixcy::block::encode_block;
+    use sixcy::recovery::{extract_recoverable_with, RecoveryOptions};
+    use sixcy::BlockType;
+
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src.6cy");
+    let offset = {
+        let mut w = SixCyWriter::new(File::create(&src).unwrap()).unwrap();
+        w.add_file("known".into(), b"plain data", CodecId::Zstd).unwrap();
+        let (mut header, payload) = encode_block(BlockType::Data, 7, 0, b"mystery", CodecId::None, 0, None).unwrap();
+        header.codec_uuid = [0xAB; 16];
+        let offset = w.write_raw_block(&header, &payload).unwrap();
+        w.finalize().unwrap();
+        offset
+    };
+
+    let recover = |raw: bool| {
+        let out  = dir.path().join(format!("out-{raw}.6cy"));
+        let opts = RecoveryOptions { include_unknown_codec_raw: raw, ..RecoveryOptions::default() };
+        let report = extract_recoverable_with(&mut File::open(&src).unwrap(),
+                                              &mut File::create(&out).unwrap(), None, &opts).unwrap();
+        assert_eq!(report.unknown_codec_blocks, 1);
+        Archive::open(&out).unwrap()
+    };
+
+    assert!(recover(false).list().iter().all(|f| !f.name.starts_with("unknown_codec/")));
+
+    let mut ar  = recover(true);
+    let stem    = format!("unknown_codec/{offset:016x}");
+    assert_eq!(ar.read_file(&format!("{stem}.bin")).unwrap(), b"mystery");
+    let sidecar: serde_json::Value = serde_json::from_slice(&ar.read_file(&format!("{stem}.json")).unwrap()).unwrap();
+    assert_eq!(sidecar["codec_uuid"], sixcy::codec::uuid_to_string(&[0xAB; 16]));
+    assert_eq!(sidecar["file_id"], 7);
+    assert_eq!(sidecar["orig_size"], 7);
+    assert_eq!(sidecar["content_hash"], hex::encode(blake3::hash(b"mystery").as_bytes()));
+}
+
+#[test]
+fn test_health_check_samples_and_escalates() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::block::BLOCK_HEADER_SIZE;
+    use sixcy::superblock::SUPERBLOCK_SIZE;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("h.6cy");
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * 31 % 253) as u8).collect();
+    let mut ar = Archive::create(&path, PackOptions { chunk_size: 1000, ..PackOptions::default() }).unwrap();
+    ar.add_file("f", &data).unwrap();
+    ar.finalize().unwrap();
+
+    let report = Archive::open(&path).unwrap().health_check(1.0).unwrap();
+    assert_eq!((report.blocks_total, report.blocks_checked), (20, 20));
+    assert!(report.is_healthy() && !report.escalated);
+    assert_eq!(Archive::open(&path).unwrap().health_check(0.1).unwrap().blocks_checked, 2);
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[SUPERBLOCK_SIZE + BLOCK_HEADER_SIZE + 2] ^= 0xFF;
+    std::fs::write(&path, bytes).unwrap();
+
+    let report = Archive::open(&path).unwrap().health_check(1.0).unwrap();
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].0, SUPERBLOCK_SIZE as u64);
+    assert!(report.score() < 1.0);
+    for _ in 0..10 {
+        let r = Archive::open(&path).unwrap().health_check(0.0).unwrap();
+        match r.escalated {
+            true  => assert_eq!((r.blocks_checked, r.failures.len()), (20, 1)),
+            false => assert_eq!((r.blocks_checked, r.failures.len()), (1, 0)),
+        }
+    }
+}
+
+#[test]
+fn test_fsck_resurrects_orphaned_blocks() {
+    use sixcy::archive::Archive;
+    use sixcy::optimize::{optimize, OptimizeOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("o.6cy");
+    let lost: Vec<u8> = (0..3000u32).map(|i| (i * 7 % 241) as u8).collect();
+    {
+        let mut w = SixCyWriter::with_options(File::create(&path).unwrap(), 1000, 3, None).unwrap();
+        w.add_file("a".into(), b"alpha", CodecId::Zstd).unwrap();
+        w.add_file("b".into(), &lost, CodecId::Zstd).unwrap();
+        w.add_file("c".into(), b"gamma", CodecId::Zstd).unwrap();
+        w.index.records.retain(|r| r.name != "b"); // an index edit gone wrong
+        w.finalize().unwrap();
+    }
+
+    let mut ar = Archive::open(&path).unwrap();
+    let orphans = ar.orphans().unwrap();
+    assert_eq!(orphans.len(), 1);
+    assert_eq!((orphans[0].name.as_str(), orphans[0].block_count), ("orphan_00000001", 3));
+    assert!(ar.stat("orphan_00000001").is_none());
+
+    ar.resurrect_orphans().unwrap();
+    assert_eq!(ar.read_file("orphan_00000001").unwrap(), lost);
+    assert!(ar.orphans().unwrap().is_empty());
+
+    let fixed = dir.path().join("fixed.6cy");
+    optimize(&mut ar, &fixed, &OptimizeOptions::default()).unwrap();
+    let mut ar = Archive::open(&fixed).unwrap();
+    assert_eq!(ar.list().len(), 3);
+    assert_eq!(ar.read_file("orphan_00000001").unwrap(), lost);
+    assert!(ar.orphans().unwrap().is_empty());
+}
+
+#[test]
+fn test_scan_recovers_archive_without_superblock() {
+    use sixcy::archive::Archive;
+    use sixcy::recovery::{extract_recoverable, scan};
+    use sixcy::superblock::SUPERBLOCK_SIZE;
+
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * 31 % 251) as u8).collect();
+    let temp = NamedTempFile::new().unwrap();
+    {
+        let mut w = SixCyWriter::with_options(File::create(temp.path()).unwrap(), 4096, 3, None).unwrap();
+        w.add_file("f".into(), &data, CodecId::Zstd).unwrap();
+        w.finalize().unwrap();
+    }
+    let bytes  = std::fs::read(temp.path()).unwrap();
+    let intact = scan::<_, fn(u64, u64)>(&mut std::io::Cursor::new(&bytes), 0, None).unwrap();
+    assert!(intact.superblock_valid);
+
+    let mut zeroed = bytes.clone();
+    zeroed[..SUPERBLOCK_SIZE].fill(0);
+    // Cut off and replaced with junk shorter than a superblock.
+    let mut truncated = vec![0x5Au8; 37];
+    truncated.extend_from_slice(&bytes[SUPERBLOCK_SIZE..]);
+
+    let dir = tempfile::tempdir().unwrap();
+    for (i, damaged) in [zeroed, truncated].into_iter().enumerate() {
+        let report = scan::<_, fn(u64, u64)>(&mut std::io::Cursor::new(&damaged), 0, None).unwrap();
+        assert!(!report.superblock_valid);
+        assert_eq!(report.corrupt_blocks, 0);
+        assert_eq!(report.healthy_blocks, intact.healthy_blocks);
+
+        let out = dir.path().join(format!("out-{i}.6cy"));
+        extract_recoverable(&mut std::io::Cursor::new(&damaged), &mut File::create(&out).unwrap(), None).unwrap();
+        let mut ar = Archive::open(&out).unwrap();
+        let name = ar.list()[0].name.clone();
+        assert_eq!(ar.read_file(&name).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_scan_reports_gaps_and_overlaps() {
+    use sixcy::block::{BlockHeader, BLOCK_HEADER_SIZE};
+    use sixcy::recovery::{scan, BlockHealth};
+    use sixcy::superblock::SUPERBLOCK_SIZE;
+
+    let temp = NamedTempFile::new().unwrap();
+    {
+        let mut w = SixCyWriter::with_options(File::create(temp.path()).unwrap(), 4096, 3, None).unwrap();
+        let data: Vec<u8> = (0..16_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        w.add_file("f".into(), &data, CodecId::Zstd).unwrap();
+        w.finalize().unwrap();
+    }
+    let bytes  = std::fs::read(temp.path()).unwrap();
+    let intact = scan::<_, fn(u64, u64)>(&mut std::io::Cursor::new(&bytes), 0, None).unwrap();
+    assert!(intact.block_log.iter().all(|b| b.gap == 0));
+    let offsets: Vec<usize> = intact.block_log.iter().map(|b| b.archive_offset as usize).collect();
+
+    // Destroy the second header: the third block follows a gap the size of
+    // the second block.
+    let mut holed = bytes.clone();
+    holed[offsets[1]..offsets[1] + BLOCK_HEADER_SIZE].fill(0);
+    let report = scan::<_, fn(u64, u64)>(&mut std::io::Cursor::new(&holed), 0, None).unwrap();
+    assert_eq!(report.block_log[1].health, BlockHealth::HeaderCorrupt);
+    assert_eq!(report.block_log[2].archive_offset as usize, offsets[2]);
+    assert_eq!(report.block_log[2].gap, (offsets[2] - offsets[1]) as i64);
+    assert!(report.block_log[2].is_gap());
+
+    // Inflate the first block's size field past EOF: the scan resyncs on
+    // the second block and reports the overlap.
+    let mut inflated = bytes.clone();
+    let mut header = BlockHeader::read(&bytes[SUPERBLOCK_SIZE..]).unwrap();
+    header.comp_size = bytes.len() as u32;
+    header.write(&mut inflated[SUPERBLOCK_SIZE..SUPERBLOCK_SIZE + BLOCK_HEADER_SIZE]).unwrap();
+    let report = scan::<_, fn(u64, u64)>(&mut std::io::Cursor::new(&inflated), 0, None).unwrap();
+    assert!(matches!(report.block_log[0].health, BlockHealth::TruncatedPayload { .. }));
+    assert_eq!(report.block_log[1].archive_offset as usize, offsets[1]);
+    assert!(report.block_log[1].is_overlap());
+    assert_eq!(report.block_log[1].gap, offsets[1] as i64 - (offsets[0] + BLOCK_HEADER_SIZE + bytes.len()) as i64);
+    assert_eq!(report.block_log[1].health, BlockHealth::Healthy);
+}
+
+#[test]
+fn test_recovery_reports_per_file_status() {
+    use sixcy::block::BLOCK_HEADER_SIZE;
+    use sixcy::recovery::{scan, scan_deep, FileStatus};
+
+    let temp = NamedTempFile::new().unwrap();
+    {
+        let mut w = SixCyWriter::with_options(File::create(temp.path()).unwrap(), 4096, 3, None).unwrap();
+        let data: Vec<u8> = (0..16_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        w.add_file("a".into(), &data, CodecId::Zstd).unwrap();
+        let other: Vec<u8> = data.iter().map(|b| b ^ 0x55).take(9_000).collect();
+        w.add_file("b".into(), &other, CodecId::Zstd).unwrap();
+        w.finalize().unwrap();
+    }
+    let bytes = std::fs::read(temp.path()).unwrap();
+    let deep  = scan_deep::<_, fn(u64, u64)>(&mut std::io::Cursor::new(&bytes), 0, None, None).unwrap();
+    assert_eq!(deep.files.len(), 2);
+    assert!(deep.files.iter().all(|f| f.status == FileStatus::HashVerified));
+    let shallow = scan::<_, fn(u64, u64)>(&mut std::io::Cursor::new(&bytes), 0, None).unwrap();
+    assert!(shallow.files.iter().all(|f| f.status == FileStatus::Complete));
+
+    // Destroy the header of file a's second chunk, and rot the payload of
+    // file b's first chunk.
+    let a_second = shallow.block_log[1].archive_offset as usize;
+    let b_first  = shallow.block_log.iter()
+        .find(|b| b.header.as_ref().is_some_and(|h| h.file_id == 1))
+        .unwrap().archive_offset as usize;
+    let mut damaged = bytes.clone();
+    damaged[a_second..a_second + BLOCK_HEADER_SIZE].fill(0);
+    damaged[b_first + BLOCK_HEADER_SIZE + 4] ^= 0xFF;
+
+    let report = scan_deep::<_, fn(u64, u64)>(&mut std::io::Cursor::new(&damaged), 0, None, None).unwrap();
+    assert_eq!(report.files[0].file_id, 0);
+    assert_eq!(report.files[0].status, FileStatus::Incomplete { missing: 1, total: 4 });
+    assert_eq!(report.files[0].chunks_found, 3);
+    assert_eq!(report.files[1].status, FileStatus::Incomplete { missing: 1, total: 3 });
+    assert!(!report.files[1].is_complete());
+}
+
+#[test]
+fn test_recover_into_custom_sinks() {
+    use sixcy::recovery::{extract_recoverable_into, DirSink, RecoveryOptions};
+
+    let temp = NamedTempFile::new().unwrap();
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i % 13) as u8).collect();
+    {
+        let mut w = SixCyWriter::with_options(File::create(temp.path()).unwrap(), 4096, 3, None).unwrap();
+        w.add_file("f".into(), &data, CodecId::Zstd).unwrap();
+        w.add_file("g".into(), b"second", CodecId::Lz4).unwrap();
+        w.finalize().unwrap();
+    }
+    let opts = RecoveryOptions::default();
+
+    let mut seen = Vec::new();
+    let mut collect = |name: &str, bytes: &[u8], raw: bool| {
+        assert!(!raw);
+        seen.push((name.to_owned(), bytes.to_vec()));
+        Ok(())
+    };
+    extract_recoverable_into(&mut File::open(temp.path()).unwrap(), &mut collect, None, &opts).unwrap();
+    assert_eq!(seen.len(), 2);
+    assert_eq!(seen[0].1, data);
+    assert_eq!(seen[1].1, b"second");
+
+    let dir  = tempfile::tempdir().unwrap();
+    let root = dir.path().join("out");
+    extract_recoverable_into(&mut File::open(temp.path()).unwrap(), &mut DirSink::new(&root), None, &opts).unwrap();
+    for (name, bytes) in &seen {
+        assert_eq!(&std::fs::read(root.join(name)).unwrap(), bytes);
+    }
+}
+
+#[test]
+fn test_recover_reports_scan_progress() {
+    use sixcy::recovery::{extract_recoverable_with_progress, RecoveryOptions};
+    use std::process::Command;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("p.6cy");
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i % 251) as u8).collect();
+    {
+        let mut w = SixCyWriter::with_options(File::create(&path).unwrap(), 4096, 3, None).unwrap();
+        w.add_file("f".into(), &data, CodecId::Zstd).unwrap();
+        w.finalize().unwrap();
+    }
+    let size = std::fs::metadata(&path).unwrap().len();
+
+    let mut calls = Vec::new();
+    let report = extract_recoverable_with_progress(
+        &mut File::open(&path).unwrap(),
+        &mut std::io::Cursor::new(Vec::new()),
+        None,
+        &RecoveryOptions::default(),
+        &mut |done, total| calls.push((done, total)),
+    ).unwrap();
+    assert_eq!(calls.len(), report.total_scanned);
+    assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+    assert!(calls.iter().all(|&(done, total)| total == size && done <= size));
+
+    let out = Command::new(env!("CARGO_BIN_EXE_6cy"))
+        .args(["recover", "--progress-json", "-o"])
+        .arg(dir.path().join("r.6cy"))
+        .arg(&path)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let lines: Vec<serde_json::Value> = String::from_utf8(out.stderr).unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let last = lines.last().unwrap();
+    assert_eq!(last["event"], "done");
+    assert_eq!(last["blocks"], report.total_scanned as u64);
+    assert_eq!(last["bytes_total"], size);
+}
+
+#[test]
+fn test_cli_exit_codes() {
+    use sixcy::archive::{Archive, PackOptions};
+    use std::process::Command;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("e.6cy");
+    let opts = PackOptions { password: Some("right".into()), ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    let noise: Vec<u8> = (0..50_000u32).map(|i| (i.wrapping_mul(2654435761) >> 11) as u8).collect();
+    ar.add_file("a", &noise).unwrap();
+    ar.finalize().unwrap();
+
+    let status = |args: &[&std::ffi::OsStr]| {
+        Command::new(env!("CARGO_BIN_EXE_6cy")).args(args).output().unwrap().status.code()
+    };
+    let os = |s: &str| std::ffi::OsString::from(s);
+    let unpack = |password: &str| status(&[
+        &os("unpack"), path.as_os_str(), &os("-C"), dir.path().join("out").as_os_str(), &os("-p"), &os(password),
+    ]);
+    assert_eq!(unpack("right"), Some(0));
+    assert_eq!(unpack("wrong"), Some(4));
+
+    let junk = dir.path().join("junk.6cy");
+    std::fs::write(&junk, vec![0xAAu8; 4096]).unwrap();
+    assert_eq!(status(&[&os("list"), junk.as_os_str()]), Some(3));
+    assert_eq!(status(&[&os("list"), dir.path().join("absent.6cy").as_os_str()]), Some(1));
+    assert_eq!(status(&[&os("no-such-command")]), Some(2));
+
+    // Cut off mid-block: recover salvages what it can and says so.
+    let bytes = std::fs::read(&path).unwrap();
+    let cut   = dir.path().join("cut.6cy");
+    std::fs::write(&cut, &bytes[..bytes.len() / 2]).unwrap();
+    let recovered = dir.path().join("r.6cy");
+    assert_eq!(status(&[&os("recover"), cut.as_os_str(), &os("-o"), recovered.as_os_str()]), Some(6));
+
+    let help = Command::new(env!("CARGO_BIN_EXE_6cy")).arg("--help").output().unwrap();
+    assert!(String::from_utf8(help.stdout).unwrap().contains("Exit codes:"));
+}
+
+#[test]
+fn test_chunk_size_validation_and_codec_defaults() {
+    use sixcy::io_stream::{validate_chunk_size, ChunkSizeWarning, DEFAULT_CHUNK_SIZE};
+    use std::process::Command;
+
+    assert_eq!(validate_chunk_size(64 * 1024).unwrap(), []);
+    assert_eq!(validate_chunk_size(1 << 30).unwrap(), [ChunkSizeWarning::DefeatsDedup { chunk_size: 1 << 30 }]);
+    for bad in [0, 4096, (1 << 30) + 1] {
+        assert_eq!(validate_chunk_size(bad).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+    assert!(CodecId::Lzma.default_chunk_size() > DEFAULT_CHUNK_SIZE);
+    assert!(CodecId::Lz4.default_chunk_size() < DEFAULT_CHUNK_SIZE);
+    assert_eq!(CodecId::Zstd.default_chunk_size(), DEFAULT_CHUNK_SIZE);
+
+    let dir   = tempfile::tempdir().unwrap();
+    let input = dir.path().join("in.bin");
+    std::fs::write(&input, vec![1u8; 3 * 1024 * 1024]).unwrap();
+    let pack = |args: &[&str]| Command::new(env!("CARGO_BIN_EXE_6cy"))
+        .arg("pack").arg("-o").arg(dir.path().join("o.6cy")).arg("-i").arg(&input)
+        .args(args)
+        .output()
+        .unwrap();
+
+    let out = pack(&["--chunk-size", "16"]);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("--chunk-size"));
+    let out = pack(&["--chunk-size", "131072"]);
+    assert!(out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Warning: --chunk-size"));
+
+    // LZ4's 1 MiB default cuts the 3 MiB input into three chunks.
+    assert!(pack(&["--codec", "lz4"]).status.success());
+    let ar = sixcy::Archive::open(dir.path().join("o.6cy")).unwrap();
+    assert_eq!(ar.list()[0].block_count, 3);
+}
+
+#[test]
+fn test_atomic_create_appears_only_on_finalize() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir    = tempfile::tempdir().unwrap();
+    let target = dir.path().join("out.6cy");
+    let opts   = PackOptions { atomic: true, ..PackOptions::default() };
+
+    // Aborted: nothing is left in the directory.
+    {
+        let mut ar = Archive::create(&target, opts.clone()).unwrap();
+        ar.add_file("a", b"abandoned").unwrap();
+    }
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+
+    // An existing file is replaced only once the new archive is complete.
+    std::fs::write(&target, b"previous").unwrap();
+    let mut ar = Archive::create(&target, opts).unwrap();
+    ar.add_file("a", b"fresh").unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"previous");
+    ar.finalize().unwrap();
+    drop(ar);
+
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    assert_eq!(Archive::open(&target).unwrap().read_file("a").unwrap(), b"fresh");
+}
+
+#[test]
+fn test_levels_per_block_type() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::Superblock;
+
+    let dir = tempfile::tempdir().unwrap();
+    let pack = |name: &str, opts: PackOptions| {
+        let path = dir.path().join(name);
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.begin_solid(CodecId::Zstd).unwrap();
+        for i in 0..300 {
+            let text = format!("entry {i}: the quick brown fox jumps over the lazy dog {}\n", i % 17).repeat(40);
+            ar.add_file(&format!("dir/sub/file_{i:04}.txt"), text.as_bytes()).unwrap();
+        }
+        ar.end_solid().unwrap();
+        ar.finalize().unwrap();
+        let sb = Superblock::read(&mut File::open(&path).unwrap()).unwrap();
+        assert_eq!(Archive::open(&path).unwrap().list().len(), 300);
+        (std::fs::metadata(&path).unwrap().len() - sb.index_size, sb.index_size)
+    };
+
+    let base          = PackOptions { level: 1, ..PackOptions::default() };
+    let (data, index) = pack("default.6cy", base.clone());
+    let (_, fast_idx) = pack("fast-index.6cy", PackOptions { index_level: 1, ..base.clone() });
+    let (strong, _)   = pack("strong-solid.6cy", PackOptions { solid_level: Some(19), ..base });
+    assert!(index < fast_idx, "index {index} vs {fast_idx}");
+    assert!(strong < data, "solid {strong} vs {data}");
+}
+
+#[test]
+fn test_solid_member_ordering() {
+    use sixcy::io_stream::{SixCyReader, SolidOrder};
+
+    // Two families of 6000-byte files whose first 4 KiB are shared within
+    // the family; each file differs past the MinHash sample.
+    let family = |seed: u32, i: u8| -> Vec<u8> {
+        let mut data: Vec<u8> = (0..6000u32).map(|j| (j.wrapping_mul(seed) >> 7) as u8).collect();
+        data[5000] = i;
+        data
+    };
+    let files: Vec<(String, Vec<u8>)> = (0..6u8)
+        .flat_map(|i| [
+            (format!("x{i}.bin"), family(2_654_435_761, i)),
+            (format!("y{i}.txt"), family(40_503, i)),
+        ])
+        .collect();
+
+    for order in [SolidOrder::Insertion, SolidOrder::Extension, SolidOrder::Size, SolidOrder::Similarity] {
+        let mut buf = std::io::Cursor::new(Vec::new());
+        {
+            let mut w = SixCyWriter::new(&mut buf).unwrap();
+            w.solid_order = order;
+            w.start_solid_session(CodecId::Zstd).unwrap();
+            for (name, data) in &files {
+                w.add_file(name.clone(), data, CodecId::Zstd).unwrap();
+            }
+            w.add_file("tiny".into(), b"t", CodecId::Zstd).unwrap();
+            w.finalize().unwrap();
+        }
+        buf.set_position(0);
+        let mut r = SixCyReader::new(buf).unwrap();
+        for (name, data) in &files {
+            let id = r.index.records.iter().find(|rec| &rec.name == name).unwrap().id;
+            assert_eq!(&r.unpack_file(id).unwrap(), data, "{order:?} {name}");
+        }
+
+        // Names in buffer order; index records stay in insertion order.
+        let mut layout: Vec<_> = r.index.records.iter()
+            .map(|rec| (rec.block_refs[0].intra_offset, rec.name.clone()))
+            .collect();
+        assert_eq!(r.index.records[0].name, "x0.bin");
+        layout.sort();
+        let names: Vec<&str> = layout.iter().map(|(_, n)| n.as_str()).collect();
+        let switches = names.windows(2).filter(|w| w[0].as_bytes()[0] != w[1].as_bytes()[0]).count();
+        match order {
+            SolidOrder::Insertion  => assert_eq!(names[..2], ["x0.bin", "y0.txt"]),
+            SolidOrder::Extension  => assert_eq!(names[..3], ["tiny", "x0.bin", "x1.bin"]),
+            SolidOrder::Size       => assert_eq!(names[0], "tiny"),
+            SolidOrder::Similarity => assert!(switches <= 2, "{names:?}"),
+        }
+    }
+}
+
+#[test]
+fn test_solid_dedups_identical_members() {
+    use sixcy::io_stream::{SixCyReader, SolidOrder};
+
+    let a: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 256) as u8).collect();
+    let b = b"something else".to_vec();
+    for order in [SolidOrder::Insertion, SolidOrder::Size] {
+        let mut buf = std::io::Cursor::new(Vec::new());
+        {
+            let mut w = SixCyWriter::new(&mut buf).unwrap();
+            w.solid_order = order;
+            w.start_solid_session(CodecId::Zstd).unwrap();
+            w.add_file("a".into(), &a, CodecId::Zstd).unwrap();
+            w.add_file("b".into(), &b, CodecId::Zstd).unwrap();
+            w.add_file("a-copy".into(), &a, CodecId::Zstd).unwrap();
+            w.add_file("empty".into(), b"", CodecId::Zstd).unwrap();
+            w.flush_solid_session().unwrap();
+            w.start_solid_session(CodecId::Lz4).unwrap();
+            w.add_file("b-later".into(), &b, CodecId::Lz4).unwrap();
+            w.add_file("c".into(), b"c", CodecId::Lz4).unwrap();
+            w.finalize().unwrap();
+        }
+        buf.set_position(0);
+        let mut r = SixCyReader::new(buf).unwrap();
+        let refs: Vec<_> = r.index.records.iter()
+            .map(|rec| rec.block_refs.first().map(|br| (br.archive_offset, br.intra_offset, br.intra_length)))
+            .collect();
+        assert_eq!(refs[2], refs[0], "{order:?}: in-session copy shares the range");
+        assert_eq!(refs[3], None, "{order:?}: an empty member has no refs");
+        assert_eq!(refs[4], refs[1], "{order:?}: later copy points into the earlier block");
+        assert_ne!(refs[5].unwrap().0, refs[0].unwrap().0);
+
+        for (id, want) in [(0, &a[..]), (1, &b[..]), (2, &a[..]), (3, b""), (4, &b[..]), (5, b"c")] {
+            assert_eq!(r.unpack_file(id).unwrap(), want, "{order:?} id {id}");
+        }
+    }
+}
+
+#[test]
+fn test_compressed_size_accounting() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("acct.6cy");
+    let shared: Vec<u8> = (0..8192u32).map(|i| (i * 31 % 251) as u8).collect();
+    let own:    Vec<u8> = (0..8192u32).map(|i| (i * 17 % 241) as u8).collect();
+    {
+        let opts = PackOptions { chunk_size: 8192, ..PackOptions::default() };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        // "twice" repeats its chunk; "also" shares it with "twice".
+        ar.add_file("twice", &[shared.clone(), shared.clone()].concat()).unwrap();
+        ar.add_file("also",  &[shared.clone(), own.clone()].concat()).unwrap();
+        ar.begin_solid(CodecId::Zstd).unwrap();
+        ar.add_file("s1", &[b'x'; 3000]).unwrap();
+        ar.add_file("s2", &[b'y'; 1000]).unwrap();
+        ar.end_solid().unwrap();
+        ar.finalize().unwrap();
+    }
+
+    let ar = Archive::open(&path).unwrap();
+    let files: std::collections::HashMap<String, _> =
+        ar.list().into_iter().map(|f| (f.name.clone(), f)).collect();
+    let (twice, also) = (&files["twice"], &files["also"]);
+
+    // The shared chunk is charged once per file and split between the two;
+    // only "also" owns a chunk nobody else references.
+    assert_eq!(twice.unique_compressed_size, 0);
+    assert!(also.unique_compressed_size > 0);
+    assert_eq!(also.compressed_size - also.unique_compressed_size, twice.compressed_size);
+    assert!(twice.ratio() > 2.0);
+
+    // Solid members are charged by slice length and never 0.
+    let (s1, s2) = (&files["s1"], &files["s2"]);
+    assert!(s2.compressed_size > 0);
+    assert!(s1.compressed_size > s2.compressed_size);
+    assert_eq!(s1.unique_compressed_size, s1.compressed_size);
+}
+
+#[test]
+fn test_read_solid_member_range() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir   = tempfile::tempdir().unwrap();
+    let first: Vec<u8> = (0..4000u32).map(|i| (i * 13 % 256) as u8).collect();
+    let rest:  Vec<u8> = (0..1_000_000u32).map(|i| (i / 7 % 256) as u8).collect();
+    for codec in [CodecId::Zstd, CodecId::Brotli, CodecId::Lzma, CodecId::Lz4, CodecId::None] {
+        let path = dir.path().join(format!("{codec:?}.6cy"));
+        {
+            let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+            ar.add_file("chunked", &first).unwrap();
+            ar.begin_solid(codec).unwrap();
+            ar.add_file("first", &first).unwrap();
+            ar.add_file("rest", &rest).unwrap();
+            ar.end_solid().unwrap();
+            ar.finalize().unwrap();
+        }
+
+        let mut ar = Archive::open(&path).unwrap();
+        ar.enable_perf_stats();
+        assert_eq!(ar.read_solid_member_range("first", 100, 1000).unwrap(), &first[100..1100], "{codec:?}");
+        if matches!(codec, CodecId::Zstd | CodecId::Brotli | CodecId::None) {
+            assert!(ar.perf_stats().unwrap().bytes_out < 64 * 1024, "{codec:?} decoded past the range");
+        }
+        assert_eq!(ar.read_solid_member_range("rest", 999_990, 100).unwrap(), &rest[999_990..], "{codec:?}");
+        assert!(ar.read_solid_member_range("first", 5000, 10).unwrap().is_empty());
+        // Not a solid member: served by read_at.
+        assert_eq!(ar.read_solid_member_range("chunked", 3990, 100).unwrap(), &first[3990..]);
+    }
+}
+
+#[test]
+fn test_extract_strip_components_and_transform() {
+    use sixcy::archive::{Archive, ExtractOptions, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("deep.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    ar.add_file("build/out/bin/tool", b"binary").unwrap();
+    ar.add_file("build/out/lib/libx.a", b"library").unwrap();
+    ar.add_file("build/README", b"readme").unwrap();
+    ar.add_file("top", b"too shallow").unwrap();
+    ar.finalize().unwrap();
+
+    let opts = ExtractOptions {
+        strip_components: 2,
+        transforms:       vec![("bin/".into(), "usr/bin/".into())],
+        ..Default::default()
+    };
+    assert_eq!(opts.dest_name("./build//out/bin/tool").unwrap().as_deref(), Some("usr/bin/tool"));
+    assert_eq!(opts.dest_name("build/README").unwrap(), None);
+
+    let out = dir.path().join("out");
+    let report = Archive::open(&path).unwrap().extract_with(&out, &opts).unwrap();
+    assert_eq!((report.files, report.stripped), (2, 2));
+    assert_eq!(std::fs::read(out.join("usr/bin/tool")).unwrap(), b"binary");
+    assert_eq!(std::fs::read(out.join("lib/libx.a")).unwrap(), b"library");
+    assert!(!out.join("README").exists() && !out.join("top").exists());
+
+    // Entries left out cannot be attested.
+    let paranoid = ExtractOptions { paranoid: true, ..opts };
+    assert!(Archive::open(&path).unwrap().extract_with(dir.path().join("p"), &paranoid).is_err());
+}
+
+#[test]
+fn test_extract_refuses_names_leaving_the_destination() {
+    use sixcy::archive::{Archive, ExtractOptions, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let out  = dir.path().join("out");
+    let evil = dir.path().join("evil.6cy");
+    let mut ar = Archive::create(&evil, PackOptions::default()).unwrap();
+    ar.add_file("../escaped.txt", b"outside").unwrap();
+    ar.finalize().unwrap();
+    let err = Archive::open(&evil).unwrap().extract_with(&out, &ExtractOptions::default()).unwrap_err();
+    assert_eq!(std::io::Error::from(err).kind(), std::io::ErrorKind::InvalidInput);
+    assert!(!dir.path().join("escaped.txt").exists());
+
+    // A transform may not introduce `..` or an absolute path either.
+    let plain = dir.path().join("plain.6cy");
+    let mut ar = Archive::create(&plain, PackOptions::default()).unwrap();
+    ar.add_file("etc/passwd", b"inside").unwrap();
+    ar.finalize().unwrap();
+    for new in ["../", "/"] {
+        let opts = ExtractOptions { transforms: vec![("etc/".into(), new.into())], ..Default::default() };
+        assert!(opts.dest_name("etc/passwd").is_err());
+        assert!(Archive::open(&plain).unwrap().extract_with(&out, &opts).is_err());
+    }
+    assert!(!dir.path().join("passwd").exists());
+}
+
+#[test]
+fn test_pack_dir_round_trips_a_tree() {
+    use sixcy::archive::{Archive, ExtractOptions, PackOptions};
+    use sixcy::io_stream::AutoSolid;
+
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    std::fs::create_dir_all(src.join("docs/img")).unwrap();
+    std::fs::create_dir_all(src.join("empty")).unwrap();
+    std::fs::write(src.join("main.rs"), b"fn main() {}").unwrap();
+    std::fs::write(src.join("docs/guide.md"), b"# Guide").unwrap();
+    std::fs::write(src.join("docs/img/logo.bin"), vec![7u8; 20_000]).unwrap();
+    std::fs::write(src.join("docs/copy.md"), b"# Guide").unwrap();
+
+    // An archive left inside the tree by an earlier run is not packed into itself.
+    let dest = src.join("tree.6cy");
+    std::fs::write(&dest, b"stale").unwrap();
+    let opts = PackOptions { auto_solid: Some(AutoSolid::default()), ..PackOptions::default() };
+    let report = Archive::pack_dir(&src, &dest, opts).unwrap();
+    assert_eq!((report.files, report.dirs, report.skipped), (4, 3, 1));
+    assert_eq!(report.input_bytes, 12 + 7 + 20_000 + 7);
+    assert_eq!(report.archive_bytes, std::fs::metadata(&dest).unwrap().len());
+
+    let mut ar = Archive::open(&dest).unwrap();
+    let mut names: Vec<String> = ar.list().into_iter().map(|f| f.name).collect();
+    names.sort();
+    assert_eq!(names, ["docs/copy.md", "docs/guide.md", "docs/img/logo.bin", "main.rs"]);
+
+    let out = dir.path().join("out");
+    ar.extract_with(&out, &ExtractOptions::default()).unwrap();
+    assert_eq!(std::fs::read(out.join("docs/img/logo.bin")).unwrap(), vec![7u8; 20_000]);
+    assert_eq!(std::fs::read(out.join("docs/copy.md")).unwrap(), b"# Guide");
+}
+
+#[test]
+fn test_preallocated_output_is_trimmed() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    std::fs::create_dir_all(&src).unwrap();
+    std::fs::write(src.join("a.bin"), vec![3u8; 300_000]).unwrap();
+    std::fs::write(src.join("b.txt"), b"preallocated").unwrap();
+
+    // Same bytes with and without a reservation.
+    let opts = |preallocate| PackOptions { deterministic: true, preallocate, ..PackOptions::default() };
+    Archive::pack_dir(&src, dir.path().join("plain.6cy"), opts(false)).unwrap();
+    let report = Archive::pack_dir(&src, dir.path().join("pre.6cy"), opts(true)).unwrap();
+    let pre = std::fs::read(dir.path().join("pre.6cy")).unwrap();
+    assert_eq!(pre, std::fs::read(dir.path().join("plain.6cy")).unwrap());
+    assert_eq!(report.archive_bytes, pre.len() as u64);
+
+    // A reservation far beyond the output, with an atomic create.
+    let path = dir.path().join("big.6cy");
+    let mut ar = Archive::create(&path, PackOptions { atomic: true, ..PackOptions::default() }).unwrap();
+    ar.preallocate(64 * 1024 * 1024).unwrap();
+    ar.add_file("x", b"small").unwrap();
+    ar.finalize().unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() < 4096);
+    let mut ar = Archive::open(&path).unwrap();
+    assert!(!ar.is_index_relocated());
+    assert_eq!(ar.read_file("x").unwrap(), b"small");
+}
+
+#[test]
+fn test_streamed_pack_matches_in_memory() {
+    use sixcy::archive::{Archive, PackOptions};
+    use std::collections::HashMap;
+
+    // Distinct, repeated-within, and repeated-across chunks.
+    let noise: Vec<u8> = (0..200_000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+    let mut big = noise.clone();
+    big.extend_from_slice(&noise[..65_536]);
+    big.extend(std::iter::repeat_n(7u8, 150_000));
+    let files = [("big.bin", big), ("copy.bin", noise[..131_072].to_vec()), ("empty", Vec::new())];
+
+    let dir  = tempfile::tempdir().unwrap();
+    let pack = |name: &str, depth: usize, stream: bool| {
+        let path = dir.path().join(name);
+        let opts = PackOptions {
+            deterministic: true,
+            chunk_size:    65_536,
+            queue_depth:   Some(depth),
+            ..PackOptions::default()
+        };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        for (name, data) in &files {
+            match stream {
+                true  => ar.add_file_from_reader(name, &data[..], data.len() as u64, HashMap::new()).unwrap(),
+                false => ar.add_file(name, data).unwrap(),
+            }
+        }
+        ar.finalize().unwrap();
+        std::fs::read(&path).unwrap()
+    };
+
+    let reference = pack("mem.6cy", 1, false);
+    assert_eq!(pack("mem8.6cy", 8, false), reference);
+    assert_eq!(pack("stream1.6cy", 1, true), reference);
+    assert_eq!(pack("stream8.6cy", 8, true), reference);
+
+    let mut ar = Archive::open(dir.path().join("stream8.6cy")).unwrap();
+    for (name, data) in &files {
+        assert_eq!(&ar.read_file(name).unwrap(), data);
+    }
+    // copy.bin repeats big.bin's first two chunks, so none of its blocks is its own.
+    let copy = ar.list().into_iter().find(|f| f.name == "copy.bin").unwrap();
+    assert_eq!(copy.unique_compressed_size, 0);
+}
+
+#[test]
+fn test_index_version_and_unknown_fields() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::index::{FileIndex, INDEX_VERSION};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("v.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    ar.add_file("a", b"alpha").unwrap();
+    ar.finalize().unwrap();
+    let ar = Archive::open(&path).unwrap();
+    assert_eq!(ar.index_version(), INDEX_VERSION);
+    assert!(ar.unknown_index_fields().is_empty());
+
+    // A newer writer's optional additions are skipped and counted.
+    let hash = vec![0u8; 32];
+    let records: Vec<_> = (0..2).map(|id| serde_json::json!({
+        "id": id, "parent_id": 0, "name": format!("f{id}"),
+        "block_refs": [{ "content_hash": hash, "archive_offset": 256, "xattr_ref": 7 }],
+        "original_size": 0, "compressed_size": 0, "mode": 0o644,
+    })).collect();
+    let newer = serde_json::json!({
+        "index_version": INDEX_VERSION,
+        "records": records,
+        "root_hash": hash,
+        "created_by": "6cy 9.0",
+    });
+    let index = FileIndex::from_bytes(newer.to_string().as_bytes()).unwrap();
+    assert_eq!(index.records.len(), 2);
+    let unknown: Vec<(&str, u64)> = index.unknown_fields.iter().map(|(k, n)| (k.as_str(), *n)).collect();
+    assert_eq!(unknown, [("created_by", 1), ("records[].block_refs[].xattr_ref", 2), ("records[].mode", 2)]);
+
+    // Indexes from before versioning read as version 0 ...
+    let legacy = serde_json::json!({ "records": [], "root_hash": hash });
+    assert_eq!(FileIndex::from_bytes(legacy.to_string().as_bytes()).unwrap().index_version, 0);
+
+    // ... and a version this build does not understand is refused.
+    let future = serde_json::json!({ "index_version": INDEX_VERSION + 1, "records": [], "root_hash": hash });
+    assert!(FileIndex::from_bytes(future.to_string().as_bytes()).is_err());
+}
+
+#[test]
+fn test_block_cache_decodes_shared_blocks_once() {
+    use sixcy::archive::{Archive, ExtractOptions, PackOptions};
+    use sixcy::io_stream::SixCyReader;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dup.6cy");
+    let shared: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    for i in 0..20 {
+        ar.add_file(&format!("copy{i}"), &shared).unwrap();
+    }
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    for i in 0..5 {
+        ar.add_file(&format!("member{i}"), format!("solid member {i}").as_bytes()).unwrap();
+    }
+    ar.end_solid().unwrap();
+    ar.finalize().unwrap();
+
+    // One decode for the shared DATA block and one for the SOLID block.
+    let mut r = SixCyReader::new(File::open(&path).unwrap()).unwrap();
+    r.enable_block_cache(1 << 20);
+    let ids: Vec<u32> = r.index.records.iter().map(|rec| rec.id).collect();
+    for &id in &ids {
+        r.unpack_file_verified(id).unwrap();
+    }
+    let stats = r.block_cache_stats().unwrap();
+    assert_eq!((stats.misses, stats.hits, stats.bytes), (2, 23, 0));
+
+    // Blocks larger than the budget are not kept; the small SOLID block still is.
+    r.enable_block_cache(100);
+    for &id in &ids {
+        r.unpack_file(id).unwrap();
+    }
+    assert_eq!(r.block_cache_stats().unwrap().hits, 4);
+
+    let out = dir.path().join("out");
+    let report = Archive::open(&path).unwrap().extract_with(&out, &ExtractOptions::default()).unwrap();
+    assert_eq!(report.cache_hits, 23);
+    assert_eq!(std::fs::read(out.join("copy19")).unwrap(), shared);
+    assert_eq!(std::fs::read(out.join("member4")).unwrap(), b"solid member 4");
+
+    let uncached = ExtractOptions { block_cache: 0, ..Default::default() };
+    let report = Archive::open(&path).unwrap().extract_with(dir.path().join("plain"), &uncached).unwrap();
+    assert_eq!(report.cache_hits, 0);
+
+    // Blocks cached while hash checks were off go when the checks resume.
+    r.enable_block_cache(1 << 20);
+    r.danger_skip_content_hash(true);
+    r.unpack_file(ids[0]).unwrap();
+    assert!(r.block_cache_stats().unwrap().bytes > 0);
+    r.danger_skip_content_hash(false);
+    assert_eq!(r.block_cache_stats().unwrap().bytes, 0);
+    assert_eq!(r.unpack_file(ids[1]).unwrap(), shared);
+}
+
+#[test]
+fn test_extract_to_tar_stream() {
+    use sixcy::archive::{Archive, PackOptions};
+    use std::io::Read;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("t.6cy");
+    let long = format!("{}/leaf.txt", "nested-directory".repeat(8));
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    ar.add_file("etc/hosts", b"127.0.0.1 localhost\n").unwrap();
+    ar.add_file(&long, b"deep").unwrap();
+    ar.add_file("empty", b"").unwrap();
+    ar.finalize().unwrap();
+
+    let mut tar_bytes = Vec::new();
+    assert_eq!(Archive::open(&path).unwrap().extract_to_tar(&mut tar_bytes).unwrap(), 3);
+
+    let mut members = Vec::new();
+    for entry in tar::Archive::new(tar_bytes.as_slice()).entries().unwrap() {
+        let mut entry = entry.unwrap();
+        let name = entry.path().unwrap().to_string_lossy().into_owned();
+        assert_eq!(entry.header().mode().unwrap(), 0o644);
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data).unwrap();
+        members.push((name, data));
+    }
+    assert_eq!(members, [
+        ("etc/hosts".to_owned(), b"127.0.0.1 localhost\n".to_vec()),
+        (long, b"deep".to_vec()),
+        ("empty".to_owned(), Vec::new()),
+    ]);
+
+    // Names that would escape the extraction root are refused.
+    let bad = dir.path().join("bad.6cy");
+    let mut ar = Archive::create(&bad, PackOptions::default()).unwrap();
+    ar.add_file("../escape", b"x").unwrap();
+    ar.finalize().unwrap();
+    assert!(Archive::open(&bad).unwrap().extract_to_tar(std::io::sink()).is_err());
+}
+
+#[test]
+fn test_windows_name_handling_on_extract() {
+    use sixcy::archive::{Archive, ExtractOptions, PackOptions, WindowsNames};
+    use sixcy::winpath::{is_reserved, windows_name};
+
+    assert!(is_reserved("CON") && is_reserved("nul.txt") && is_reserved("Lpt9.log"));
+    assert!(!is_reserved("CONSOLE") && !is_reserved("COM0") && !is_reserved("lpt10"));
+    assert_eq!(windows_name("docs/readme.md"), None);
+    assert_eq!(windows_name("dev/CON.txt").as_deref(), Some("dev/CON_.txt"));
+    assert_eq!(windows_name("aux").as_deref(), Some("aux_"));
+    assert_eq!(windows_name("a:b/what?.txt").as_deref(), Some("a_b/what_.txt"));
+    assert_eq!(windows_name("trailing. /x").as_deref(), Some("trailing__/x"));
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("w.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    ar.add_file("ok.txt", b"fine").unwrap();
+    ar.add_file("nul", b"device").unwrap();
+    ar.add_file("q?.txt", b"query").unwrap();
+    ar.finalize().unwrap();
+
+    let rename = ExtractOptions { windows_names: WindowsNames::Rename, ..Default::default() };
+    let out = dir.path().join("out");
+    let report = Archive::open(&path).unwrap().extract_with(&out, &rename).unwrap();
+    assert_eq!(report.renamed, [
+        ("nul".to_owned(), "nul_".to_owned()),
+        ("q?.txt".to_owned(), "q_.txt".to_owned()),
+    ]);
+    assert_eq!(std::fs::read(out.join("nul_")).unwrap(), b"device");
+    assert_eq!(std::fs::read(out.join("q_.txt")).unwrap(), b"query");
+
+    let reject = ExtractOptions { windows_names: WindowsNames::Reject, ..Default::default() };
+    let out = dir.path().join("rejected");
+    assert!(Archive::open(&path).unwrap().extract_with(&out, &reject).is_err());
+    assert!(!out.join("ok.txt").exists());
+
+    if !cfg!(windows) {
+        let keep = ExtractOptions { windows_names: WindowsNames::Keep, ..Default::default() };
+        let out = dir.path().join("kept");
+        let report = Archive::open(&path).unwrap().extract_with(&out, &keep).unwrap();
+        assert!(report.renamed.is_empty());
+        assert_eq!(std::fs::read(out.join("q?.txt")).unwrap(), b"query");
+    }
+}
+
+#[test]
+fn test_extract_progress_is_byte_accurate() {
+    use sixcy::archive::{Archive, ExtractOptions, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("p.6cy");
+    let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+    ar.add_file("big", &vec![1u8; 9000]).unwrap();
+    ar.add_file("small", &[2u8; 1000]).unwrap();
+    ar.finalize().unwrap();
+
+    let mut seen = Vec::new();
+    let mut ar = Archive::open(&path).unwrap();
+    ar.extract_with_progress(dir.path().join("out"), &ExtractOptions::default(), |p| {
+        assert_eq!((p.bytes_total, p.files_total), (10_000, 2));
+        seen.push((p.bytes_done, p.files_done, p.current.map(str::to_owned), p.fraction()));
+    }).unwrap();
+    assert_eq!(seen, [
+        (0,      0, Some("big".to_owned()),   0.0),
+        (9_000,  1, Some("small".to_owned()), 0.9),
+        (10_000, 2, None,                     1.0),
+    ]);
+}
+
+#[test]
+fn test_codec_display_names() {
+    use sixcy::codec::{codec_display_name, codec_name, load_codec_names, register_codec_name,
+                       register_name_resolver, uuid_to_string, CodecId};
+    use sixcy::plugin::{self, rc, SixcyCodecPlugin, SIXCY_PLUGIN_ABI_VERSION};
+
+    unsafe extern "C" fn copy(inp: *const u8, in_len: u32, out: *mut u8, out_len: *mut u32) -> i32 {
+        if *out_len < in_len { return rc::OVERFLOW; }
+        std::ptr::copy_nonoverlapping(inp, out, in_len as usize);
+        *out_len = in_len;
+        rc::OK
+    }
+    static NAMED_PLUGIN: SixcyCodecPlugin = SixcyCodecPlugin {
+        uuid: [0x7A; 16], short_id: 0, abi_version: SIXCY_PLUGIN_ABI_VERSION,
+        compress: None, decompress: Some(copy), compress_bound: None,
+    };
+
+    assert_eq!(codec_display_name(&CodecId::Zstd.uuid()), "zstd");
+    let stranger = [0x71; 16];
+    assert_eq!(codec_display_name(&stranger), uuid_to_string(&stranger));
+
+    plugin::register(&NAMED_PLUGIN).unwrap();
+    assert_eq!(codec_display_name(&NAMED_PLUGIN.uuid), format!("{} (plugin)", uuid_to_string(&NAMED_PLUGIN.uuid)));
+    register_codec_name(NAMED_PLUGIN.uuid, "zstd-custom-v2");
+    assert_eq!(codec_display_name(&NAMED_PLUGIN.uuid), "zstd-custom-v2 (plugin)");
+
+    // A registry file names codecs that are not installed.
+    let dir   = tempfile::tempdir().unwrap();
+    let names = dir.path().join("codec-names");
+    let lzham = [0x72; 16];
+    std::fs::write(&names, format!("# local codecs\n\n{}  lzham-1.1  # archival\n", uuid_to_string(&lzham))).unwrap();
+    assert_eq!(load_codec_names(&names).unwrap(), 1);
+    assert_eq!(codec_display_name(&lzham), "lzham-1.1 (not installed)");
+    std::fs::write(&names, "not-a-uuid lzham\n").unwrap();
+    assert!(load_codec_names(&names).is_err());
+
+    // Resolvers answer for anything still unnamed.
+    let vendor = [0x73; 16];
+    register_name_resolver(move |uuid: &[u8; 16]| (uuid == &vendor).then(|| "vendor-x".to_owned()));
+    assert_eq!(codec_name(&vendor).as_deref(), Some("vendor-x"));
+    assert_eq!(codec_name(&[0x74; 16]), None);
+}
+
+#[test]
+fn test_default_metadata_tags_every_record() {
+    use std::collections::HashMap;
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("tagged.6cy");
+    let opts = PackOptions {
+        default_metadata: HashMap::from([
+            ("source".to_owned(), "host-a".to_owned()),
+            ("job".to_owned(),    "nightly".to_owned()),
+        ]),
+        ..PackOptions::default()
+    };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("a.txt", b"plain").unwrap();
+    let own = HashMap::from([("job".to_owned(), "manual".to_owned())]);
+    ar.add_file_with_metadata("b.txt", b"overridden", own).unwrap();
+    ar.finalize().unwrap();
+
+    let ar = Archive::open(&path).unwrap();
+    let nightly: Vec<String> = ar.entries()
+        .filter(|e| e.metadata.get("job").map(String::as_str) == Some("nightly"))
+        .map(|e| e.name)
+        .collect();
+    assert_eq!(nightly, ["a.txt"]);
+    assert!(ar.entries().all(|e| e.metadata["source"] == "host-a"));
+}
+
+#[test]
+fn test_entries_detailed_reports_codec_and_encryption() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir = tempfile::tempdir().unwrap();
+    for password in [None, Some("pw".to_owned())] {
+        let path = dir.path().join("detail.6cy");
+        let opts = PackOptions { default_codec: CodecId::Lz4, password: password.clone(), ..PackOptions::default() };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("data.txt", b"some bytes").unwrap();
+        ar.add_file("empty", b"").unwrap();
+        ar.finalize().unwrap();
+
+        // No password needed to read headers.
+        let mut ar = Archive::open(&path).unwrap();
+        assert!(ar.entries().all(|e| e.codec.is_none()));
+        let infos: Vec<_> = ar.entries_detailed().map(Result::unwrap).collect();
+        assert_eq!(infos[0].codec, Some(CodecId::Lz4.uuid()));
+        assert_eq!(infos[0].encrypted, password.is_some());
+        assert_eq!(infos[1].codec, None);
+        assert!(!infos[1].encrypted);
+    }
+}
+
+#[test]
+fn test_merge_conflict_strategies() {
+    use sixcy::archive::{Archive, MergeConflict, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let make = |name: &str, files: &[(&str, &[u8])]| {
+        let path = dir.path().join(name);
+        let mut ar = Archive::create(&path, PackOptions::default()).unwrap();
+        for (n, data) in files {
+            ar.add_file(n, data).unwrap();
+        }
+        ar.finalize().unwrap();
+        path
+    };
+    let mon = make("mon.6cy", &[("same.txt", b"unchanged"), ("conf.ini", b"v1"), ("docs/.env", b"a")]);
+    let tue = make("tue.6cy", &[("same.txt", b"unchanged"), ("conf.ini", b"v2"), ("docs/.env", b"b")]);
+    let merged = dir.path().join("merged.6cy");
+    let merge = |on_conflict| {
+        let mut sources = vec![Archive::open(&mon).unwrap(), Archive::open(&tue).unwrap()];
+        Archive::merge(&mut sources, &merged, PackOptions::default(), on_conflict)
+    };
+    let contents = || {
+        let mut ar = Archive::open(&merged).unwrap();
+        let mut out: Vec<(String, Vec<u8>)> = ar.list().into_iter()
+            .map(|e| (e.name.clone(), ar.read_file_by_id(e.id).unwrap()))
+            .collect();
+        out.sort();
+        out
+    };
+    let entry = |n: &str, d: &[u8]| (n.to_owned(), d.to_vec());
+
+    let report = merge(MergeConflict::Prefix).unwrap();
+    assert_eq!(report.files, 6);
+    assert!(contents().contains(&entry("tue/conf.ini", b"v2")));
+
+    let report = merge(MergeConflict::KeepFirst).unwrap();
+    assert_eq!((report.files, report.identical, report.dropped), (3, 1, 2));
+    assert_eq!(contents(), [entry("conf.ini", b"v1"), entry("docs/.env", b"a"), entry("same.txt", b"unchanged")]);
+
+    // Same modification second: the later source is the newer snapshot.
+    merge(MergeConflict::KeepNewest).unwrap();
+    assert_eq!(contents(), [entry("conf.ini", b"v2"), entry("docs/.env", b"b"), entry("same.txt", b"unchanged")]);
+
+    let report = merge(MergeConflict::Rename).unwrap();
+    assert_eq!(report.renamed, [
+        ("conf.ini".to_owned(), "conf (1).ini".to_owned()),
+        ("docs/.env".to_owned(), "docs/.env (1)".to_owned()),
+    ]);
+    assert_eq!(contents().len(), 5);
+
+    let err = merge(MergeConflict::Error).unwrap_err().to_string();
+    assert!(err.contains("conf.ini"), "{err}");
+}
+
+#[test]
+fn test_dedup_stats_cdc_survives_shifted_content() {
+    use sixcy::dedup::{cdc_chunks, Chunking, DedupSimulator};
+
+    let mut base = vec![0u8; 1 << 20];
+    let mut x = 0x2545_F491_4F6C_DD1Du64;
+    for b in base.iter_mut() {
+        x ^= x << 13; x ^= x >> 7; x ^= x << 17;
+        *b = x as u8;
+    }
+    let shifted: Vec<u8> = b"one inserted line\n".iter().chain(&base).copied().collect();
+
+    let chunks: Vec<&[u8]> = cdc_chunks(&base, 16 << 10).collect();
+    assert_eq!(chunks.concat(), base);
+    assert!(chunks.iter().all(|c| c.len() <= 64 << 10));
+
+    let mut sim = DedupSimulator::new(&[16 << 10]);
+    sim.add(&base);
+    sim.add(&shifted);
+    let results = sim.results();
+    let (fixed, cdc) = (&results[0], &results[1]);
+    assert_eq!((fixed.chunking, cdc.chunking), (Chunking::Fixed, Chunking::Cdc));
+    assert_eq!(fixed.input_bytes, (base.len() + shifted.len()) as u64);
+    assert!(fixed.saving() < 0.01, "fixed chunks all shift: {}", fixed.saving());
+    assert!(cdc.saving() > 0.45, "CDC resynchronises: {}", cdc.saving());
+}
+
+#[test]
+fn test_verify_on_write_reads_back_every_block() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::io_stream::AutoSolid;
+
+    let dir = tempfile::tempdir().unwrap();
+    for (atomic, password) in [(false, None), (true, Some("pw".to_owned()))] {
+        let path = dir.path().join("verified.6cy");
+        let opts = PackOptions {
+            verify_on_write: true,
+            atomic,
+            password: password.clone(),
+            auto_solid: Some(AutoSolid::default()),
+            chunk_size: 4096,
+            ..PackOptions::default()
+        };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file("small.txt", b"solid member").unwrap();
+        ar.add_file("big.bin", &vec![7u8; 300_000]).unwrap();
+        ar.add_file("empty", b"").unwrap();
+        ar.finalize().unwrap();
+
+        let mut ar = Archive::open_untrusted(&path, password.as_deref(), Default::default()).unwrap();
+        assert_eq!(ar.read_file("big.bin").unwrap(), vec![7u8; 300_000]);
+    }
+}
+
+#[test]
+fn test_index_codec_none_is_readable_without_codecs() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::core_reader::{parse_block_header, parse_superblock, verify_stored, SUPERBLOCK_SIZE};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("plain-index.6cy");
+    let opts = PackOptions { default_codec: CodecId::None, index_codec: CodecId::None, ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("boot.img", b"firmware").unwrap();
+    ar.finalize().unwrap();
+
+    let image = std::fs::read(&path).unwrap();
+    let sb    = parse_superblock(&image[..SUPERBLOCK_SIZE]).unwrap();
+    assert!(sb.required_codec_uuids.is_empty());
+    let at      = sb.index_offset as usize;
+    let header  = parse_block_header(&image[at..at + 84]).unwrap();
+    let payload = &image[at + 84..at + 84 + header.comp_size as usize];
+    let json: serde_json::Value = serde_json::from_slice(verify_stored(&header, payload).unwrap()).unwrap();
+    assert_eq!(json["records"][0]["name"], "boot.img");
+    assert_eq!(Archive::open(&path).unwrap().read_file("boot.img").unwrap(), b"firmware");
+
+    // The default Zstd INDEX is declared like any other codec.
+    let mut ar = Archive::create(&path, PackOptions { default_codec: CodecId::Lz4, ..PackOptions::default() }).unwrap();
+    ar.add_file("a", b"a").unwrap();
+    ar.finalize().unwrap();
+    let codecs = Archive::open(&path).unwrap().required_codecs().to_vec();
+    assert_eq!(codecs, [CodecId::Lz4.uuid(), CodecId::Zstd.uuid()]);
+}
+
+#[test]
+fn test_file_blocks_layout_and_header_damage() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("layout.6cy");
+    let opts = PackOptions { chunk_size: 4096, ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i * 7 % 251) as u8).collect();
+    ar.add_file("chunked.bin", &data).unwrap();
+    ar.begin_solid(CodecId::Zstd).unwrap();
+    ar.add_file("one.txt", b"first member").unwrap();
+    ar.add_file("two.txt", b"second member").unwrap();
+    ar.end_solid().unwrap();
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open(&path).unwrap();
+    let blocks = ar.file_blocks("chunked.bin").unwrap();
+    let layout: Vec<(u64, u64)> = blocks.iter().map(|b| (b.file_offset, b.length)).collect();
+    assert_eq!(layout, [(0, 4096), (4096, 4096), (8192, 1808)]);
+    assert!(blocks.iter().all(|b| b.codec == Some(CodecId::Zstd.uuid()) && !b.encrypted && b.error.is_none()));
+    assert!(blocks.windows(2).all(|w| w[0].archive_range().end <= w[1].offset));
+    ar.verify_block(blocks[1].offset).unwrap();
+
+    let two = ar.file_blocks("two.txt").unwrap();
+    assert_eq!((two.len(), two[0].intra_offset, two[0].length), (1, Some(12), 13));
+    assert!(ar.file_blocks("missing").is_err());
+
+    // Damage the second chunk's header: reported per block, not as an error.
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[blocks[1].offset as usize + 30] ^= 0xFF;
+    std::fs::write(&path, bytes).unwrap();
+    let mut ar = Archive::open(&path).unwrap();
+    let damaged = ar.file_blocks("chunked.bin").unwrap();
+    assert!(damaged[0].error.is_none() && damaged[2].error.is_none());
+    assert!(damaged[1].error.is_some());
+    assert!(ar.verify_block(damaged[1].offset).is_err());
+}
+
+#[test]
+fn test_chunks_repeated_within_a_file_are_encoded_once() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("window.6cy");
+    // 24 chunks cycling through three patterns: every window sees repeats.
+    let data: Vec<u8> = (0..24 * 1024u32).map(|i| ((i / 1024 % 3) * 31 + i % 8) as u8).collect();
+    let opts = PackOptions { chunk_size: 1024, perf_stats: true, ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("cycle.bin", &data).unwrap();
+    ar.finalize().unwrap();
+    assert_eq!(ar.perf_stats().unwrap().blocks, 3 + 1);
+
+    let mut ar = Archive::open(&path).unwrap();
+    assert_eq!(ar.read_file("cycle.bin").unwrap(), data);
+    let blocks = ar.file_blocks("cycle.bin").unwrap();
+    assert_eq!(blocks.len(), 24);
+    assert!(blocks.iter().enumerate().all(|(i, b)| b.offset == blocks[i % 3].offset));
+
+    let chunks: Vec<&[u8]> = data.chunks(1024).take(3).collect();
+    let hashes = sixcy::perf::hash_chunks(&chunks);
+    assert!(hashes.iter().zip(&blocks).all(|(h, b)| *h == b.hash));
+    assert_eq!(sixcy::perf::hash_chunk(&data), *blake3::hash(&data).as_bytes());
+}
+
+#[test]
+fn test_raw_key_archives_skip_the_password() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("kms.6cy");
+    let key  = [0x5Au8; 32];
+    let mut ar = Archive::create_with_key(&path, PackOptions::default(), key).unwrap();
+    ar.add_file("secret.txt", b"wrapped by the KMS").unwrap();
+    ar.finalize().unwrap();
+
+    let mut ar = Archive::open_with_key(&path, key).unwrap();
+    assert_eq!(ar.read_file("secret.txt").unwrap(), b"wrapped by the KMS");
+    assert!(Archive::open(&path).unwrap().read_file("secret.txt").is_err());
+    assert!(Archive::open_with_key(&path, [0xA5; 32]).is_err());
+
+    let opts = PackOptions { password: Some("pw".into()), ..PackOptions::default() };
+    assert!(Archive::create_with_key(dir.path().join("both.6cy"), opts, key).is_err());
+}
+
+#[test]
+fn test_key_derivation_progress_and_estimate() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::crypto::{derive_key, derive_key_with_progress, estimate_derive_time};
+    use std::time::Duration;
+
+    assert!(!estimate_derive_time().unwrap().is_zero());
+    let salt = [7u8; 16];
+    let mut ticks = Vec::new();
+    let key = derive_key_with_progress("pw", &salt, Duration::from_millis(1), |p| ticks.push(p)).unwrap();
+    assert_eq!(key, derive_key("pw", &salt).unwrap());
+    assert!(!ticks.is_empty());
+    assert!(ticks.windows(2).all(|w| w[0].elapsed <= w[1].elapsed));
+    assert!(ticks.iter().all(|p| (0.0..=0.99).contains(&p.fraction())));
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("slow.6cy");
+    let opts = PackOptions { password: Some("pw".into()), ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("a", b"behind argon2").unwrap();
+    ar.finalize().unwrap();
+    let mut ar = Archive::open_encrypted_with_progress(&path, "pw", Duration::from_millis(5), |_| {}).unwrap();
+    assert_eq!(ar.read_file("a").unwrap(), b"behind argon2");
+}
+
+#[test]
+fn test_wrong_password_is_told_apart_from_damage() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::{CryptoError, SixcyError};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("check.6cy");
+    let opts = PackOptions { password: Some("right".into()), ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    ar.add_file("a.txt", &[b'x'; 5000]).unwrap();
+    ar.finalize().unwrap();
+
+    assert!(matches!(Archive::open_encrypted(&path, "wrong"),
+                     Err(SixcyError::Crypto(CryptoError::WrongPassword))));
+
+    let mut ar = Archive::open_encrypted(&path, "right").unwrap();
+    let offset = ar.file_blocks("a.txt").unwrap()[0].offset;
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes[offset as usize + 84 + 20] ^= 0xFF; // inside the ciphertext
+    std::fs::write(&path, bytes).unwrap();
+    let mut ar = Archive::open_encrypted(&path, "right").unwrap();
+    assert!(matches!(ar.read_file("a.txt"), Err(SixcyError::Crypto(CryptoError::DecryptionFailed))));
+}
+
+#[test]
+fn test_try_clone_shares_the_index_across_threads() {
+    use sixcy::archive::{Archive, PackOptions};
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("served.6cy");
+    let opts = PackOptions { chunk_size: 1024, ..PackOptions::default() };
+    let mut ar = Archive::create(&path, opts).unwrap();
+    let files: Vec<Vec<u8>> = (0..4u8).map(|n| (0..5000u32).map(|i| (i as u8) ^ n.wrapping_mul(37)).collect()).collect();
+    for (n, data) in files.iter().enumerate() {
+        ar.add_file(&format!("f{n}"), data).unwrap();
+    }
+    assert!(ar.try_clone().is_err(), "write mode");
+    ar.finalize().unwrap();
+
+    let ar = Archive::open(&path).unwrap();
+    std::thread::scope(|scope| {
+        for (n, data) in files.iter().enumerate() {
+            let mut handle = ar.try_clone().unwrap();
+            scope.spawn(move || {
+                for _ in 0..20 {
+                    assert_eq!(&handle.read_file(&format!("f{n}")).unwrap(), data);
+                }
+            });
+        }
+    });
+    assert_eq!(ar.list().len(), 4);
+}
+
+#[test]
+fn test_block_pipeline_prefilter_codec_cipher() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::pipeline::{BlockPipeline, Cipher, Filter};
+    use sixcy::superblock::FEATURE_PREFILTER;
+
+    for filter in [Filter::Delta(1), Filter::Delta(8), Filter::Shuffle(2), Filter::Shuffle(8)] {
+        for len in [0usize, 1, 7, 8, 1001] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 31 % 256) as u8).collect();
+            let mut out = Vec::new();
+            filter.apply(&data, &mut out);
+            filter.revert(&mut out);
+            assert_eq!(out, data, "{} over {len} bytes", filter.name());
+        }
+    }
+    let spec = BlockPipeline::from_spec("delta4,zstd:19,aes-256-gcm").unwrap();
+    assert_eq!(spec, BlockPipeline::new(CodecId::Zstd).filter(Filter::Delta(4)).level(19).cipher(Cipher::Aes256Gcm));
+    assert_eq!(spec.to_string(), "delta4 → zstd:19 → aes-256-gcm");
+    assert!(BlockPipeline::from_spec("delta3,zstd").is_err());
+    assert!(BlockPipeline::new(CodecId::Lz4).cipher(Cipher::Aes256Gcm).validate(false).is_err());
+
+    // 32-bit counters: the delta filter turns them into a constant stream.
+    let samples: Vec<u8> = (0..50_000u32).flat_map(|i| (i * 7919).to_le_bytes()).collect();
+    let dir = tempfile::tempdir().unwrap();
+    let pack = |name: &str, pipeline: Option<BlockPipeline>| {
+        let path = dir.path().join(name);
+        let opts = PackOptions {
+            password: Some("pw".into()),
+            chunk_size: 64 << 10,
+            pipeline,
+            ..PackOptions::default()
+        };
+        let mut ar = Archive::create(&path, opts).unwrap();
+        ar.add_file_with_codec("samples.bin", &samples, CodecId::Lz4).unwrap();
+        ar.begin_solid(CodecId::Lz4).unwrap();
+        ar.add_file("small.txt", b"solid members are filtered too").unwrap();
+        ar.end_solid().unwrap();
+        ar.finalize().unwrap();
+        path
+    };
+    let plain    = pack("plain.6cy", Some(BlockPipeline::new(CodecId::Zstd).cipher(Cipher::Aes256Gcm)));
+    let filtered = pack("filtered.6cy", Some(BlockPipeline::new(CodecId::Zstd).filter(Filter::Delta(4))
+        .cipher(Cipher::Aes256Gcm)));
+    assert!(std::fs::metadata(&filtered).unwrap().len() < std::fs::metadata(&plain).unwrap().len() / 4);
+
+    let mut ar = Archive::open_encrypted(&filtered, "pw").unwrap();
+    assert_eq!(ar.read_file("samples.bin").unwrap(), samples);
+    assert_eq!(ar.read_file("small.txt").unwrap(), b"solid members are filtered too");
+    assert_eq!(ar.required_codecs(), [CodecId::Zstd.uuid()]);
+    let sb = sixcy::Superblock::read(&mut std::fs::File::open(&filtered).unwrap()).unwrap();
+    assert_eq!(sb.feature_flags & FEATURE_PREFILTER, FEATURE_PREFILTER);
+
+    let bytes  = std::fs::read(&filtered).unwrap();
+    let offset = ar.file_blocks("samples.bin").unwrap()[0].offset as usize;
+    let header = BlockHeader::parse(bytes[offset..offset + 84].try_into().unwrap()).unwrap();
+    assert!(header.is_encrypted());
+    assert_eq!(sixcy::pipeline::Filter::from_flags(header.flags).unwrap(), Some(Filter::Delta(4)));
+
+    // A pipeline without a cipher stage cannot write an encrypted archive.
+    let opts = PackOptions { password: Some("pw".into()), pipeline: Some(BlockPipeline::new(CodecId::Zstd)),
+                             ..PackOptions::default() };
+    assert!(Archive::create(dir.path().join("bad.6cy"), opts).is_err());
+}
+
+#[test]
+fn test_index_located_from_tail_when_offset_is_wrong() {
+    use sixcy::archive::{Archive, PackOptions};
+    use sixcy::io_stream::locate_index_from_tail;
+
+    let dir  = tempfile::tempdir().unwrap();
+    let path = dir.path().join("misplaced.6cy");
+    let mut ar = Archive::create(&path, PackOptions { chunk_size: 4096, ..PackOptions::default() }).unwrap();
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i % 241) as u8).collect();
+    ar.add_file("a.bin", &data).unwrap();
+    ar.add_file("b.txt", b"second").unwrap();
+    ar.finalize().unwrap();
+
+    let mut file = File::open(&path).unwrap();
+    let mut sb   = sixcy::Superblock::read(&mut file).unwrap();
+    let real     = sb.index_offset;
+    assert_eq!(locate_index_from_tail(&mut file).unwrap(), Some(real));
+    assert!(!Archive::open(&path).unwrap().is_index_relocated());
+
+    // Point index_offset at the first DATA block, with a valid CRC.
+    sb.index_offset = 256;
+    let mut bytes = std::fs::read(&path).unwrap();
+    let mut head  = Vec::new();
+    sb.write(&mut head).unwrap();
+    bytes[..head.len()].copy_from_slice(&head);
+    std::fs::write(&path, &bytes).unwrap();
+
+    let mut ar = Archive::open(&path).unwrap();
+    assert!(ar.is_index_relocated());
+    assert_eq!(ar.read_file("a.bin").unwrap(), data);
+    assert_eq!(ar.read_file("b.txt").unwrap(), b"second");
+
+    // Nothing to find in a file without any INDEX header.
+    let mut junk = std::io::Cursor::new(vec![0u8; 4096]);
+    assert_eq!(locate_index_from_tail(&mut junk).unwrap(), None);
+}